@@ -0,0 +1,146 @@
+//! How fast the hot paths are: parsing a terminfo file, expanding a capability, writing styled text, and flushing a
+//! full screen that changed.
+//!
+//! There's no benchmark harness, so each case runs for about a second and prints the time per iteration. Run with
+//! `cargo bench --bench throughput`, or give a case's name (or part of it) to run only that one.
+//!
+//! The cases that write to a terminal only run on Unix, where there's a `Term`.
+extern crate nixterm;
+
+#[cfg(unix)]
+use nixterm::ansi::{ColorChoice, SanitizePolicy, Style};
+#[cfg(unix)]
+use nixterm::screen::Screen;
+use nixterm::terminfo::lang::{Argument, Program};
+use nixterm::terminfo::TermInfo;
+#[cfg(unix)]
+use nixterm::terminfo::TermInfoBuf;
+#[cfg(unix)]
+use nixterm::Term;
+use std::env;
+#[cfg(unix)]
+use std::fs::{File, OpenOptions};
+use std::hint::black_box;
+#[cfg(unix)]
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+const XTERM_INFO: &[u8] = include_bytes!("../test-data/xterm-256color");
+
+/// Run `f` until a second's gone by and print how long each call took.
+fn bench<F: FnMut()>(name: &str, mut f: F) {
+    if let Some(filter) = env::args().skip(1).find(|arg| !arg.starts_with('-')) {
+        if !name.contains(&filter) {
+            return;
+        }
+    }
+
+    // warm up, and find how many calls make a batch worth timing
+    let mut batch = 1u64;
+    loop {
+        let start = Instant::now();
+        for _ in 0..batch {
+            f();
+        }
+        if start.elapsed() > Duration::from_millis(10) {
+            break;
+        }
+        batch *= 2;
+    }
+
+    let mut iters = 0;
+    let start = Instant::now();
+    while start.elapsed() < Duration::from_secs(1) {
+        for _ in 0..batch {
+            f();
+        }
+        iters += batch;
+    }
+    let ns = start.elapsed().as_nanos() / u128::from(iters);
+    println!("{:<24} {:>12} ns/iter ({} iterations)", name, ns, iters);
+}
+
+/// A terminal that writes to /dev/null, `cols` by `rows`.
+#[cfg(unix)]
+fn null_term(cols: usize, rows: usize) -> Term<File, File> {
+    let null = || {
+        OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open("/dev/null")
+            .unwrap()
+    };
+    let t = Term::from_streams(
+        TermInfoBuf::from(TermInfo::parse(XTERM_INFO).unwrap()),
+        null(),
+        null(),
+    );
+    let (cols, rows) = (cols.to_string(), rows.to_string());
+    t.set_env_overrides(nixterm::EnvOverrides::from_map(vec![
+        ("COLUMNS", &cols[..]),
+        ("LINES", &rows[..]),
+    ]));
+    t.set_color_choice(ColorChoice::Always);
+    t
+}
+
+fn main() {
+    bench("parse", || {
+        black_box(TermInfo::parse(black_box(XTERM_INFO)).unwrap());
+    });
+
+    let info = TermInfo::parse(XTERM_INFO).unwrap();
+    let cup = info
+        .get_str_bytes(nixterm::terminfo::CursorAddress)
+        .unwrap();
+    bench("compile", || {
+        black_box(Program::compile(black_box(cup)).unwrap());
+    });
+    let program = Program::compile(cup).unwrap();
+    let args = [Argument::Integer(12), Argument::Integer(40)];
+    let mut buf = Vec::new();
+    bench("expand", || {
+        buf.clear();
+        program.exec_append(black_box(&args), &mut buf).unwrap();
+        black_box(&buf);
+    });
+
+    #[cfg(unix)]
+    terminal();
+}
+
+/// The cases that write to a terminal.
+#[cfg(unix)]
+fn terminal() {
+    let term = null_term(80, 24);
+    let line = "The quick brown fox jumps over the lazy dog, again and again and again.";
+    bench("styled_write", || {
+        let mut w = term.styled_writer();
+        w.set_style(Style::new().bold());
+        w.write_all(black_box(line.as_bytes())).unwrap();
+    });
+    bench("styled_write_sanitized", || {
+        let mut w = term.styled_writer();
+        w.set_sanitize(Some(SanitizePolicy::caret()));
+        w.write_all(black_box(line.as_bytes())).unwrap();
+    });
+
+    let mut screen = Screen::new(80, 24);
+    let mut frame = 0u32;
+    bench("full_frame_flush", || {
+        // every cell changes, in alternating styles
+        frame += 1;
+        let c = if frame % 2 == 0 { "#" } else { "." };
+        let style = if frame % 2 == 0 {
+            Style::new().bold()
+        } else {
+            Style::new()
+        };
+        for y in 0..24 {
+            for x in 0..80 {
+                screen.print(x, y, c, style);
+            }
+        }
+        screen.flush(&term).unwrap();
+    });
+}
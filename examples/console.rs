@@ -0,0 +1,34 @@
+extern crate nixterm;
+
+#[cfg(windows)]
+use nixterm::events::{Event, Key};
+#[cfg(windows)]
+use nixterm::windows::Console;
+#[cfg(windows)]
+use std::io::Write;
+
+#[cfg(not(windows))]
+pub fn main() {
+    eprintln!("`Console` is only built on Windows, see the `raw` example");
+}
+
+#[cfg(windows)]
+pub fn main() {
+    let mut console = Console::new().unwrap();
+
+    let (cols, rows) = console.size().unwrap();
+    write!(console, "The console is {}x{}.\r\n", cols, rows).unwrap();
+    write!(console, "Try pressing a few keys, or resizing the window (Ctrl-C to quit): ").unwrap();
+    console.flush().unwrap();
+
+    loop {
+        match console.read_event(None).unwrap() {
+            Some(Event::Key(Key::Control('C'))) => break,
+            // clear the line, and show what happened
+            Some(event) => write!(console, "\x1b[2K\r{:?}", event).unwrap(),
+            None => (),
+        }
+        console.flush().unwrap();
+    }
+    write!(console, "\r\n").unwrap();
+}
@@ -1,8 +1,16 @@
 extern crate nixterm;
 
+#[cfg(unix)]
 use nixterm::events::Key;
+#[cfg(unix)]
 use nixterm::Term;
 
+#[cfg(not(unix))]
+pub fn main() {
+    eprintln!("`Term` is only built on Unix, see the `console` example");
+}
+
+#[cfg(unix)]
 pub fn main() {
     let term = Term::new().unwrap();
 
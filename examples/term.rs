@@ -1,7 +1,14 @@
 extern crate nixterm;
 
+#[cfg(unix)]
 use nixterm::term::Term;
 
+#[cfg(not(unix))]
+pub fn main() {
+    eprintln!("`Term` is only built on Unix, see the `console` example");
+}
+
+#[cfg(unix)]
 pub fn main() {
     let term = Term::new().unwrap();
 
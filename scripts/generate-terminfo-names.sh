@@ -0,0 +1,89 @@
+#!/usr/bin/sh
+# Generate the capability name tables from the term.h header file and the terminfo(5) man page
+
+# The capabilities terminfo(5) doesn't list: the obsolete termcap ones, and a few ncurses extensions, as
+# "variable terminfo termcap description", in the same words as ncurses' Caps file.
+unlisted() {
+    cat - <<-EOF
+	backspaces_with_bs OTbs bs uses ^H to move left
+	crt_no_scrolling OTns ns crt cannot scroll
+	no_correctly_working_cr OTnc nc no way to go to start of line
+	gnu_has_meta_key OTMT MT has meta key
+	linefeed_is_newline OTNL NL move down with a newline
+	has_hardware_tabs OTpt pt has 8-char tabs invoked with ^I
+	return_does_clr_eol OTxr xr return clears the line
+	magic_cookie_glitch_ul OTug ug number of blanks left by ul
+	carriage_return_delay OTdC dC pad needed for CR
+	new_line_delay OTdN dN pad needed for LF
+	backspace_delay OTdB dB padding required for ^H
+	horizontal_tab_delay OTdT dT padding required for ^I
+	number_of_function_keys OTkn kn count of function keys
+	termcap_init2 OTi2 i2 secondary initialization string
+	termcap_reset OTrs rs terminal reset string
+	linefeed_if_not_lf OTnl nl use to move down
+	backspace_if_not_bs OTbc bc move left, if not ^H
+	other_non_function_keys OTko ko list of self-mapped keycaps
+	arrow_key_map OTma ma map motion-keys for vi version 2
+	acs_ulcorner OTG2 G2 single upper left
+	acs_llcorner OTG3 G3 single lower left
+	acs_urcorner OTG1 G1 single upper right
+	acs_lrcorner OTG4 G4 single lower right
+	acs_ltee OTGR GR tee pointing right
+	acs_rtee OTGL GL tee pointing left
+	acs_btee OTGU GU tee pointing up
+	acs_ttee OTGD GD tee pointing down
+	acs_hline OTGH GH single horizontal line
+	acs_vline OTGV GV single vertical line
+	acs_plus OTGC GC single intersection
+	memory_lock meml ml lock memory above cursor
+	memory_unlock memu mu unlock memory
+	box_chars_1 box1 bx box characters primary set
+	EOF
+}
+
+# "variable terminfo termcap description" for every capability terminfo(5) lists
+listed() {
+    zcat /usr/share/man/man5/terminfo.5.gz | awk -F '\t' '
+        $4 == "T{" && $1 ~ /^[a-z_0-9]+$/ { name = $1 " " $2 " " $3; desc = ""; next }
+        $0 == "T}" && name != "" { print name " " desc; name = ""; next }
+        name != "" { desc = desc == "" ? $0 : desc " " $0 }
+    '
+}
+
+table() {
+    kind=$1
+    const=$2
+    echo "pub const $const: &[CapInfo] = &["
+    cat /usr/include/term.h | /bin/grep "$kind\\[" | awk '$1 == "#define" { print $2 }' | while read var; do
+        line=$( (listed; unlisted) | awk -v var="$var" '$1 == var { print; exit }')
+        if [ -z "$line" ]; then
+            echo "$var isn't documented anywhere" >&2
+            exit 1
+        fi
+        printf "%s\n" "$line" | awk '{
+            desc = $4
+            for (i = 5; i <= NF; i++) desc = desc " " $i
+            gsub(/\\/, "\\\\", desc)
+            gsub(/"/, "\\\"", desc)
+            printf "    CapInfo { terminfo_name: \"%s\", termcap_code: \"%s\", variable_name: \"%s\", description: \"%s\" },\n", $2, $3, $1, desc
+        }'
+    done
+    echo "];"
+}
+
+echo "$(cat - <<-EOF
+    //! This file was generated by "$0".
+    //! Please do not modify it.
+
+    use terminfo::names::CapInfo;
+
+    /// Every standard boolean capability, in the order of \`BooleanField\`.
+    $(table Booleans ALL_BOOLEANS)
+
+    /// Every standard numeric capability, in the order of \`NumericField\`.
+    $(table Numbers ALL_NUMBERS)
+
+    /// Every standard string capability, in the order of \`StringField\`.
+    $(table Strings ALL_STRINGS)
+EOF
+)" | rustfmt
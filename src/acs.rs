@@ -0,0 +1,380 @@
+//! Line-drawing through the terminal's alternate character set.
+//!
+//! Terminals that can't (or won't) draw Unicode box characters usually implement the VT100 "special graphics"
+//! character set. The `acsc` capability describes how that set is laid out on a specific terminal, as packed pairs
+//! of `<vt100 code><terminal byte>`. `Acs` wraps that table, and switches to real Unicode characters when the
+//! user's locale is UTF-8, so callers only have to write one code path.
+use encoding::Charset;
+use overrides::EnvOverrides;
+use terminfo;
+
+/// Logical line-drawing characters, named after their curses `ACS_*` equivalents.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum AcsChar {
+    UlCorner,
+    LlCorner,
+    UrCorner,
+    LrCorner,
+    LTee,
+    RTee,
+    BTee,
+    TTee,
+    HLine,
+    VLine,
+    Plus,
+    S1,
+    S3,
+    S7,
+    S9,
+    Diamond,
+    CkBoard,
+    Degree,
+    PlMinus,
+    Bullet,
+    LArrow,
+    RArrow,
+    DArrow,
+    UArrow,
+    Board,
+    Lantern,
+    Block,
+    LEqual,
+    GEqual,
+    Pi,
+    NEqual,
+    Sterling,
+}
+
+impl AcsChar {
+    /// Every line-drawing character.
+    pub const ALL: [AcsChar; 32] = [
+        AcsChar::UlCorner,
+        AcsChar::LlCorner,
+        AcsChar::UrCorner,
+        AcsChar::LrCorner,
+        AcsChar::LTee,
+        AcsChar::RTee,
+        AcsChar::BTee,
+        AcsChar::TTee,
+        AcsChar::HLine,
+        AcsChar::VLine,
+        AcsChar::Plus,
+        AcsChar::S1,
+        AcsChar::S3,
+        AcsChar::S7,
+        AcsChar::S9,
+        AcsChar::Diamond,
+        AcsChar::CkBoard,
+        AcsChar::Degree,
+        AcsChar::PlMinus,
+        AcsChar::Bullet,
+        AcsChar::LArrow,
+        AcsChar::RArrow,
+        AcsChar::DArrow,
+        AcsChar::UArrow,
+        AcsChar::Board,
+        AcsChar::Lantern,
+        AcsChar::Block,
+        AcsChar::LEqual,
+        AcsChar::GEqual,
+        AcsChar::Pi,
+        AcsChar::NEqual,
+        AcsChar::Sterling,
+    ];
+
+    /// Look up the character with the VT100 special graphics code `code`.
+    pub fn from_vt100_code(code: u8) -> Option<AcsChar> {
+        AcsChar::ALL.iter().cloned().find(|c| c.vt100_code() == code)
+    }
+
+    /// The character's code in the VT100 special graphics set, this is the key used by `acsc`.
+    pub fn vt100_code(self) -> u8 {
+        match self {
+            AcsChar::UlCorner => b'l',
+            AcsChar::LlCorner => b'm',
+            AcsChar::UrCorner => b'k',
+            AcsChar::LrCorner => b'j',
+            AcsChar::LTee => b't',
+            AcsChar::RTee => b'u',
+            AcsChar::BTee => b'v',
+            AcsChar::TTee => b'w',
+            AcsChar::HLine => b'q',
+            AcsChar::VLine => b'x',
+            AcsChar::Plus => b'n',
+            AcsChar::S1 => b'o',
+            AcsChar::S3 => b'p',
+            AcsChar::S7 => b'r',
+            AcsChar::S9 => b's',
+            AcsChar::Diamond => b'`',
+            AcsChar::CkBoard => b'a',
+            AcsChar::Degree => b'f',
+            AcsChar::PlMinus => b'g',
+            AcsChar::Bullet => b'~',
+            AcsChar::LArrow => b',',
+            AcsChar::RArrow => b'+',
+            AcsChar::DArrow => b'.',
+            AcsChar::UArrow => b'-',
+            AcsChar::Board => b'h',
+            AcsChar::Lantern => b'i',
+            AcsChar::Block => b'0',
+            AcsChar::LEqual => b'y',
+            AcsChar::GEqual => b'z',
+            AcsChar::Pi => b'{',
+            AcsChar::NEqual => b'|',
+            AcsChar::Sterling => b'}',
+        }
+    }
+
+    /// The Unicode character this should be drawn as on UTF-8 terminals.
+    pub fn unicode(self) -> char {
+        match self {
+            AcsChar::UlCorner => '┌',
+            AcsChar::LlCorner => '└',
+            AcsChar::UrCorner => '┐',
+            AcsChar::LrCorner => '┘',
+            AcsChar::LTee => '├',
+            AcsChar::RTee => '┤',
+            AcsChar::BTee => '┴',
+            AcsChar::TTee => '┬',
+            AcsChar::HLine => '─',
+            AcsChar::VLine => '│',
+            AcsChar::Plus => '┼',
+            AcsChar::S1 => '⎺',
+            AcsChar::S3 => '⎻',
+            AcsChar::S7 => '⎼',
+            AcsChar::S9 => '⎽',
+            AcsChar::Diamond => '◆',
+            AcsChar::CkBoard => '▒',
+            AcsChar::Degree => '°',
+            AcsChar::PlMinus => '±',
+            AcsChar::Bullet => '·',
+            AcsChar::LArrow => '←',
+            AcsChar::RArrow => '→',
+            AcsChar::DArrow => '↓',
+            AcsChar::UArrow => '↑',
+            AcsChar::Board => '░',
+            AcsChar::Lantern => '☃',
+            AcsChar::Block => '█',
+            AcsChar::LEqual => '≤',
+            AcsChar::GEqual => '≥',
+            AcsChar::Pi => 'π',
+            AcsChar::NEqual => '≠',
+            AcsChar::Sterling => '£',
+        }
+    }
+
+    /// The character drawn by `unicode`, or one of the heavy, double or rounded versions of the lines and corners.
+    pub fn from_unicode(c: char) -> Option<AcsChar> {
+        let c = match c {
+            '━' | '═' | '╌' | '╍' | '┄' | '┅' | '┈' | '┉' => AcsChar::HLine,
+            '┃' | '║' | '╎' | '╏' | '┆' | '┇' | '┊' | '┋' => AcsChar::VLine,
+            '┏' | '╔' | '╭' | '┍' | '┎' | '╒' | '╓' => AcsChar::UlCorner,
+            '┓' | '╗' | '╮' | '┑' | '┒' | '╕' | '╖' => AcsChar::UrCorner,
+            '┗' | '╚' | '╰' | '┕' | '┖' | '╘' | '╙' => AcsChar::LlCorner,
+            '┛' | '╝' | '╯' | '┙' | '┚' | '╛' | '╜' => AcsChar::LrCorner,
+            '┣' | '╠' | '┝' | '┠' | '╞' | '╟' => AcsChar::LTee,
+            '┫' | '╣' | '┥' | '┨' | '╡' | '╢' => AcsChar::RTee,
+            '┳' | '╦' | '┯' | '┰' | '╤' | '╥' => AcsChar::TTee,
+            '┻' | '╩' | '┷' | '┸' | '╧' | '╨' => AcsChar::BTee,
+            '╋' | '╬' | '┿' | '╂' | '╪' | '╫' => AcsChar::Plus,
+            c => return AcsChar::ALL.iter().cloned().find(|a| a.unicode() == c),
+        };
+        Some(c)
+    }
+
+    /// A plain ASCII stand-in, used when the terminal has no mapping for this character.
+    ///
+    /// These are the same fallbacks curses uses.
+    pub fn ascii(self) -> char {
+        match self {
+            AcsChar::UlCorner
+            | AcsChar::LlCorner
+            | AcsChar::UrCorner
+            | AcsChar::LrCorner
+            | AcsChar::LTee
+            | AcsChar::RTee
+            | AcsChar::BTee
+            | AcsChar::TTee
+            | AcsChar::Plus
+            | AcsChar::Lantern => '+',
+            AcsChar::HLine | AcsChar::S1 | AcsChar::S3 | AcsChar::S7 | AcsChar::S9 => '-',
+            AcsChar::VLine => '|',
+            AcsChar::Diamond => '+',
+            AcsChar::CkBoard => ':',
+            AcsChar::Degree => '\'',
+            AcsChar::PlMinus => '#',
+            AcsChar::Bullet => 'o',
+            AcsChar::LArrow => '<',
+            AcsChar::RArrow => '>',
+            AcsChar::DArrow => 'v',
+            AcsChar::UArrow => '^',
+            AcsChar::Board => '#',
+            AcsChar::Block => '#',
+            AcsChar::LEqual => '<',
+            AcsChar::GEqual => '>',
+            AcsChar::Pi => '*',
+            AcsChar::NEqual => '!',
+            AcsChar::Sterling => 'f',
+        }
+    }
+}
+
+/// Parse an `acsc` string into a table mapping VT100 codes to the bytes this terminal uses for them.
+///
+/// `acsc` is a list of packed pairs, the first byte of each pair is the VT100 code and the second is
+/// the byte to send (while in alternate character set mode) to draw it. Unmapped codes are left as 0.
+/// A trailing unpaired byte is ignored.
+pub fn parse_acsc(acsc: &[u8]) -> [u8; 128] {
+    let mut map = [0u8; 128];
+    for pair in acsc.chunks(2) {
+        if pair.len() == 2 && pair[0] < 128 {
+            map[pair[0] as usize] = pair[1];
+        }
+    }
+    map
+}
+
+/// Check if the user's locale is UTF-8 based on `LC_ALL`, `LC_CTYPE` and `LANG`, in that order of precedence.
+pub fn utf8_locale() -> bool {
+    Charset::from_env(&EnvOverrides::from_env()) == Charset::Utf8
+}
+
+/// The terminal's line-drawing characters.
+#[derive(Clone)]
+pub struct Acs {
+    map: [u8; 128],
+    utf8: bool,
+}
+
+impl Acs {
+    /// Build the character table from a terminal's `acsc` capability, using Unicode if the locale is UTF-8.
+    pub fn new(info: &terminfo::TermInfoBuf) -> Acs {
+        Acs::with_utf8(info, utf8_locale())
+    }
+
+    /// Build the character table from a terminal's `acsc` capability.
+    ///
+    /// If `utf8` is true then the table is ignored and `Acs::get` always returns Unicode box drawing characters.
+    pub fn with_utf8(info: &terminfo::TermInfoBuf, utf8: bool) -> Acs {
+        Acs {
+            map: parse_acsc(
+                info.string(terminfo::AcsChars)
+                    .map(|s| s.as_bytes())
+                    .unwrap_or(b""),
+            ),
+            utf8,
+        }
+    }
+
+    /// True if this table produces Unicode characters rather than alternate character set bytes.
+    ///
+    /// When this is true nothing needs to be done to enter the alternate character set.
+    pub fn is_utf8(&self) -> bool {
+        self.utf8
+    }
+
+    /// True if the terminal has a mapping for `c`, this is always true in UTF-8 mode.
+    pub fn supports(&self, c: AcsChar) -> bool {
+        self.utf8 || self.map[c.vt100_code() as usize] != 0
+    }
+
+    /// Get the character to write in order to draw `c`.
+    ///
+    /// In UTF-8 mode this is a Unicode box drawing character. Otherwise it's the byte that draws `c`
+    /// in the terminal's alternate character set, or an ASCII approximation if the terminal has no mapping, which
+    /// has to be written outside the alternate character set (not in `Term::with_acs`).
+    pub fn get(&self, c: AcsChar) -> char {
+        if self.utf8 {
+            return c.unicode();
+        }
+
+        match self.map[c.vt100_code() as usize] {
+            0 => c.ascii(),
+            x => x as char,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use acs::*;
+    use encoding::Charset;
+    use terminfo;
+
+    const RXVT_INFO: &'static [u8] = include_bytes!("../test-data/rxvt");
+    const LINUX_16COLOR_INFO: &'static [u8] = include_bytes!("../test-data/linux-16color");
+
+    #[test]
+    fn acsc() {
+        let map = parse_acsc(b"``aaffjjqqxx");
+        assert_eq!(map[b'q' as usize], b'q');
+        assert_eq!(map[b'j' as usize], b'j');
+        assert_eq!(map[b'l' as usize], 0);
+
+        // linux remaps a few characters to different bytes
+        let map = parse_acsc(b"++,,--..00__``aahhiijjkkllmmnnooppqqrrssttuuvvwwxxyyzz{{||}c~~");
+        assert_eq!(map[b'}' as usize], b'c');
+        assert_eq!(map[b'+' as usize], b'+');
+
+        // trailing bytes without a pair are ignored
+        let map = parse_acsc(b"qqx");
+        assert_eq!(map[b'q' as usize], b'q');
+        assert_eq!(map[b'x' as usize], 0);
+    }
+
+    #[test]
+    fn acs_mode() {
+        let rxvt: terminfo::TermInfoBuf = terminfo::TermInfo::parse(RXVT_INFO).unwrap().into();
+        let acs = Acs::with_utf8(&rxvt, false);
+
+        assert!(!acs.is_utf8());
+        assert_eq!(acs.get(AcsChar::HLine), 'q');
+        assert_eq!(acs.get(AcsChar::UlCorner), 'l');
+        // rxvt doesn't define the arrows
+        assert!(!acs.supports(AcsChar::LArrow));
+        assert_eq!(acs.get(AcsChar::LArrow), '<');
+
+        let linux: terminfo::TermInfoBuf = terminfo::TermInfo::parse(LINUX_16COLOR_INFO)
+            .unwrap()
+            .into();
+        assert_eq!(Acs::with_utf8(&linux, false).get(AcsChar::Sterling), 'c');
+
+        let empty = Acs::with_utf8(&terminfo::TermInfoBuf::new(), false);
+        assert_eq!(empty.get(AcsChar::VLine), '|');
+        assert_eq!(empty.get(AcsChar::UrCorner), '+');
+    }
+
+    #[test]
+    fn utf8_mode() {
+        let rxvt: terminfo::TermInfoBuf = terminfo::TermInfo::parse(RXVT_INFO).unwrap().into();
+        let acs = Acs::with_utf8(&rxvt, true);
+
+        assert!(acs.is_utf8());
+        assert!(acs.supports(AcsChar::LArrow));
+        assert_eq!(acs.get(AcsChar::HLine), '─');
+        assert_eq!(acs.get(AcsChar::UlCorner), '┌');
+        assert_eq!(acs.get(AcsChar::LArrow), '←');
+    }
+
+    #[test]
+    fn vt100_codes() {
+        for &c in AcsChar::ALL.iter() {
+            assert_eq!(AcsChar::from_vt100_code(c.vt100_code()), Some(c));
+            assert_eq!(AcsChar::from_unicode(c.unicode()), Some(c));
+        }
+        assert_eq!(AcsChar::from_unicode('╔'), Some(AcsChar::UlCorner));
+        assert_eq!(AcsChar::from_unicode('━'), Some(AcsChar::HLine));
+        assert_eq!(AcsChar::from_unicode('x'), None);
+        assert_eq!(AcsChar::from_vt100_code(b'A'), None);
+    }
+
+    #[test]
+    fn locale() {
+        assert_eq!(Charset::from_locale("en_US.UTF-8"), Charset::Utf8);
+        assert_eq!(Charset::from_locale("de_DE.utf8"), Charset::Utf8);
+        assert_eq!(Charset::from_locale("sr_RS.UTF-8@latin"), Charset::Utf8);
+        assert_ne!(Charset::from_locale("C"), Charset::Utf8);
+        assert_ne!(Charset::from_locale("POSIX"), Charset::Utf8);
+        assert_ne!(Charset::from_locale("en_US.ISO-8859-1"), Charset::Utf8);
+    }
+}
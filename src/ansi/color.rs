@@ -0,0 +1,1026 @@
+#[cfg(feature = "x11-colors")]
+use ansi::x11::X11_COLORS;
+use ansi::{Color, Rgb};
+use errors::*;
+use std::fmt;
+use std::str::FromStr;
+
+/// How many colors output can use.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum ColorDepth {
+    /// No colors or other styling at all, just plain text.
+    None,
+    /// The 8 basic colors and their bright variants.
+    Ansi16,
+    /// The xterm 256 color palette.
+    Ansi256,
+    /// Any 24-bit color.
+    TrueColor,
+}
+
+/// Whether to use colors, as an application's `--color` option would choose.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub enum ColorChoice {
+    /// Use colors, even when writing to a pipe or when `NO_COLOR` is set.
+    Always,
+    /// Use colors if the output is a tty, unless `NO_COLOR` says not to or `CLICOLOR_FORCE` says to anyway.
+    #[default]
+    Auto,
+    /// Never use colors.
+    Never,
+}
+
+impl ColorChoice {
+    /// Work out how many colors output can use.
+    ///
+    /// `var` looks up environment variables, `is_tty` says whether output goes to a tty, and `max_colors` is the
+    /// terminfo entry's `colors`. How many colors there are comes from `max_colors`, or `COLORTERM` for 24-bit
+    /// colors, and when colors are forced on there are always at least 16.
+    pub fn resolve<F>(self, var: F, is_tty: bool, max_colors: Option<u32>) -> ColorDepth
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        // both conventions ignore empty values, and `CLICOLOR_FORCE=0` doesn't force anything
+        let set = |name| var(name).is_some_and(|v| !v.is_empty());
+        let forced = match self {
+            ColorChoice::Never => return ColorDepth::None,
+            ColorChoice::Always => true,
+            ColorChoice::Auto if set("NO_COLOR") => return ColorDepth::None,
+            ColorChoice::Auto => var("CLICOLOR_FORCE").is_some_and(|v| !v.is_empty() && v != "0"),
+        };
+        if !forced && !is_tty {
+            return ColorDepth::None;
+        }
+
+        let truecolor = var("COLORTERM").is_some_and(|v| v == "truecolor" || v == "24bit");
+        let depth = match max_colors {
+            _ if truecolor => ColorDepth::TrueColor,
+            Some(n) if n >= 1 << 24 => ColorDepth::TrueColor,
+            Some(n) if n >= 256 => ColorDepth::Ansi256,
+            Some(n) if n >= 8 => ColorDepth::Ansi16,
+            _ => ColorDepth::None,
+        };
+        if forced {
+            depth.max(ColorDepth::Ansi16)
+        } else {
+            depth
+        }
+    }
+}
+
+/// The default xterm 256 color palette: the 16 ANSI colors, a 6x6x6 color cube, and a 24 step grey ramp.
+pub const XTERM_PALETTE: [Rgb; 256] = [
+    rgb(0, 0, 0),
+    rgb(205, 0, 0),
+    rgb(0, 205, 0),
+    rgb(205, 205, 0),
+    rgb(0, 0, 238),
+    rgb(205, 0, 205),
+    rgb(0, 205, 205),
+    rgb(229, 229, 229),
+    rgb(127, 127, 127),
+    rgb(255, 0, 0),
+    rgb(0, 255, 0),
+    rgb(255, 255, 0),
+    rgb(92, 92, 255),
+    rgb(255, 0, 255),
+    rgb(0, 255, 255),
+    rgb(255, 255, 255),
+    rgb(0, 0, 0),
+    rgb(0, 0, 95),
+    rgb(0, 0, 135),
+    rgb(0, 0, 175),
+    rgb(0, 0, 215),
+    rgb(0, 0, 255),
+    rgb(0, 95, 0),
+    rgb(0, 95, 95),
+    rgb(0, 95, 135),
+    rgb(0, 95, 175),
+    rgb(0, 95, 215),
+    rgb(0, 95, 255),
+    rgb(0, 135, 0),
+    rgb(0, 135, 95),
+    rgb(0, 135, 135),
+    rgb(0, 135, 175),
+    rgb(0, 135, 215),
+    rgb(0, 135, 255),
+    rgb(0, 175, 0),
+    rgb(0, 175, 95),
+    rgb(0, 175, 135),
+    rgb(0, 175, 175),
+    rgb(0, 175, 215),
+    rgb(0, 175, 255),
+    rgb(0, 215, 0),
+    rgb(0, 215, 95),
+    rgb(0, 215, 135),
+    rgb(0, 215, 175),
+    rgb(0, 215, 215),
+    rgb(0, 215, 255),
+    rgb(0, 255, 0),
+    rgb(0, 255, 95),
+    rgb(0, 255, 135),
+    rgb(0, 255, 175),
+    rgb(0, 255, 215),
+    rgb(0, 255, 255),
+    rgb(95, 0, 0),
+    rgb(95, 0, 95),
+    rgb(95, 0, 135),
+    rgb(95, 0, 175),
+    rgb(95, 0, 215),
+    rgb(95, 0, 255),
+    rgb(95, 95, 0),
+    rgb(95, 95, 95),
+    rgb(95, 95, 135),
+    rgb(95, 95, 175),
+    rgb(95, 95, 215),
+    rgb(95, 95, 255),
+    rgb(95, 135, 0),
+    rgb(95, 135, 95),
+    rgb(95, 135, 135),
+    rgb(95, 135, 175),
+    rgb(95, 135, 215),
+    rgb(95, 135, 255),
+    rgb(95, 175, 0),
+    rgb(95, 175, 95),
+    rgb(95, 175, 135),
+    rgb(95, 175, 175),
+    rgb(95, 175, 215),
+    rgb(95, 175, 255),
+    rgb(95, 215, 0),
+    rgb(95, 215, 95),
+    rgb(95, 215, 135),
+    rgb(95, 215, 175),
+    rgb(95, 215, 215),
+    rgb(95, 215, 255),
+    rgb(95, 255, 0),
+    rgb(95, 255, 95),
+    rgb(95, 255, 135),
+    rgb(95, 255, 175),
+    rgb(95, 255, 215),
+    rgb(95, 255, 255),
+    rgb(135, 0, 0),
+    rgb(135, 0, 95),
+    rgb(135, 0, 135),
+    rgb(135, 0, 175),
+    rgb(135, 0, 215),
+    rgb(135, 0, 255),
+    rgb(135, 95, 0),
+    rgb(135, 95, 95),
+    rgb(135, 95, 135),
+    rgb(135, 95, 175),
+    rgb(135, 95, 215),
+    rgb(135, 95, 255),
+    rgb(135, 135, 0),
+    rgb(135, 135, 95),
+    rgb(135, 135, 135),
+    rgb(135, 135, 175),
+    rgb(135, 135, 215),
+    rgb(135, 135, 255),
+    rgb(135, 175, 0),
+    rgb(135, 175, 95),
+    rgb(135, 175, 135),
+    rgb(135, 175, 175),
+    rgb(135, 175, 215),
+    rgb(135, 175, 255),
+    rgb(135, 215, 0),
+    rgb(135, 215, 95),
+    rgb(135, 215, 135),
+    rgb(135, 215, 175),
+    rgb(135, 215, 215),
+    rgb(135, 215, 255),
+    rgb(135, 255, 0),
+    rgb(135, 255, 95),
+    rgb(135, 255, 135),
+    rgb(135, 255, 175),
+    rgb(135, 255, 215),
+    rgb(135, 255, 255),
+    rgb(175, 0, 0),
+    rgb(175, 0, 95),
+    rgb(175, 0, 135),
+    rgb(175, 0, 175),
+    rgb(175, 0, 215),
+    rgb(175, 0, 255),
+    rgb(175, 95, 0),
+    rgb(175, 95, 95),
+    rgb(175, 95, 135),
+    rgb(175, 95, 175),
+    rgb(175, 95, 215),
+    rgb(175, 95, 255),
+    rgb(175, 135, 0),
+    rgb(175, 135, 95),
+    rgb(175, 135, 135),
+    rgb(175, 135, 175),
+    rgb(175, 135, 215),
+    rgb(175, 135, 255),
+    rgb(175, 175, 0),
+    rgb(175, 175, 95),
+    rgb(175, 175, 135),
+    rgb(175, 175, 175),
+    rgb(175, 175, 215),
+    rgb(175, 175, 255),
+    rgb(175, 215, 0),
+    rgb(175, 215, 95),
+    rgb(175, 215, 135),
+    rgb(175, 215, 175),
+    rgb(175, 215, 215),
+    rgb(175, 215, 255),
+    rgb(175, 255, 0),
+    rgb(175, 255, 95),
+    rgb(175, 255, 135),
+    rgb(175, 255, 175),
+    rgb(175, 255, 215),
+    rgb(175, 255, 255),
+    rgb(215, 0, 0),
+    rgb(215, 0, 95),
+    rgb(215, 0, 135),
+    rgb(215, 0, 175),
+    rgb(215, 0, 215),
+    rgb(215, 0, 255),
+    rgb(215, 95, 0),
+    rgb(215, 95, 95),
+    rgb(215, 95, 135),
+    rgb(215, 95, 175),
+    rgb(215, 95, 215),
+    rgb(215, 95, 255),
+    rgb(215, 135, 0),
+    rgb(215, 135, 95),
+    rgb(215, 135, 135),
+    rgb(215, 135, 175),
+    rgb(215, 135, 215),
+    rgb(215, 135, 255),
+    rgb(215, 175, 0),
+    rgb(215, 175, 95),
+    rgb(215, 175, 135),
+    rgb(215, 175, 175),
+    rgb(215, 175, 215),
+    rgb(215, 175, 255),
+    rgb(215, 215, 0),
+    rgb(215, 215, 95),
+    rgb(215, 215, 135),
+    rgb(215, 215, 175),
+    rgb(215, 215, 215),
+    rgb(215, 215, 255),
+    rgb(215, 255, 0),
+    rgb(215, 255, 95),
+    rgb(215, 255, 135),
+    rgb(215, 255, 175),
+    rgb(215, 255, 215),
+    rgb(215, 255, 255),
+    rgb(255, 0, 0),
+    rgb(255, 0, 95),
+    rgb(255, 0, 135),
+    rgb(255, 0, 175),
+    rgb(255, 0, 215),
+    rgb(255, 0, 255),
+    rgb(255, 95, 0),
+    rgb(255, 95, 95),
+    rgb(255, 95, 135),
+    rgb(255, 95, 175),
+    rgb(255, 95, 215),
+    rgb(255, 95, 255),
+    rgb(255, 135, 0),
+    rgb(255, 135, 95),
+    rgb(255, 135, 135),
+    rgb(255, 135, 175),
+    rgb(255, 135, 215),
+    rgb(255, 135, 255),
+    rgb(255, 175, 0),
+    rgb(255, 175, 95),
+    rgb(255, 175, 135),
+    rgb(255, 175, 175),
+    rgb(255, 175, 215),
+    rgb(255, 175, 255),
+    rgb(255, 215, 0),
+    rgb(255, 215, 95),
+    rgb(255, 215, 135),
+    rgb(255, 215, 175),
+    rgb(255, 215, 215),
+    rgb(255, 215, 255),
+    rgb(255, 255, 0),
+    rgb(255, 255, 95),
+    rgb(255, 255, 135),
+    rgb(255, 255, 175),
+    rgb(255, 255, 215),
+    rgb(255, 255, 255),
+    rgb(8, 8, 8),
+    rgb(18, 18, 18),
+    rgb(28, 28, 28),
+    rgb(38, 38, 38),
+    rgb(48, 48, 48),
+    rgb(58, 58, 58),
+    rgb(68, 68, 68),
+    rgb(78, 78, 78),
+    rgb(88, 88, 88),
+    rgb(98, 98, 98),
+    rgb(108, 108, 108),
+    rgb(118, 118, 118),
+    rgb(128, 128, 128),
+    rgb(138, 138, 138),
+    rgb(148, 148, 148),
+    rgb(158, 158, 158),
+    rgb(168, 168, 168),
+    rgb(178, 178, 178),
+    rgb(188, 188, 188),
+    rgb(198, 198, 198),
+    rgb(208, 208, 208),
+    rgb(218, 218, 218),
+    rgb(228, 228, 228),
+    rgb(238, 238, 238),
+];
+
+const fn rgb(r: u8, g: u8, b: u8) -> Rgb {
+    Rgb { r, g, b }
+}
+
+impl Color {
+    /// The RGB value of this color, looking indexed colors up in the xterm palette.
+    pub fn to_rgb(&self) -> Rgb {
+        match *self {
+            Color::Rgb(r, g, b) => Rgb::new(r, g, b),
+            Color::Index(i) => XTERM_PALETTE[i as usize],
+        }
+    }
+
+    /// The index of the entry in `palette` that looks closest to this color, for example a palette read from the
+    /// terminal with `Term::query_palette_color`.
+    ///
+    /// Indexed colors are looked up in the xterm palette first. Returns 0 if `palette` is empty.
+    pub fn nearest_indexed(&self, palette: &[Rgb]) -> u8 {
+        let lab = Lab::from(self.to_rgb());
+        palette
+            .iter()
+            .map(|&c| lab.distance(&Lab::from(c)))
+            .enumerate()
+            .fold(
+                (0, f64::INFINITY),
+                |best, (i, d)| if d < best.1 { (i, d) } else { best },
+            )
+            .0 as u8
+    }
+
+    /// The closest color that can be shown with `depth`, or `None` if there are no colors.
+    ///
+    /// For 256 colors only the color cube and grey ramp are used, since the first 16 colors are often changed by
+    /// the user's theme.
+    pub fn downsample(&self, depth: ColorDepth) -> Option<Color> {
+        match (depth, *self) {
+            (ColorDepth::None, _) => None,
+            (ColorDepth::TrueColor, c) => Some(c),
+            (ColorDepth::Ansi256, c @ Color::Index(_)) => Some(c),
+            (ColorDepth::Ansi16, c @ Color::Index(0..=15)) => Some(c),
+            (ColorDepth::Ansi256, c) => {
+                Some(Color::Index(16 + c.nearest_indexed(&XTERM_PALETTE[16..])))
+            }
+            (ColorDepth::Ansi16, c) => Some(Color::Index(c.nearest_indexed(&XTERM_PALETTE[..16]))),
+        }
+    }
+}
+
+/// The names of the 16 standard colors, and their palette indices.
+const COLOR_NAMES: &[(&str, u8)] = &[
+    ("black", 0),
+    ("red", 1),
+    ("green", 2),
+    ("yellow", 3),
+    ("blue", 4),
+    ("magenta", 5),
+    ("cyan", 6),
+    ("grey", 7),
+    ("gray", 7),
+    ("darkgrey", 8),
+    ("darkgray", 8),
+    ("brightblack", 8),
+    ("brightred", 9),
+    ("brightgreen", 10),
+    ("brightyellow", 11),
+    ("brightblue", 12),
+    ("brightmagenta", 13),
+    ("brightcyan", 14),
+    ("white", 15),
+    ("brightgrey", 15),
+    ("brightgray", 15),
+    ("brightwhite", 15),
+];
+
+impl From<&'static str> for Color {
+    fn from(v: &'static str) -> Color {
+        Color::from_str(v).unwrap()
+    }
+}
+
+impl FromStr for Color {
+    type Err = Error;
+
+    /// Parse a color from a config file or the command line.
+    ///
+    /// Accepts `#rgb` and `#rrggbb` hex colors, `rgb(r, g, b)` with channels from 0-255 (or 0.0-1.0), palette
+    /// indices from 0-255, and the names of the 16 standard colors (like `red` and `brightblue`). With the
+    /// `x11-colors` feature the X11 color names (like `dodgerblue`) work too. Case, and spaces, `-` and `_` in
+    /// names, are ignored.
+    fn from_str(s: &str) -> Result<Color> {
+        let s = s.trim();
+        let lower = s.to_lowercase();
+        let invalid = || Error::from(ParseError::InvalidColor(s.to_owned()));
+
+        if let Some(hex) = lower.strip_prefix('#') {
+            return parse_hex(hex).ok_or_else(invalid);
+        }
+        if let Some(args) = lower.strip_prefix("rgb(") {
+            return args
+                .strip_suffix(')')
+                .and_then(parse_rgb_args)
+                .ok_or_else(invalid);
+        }
+        if !lower.is_empty() && lower.bytes().all(|c| c.is_ascii_digit()) {
+            return lower.parse().map(Color::Index).map_err(|_| invalid());
+        }
+
+        let name: String = lower
+            .chars()
+            .filter(|&c| c != ' ' && c != '-' && c != '_')
+            .collect();
+        if let Some(&(_, i)) = COLOR_NAMES.iter().find(|&&(n, _)| n == name) {
+            return Ok(Color::Index(i));
+        }
+        #[cfg(feature = "x11-colors")]
+        {
+            if let Ok(i) = X11_COLORS.binary_search_by_key(&name.as_str(), |&(n, _)| n) {
+                return Ok(X11_COLORS[i].1.into());
+            }
+        }
+        Err(ParseError::UnknownColorName(s.to_owned()).into())
+    }
+}
+
+/// Parse the digits of a `#rgb` or `#rrggbb` color.
+fn parse_hex(hex: &str) -> Option<Color> {
+    if !hex.bytes().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let channel = |i: usize, len: usize| u8::from_str_radix(&hex[i * len..(i + 1) * len], 16).ok();
+    match hex.len() {
+        // each digit is repeated, so `#f80` is `#ff8800`
+        3 => Some(Color::Rgb(
+            channel(0, 1)? * 17,
+            channel(1, 1)? * 17,
+            channel(2, 1)? * 17,
+        )),
+        6 => Some(Color::Rgb(channel(0, 2)?, channel(1, 2)?, channel(2, 2)?)),
+        _ => None,
+    }
+}
+
+/// Parse the `r, g, b` inside `rgb(...)`.
+fn parse_rgb_args(args: &str) -> Option<Color> {
+    let channel = |s: &str| {
+        let s = s.trim();
+        if s.contains('.') {
+            s.parse::<f64>()
+                .ok()
+                .filter(|v| (0.0..=1.0).contains(v))
+                .map(|v| (v * 255.0).round() as u8)
+        } else {
+            s.parse::<u8>().ok()
+        }
+    };
+    let mut channels = args.split(',').map(channel);
+    let c = Color::Rgb(channels.next()??, channels.next()??, channels.next()??);
+    match channels.next() {
+        Some(_) => None,
+        None => Some(c),
+    }
+}
+
+/// Parse an X11 color spec, like the colors in `OSC 4`, `OSC 10` and `OSC 11` replies, X resources, and some
+/// config files.
+///
+/// - `rgb:r/g/b` has 1-4 hex digits per channel, each scaled up to 16 bits, so `rgb:f/8/0` is
+///   `rgb:ffff/8888/0000`.
+/// - `rgba:r/g/b/a` is the same with an alpha channel, which is dropped.
+/// - `#rgb`, `#rrggbb`, `#rrrgggbbb` and `#rrrrggggbbbb` give the high bits of each channel, so `#f80` is `#f08000`.
+///
+/// Like xterm, each 8-bit channel is the high byte of the 16-bit one. The prefix is case insensitive.
+///
+/// ```
+/// use nixterm::ansi::{parse_x_color, Rgb};
+///
+/// assert_eq!(parse_x_color("rgb:cdcd/0000/eeee").unwrap(), Rgb::new(0xcd, 0, 0xee));
+/// assert_eq!(parse_x_color("rgb:f/80/0").unwrap(), Rgb::new(0xff, 0x80, 0));
+/// assert!(parse_x_color("rgb:f/80").is_err());
+/// ```
+pub fn parse_x_color(s: &str) -> Result<Rgb> {
+    let lower = s.to_ascii_lowercase();
+    let channels = if let Some(spec) = lower.strip_prefix("rgba:") {
+        scale_x_channels(spec, 4)
+    } else if let Some(spec) = lower.strip_prefix("rgb:") {
+        scale_x_channels(spec, 3)
+    } else if let Some(digits) = lower.strip_prefix('#') {
+        sharp_x_channels(digits)
+    } else {
+        None
+    };
+    let [r, g, b] = channels.ok_or_else(|| ParseError::InvalidXColor(s.to_owned()))?;
+    Ok(Rgb::new((r >> 8) as u8, (g >> 8) as u8, (b >> 8) as u8))
+}
+
+/// Parse 1-4 hex digits.
+fn hex_digits(digits: &str) -> Option<u32> {
+    if digits.is_empty() || digits.len() > 4 || !digits.bytes().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    u32::from_str_radix(digits, 16).ok()
+}
+
+/// Parse the `/` separated channels of an `rgb:` or `rgba:` spec, which has `count` of them, scaling the first three
+/// to 16 bits.
+fn scale_x_channels(spec: &str, count: usize) -> Option<[u32; 3]> {
+    let mut channels = [0; 3];
+    let mut n = 0;
+    for digits in spec.split('/') {
+        // `hex_digits` rejects more than 4 digits, so the shift can't overflow
+        let v = hex_digits(digits)?;
+        let max = (1 << (4 * digits.len())) - 1;
+        if let Some(c) = channels.get_mut(n) {
+            *c = v * 0xffff / max;
+        }
+        n += 1;
+    }
+    if n == count {
+        Some(channels)
+    } else {
+        None
+    }
+}
+
+/// Parse the digits after the `#` of a spec, which are the high bits of 16-bit channels.
+fn sharp_x_channels(digits: &str) -> Option<[u32; 3]> {
+    let width = match digits.len() {
+        3 | 6 | 9 | 12 => digits.len() / 3,
+        _ => return None,
+    };
+    let channel = |i: usize| {
+        hex_digits(digits.get(i * width..(i + 1) * width)?).map(|v| v << (16 - 4 * width))
+    };
+    Some([channel(0)?, channel(1)?, channel(2)?])
+}
+
+/// RGB colors are written as `#rrggbb`, and indexed colors as their index, which both parse back to the same color.
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Color::Index(i) => write!(f, "{}", i),
+            Color::Rgb(r, g, b) => write!(f, "#{:02x}{:02x}{:02x}", r, g, b),
+        }
+    }
+}
+
+impl Rgb {
+    /// How bright this color is, from 0 for black to 1 for white, as defined by WCAG.
+    pub fn relative_luminance(&self) -> f64 {
+        0.2126 * linear(self.r) + 0.7152 * linear(self.g) + 0.0722 * linear(self.b)
+    }
+
+    /// The WCAG contrast ratio between this color and `other`, from 1 (no contrast) to 21 (black and white).
+    ///
+    /// WCAG asks for at least 4.5 between normal text and its background, and 3 for large text.
+    pub fn contrast_ratio(&self, other: Rgb) -> f64 {
+        let (a, b) = (self.relative_luminance(), other.relative_luminance());
+        (a.max(b) + 0.05) / (a.min(b) + 0.05)
+    }
+
+    /// Black or white, whichever is easier to read on top of this color.
+    pub fn readable_foreground(&self) -> Rgb {
+        let (black, white) = (Rgb::new(0, 0, 0), Rgb::new(255, 255, 255));
+        if self.contrast_ratio(black) >= self.contrast_ratio(white) {
+            black
+        } else {
+            white
+        }
+    }
+
+    /// The color this would look like drawn with opacity `alpha` (from 0 to 1) over the opaque color `other`.
+    pub fn blend(&self, other: Rgb, alpha: f64) -> Rgb {
+        let alpha = alpha.clamp(0.0, 1.0);
+        let mix =
+            |a: u8, b: u8| (f64::from(a) * alpha + f64::from(b) * (1.0 - alpha)).round() as u8;
+        Rgb::new(
+            mix(self.r, other.r),
+            mix(self.g, other.g),
+            mix(self.b, other.b),
+        )
+    }
+}
+
+/// Turn an sRGB channel into linear light, from 0 to 1.
+fn linear(v: u8) -> f64 {
+    let v = f64::from(v) / 255.0;
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// A color in the CIE L*a*b* space, where the distance between colors follows how different they look.
+struct Lab {
+    l: f64,
+    a: f64,
+    b: f64,
+}
+
+impl From<Rgb> for Lab {
+    fn from(c: Rgb) -> Lab {
+        // sRGB to linear light, then to CIE XYZ relative to the D65 white point
+        let (r, g, b) = (linear(c.r), linear(c.g), linear(c.b));
+        let x = (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.95047;
+        let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+        let z = (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.08883;
+
+        let f = |t: f64| {
+            if t > 0.008856 {
+                t.cbrt()
+            } else {
+                7.787 * t + 16.0 / 116.0
+            }
+        };
+        Lab {
+            l: 116.0 * f(y) - 16.0,
+            a: 500.0 * (f(x) - f(y)),
+            b: 200.0 * (f(y) - f(z)),
+        }
+    }
+}
+
+impl Lab {
+    /// The squared CIE76 color difference.
+    fn distance(&self, other: &Lab) -> f64 {
+        (self.l - other.l).powi(2) + (self.a - other.a).powi(2) + (self.b - other.b).powi(2)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ansi::*;
+    use std::collections::HashMap;
+
+    fn resolve(
+        choice: ColorChoice,
+        vars: &[(&str, &str)],
+        is_tty: bool,
+        max_colors: Option<u32>,
+    ) -> ColorDepth {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|&(k, v)| (k.to_owned(), v.to_owned()))
+            .collect();
+        choice.resolve(|k| vars.get(k).cloned(), is_tty, max_colors)
+    }
+
+    #[test]
+    fn color_choice() {
+        use ansi::ColorChoice::*;
+        use ansi::ColorDepth::*;
+
+        // a tty gets whatever the terminal supports
+        assert_eq!(resolve(Auto, &[], true, Some(8)), Ansi16);
+        assert_eq!(resolve(Auto, &[], true, Some(256)), Ansi256);
+        assert_eq!(resolve(Auto, &[], true, Some(1 << 24)), TrueColor);
+        assert_eq!(
+            resolve(Auto, &[("COLORTERM", "truecolor")], true, Some(256)),
+            TrueColor
+        );
+        assert_eq!(
+            resolve(Auto, &[("COLORTERM", "24bit")], true, Some(8)),
+            TrueColor
+        );
+        assert_eq!(
+            resolve(Auto, &[("COLORTERM", "yes")], true, Some(8)),
+            Ansi16
+        );
+        assert_eq!(resolve(Auto, &[], true, Some(2)), None);
+        assert_eq!(resolve(Auto, &[], true, Option::None), None);
+
+        // a pipe gets plain text unless it's forced
+        assert_eq!(resolve(Auto, &[], false, Some(256)), None);
+        assert_eq!(
+            resolve(Auto, &[("CLICOLOR_FORCE", "1")], false, Some(256)),
+            Ansi256
+        );
+        assert_eq!(
+            resolve(Auto, &[("CLICOLOR_FORCE", "1")], false, Option::None),
+            Ansi16
+        );
+        assert_eq!(
+            resolve(Auto, &[("CLICOLOR_FORCE", "0")], false, Some(256)),
+            None
+        );
+        assert_eq!(
+            resolve(Auto, &[("CLICOLOR_FORCE", "")], false, Some(256)),
+            None
+        );
+
+        // NO_COLOR wins over everything but an explicit choice
+        assert_eq!(resolve(Auto, &[("NO_COLOR", "1")], true, Some(256)), None);
+        assert_eq!(
+            resolve(
+                Auto,
+                &[("NO_COLOR", "1"), ("CLICOLOR_FORCE", "1")],
+                false,
+                Some(256)
+            ),
+            None
+        );
+        assert_eq!(resolve(Auto, &[("NO_COLOR", "")], true, Some(256)), Ansi256);
+        assert_eq!(
+            resolve(Always, &[("NO_COLOR", "1")], false, Some(256)),
+            Ansi256
+        );
+        assert_eq!(resolve(Always, &[], false, Option::None), Ansi16);
+        assert_eq!(
+            resolve(Never, &[("CLICOLOR_FORCE", "1")], true, Some(256)),
+            None
+        );
+    }
+
+    #[test]
+    fn downsample() {
+        assert_eq!(Color::Index(196).to_rgb(), Rgb::new(255, 0, 0));
+        assert_eq!(Color::Index(232).to_rgb(), Rgb::new(8, 8, 8));
+        assert_eq!(Color::Index(255).to_rgb(), Rgb::new(238, 238, 238));
+
+        let c = Color::Rgb(250, 10, 10);
+        assert_eq!(c.downsample(ColorDepth::TrueColor), Some(c));
+        assert_eq!(c.downsample(ColorDepth::Ansi256), Some(Color::Index(196)));
+        assert_eq!(c.downsample(ColorDepth::Ansi16), Some(Color::Index(9)));
+        assert_eq!(c.downsample(ColorDepth::None), None);
+
+        assert_eq!(
+            Color::Rgb(95, 135, 175).downsample(ColorDepth::Ansi256),
+            Some(Color::Index(67))
+        );
+        assert_eq!(
+            Color::Index(67).downsample(ColorDepth::Ansi256),
+            Some(Color::Index(67))
+        );
+        assert_eq!(
+            Color::Index(12).downsample(ColorDepth::Ansi16),
+            Some(Color::Index(12))
+        );
+        assert_eq!(
+            Color::Index(244).downsample(ColorDepth::Ansi16),
+            Some(Color::Index(8))
+        );
+    }
+
+    fn parse(s: &str) -> Color {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parse_colors() {
+        assert_eq!(parse("#ff8800"), Color::Rgb(255, 136, 0));
+        assert_eq!(parse("#FF8800"), Color::Rgb(255, 136, 0));
+        assert_eq!(parse("#f80"), Color::Rgb(255, 136, 0));
+        assert_eq!(parse("  #000  "), Color::Rgb(0, 0, 0));
+        assert_eq!(parse("rgb(255,136,0)"), Color::Rgb(255, 136, 0));
+        assert_eq!(parse("RGB( 64, 64 , 128 )"), Color::Rgb(64, 64, 128));
+        assert_eq!(parse("rgb(1.0, 0.5, 0.0)"), Color::Rgb(255, 128, 0));
+        assert_eq!(parse("0"), Color::Index(0));
+        assert_eq!(parse("208"), Color::Index(208));
+        assert_eq!(parse("255"), Color::Index(255));
+
+        for bad in &[
+            "#ff880",
+            "#ff88zz",
+            "#",
+            "rgb(1, 2)",
+            "rgb(1, 2, 3, 4)",
+            "rgb(256, 0, 0)",
+            "rgb(1,2,3",
+            "rgb(2.0, 0, 0)",
+            "256",
+        ] {
+            match bad.parse::<Color>() {
+                Err(Error::Parse(e)) => assert_eq!(e, ParseError::InvalidColor(bad.to_string())),
+                Err(e) => panic!("{:?} failed with {:?}", bad, e),
+                Ok(c) => panic!("{:?} parsed as {:?}", bad, c),
+            }
+        }
+        let e = "chartreuse-ish".parse::<Color>().unwrap_err();
+        match e {
+            Error::Parse(ParseError::UnknownColorName(ref name)) => assert_eq!(name, "chartreuse-ish"),
+            ref e => panic!("{:?}", e),
+        }
+        assert!(e.to_string().contains("#rrggbb"), "{}", e);
+        assert!("".parse::<Color>().is_err());
+    }
+
+    #[test]
+    fn x_colors() {
+        let parse = |s: &str| parse_x_color(s).unwrap();
+        // 4, 8, 12 and 16 bits, which keep the high byte of the channel scaled to 16 bits
+        assert_eq!(parse("rgb:f/8/0"), Rgb::new(0xff, 0x88, 0));
+        assert_eq!(parse("rgb:ff/80/01"), Rgb::new(0xff, 0x80, 0x01));
+        assert_eq!(parse("rgb:fff/800/123"), Rgb::new(0xff, 0x80, 0x12));
+        assert_eq!(parse("rgb:ffff/80ff/00ff"), Rgb::new(0xff, 0x80, 0));
+        assert_eq!(parse("rgb:cdcd/0000/eeee"), Rgb::new(0xcd, 0, 0xee));
+        assert_eq!(parse("rgb:f/ff/fff"), Rgb::new(0xff, 0xff, 0xff));
+        assert_eq!(parse("RGB:FF/80/00"), Rgb::new(0xff, 0x80, 0));
+
+        // alpha is dropped
+        assert_eq!(parse("rgba:ffff/8080/0000/7fff"), Rgb::new(0xff, 0x80, 0));
+        assert_eq!(parse("rgba:f/8/0/0"), Rgb::new(0xff, 0x88, 0));
+
+        // these are the high bits
+        assert_eq!(parse("#f80"), Rgb::new(0xf0, 0x80, 0));
+        assert_eq!(parse("#ff8000"), Rgb::new(0xff, 0x80, 0));
+        assert_eq!(parse("#fff888000"), Rgb::new(0xff, 0x88, 0));
+        assert_eq!(parse("#ffff88880000"), Rgb::new(0xff, 0x88, 0));
+
+        for bad in &[
+            "",
+            "rgb:",
+            "rgb:ff/80",
+            "rgb:ff/80/00/ff",
+            "rgba:ff/80/00",
+            "rgba:ff/80/00/ff/ff",
+            "rgb:ff//00",
+            "rgb:fffff/0/0",
+            // too many digits to scale, not just too many to be a channel
+            "rgb:ffffffff/0/0",
+            "rgb:123456789/0/0",
+            "rgba:0/0/0/fffffffff",
+            "rgb:+f/0/0",
+            "rgb:gg/0/0",
+            "#ff800",
+            "#ggg",
+            "ff8000",
+            "rgb(255, 128, 0)",
+        ] {
+            match parse_x_color(bad) {
+                Err(Error::Parse(e)) => assert_eq!(e, ParseError::InvalidXColor(bad.to_string())),
+                Err(e) => panic!("{:?} failed with {:?}", bad, e),
+                Ok(c) => panic!("{:?} parsed as {:?}", bad, c),
+            }
+        }
+    }
+
+    #[test]
+    fn color_names() {
+        let names = [
+            ("black", 0),
+            ("red", 1),
+            ("green", 2),
+            ("yellow", 3),
+            ("blue", 4),
+            ("magenta", 5),
+            ("cyan", 6),
+            ("grey", 7),
+            ("gray", 7),
+            ("darkgrey", 8),
+            ("darkgray", 8),
+            ("brightblack", 8),
+            ("brightred", 9),
+            ("brightgreen", 10),
+            ("brightyellow", 11),
+            ("brightblue", 12),
+            ("brightmagenta", 13),
+            ("brightcyan", 14),
+            ("white", 15),
+            ("brightgrey", 15),
+            ("brightgray", 15),
+            ("brightwhite", 15),
+        ];
+        for &(name, i) in names.iter() {
+            assert_eq!(parse(name), Color::Index(i), "{}", name);
+            assert_eq!(parse(&name.to_uppercase()), Color::Index(i), "{}", name);
+        }
+        assert_eq!(parse("Bright Red"), Color::Index(9));
+        assert_eq!(parse("bright-red"), Color::Index(9));
+        assert_eq!(parse("bright_red"), Color::Index(9));
+
+        // every index from 0-15 has a name
+        for i in 0..16 {
+            assert!(names.iter().any(|&(_, n)| n == i), "{}", i);
+        }
+    }
+
+    #[cfg(feature = "x11-colors")]
+    #[test]
+    fn x11_color_names() {
+        assert_eq!(parse("dodgerblue"), Color::Rgb(30, 144, 255));
+        assert_eq!(parse("Dodger Blue"), Color::Rgb(30, 144, 255));
+        assert_eq!(parse("LightGoldenrodYellow"), Color::Rgb(250, 250, 210));
+        assert_eq!(parse("grey50"), Color::Rgb(127, 127, 127));
+
+        // the standard names keep meaning the palette colors
+        assert_eq!(parse("red"), Color::Index(1));
+        assert_eq!(parse("grey"), Color::Index(7));
+    }
+
+    #[cfg(not(feature = "x11-colors"))]
+    #[test]
+    fn x11_color_names() {
+        assert!("dodgerblue".parse::<Color>().is_err());
+    }
+
+    #[test]
+    fn display_round_trip() {
+        for &c in &[
+            Color::Rgb(255, 136, 0),
+            Color::Rgb(0, 0, 0),
+            Color::Rgb(1, 2, 254),
+            Color::Index(0),
+            Color::Index(208),
+        ] {
+            assert_eq!(parse(&c.to_string()), c);
+        }
+        assert_eq!(Color::Rgb(255, 136, 0).to_string(), "#ff8800");
+        assert_eq!(Color::Index(12).to_string(), "12");
+    }
+
+    #[test]
+    fn contrast() {
+        let black = Rgb::new(0, 0, 0);
+        let white = Rgb::new(255, 255, 255);
+        let ratio = |a: Rgb, b: Rgb| (a.contrast_ratio(b) * 100.0).round() / 100.0;
+
+        assert_eq!(black.relative_luminance(), 0.0);
+        assert_eq!(white.relative_luminance(), 1.0);
+        assert!((Rgb::new(128, 128, 128).relative_luminance() - 0.2159).abs() < 0.0001);
+
+        // the examples WCAG gives of the lightest greys readable on white
+        assert_eq!(ratio(black, white), 21.0);
+        assert_eq!(ratio(white, black), 21.0);
+        assert_eq!(ratio(Rgb::new(0x76, 0x76, 0x76), white), 4.54);
+        assert_eq!(ratio(Rgb::new(0x77, 0x77, 0x77), white), 4.48);
+        assert_eq!(ratio(Rgb::new(0x59, 0x59, 0x59), white), 7.0);
+        assert_eq!(ratio(Rgb::new(0, 0, 255), white), 8.59);
+        assert_eq!(ratio(Rgb::new(255, 0, 0), white), 4.0);
+        assert_eq!(ratio(white, white), 1.0);
+
+        assert_eq!(Rgb::new(255, 255, 0).readable_foreground(), black);
+        assert_eq!(Rgb::new(0, 0, 255).readable_foreground(), white);
+        assert_eq!(Rgb::new(255, 0, 0).readable_foreground(), black);
+        assert_eq!(Rgb::new(0, 128, 0).readable_foreground(), white);
+        assert_eq!(Rgb::new(128, 128, 128).readable_foreground(), black);
+    }
+
+    #[test]
+    fn blend() {
+        let red = Rgb::new(255, 0, 0);
+        let navy = Rgb::new(0, 0, 128);
+
+        assert_eq!(red.blend(navy, 1.0), red);
+        assert_eq!(red.blend(navy, 0.0), navy);
+        assert_eq!(red.blend(navy, 0.5), Rgb::new(128, 0, 64));
+        assert_eq!(red.blend(navy, 0.25), Rgb::new(64, 0, 96));
+        assert_eq!(
+            Rgb::new(255, 255, 255).blend(Rgb::new(0, 0, 0), 0.2),
+            Rgb::new(51, 51, 51)
+        );
+
+        // out of range opacities are clamped
+        assert_eq!(red.blend(navy, 1.5), red);
+        assert_eq!(red.blend(navy, -1.0), navy);
+    }
+
+    #[test]
+    fn nearest_colors() {
+        let nearest = |r, g, b| {
+            let c = Color::Rgb(r, g, b);
+            (
+                c.downsample(ColorDepth::Ansi256),
+                c.downsample(ColorDepth::Ansi16),
+            )
+        };
+        let indexed = |a, b| (Some(Color::Index(a)), Some(Color::Index(b)));
+
+        // orange
+        assert_eq!(nearest(255, 165, 0), indexed(214, 3));
+        // dark purple, which comparing RGB values directly turns grey
+        assert_eq!(nearest(48, 25, 82), indexed(53, 0));
+        // pastel cyan
+        assert_eq!(nearest(175, 238, 238), indexed(159, 7));
+        // teal and salmon, which comparing RGB values directly turn grey with 16 colors
+        assert_eq!(nearest(0, 128, 128), indexed(30, 6));
+        assert_eq!(nearest(250, 128, 114), indexed(210, 1));
+
+        // a palette read from the terminal
+        let palette = [
+            Rgb::new(40, 42, 54),
+            Rgb::new(255, 85, 85),
+            Rgb::new(80, 250, 123),
+        ];
+        assert_eq!(Color::Rgb(200, 60, 60).nearest_indexed(&palette), 1);
+        assert_eq!(Color::Index(2).nearest_indexed(&palette), 2);
+        assert_eq!(Color::Rgb(0, 0, 0).nearest_indexed(&palette), 0);
+        assert_eq!(Color::Rgb(0, 0, 0).nearest_indexed(&[]), 0);
+
+        // every palette entry finds itself, or an earlier entry with the same color
+        for &c in XTERM_PALETTE.iter() {
+            let i = Color::from(c).nearest_indexed(&XTERM_PALETTE);
+            assert_eq!(XTERM_PALETTE[i as usize], c);
+        }
+    }
+}
@@ -0,0 +1,536 @@
+//! Human readable descriptions of escape sequences, for debugging and bug reports.
+use ansi::parser::{Parser, Perform};
+use std::fmt::Write;
+
+/// Describe everything in `bytes`, one entry for each escape sequence, control character, or run of text.
+///
+/// Each sequence is spelled out with its parameters, followed by what it does when that's known.
+///
+/// ```
+/// use nixterm::ansi;
+///
+/// assert_eq!(
+///     ansi::describe(b"\x1b[38;5;208mhi\r\n"),
+///     vec![
+///         "CSI 38;5;208 m  (set fg indexed 208)",
+///         "text \"hi\"",
+///         "CR  (carriage return)",
+///         "LF  (line feed)",
+///     ]
+/// );
+/// ```
+pub fn describe(bytes: &[u8]) -> Vec<String> {
+    let mut parser = Parser::new();
+    let mut describer = Describer::new(true);
+    parser.feed(&mut describer, bytes);
+    describer.finish();
+    describer.take()
+}
+
+/// A `Perform` that describes everything the parser finds, see `describe`.
+pub(crate) struct Describer {
+    lines: Vec<String>,
+    /// Describe runs of text too, not just sequences and control characters.
+    keep_text: bool,
+    text: String,
+    /// The last sequence was `ESC O` (SS3), which picks the meaning of the next character.
+    ss3: bool,
+    /// The start of the DCS sequence being read, and the length of its payload so far.
+    dcs: Option<(String, usize)>,
+    /// An OSC or DCS string just ended, so the `ESC \\` that follows is part of it.
+    string_ended: bool,
+}
+
+impl Describer {
+    pub(crate) fn new(keep_text: bool) -> Describer {
+        Describer {
+            lines: Vec::new(),
+            keep_text,
+            text: String::new(),
+            ss3: false,
+            dcs: None,
+            string_ended: false,
+        }
+    }
+
+    /// Take the descriptions so far.
+    pub(crate) fn take(&mut self) -> Vec<String> {
+        ::std::mem::take(&mut self.lines)
+    }
+
+    /// Describe any text that hasn't been ended by a sequence yet.
+    pub(crate) fn finish(&mut self) {
+        if !self.text.is_empty() {
+            let text = ::std::mem::take(&mut self.text);
+            self.lines.push(format!("text {:?}", text));
+        }
+    }
+
+    fn push(&mut self, raw: String, meaning: Option<String>) {
+        self.finish();
+        self.ss3 = false;
+        self.string_ended = false;
+        self.lines.push(match meaning {
+            Some(meaning) => format!("{}  ({})", raw, meaning),
+            None => raw,
+        });
+    }
+}
+
+impl Perform for Describer {
+    fn print(&mut self, c: char) {
+        if self.ss3 {
+            let meaning = ss3_meaning(c).map(str::to_string);
+            self.push(format!("SS3 {}", c), meaning);
+        } else if self.keep_text {
+            self.text.push(c);
+        }
+    }
+
+    fn execute(&mut self, byte: u8) {
+        let (name, meaning) = control_name(byte);
+        let raw = match name {
+            Some(name) => name.to_string(),
+            None => format!("C0 0x{:02x}", byte),
+        };
+        self.push(raw, meaning.map(str::to_string));
+    }
+
+    fn csi_dispatch(&mut self, params: &[i64], intermediates: &[u8], action: u8) {
+        let raw = sequence("CSI", params, intermediates, action);
+        let meaning = csi_meaning(params, intermediates, action);
+        self.push(raw, meaning);
+    }
+
+    fn esc_dispatch(&mut self, intermediates: &[u8], byte: u8) {
+        if self.string_ended && intermediates.is_empty() && byte == b'\\' {
+            self.string_ended = false;
+            return;
+        }
+
+        let mut raw = String::from("ESC");
+        for &b in intermediates {
+            push_byte(&mut raw, b);
+        }
+        push_byte(&mut raw, byte);
+
+        let meaning = esc_meaning(intermediates, byte).map(str::to_string);
+        let ss3 = intermediates.is_empty() && byte == b'O';
+        if !ss3 {
+            self.push(raw, meaning);
+        } else {
+            self.finish();
+            self.ss3 = true;
+        }
+    }
+
+    fn osc_dispatch(&mut self, params: &[&[u8]], bell_terminated: bool) {
+        let joined = params
+            .iter()
+            .map(|p| String::from_utf8_lossy(p).into_owned())
+            .collect::<Vec<_>>()
+            .join(";");
+        let raw = format!(
+            "OSC {} {}",
+            joined,
+            if bell_terminated { "BEL" } else { "ST" }
+        );
+        let meaning = osc_meaning(params).map(str::to_string);
+        self.push(raw, meaning);
+        self.string_ended = !bell_terminated;
+    }
+
+    fn hook(&mut self, params: &[i64], intermediates: &[u8], action: u8) {
+        self.finish();
+        self.dcs = Some((sequence("DCS", params, intermediates, action), 0));
+    }
+
+    fn put(&mut self, _byte: u8) {
+        if let Some((_, ref mut len)) = self.dcs {
+            *len += 1;
+        }
+    }
+
+    fn unhook(&mut self) {
+        if let Some((raw, len)) = self.dcs.take() {
+            let meaning = match &raw[4..] {
+                "q" => Some("sixel image"),
+                "$ q" => Some("request setting (DECRQSS)"),
+                "+ q" => Some("request termcap (XTGETTCAP)"),
+                _ => None,
+            };
+            let meaning = match meaning {
+                Some(meaning) => format!("{}, {} bytes", meaning, len),
+                None => format!("{} bytes", len),
+            };
+            self.push(format!("{} ... ST", raw), Some(meaning));
+            self.string_ended = true;
+        }
+    }
+}
+
+/// Spell out a CSI or DCS sequence, private markers first, then the parameters, intermediates and final byte.
+fn sequence(kind: &str, params: &[i64], intermediates: &[u8], action: u8) -> String {
+    let mut s = String::from(kind);
+    let (private, intermediates) = split_private(intermediates);
+    if !private.is_empty() {
+        s.push(' ');
+        s.extend(private.iter().map(|&b| b as char));
+    }
+    if !params.is_empty() {
+        s.push(' ');
+        for (i, p) in params.iter().enumerate() {
+            if i > 0 {
+                s.push(';');
+            }
+            let _ = write!(s, "{}", p);
+        }
+    }
+    for &b in intermediates {
+        push_byte(&mut s, b);
+    }
+    push_byte(&mut s, action);
+    s
+}
+
+/// Add a space and then `b` to `s`, spelling out a space as `SP`.
+fn push_byte(s: &mut String, b: u8) {
+    s.push(' ');
+    match b {
+        b' ' => s.push_str("SP"),
+        b => s.push(b as char),
+    }
+}
+
+/// Split the private markers (`<`, `=`, `>` and `?`) from the real intermediates.
+fn split_private(intermediates: &[u8]) -> (&[u8], &[u8]) {
+    let n = intermediates
+        .iter()
+        .take_while(|&&b| (0x3c..=0x3f).contains(&b))
+        .count();
+    intermediates.split_at(n)
+}
+
+fn control_name(byte: u8) -> (Option<&'static str>, Option<&'static str>) {
+    match byte {
+        0x00 => (Some("NUL"), None),
+        0x05 => (Some("ENQ"), Some("enquiry")),
+        0x07 => (Some("BEL"), Some("bell")),
+        0x08 => (Some("BS"), Some("backspace")),
+        0x09 => (Some("HT"), Some("tab")),
+        0x0a => (Some("LF"), Some("line feed")),
+        0x0b => (Some("VT"), Some("vertical tab")),
+        0x0c => (Some("FF"), Some("form feed")),
+        0x0d => (Some("CR"), Some("carriage return")),
+        0x0e => (Some("SO"), Some("shift out, use G1")),
+        0x0f => (Some("SI"), Some("shift in, use G0")),
+        0x18 => (Some("CAN"), Some("cancel")),
+        0x1a => (Some("SUB"), Some("substitute")),
+        0x7f => (Some("DEL"), None),
+        _ => (None, None),
+    }
+}
+
+fn esc_meaning(intermediates: &[u8], byte: u8) -> Option<&'static str> {
+    Some(match (intermediates, byte) {
+        (b"", b'7') => "save cursor",
+        (b"", b'8') => "restore cursor",
+        (b"", b'=') => "keypad application mode",
+        (b"", b'>') => "keypad numeric mode",
+        (b"", b'D') => "index",
+        (b"", b'E') => "next line",
+        (b"", b'H') => "set tab stop",
+        (b"", b'M') => "reverse index",
+        (b"", b'\\') => "string terminator",
+        (b"", b'c') => "full reset",
+        (b"(", b'B') => "designate G0 as US ASCII",
+        (b"(", b'0') => "designate G0 as line drawing",
+        (b")", b'B') => "designate G1 as US ASCII",
+        (b")", b'0') => "designate G1 as line drawing",
+        (b"#", b'8') => "screen alignment test",
+        _ => return None,
+    })
+}
+
+fn ss3_meaning(c: char) -> Option<&'static str> {
+    Some(match c {
+        'A' => "up",
+        'B' => "down",
+        'C' => "right",
+        'D' => "left",
+        'F' => "end",
+        'H' => "home",
+        'P' => "F1",
+        'Q' => "F2",
+        'R' => "F3",
+        'S' => "F4",
+        _ => return None,
+    })
+}
+
+fn osc_meaning(params: &[&[u8]]) -> Option<&'static str> {
+    Some(match params.first().map_or(&b""[..], |p| p) {
+        b"0" => "set icon name and window title",
+        b"1" => "set icon name",
+        b"2" => "set window title",
+        b"4" => "set palette color",
+        b"7" => "set working directory",
+        b"8" => "hyperlink",
+        b"9" => "notification",
+        b"10" => "foreground color",
+        b"11" => "background color",
+        b"12" => "cursor color",
+        b"52" => "clipboard",
+        b"104" => "reset palette color",
+        b"777" => "notification",
+        _ => return None,
+    })
+}
+
+fn csi_meaning(params: &[i64], intermediates: &[u8], action: u8) -> Option<String> {
+    let (private, intermediates) = split_private(intermediates);
+    let n = |default: i64| {
+        params
+            .first()
+            .cloned()
+            .filter(|&n| n != 0)
+            .unwrap_or(default)
+    };
+    let s = match (private, intermediates, action) {
+        (b"", b"", b'm') => return Some(sgr_meaning(params)),
+        (b"", b"", b'A') => format!("cursor up {}", n(1)),
+        (b"", b"", b'B') => format!("cursor down {}", n(1)),
+        (b"", b"", b'C') => format!("cursor forward {}", n(1)),
+        (b"", b"", b'D') => format!("cursor back {}", n(1)),
+        (b"", b"", b'E') => format!("cursor down {} lines, to column 1", n(1)),
+        (b"", b"", b'F') => format!("cursor up {} lines, to column 1", n(1)),
+        (b"", b"", b'G') => format!("cursor to column {}", n(1)),
+        (b"", b"", b'd') => format!("cursor to row {}", n(1)),
+        (b"", b"", b'H') | (b"", b"", b'f') => {
+            let col = params.get(1).cloned().filter(|&n| n != 0).unwrap_or(1);
+            format!("cursor to row {}, column {}", n(1), col)
+        }
+        (b"", b"", b'I') => format!("tab forward {}", n(1)),
+        (b"", b"", b'Z') => format!("tab back {}", n(1)),
+        (b"", b"", b'J') => match n(0) {
+            0 => "erase below".to_string(),
+            1 => "erase above".to_string(),
+            2 => "erase screen".to_string(),
+            3 => "erase scrollback".to_string(),
+            _ => return None,
+        },
+        (b"", b"", b'K') => match n(0) {
+            0 => "erase to end of line".to_string(),
+            1 => "erase to start of line".to_string(),
+            2 => "erase line".to_string(),
+            _ => return None,
+        },
+        (b"", b"", b'@') => format!("insert {} characters", n(1)),
+        (b"", b"", b'P') => format!("delete {} characters", n(1)),
+        (b"", b"", b'X') => format!("erase {} characters", n(1)),
+        (b"", b"", b'L') => format!("insert {} lines", n(1)),
+        (b"", b"", b'M') => format!("delete {} lines", n(1)),
+        (b"", b"", b'S') => format!("scroll up {}", n(1)),
+        (b"", b"", b'T') => format!("scroll down {}", n(1)),
+        (b"", b"", b'g') => match n(0) {
+            0 => "clear tab stop".to_string(),
+            3 => "clear all tab stops".to_string(),
+            _ => return None,
+        },
+        (b"", b"", b'r') => match params {
+            [] => "reset scroll region".to_string(),
+            [top] => format!("scroll region from row {}", top),
+            [top, bottom, ..] => format!("scroll region rows {}-{}", top, bottom),
+        },
+        (b"", b"", b's') => "save cursor".to_string(),
+        (b"", b"", b'u') => "restore cursor".to_string(),
+        (b"", b"", b'c') => "request primary device attributes".to_string(),
+        (b">", b"", b'c') => "request secondary device attributes".to_string(),
+        (b">", b"", b'q') => "request terminal version (XTVERSION)".to_string(),
+        (b"", b"", b'n') => match n(0) {
+            5 => "request status".to_string(),
+            6 => "request cursor position".to_string(),
+            _ => return None,
+        },
+        (b"", b" ", b'q') => format!("cursor style {}", n(0)),
+        (b"", b"", b'h') => format!("set mode {}", modes(params)),
+        (b"", b"", b'l') => format!("reset mode {}", modes(params)),
+        (b"?", b"", b'h') => format!("set private mode {}", private_modes(params)),
+        (b"?", b"", b'l') => format!("reset private mode {}", private_modes(params)),
+        (b"?", b"$", b'p') => format!("request private mode {}", private_modes(params)),
+        (b"", b"", b't') => "window operation".to_string(),
+        _ => return None,
+    };
+    Some(s)
+}
+
+fn modes(params: &[i64]) -> String {
+    params
+        .iter()
+        .map(|p| match p {
+            4 => "4 (insert)".to_string(),
+            20 => "20 (newline)".to_string(),
+            p => p.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn private_modes(params: &[i64]) -> String {
+    params
+        .iter()
+        .map(|&p| {
+            let name = match p {
+                1 => "application cursor keys",
+                5 => "reverse video",
+                6 => "origin",
+                7 => "autowrap",
+                12 => "blinking cursor",
+                25 => "cursor visible",
+                47 | 1047 => "alternate screen",
+                1000 => "mouse clicks",
+                1002 => "mouse drags",
+                1003 => "mouse motion",
+                1004 => "focus events",
+                1006 => "SGR mouse",
+                1049 => "alternate screen, saving the cursor",
+                2004 => "bracketed paste",
+                2026 => "synchronized output",
+                _ => return p.to_string(),
+            };
+            format!("{} ({})", p, name)
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+const COLOR_NAMES: [&str; 8] = [
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+];
+
+/// Describe the attributes set by an SGR sequence, like "set bold, set fg red".
+fn sgr_meaning(params: &[i64]) -> String {
+    if params.is_empty() {
+        return "reset attributes".to_string();
+    }
+
+    let mut parts = Vec::new();
+    let mut i = 0;
+    while i < params.len() {
+        let p = params[i];
+        i += 1;
+        let part = match p {
+            0 => "reset attributes".to_string(),
+            1 => "set bold".to_string(),
+            2 => "set dim".to_string(),
+            3 => "set italic".to_string(),
+            4 => "set underline".to_string(),
+            5 => "set blink".to_string(),
+            7 => "set reverse".to_string(),
+            8 => "set invisible".to_string(),
+            9 => "set strikethrough".to_string(),
+            22 => "reset bold and dim".to_string(),
+            23 => "reset italic".to_string(),
+            24 => "reset underline".to_string(),
+            25 => "reset blink".to_string(),
+            27 => "reset reverse".to_string(),
+            28 => "reset invisible".to_string(),
+            29 => "reset strikethrough".to_string(),
+            30..=37 => format!("set fg {}", COLOR_NAMES[(p - 30) as usize]),
+            39 => "reset fg".to_string(),
+            40..=47 => format!("set bg {}", COLOR_NAMES[(p - 40) as usize]),
+            49 => "reset bg".to_string(),
+            90..=97 => format!("set fg bright {}", COLOR_NAMES[(p - 90) as usize]),
+            100..=107 => format!("set bg bright {}", COLOR_NAMES[(p - 100) as usize]),
+            38 | 48 | 58 => {
+                let which = match p {
+                    38 => "fg",
+                    48 => "bg",
+                    _ => "underline color",
+                };
+                match params.get(i) {
+                    Some(5) if i + 1 < params.len() => {
+                        i += 2;
+                        format!("set {} indexed {}", which, params[i - 1])
+                    }
+                    Some(2) if i + 3 < params.len() => {
+                        i += 4;
+                        format!(
+                            "set {} rgb {},{},{}",
+                            which,
+                            params[i - 3],
+                            params[i - 2],
+                            params[i - 1]
+                        )
+                    }
+                    _ => {
+                        // the rest can't be made sense of
+                        i = params.len();
+                        format!("set {}, malformed", which)
+                    }
+                }
+            }
+            p => format!("unknown attribute {}", p),
+        };
+        parts.push(part);
+    }
+    parts.join(", ")
+}
+
+#[cfg(test)]
+mod test {
+    use ansi::describe;
+
+    #[test]
+    fn sequences() {
+        let one = |bytes: &[u8]| {
+            let lines = describe(bytes);
+            assert_eq!(lines.len(), 1, "{:?}", lines);
+            lines.into_iter().next().unwrap()
+        };
+
+        assert_eq!(
+            one(b"\x1b[38;5;208m"),
+            "CSI 38;5;208 m  (set fg indexed 208)"
+        );
+        assert_eq!(
+            one(b"\x1b[0;1;48;2;1;2;3;91m"),
+            "CSI 0;1;48;2;1;2;3;91 m  (reset attributes, set bold, set bg rgb 1,2,3, set fg bright red)"
+        );
+        assert_eq!(one(b"\x1b[m"), "CSI m  (reset attributes)");
+        assert_eq!(one(b"\x1b[3;4H"), "CSI 3;4 H  (cursor to row 3, column 4)");
+        assert_eq!(one(b"\x1b[2J"), "CSI 2 J  (erase screen)");
+        assert_eq!(
+            one(b"\x1b[?25;1049l"),
+            "CSI ? 25;1049 l  (reset private mode 25 (cursor visible), 1049 (alternate screen, saving the cursor))"
+        );
+        assert_eq!(
+            one(b"\x1b[?2026$p"),
+            "CSI ? 2026 $ p  (request private mode 2026 (synchronized output))"
+        );
+        assert_eq!(one(b"\x1b[5 q"), "CSI 5 SP q  (cursor style 5)");
+        assert_eq!(one(b"\x1b[1x"), "CSI 1 x");
+        assert_eq!(one(b"\x1b(B"), "ESC ( B  (designate G0 as US ASCII)");
+        assert_eq!(one(b"\x1bOA"), "SS3 A  (up)");
+        assert_eq!(one(b"\x1b]2;hi\x07"), "OSC 2;hi BEL  (set window title)");
+        assert_eq!(
+            one(b"\x1b]8;;http://x\x1b\\"),
+            "OSC 8;;http://x ST  (hyperlink)"
+        );
+        assert_eq!(
+            one(b"\x1bPq#0!3~\x1b\\"),
+            "DCS q ... ST  (sixel image, 5 bytes)"
+        );
+        assert_eq!(one(b"\x01"), "C0 0x01");
+
+        assert_eq!(
+            describe(b"a\x1b[1mb\x07"),
+            vec![
+                "text \"a\"",
+                "CSI 1 m  (set bold)",
+                "text \"b\"",
+                "BEL  (bell)"
+            ]
+        );
+    }
+}
@@ -0,0 +1,59 @@
+use errors::*;
+
+/// The layout of an `Image`'s pixels.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum PixelFormat {
+    /// 3 bytes per pixel: red, green, blue.
+    Rgb,
+    /// 4 bytes per pixel: red, green, blue, alpha.
+    Rgba,
+}
+
+impl PixelFormat {
+    pub fn bytes_per_pixel(self) -> usize {
+        match self {
+            PixelFormat::Rgb => 3,
+            PixelFormat::Rgba => 4,
+        }
+    }
+}
+
+/// Raw pixel data, stored row by row from the top left.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Image {
+    width: u32,
+    height: u32,
+    format: PixelFormat,
+    data: Vec<u8>,
+}
+
+impl Image {
+    /// Create an image from `data`, which must hold exactly `width * height` pixels in `format`.
+    pub fn new(width: u32, height: u32, format: PixelFormat, data: Vec<u8>) -> Result<Image> {
+        if data.len() != width as usize * height as usize * format.bytes_per_pixel() {
+            return Err(Error::InvalidInput("the image's data doesn't match its size"));
+        }
+        Ok(Image {
+            width,
+            height,
+            format,
+            data,
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn format(&self) -> PixelFormat {
+        self.format
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
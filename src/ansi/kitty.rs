@@ -0,0 +1,328 @@
+//! The kitty graphics protocol.
+//!
+//! Images are sent as APC sequences (`ESC _ G <keys> ; <payload> ESC \`), where the keys are comma separated
+//! `key=value` pairs and the payload is the base64 encoded pixel data. Payloads are split into chunks of at most
+//! `CHUNK_SIZE` bytes; every chunk but the last carries `m=1`.
+//!
+//! [details](https://sw.kovidgoyal.net/kitty/graphics-protocol/)
+use ansi::{Image, PixelFormat};
+use errors::*;
+use std::fmt::{self, Write as FmtWrite};
+use std::io::Write;
+use util;
+
+/// The largest base64 payload the terminal accepts in a single escape sequence.
+pub const CHUNK_SIZE: usize = 4096;
+
+/// Where and how big to draw an image, relative to the cursor.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct Placement {
+    /// Horizontal offset into the cursor's cell, in pixels (`X`).
+    pub x_offset: u32,
+    /// Vertical offset into the cursor's cell, in pixels (`Y`).
+    pub y_offset: u32,
+    /// Scale the image to span this many columns (`c`).
+    pub columns: Option<u32>,
+    /// Scale the image to span this many rows (`r`).
+    pub rows: Option<u32>,
+    /// The stacking order, images with a negative z-index are drawn below text (`z`).
+    pub z_index: i32,
+    /// Leave the cursor where it is, instead of moving it past the image (`C=1`).
+    pub keep_cursor: bool,
+}
+
+/// Writes kitty graphics commands to `W`.
+pub struct ImageWriter<W: Write> {
+    inner: W,
+    quiet: bool,
+}
+
+impl Placement {
+    fn keys(&self, header: &mut String) {
+        if self.x_offset > 0 {
+            key(header, 'X', self.x_offset);
+        }
+        if self.y_offset > 0 {
+            key(header, 'Y', self.y_offset);
+        }
+        if let Some(c) = self.columns {
+            key(header, 'c', c);
+        }
+        if let Some(r) = self.rows {
+            key(header, 'r', r);
+        }
+        if self.z_index != 0 {
+            key(header, 'z', self.z_index);
+        }
+        if self.keep_cursor {
+            key(header, 'C', 1);
+        }
+    }
+}
+
+/// Append `k=v` to a command's keys.
+fn key<T: fmt::Display>(header: &mut String, k: char, v: T) {
+    if !header.is_empty() {
+        header.push(',');
+    }
+    let _ = write!(header, "{}={}", k, v);
+}
+
+/// The keys describing `image`'s pixel data.
+fn image_keys(header: &mut String, image: &Image) {
+    key(
+        header,
+        'f',
+        match image.format() {
+            PixelFormat::Rgb => 24,
+            PixelFormat::Rgba => 32,
+        },
+    );
+    key(header, 's', image.width());
+    key(header, 'v', image.height());
+}
+
+impl<W: Write> ImageWriter<W> {
+    pub fn new(inner: W) -> ImageWriter<W> {
+        ImageWriter { inner, quiet: true }
+    }
+
+    /// Ask the terminal not to reply to commands (`q=2`).
+    ///
+    /// This is on by default, since replies show up in the input stream.
+    pub fn quiet(mut self, v: bool) -> Self {
+        self.quiet = v;
+        self
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    /// Send `image` to the terminal without displaying it, so it can be placed later with `place`.
+    pub fn transmit(&mut self, image: &Image, id: u32) -> Result<()> {
+        let mut header = String::new();
+        key(&mut header, 'a', 't');
+        image_keys(&mut header, image);
+        key(&mut header, 'i', id);
+        self.command(header, image.data())
+    }
+
+    /// Send `image` to the terminal and display it at the cursor.
+    pub fn display(&mut self, image: &Image, id: Option<u32>, placement: &Placement) -> Result<()> {
+        let mut header = String::new();
+        key(&mut header, 'a', 'T');
+        image_keys(&mut header, image);
+        if let Some(id) = id {
+            key(&mut header, 'i', id);
+        }
+        placement.keys(&mut header);
+        self.command(header, image.data())
+    }
+
+    /// Display an image that was already sent with `transmit` at the cursor.
+    pub fn place(&mut self, id: u32, placement: &Placement) -> Result<()> {
+        let mut header = String::new();
+        key(&mut header, 'a', 'p');
+        key(&mut header, 'i', id);
+        placement.keys(&mut header);
+        self.command(header, &[])
+    }
+
+    /// Delete every placement of the image `id`, and free its data.
+    pub fn delete(&mut self, id: u32) -> Result<()> {
+        let mut header = String::new();
+        key(&mut header, 'a', 'd');
+        key(&mut header, 'd', 'I');
+        key(&mut header, 'i', id);
+        self.command(header, &[])
+    }
+
+    /// Write a command with the keys in `header`, splitting `data` into as many chunks as it needs.
+    fn command(&mut self, mut header: String, data: &[u8]) -> Result<()> {
+        if self.quiet {
+            key(&mut header, 'q', 2);
+        }
+
+        let payload = util::base64_encode(data);
+        if payload.len() <= CHUNK_SIZE {
+            return self.chunk(&header, &payload);
+        }
+
+        let mut chunks = payload.chunks(CHUNK_SIZE).peekable();
+        let mut first = true;
+        while let Some(chunk) = chunks.next() {
+            // after the first chunk, only `m` (and `q`) may be given
+            let mut keys = if first { header.clone() } else { String::new() };
+            if !first && self.quiet {
+                key(&mut keys, 'q', 2);
+            }
+            key(&mut keys, 'm', if chunks.peek().is_some() { 1 } else { 0 });
+            self.chunk(&keys, chunk)?;
+            first = false;
+        }
+        Ok(())
+    }
+
+    fn chunk(&mut self, keys: &str, payload: &[u8]) -> Result<()> {
+        self.inner
+            .write_all(b"\x1b_G")
+            .and_then(|_| self.inner.write_all(keys.as_bytes()))
+            .and_then(|_| {
+                if payload.is_empty() {
+                    Ok(())
+                } else {
+                    self.inner.write_all(b";")?;
+                    self.inner.write_all(payload)
+                }
+            })
+            .and_then(|_| self.inner.write_all(b"\x1b\\"))
+            .context(ErrorKind::ApcFailed)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ansi::kitty::*;
+
+    fn decode(s: &[u8]) -> Vec<u8> {
+        let value = |c: u8| match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => panic!("invalid base64 {:?}", c as char),
+        };
+        let mut out = Vec::new();
+        for group in s.chunks(4) {
+            let n = group
+                .iter()
+                .take_while(|&&c| c != b'=')
+                .enumerate()
+                .fold(0u32, |n, (i, &c)| n | u32::from(value(c)) << (18 - i * 6));
+            let len = group.iter().filter(|&&c| c != b'=').count() - 1;
+            out.extend_from_slice(&[(n >> 16) as u8, (n >> 8) as u8, n as u8][..len]);
+        }
+        out
+    }
+
+    /// Split the output into (keys, payload) pairs.
+    fn commands(out: &[u8]) -> Vec<(String, Vec<u8>)> {
+        let s = String::from_utf8(out.to_vec()).unwrap();
+        s.split("\x1b\\")
+            .filter(|c| !c.is_empty())
+            .map(|c| {
+                assert!(c.starts_with("\x1b_G"), "{:?}", c);
+                let mut parts = c[3..].splitn(2, ';');
+                let keys = parts.next().unwrap().to_owned();
+                (keys, parts.next().unwrap_or("").as_bytes().to_vec())
+            })
+            .collect()
+    }
+
+    fn image(width: u32, height: u32, format: PixelFormat) -> Image {
+        let len = width as usize * height as usize * format.bytes_per_pixel();
+        Image::new(
+            width,
+            height,
+            format,
+            (0..len).map(|i| (i * 7) as u8).collect(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn header() {
+        let mut w = ImageWriter::new(Vec::new());
+        w.display(&image(2, 1, PixelFormat::Rgb), None, &Placement::default())
+            .unwrap();
+        assert_eq!(
+            w.get_ref().as_slice(),
+            &b"\x1b_Ga=T,f=24,s=2,v=1,q=2;AAcOFRwj\x1b\\"[..]
+        );
+
+        let placement = Placement {
+            x_offset: 3,
+            y_offset: 4,
+            columns: Some(10),
+            rows: Some(5),
+            z_index: -1,
+            keep_cursor: true,
+        };
+        let mut w = ImageWriter::new(Vec::new()).quiet(false);
+        w.display(&image(1, 1, PixelFormat::Rgba), Some(7), &placement)
+            .unwrap();
+        let c = commands(w.get_ref());
+        assert_eq!(c[0].0, "a=T,f=32,s=1,v=1,i=7,X=3,Y=4,c=10,r=5,z=-1,C=1");
+
+        let mut w = ImageWriter::new(Vec::new());
+        w.transmit(&image(1, 1, PixelFormat::Rgb), 42).unwrap();
+        w.place(42, &Placement::default()).unwrap();
+        w.delete(42).unwrap();
+        let c = commands(w.get_ref());
+        assert_eq!(c[0].0, "a=t,f=24,s=1,v=1,i=42,q=2");
+        assert_eq!(c[1], ("a=p,i=42,q=2".to_owned(), vec![]));
+        assert_eq!(c[2], ("a=d,d=I,i=42,q=2".to_owned(), vec![]));
+    }
+
+    #[test]
+    fn chunking() {
+        // 1024 RGB pixels encode to exactly one full chunk
+        let mut w = ImageWriter::new(Vec::new());
+        w.display(
+            &image(32, 32, PixelFormat::Rgb),
+            None,
+            &Placement::default(),
+        )
+        .unwrap();
+        let c = commands(w.get_ref());
+        assert_eq!(c.len(), 1);
+        assert_eq!(c[0].1.len(), CHUNK_SIZE);
+        assert!(!c[0].0.contains("m="));
+
+        // one more pixel, and it spills into a second chunk
+        let img = image(1025, 1, PixelFormat::Rgb);
+        let mut w = ImageWriter::new(Vec::new());
+        w.display(&img, Some(1), &Placement::default()).unwrap();
+        let c = commands(w.get_ref());
+        assert_eq!(c.len(), 2);
+        assert_eq!(c[0].0, "a=T,f=24,s=1025,v=1,i=1,q=2,m=1");
+        assert_eq!(c[0].1.len(), CHUNK_SIZE);
+        assert_eq!(c[1].0, "q=2,m=0");
+        assert_eq!(c[1].1.len(), 4);
+
+        let img = image(100, 100, PixelFormat::Rgba);
+        let mut w = ImageWriter::new(Vec::new()).quiet(false);
+        w.transmit(&img, 1).unwrap();
+        let c = commands(w.get_ref());
+        assert_eq!(c.len(), 40000usize.div_ceil(3) * 4 / CHUNK_SIZE + 1);
+        for (i, &(ref keys, ref payload)) in c.iter().enumerate().skip(1) {
+            assert_eq!(keys, if i == c.len() - 1 { "m=0" } else { "m=1" });
+            assert!(payload.len() <= CHUNK_SIZE && payload.len() % 4 == 0);
+        }
+
+        let payload: Vec<u8> = c.into_iter().flat_map(|(_, p)| p).collect();
+        assert_eq!(decode(&payload), img.data());
+    }
+
+    #[test]
+    fn round_trip() {
+        for len in 0..10 {
+            let data: Vec<u8> = (0..len).map(|i| (i * 37) as u8).collect();
+            assert_eq!(decode(&util::base64_encode(&data)), data);
+        }
+        assert_eq!(util::base64_encode(b"kitty"), b"a2l0dHk=");
+
+        assert!(Image::new(2, 2, PixelFormat::Rgb, vec![0; 11]).is_err());
+    }
+}
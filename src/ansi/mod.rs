@@ -0,0 +1,262 @@
+use errors::*;
+use std::io::Write;
+use util;
+
+mod color;
+pub(crate) mod describe;
+mod image;
+pub mod kitty;
+pub mod parser;
+mod passthrough;
+mod prompt;
+mod sanitize;
+mod sequence;
+pub mod screen;
+pub mod sixel;
+mod strip;
+mod style;
+mod width;
+mod wrap;
+#[cfg(feature = "x11-colors")]
+mod x11;
+
+pub use self::color::*;
+pub use self::describe::describe;
+pub use self::image::*;
+pub use self::parser::{Parser, Perform};
+pub use self::passthrough::{passthrough, Mux, SCREEN_CHUNK_SIZE, TMUX_CHUNK_SIZE};
+pub use self::prompt::{PromptWriter, Shell};
+pub use self::sanitize::{needs_sanitizing, sanitize, SanitizePolicy};
+pub use self::screen::TestScreen;
+pub use self::sequence::{csi, csi_private, csi_sub, dcs, osc, OscTerminator, MAX_PARAMS};
+pub use self::strip::*;
+pub use self::style::*;
+pub use self::width::*;
+pub use self::wrap::{wrap, WrapOptions};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Color {
+    Index(u8),
+    Rgb(u8, u8, u8),
+}
+
+/// A 24-bit color.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub struct Rgb {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+pub const ALL_OFF: &[u8] = b"\x1b[0m";
+
+pub const IMPACT_BOLD: &[u8] = b"\x1b[1m";
+pub const IMPACT_FAINT: &[u8] = b"\x1b[2m";
+pub const IMPACT_OFF: &[u8] = b"\x1b[22m";
+
+pub const BLINK: &[u8] = b"\x1b[5m";
+pub const BLINK_OFF: &[u8] = b"\x1b[25m";
+
+pub const ITALICS: &[u8] = b"\x1b[3m";
+pub const ITALICS_OFF: &[u8] = b"\x1b[23m";
+
+pub const UNDERLINE: &[u8] = b"\x1b[4m";
+pub const UNDERLINE_OFF: &[u8] = b"\x1b[24m";
+
+pub const RESET_BACKGROUND: &[u8] = b"\x1b[49m";
+pub const RESET_FOREGROUND: &[u8] = b"\x1b[39m";
+
+pub const SET_TAB_STOP: &[u8] = b"\x1bH";
+pub const CLEAR_TAB_STOP: &[u8] = b"\x1b[0g";
+pub const CLEAR_ALL_TAB_STOPS: &[u8] = b"\x1b[3g";
+
+/// Move the cursor home, then erase the whole screen.
+pub const CLEAR_SCREEN: &[u8] = b"\x1b[H\x1b[2J";
+
+/// Ask for the terminal's primary device attributes (DA1).
+pub const REQUEST_PRIMARY_DA: &[u8] = b"\x1b[c";
+/// Ask for the terminal's secondary device attributes (DA2).
+pub const REQUEST_SECONDARY_DA: &[u8] = b"\x1b[>c";
+/// Ask where the cursor is (DSR 6), the terminal replies with a cursor position report.
+pub const REQUEST_CURSOR_POSITION: &[u8] = b"\x1b[6n";
+/// Ask for the terminal's name and version (XTVERSION).
+pub const REQUEST_XTVERSION: &[u8] = b"\x1b[>0q";
+
+/// DEC private mode for showing the whole screen in reverse video (DECSCNM).
+pub const MODE_REVERSE_VIDEO: u32 = 5;
+/// DEC private mode for the alternate screen, which also saves and restores the cursor.
+pub const MODE_ALT_SCREEN: u32 = 1049;
+/// DEC private mode for bracketed paste.
+pub const MODE_BRACKETED_PASTE: u32 = 2004;
+/// DEC private mode for synchronized output.
+pub const MODE_SYNCHRONIZED_OUTPUT: u32 = 2026;
+
+#[repr(u8)]
+pub enum GraphicRendition {
+    Reset = 0,
+    Bold = 1,
+    Faint = 2,
+    Italic = 3,
+    Underline = 4,
+    Blink = 5,
+    FastBlink = 6,
+    Invert = 7,
+    Conceal = 8,
+    Strike = 9,
+    Fraktur = 20,
+
+    ResetImpact = 22,
+    ResetStyle = 23,
+    ResetUnderline = 24,
+    ResetBlink = 25,
+    ResetInvert = 27,
+    ResetConceal = 28,
+    ResetStrike = 29,
+
+    ResetFrame = 54,
+    ResetOverline = 55,
+    ResetIdeogram = 65,
+
+    ResetFont = 10,
+    ResetBackground = 49,
+    ResetForeground = 39,
+}
+
+impl From<u8> for Color {
+    fn from(v: u8) -> Color {
+        Color::Index(v)
+    }
+}
+
+impl From<(u8, u8, u8)> for Color {
+    fn from(v: (u8, u8, u8)) -> Color {
+        Color::Rgb(v.0, v.1, v.2)
+    }
+}
+
+impl From<Rgb> for Color {
+    fn from(v: Rgb) -> Color {
+        Color::Rgb(v.r, v.g, v.b)
+    }
+}
+
+impl From<(u8, u8, u8)> for Rgb {
+    fn from(v: (u8, u8, u8)) -> Rgb {
+        Rgb::new(v.0, v.1, v.2)
+    }
+}
+
+impl Rgb {
+    pub fn new(r: u8, g: u8, b: u8) -> Rgb {
+        Rgb { r, g, b }
+    }
+}
+
+pub fn set_foreground<W: Write>(w: &mut W, c: Color) -> Result<()> {
+    match c {
+        Color::Index(x @ 0...7) => write!(w, "\x1b[{}m", x + 30),
+        Color::Index(x @ 8...15) => write!(w, "\x1b[{}m", x + 82),
+        Color::Index(x) => write!(w, "\x1b[38;5;{}m", x),
+        Color::Rgb(r, g, b) => write!(w, "\x1b[38;2;{};{};{}m", r, g, b),
+    }.context(ErrorKind::CsiFailed)
+}
+
+pub fn set_background<W: Write>(w: &mut W, c: Color) -> Result<()> {
+    match c {
+        Color::Index(x @ 0...7) => write!(w, "\x1b[{}m", x + 40),
+        Color::Index(x @ 8...15) => write!(w, "\x1b[{}m", x + 92),
+        Color::Index(x) => write!(w, "\x1b[48;5;{}m", x),
+        Color::Rgb(r, g, b) => write!(w, "\x1b[98;2;{};{};{}m", r, g, b),
+    }.context(ErrorKind::CsiFailed)
+}
+
+pub fn cursor_shift_vertical<W: Write>(w: &mut W, shift: isize) -> Result<()> {
+    if shift < 0 {
+        write_csi(w, &[sequence::param(shift.unsigned_abs())?], b'B')
+    } else if shift > 0 {
+        write_csi(w, &[sequence::param(shift as usize)?], b'A')
+    } else {
+        Ok(())
+    }
+}
+
+pub fn cursor_shift_horizontal<W: Write>(w: &mut W, shift: isize) -> Result<()> {
+    if shift < 0 {
+        write_csi(w, &[sequence::param(shift.unsigned_abs())?], b'D')
+    } else if shift > 0 {
+        write_csi(w, &[sequence::param(shift as usize)?], b'C')
+    } else {
+        Ok(())
+    }
+}
+
+/// Turn a DEC private mode on (DECSET) or off (DECRST).
+pub fn set_private_mode<W: Write>(w: &mut W, mode: u32, on: bool) -> Result<()> {
+    let mode = sequence::param(mode as usize)?;
+    let seq = csi_private(b'?', &[mode], b"", if on { b'h' } else { b'l' })?;
+    w.write_all(&seq).context(ErrorKind::CsiFailed)
+}
+
+/// Ask the terminal to report the state of a DEC private mode (DECRQM).
+pub fn request_private_mode<W: Write>(w: &mut W, mode: u32) -> Result<()> {
+    let seq = csi_private(b'?', &[sequence::param(mode as usize)?], b"$", b'p')?;
+    w.write_all(&seq).context(ErrorKind::CsiFailed)
+}
+
+/// Ask the terminal for the value of one of its termcap or terminfo capabilities (XTGETTCAP), like `Co` or `RGB`.
+///
+/// The name is sent in hex, as `DCS + q <name> ST`.
+pub fn request_termcap<W: Write>(w: &mut W, name: &str) -> Result<()> {
+    let hex: String = name.bytes().map(|b| format!("{:02X}", b)).collect();
+    w.write_all(&dcs(&[], b"+", b'q', hex.as_bytes())?)
+        .context(ErrorKind::DcsFailed)
+}
+
+/// Move the cursor forward `n` tab stops (CHT).
+pub fn cursor_forward_tabs<W: Write>(w: &mut W, n: usize) -> Result<()> {
+    write_csi(w, &[sequence::param(n)?], b'I')
+}
+
+/// Move the cursor back `n` tab stops (CBT).
+pub fn cursor_backward_tabs<W: Write>(w: &mut W, n: usize) -> Result<()> {
+    write_csi(w, &[sequence::param(n)?], b'Z')
+}
+
+/// Move the cursor to column `x` and row `y` (CUP), both counted from 0.
+pub fn cursor_move<W: Write>(w: &mut W, x: usize, y: usize) -> Result<()> {
+    write_csi(w, &[one_based(y)?, one_based(x)?], b'H')
+}
+
+/// Move the cursor to row `y` without changing its column (VPA), counted from 0.
+pub fn cursor_row<W: Write>(w: &mut W, y: usize) -> Result<()> {
+    write_csi(w, &[one_based(y)?], b'd')
+}
+
+/// Move the cursor to column `x` without changing its row (CHA), counted from 0.
+pub fn cursor_set_column<W: Write>(w: &mut W, x: usize) -> Result<()> {
+    write_csi(w, &[one_based(x)?], b'G')
+}
+
+/// Only scroll rows `top` to `bottom` (DECSTBM), both counted from 0 and both included. This moves the cursor to
+/// the top left corner.
+pub fn set_scroll_region<W: Write>(w: &mut W, top: usize, bottom: usize) -> Result<()> {
+    write_csi(w, &[one_based(top)?, one_based(bottom)?], b'r')
+}
+
+/// Write the control sequence `CSI <params> <final_byte>`.
+fn write_csi<W: Write>(w: &mut W, params: &[u16], final_byte: u8) -> Result<()> {
+    w.write_all(&csi(params, b"", final_byte)?)
+        .context(ErrorKind::CsiFailed)
+}
+
+/// A position counted from 0, as the parameter counted from 1 that sequences take.
+fn one_based(n: usize) -> Result<u16> {
+    sequence::param(n.saturating_add(1))
+}
+
+#[inline]
+pub fn sgr<W: Write>(w: &mut W, gr: GraphicRendition) -> Result<usize> {
+    Ok(w.write(b"\x1b[").context(ErrorKind::CsiFailed)?
+        + util::write_u8_ansi(w, gr as u8)?
+        + w.write(b"m").context(ErrorKind::CsiFailed)?)
+}
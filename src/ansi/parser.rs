@@ -0,0 +1,705 @@
+//! An incremental parser for terminal output.
+//!
+//! This is a state machine based on Paul Williams' [DEC compatible parser](https://vt100.net/emu/dec_ansi_parser),
+//! extended to decode UTF-8. Bytes are fed in one at a time (or in chunks of any size), and the parser calls back
+//! into a `Perform` implementation whenever it recognises a character, control function or escape sequence.
+//!
+//! Memory use is fixed: CSI and DCS sequences with too many parameters or intermediates are ignored, and OSC
+//! strings longer than `MAX_OSC_LEN` are cut short. DCS payloads are streamed through `Perform::put` rather than
+//! buffered.
+use std::str;
+
+/// The most parameters a CSI or DCS sequence can have before it's ignored.
+pub const MAX_PARAMS: usize = 32;
+/// The most intermediate (and private marker) bytes a sequence can have before it's ignored.
+pub const MAX_INTERMEDIATES: usize = 2;
+/// The longest OSC string the parser will keep, anything after this is dropped.
+pub const MAX_OSC_LEN: usize = 1024;
+/// The most `;` separated parameters an OSC string will be split into, the last one keeps any remaining separators.
+pub const MAX_OSC_PARAMS: usize = 16;
+
+const REPLACEMENT: char = '\u{fffd}';
+
+/// Actions performed by the parser.
+///
+/// Every method except `print` has a default no-op implementation.
+pub trait Perform {
+    /// Draw a character to the screen.
+    fn print(&mut self, c: char);
+
+    /// Run a C0 control function such as `\n` or `BEL`.
+    fn execute(&mut self, _byte: u8) {}
+
+    /// A CSI sequence (`ESC [ <params> <intermediates> <action>`) has been read.
+    ///
+    /// Private markers like the `?` in `CSI ? 25 h` are included in `intermediates`. Empty parameters are 0,
+    /// and `:` separated subparameters are flattened into `params`.
+    fn csi_dispatch(&mut self, _params: &[i64], _intermediates: &[u8], _action: u8) {}
+
+    /// An escape sequence (`ESC <intermediates> <byte>`) has been read.
+    fn esc_dispatch(&mut self, _intermediates: &[u8], _byte: u8) {}
+
+    /// An OSC string has been read, split into its `;` separated parameters.
+    ///
+    /// `bell_terminated` is true if the string ended in `BEL` rather than `ST`, replies should use the same terminator.
+    fn osc_dispatch(&mut self, _params: &[&[u8]], _bell_terminated: bool) {}
+
+    /// A DCS sequence has started, its payload will be passed to `put` until `unhook` is called.
+    fn hook(&mut self, _params: &[i64], _intermediates: &[u8], _action: u8) {}
+
+    /// A byte of a DCS payload.
+    fn put(&mut self, _byte: u8) {}
+
+    /// The DCS sequence started by `hook` has ended.
+    fn unhook(&mut self) {}
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum State {
+    Ground,
+    Escape,
+    EscapeIntermediate,
+    CsiEntry,
+    CsiParam,
+    CsiIntermediate,
+    CsiIgnore,
+    DcsEntry,
+    DcsParam,
+    DcsIntermediate,
+    DcsPassthrough,
+    DcsIgnore,
+    OscString,
+    SosPmApcString,
+}
+
+/// The VT parser's state.
+pub struct Parser {
+    state: State,
+
+    params: [i64; MAX_PARAMS],
+    num_params: usize,
+    /// True if a parameter has been started but not yet pushed to `params`.
+    param_pending: bool,
+    param: i64,
+
+    intermediates: [u8; MAX_INTERMEDIATES],
+    num_intermediates: usize,
+    /// Set when an escape sequence has overflowed its intermediates and shouldn't be dispatched.
+    ignoring: bool,
+
+    osc: Vec<u8>,
+
+    utf8: [u8; 4],
+    utf8_len: usize,
+    utf8_needed: usize,
+}
+
+impl Default for Parser {
+    fn default() -> Parser {
+        Parser::new()
+    }
+}
+
+impl Parser {
+    pub fn new() -> Parser {
+        Parser {
+            state: State::Ground,
+            params: [0; MAX_PARAMS],
+            num_params: 0,
+            param_pending: false,
+            param: 0,
+            intermediates: [0; MAX_INTERMEDIATES],
+            num_intermediates: 0,
+            ignoring: false,
+            osc: Vec::new(),
+            utf8: [0; 4],
+            utf8_len: 0,
+            utf8_needed: 0,
+        }
+    }
+
+    /// True if the parser is in its ground state, i.e. it isn't part of the way through a sequence or character.
+    pub fn is_ground(&self) -> bool {
+        self.state == State::Ground && self.utf8_needed == 0
+    }
+
+    /// Feed every byte in `bytes` to the parser.
+    pub fn feed<P: Perform>(&mut self, performer: &mut P, bytes: &[u8]) {
+        for &b in bytes {
+            self.advance(performer, b);
+        }
+    }
+
+    /// Feed a single byte to the parser.
+    pub fn advance<P: Perform>(&mut self, performer: &mut P, byte: u8) {
+        if self.state == State::Ground && (self.utf8_needed > 0 || byte >= 0x80) {
+            self.advance_utf8(performer, byte);
+            return;
+        }
+
+        // these have the same effect in every state
+        match byte {
+            0x18 | 0x1a => {
+                self.exit_state(performer, false);
+                performer.execute(byte);
+                self.state = State::Ground;
+                return;
+            }
+            0x1b => {
+                self.exit_state(performer, true);
+                self.clear();
+                self.state = State::Escape;
+                return;
+            }
+            _ => (),
+        }
+
+        match self.state {
+            State::Ground => match byte {
+                0x00..=0x1f => performer.execute(byte),
+                0x7f => (),
+                _ => performer.print(byte as char),
+            },
+            State::Escape => match byte {
+                0x00..=0x1f => performer.execute(byte),
+                0x20..=0x2f => {
+                    self.collect(byte);
+                    self.state = State::EscapeIntermediate;
+                }
+                b'P' => self.state = State::DcsEntry,
+                b'[' => self.state = State::CsiEntry,
+                b']' => {
+                    self.osc.clear();
+                    self.state = State::OscString;
+                }
+                b'X' | b'^' | b'_' => self.state = State::SosPmApcString,
+                0x7f => (),
+                _ => self.esc_dispatch(performer, byte),
+            },
+            State::EscapeIntermediate => match byte {
+                0x00..=0x1f => performer.execute(byte),
+                0x20..=0x2f => self.collect(byte),
+                0x7f => (),
+                _ => self.esc_dispatch(performer, byte),
+            },
+            State::CsiEntry | State::CsiParam | State::CsiIntermediate => match byte {
+                0x00..=0x1f => performer.execute(byte),
+                0x7f => (),
+                _ => self.csi(performer, byte),
+            },
+            State::CsiIgnore => match byte {
+                0x00..=0x1f => performer.execute(byte),
+                0x40..=0x7e => self.state = State::Ground,
+                _ => (),
+            },
+            State::DcsEntry | State::DcsParam | State::DcsIntermediate => match byte {
+                0x00..=0x1f | 0x7f => (),
+                _ => self.dcs(performer, byte),
+            },
+            State::DcsPassthrough => match byte {
+                0x7f => (),
+                _ => performer.put(byte),
+            },
+            State::DcsIgnore | State::SosPmApcString => (),
+            State::OscString => match byte {
+                0x07 => {
+                    self.osc_dispatch(performer, true);
+                    self.state = State::Ground;
+                }
+                0x00..=0x1f => (),
+                _ => {
+                    if self.osc.len() < MAX_OSC_LEN {
+                        self.osc.push(byte);
+                    }
+                }
+            },
+        }
+    }
+
+    fn advance_utf8<P: Perform>(&mut self, performer: &mut P, byte: u8) {
+        if self.utf8_needed == 0 {
+            self.utf8_needed = match byte {
+                0xc2..=0xdf => 2,
+                0xe0..=0xef => 3,
+                0xf0..=0xf4 => 4,
+                _ => {
+                    performer.print(REPLACEMENT);
+                    return;
+                }
+            };
+            self.utf8[0] = byte;
+            self.utf8_len = 1;
+            return;
+        }
+
+        if byte & 0xc0 != 0x80 {
+            // the character was cut short, handle this byte on its own
+            self.utf8_needed = 0;
+            performer.print(REPLACEMENT);
+            self.advance(performer, byte);
+            return;
+        }
+
+        self.utf8[self.utf8_len] = byte;
+        self.utf8_len += 1;
+        if self.utf8_len == self.utf8_needed {
+            self.utf8_needed = 0;
+            match str::from_utf8(&self.utf8[..self.utf8_len]) {
+                Ok(s) => s.chars().for_each(|c| performer.print(c)),
+                Err(_) => performer.print(REPLACEMENT),
+            }
+        }
+    }
+
+    /// Run the exit action for the current state, before an `ESC`, `CAN` or `SUB` moves the parser somewhere else.
+    fn exit_state<P: Perform>(&mut self, performer: &mut P, terminated: bool) {
+        match self.state {
+            State::DcsPassthrough => performer.unhook(),
+            State::OscString if terminated => self.osc_dispatch(performer, false),
+            _ => (),
+        }
+    }
+
+    fn clear(&mut self) {
+        self.num_params = 0;
+        self.param_pending = false;
+        self.param = 0;
+        self.num_intermediates = 0;
+        self.ignoring = false;
+    }
+
+    fn collect(&mut self, byte: u8) {
+        if self.num_intermediates == MAX_INTERMEDIATES {
+            self.ignoring = true;
+        } else {
+            self.intermediates[self.num_intermediates] = byte;
+            self.num_intermediates += 1;
+        }
+    }
+
+    /// Handle a parameter byte (`0-9`, `:` or `;`), returns false if there are too many parameters.
+    fn param(&mut self, byte: u8) -> bool {
+        if byte == b';' || byte == b':' {
+            if !self.push_param() {
+                return false;
+            }
+            self.param_pending = true;
+        } else {
+            self.param_pending = true;
+            self.param = self
+                .param
+                .saturating_mul(10)
+                .saturating_add(i64::from(byte - b'0'));
+        }
+        true
+    }
+
+    fn push_param(&mut self) -> bool {
+        if self.num_params == MAX_PARAMS {
+            return false;
+        }
+        self.params[self.num_params] = self.param;
+        self.num_params += 1;
+        self.param = 0;
+        self.param_pending = false;
+        true
+    }
+
+    /// Push the last parameter, if there is one. Returns false if there are too many parameters.
+    fn finish_params(&mut self) -> bool {
+        !self.param_pending || self.push_param()
+    }
+
+    fn esc_dispatch<P: Perform>(&mut self, performer: &mut P, byte: u8) {
+        if !self.ignoring {
+            performer.esc_dispatch(&self.intermediates[..self.num_intermediates], byte);
+        }
+        self.state = State::Ground;
+    }
+
+    fn csi<P: Perform>(&mut self, performer: &mut P, byte: u8) {
+        let intermediate = self.state == State::CsiIntermediate;
+        self.state = match byte {
+            0x30..=0x3b if !intermediate => {
+                if self.param(byte) {
+                    State::CsiParam
+                } else {
+                    State::CsiIgnore
+                }
+            }
+            // private markers are only allowed at the start
+            0x3c..=0x3f if self.state == State::CsiEntry => {
+                self.collect(byte);
+                State::CsiParam
+            }
+            0x30..=0x3f => State::CsiIgnore,
+            0x20..=0x2f => {
+                self.collect(byte);
+                State::CsiIntermediate
+            }
+            _ => {
+                if self.finish_params() && !self.ignoring {
+                    performer.csi_dispatch(
+                        &self.params[..self.num_params],
+                        &self.intermediates[..self.num_intermediates],
+                        byte,
+                    );
+                }
+                State::Ground
+            }
+        };
+
+        if self.ignoring && self.state != State::Ground {
+            self.state = State::CsiIgnore;
+        }
+    }
+
+    fn dcs<P: Perform>(&mut self, performer: &mut P, byte: u8) {
+        let intermediate = self.state == State::DcsIntermediate;
+        self.state = match byte {
+            0x30..=0x3b if !intermediate => {
+                if self.param(byte) {
+                    State::DcsParam
+                } else {
+                    State::DcsIgnore
+                }
+            }
+            0x3c..=0x3f if self.state == State::DcsEntry => {
+                self.collect(byte);
+                State::DcsParam
+            }
+            0x30..=0x3f => State::DcsIgnore,
+            0x20..=0x2f => {
+                self.collect(byte);
+                State::DcsIntermediate
+            }
+            _ => {
+                if self.finish_params() && !self.ignoring {
+                    performer.hook(
+                        &self.params[..self.num_params],
+                        &self.intermediates[..self.num_intermediates],
+                        byte,
+                    );
+                    State::DcsPassthrough
+                } else {
+                    State::DcsIgnore
+                }
+            }
+        };
+
+        if self.ignoring && self.state != State::DcsPassthrough {
+            self.state = State::DcsIgnore;
+        }
+    }
+
+    fn osc_dispatch<P: Perform>(&mut self, performer: &mut P, bell_terminated: bool) {
+        let mut params: [&[u8]; MAX_OSC_PARAMS] = [&[]; MAX_OSC_PARAMS];
+        let mut n = 0;
+        let mut rest = &self.osc[..];
+
+        while n < MAX_OSC_PARAMS - 1 {
+            match rest.iter().position(|&b| b == b';') {
+                Some(i) => {
+                    params[n] = &rest[..i];
+                    rest = &rest[i + 1..];
+                    n += 1;
+                }
+                None => break,
+            }
+        }
+        params[n] = rest;
+
+        performer.osc_dispatch(&params[..=n], bell_terminated);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ansi::parser::*;
+
+    const VIM: &'static [u8] = include_bytes!("../../test-data/vim-startup.out");
+    const TOP: &'static [u8] = include_bytes!("../../test-data/top.out");
+
+    /// Records every call as a string, merging runs of printed characters.
+    #[derive(Default)]
+    struct Recorder {
+        calls: Vec<String>,
+        text: String,
+    }
+
+    impl Recorder {
+        fn call(&mut self, s: String) {
+            if !self.text.is_empty() {
+                let text = format!("print {:?}", self.text);
+                self.calls.push(text);
+                self.text.clear();
+            }
+            self.calls.push(s);
+        }
+
+        fn finish(mut self) -> Vec<String> {
+            self.call(String::new());
+            self.calls.pop();
+            self.calls
+        }
+    }
+
+    impl Perform for Recorder {
+        fn print(&mut self, c: char) {
+            self.text.push(c);
+        }
+
+        fn execute(&mut self, byte: u8) {
+            self.call(format!("execute {:#04x}", byte));
+        }
+
+        fn csi_dispatch(&mut self, params: &[i64], intermediates: &[u8], action: u8) {
+            self.call(format!(
+                "csi {:?} {:?} {}",
+                params,
+                String::from_utf8_lossy(intermediates),
+                action as char
+            ));
+        }
+
+        fn esc_dispatch(&mut self, intermediates: &[u8], byte: u8) {
+            self.call(format!(
+                "esc {:?} {}",
+                String::from_utf8_lossy(intermediates),
+                byte as char
+            ));
+        }
+
+        fn osc_dispatch(&mut self, params: &[&[u8]], bell_terminated: bool) {
+            let params: Vec<_> = params.iter().map(|p| String::from_utf8_lossy(p)).collect();
+            self.call(format!("osc {:?} {}", params, bell_terminated));
+        }
+
+        fn hook(&mut self, params: &[i64], intermediates: &[u8], action: u8) {
+            self.call(format!(
+                "hook {:?} {:?} {}",
+                params,
+                String::from_utf8_lossy(intermediates),
+                action as char
+            ));
+        }
+
+        fn put(&mut self, byte: u8) {
+            self.call(format!("put {:?}", byte as char));
+        }
+
+        fn unhook(&mut self) {
+            self.call(String::from("unhook"));
+        }
+    }
+
+    fn parse(bytes: &[u8]) -> Vec<String> {
+        let mut parser = Parser::new();
+        let mut recorder = Recorder::default();
+        parser.feed(&mut recorder, bytes);
+        recorder.finish()
+    }
+
+    /// Parse `bytes` one byte at a time, with a fresh `feed` call for each.
+    fn parse_bytewise(bytes: &[u8]) -> Vec<String> {
+        let mut parser = Parser::new();
+        let mut recorder = Recorder::default();
+        for b in bytes {
+            parser.feed(&mut recorder, &[*b]);
+        }
+        recorder.finish()
+    }
+
+    #[test]
+    fn csi() {
+        assert_eq!(
+            parse(b"\x1b[1;31mred\x1b[m"),
+            vec!["csi [1, 31] \"\" m", "print \"red\"", "csi [] \"\" m"]
+        );
+        assert_eq!(parse(b"\x1b[?25l"), vec!["csi [25] \"?\" l"]);
+        assert_eq!(parse(b"\x1b[;5H"), vec!["csi [0, 5] \"\" H"]);
+        assert_eq!(parse(b"\x1b[2 q"), vec!["csi [2] \" \" q"]);
+        assert_eq!(
+            parse(b"\x1b[38:2::1:2:3m"),
+            vec!["csi [38, 2, 0, 1, 2, 3] \"\" m"]
+        );
+        // control characters are executed in the middle of a sequence
+        assert_eq!(
+            parse(b"\x1b[1\n;2H"),
+            vec!["execute 0x0a", "csi [1, 2] \"\" H"]
+        );
+        // a private marker after the parameters is invalid
+        assert_eq!(parse(b"\x1b[1?hx"), vec!["print \"x\""]);
+    }
+
+    #[test]
+    fn limits() {
+        let mut many = b"\x1b[".to_vec();
+        for _ in 0..MAX_PARAMS + 1 {
+            many.extend_from_slice(b"1;");
+        }
+        many.extend_from_slice(b"mok");
+        assert_eq!(parse(&many), vec!["print \"ok\""]);
+
+        assert_eq!(parse(b"\x1b[1$$$pok"), vec!["print \"ok\""]);
+        assert_eq!(
+            parse(b"\x1b[99999999999999999999999m"),
+            vec![format!("csi [{}] \"\" m", i64::max_value())]
+        );
+
+        let mut long = b"\x1b]0;".to_vec();
+        long.extend(vec![b'x'; MAX_OSC_LEN * 2]);
+        long.push(0x07);
+        let calls = parse(&long);
+        assert_eq!(calls.len(), 1);
+        assert_eq!(
+            calls[0].len(),
+            format!("osc [\"0\", \"\"] true",).len() + MAX_OSC_LEN - 2
+        );
+    }
+
+    #[test]
+    fn esc() {
+        assert_eq!(parse(b"\x1b7\x1b8"), vec!["esc \"\" 7", "esc \"\" 8"]);
+        assert_eq!(
+            parse(b"\x1b(0q\x1b(B"),
+            vec!["esc \"(\" 0", "print \"q\"", "esc \"(\" B"]
+        );
+        assert_eq!(parse(b"\x1b[12\x18x"), vec!["execute 0x18", "print \"x\""]);
+    }
+
+    #[test]
+    fn osc() {
+        assert_eq!(
+            parse(b"\x1b]8;;http://example.com\x1b\\link\x1b]8;;\x07"),
+            vec![
+                "osc [\"8\", \"\", \"http://example.com\"] false",
+                "esc \"\" \\",
+                "print \"link\"",
+                "osc [\"8\", \"\", \"\"] true",
+            ]
+        );
+        assert_eq!(
+            parse("\x1b]2;títle\x07".as_bytes()),
+            vec!["osc [\"2\", \"títle\"] true"]
+        );
+    }
+
+    #[test]
+    fn dcs() {
+        assert_eq!(
+            parse(b"\x1bP1$qm\x1b\\"),
+            vec!["hook [1] \"$\" q", "put 'm'", "unhook", "esc \"\" \\"]
+        );
+        // SOS, PM and APC strings are ignored
+        assert_eq!(
+            parse(b"\x1b_Gf=24\x1b\\x"),
+            vec!["esc \"\" \\", "print \"x\""]
+        );
+    }
+
+    #[test]
+    fn utf8() {
+        assert_eq!(
+            parse("héllo 日本 😀".as_bytes()),
+            vec!["print \"héllo 日本 😀\""]
+        );
+        // invalid and truncated sequences are replaced
+        assert_eq!(parse(b"a\xffb"), vec!["print \"a\u{fffd}b\""]);
+        assert_eq!(parse(b"a\xe6\x97b"), vec!["print \"a\u{fffd}b\""]);
+        assert_eq!(
+            parse(b"\xe6\x97\x1b[m"),
+            vec!["print \"\u{fffd}\"", "csi [] \"\" m"]
+        );
+
+        let mut parser = Parser::new();
+        let mut recorder = Recorder::default();
+        parser.feed(&mut recorder, b"\xe6\x97");
+        assert!(!parser.is_ground());
+        parser.feed(&mut recorder, b"\xa5");
+        assert!(parser.is_ground());
+        assert_eq!(recorder.finish(), vec!["print \"日\""]);
+    }
+
+    #[test]
+    fn vim() {
+        let calls = parse(VIM);
+        assert_eq!(calls, parse_bytewise(VIM));
+        assert_eq!(
+            &calls[..20],
+            &[
+                "csi [1049] \"?\" h",
+                "csi [22, 0, 0] \"\" t",
+                "csi [4, 2] \">\" m",
+                "csi [1] \"?\" h",
+                "esc \"\" =",
+                "csi [2004] \"?\" h",
+                "csi [1004] \"?\" h",
+                "csi [1, 10] \"\" r",
+                "csi [12] \"?\" h",
+                "csi [12] \"?\" l",
+                "csi [22, 2] \"\" t",
+                "csi [22, 1] \"\" t",
+                "csi [27] \"\" m",
+                "csi [23] \"\" m",
+                "csi [29] \"\" m",
+                "csi [] \"\" m",
+                "csi [] \"\" H",
+                "csi [2] \"\" J",
+                "csi [25] \"?\" l",
+                "csi [10, 1] \"\" H",
+            ][..]
+        );
+        assert_eq!(
+            &calls[27..36],
+            &[
+                "csi [1, 1] \"\" H",
+                "print \"hello world\"",
+                "execute 0x0d",
+                "execute 0x0a",
+                "print \"second line\"",
+                "execute 0x0d",
+                "execute 0x0a",
+                "csi [94] \"\" m",
+                "print \"~                                       \"",
+            ][..]
+        );
+        assert_eq!(
+            &calls[calls.len() - 3..],
+            &[
+                "csi [23, 0, 0] \"\" t",
+                "csi [25] \"?\" h",
+                "csi [4, 0] \">\" m"
+            ][..]
+        );
+    }
+
+    #[test]
+    fn top() {
+        let calls = parse(TOP);
+        assert_eq!(calls, parse_bytewise(TOP));
+        assert_eq!(
+            &calls[..10],
+            &[
+                "csi [1] \"?\" h",
+                "esc \"\" =",
+                "csi [25] \"?\" l",
+                "csi [] \"\" H",
+                "csi [2] \"\" J",
+                "esc \"(\" B",
+                "csi [] \"\" m",
+                "print \"top - 11:30:03 up 11 min,  0 user,  load average: 0.40, 0.36\"",
+                "esc \"(\" B",
+                "csi [] \"\" m",
+            ][..]
+        );
+
+        // the header row of the process table is drawn in reverse video
+        let header = calls
+            .iter()
+            .position(|c| c.starts_with("print \"  PID USER"))
+            .unwrap();
+        assert_eq!(calls[header - 1], "csi [7] \"\" m");
+        assert_eq!(calls.iter().filter(|c| *c == "csi [] \"\" K").count(), 9);
+    }
+}
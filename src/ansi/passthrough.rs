@@ -0,0 +1,183 @@
+//! Getting escape sequences through a terminal multiplexer to the terminal it's running in.
+//!
+//! tmux and GNU screen keep their own screen, and drop the sequences they don't understand themselves, like OSC 52
+//! (the clipboard) and kitty graphics. Both pass a device control string's payload on untouched though:
+//!
+//! - tmux's is `ESC P tmux; <payload> ESC \`, with every `ESC` in the payload doubled. tmux 3.3 and later only
+//!   pass it on with `set -g allow-passthrough on`.
+//! - screen's is `ESC P <payload> ESC \`, and screen ends it at an `ESC \` in the payload, so the payload is split
+//!   between the `ESC` and the `\`.
+//!
+//! Neither keeps an arbitrarily long string, so long payloads are split into several passthrough sequences, which
+//! the terminal gets back to back as if they were one.
+use std::env;
+
+/// The most payload bytes put in one tmux passthrough sequence, before the `ESC`s are doubled.
+pub const TMUX_CHUNK_SIZE: usize = 4096;
+
+/// The most payload bytes put in one screen passthrough sequence, well under the 768 bytes screen keeps of a string.
+pub const SCREEN_CHUNK_SIZE: usize = 512;
+
+/// A terminal multiplexer that sequences are passed through, see the module documentation.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Mux {
+    Tmux,
+    Screen,
+}
+
+impl Mux {
+    /// Find the multiplexer from `$TMUX` or `$STY`, which tmux and screen set for the programs running in them, or
+    /// failing that (over ssh, say) from `$TERM`.
+    ///
+    /// tmux is picked when both are set, as that's tmux running inside screen: screen running inside tmux can't be
+    /// told apart from the environment.
+    pub fn detect() -> Option<Mux> {
+        Mux::detect_with(|name| env::var(name).ok())
+    }
+
+    /// Like `detect`, but `var` looks up environment variables.
+    pub fn detect_with<F>(var: F) -> Option<Mux>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let set = |name| var(name).is_some_and(|v: String| !v.is_empty());
+        if set("TMUX") {
+            return Some(Mux::Tmux);
+        }
+        if set("STY") {
+            return Some(Mux::Screen);
+        }
+        // tmux uses `screen` entries too, but its own ones start with `tmux`
+        match var("TERM") {
+            Some(ref term) if term.starts_with("tmux") => Some(Mux::Tmux),
+            Some(ref term) if term.starts_with("screen") => Some(Mux::Screen),
+            _ => None,
+        }
+    }
+
+    /// The most payload bytes put in one passthrough sequence.
+    pub fn chunk_size(self) -> usize {
+        match self {
+            Mux::Tmux => TMUX_CHUNK_SIZE,
+            Mux::Screen => SCREEN_CHUNK_SIZE,
+        }
+    }
+}
+
+/// Wrap `payload`, one or more escape sequences for the outer terminal, so `mux` passes it on.
+pub fn passthrough(payload: &[u8], mux: Mux) -> Vec<u8> {
+    let mut out = Vec::with_capacity(payload.len() + 16);
+    let mut rest = payload;
+    while !rest.is_empty() {
+        let (chunk, tail) = rest.split_at(chunk_len(rest, mux));
+        match mux {
+            Mux::Tmux => {
+                out.extend_from_slice(b"\x1bPtmux;");
+                for &c in chunk {
+                    if c == 0x1b {
+                        out.push(0x1b);
+                    }
+                    out.push(c);
+                }
+            }
+            Mux::Screen => {
+                out.extend_from_slice(b"\x1bP");
+                out.extend_from_slice(chunk);
+            }
+        }
+        out.extend_from_slice(b"\x1b\\");
+        rest = tail;
+    }
+    out
+}
+
+/// How much of `payload` goes in the next passthrough sequence.
+fn chunk_len(payload: &[u8], mux: Mux) -> usize {
+    let len = payload.len().min(mux.chunk_size());
+    match mux {
+        Mux::Tmux => len,
+        Mux::Screen => payload[..len]
+            .windows(2)
+            .position(|w| w == b"\x1b\\")
+            .map_or(len, |i| i + 1),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ansi::passthrough::*;
+
+    #[test]
+    fn escaping() {
+        let osc = b"\x1b]52;c;aGk=\x1b\\";
+        assert_eq!(
+            passthrough(osc, Mux::Tmux),
+            &b"\x1bPtmux;\x1b\x1b]52;c;aGk=\x1b\x1b\\\x1b\\"[..]
+        );
+        assert_eq!(
+            passthrough(osc, Mux::Screen),
+            &b"\x1bP\x1b]52;c;aGk=\x1b\x1b\\\x1bP\\\x1b\\"[..]
+        );
+        assert_eq!(
+            passthrough(b"\x1b]9;done\x07", Mux::Screen),
+            &b"\x1bP\x1b]9;done\x07\x1b\\"[..]
+        );
+        assert!(passthrough(b"", Mux::Tmux).is_empty());
+    }
+
+    #[test]
+    fn chunks() {
+        // the `ESC` at the boundary is doubled in the first chunk, not split from its double
+        let mut payload = vec![b'x'; TMUX_CHUNK_SIZE - 1];
+        payload.extend_from_slice(b"\x1b[1m");
+        let out = passthrough(&payload, Mux::Tmux);
+        let mut expected = b"\x1bPtmux;".to_vec();
+        expected.extend_from_slice(&payload[..TMUX_CHUNK_SIZE - 1]);
+        expected.extend_from_slice(b"\x1b\x1b\x1b\\\x1bPtmux;[1m\x1b\\");
+        assert_eq!(out, expected);
+
+        let payload = vec![b'x'; SCREEN_CHUNK_SIZE * 2 + 1];
+        let out = passthrough(&payload, Mux::Screen);
+        let chunks: Vec<_> = out
+            .split(|&c| c == 0x1b)
+            .filter(|s| s.starts_with(b"P"))
+            .map(|s| s.len() - 1)
+            .collect();
+        assert_eq!(chunks, [SCREEN_CHUNK_SIZE, SCREEN_CHUNK_SIZE, 1]);
+
+        assert_eq!(
+            passthrough(b"\x1b\\\x1b\\", Mux::Screen),
+            &b"\x1bP\x1b\x1b\\\x1bP\\\x1b\x1b\\\x1bP\\\x1b\\"[..]
+        );
+    }
+
+    #[test]
+    fn detect() {
+        let detect = |vars: &[(&str, &str)]| {
+            Mux::detect_with(|name| {
+                vars.iter()
+                    .find(|&&(k, _)| k == name)
+                    .map(|&(_, v)| v.to_owned())
+            })
+        };
+        let tmux = ("TMUX", "/tmp/tmux-1000/default,1234,0");
+        let sty = ("STY", "1234.pts-0.host");
+
+        assert_eq!(detect(&[tmux, ("TERM", "screen")]), Some(Mux::Tmux));
+        assert_eq!(
+            detect(&[sty, ("TERM", "screen.xterm-256color")]),
+            Some(Mux::Screen)
+        );
+        // tmux inside screen
+        assert_eq!(
+            detect(&[sty, tmux, ("TERM", "tmux-256color")]),
+            Some(Mux::Tmux)
+        );
+        assert_eq!(detect(&[("TMUX", ""), sty]), Some(Mux::Screen));
+
+        assert_eq!(detect(&[("TERM", "tmux-256color")]), Some(Mux::Tmux));
+        assert_eq!(detect(&[("TERM", "screen-256color")]), Some(Mux::Screen));
+        assert_eq!(detect(&[("TERM", "xterm-256color")]), None);
+        assert_eq!(detect(&[]), None);
+    }
+}
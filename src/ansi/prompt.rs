@@ -0,0 +1,265 @@
+//! Writing escape sequences into shell prompts.
+//!
+//! Shells work out where the cursor is from how long the prompt is, so everything in it that doesn't take up any
+//! room (escape sequences, and control characters like `BEL`) has to be marked, or line editing goes wrong once the
+//! line wraps. `PromptWriter` puts the markers in for you.
+use super::strip::Scanner;
+use super::style::Style;
+use std::env;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Which shell a prompt is for, which decides how the parts that don't take up any room are marked.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Shell {
+    /// `\[` and `\]`, for bash's `PS1` and the other prompt variables.
+    Bash,
+    /// `%{` and `%}`, for zsh's `PROMPT`.
+    Zsh,
+    /// `\x01` and `\x02`, which readline understands directly. Use these for text that's printed by a command
+    /// substitution in a bash prompt, as bash only looks for `\[` and `\]` before running it.
+    Readline,
+}
+
+impl Shell {
+    /// Guess the shell from `$BASH_VERSION` and `$ZSH_VERSION`, which shells set but don't usually export, then from
+    /// the name of `$SHELL`, the user's login shell.
+    pub fn detect() -> Option<Shell> {
+        Shell::detect_with(|name| env::var(name).ok())
+    }
+
+    /// Like `detect`, but `var` looks up environment variables.
+    pub fn detect_with<F>(var: F) -> Option<Shell>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        if var("BASH_VERSION").is_some() {
+            return Some(Shell::Bash);
+        }
+        if var("ZSH_VERSION").is_some() {
+            return Some(Shell::Zsh);
+        }
+        let shell = var("SHELL")?;
+        match Path::new(&shell).file_name()?.to_str()? {
+            "bash" => Some(Shell::Bash),
+            "zsh" => Some(Shell::Zsh),
+            _ => None,
+        }
+    }
+
+    /// The markers that go before and after text that doesn't take up any room.
+    pub fn markers(self) -> (&'static [u8], &'static [u8]) {
+        match self {
+            Shell::Bash => (b"\\[", b"\\]"),
+            Shell::Zsh => (b"%{", b"%}"),
+            Shell::Readline => (b"\x01", b"\x02"),
+        }
+    }
+}
+
+/// Writes a prompt in a `Style`, marking every escape sequence and control character for the shell.
+///
+/// Like `Term::styled_writer`, style changes are sent with the next write. The style is reset by `into_inner`, so
+/// the prompt doesn't leave the command line styled. Escape sequences in the text written to it are marked too, even
+/// when they're split across writes. The text is left exactly as it is otherwise, so `\` in a bash prompt and `%` in
+/// a zsh one still mean what they always do.
+///
+/// ```
+/// use nixterm::ansi::{PromptWriter, Shell, Style};
+/// use std::io::Write;
+///
+/// let mut w = PromptWriter::new(Shell::Bash);
+/// w.set_style(Style::new().bold());
+/// write!(w, "$ ").unwrap();
+/// assert_eq!(w.into_inner().unwrap(), b"\\[\x1b[1m\\]$ \\[\x1b[0m\\]");
+/// ```
+pub struct PromptWriter<W: Write = Vec<u8>> {
+    inner: W,
+    shell: Shell,
+    scanner: Scanner,
+    /// True if the start marker has been written, but not the end one.
+    open: bool,
+    style: Style,
+    /// The style the prompt is actually in.
+    current: Style,
+}
+
+impl PromptWriter<Vec<u8>> {
+    /// Write a prompt for `shell` into a buffer, which `into_inner` gives back.
+    pub fn new(shell: Shell) -> PromptWriter<Vec<u8>> {
+        PromptWriter::with_writer(Vec::new(), shell)
+    }
+}
+
+impl<W: Write> PromptWriter<W> {
+    /// Write a prompt for `shell` to `inner`.
+    pub fn with_writer(inner: W, shell: Shell) -> PromptWriter<W> {
+        PromptWriter {
+            inner,
+            shell,
+            scanner: Scanner::new(),
+            open: false,
+            style: Style::default(),
+            current: Style::default(),
+        }
+    }
+
+    pub fn style(&self) -> Style {
+        self.style
+    }
+
+    /// Write everything after this in `style`.
+    pub fn set_style(&mut self, style: Style) {
+        self.style = style;
+    }
+
+    /// Go back to the terminal's default style.
+    pub fn reset_style(&mut self) {
+        self.style = Style::default();
+    }
+
+    /// Reset the style, and get the underlying writer back.
+    pub fn into_inner(mut self) -> io::Result<W> {
+        self.reset_style();
+        self.sync_style()?;
+        if self.open {
+            self.inner.write_all(self.shell.markers().1)?;
+        }
+        Ok(self.inner)
+    }
+
+    /// Switch into `self.style` if the prompt isn't already in it.
+    fn sync_style(&mut self) -> io::Result<()> {
+        let sgr = self.current.transition(&self.style);
+        self.current = self.style;
+        self.write_marked(&sgr)
+    }
+
+    /// Write `buf`, putting the markers around everything that doesn't take up any room.
+    fn write_marked(&mut self, buf: &[u8]) -> io::Result<()> {
+        let (start_marker, end_marker) = self.shell.markers();
+        let mut start = 0;
+        for (i, &c) in buf.iter().enumerate() {
+            let text = self.scanner.advance(c);
+            if text == self.open {
+                self.inner.write_all(&buf[start..i])?;
+                self.inner
+                    .write_all(if text { end_marker } else { start_marker })?;
+                self.open = !text;
+                start = i;
+            }
+        }
+        self.inner.write_all(&buf[start..])?;
+
+        // a sequence that's cut off stays inside the markers until the next write
+        if self.open && !self.scanner.in_sequence() {
+            self.inner.write_all(end_marker)?;
+            self.open = false;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for PromptWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.sync_style()?;
+        self.write_marked(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ansi::prompt::*;
+    use std::io::Write;
+
+    /// `user@host:~$ `, with the user in bold green and the path in blue.
+    fn styled_prompt(shell: Shell) -> String {
+        let mut w = PromptWriter::new(shell);
+        w.set_style(Style::new().bold().foreground(2));
+        write!(w, "user@host").unwrap();
+        w.reset_style();
+        write!(w, ":").unwrap();
+        w.set_style(Style::new().foreground(4));
+        write!(w, "~").unwrap();
+        w.reset_style();
+        write!(w, "$ ").unwrap();
+        String::from_utf8(w.into_inner().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn styled() {
+        assert_eq!(
+            styled_prompt(Shell::Bash),
+            "\\[\x1b[1;32m\\]user@host\\[\x1b[0m\\]:\\[\x1b[34m\\]~\\[\x1b[0m\\]$ "
+        );
+        assert_eq!(
+            styled_prompt(Shell::Zsh),
+            "%{\x1b[1;32m%}user@host%{\x1b[0m%}:%{\x1b[34m%}~%{\x1b[0m%}$ "
+        );
+        assert_eq!(
+            styled_prompt(Shell::Readline),
+            "\x01\x1b[1;32m\x02user@host\x01\x1b[0m\x02:\x01\x1b[34m\x02~\x01\x1b[0m\x02$ "
+        );
+    }
+
+    #[test]
+    fn text_untouched() {
+        let text = "plain % text \\w, ünicode\t日本\n";
+        for &shell in &[Shell::Bash, Shell::Zsh, Shell::Readline] {
+            let mut w = PromptWriter::new(shell);
+            write!(w, "{}", text).unwrap();
+            assert_eq!(w.into_inner().unwrap(), text.as_bytes());
+        }
+    }
+
+    #[test]
+    fn sequences_in_text() {
+        // a title, a bell, and a sequence split across writes
+        let mut w = PromptWriter::new(Shell::Zsh);
+        w.write_all(b"\x1b]0;title\x07> \x07a\x1b[3").unwrap();
+        w.write_all(b"1mb").unwrap();
+        assert_eq!(
+            w.into_inner().unwrap(),
+            &b"%{\x1b]0;title\x07%}> %{\x07%}a%{\x1b[31m%}b"[..]
+        );
+
+        // cut off at the end
+        let mut w = PromptWriter::new(Shell::Bash);
+        w.write_all(b"a\x1b[").unwrap();
+        assert_eq!(w.into_inner().unwrap(), &b"a\\[\x1b[\\]"[..]);
+    }
+
+    #[test]
+    fn detect() {
+        let vars = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|&&(k, _)| k == name)
+                    .map(|&(_, v)| v.to_owned())
+            }
+        };
+        assert_eq!(
+            Shell::detect_with(vars(&[("SHELL", "/usr/bin/zsh"), ("BASH_VERSION", "5.2")])),
+            Some(Shell::Bash)
+        );
+        assert_eq!(
+            Shell::detect_with(vars(&[("SHELL", "/bin/bash"), ("ZSH_VERSION", "5.9")])),
+            Some(Shell::Zsh)
+        );
+        assert_eq!(
+            Shell::detect_with(vars(&[("SHELL", "/usr/local/bin/zsh")])),
+            Some(Shell::Zsh)
+        );
+        assert_eq!(
+            Shell::detect_with(vars(&[("SHELL", "/bin/bash")])),
+            Some(Shell::Bash)
+        );
+        assert_eq!(Shell::detect_with(vars(&[("SHELL", "/bin/fish")])), None);
+        assert_eq!(Shell::detect_with(vars(&[])), None);
+    }
+}
@@ -0,0 +1,203 @@
+//! Making untrusted text safe to show on a terminal.
+//!
+//! Text from a file or from the user can contain control characters, and an `ESC` among them starts an escape
+//! sequence that the terminal will carry out when it's shown: changing the window title, redefining keys, or worse.
+//! The C1 controls do the same without an `ESC`, U+009B is taken like `ESC [`. `sanitize` replaces every control
+//! character, so the escape sequences come out as plain text.
+
+/// What `sanitize` replaces control characters with.
+///
+/// The C0 controls (0x00 to 0x1f), DEL (0x7f) and the C1 controls written in UTF-8 (U+0080 to U+009F) are
+/// replaced, and `\n` and `\t` can be kept with `keep_whitespace`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct SanitizePolicy {
+    replacement: Replacement,
+    keep_whitespace: bool,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Replacement {
+    Strip,
+    Caret,
+    Pictures,
+}
+
+impl SanitizePolicy {
+    /// Leave control characters out.
+    pub fn strip() -> SanitizePolicy {
+        SanitizePolicy::new(Replacement::Strip)
+    }
+
+    /// Write control characters in caret notation, like `^[` for `ESC` and `^?` for DEL. A C1 control is written as
+    /// the `ESC` sequence it stands for, like `^[[` for U+009B.
+    pub fn caret() -> SanitizePolicy {
+        SanitizePolicy::new(Replacement::Caret)
+    }
+
+    /// Write control characters as the Unicode control pictures, like `␛` (U+241B) for `ESC` and `␡` for DEL. There
+    /// are none for the C1 controls, so they're written as the `ESC` sequence they stand for, like `␛[` for U+009B.
+    pub fn pictures() -> SanitizePolicy {
+        SanitizePolicy::new(Replacement::Pictures)
+    }
+
+    fn new(replacement: Replacement) -> SanitizePolicy {
+        SanitizePolicy {
+            replacement,
+            keep_whitespace: false,
+        }
+    }
+
+    /// Leave `\n` and `\t` as they are, by default they're replaced too.
+    pub fn keep_whitespace(mut self, v: bool) -> SanitizePolicy {
+        self.keep_whitespace = v;
+        self
+    }
+
+    /// True if `c` is left as it is.
+    fn keeps(&self, c: u8) -> bool {
+        let control = c < 0x20 || c == 0x7f;
+        !control || (self.keep_whitespace && (c == b'\n' || c == b'\t'))
+    }
+}
+
+/// The control character at the start of `input` that `policy` replaces, and how many bytes it is.
+fn control(input: &[u8], policy: SanitizePolicy) -> Option<(u8, usize)> {
+    match *input {
+        [0xc2, c @ 0x80..=0x9f, ..] => Some((c, 2)),
+        [c, ..] if !policy.keeps(c) => Some((c, 1)),
+        _ => None,
+    }
+}
+
+/// True if `sanitize` would change `input`, so text that's already safe can be written without a copy.
+pub fn needs_sanitizing(input: &[u8], policy: SanitizePolicy) -> bool {
+    (0..input.len()).any(|i| control(&input[i..], policy).is_some())
+}
+
+/// Replace the control characters in `input` as `policy` says, see the module documentation.
+///
+/// A C1 control is replaced as a whole, and the others are never part of a multibyte UTF-8 character, so UTF-8 text
+/// stays valid. Bytes from 0x80 to 0x9f that aren't UTF-8 are left alone, a terminal in UTF-8 doesn't take them as
+/// C1 controls.
+pub fn sanitize(input: &[u8], policy: SanitizePolicy) -> Vec<u8> {
+    let mut out = Vec::with_capacity(input.len());
+    let mut i = 0;
+    while i < input.len() {
+        let (c, len) = match control(&input[i..], policy) {
+            Some(control) => control,
+            None => {
+                out.push(input[i]);
+                i += 1;
+                continue;
+            }
+        };
+        i += len;
+
+        // a C1 control is replaced like the `ESC` sequence it stands for
+        if c >= 0x80 {
+            match policy.replacement {
+                Replacement::Strip => continue,
+                Replacement::Caret => out.extend_from_slice(b"^["),
+                Replacement::Pictures => out.extend_from_slice("\u{241b}".as_bytes()),
+            }
+            out.push(c - 0x40);
+            continue;
+        }
+        match policy.replacement {
+            Replacement::Strip => (),
+            Replacement::Caret => out.extend_from_slice(&[b'^', c ^ 0x40]),
+            Replacement::Pictures => {
+                // DEL's picture is out of order, after the space's
+                let picture = if c == 0x7f {
+                    '\u{2421}'
+                } else {
+                    char::from_u32(0x2400 + u32::from(c)).unwrap()
+                };
+                let mut utf8 = [0u8; 4];
+                out.extend_from_slice(char::encode_utf8(picture, &mut utf8).as_bytes());
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use ansi::sanitize::*;
+    use ansi::strip;
+
+    /// Sets the window title, then hides the rest of the line.
+    const ATTACK: &[u8] = b"file.txt\x1b]0;pwned\x07\x1b[8mhidden\x1b[0m\r\n";
+
+    #[test]
+    fn policies() {
+        assert_eq!(
+            sanitize(ATTACK, SanitizePolicy::strip()),
+            &b"file.txt]0;pwned[8mhidden[0m"[..]
+        );
+        assert_eq!(
+            sanitize(ATTACK, SanitizePolicy::caret()),
+            &b"file.txt^[]0;pwned^G^[[8mhidden^[[0m^M^J"[..]
+        );
+        assert_eq!(
+            String::from_utf8(sanitize(ATTACK, SanitizePolicy::pictures())).unwrap(),
+            "file.txt\u{241b}]0;pwned\u{2407}\u{241b}[8mhidden\u{241b}[0m\u{240d}\u{240a}"
+        );
+
+        for &policy in &[
+            SanitizePolicy::strip(),
+            SanitizePolicy::caret(),
+            SanitizePolicy::pictures(),
+        ] {
+            let out = sanitize(ATTACK, policy);
+            assert!(!out.iter().any(|&c| c < 0x20 || c == 0x7f), "{:?}", policy);
+            // there's nothing left for a terminal to carry out
+            assert_eq!(strip(&out), out);
+        }
+    }
+
+    #[test]
+    fn whitespace() {
+        let input = b"a\tb\nc\r\x7f";
+        assert_eq!(sanitize(input, SanitizePolicy::caret()), b"a^Ib^Jc^M^?");
+        assert_eq!(
+            sanitize(input, SanitizePolicy::caret().keep_whitespace(true)),
+            b"a\tb\nc^M^?"
+        );
+        assert_eq!(
+            sanitize(input, SanitizePolicy::strip().keep_whitespace(true)),
+            b"a\tb\nc"
+        );
+        assert!(needs_sanitizing(input, SanitizePolicy::caret().keep_whitespace(true)));
+        assert!(!needs_sanitizing(b"a\tb\n", SanitizePolicy::caret().keep_whitespace(true)));
+        assert!(needs_sanitizing(b"a\tb\n", SanitizePolicy::caret()));
+        assert_eq!(
+            String::from_utf8(sanitize("ü\x00\x7f".as_bytes(), SanitizePolicy::pictures()))
+                .unwrap(),
+            "ü\u{2400}\u{2421}"
+        );
+    }
+
+    #[test]
+    fn c1() {
+        // U+009B is CSI on its own, so this hides the rest of the line without an ESC
+        let input = "a\u{9b}8mhidden\u{9d}0;pwned\u{9c}".as_bytes();
+        assert_eq!(sanitize(input, SanitizePolicy::strip()), b"a8mhidden0;pwned");
+        assert_eq!(
+            sanitize(input, SanitizePolicy::caret()),
+            &b"a^[[8mhidden^[]0;pwned^[\\"[..]
+        );
+        assert_eq!(
+            String::from_utf8(sanitize(input, SanitizePolicy::pictures())).unwrap(),
+            "a\u{241b}[8mhidden\u{241b}]0;pwned\u{241b}\\"
+        );
+        assert!(needs_sanitizing("\u{80}".as_bytes(), SanitizePolicy::strip()));
+        assert!(needs_sanitizing("\u{9f}".as_bytes(), SanitizePolicy::strip()));
+
+        // the characters either side of them are fine, and so are the bytes when they aren't UTF-8
+        let input = "\u{7e}\u{a0}é\u{71c}".as_bytes();
+        assert!(!needs_sanitizing(input, SanitizePolicy::caret()));
+        assert_eq!(sanitize(input, SanitizePolicy::caret()), input);
+        assert_eq!(sanitize(b"\x9b\xc2", SanitizePolicy::caret()), b"\x9b\xc2");
+    }
+}
@@ -0,0 +1,1108 @@
+//! An in-memory terminal screen, for testing what a program draws without a real terminal.
+//!
+//! # Examples
+//! ```
+//! use nixterm::ansi::TestScreen;
+//!
+//! let mut screen = TestScreen::new(2, 20);
+//! screen.feed(b"\x1b[7mFile  Edit\x1b[m\r\nhello");
+//! assert_eq!(screen.row_text(0), "File  Edit");
+//! assert!(screen.cell(0, 0).attrs.reverse);
+//! screen.assert_snapshot("File  Edit\nhello");
+//! ```
+use super::parser::{Parser, Perform};
+use super::width::char_width;
+use super::Color;
+use acs::AcsChar;
+use errors::*;
+use record::{self, Event, RecordFormat};
+use std::io;
+use std::mem;
+
+/// The most lines `TestScreen` keeps after they've scrolled off the top of the screen.
+pub const SCROLLBACK_LIMIT: usize = 1000;
+
+/// Character attributes set with SGR.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Attrs {
+    pub bold: bool,
+    pub dim: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub blink: bool,
+    pub reverse: bool,
+    pub invisible: bool,
+    pub strikethrough: bool,
+}
+
+/// A single character cell on the screen.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Cell {
+    /// The character in this cell. The cell to the right of a wide character holds `'\0'`.
+    pub c: char,
+    /// The foreground color, or `None` for the terminal's default.
+    pub fg: Option<Color>,
+    /// The background color, or `None` for the terminal's default.
+    pub bg: Option<Color>,
+    pub attrs: Attrs,
+}
+
+impl Default for Cell {
+    fn default() -> Cell {
+        Cell {
+            c: ' ',
+            fg: None,
+            bg: None,
+            attrs: Attrs::default(),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Charset {
+    Ascii,
+    DecGraphics,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct SavedCursor {
+    row: usize,
+    col: usize,
+    pen: Cell,
+    charsets: [Charset; 2],
+    shift: usize,
+}
+
+/// A grid of cells that interprets terminal output the way a VT100/xterm style terminal would.
+///
+/// It understands enough to render typical full screen programs: cursor movement, wrapping, SGR, erasing, inserting
+/// and deleting, scroll regions, the alternate screen and the DEC line drawing character set.
+pub struct TestScreen {
+    rows: usize,
+    cols: usize,
+    grid: Vec<Vec<Cell>>,
+    /// The primary screen, while the alternate screen is active.
+    primary: Option<Vec<Vec<Cell>>>,
+    scrollback: Vec<Vec<Cell>>,
+
+    row: usize,
+    col: usize,
+    /// Set when a character was printed in the last column, the next one wraps onto the next line.
+    wrap_pending: bool,
+    saved: Option<SavedCursor>,
+
+    pen: Cell,
+    last_char: Option<char>,
+    charsets: [Charset; 2],
+    shift: usize,
+
+    scroll_top: usize,
+    scroll_bottom: usize,
+    tab_stops: Vec<bool>,
+    autowrap: bool,
+    /// Wait for the next character before wrapping, like xterm, see `set_deferred_wrap`.
+    deferred_wrap: bool,
+    /// Insert mode (IRM), characters push the rest of the line right.
+    insert: bool,
+    cursor_visible: bool,
+
+    parser: Parser,
+}
+
+impl TestScreen {
+    /// Create an empty screen, `rows` lines tall and `cols` columns wide.
+    ///
+    /// Panics if either dimension is 0.
+    pub fn new(rows: usize, cols: usize) -> TestScreen {
+        assert!(
+            rows > 0 && cols > 0,
+            "a screen must have at least one row and column"
+        );
+
+        TestScreen {
+            rows,
+            cols,
+            grid: vec![vec![Cell::default(); cols]; rows],
+            primary: None,
+            scrollback: Vec::new(),
+            row: 0,
+            col: 0,
+            wrap_pending: false,
+            saved: None,
+            pen: Cell::default(),
+            last_char: None,
+            charsets: [Charset::Ascii; 2],
+            shift: 0,
+            scroll_top: 0,
+            scroll_bottom: rows - 1,
+            tab_stops: (0..cols).map(|c| c % 8 == 0).collect(),
+            autowrap: true,
+            deferred_wrap: true,
+            insert: false,
+            cursor_visible: true,
+            parser: Parser::new(),
+        }
+    }
+
+    /// Feed terminal output to the screen.
+    pub fn feed(&mut self, bytes: &[u8]) {
+        let mut parser = mem::take(&mut self.parser);
+        parser.feed(self, bytes);
+        self.parser = parser;
+    }
+
+    /// Choose whether printing in the last column waits for the next character before wrapping, like xterm (and
+    /// terminfo's `xenl`), which it does by default. Otherwise the cursor wraps straight away, so printing in the
+    /// bottom right corner scrolls the screen.
+    pub fn set_deferred_wrap(&mut self, deferred: bool) {
+        self.deferred_wrap = deferred;
+    }
+
+    /// Resize the screen to `rows` by `cols`, like a terminal window: text that no longer fits on a line is cut off,
+    /// and when the screen gets shorter the lines at the top scroll off it, so the cursor's line is kept. The scroll
+    /// region is reset to the whole screen.
+    ///
+    /// Panics if either dimension is 0.
+    pub fn resize(&mut self, rows: usize, cols: usize) {
+        assert!(
+            rows > 0 && cols > 0,
+            "a screen must have at least one row and column"
+        );
+
+        let scrolled = (self.row + 1).saturating_sub(rows);
+        for line in self.grid.drain(..scrolled) {
+            if self.primary.is_none() {
+                self.scrollback.push(line);
+            }
+        }
+        if self.scrollback.len() > SCROLLBACK_LIMIT {
+            let excess = self.scrollback.len() - SCROLLBACK_LIMIT;
+            self.scrollback.drain(..excess);
+        }
+        let fit = |grid: &mut Vec<Vec<Cell>>| {
+            grid.resize(rows, vec![Cell::default(); cols]);
+            for line in grid.iter_mut() {
+                line.resize(cols, Cell::default());
+            }
+        };
+        fit(&mut self.grid);
+        if let Some(ref mut primary) = self.primary {
+            fit(primary);
+        }
+
+        self.rows = rows;
+        self.cols = cols;
+        self.row -= scrolled;
+        self.col = self.col.min(cols - 1);
+        self.wrap_pending = false;
+        self.scroll_top = 0;
+        self.scroll_bottom = rows - 1;
+        let stops = self.tab_stops.len();
+        self.tab_stops.truncate(cols);
+        self.tab_stops.extend((stops..cols).map(|c| c % 8 == 0));
+        if let Some(ref mut saved) = self.saved {
+            saved.row = saved.row.min(rows - 1);
+            saved.col = saved.col.min(cols - 1);
+        }
+    }
+
+    /// Play a recording back onto a new screen, returning the screen as it was at the end. See
+    /// `replay_frames`.
+    pub fn replay<R: io::Read>(reader: R, format: RecordFormat) -> Result<TestScreen> {
+        TestScreen::replay_frames(reader, format, |_, _| ())
+    }
+
+    /// Play a recording back onto a new screen, calling `frame` with each event's time (in seconds) and the screen
+    /// after it, and returning the screen as it was at the end.
+    ///
+    /// An asciicast recording's screen is the size its header says, and is resized by its resize events. A raw one
+    /// has no size, so its screen is 24 by 80, and it's all one frame at 0 seconds. A header or resize bigger than
+    /// `record::MAX_SIZE` on a side fails with `ParseError::RecordingTooLarge`.
+    pub fn replay_frames<R, F>(mut reader: R, format: RecordFormat, mut frame: F) -> Result<TestScreen>
+    where
+        R: io::Read,
+        F: FnMut(f64, &TestScreen),
+    {
+        let mut recording = Vec::new();
+        reader
+            .read_to_end(&mut recording)
+            .context(ErrorKind::ReadFailed)?;
+
+        match format {
+            RecordFormat::Raw => {
+                let mut screen = TestScreen::new(24, 80);
+                screen.feed(&recording);
+                frame(0.0, &screen);
+                Ok(screen)
+            }
+            RecordFormat::Asciicast => {
+                let recording = String::from_utf8_lossy(&recording);
+                let (header, events) = record::read_asciicast(&recording)?;
+                let mut screen = TestScreen::new(header.height, header.width);
+                for (time, event) in events {
+                    match event {
+                        Event::Output(text) => screen.feed(text.as_bytes()),
+                        Event::Resize(cols, rows) => screen.resize(rows, cols),
+                        Event::Other => continue,
+                    }
+                    frame(time, &screen);
+                }
+                Ok(screen)
+            }
+        }
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    /// The cursor's position, as `(row, column)`.
+    pub fn cursor(&self) -> (usize, usize) {
+        (self.row, self.col)
+    }
+
+    pub fn cursor_visible(&self) -> bool {
+        self.cursor_visible
+    }
+
+    /// True if the alternate screen is being displayed.
+    pub fn is_alternate(&self) -> bool {
+        self.primary.is_some()
+    }
+
+    /// Get the cell at `row`, `col`.
+    ///
+    /// Panics if the position is off the screen.
+    pub fn cell(&self, row: usize, col: usize) -> &Cell {
+        &self.grid[row][col]
+    }
+
+    /// The text on `row`, with trailing whitespace removed.
+    pub fn row_text(&self, row: usize) -> String {
+        line_text(&self.grid[row])
+    }
+
+    /// The text on the screen, one line per row. Trailing whitespace and blank lines are removed.
+    pub fn contents(&self) -> String {
+        let lines: Vec<_> = (0..self.rows).map(|r| self.row_text(r)).collect();
+        lines.join("\n").trim_end_matches('\n').to_string()
+    }
+
+    /// The lines that have scrolled off the top of the screen, oldest first.
+    pub fn scrollback(&self) -> Vec<String> {
+        self.scrollback.iter().map(|l| line_text(l)).collect()
+    }
+
+    /// Assert the screen's contents match `expected`.
+    ///
+    /// Trailing whitespace on each line of `expected`, and any trailing blank lines, are ignored. On failure the
+    /// expected and actual screens are printed next to each other.
+    pub fn assert_snapshot(&self, expected: &str) {
+        let expected: Vec<_> = expected.lines().map(|l| l.trim_end()).collect();
+        let expected = expected.join("\n").trim_end_matches('\n').to_string();
+        let actual = self.contents();
+
+        if expected != actual {
+            let mut msg = String::from("screen doesn't match snapshot\n");
+            let expected: Vec<_> = expected.lines().collect();
+            let actual: Vec<_> = actual.lines().collect();
+            for i in 0..expected.len().max(actual.len()) {
+                let e = expected.get(i).cloned().unwrap_or("");
+                let a = actual.get(i).cloned().unwrap_or("");
+                let marker = if e == a { ' ' } else { '!' };
+                msg.push_str(&format!(
+                    "{} {:>3} |{:<width$}| |{}|\n",
+                    marker,
+                    i,
+                    e,
+                    a,
+                    width = self.cols
+                ));
+            }
+            panic!("{}", msg);
+        }
+    }
+
+    fn blank(&self) -> Cell {
+        Cell {
+            bg: self.pen.bg,
+            ..Cell::default()
+        }
+    }
+
+    fn blank_line(&self) -> Vec<Cell> {
+        vec![self.blank(); self.cols]
+    }
+
+    fn put(&mut self, c: char) {
+        let c = match self.charsets[self.shift] {
+            Charset::DecGraphics if c.is_ascii() => AcsChar::from_vt100_code(c as u8)
+                .map(|a| a.unicode())
+                .unwrap_or(c),
+            _ => c,
+        };
+
+        let width = char_width(c);
+        if width == 0 {
+            return;
+        }
+
+        if self.wrap_pending || self.col + width > self.cols {
+            if self.autowrap {
+                self.col = 0;
+                self.linefeed();
+            } else {
+                self.col = self.cols - width.min(self.cols);
+            }
+        }
+        self.wrap_pending = false;
+        if self.insert {
+            self.insert_chars(width);
+        }
+
+        let cell = Cell { c, ..self.pen };
+        self.grid[self.row][self.col] = cell;
+        if width == 2 && self.col + 1 < self.cols {
+            self.grid[self.row][self.col + 1] = Cell { c: '\0', ..cell };
+        }
+
+        if self.col + width >= self.cols {
+            self.col = self.cols - 1;
+            self.wrap_pending = true;
+            if self.autowrap && !self.deferred_wrap {
+                self.col = 0;
+                self.wrap_pending = false;
+                self.linefeed();
+            }
+        } else {
+            self.col += width;
+        }
+        self.last_char = Some(c);
+    }
+
+    fn linefeed(&mut self) {
+        if self.row == self.scroll_bottom {
+            self.scroll_up(1);
+        } else if self.row + 1 < self.rows {
+            self.row += 1;
+        }
+    }
+
+    fn reverse_index(&mut self) {
+        if self.row == self.scroll_top {
+            self.scroll_down(1);
+        } else if self.row > 0 {
+            self.row -= 1;
+        }
+    }
+
+    /// Scroll the lines in the scroll region up by `n`, adding blank lines at the bottom.
+    fn scroll_up(&mut self, n: usize) {
+        for _ in 0..n.min(self.scroll_bottom - self.scroll_top + 1) {
+            let line = self.grid.remove(self.scroll_top);
+            if self.scroll_top == 0 && self.primary.is_none() {
+                self.scrollback.push(line);
+                if self.scrollback.len() > SCROLLBACK_LIMIT {
+                    self.scrollback.remove(0);
+                }
+            }
+            let blank = self.blank_line();
+            self.grid.insert(self.scroll_bottom, blank);
+        }
+    }
+
+    /// Scroll the lines in the scroll region down by `n`, adding blank lines at the top.
+    fn scroll_down(&mut self, n: usize) {
+        for _ in 0..n.min(self.scroll_bottom - self.scroll_top + 1) {
+            self.grid.remove(self.scroll_bottom);
+            let blank = self.blank_line();
+            self.grid.insert(self.scroll_top, blank);
+        }
+    }
+
+    fn goto(&mut self, row: usize, col: usize) {
+        self.row = row.min(self.rows - 1);
+        self.col = col.min(self.cols - 1);
+        self.wrap_pending = false;
+    }
+
+    fn erase(&mut self, row: usize, from: usize, to: usize) {
+        let blank = self.blank();
+        for cell in &mut self.grid[row][from.min(self.cols)..to.min(self.cols)] {
+            *cell = blank;
+        }
+    }
+
+    fn erase_display(&mut self, mode: i64) {
+        let (row, col, rows, cols) = (self.row, self.col, self.rows, self.cols);
+        match mode {
+            0 => {
+                self.erase(row, col, cols);
+                (row + 1..rows).for_each(|r| self.erase(r, 0, cols));
+            }
+            1 => {
+                (0..row).for_each(|r| self.erase(r, 0, cols));
+                self.erase(row, 0, col + 1);
+            }
+            2 => (0..rows).for_each(|r| self.erase(r, 0, cols)),
+            3 => self.scrollback.clear(),
+            _ => (),
+        }
+    }
+
+    fn erase_line(&mut self, mode: i64) {
+        let (row, col, cols) = (self.row, self.col, self.cols);
+        match mode {
+            0 => self.erase(row, col, cols),
+            1 => self.erase(row, 0, col + 1),
+            2 => self.erase(row, 0, cols),
+            _ => (),
+        }
+    }
+
+    fn insert_chars(&mut self, n: usize) {
+        let blank = self.blank();
+        let line = &mut self.grid[self.row];
+        for _ in 0..n.min(self.cols - self.col) {
+            line.pop();
+            line.insert(self.col, blank);
+        }
+    }
+
+    fn delete_chars(&mut self, n: usize) {
+        let blank = self.blank();
+        let line = &mut self.grid[self.row];
+        for _ in 0..n.min(self.cols - self.col) {
+            line.remove(self.col);
+            line.push(blank);
+        }
+    }
+
+    /// Insert (or delete, if `insert` is false) `n` lines at the cursor, within the scroll region.
+    fn insert_lines(&mut self, n: usize, insert: bool) {
+        if self.row < self.scroll_top || self.row > self.scroll_bottom {
+            return;
+        }
+
+        let top = self.scroll_top;
+        self.scroll_top = self.row;
+        if insert {
+            self.scroll_down(n);
+        } else {
+            // lines deleted from a region never go to the scrollback
+            let primary = self.primary.take();
+            self.primary = Some(Vec::new());
+            self.scroll_up(n);
+            self.primary = primary;
+        }
+        self.scroll_top = top;
+        self.col = 0;
+        self.wrap_pending = false;
+    }
+
+    fn save_cursor(&mut self) {
+        self.saved = Some(SavedCursor {
+            row: self.row,
+            col: self.col,
+            pen: self.pen,
+            charsets: self.charsets,
+            shift: self.shift,
+        });
+    }
+
+    fn restore_cursor(&mut self) {
+        match self.saved {
+            Some(s) => {
+                self.goto(s.row, s.col);
+                self.pen = s.pen;
+                self.charsets = s.charsets;
+                self.shift = s.shift;
+            }
+            None => self.goto(0, 0),
+        }
+    }
+
+    fn set_alternate(&mut self, alternate: bool) {
+        if alternate && self.primary.is_none() {
+            let blank = vec![vec![Cell::default(); self.cols]; self.rows];
+            self.primary = Some(mem::replace(&mut self.grid, blank));
+        } else if !alternate {
+            if let Some(primary) = self.primary.take() {
+                self.grid = primary;
+            }
+        }
+    }
+
+    fn set_mode(&mut self, mode: i64, private: bool, on: bool) {
+        if !private {
+            if mode == 4 {
+                self.insert = on;
+            }
+            return;
+        }
+
+        match mode {
+            7 => self.autowrap = on,
+            25 => self.cursor_visible = on,
+            47 | 1047 => self.set_alternate(on),
+            1048 => {
+                if on {
+                    self.save_cursor()
+                } else {
+                    self.restore_cursor()
+                }
+            }
+            1049 => {
+                if on {
+                    self.save_cursor();
+                    self.set_alternate(true);
+                } else {
+                    self.set_alternate(false);
+                    self.restore_cursor();
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn sgr(&mut self, params: &[i64]) {
+        if params.is_empty() {
+            self.pen = Cell::default();
+            return;
+        }
+
+        let mut i = 0;
+        while i < params.len() {
+            let attrs = &mut self.pen.attrs;
+            match params[i] {
+                0 => self.pen = Cell::default(),
+                1 => attrs.bold = true,
+                2 => attrs.dim = true,
+                3 => attrs.italic = true,
+                4 => attrs.underline = true,
+                5 => attrs.blink = true,
+                7 => attrs.reverse = true,
+                8 => attrs.invisible = true,
+                9 => attrs.strikethrough = true,
+                21 => attrs.underline = true,
+                22 => {
+                    attrs.bold = false;
+                    attrs.dim = false;
+                }
+                23 => attrs.italic = false,
+                24 => attrs.underline = false,
+                25 => attrs.blink = false,
+                27 => attrs.reverse = false,
+                28 => attrs.invisible = false,
+                29 => attrs.strikethrough = false,
+                x @ 30..=37 => self.pen.fg = Some(Color::Index(x as u8 - 30)),
+                x @ 40..=47 => self.pen.bg = Some(Color::Index(x as u8 - 40)),
+                x @ 90..=97 => self.pen.fg = Some(Color::Index(x as u8 - 90 + 8)),
+                x @ 100..=107 => self.pen.bg = Some(Color::Index(x as u8 - 100 + 8)),
+                39 => self.pen.fg = None,
+                49 => self.pen.bg = None,
+                x @ 38 | x @ 48 => {
+                    let color = match params.get(i + 1) {
+                        Some(&5) if i + 2 < params.len() => {
+                            i += 2;
+                            Some(Color::Index(params[i] as u8))
+                        }
+                        Some(&2) if i + 4 < params.len() => {
+                            i += 4;
+                            Some(Color::Rgb(
+                                params[i - 2] as u8,
+                                params[i - 1] as u8,
+                                params[i] as u8,
+                            ))
+                        }
+                        _ => None,
+                    };
+                    if x == 38 {
+                        self.pen.fg = color.or(self.pen.fg);
+                    } else {
+                        self.pen.bg = color.or(self.pen.bg);
+                    }
+                }
+                _ => (),
+            }
+            i += 1;
+        }
+    }
+
+    fn reset(&mut self) {
+        let scrollback = mem::take(&mut self.scrollback);
+        *self = TestScreen::new(self.rows, self.cols);
+        self.scrollback = scrollback;
+    }
+}
+
+fn line_text(line: &[Cell]) -> String {
+    let s: String = line.iter().map(|c| c.c).filter(|&c| c != '\0').collect();
+    s.trim_end().to_string()
+}
+
+impl Perform for TestScreen {
+    fn print(&mut self, c: char) {
+        self.put(c);
+    }
+
+    fn execute(&mut self, byte: u8) {
+        match byte {
+            b'\r' => {
+                self.col = 0;
+                self.wrap_pending = false;
+            }
+            b'\n' | 0x0b | 0x0c => {
+                self.linefeed();
+                self.wrap_pending = false;
+            }
+            0x08 => {
+                self.col = self.col.saturating_sub(1);
+                self.wrap_pending = false;
+            }
+            b'\t' => {
+                let next = (self.col + 1..self.cols)
+                    .find(|&c| self.tab_stops[c])
+                    .unwrap_or(self.cols - 1);
+                self.col = next;
+            }
+            // shift out / shift in
+            0x0e => self.shift = 1,
+            0x0f => self.shift = 0,
+            _ => (),
+        }
+    }
+
+    fn csi_dispatch(&mut self, params: &[i64], intermediates: &[u8], action: u8) {
+        let private = intermediates == b"?";
+        if !intermediates.is_empty() && !private {
+            return;
+        }
+
+        // the first parameter, defaulting to `default` when it's missing or 0
+        let arg = |i: usize, default: usize| match params.get(i) {
+            Some(&x) if x > 0 => x as usize,
+            _ => default,
+        };
+        let n = arg(0, 1);
+
+        match action {
+            b'h' | b'l' => {
+                for &mode in params {
+                    self.set_mode(mode, private, action == b'h');
+                }
+            }
+            _ if private => (),
+            b'A' => {
+                let top = if self.row >= self.scroll_top {
+                    self.scroll_top
+                } else {
+                    0
+                };
+                let row = self.row.saturating_sub(n).max(top);
+                let col = self.col;
+                self.goto(row, col);
+            }
+            b'B' => {
+                let bottom = if self.row <= self.scroll_bottom {
+                    self.scroll_bottom
+                } else {
+                    self.rows - 1
+                };
+                let row = (self.row + n).min(bottom);
+                let col = self.col;
+                self.goto(row, col);
+            }
+            b'C' => {
+                let (row, col) = (self.row, self.col + n);
+                self.goto(row, col);
+            }
+            b'D' => {
+                let (row, col) = (self.row, self.col.saturating_sub(n));
+                self.goto(row, col);
+            }
+            b'E' => {
+                let row = self.row + n;
+                self.goto(row, 0);
+            }
+            b'F' => {
+                let row = self.row.saturating_sub(n);
+                self.goto(row, 0);
+            }
+            b'G' | b'`' => {
+                let row = self.row;
+                self.goto(row, n - 1);
+            }
+            b'd' => {
+                let col = self.col;
+                self.goto(n - 1, col);
+            }
+            b'H' | b'f' => self.goto(n - 1, arg(1, 1) - 1),
+            b'J' => self.erase_display(params.first().cloned().unwrap_or(0)),
+            b'K' => self.erase_line(params.first().cloned().unwrap_or(0)),
+            b'X' => {
+                let (row, col) = (self.row, self.col);
+                self.erase(row, col, col + n);
+            }
+            b'@' => self.insert_chars(n),
+            b'P' => self.delete_chars(n),
+            b'L' => self.insert_lines(n, true),
+            b'M' => self.insert_lines(n, false),
+            b'S' => self.scroll_up(n),
+            b'T' => self.scroll_down(n),
+            b'b' => {
+                // repeating more than fills the screen looks the same, and a huge count would hang
+                if let Some(c) = self.last_char {
+                    (0..n.min(self.rows * self.cols)).for_each(|_| self.put(c));
+                }
+            }
+            b'I' => (0..n.min(self.cols)).for_each(|_| self.execute(b'\t')),
+            b'Z' => {
+                for _ in 0..n.min(self.cols) {
+                    self.col = (0..self.col)
+                        .rev()
+                        .find(|&c| self.tab_stops[c])
+                        .unwrap_or(0);
+                }
+            }
+            b'g' => match params.first().cloned().unwrap_or(0) {
+                0 => self.tab_stops[self.col] = false,
+                3 => self.tab_stops.iter_mut().for_each(|t| *t = false),
+                _ => (),
+            },
+            b'm' => self.sgr(params),
+            b'r' => {
+                let top = arg(0, 1) - 1;
+                let bottom = arg(1, self.rows).min(self.rows) - 1;
+                if top < bottom {
+                    self.scroll_top = top;
+                    self.scroll_bottom = bottom;
+                    self.goto(0, 0);
+                }
+            }
+            b's' => self.save_cursor(),
+            b'u' => self.restore_cursor(),
+            _ => (),
+        }
+    }
+
+    fn esc_dispatch(&mut self, intermediates: &[u8], byte: u8) {
+        match (intermediates, byte) {
+            (b"", b'7') => self.save_cursor(),
+            (b"", b'8') => self.restore_cursor(),
+            (b"", b'D') => self.linefeed(),
+            (b"", b'E') => {
+                self.col = 0;
+                self.linefeed();
+            }
+            (b"", b'M') => self.reverse_index(),
+            (b"", b'H') => self.tab_stops[self.col] = true,
+            (b"", b'c') => self.reset(),
+            (b"(", b'0') => self.charsets[0] = Charset::DecGraphics,
+            (b"(", _) => self.charsets[0] = Charset::Ascii,
+            (b")", b'0') => self.charsets[1] = Charset::DecGraphics,
+            (b")", _) => self.charsets[1] = Charset::Ascii,
+            _ => (),
+        }
+    }
+}
+
+impl io::Write for TestScreen {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.feed(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ansi::screen::*;
+    use ansi::Color;
+
+    const VIM: &'static [u8] = include_bytes!("../../test-data/vim-startup.out");
+    const TOP: &'static [u8] = include_bytes!("../../test-data/top.out");
+    const CAST: &'static [u8] = include_bytes!("../../test-data/replay.cast");
+
+    #[test]
+    fn print_and_wrap() {
+        let mut s = TestScreen::new(3, 5);
+        s.feed(b"hello world");
+        s.assert_snapshot("hello\n worl\nd");
+        assert_eq!(s.cursor(), (2, 1));
+
+        // the last column doesn't wrap until another character is printed
+        let mut s = TestScreen::new(2, 5);
+        s.feed(b"abcde");
+        assert_eq!(s.cursor(), (0, 4));
+        s.feed(b"\r\nx");
+        s.assert_snapshot("abcde\nx");
+
+        let mut s = TestScreen::new(2, 5);
+        s.feed(b"\x1b[?7labcdefg");
+        s.assert_snapshot("abcdg");
+
+        // unless it's told to wrap straight away, then the bottom right corner scrolls
+        let mut s = TestScreen::new(2, 5);
+        s.set_deferred_wrap(false);
+        s.feed(b"abcde");
+        assert_eq!(s.cursor(), (1, 0));
+        s.feed(b"\x1b[2;1Hfghij");
+        s.assert_snapshot("fghij");
+        assert_eq!(s.scrollback(), vec!["abcde"]);
+    }
+
+    #[test]
+    fn insert() {
+        let mut s = TestScreen::new(1, 6);
+        s.feed(b"abcd\x1b[1;2H\x1b[@x\x1b[4hyz\x1b[4lw");
+        s.assert_snapshot("axyzwc");
+    }
+
+    #[test]
+    fn wide_chars() {
+        let mut s = TestScreen::new(2, 5);
+        s.feed("日本語".as_bytes());
+        s.assert_snapshot("日本\n語");
+        assert_eq!(s.cell(0, 1).c, '\0');
+        assert_eq!(s.cursor(), (1, 2));
+    }
+
+    #[test]
+    fn cursor_movement() {
+        let mut s = TestScreen::new(5, 10);
+        s.feed(b"\x1b[3;4Hx\x1b[Hy\x1b[5;10Hz");
+        assert_eq!(s.cell(2, 3).c, 'x');
+        assert_eq!(s.cell(0, 0).c, 'y');
+        assert_eq!(s.cell(4, 9).c, 'z');
+
+        s.feed(b"\x1b[2;2H\x1b[2B\x1b[3Ca\x1b[A\x1b[2Db");
+        assert_eq!(s.cell(3, 4).c, 'a');
+        assert_eq!(s.cell(2, 3).c, 'b');
+
+        s.feed(b"\x1b[9G\x1b[2dc\x1b[99;99Hd");
+        assert_eq!(s.cell(1, 8).c, 'c');
+        assert_eq!(s.cell(4, 9).c, 'd');
+
+        s.feed(b"\x1b[1;1H\x1b7\x1b[4;4H\x1b8e");
+        assert_eq!(s.cell(0, 0).c, 'e');
+
+        s.feed(b"\x1b[3;1H\tf");
+        assert_eq!(s.cell(2, 8).c, 'f');
+    }
+
+    #[test]
+    fn sgr() {
+        let mut s = TestScreen::new(1, 20);
+        s.feed(b"\x1b[1;31mA\x1b[22;4;44mB\x1b[38;5;200;48;2;1;2;3mC\x1b[0mD\x1b[7;95mE");
+
+        assert!(s.cell(0, 0).attrs.bold);
+        assert_eq!(s.cell(0, 0).fg, Some(Color::Index(1)));
+        assert!(!s.cell(0, 1).attrs.bold);
+        assert!(s.cell(0, 1).attrs.underline);
+        assert_eq!(s.cell(0, 1).bg, Some(Color::Index(4)));
+        assert_eq!(s.cell(0, 2).fg, Some(Color::Index(200)));
+        assert_eq!(s.cell(0, 2).bg, Some(Color::Rgb(1, 2, 3)));
+        assert_eq!(
+            *s.cell(0, 3),
+            Cell {
+                c: 'D',
+                ..Cell::default()
+            }
+        );
+        assert!(s.cell(0, 4).attrs.reverse);
+        assert_eq!(s.cell(0, 4).fg, Some(Color::Index(13)));
+    }
+
+    #[test]
+    fn erase() {
+        let mut s = TestScreen::new(3, 5);
+        s.feed(b"aaaaa\r\nbbbbb\r\nccccc");
+        s.feed(b"\x1b[2;3H\x1b[K");
+        s.assert_snapshot("aaaaa\nbb\nccccc");
+        s.feed(b"\x1b[1K");
+        s.assert_snapshot("aaaaa\n\nccccc");
+        s.feed(b"\x1b[1;2H\x1b[J");
+        s.assert_snapshot("a");
+        s.feed(b"\x1b[2J");
+        s.assert_snapshot("");
+
+        // erasing uses the current background color
+        s.feed(b"\x1b[41m\x1b[2J\x1b[m");
+        assert_eq!(s.cell(2, 4).bg, Some(Color::Index(1)));
+
+        let mut s = TestScreen::new(1, 8);
+        s.feed(b"abcdefgh\x1b[1;3H\x1b[2X\x1b[2P\x1b[1@");
+        s.assert_snapshot("ab efgh");
+    }
+
+    #[test]
+    fn scrolling() {
+        let mut s = TestScreen::new(3, 5);
+        s.feed(b"1\r\n2\r\n3\r\n4\r\n5");
+        s.assert_snapshot("3\n4\n5");
+        assert_eq!(s.scrollback(), vec!["1", "2"]);
+
+        // a scroll region only scrolls the lines inside it
+        let mut s = TestScreen::new(4, 5);
+        s.feed(b"top\x1b[2;3r\x1b[2;1Ha\r\nb\r\nc\x1b[4;1Hend");
+        s.assert_snapshot("top\nb\nc\nend");
+        assert!(s.scrollback().is_empty());
+
+        s.feed(b"\x1b[2;1H\x1bMx");
+        s.assert_snapshot("top\nx\nb\nend");
+
+        s.feed(b"\x1b[2;1H\x1b[L");
+        s.assert_snapshot("top\n\nx\nend");
+        s.feed(b"\x1b[M\x1b[M");
+        s.assert_snapshot("top\n\n\nend");
+    }
+
+    #[test]
+    fn line_drawing() {
+        let mut s = TestScreen::new(1, 10);
+        s.feed(b"\x1b)0\x0elqk\x0f lqk");
+        s.assert_snapshot("┌─┐ lqk");
+    }
+
+    #[test]
+    fn repeat() {
+        let mut s = TestScreen::new(1, 10);
+        s.feed(b"-\x1b[4b|");
+        s.assert_snapshot("-----|");
+
+        // counts that would take forever to carry out fill the screen, or the line, instead
+        let mut s = TestScreen::new(2, 4);
+        s.feed(b"x\x1b[99999999999b");
+        s.assert_snapshot("xxxx\nx");
+        let mut s = TestScreen::new(1, 20);
+        s.feed(b"\x1b[99999999999I\x08a\x1b[99999999999Zb");
+        s.assert_snapshot("b                 a");
+    }
+
+    #[test]
+    #[should_panic(expected = "screen doesn't match snapshot")]
+    fn snapshot_mismatch() {
+        let mut s = TestScreen::new(2, 10);
+        s.feed(b"hello");
+        s.assert_snapshot("goodbye");
+    }
+
+    #[test]
+    fn vim() {
+        let mut s = TestScreen::new(10, 40);
+        s.feed(b"shell prompt\r\n");
+
+        // everything up to vim leaving the alternate screen
+        let exit = VIM.windows(8).position(|w| w == b"\x1b[?1049l").unwrap();
+        s.feed(&VIM[..exit]);
+
+        assert!(s.is_alternate());
+        s.assert_snapshot(
+            "hello world
+second line
+~
+~
+~
+~
+~
+~
+~",
+        );
+        assert_eq!(s.cell(2, 0).fg, Some(Color::Index(12)));
+        assert_eq!(s.cell(0, 0).fg, None);
+        assert_eq!(s.cursor(), (9, 0));
+
+        s.feed(&VIM[exit..]);
+        assert!(!s.is_alternate());
+        s.assert_snapshot("shell prompt");
+        assert!(s.cursor_visible());
+    }
+
+    #[test]
+    fn top() {
+        let mut s = TestScreen::new(8, 60);
+        s.feed(TOP);
+
+        // top moves to the line below the screen when it exits, which scrolls its first line away
+        assert_eq!(s.scrollback().len(), 1);
+        assert!(s.scrollback()[0].starts_with("top - 11:30:03 up 11 min,  0 user,  load average:"));
+        assert!(s
+            .row_text(0)
+            .starts_with("Tasks:  59 total,   1 running,  58 sleeping,"));
+        assert!(s.row_text(1).starts_with("%Cpu(s):  0.0 us,"));
+        assert_eq!(s.row_text(4), "");
+
+        // the process table's header is in reverse video, the rest isn't
+        assert!(s
+            .row_text(5)
+            .starts_with("  PID USER      PR  NI    VIRT    RES    SHR S  %CPU  %MEM"));
+        assert!(s.cell(5, 0).attrs.reverse);
+        assert!(!s.cell(6, 0).attrs.reverse);
+        assert!(s.row_text(6).starts_with("14234 root"));
+        // the values in the summary are bold
+        assert!(!s.cell(0, 0).attrs.bold);
+        assert!(s.cell(0, 8).attrs.bold);
+        assert_eq!(s.cursor(), (7, 0));
+    }
+
+    #[test]
+    fn resize() {
+        let mut s = TestScreen::new(3, 6);
+        s.feed(b"one\r\ntwo\r\nthree!");
+        s.resize(2, 4);
+        // the cursor was on the last line, so the first one scrolls off
+        s.assert_snapshot("two\nthre");
+        assert_eq!(s.scrollback(), ["one"]);
+        assert_eq!(s.cursor(), (1, 3));
+
+        s.resize(3, 8);
+        s.feed(b"\r\n\tx");
+        s.assert_snapshot("two\nthre\n       x");
+        assert_eq!((s.rows(), s.cols()), (3, 8));
+    }
+
+    #[test]
+    fn replay() {
+        use record::RecordFormat;
+
+        let mut frames = Vec::new();
+        let s = TestScreen::replay_frames(CAST, RecordFormat::Asciicast, |time, s| {
+            frames.push((time, s.rows(), s.cols()))
+        })
+        .unwrap();
+        assert_eq!(
+            frames,
+            [
+                (0.1, 4, 20),
+                (0.2, 4, 20),
+                (0.3, 4, 20),
+                (0.5, 3, 10),
+                (0.6, 3, 10),
+            ]
+        );
+        assert!(s.is_alternate());
+        s.assert_snapshot("red title\nline two,\nbottom");
+        assert_eq!(s.cell(0, 0).fg, Some(Color::Index(1)));
+        assert!(s.cell(0, 2).attrs.bold);
+        assert!(!s.cell(0, 4).attrs.bold);
+        assert_eq!(s.cursor(), (2, 6));
+
+        // a raw recording is fed as it is
+        let s = TestScreen::replay(VIM, RecordFormat::Raw).unwrap();
+        let mut fed = TestScreen::new(24, 80);
+        fed.feed(VIM);
+        assert_eq!(s.contents(), fed.contents());
+        assert_eq!(s.cursor(), fed.cursor());
+
+        let err = TestScreen::replay(&b"not json"[..], RecordFormat::Asciicast).err();
+        assert!(err.unwrap().is_parse());
+    }
+}
@@ -0,0 +1,277 @@
+//! Putting together control sequences (CSI), operating system commands (OSC) and device control strings (DCS), and
+//! checking they're well formed on the way.
+//!
+//! A terminal can't tell where a malformed sequence ends, so it might show part of it as text, or take text after it
+//! as part of it. A payload with its own terminator in it is worse: whatever comes after the terminator is carried
+//! out as if it had been sent on purpose. So these fail with a `SequenceError` rather than writing anything odd.
+use errors::*;
+use std::io::Write;
+
+/// The most parameters a sequence can have, the VT500 series drops any after the 16th.
+pub const MAX_PARAMS: usize = 16;
+
+/// What ends an OSC sequence.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum OscTerminator {
+    /// `ESC \`, the string terminator ECMA-48 defines.
+    St,
+    /// `BEL`, which xterm accepted first and some programs still expect.
+    Bel,
+}
+
+/// Write `params` separated by `;`, with `intermediates` and `final_byte` after them.
+fn write_params(
+    out: &mut Vec<u8>,
+    params: &[u16],
+    intermediates: &[u8],
+    final_byte: u8,
+) -> Result<()> {
+    if params.len() > MAX_PARAMS {
+        return Err(SequenceError::TooManyParameters(params.len()).into());
+    }
+    if let Some(&c) = intermediates.iter().find(|&&c| !(0x20..=0x2f).contains(&c)) {
+        return Err(SequenceError::InvalidIntermediate(c).into());
+    }
+    if !(0x40..=0x7e).contains(&final_byte) {
+        return Err(SequenceError::InvalidFinalByte(final_byte).into());
+    }
+    for (i, param) in params.iter().enumerate() {
+        if i > 0 {
+            out.push(b';');
+        }
+        let _ = write!(out, "{}", param);
+    }
+    out.extend_from_slice(intermediates);
+    out.push(final_byte);
+    Ok(())
+}
+
+/// Check there's nothing in `payload` that would end the string early.
+///
+/// That's any C0 or C1 control when `text` is set (OSC payloads are text), or otherwise `ESC`, the C1 controls, and
+/// `CAN` and `SUB` which cancel the string. C1 controls are checked for both as raw bytes and, when the payload is
+/// UTF-8, as characters.
+fn check_payload(payload: &[u8], text: bool) -> Result<()> {
+    let bad = match ::std::str::from_utf8(payload) {
+        Ok(s) => s.chars().find(|&c| match c {
+            '\x1b' | '\x18' | '\x1a' | '\u{7f}'..='\u{9f}' => true,
+            c => text && c.is_control(),
+        }),
+        Err(_) => payload
+            .iter()
+            .find(|&&c| match c {
+                0x1b | 0x18 | 0x1a | 0x7f..=0x9f => true,
+                c => text && c < 0x20,
+            })
+            .map(|&c| char::from(c)),
+    };
+    match bad {
+        Some(c) => Err(SequenceError::ControlInPayload(c).into()),
+        None => Ok(()),
+    }
+}
+
+/// The control sequence `CSI <params> <intermediates> <final_byte>`.
+///
+/// Intermediates have to be between 0x20 and 0x2f, and the final byte between 0x40 and 0x7e.
+pub fn csi(params: &[u16], intermediates: &[u8], final_byte: u8) -> Result<Vec<u8>> {
+    let mut out = b"\x1b[".to_vec();
+    write_params(&mut out, params, intermediates, final_byte)?;
+    Ok(out)
+}
+
+/// A control sequence whose parameters have sub-parameters, separated by `:` like ITU T.416 says, as in the curly
+/// underline `CSI 4 : 3 m`. Each of `params` is one parameter, with its sub-parameters after it.
+pub fn csi_sub(params: &[&[u16]], intermediates: &[u8], final_byte: u8) -> Result<Vec<u8>> {
+    if params.len() > MAX_PARAMS {
+        return Err(SequenceError::TooManyParameters(params.len()).into());
+    }
+    let mut out = b"\x1b[".to_vec();
+    for (i, param) in params.iter().enumerate() {
+        if i > 0 {
+            out.push(b';');
+        }
+        for (j, sub) in param.iter().enumerate() {
+            if j > 0 {
+                out.push(b':');
+            }
+            let _ = write!(out, "{}", sub);
+        }
+    }
+    // just the intermediates and final byte, checked like `csi` checks them
+    write_params(&mut out, &[], intermediates, final_byte)?;
+    Ok(out)
+}
+
+/// A control sequence with a private marker (one of `<`, `=`, `>` or `?`) before its parameters, like the DEC
+/// private modes' `CSI ? <mode> h`.
+pub fn csi_private(
+    marker: u8,
+    params: &[u16],
+    intermediates: &[u8],
+    final_byte: u8,
+) -> Result<Vec<u8>> {
+    if !(0x3c..=0x3f).contains(&marker) {
+        return Err(SequenceError::InvalidPrivateMarker(marker).into());
+    }
+    let mut out = vec![0x1b, b'[', marker];
+    write_params(&mut out, params, intermediates, final_byte)?;
+    Ok(out)
+}
+
+/// The operating system command `OSC <number> ; <payload>`, ended by `terminator`.
+///
+/// The payload can't have any control characters in it, so text from somewhere else can't end the sequence early.
+/// The `;` is there even when the payload is empty, xterm ignores `OSC 2` without one rather than clearing the title.
+pub fn osc(number: u16, payload: &[u8], terminator: OscTerminator) -> Result<Vec<u8>> {
+    check_payload(payload, true)?;
+    let mut out = Vec::with_capacity(payload.len() + 10);
+    let _ = write!(out, "\x1b]{};", number);
+    out.extend_from_slice(payload);
+    out.extend_from_slice(match terminator {
+        OscTerminator::St => b"\x1b\\",
+        OscTerminator::Bel => b"\x07",
+    });
+    Ok(out)
+}
+
+/// The device control string `DCS <params> <intermediates> <final_byte> <payload> ST`.
+///
+/// The payload can't have `ESC`, `CAN`, `SUB` or a C1 control in it.
+pub fn dcs(
+    params: &[u16],
+    intermediates: &[u8],
+    final_byte: u8,
+    payload: &[u8],
+) -> Result<Vec<u8>> {
+    check_payload(payload, false)?;
+    let mut out = Vec::with_capacity(payload.len() + 16);
+    out.extend_from_slice(b"\x1bP");
+    write_params(&mut out, params, intermediates, final_byte)?;
+    out.extend_from_slice(payload);
+    out.extend_from_slice(b"\x1b\\");
+    Ok(out)
+}
+
+/// `n` as a parameter, if it fits.
+pub(crate) fn param(n: usize) -> Result<u16> {
+    if n > usize::from(u16::MAX) {
+        return Err(SequenceError::ParameterOutOfRange(n).into());
+    }
+    Ok(n as u16)
+}
+
+#[cfg(test)]
+mod test {
+    use ansi::sequence::*;
+
+    fn error<T: ::std::fmt::Debug>(r: Result<T>) -> SequenceError {
+        match r {
+            Err(Error::InvalidSequence(e)) => e,
+            r => panic!("expected a SequenceError, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn control_sequences() {
+        assert_eq!(csi(&[], b"", b'H').unwrap(), b"\x1b[H");
+        assert_eq!(csi(&[0, 65535], b"", b'r').unwrap(), b"\x1b[0;65535r");
+        assert_eq!(csi(&[2], b" ", b'q').unwrap(), b"\x1b[2 q");
+        assert_eq!(csi(&[1; 16], b"", b'm').unwrap().len(), 2 + 31 + 1);
+        assert_eq!(csi_sub(&[&[4, 3]], b"", b'm').unwrap(), b"\x1b[4:3m");
+        assert_eq!(csi_sub(&[&[1], &[58, 2, 0, 9]], b"", b'm').unwrap(), b"\x1b[1;58:2:0:9m");
+        assert!(csi_sub(&[&[1u16][..]; 17], b"", b'm').is_err());
+        assert_eq!(
+            csi_private(b'?', &[1049], b"", b'h').unwrap(),
+            b"\x1b[?1049h"
+        );
+        assert_eq!(
+            csi_private(b'?', &[2026], b"$", b'p').unwrap(),
+            b"\x1b[?2026$p"
+        );
+        assert_eq!(csi_private(b'>', &[], b"", b'c').unwrap(), b"\x1b[>c");
+
+        // the edges of the final byte's range
+        assert!(csi(&[], b"", 0x40).is_ok());
+        assert!(csi(&[], b"", 0x7e).is_ok());
+        assert_eq!(
+            error(csi(&[], b"", 0x3f)),
+            SequenceError::InvalidFinalByte(0x3f)
+        );
+        assert_eq!(
+            error(csi(&[], b"", 0x7f)),
+            SequenceError::InvalidFinalByte(0x7f)
+        );
+        assert_eq!(
+            error(csi(&[1], b"0", b'm')),
+            SequenceError::InvalidIntermediate(b'0')
+        );
+        assert_eq!(
+            error(csi(&[1; 17], b"", b'm')),
+            SequenceError::TooManyParameters(17)
+        );
+        assert_eq!(
+            error(csi_private(b';', &[1], b"", b'h')),
+            SequenceError::InvalidPrivateMarker(b';')
+        );
+        assert_eq!(param(65535).unwrap(), 65535);
+        assert_eq!(
+            error(param(65536)),
+            SequenceError::ParameterOutOfRange(65536)
+        );
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(
+            osc(0, b"title", OscTerminator::St).unwrap(),
+            b"\x1b]0;title\x1b\\"
+        );
+        assert_eq!(
+            osc(2, "caf\u{e9}".as_bytes(), OscTerminator::Bel).unwrap(),
+            &b"\x1b]2;caf\xc3\xa9\x07"[..]
+        );
+        assert_eq!(osc(104, b"", OscTerminator::St).unwrap(), b"\x1b]104;\x1b\\");
+        assert_eq!(
+            dcs(&[0, 1, 0], b"", b'q', b"#0~-").unwrap(),
+            b"\x1bP0;1;0q#0~-\x1b\\"
+        );
+        assert_eq!(dcs(&[], b"+", b'q', b"544e").unwrap(), b"\x1bP+q544e\x1b\\");
+    }
+
+    #[test]
+    fn injection() {
+        // a terminator in the payload would end the string, and run whatever follows
+        let attacks: &[&[u8]] = &[
+            b"a\x1b\\\x1b]0;pwned",
+            b"a\x07\x1b[2J",
+            b"a\x9c\x1b[2J",
+            "a\u{9c}b".as_bytes(),
+            b"a\x18b",
+        ];
+        for &payload in attacks {
+            assert!(osc(0, payload, OscTerminator::St).is_err(), "{:?}", payload);
+            assert!(dcs(&[], b"", b'q', payload).is_err(), "{:?}", payload);
+        }
+        assert_eq!(
+            error(osc(0, b"a\x1b\\b", OscTerminator::Bel)),
+            SequenceError::ControlInPayload('\x1b')
+        );
+        assert_eq!(
+            error(osc(0, "a\u{9c}".as_bytes(), OscTerminator::St)),
+            SequenceError::ControlInPayload('\u{9c}')
+        );
+        assert_eq!(
+            error(osc(0, b"line\nline", OscTerminator::St)),
+            SequenceError::ControlInPayload('\n')
+        );
+
+        // other C0 controls are fine in a DCS, and so is UTF-8 that merely has 0x9c in it
+        assert!(dcs(&[], b"", b'q', b"a\nb").is_ok());
+        assert!(osc(0, "\u{71c}".as_bytes(), OscTerminator::St).is_ok());
+        assert_eq!(
+            error(dcs(&[], b"", b'q', b"\xff\x9c")),
+            SequenceError::ControlInPayload('\u{9c}')
+        );
+    }
+}
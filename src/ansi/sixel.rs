@@ -0,0 +1,370 @@
+//! Sixel graphics.
+//!
+//! A sixel image is a DCS string (`ESC P <params> q ... ESC \`) that paints the image in bands six pixels tall.
+//! Each band is drawn once per color: `#n` picks a color register, and each character from `?` to `~` then sets
+//! the pixels of one column that have that color, as a 6 bit mask offset by 63. `$` returns to the start of the
+//! band to draw the next color, and `-` moves down to the next band.
+use ansi::{dcs, Image, PixelFormat};
+use std::collections::HashMap;
+use std::io::Write;
+
+/// The most color registers an image can use.
+pub const MAX_COLORS: usize = 256;
+
+type Rgb = [u8; 3];
+
+/// The image's pixels, with `None` for the ones that are mostly transparent.
+fn pixels(image: &Image) -> Vec<Option<Rgb>> {
+    let bpp = image.format().bytes_per_pixel();
+    image
+        .data()
+        .chunks(bpp)
+        .map(|p| match image.format() {
+            PixelFormat::Rgba if p[3] < 128 => None,
+            _ => Some([p[0], p[1], p[2]]),
+        })
+        .collect()
+}
+
+/// Pick at most `max_colors` colors to represent `pixels`, using median cut.
+fn quantize(pixels: &[Option<Rgb>], max_colors: usize) -> Vec<Rgb> {
+    let mut histogram = HashMap::new();
+    for p in pixels.iter().filter_map(|&p| p) {
+        *histogram.entry(p).or_insert(0u64) += 1;
+    }
+    let mut colors: Vec<(Rgb, u64)> = histogram.into_iter().collect();
+    colors.sort();
+    if colors.len() <= max_colors {
+        return colors.into_iter().map(|(c, _)| c).collect();
+    }
+
+    let mut boxes = vec![colors];
+    while boxes.len() < max_colors {
+        // split the box that's widest along any channel
+        let widest = boxes
+            .iter()
+            .enumerate()
+            .filter(|&(_, b)| b.len() > 1)
+            .map(|(i, b)| {
+                let (channel, range) = widest_channel(b);
+                (range, i, channel)
+            })
+            .max();
+        let (i, channel) = match widest {
+            Some((_, i, channel)) => (i, channel),
+            None => break,
+        };
+
+        let mut b = boxes.swap_remove(i);
+        b.sort_by_key(|&(c, _)| c[channel]);
+        let total: u64 = b.iter().map(|&(_, n)| n).sum();
+        let mut seen = 0;
+        let mut split = b.len() - 1;
+        for (j, &(_, n)) in b.iter().enumerate() {
+            seen += n;
+            if seen * 2 >= total {
+                split = j + 1;
+                break;
+            }
+        }
+        let split = split.clamp(1, b.len() - 1);
+        let rest = b.split_off(split);
+        boxes.push(b);
+        boxes.push(rest);
+    }
+
+    let mut palette: Vec<Rgb> = boxes.iter().map(|b| average(b)).collect();
+    palette.sort();
+    palette
+}
+
+/// The channel with the largest spread of values in `colors`, and that spread.
+fn widest_channel(colors: &[(Rgb, u64)]) -> (usize, u8) {
+    (0..3)
+        .map(|ch| {
+            let min = colors.iter().map(|&(c, _)| c[ch]).min().unwrap_or(0);
+            let max = colors.iter().map(|&(c, _)| c[ch]).max().unwrap_or(0);
+            (ch, max - min)
+        })
+        .max_by_key(|&(ch, range)| (range, 2 - ch))
+        .unwrap()
+}
+
+/// The average of `colors`, weighted by how many pixels have each one.
+fn average(colors: &[(Rgb, u64)]) -> Rgb {
+    let total: u64 = colors.iter().map(|&(_, n)| n).sum();
+    let mut avg = [0u8; 3];
+    for (ch, v) in avg.iter_mut().enumerate() {
+        let sum: u64 = colors.iter().map(|&(c, n)| u64::from(c[ch]) * n).sum();
+        *v = ((sum + total / 2) / total) as u8;
+    }
+    avg
+}
+
+/// The index of the color in `palette` closest to `c`.
+fn nearest(palette: &[Rgb], c: Rgb) -> usize {
+    let distance = |p: &Rgb| -> i32 {
+        (0..3)
+            .map(|ch| {
+                let d = i32::from(p[ch]) - i32::from(c[ch]);
+                d * d
+            })
+            .sum()
+    };
+    (0..palette.len())
+        .min_by_key(|&i| distance(&palette[i]))
+        .unwrap()
+}
+
+/// Scale a channel from 0-255 to the 0-100 that sixel color definitions use.
+fn percent(v: u8) -> u32 {
+    (u32::from(v) * 100 + 127) / 255
+}
+
+/// Write a band's worth of sixels for one color, run length encoded.
+fn write_run(out: &mut Vec<u8>, sixel: u8, count: usize) {
+    let c = sixel + 63;
+    if count > 3 {
+        let _ = write!(out, "!{}", count);
+        out.push(c);
+    } else {
+        out.extend((0..count).map(|_| c));
+    }
+}
+
+/// Encode `image` as sixels, using at most `max_colors` color registers (clamped to 1-256).
+///
+/// Pixels with an alpha below 50% are left transparent.
+pub fn encode(image: &Image, max_colors: usize) -> Vec<u8> {
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    let pixels = pixels(image);
+    let palette = quantize(&pixels, max_colors.clamp(1, MAX_COLORS));
+
+    let mut cache = HashMap::new();
+    let indices: Vec<Option<usize>> = pixels
+        .iter()
+        .map(|p| p.map(|c| *cache.entry(c).or_insert_with(|| nearest(&palette, c))))
+        .collect();
+
+    let mut out = Vec::new();
+    // use square pixels (the raster attributes' 1;1)
+    let _ = write!(out, "\"1;1;{};{}", width, height);
+    for (i, c) in palette.iter().enumerate() {
+        let _ = write!(
+            out,
+            "#{};2;{};{};{}",
+            i,
+            percent(c[0]),
+            percent(c[1]),
+            percent(c[2])
+        );
+    }
+
+    let mut row = vec![0u8; width];
+    for top in (0..height).step_by(6) {
+        let bottom = (top + 6).min(height);
+        let mut used: Vec<usize> = (top..bottom)
+            .flat_map(|y| {
+                indices[y * width..(y + 1) * width]
+                    .iter()
+                    .filter_map(|&i| i)
+            })
+            .collect();
+        used.sort();
+        used.dedup();
+
+        for (n, &color) in used.iter().enumerate() {
+            for (x, sixel) in row.iter_mut().enumerate() {
+                *sixel = (top..bottom)
+                    .filter(|&y| indices[y * width + x] == Some(color))
+                    .fold(0, |bits, y| bits | 1 << (y - top));
+            }
+            // trailing empty columns don't need to be drawn
+            let len = row.iter().rposition(|&s| s != 0).map_or(0, |i| i + 1);
+
+            if n > 0 {
+                out.push(b'$');
+            }
+            let _ = write!(out, "#{}", color);
+            let mut x = 0;
+            while x < len {
+                let count = row[x..len].iter().take_while(|&&s| s == row[x]).count();
+                write_run(&mut out, row[x], count);
+                x += count;
+            }
+        }
+        if bottom < height {
+            out.push(b'-');
+        }
+    }
+
+    // leave unpainted pixels alone
+    dcs(&[0, 1, 0], b"", b'q', &out).expect("sixel data is only printable characters")
+}
+
+#[cfg(test)]
+mod test {
+    use ansi::sixel::*;
+
+    /// A small sixel decoder, returning the image's size and pixels.
+    fn decode(data: &[u8]) -> (usize, usize, Vec<Option<Rgb>>) {
+        assert!(data.starts_with(b"\x1bP"));
+        assert!(data.ends_with(b"\x1b\\"));
+        let start = data.iter().position(|&c| c == b'q').unwrap() + 1;
+        let body = &data[start..data.len() - 2];
+
+        let mut i = 0;
+        let number = |i: &mut usize| {
+            let start = *i;
+            while *i < body.len() && body[*i].is_ascii_digit() {
+                *i += 1;
+            }
+            ::std::str::from_utf8(&body[start..*i])
+                .unwrap()
+                .parse::<usize>()
+                .unwrap()
+        };
+        let numbers = |i: &mut usize| {
+            let mut v = vec![number(i)];
+            while *i < body.len() && body[*i] == b';' {
+                *i += 1;
+                v.push(number(i));
+            }
+            v
+        };
+
+        let (mut width, mut height) = (0, 0);
+        let mut pixels: Vec<Option<Rgb>> = Vec::new();
+        let mut palette: HashMap<usize, Rgb> = HashMap::new();
+        let (mut color, mut x, mut top) = (0, 0, 0);
+        while i < body.len() {
+            let c = body[i];
+            i += 1;
+            match c {
+                b'"' => {
+                    let v = numbers(&mut i);
+                    width = v[2];
+                    height = v[3];
+                    pixels = vec![None; width * height];
+                }
+                b'#' => {
+                    let v = numbers(&mut i);
+                    if v.len() == 5 {
+                        assert_eq!(v[1], 2);
+                        let scale = |p: usize| (p * 255 / 100) as u8;
+                        palette.insert(v[0], [scale(v[2]), scale(v[3]), scale(v[4])]);
+                    } else {
+                        color = v[0];
+                    }
+                }
+                b'$' => x = 0,
+                b'-' => {
+                    x = 0;
+                    top += 6;
+                }
+                b'!' | b'?'..=b'~' => {
+                    let (count, sixel) = if c == b'!' {
+                        let n = number(&mut i);
+                        i += 1;
+                        (n, body[i - 1])
+                    } else {
+                        (1, c)
+                    };
+                    for _ in 0..count {
+                        for bit in 0..6 {
+                            if (sixel - 63) & (1 << bit) != 0 {
+                                pixels[(top + bit) * width + x] = Some(palette[&color]);
+                            }
+                        }
+                        x += 1;
+                    }
+                }
+                _ => panic!("unexpected {:?}", c as char),
+            }
+        }
+        (width, height, pixels)
+    }
+
+    fn close(a: Rgb, b: Rgb, tolerance: i32) -> bool {
+        (0..3).all(|ch| (i32::from(a[ch]) - i32::from(b[ch])).abs() <= tolerance)
+    }
+
+    #[test]
+    fn few_colors() {
+        // a 4x8 image, so there's a second, partial band
+        let colors = [[255, 0, 0], [0, 128, 255], [255, 255, 255]];
+        let data: Vec<u8> = (0..32)
+            .flat_map(|i| colors[(i / 3 + i % 4) % 3].to_vec())
+            .collect();
+        let image = Image::new(4, 8, PixelFormat::Rgb, data.clone()).unwrap();
+        let out = encode(&image, 256);
+
+        assert!(out.starts_with(b"\x1bP0;1;0q\"1;1;4;8#0;2;0;50;100#1;2;100;0;0#2;2;100;100;100"));
+        let (width, height, pixels) = decode(&out);
+        assert_eq!((width, height), (4, 8));
+        for (p, src) in pixels.iter().zip(data.chunks(3)) {
+            assert!(close(p.unwrap(), [src[0], src[1], src[2]], 2));
+        }
+    }
+
+    #[test]
+    fn run_length() {
+        let image = Image::new(10, 1, PixelFormat::Rgb, vec![0; 30]).unwrap();
+        assert_eq!(
+            encode(&image, 256),
+            &b"\x1bP0;1;0q\"1;1;10;1#0;2;0;0;0#0!10@\x1b\\"[..]
+        );
+
+        let image = Image::new(3, 6, PixelFormat::Rgb, vec![9; 54]).unwrap();
+        assert_eq!(
+            encode(&image, 256),
+            &b"\x1bP0;1;0q\"1;1;3;6#0;2;4;4;4#0~~~\x1b\\"[..]
+        );
+    }
+
+    #[test]
+    fn transparency() {
+        let data = vec![255, 0, 0, 255, 0, 0, 0, 0, 0, 255, 0, 255];
+        let image = Image::new(3, 1, PixelFormat::Rgba, data).unwrap();
+        let (_, _, pixels) = decode(&encode(&image, 256));
+        assert_eq!(pixels, vec![Some([255, 0, 0]), None, Some([0, 255, 0])]);
+    }
+
+    #[test]
+    fn quantized() {
+        // a gradient with far more colors than registers
+        let (width, height) = (64, 20);
+        let data: Vec<u8> = (0..width * height)
+            .flat_map(|i| {
+                let (x, y) = (i % width, i / width);
+                vec![(x * 4) as u8, (y * 12) as u8, 128]
+            })
+            .collect();
+        let image =
+            Image::new(width as u32, height as u32, PixelFormat::Rgb, data.clone()).unwrap();
+
+        for &max in &[2, 16, 64] {
+            let out = encode(&image, max);
+            let registers = (0..MAX_COLORS)
+                .filter(|i| {
+                    let def = format!("#{};2;", i);
+                    out.windows(def.len()).any(|w| w == def.as_bytes())
+                })
+                .count();
+            assert_eq!(registers, max);
+
+            let (_, _, pixels) = decode(&out);
+            let tolerance = match max {
+                2 => 128,
+                16 => 40,
+                _ => 24,
+            };
+            for (p, src) in pixels.iter().zip(data.chunks(3)) {
+                assert!(close(p.unwrap(), [src[0], src[1], src[2]], tolerance));
+            }
+        }
+
+        // there's always at least one register
+        assert!(decode(&encode(&image, 0)).2.iter().all(|p| p.is_some()));
+    }
+}
@@ -0,0 +1,242 @@
+//! Removing escape sequences from terminal output.
+use std::io;
+use std::io::Write;
+
+const BEL: u8 = 0x07;
+const CAN: u8 = 0x18;
+const SUB: u8 = 0x1a;
+const ESC: u8 = 0x1b;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum State {
+    Ground,
+    /// Just read an `ESC`.
+    Escape,
+    /// Inside an `ESC <intermediate>... <final>` sequence, such as `ESC ( B`.
+    EscapeIntermediate,
+    /// Inside a CSI sequence, waiting for the final byte.
+    Csi,
+    /// Inside an OSC string, waiting for `BEL` or `ST`.
+    Osc,
+    /// Inside a DCS, SOS, PM or APC string, waiting for `ST`.
+    Str,
+    /// Read an `ESC` inside a string, if the next byte is `\` it was `ST`.
+    StrEscape,
+}
+
+/// Tracks whether a stream of bytes is inside an escape sequence.
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct Scanner {
+    state: State,
+}
+
+impl Scanner {
+    pub fn new() -> Scanner {
+        Scanner {
+            state: State::Ground,
+        }
+    }
+
+    pub fn in_sequence(&self) -> bool {
+        self.state != State::Ground
+    }
+
+    /// Advance the state machine by one byte, returns true if the byte is text that should be kept.
+    pub fn advance(&mut self, c: u8) -> bool {
+        match self.state {
+            State::Ground => match c {
+                ESC => self.state = State::Escape,
+                b'\t' | b'\n' | b'\r' => return true,
+                0x00..=0x1f | 0x7f => (),
+                _ => return true,
+            },
+            State::Escape => self.escape(c),
+            State::EscapeIntermediate => match c {
+                ESC => self.state = State::Escape,
+                CAN | SUB => self.state = State::Ground,
+                0x20..=0x2f => (),
+                0x30..=0x7e => self.state = State::Ground,
+                _ => (),
+            },
+            State::Csi => match c {
+                ESC => self.state = State::Escape,
+                CAN | SUB => self.state = State::Ground,
+                0x40..=0x7e => self.state = State::Ground,
+                // parameter and intermediate bytes, or stray control characters
+                _ => (),
+            },
+            State::Osc => match c {
+                BEL | CAN | SUB => self.state = State::Ground,
+                ESC => self.state = State::StrEscape,
+                _ => (),
+            },
+            State::Str => match c {
+                CAN | SUB => self.state = State::Ground,
+                ESC => self.state = State::StrEscape,
+                _ => (),
+            },
+            State::StrEscape => match c {
+                b'\\' => self.state = State::Ground,
+                // the string was cut off by a new escape sequence
+                _ => self.escape(c),
+            },
+        }
+        false
+    }
+
+    /// Handle the byte following an `ESC`.
+    fn escape(&mut self, c: u8) {
+        self.state = match c {
+            b'[' => State::Csi,
+            b']' => State::Osc,
+            b'P' | b'X' | b'^' | b'_' => State::Str,
+            ESC => State::Escape,
+            0x20..=0x2f => State::EscapeIntermediate,
+            // a lone ESC + byte sequence, or a cancelled one
+            _ => State::Ground,
+        }
+    }
+}
+
+/// A writer that forwards only the printable text written to it, dropping any escape sequences.
+///
+/// Sequences may be split across several calls to `write`, the stripper remembers where it was.
+/// Tabs, newlines and carriage returns are kept, every other control character is dropped.
+pub struct Stripper<W: Write> {
+    inner: W,
+    scanner: Scanner,
+}
+
+impl<W: Write> Stripper<W> {
+    pub fn new(inner: W) -> Stripper<W> {
+        Stripper {
+            inner,
+            scanner: Scanner::new(),
+        }
+    }
+
+    /// True if the stripper is in the middle of an escape sequence.
+    pub fn in_sequence(&self) -> bool {
+        self.scanner.in_sequence()
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Get the underlying writer back, an unfinished escape sequence is discarded.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write> Write for Stripper<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut start = 0;
+
+        for (i, &c) in buf.iter().enumerate() {
+            if !self.scanner.advance(c) {
+                if start < i {
+                    self.inner.write_all(&buf[start..i])?;
+                }
+                start = i + 1;
+            }
+        }
+
+        if start < buf.len() {
+            self.inner.write_all(&buf[start..])?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Remove all escape sequences from `input`, leaving only the printable text.
+///
+/// An escape sequence cut off at the end of `input` is dropped.
+pub fn strip(input: &[u8]) -> Vec<u8> {
+    let mut stripper = Stripper::new(Vec::with_capacity(input.len()));
+    // writing to a Vec can't fail
+    stripper.write_all(input).unwrap();
+    stripper.into_inner()
+}
+
+#[cfg(test)]
+mod test {
+    use ansi::strip::*;
+    use std::io::Write;
+
+    #[test]
+    fn sgr() {
+        assert_eq!(strip(b"\x1b[1;31mred\x1b[0m text"), b"red text");
+        assert_eq!(strip(b"\x1b[38;2;255;0;0mrgb\x1b[m"), b"rgb");
+        assert_eq!(strip(b"plain\ttext\r\n"), b"plain\ttext\r\n");
+        assert_eq!(
+            strip("\x1b[4mün\x1b[24micode".as_bytes()),
+            "ünicode".as_bytes()
+        );
+    }
+
+    #[test]
+    fn osc() {
+        // OSC 8 hyperlinks, terminated by ST and BEL
+        assert_eq!(
+            strip(b"\x1b]8;;https://example.com\x1b\\link\x1b]8;;\x1b\\"),
+            b"link"
+        );
+        assert_eq!(
+            strip(b"\x1b]8;;https://example.com\x07link\x1b]8;;\x07"),
+            b"link"
+        );
+        assert_eq!(strip(b"\x1b]0;title\x07text"), b"text");
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(strip(b"a\x1bPq#0;2;0;0;0\x1b\\b"), b"ab");
+        assert_eq!(strip(b"a\x1b_Gf=24;AAAA\x1b\\b"), b"ab");
+        assert_eq!(strip(b"a\x1bXsos\x1b\\b\x1b^pm\x1b\\c"), b"abc");
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(strip(b"\x1b7saved\x1b8"), b"saved");
+        assert_eq!(strip(b"\x1b(0qqq\x1b(B"), b"qqq");
+        assert_eq!(strip(b"\x1bHtab"), b"tab");
+        // CAN aborts a sequence
+        assert_eq!(strip(b"\x1b[12\x18abc"), b"abc");
+        assert_eq!(strip(b"a\x07b\x08c"), b"abc");
+    }
+
+    #[test]
+    fn split_writes() {
+        let input = b"\x1b[1;31mred\x1b[0m \x1b]8;;http://a\x1b\\link\x1b]8;;\x1b\\";
+        for split in 0..input.len() {
+            let mut stripper = Stripper::new(Vec::new());
+            stripper.write_all(&input[..split]).unwrap();
+            stripper.write_all(&input[split..]).unwrap();
+            assert_eq!(stripper.into_inner(), b"red link", "split at {}", split);
+        }
+    }
+
+    #[test]
+    fn truncated() {
+        assert_eq!(strip(b"text\x1b[38;5"), b"text");
+        assert_eq!(strip(b"text\x1b]8;;http://"), b"text");
+        assert_eq!(strip(b"text\x1b"), b"text");
+
+        let mut stripper = Stripper::new(Vec::new());
+        stripper.write_all(b"abc\x1b[3").unwrap();
+        assert!(stripper.in_sequence());
+        stripper.write_all(b"1mdef").unwrap();
+        assert!(!stripper.in_sequence());
+        assert_eq!(stripper.into_inner(), b"abcdef");
+    }
+}
@@ -0,0 +1,241 @@
+use ansi::{Color, ColorDepth};
+use errors::*;
+use std::fmt::Write as FmtWrite;
+use std::io::Write;
+
+/// The colors and attributes text is drawn with.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub struct Style {
+    pub foreground: Option<Color>,
+    pub background: Option<Color>,
+    pub bold: bool,
+    pub dim: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub blink: bool,
+    pub invert: bool,
+    pub invisible: bool,
+    pub strike: bool,
+}
+
+impl Style {
+    pub fn new() -> Style {
+        Style::default()
+    }
+
+    pub fn foreground<T: Into<Color>>(mut self, color: T) -> Self {
+        self.foreground = Some(color.into());
+        self
+    }
+
+    pub fn background<T: Into<Color>>(mut self, color: T) -> Self {
+        self.background = Some(color.into());
+        self
+    }
+
+    pub fn bold(mut self) -> Self {
+        self.bold = true;
+        self
+    }
+
+    pub fn dim(mut self) -> Self {
+        self.dim = true;
+        self
+    }
+
+    pub fn italic(mut self) -> Self {
+        self.italic = true;
+        self
+    }
+
+    pub fn underline(mut self) -> Self {
+        self.underline = true;
+        self
+    }
+
+    pub fn blink(mut self) -> Self {
+        self.blink = true;
+        self
+    }
+
+    pub fn invert(mut self) -> Self {
+        self.invert = true;
+        self
+    }
+
+    pub fn invisible(mut self) -> Self {
+        self.invisible = true;
+        self
+    }
+
+    pub fn strike(mut self) -> Self {
+        self.strike = true;
+        self
+    }
+
+    /// True if this is the terminal's default style, with no colors or attributes.
+    pub fn is_plain(&self) -> bool {
+        *self == Style::default()
+    }
+
+    /// The closest style that can be shown with `depth`: its colors are downsampled, and with no colors at all
+    /// it's plain.
+    pub fn downsample(&self, depth: ColorDepth) -> Style {
+        if depth == ColorDepth::None {
+            return Style::default();
+        }
+        Style {
+            foreground: self.foreground.and_then(|c| c.downsample(depth)),
+            background: self.background.and_then(|c| c.downsample(depth)),
+            ..*self
+        }
+    }
+
+    /// The SGR sequence that switches the terminal from `self` to `to`, or nothing if they're the same.
+    ///
+    /// This is whichever is shorter of only changing what differs, or resetting everything and starting again.
+    pub fn transition(&self, to: &Style) -> Vec<u8> {
+        if self == to {
+            return Vec::new();
+        }
+
+        let params = if to.is_plain() {
+            "0".to_owned()
+        } else {
+            let diff = self.params(to);
+            let reset = format!("0;{}", Style::default().params(to));
+            if reset.len() < diff.len() {
+                reset
+            } else {
+                diff
+            }
+        };
+        format!("\x1b[{}m", params).into_bytes()
+    }
+
+    /// Write the sequence from `transition`.
+    pub fn write_transition<W: Write>(&self, w: &mut W, to: &Style) -> Result<()> {
+        w.write_all(&self.transition(to))
+            .context(ErrorKind::CsiFailed)?;
+        Ok(())
+    }
+
+    /// The SGR parameters that change just what differs between `self` and `to`.
+    fn params(&self, to: &Style) -> String {
+        let mut params = Vec::new();
+
+        // bold and dim are both turned off by 22
+        if (self.bold && !to.bold) || (self.dim && !to.dim) {
+            params.push("22".to_owned());
+            if to.bold {
+                params.push("1".to_owned());
+            }
+            if to.dim {
+                params.push("2".to_owned());
+            }
+        } else {
+            if to.bold && !self.bold {
+                params.push("1".to_owned());
+            }
+            if to.dim && !self.dim {
+                params.push("2".to_owned());
+            }
+        }
+
+        let attributes = [
+            (self.italic, to.italic, "3", "23"),
+            (self.underline, to.underline, "4", "24"),
+            (self.blink, to.blink, "5", "25"),
+            (self.invert, to.invert, "7", "27"),
+            (self.invisible, to.invisible, "8", "28"),
+            (self.strike, to.strike, "9", "29"),
+        ];
+        for &(from, to, on, off) in attributes.iter() {
+            if from != to {
+                params.push(if to { on } else { off }.to_owned());
+            }
+        }
+
+        if self.foreground != to.foreground {
+            params.push(color_params(to.foreground, 30, 90, 38));
+        }
+        if self.background != to.background {
+            params.push(color_params(to.background, 40, 100, 48));
+        }
+        params.join(";")
+    }
+}
+
+/// The SGR parameters for `color`, where `base` and `bright` are where the 8 basic and bright colors start, and
+/// `extended` introduces 256-color and RGB colors.
+fn color_params(color: Option<Color>, base: u8, bright: u8, extended: u8) -> String {
+    let mut s = String::new();
+    let _ = match color {
+        None => write!(s, "{}", base + 9),
+        Some(Color::Index(i @ 0..=7)) => write!(s, "{}", base + i),
+        Some(Color::Index(i @ 8..=15)) => write!(s, "{}", bright + i - 8),
+        Some(Color::Index(i)) => write!(s, "{};5;{}", extended, i),
+        Some(Color::Rgb(r, g, b)) => write!(s, "{};2;{};{};{}", extended, r, g, b),
+    };
+    s
+}
+
+#[cfg(test)]
+mod test {
+    use ansi::*;
+
+    fn transition(from: Style, to: Style) -> String {
+        String::from_utf8(from.transition(&to)).unwrap()
+    }
+
+    #[test]
+    fn transitions() {
+        let plain = Style::new();
+        assert_eq!(transition(plain, plain), "");
+        assert_eq!(transition(plain, Style::new().bold()), "\x1b[1m");
+        assert_eq!(
+            transition(plain, Style::new().foreground(1).background(12)),
+            "\x1b[31;104m"
+        );
+        assert_eq!(
+            transition(plain, Style::new().foreground(200).background((1, 2, 3))),
+            "\x1b[38;5;200;48;2;1;2;3m"
+        );
+        assert_eq!(
+            transition(Style::new().underline().bold(), plain),
+            "\x1b[0m"
+        );
+
+        // dropping bold also drops dim, so it has to be turned back on
+        assert_eq!(
+            transition(
+                Style::new().bold().dim().underline().foreground(1),
+                Style::new().dim().underline().foreground(1)
+            ),
+            "\x1b[22;2m"
+        );
+        assert_eq!(
+            transition(
+                Style::new().foreground(1),
+                Style::new().foreground(2).italic()
+            ),
+            "\x1b[3;32m"
+        );
+
+        // resetting is shorter than turning four things off
+        let busy = Style::new()
+            .bold()
+            .italic()
+            .underline()
+            .blink()
+            .foreground(3);
+        assert_eq!(
+            transition(busy, Style::new().foreground(3).strike()),
+            "\x1b[0;9;33m"
+        );
+        assert_eq!(
+            transition(Style::new().foreground(3), Style::new().background(3)),
+            "\x1b[0;43m"
+        );
+    }
+}
@@ -0,0 +1,644 @@
+//! Measuring how many columns text takes up on the screen.
+//!
+//! Text is measured a grapheme cluster at a time, the way terminals draw it, see `graphemes`. With the `unicode`
+//! feature clusters follow the Unicode rules (UAX #29). Without it they're approximated: a character joins the one
+//! before it if it's a combining mark, a variation selector or an emoji modifier, or if it follows a ZWJ, and
+//! regional indicators pair up into flags. That gets emoji sequences right, but splits the clusters of scripts whose
+//! marks take up a column of their own, like Devanagari's conjuncts and vowel signs, in the middle. They measure the
+//! same either way, but they can be wrapped or clipped between their characters.
+use super::strip::Scanner;
+use std::borrow::Cow;
+
+/// The tab width used by `display_width` and `truncate_to_width`.
+pub const DEFAULT_TAB_WIDTH: usize = 8;
+
+const ZWJ: char = '\u{200d}';
+const VS16: char = '\u{fe0f}';
+
+/// Characters that take up no space, and attach to the character before them (combining marks, variation
+/// selectors, emoji modifiers, etc).
+static ZERO_WIDTH: &[(u32, u32)] = &[
+    (0x0300, 0x036f),
+    (0x0483, 0x0489),
+    (0x0591, 0x05bd),
+    (0x05bf, 0x05bf),
+    (0x05c1, 0x05c2),
+    (0x05c4, 0x05c5),
+    (0x05c7, 0x05c7),
+    (0x0610, 0x061a),
+    (0x064b, 0x065f),
+    (0x0670, 0x0670),
+    (0x06d6, 0x06dc),
+    (0x06df, 0x06e4),
+    (0x06e7, 0x06e8),
+    (0x06ea, 0x06ed),
+    (0x0711, 0x0711),
+    (0x0730, 0x074a),
+    (0x07a6, 0x07b0),
+    (0x0900, 0x0902),
+    (0x093a, 0x093a),
+    (0x093c, 0x093c),
+    (0x0941, 0x0948),
+    (0x094d, 0x094d),
+    (0x0951, 0x0957),
+    (0x0962, 0x0963),
+    (0x0981, 0x0981),
+    (0x09bc, 0x09bc),
+    (0x09c1, 0x09c4),
+    (0x09cd, 0x09cd),
+    (0x0e31, 0x0e31),
+    (0x0e34, 0x0e3a),
+    (0x0e47, 0x0e4e),
+    (0x1160, 0x11ff),
+    (0x1ab0, 0x1aff),
+    (0x1dc0, 0x1dff),
+    (0x200b, 0x200f),
+    (0x2028, 0x202e),
+    (0x2060, 0x2064),
+    (0x20d0, 0x20ff),
+    (0xfe00, 0xfe0f),
+    (0xfe20, 0xfe2f),
+    (0xfeff, 0xfeff),
+    (0x1f3fb, 0x1f3ff),
+    (0xe0001, 0xe0001),
+    (0xe0020, 0xe007f),
+    (0xe0100, 0xe01ef),
+];
+
+/// East Asian wide and full-width characters, and emoji that are drawn two columns wide.
+static WIDE: &[(u32, u32)] = &[
+    (0x1100, 0x115f),
+    (0x231a, 0x231b),
+    (0x2329, 0x232a),
+    (0x23e9, 0x23ec),
+    (0x23f0, 0x23f0),
+    (0x23f3, 0x23f3),
+    (0x25fd, 0x25fe),
+    (0x2614, 0x2615),
+    (0x2648, 0x2653),
+    (0x267f, 0x267f),
+    (0x2693, 0x2693),
+    (0x26a1, 0x26a1),
+    (0x26aa, 0x26ab),
+    (0x26bd, 0x26be),
+    (0x26c4, 0x26c5),
+    (0x26ce, 0x26ce),
+    (0x26d4, 0x26d4),
+    (0x26ea, 0x26ea),
+    (0x26f2, 0x26f3),
+    (0x26f5, 0x26f5),
+    (0x26fa, 0x26fa),
+    (0x26fd, 0x26fd),
+    (0x2705, 0x2705),
+    (0x270a, 0x270b),
+    (0x2728, 0x2728),
+    (0x274c, 0x274c),
+    (0x274e, 0x274e),
+    (0x2753, 0x2755),
+    (0x2757, 0x2757),
+    (0x2795, 0x2797),
+    (0x27b0, 0x27b0),
+    (0x27bf, 0x27bf),
+    (0x2b1b, 0x2b1c),
+    (0x2b50, 0x2b50),
+    (0x2b55, 0x2b55),
+    (0x2e80, 0x303e),
+    (0x3041, 0x33ff),
+    (0x3400, 0x4dbf),
+    (0x4e00, 0x9fff),
+    (0xa000, 0xa4cf),
+    (0xa960, 0xa97f),
+    (0xac00, 0xd7a3),
+    (0xf900, 0xfaff),
+    (0xfe10, 0xfe19),
+    (0xfe30, 0xfe6f),
+    (0xff00, 0xff60),
+    (0xffe0, 0xffe6),
+    (0x16fe0, 0x16fe4),
+    (0x17000, 0x18aff),
+    (0x1b000, 0x1b2ff),
+    (0x1f004, 0x1f004),
+    (0x1f0cf, 0x1f0cf),
+    (0x1f18e, 0x1f18e),
+    (0x1f191, 0x1f19a),
+    (0x1f200, 0x1f202),
+    (0x1f210, 0x1f23b),
+    (0x1f240, 0x1f248),
+    (0x1f250, 0x1f251),
+    (0x1f260, 0x1f265),
+    (0x1f300, 0x1f64f),
+    (0x1f680, 0x1f6ff),
+    (0x1f7e0, 0x1f7eb),
+    (0x1f900, 0x1f9ff),
+    (0x1fa70, 0x1faff),
+    (0x20000, 0x2fffd),
+    (0x30000, 0x3fffd),
+];
+
+fn in_table(table: &[(u32, u32)], c: char) -> bool {
+    let c = c as u32;
+    table
+        .binary_search_by(|&(lo, hi)| {
+            if hi < c {
+                ::std::cmp::Ordering::Less
+            } else if lo > c {
+                ::std::cmp::Ordering::Greater
+            } else {
+                ::std::cmp::Ordering::Equal
+            }
+        })
+        .is_ok()
+}
+
+fn is_regional_indicator(c: char) -> bool {
+    ('\u{1f1e6}'..='\u{1f1ff}').contains(&c)
+}
+
+/// True if `c` joins onto the character before it, rather than starting a new grapheme.
+fn is_extend(c: char) -> bool {
+    in_table(ZERO_WIDTH, c)
+}
+
+/// The number of columns a single character takes up.
+///
+/// Control characters and combining marks are 0 columns wide, East Asian wide characters and most emoji are 2.
+pub fn char_width(c: char) -> usize {
+    if c < ' ' || ('\u{7f}'..'\u{a0}').contains(&c) || is_extend(c) {
+        0
+    } else if in_table(WIDE, c) {
+        2
+    } else {
+        1
+    }
+}
+
+#[cfg(not(feature = "unicode"))]
+struct Cluster {
+    start: usize,
+    end: usize,
+    width: usize,
+    last: char,
+    regional_indicators: usize,
+}
+
+#[cfg(not(feature = "unicode"))]
+impl Cluster {
+    /// Try to add `c` to the end of this cluster, returns false if `c` starts a new one.
+    fn extend(&mut self, c: char, end: usize) -> bool {
+        let joins = if is_regional_indicator(c) {
+            // flags are made from pairs of regional indicators
+            self.regional_indicators == 1
+        } else {
+            (is_extend(c) && self.last != '\t' && self.last >= ' ') || self.last == ZWJ
+        };
+
+        if !joins {
+            return false;
+        }
+
+        if is_regional_indicator(c) {
+            self.regional_indicators += 1;
+            self.width = 2;
+        } else if c == VS16 && self.width == 1 {
+            // emoji presentation selector
+            self.width = 2;
+        }
+        self.last = c;
+        self.end = end;
+        true
+    }
+}
+
+/// Walk over the grapheme clusters in `s`, skipping escape sequences, calling `f` with each cluster's byte
+/// range and width. Stops early if `f` returns false.
+#[cfg(not(feature = "unicode"))]
+pub(crate) fn clusters<F>(s: &str, tab_width: usize, mut f: F)
+where
+    F: FnMut(usize, usize, usize) -> bool,
+{
+    let mut scanner = Scanner::new();
+    let mut col = 0;
+    let mut current: Option<Cluster> = None;
+
+    for (i, c) in s.char_indices() {
+        let mut utf8 = [0u8; 4];
+        let mut text = true;
+        for &b in c.encode_utf8(&mut utf8).as_bytes() {
+            text = scanner.advance(b);
+        }
+        if !text {
+            continue;
+        }
+
+        let end = i + c.len_utf8();
+        if let Some(ref mut cluster) = current {
+            if cluster.extend(c, end) {
+                continue;
+            }
+        }
+
+        if let Some(cluster) = current.take() {
+            col += cluster.width;
+            if !f(cluster.start, cluster.end, cluster.width) {
+                return;
+            }
+        }
+
+        current = Some(Cluster {
+            start: i,
+            end,
+            width: match c {
+                '\t' if tab_width > 0 => tab_width - col % tab_width,
+                c => char_width(c),
+            },
+            last: c,
+            regional_indicators: if is_regional_indicator(c) { 1 } else { 0 },
+        });
+    }
+
+    if let Some(cluster) = current {
+        f(cluster.start, cluster.end, cluster.width);
+    }
+}
+
+/// Walk over the grapheme clusters in `s`, skipping escape sequences, calling `f` with each cluster's byte
+/// range and width. Stops early if `f` returns false.
+#[cfg(feature = "unicode")]
+pub(crate) fn clusters<F>(s: &str, tab_width: usize, mut f: F)
+where
+    F: FnMut(usize, usize, usize) -> bool,
+{
+    use unicode_segmentation::UnicodeSegmentation;
+
+    // the text in `s` without its escape sequences, and where each of its characters starts and ends in `s`
+    let mut text = String::with_capacity(s.len());
+    let mut positions = Vec::new();
+    let mut scanner = Scanner::new();
+    for (i, c) in s.char_indices() {
+        let mut utf8 = [0u8; 4];
+        let mut is_text = true;
+        for &b in c.encode_utf8(&mut utf8).as_bytes() {
+            is_text = scanner.advance(b);
+        }
+        if is_text {
+            positions.push((i, i + c.len_utf8()));
+            text.push(c);
+        }
+    }
+
+    let mut col = 0;
+    let mut next = 0;
+    for grapheme in text.graphemes(true) {
+        let chars = grapheme.chars().count();
+        let start = positions[next].0;
+        let end = positions[next + chars - 1].1;
+        next += chars;
+        let width = match grapheme {
+            "\t" if tab_width > 0 => tab_width - col % tab_width,
+            grapheme => grapheme_width(grapheme),
+        };
+        col += width;
+        if !f(start, end, width) {
+            return;
+        }
+    }
+}
+
+/// The number of columns a grapheme cluster takes up: the widths of its characters added up, except that a
+/// character after a ZWJ is drawn together with the one before it, a flag is 2 columns, and an emoji presentation
+/// selector makes its character 2 columns.
+#[cfg(feature = "unicode")]
+fn grapheme_width(grapheme: &str) -> usize {
+    let mut width = 0;
+    let mut last = '\0';
+    let mut regional_indicators = 0;
+    for c in grapheme.chars() {
+        if is_regional_indicator(c) {
+            regional_indicators += 1;
+        }
+        if regional_indicators == 2 || (c == VS16 && width == 1) {
+            width = 2;
+        } else if last != ZWJ {
+            width += char_width(c);
+        }
+        last = c;
+    }
+    width
+}
+
+/// The grapheme clusters in `s`, each with the number of columns it takes up, which is how far it moves the cursor.
+///
+/// Escape sequences are skipped, unless they're in the middle of a cluster, where they stay part of it. Tabs and other
+/// control characters are 0 columns wide, like they are for `char_width`. See the module documentation for how
+/// clusters are found with and without the `unicode` feature.
+pub fn graphemes(s: &str) -> impl Iterator<Item = (&str, usize)> {
+    let mut found = Vec::new();
+    clusters(s, 0, |start, end, width| {
+        found.push((&s[start..end], width));
+        true
+    });
+    found.into_iter()
+}
+
+/// The number of columns `s` will take up when printed, with tabs expanded to `tab_width` columns.
+pub fn display_width_with_tabs(s: &str, tab_width: usize) -> usize {
+    let mut width = 0;
+    clusters(s, tab_width, |_, _, w| {
+        width += w;
+        true
+    });
+    width
+}
+
+/// The number of columns `s` will take up when printed.
+///
+/// Escape sequences are ignored, tabs are expanded to the next multiple of `DEFAULT_TAB_WIDTH`, and combining characters
+/// are counted along with the character they attach to.
+pub fn display_width(s: &str) -> usize {
+    display_width_with_tabs(s, DEFAULT_TAB_WIDTH)
+}
+
+/// Cut `s` down so it takes up at most `cols` columns.
+///
+/// The string is never split inside an escape sequence or a grapheme cluster, so the result may be narrower than
+/// `cols` if a wide character would have straddled the edge. Escape sequences before the first character that
+/// doesn't fit are kept, even after the last one that does, and the rest are dropped.
+pub fn truncate_to_width(s: &str, cols: usize) -> &str {
+    let mut col = 0;
+    let mut cut = s.len();
+    clusters(s, DEFAULT_TAB_WIDTH, |start, _, w| {
+        if col + w > cols {
+            cut = start;
+            return false;
+        }
+        col += w;
+        true
+    });
+    &s[..cut]
+}
+
+/// Like `truncate_to_width`, but when `s` doesn't fit the last column is replaced with an ellipsis.
+pub fn truncate_with_ellipsis<'a>(s: &'a str, cols: usize) -> Cow<'a, str> {
+    if display_width(s) <= cols {
+        return Cow::Borrowed(s);
+    }
+
+    if cols == 0 {
+        return Cow::Borrowed("");
+    }
+
+    let mut truncated = String::from(truncate_to_width(s, cols - 1));
+    truncated.push('…');
+    Cow::Owned(truncated)
+}
+
+/// The part of a line that shows in a window of columns, returned by `clip_line`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ClippedLine<'a> {
+    /// The grapheme clusters that fit in the window, with the escape sequences between them. Escape sequences from
+    /// the part cut off on the left are kept at the front, so the text starts out in the right style.
+    pub text: Cow<'a, str>,
+    /// The blank columns before `text`, where a wide character was cut in half by the left edge.
+    pub pad_left: usize,
+    /// The number of columns `text` takes up.
+    pub width: usize,
+    /// True if some of the line was cut off on the left, or on the right, so the caller can draw an ellipsis.
+    pub clipped_left: bool,
+    pub clipped_right: bool,
+}
+
+/// Clip `s` to the `width` columns starting at column `start_col`, for drawing a line that might not fit and
+/// scrolling along it.
+///
+/// Like `truncate_to_width`, grapheme clusters and escape sequences are never split, so a wide character
+/// straddling either edge is left out. On the left that leaves a blank column, see `ClippedLine::pad_left`. Tabs are
+/// expanded to `DEFAULT_TAB_WIDTH` columns.
+pub fn clip_line<'a>(s: &'a str, start_col: usize, width: usize) -> ClippedLine<'a> {
+    let end_col = start_col + width;
+    // the escape sequences in the part cut off on the left
+    let mut prefix = String::new();
+    let mut visible: Option<usize> = None;
+    let mut cut = s.len();
+    let mut prev_end = 0;
+    let mut line = ClippedLine {
+        text: Cow::Borrowed(""),
+        pad_left: 0,
+        width: 0,
+        clipped_left: false,
+        clipped_right: false,
+    };
+
+    let mut col = 0;
+    clusters(s, DEFAULT_TAB_WIDTH, |start, end, w| {
+        if col < start_col {
+            line.clipped_left |= w > 0;
+            line.pad_left = (col + w).saturating_sub(start_col).min(width);
+            prefix.push_str(&s[prev_end..start]);
+        } else if col + w <= end_col {
+            visible.get_or_insert(prev_end);
+            line.width += w;
+        } else {
+            line.clipped_right = true;
+            cut = start;
+            return false;
+        }
+        col += w;
+        prev_end = end;
+        true
+    });
+    // a wide character cut in half by both edges
+    line.clipped_right |= col > end_col;
+
+    let text = &s[visible.unwrap_or(prev_end)..cut];
+    line.text = if prefix.is_empty() {
+        Cow::Borrowed(text)
+    } else {
+        Cow::Owned(prefix + text)
+    };
+    line
+}
+
+#[cfg(test)]
+mod test {
+    use ansi::width::*;
+
+    #[test]
+    fn ascii() {
+        assert_eq!(display_width(""), 0);
+        assert_eq!(display_width("hello"), 5);
+        assert_eq!(display_width("a\tb"), 9);
+        assert_eq!(display_width_with_tabs("abc\td", 4), 5);
+        assert_eq!(display_width("\x07bell"), 4);
+    }
+
+    #[test]
+    fn sgr() {
+        assert_eq!(display_width("\x1b[1;31mred\x1b[0m"), 3);
+        assert_eq!(display_width("\x1b]8;;http://a\x1b\\link\x1b]8;;\x1b\\"), 4);
+        assert_eq!(truncate_to_width("\x1b[1mbold\x1b[0m", 2), "\x1b[1mbo");
+        assert_eq!(
+            truncate_to_width("\x1b[1mbold\x1b[0m", 4),
+            "\x1b[1mbold\x1b[0m"
+        );
+        assert_eq!(truncate_to_width("ab\x1b[31mcd", 2), "ab\x1b[31m");
+    }
+
+    #[test]
+    fn cjk() {
+        assert_eq!(char_width('日'), 2);
+        assert_eq!(display_width("日本語"), 6);
+        assert_eq!(display_width("ｆｕｌｌ"), 8);
+        assert_eq!(display_width("한국어"), 6);
+        // never split a wide character in half
+        assert_eq!(truncate_to_width("日本語", 3), "日");
+        assert_eq!(truncate_to_width("日本語", 4), "日本");
+    }
+
+    #[test]
+    fn combining() {
+        // "e" followed by a combining acute accent
+        assert_eq!(display_width("cafe\u{301}"), 4);
+        assert_eq!(truncate_to_width("cafe\u{301}s", 4), "cafe\u{301}");
+        assert_eq!(truncate_to_width("e\u{301}\u{302}x", 1), "e\u{301}\u{302}");
+    }
+
+    #[test]
+    fn emoji() {
+        assert_eq!(display_width("😀"), 2);
+        // family, joined with ZWJs
+        let family = "👨\u{200d}👩\u{200d}👧";
+        assert_eq!(display_width(family), 2);
+        assert_eq!(truncate_to_width(family, 1), "");
+        assert_eq!(truncate_to_width(family, 2), family);
+        // skin tone modifier
+        assert_eq!(display_width("👍🏽"), 2);
+        // flag
+        assert_eq!(display_width("🇯🇵"), 2);
+        assert_eq!(display_width("🇯🇵🇺🇸"), 4);
+        // emoji presentation
+        assert_eq!(display_width("❤\u{fe0f}"), 2);
+    }
+
+    #[test]
+    fn ellipsis() {
+        assert_eq!(truncate_with_ellipsis("hello", 5), "hello");
+        assert_eq!(truncate_with_ellipsis("hello world", 8), "hello w…");
+        assert_eq!(truncate_with_ellipsis("日本語", 4), "日…");
+        assert_eq!(truncate_with_ellipsis("hello", 0), "");
+    }
+
+    #[test]
+    fn clip() {
+        let line = clip_line("hello world", 3, 5);
+        assert_eq!(line.text, "lo wo");
+        assert_eq!((line.pad_left, line.width), (0, 5));
+        assert!(line.clipped_left && line.clipped_right);
+
+        let line = clip_line("hello", 0, 10);
+        assert_eq!(line.text, "hello");
+        assert!(!line.clipped_left && !line.clipped_right);
+
+        // a wide character cut in half on the left leaves a blank column, on the right it's just left out
+        let line = clip_line("日本語", 1, 4);
+        assert_eq!(line.text, "本");
+        assert_eq!((line.pad_left, line.width), (1, 2));
+        assert!(line.clipped_left && line.clipped_right);
+
+        assert_eq!(clip_line("", 5, 5).text, "");
+        assert_eq!(clip_line("abc", 5, 5).text, "");
+        assert!(clip_line("abc", 5, 5).clipped_left);
+        assert_eq!(clip_line("cafe\u{301}s", 3, 1).text, "e\u{301}");
+    }
+
+    #[test]
+    fn clip_escapes() {
+        // the escape sequences cut off on the left still apply
+        let line = clip_line("\x1b[31m日本\x1b[0m語", 2, 2);
+        assert_eq!(line.text, "\x1b[31m本\x1b[0m");
+        assert!(line.clipped_left && line.clipped_right);
+        let line = clip_line("\x1b[1mab\x1b[0m", 2, 3);
+        assert_eq!(line.text, "\x1b[1m\x1b[0m");
+        assert_eq!(line.width, 0);
+        // and sequences are never split
+        assert_eq!(
+            clip_line("a\x1b]8;;http://b\x1b\\c", 1, 1).text,
+            "\x1b]8;;http://b\x1b\\c"
+        );
+    }
+
+    #[test]
+    fn clip_every_window() {
+        // each piece is one grapheme cluster, with its width
+        let pieces = [
+            ("a", 1),
+            ("日", 2),
+            ("e\u{301}", 1),
+            ("😀", 2),
+            ("b", 1),
+            ("👨\u{200d}👩\u{200d}👧", 2),
+            ("本", 2),
+            ("c", 1),
+        ];
+        let line: String = pieces.iter().map(|&(p, _)| p).collect();
+        let total: usize = pieces.iter().map(|&(_, w)| w).sum();
+
+        for start in 0..total + 2 {
+            for width in 0..total + 2 {
+                let end = start + width;
+                let (mut col, mut text, mut pad_left) = (0, String::new(), 0);
+                for &(piece, w) in &pieces {
+                    if col >= start && col + w <= end {
+                        text.push_str(piece);
+                    } else if col < start && col + w > start {
+                        pad_left = (col + w - start).min(width);
+                    }
+                    col += w;
+                }
+
+                let clipped = clip_line(&line, start, width);
+                let window = format!("{}..{}", start, end);
+                assert_eq!(clipped.text, text, "{}", window);
+                assert_eq!(clipped.pad_left, pad_left, "{}", window);
+                assert_eq!(clipped.width, display_width(&text), "{}", window);
+                assert!(clipped.pad_left + clipped.width <= width, "{}", window);
+                assert_eq!(clipped.clipped_left, start > 0, "{}", window);
+                assert_eq!(clipped.clipped_right, end < total, "{}", window);
+            }
+        }
+    }
+
+    #[test]
+    fn grapheme_columns() {
+        let family = "👨\u{200d}👩\u{200d}👧";
+        let text = format!("a{}\x1b[1m🇯🇵🇺🇸👍🏽b", family);
+        let clusters: Vec<(&str, usize)> = graphemes(&text).collect();
+        assert_eq!(
+            clusters,
+            [("a", 1), (family, 2), ("🇯🇵", 2), ("🇺🇸", 2), ("👍🏽", 2), ("b", 1)]
+        );
+
+        // where the cursor is after each cluster
+        let columns = |s| {
+            let mut col = 0;
+            graphemes(s)
+                .map(|(_, w)| {
+                    col += w;
+                    col
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(columns("x\ty"), [1, 1, 2]);
+        assert_eq!(columns("e\u{301}日"), [1, 3]);
+        // a conjunct (ka, virama, ssa) is one cluster with the feature, and both ways it's 2 columns
+        #[cfg(feature = "unicode")]
+        assert_eq!(columns("क्ष|"), [2, 3]);
+        #[cfg(not(feature = "unicode"))]
+        assert_eq!(columns("क्ष|"), [1, 2, 3]);
+        assert_eq!(display_width("नमस्ते"), 4);
+        assert_eq!(display_width("क्ष"), 2);
+
+        // so it isn't cut in half
+        #[cfg(feature = "unicode")]
+        assert_eq!(truncate_to_width("क्ष", 1), "");
+    }
+}
@@ -0,0 +1,389 @@
+//! Wrapping text to a width, for help text and dialogs.
+use super::width::clusters;
+use std::borrow::Cow;
+
+/// How `wrap` lays text out.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct WrapOptions {
+    /// Columns to indent every wrapped line by, after the first line of each paragraph.
+    pub hanging_indent: usize,
+    /// Break words too long for a line between characters, instead of letting them run past the width. This is on
+    /// by default.
+    pub break_long_words: bool,
+}
+
+impl Default for WrapOptions {
+    fn default() -> WrapOptions {
+        WrapOptions {
+            hanging_indent: 0,
+            break_long_words: true,
+        }
+    }
+}
+
+impl WrapOptions {
+    pub fn new() -> WrapOptions {
+        WrapOptions::default()
+    }
+
+    pub fn hanging_indent(mut self, cols: usize) -> Self {
+        self.hanging_indent = cols;
+        self
+    }
+
+    pub fn break_long_words(mut self, v: bool) -> Self {
+        self.break_long_words = v;
+        self
+    }
+}
+
+/// One grapheme cluster, along with the escape sequences before it.
+#[derive(Debug, Copy, Clone)]
+struct Piece {
+    /// Where the escape sequences before the cluster start.
+    escapes: usize,
+    start: usize,
+    end: usize,
+    width: usize,
+    space: bool,
+}
+
+/// A line of output, borrowed from the text for as long as it's one piece of it.
+struct Line<'a> {
+    text: &'a str,
+    range: Option<(usize, usize)>,
+    owned: Option<String>,
+}
+
+impl<'a> Line<'a> {
+    fn new(text: &'a str) -> Line<'a> {
+        Line {
+            text,
+            range: None,
+            owned: None,
+        }
+    }
+
+    fn push_range(&mut self, start: usize, end: usize) {
+        if start == end {
+            return;
+        }
+        match (self.owned.as_mut(), self.range) {
+            (Some(owned), _) => owned.push_str(&self.text[start..end]),
+            (None, None) => self.range = Some((start, end)),
+            (None, Some((from, to))) if to == start => self.range = Some((from, end)),
+            (None, Some(_)) => {
+                self.push_str("");
+                self.push_range(start, end);
+            }
+        }
+    }
+
+    fn push_str(&mut self, s: &str) {
+        let text = self.text;
+        let range = self.range;
+        let owned = self.owned.get_or_insert_with(|| match range {
+            Some((from, to)) => text[from..to].to_owned(),
+            None => String::new(),
+        });
+        owned.push_str(s);
+    }
+
+    fn finish(self) -> Cow<'a, str> {
+        match (self.owned, self.range) {
+            (Some(owned), _) => Cow::Owned(owned),
+            (None, Some((from, to))) => Cow::Borrowed(&self.text[from..to]),
+            (None, None) => Cow::Borrowed(""),
+        }
+    }
+}
+
+struct Wrapper<'a> {
+    text: &'a str,
+    width: usize,
+    opts: WrapOptions,
+    lines: Vec<Cow<'a, str>>,
+    line: Line<'a>,
+    col: usize,
+    /// True once the line has some text on it, not just an indent.
+    content: bool,
+    /// The SGR sequences in effect, since the last reset.
+    active: String,
+    /// True until the active style has been written on this line.
+    needs_style: bool,
+}
+
+impl<'a> Wrapper<'a> {
+    fn start_line(&mut self, continuation: bool) {
+        self.line = Line::new(self.text);
+        self.col = 0;
+        self.content = false;
+        self.needs_style = true;
+        if continuation {
+            // always leave room for at least one column of text
+            self.col = self.opts.hanging_indent.min(self.width.saturating_sub(1));
+            let indent = " ".repeat(self.col);
+            self.line.push_str(&indent);
+        }
+    }
+
+    fn finish_line(&mut self) {
+        if !self.active.is_empty() && !self.needs_style {
+            self.line.push_str("\x1b[0m");
+        }
+        let line = ::std::mem::replace(&mut self.line, Line::new(self.text));
+        self.lines.push(line.finish());
+    }
+
+    fn push_range(&mut self, start: usize, end: usize) {
+        if start == end {
+            return;
+        }
+        if self.needs_style {
+            self.needs_style = false;
+            if !self.active.is_empty() {
+                let active = self.active.clone();
+                self.line.push_str(&active);
+            }
+        }
+        self.line.push_range(start, end);
+        track_style(&mut self.active, &self.text[start..end]);
+    }
+
+    fn push(&mut self, piece: &Piece) {
+        self.push_range(piece.escapes, piece.end);
+        self.col += piece.width;
+        self.content |= piece.width > 0;
+    }
+
+    /// Leave a piece out, keeping its escape sequences.
+    fn drop_piece(&mut self, piece: &Piece) {
+        self.push_range(piece.escapes, piece.start);
+    }
+
+    fn break_line(&mut self) {
+        self.finish_line();
+        self.start_line(true);
+    }
+
+    fn paragraph(&mut self, pieces: &[Piece]) {
+        self.start_line(false);
+        let mut i = 0;
+        let mut first = true;
+        while i < pieces.len() {
+            let spaces = pieces[i..].iter().take_while(|p| p.space).count();
+            let (ws, rest) = pieces[i..].split_at(spaces);
+            let len = rest.iter().take_while(|p| !p.space).count();
+            let word = &rest[..len];
+            i += spaces + len;
+
+            let ws_width: usize = ws.iter().map(|p| p.width).sum();
+            let word_width: usize = word.iter().map(|p| p.width).sum();
+
+            // whitespace at the start of a paragraph is its indent
+            if self.col + ws_width + word_width <= self.width || (first && !self.content) {
+                ws.iter().for_each(|p| self.push(p));
+            } else {
+                ws.iter().for_each(|p| self.drop_piece(p));
+                if self.content && word_width > 0 {
+                    self.break_line();
+                }
+            }
+            first = false;
+
+            if self.col + word_width <= self.width || !self.opts.break_long_words {
+                word.iter().for_each(|p| self.push(p));
+                continue;
+            }
+            for p in word {
+                if self.col + p.width > self.width && self.content {
+                    self.break_line();
+                }
+                self.push(p);
+            }
+        }
+        self.finish_line();
+    }
+}
+
+/// Record the SGR sequences in `escapes` in `active`, which is cleared by a reset.
+fn track_style(active: &mut String, escapes: &str) {
+    let mut rest = escapes;
+    while let Some(i) = rest.find("\x1b[") {
+        let seq = &rest[i..];
+        let end = match seq[2..].bytes().position(|c| (0x40..=0x7e).contains(&c)) {
+            Some(n) => n + 3,
+            None => return,
+        };
+        let (seq, tail) = seq.split_at(end);
+        rest = tail;
+        if !seq.ends_with('m') {
+            continue;
+        }
+        let params = &seq[2..seq.len() - 1];
+        if params.is_empty() || params == "0" {
+            active.clear();
+        } else {
+            if params.starts_with("0;") {
+                active.clear();
+            }
+            active.push_str(seq);
+        }
+    }
+}
+
+/// Wrap `text` into lines at most `width` columns wide, breaking between words.
+///
+/// Newlines in `text` start new paragraphs. Widths are measured like `display_width`, so wide characters take two
+/// columns, escape sequences take none, and tabs are counted as single spaces. The spaces where a line was broken
+/// are left out, but a paragraph's leading whitespace is kept as its indent. Words wider than a line are broken
+/// between grapheme clusters unless `WrapOptions::break_long_words` is off.
+///
+/// A style (SGR sequence) carries on across line breaks: lines start with the styles in effect, and end with a
+/// reset, so each line can be drawn on its own.
+///
+/// ```
+/// use nixterm::ansi::{wrap, WrapOptions};
+///
+/// let lines = wrap("-v, --verbose  print more output", 26, WrapOptions::new().hanging_indent(15));
+/// assert_eq!(lines, vec!["-v, --verbose  print more", "               output"]);
+/// ```
+pub fn wrap<'a>(text: &'a str, width: usize, opts: WrapOptions) -> Vec<Cow<'a, str>> {
+    let mut wrapper = Wrapper {
+        text,
+        width,
+        opts,
+        lines: Vec::new(),
+        line: Line::new(text),
+        col: 0,
+        content: false,
+        active: String::new(),
+        needs_style: true,
+    };
+
+    let mut offset = 0;
+    for paragraph in text.split('\n') {
+        let mut pieces = Vec::new();
+        let mut prev_end = 0;
+        clusters(paragraph, 0, |start, end, width| {
+            let space = paragraph[start..end].starts_with([' ', '\t']);
+            pieces.push(Piece {
+                escapes: offset + prev_end,
+                start: offset + start,
+                end: offset + end,
+                width: if space { 1 } else { width },
+                space,
+            });
+            prev_end = end;
+            true
+        });
+        // escape sequences after the last character
+        if prev_end < paragraph.len() {
+            pieces.push(Piece {
+                escapes: offset + prev_end,
+                start: offset + paragraph.len(),
+                end: offset + paragraph.len(),
+                width: 0,
+                space: false,
+            });
+        }
+        wrapper.paragraph(&pieces);
+        offset += paragraph.len() + 1;
+    }
+    wrapper.lines
+}
+
+#[cfg(test)]
+mod test {
+    use ansi::wrap::*;
+
+    fn plain(text: &str, width: usize) -> Vec<Cow<'_, str>> {
+        wrap(text, width, WrapOptions::new())
+    }
+
+    #[test]
+    fn words() {
+        assert_eq!(
+            plain("the quick brown fox", 10),
+            vec!["the quick", "brown fox"]
+        );
+        assert_eq!(
+            plain("the quick brown fox", 9),
+            vec!["the quick", "brown fox"]
+        );
+        assert_eq!(
+            plain("the quick brown fox", 8),
+            vec!["the", "quick", "brown", "fox"]
+        );
+        assert_eq!(
+            plain("one\n\n  two three", 7),
+            vec!["one", "", "  two", "three"]
+        );
+        assert_eq!(plain("", 10), vec![""]);
+        // lines that fit are borrowed from the text
+        assert!(plain("short\nlines", 10).iter().all(|l| match *l {
+            Cow::Borrowed(_) => true,
+            Cow::Owned(_) => false,
+        }));
+    }
+
+    #[test]
+    fn long_words() {
+        assert_eq!(
+            plain("a verylongword b", 5),
+            vec!["a", "veryl", "ongwo", "rd b"]
+        );
+        let opts = WrapOptions::new().break_long_words(false);
+        assert_eq!(
+            wrap("a verylongword b", 5, opts),
+            vec!["a", "verylongword", "b"]
+        );
+        assert_eq!(
+            wrap("usage: tool", 8, WrapOptions::new().hanging_indent(2)),
+            vec!["usage:", "  tool"]
+        );
+        // an indent as wide as the line still leaves a column for text
+        assert_eq!(
+            wrap("ab cd", 2, WrapOptions::new().hanging_indent(4)),
+            vec!["ab", " c", " d"]
+        );
+    }
+
+    #[test]
+    fn wide() {
+        assert_eq!(
+            plain("日本語のテキスト", 6),
+            vec!["日本語", "のテキ", "スト"]
+        );
+        // a wide character never straddles the edge
+        assert_eq!(plain("a日本", 4), vec!["a日", "本"]);
+        assert_eq!(plain("😀 😀 😀", 5), vec!["😀 😀", "😀"]);
+        assert_eq!(plain("cafe\u{301} ok", 4), vec!["cafe\u{301}", "ok"]);
+    }
+
+    #[test]
+    fn styles() {
+        assert_eq!(
+            plain("\x1b[31mred text\x1b[0m plain", 5),
+            vec!["\x1b[31mred\x1b[0m", "\x1b[31mtext\x1b[0m", "plain"]
+        );
+        assert_eq!(
+            plain("\x1b[1mbold \x1b[4mboth\x1b[22m under\x1b[0m", 6),
+            vec![
+                "\x1b[1mbold\x1b[0m",
+                "\x1b[1m\x1b[4mboth\x1b[22m\x1b[0m",
+                "\x1b[1m\x1b[4m\x1b[22munder\x1b[0m",
+            ]
+        );
+        // the style carries across newlines too, and a reset ends it
+        assert_eq!(
+            plain("\x1b[32mgreen\nstill\x1b[m\nnot", 10),
+            vec!["\x1b[32mgreen\x1b[0m", "\x1b[32mstill\x1b[m", "not"]
+        );
+        // escapes in the dropped space are kept
+        assert_eq!(
+            plain("ab\x1b[33m cd", 2),
+            vec!["ab\x1b[33m\x1b[0m", "\x1b[33mcd\x1b[0m"]
+        );
+    }
+}
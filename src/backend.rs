@@ -0,0 +1,353 @@
+//! What a `Term` needs from the terminal device.
+//!
+//! Reading and writing go through the `Term`'s input and output streams, everything else (its settings, its size,
+//! and whether it's a tty at all) goes through a `TermBackend`. `Term::from_streams` uses a `UnixBackend`, which
+//! asks the streams' file descriptors. A `MockBackend` pretends to be the whole terminal, streams included, so code
+//! that takes a `Term` can be tested without a pseudo terminal.
+use nix::errno::Errno;
+use nix::poll;
+use nix::sys::termios;
+use nix::unistd;
+use std::collections::VecDeque;
+use std::io;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+use term::{window_size, Settings, Term};
+use terminfo;
+
+/// The terminal device behind a `Term`, see the module documentation.
+pub trait TermBackend {
+    /// True if the output is a tty, rather than a pipe or a file.
+    fn is_tty(&self) -> bool;
+
+    /// The terminal's size, as (columns, rows). A terminal that doesn't know its size can say it's 0x0.
+    fn size(&self) -> io::Result<(usize, usize)>;
+
+    /// Get the terminal's settings.
+    fn attributes(&self) -> io::Result<Settings>;
+
+    /// Change the terminal's settings, once everything that's been written has been sent.
+    fn set_attributes(&self, settings: &Settings) -> io::Result<()>;
+
+    /// Like `set_attributes`, but anything typed and not read yet is kept. `UnixBackend` throws it away otherwise,
+    /// like `tcsetattr(3)` with `TCSAFLUSH`.
+    fn set_attributes_keeping_input(&self, settings: &Settings) -> io::Result<()>;
+
+    /// Wait up to `timeout` for input, returns false if the timeout runs out first.
+    fn poll_input(&self, timeout: Duration) -> io::Result<bool>;
+}
+
+/// The backend for a real terminal, which asks the file descriptors of the `Term`'s streams.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct UnixBackend {
+    stdin: RawFd,
+    stdout: RawFd,
+}
+
+/// A pretend terminal, for tests.
+///
+/// It's its own input and output stream: reads are served from the scripted input (and return 0 once it runs out),
+/// and everything written is recorded. Input can be held back for a while with `push_input_after`, like a reply
+/// coming from a slow terminal. Clones share the same terminal, so keep one to look at while a `Term` uses
+/// another.
+///
+/// ```
+/// use nixterm::backend::MockBackend;
+/// use nixterm::terminfo::TermInfoBuf;
+/// use std::io::Write;
+///
+/// let mock = MockBackend::new();
+/// let term = mock.term(TermInfoBuf::new());
+/// term.styled_writer().write_all(b"hello").unwrap();
+/// assert!(mock.written_contains(b"hello"));
+/// ```
+#[derive(Clone)]
+pub struct MockBackend {
+    state: Arc<Mutex<MockState>>,
+}
+
+struct MockState {
+    input: VecDeque<u8>,
+    /// Input that isn't there yet, and when it arrives, in order.
+    delayed: VecDeque<(Instant, Vec<u8>)>,
+    output: Vec<u8>,
+    size: (usize, usize),
+    tty: bool,
+    settings: Settings,
+}
+
+impl UnixBackend {
+    pub fn new(stdin: RawFd, stdout: RawFd) -> UnixBackend {
+        UnixBackend { stdin, stdout }
+    }
+}
+
+impl TermBackend for UnixBackend {
+    fn is_tty(&self) -> bool {
+        unistd::isatty(self.stdout).unwrap_or(false)
+    }
+
+    fn size(&self) -> io::Result<(usize, usize)> {
+        window_size(self.stdout).map_err(nix_to_io)
+    }
+
+    fn attributes(&self) -> io::Result<Settings> {
+        termios::tcgetattr(self.stdin)
+            .map(Settings::from_termios)
+            .map_err(nix_to_io)
+    }
+
+    fn set_attributes(&self, settings: &Settings) -> io::Result<()> {
+        termios::tcsetattr(self.stdin, termios::SetArg::TCSAFLUSH, settings.termios())
+            .map_err(nix_to_io)
+    }
+
+    fn set_attributes_keeping_input(&self, settings: &Settings) -> io::Result<()> {
+        termios::tcsetattr(self.stdin, termios::SetArg::TCSADRAIN, settings.termios())
+            .map_err(nix_to_io)
+    }
+
+    fn poll_input(&self, timeout: Duration) -> io::Result<bool> {
+        // round up, so we never give up before the timeout has actually passed
+        let ms = timeout.as_secs() * 1000 + u64::from(timeout.subsec_nanos()).div_ceil(1_000_000);
+        let mut fds = [poll::PollFd::new(self.stdin, poll::EventFlags::POLLIN)];
+        loop {
+            match poll::poll(&mut fds, ms.min(i32::MAX as u64) as i32) {
+                Ok(n) => return Ok(n > 0),
+                Err(::nix::Error::Sys(Errno::EINTR)) => continue,
+                Err(e) => return Err(nix_to_io(e)),
+            }
+        }
+    }
+}
+
+fn nix_to_io(e: ::nix::Error) -> io::Error {
+    match e {
+        ::nix::Error::Sys(errno) => io::Error::from_raw_os_error(errno as i32),
+        e => io::Error::other(e),
+    }
+}
+
+impl MockBackend {
+    /// Create an 80x24 tty, in the usual cooked mode, with nothing to read.
+    pub fn new() -> MockBackend {
+        MockBackend {
+            state: Arc::new(Mutex::new(MockState {
+                input: VecDeque::new(),
+                delayed: VecDeque::new(),
+                output: Vec::new(),
+                size: (80, 24),
+                tty: true,
+                settings: Settings::cooked(),
+            })),
+        }
+    }
+
+    /// Create a `Term` for this terminal, described by `info`.
+    pub fn term(&self, info: terminfo::TermInfoBuf) -> Term<MockBackend, MockBackend> {
+        Term::with_backend(info, self.clone(), self.clone(), self.clone())
+    }
+
+    /// Add `bytes` to the end of the input.
+    pub fn push_input(&self, bytes: &[u8]) {
+        self.state().input.extend(bytes);
+    }
+
+    /// Add `bytes` to the end of the input once `delay` has passed, but not before input pushed earlier. Waiting for
+    /// input waits for them, while waiting with nothing on the way gives up straight away, since nothing can arrive.
+    pub fn push_input_after(&self, delay: Duration, bytes: &[u8]) {
+        let mut state = self.state();
+        let mut at = Instant::now() + delay;
+        if let Some(&(last, _)) = state.delayed.back() {
+            at = at.max(last);
+        }
+        state.delayed.push_back((at, bytes.to_vec()));
+    }
+
+    /// Everything that's been written so far.
+    pub fn written(&self) -> Vec<u8> {
+        self.state().output.clone()
+    }
+
+    /// Everything that's been written since the last call, which is then forgotten.
+    pub fn take_written(&self) -> Vec<u8> {
+        let mut state = self.state();
+        ::std::mem::take(&mut state.output)
+    }
+
+    /// True if `bytes` appear anywhere in what's been written.
+    pub fn written_contains(&self, bytes: &[u8]) -> bool {
+        bytes.is_empty() || self.state().output.windows(bytes.len()).any(|w| w == bytes)
+    }
+
+    /// Change the terminal's size, as (columns, rows).
+    pub fn set_size(&self, cols: usize, rows: usize) {
+        self.state().size = (cols, rows);
+    }
+
+    /// Choose whether the terminal is a tty. When it isn't, its settings and size can't be used, just like a pipe.
+    pub fn set_tty(&self, tty: bool) {
+        self.state().tty = tty;
+    }
+
+    /// The terminal's current settings, as last set by the `Term`.
+    pub fn settings(&self) -> Settings {
+        self.state().settings.clone()
+    }
+
+    /// Lock the terminal's state, once any delayed input that's due has arrived.
+    fn state(&self) -> MutexGuard<'_, MockState> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        while state.delayed.front().is_some_and(|&(at, _)| at <= now) {
+            let (_, bytes) = state.delayed.pop_front().unwrap();
+            state.input.extend(bytes);
+        }
+        state
+    }
+
+    fn tty_state(&self) -> io::Result<MutexGuard<'_, MockState>> {
+        let state = self.state();
+        if state.tty {
+            Ok(state)
+        } else {
+            Err(io::Error::from_raw_os_error(Errno::ENOTTY as i32))
+        }
+    }
+}
+
+impl Default for MockBackend {
+    fn default() -> MockBackend {
+        MockBackend::new()
+    }
+}
+
+impl TermBackend for MockBackend {
+    fn is_tty(&self) -> bool {
+        self.state().tty
+    }
+
+    fn size(&self) -> io::Result<(usize, usize)> {
+        Ok(self.tty_state()?.size)
+    }
+
+    fn attributes(&self) -> io::Result<Settings> {
+        Ok(self.tty_state()?.settings.clone())
+    }
+
+    fn set_attributes(&self, settings: &Settings) -> io::Result<()> {
+        self.tty_state()?.settings = settings.clone();
+        Ok(())
+    }
+
+    fn set_attributes_keeping_input(&self, settings: &Settings) -> io::Result<()> {
+        self.set_attributes(settings)
+    }
+
+    fn poll_input(&self, timeout: Duration) -> io::Result<bool> {
+        let next = {
+            let state = self.state();
+            match state.delayed.front() {
+                _ if !state.input.is_empty() => return Ok(true),
+                Some(&(at, _)) => at,
+                None => return Ok(false),
+            }
+        };
+        thread::sleep(next.saturating_duration_since(Instant::now()).min(timeout));
+        Ok(!self.state().input.is_empty())
+    }
+}
+
+impl io::Read for MockBackend {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut state = self.state();
+        let len = buf.len().min(state.input.len());
+        for (i, c) in state.input.drain(..len).enumerate() {
+            buf[i] = c;
+        }
+        Ok(len)
+    }
+}
+
+impl io::Write for MockBackend {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.state().output.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// There's no file descriptor behind a mock, anything that uses it directly (like `EventStream`) fails.
+impl AsRawFd for MockBackend {
+    fn as_raw_fd(&self) -> RawFd {
+        -1
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use backend::{MockBackend, TermBackend};
+    use errors::Error;
+    use std::io::Write;
+    use std::time::Duration;
+    use terminfo;
+
+    const TERMINFO: &'static [u8] = include_bytes!("../test-data/xterm");
+
+    fn xterm() -> terminfo::TermInfoBuf {
+        terminfo::TermInfo::parse(TERMINFO).unwrap().into()
+    }
+
+    #[test]
+    fn mock() {
+        let mock = MockBackend::new();
+        let term = mock.term(xterm());
+        term.set_env_overrides(::EnvOverrides::from_map(Vec::<(String, String)>::new()));
+
+        assert!(term.is_tty_out());
+        mock.set_size(132, 50);
+        assert_eq!(term.size().unwrap(), (132, 50));
+
+        // settings go to the mock, and newlines follow them
+        assert!(term.translates_newlines());
+        term.update(term.settings().raw()).unwrap();
+        assert!(!mock.settings().translates_newlines());
+        term.styled_writer().write_all(b"a\n").unwrap();
+        assert_eq!(mock.take_written(), b"a\r\n");
+        assert!(mock.written().is_empty());
+
+        assert!(!mock.poll_input(Duration::from_millis(0)).unwrap());
+        mock.push_input(b"hi");
+        assert!(mock.poll_input(Duration::from_millis(0)).unwrap());
+        let mut buf = [0u8; 8];
+        assert_eq!(term.read(&mut buf), 2);
+        assert_eq!(&buf[..2], b"hi");
+        assert_eq!(term.read(&mut buf), 0);
+
+        term.print("hello there").unwrap();
+        assert!(mock.written_contains(b"o th"));
+        assert!(!mock.written_contains(b"hi"));
+    }
+
+    #[test]
+    fn not_a_tty() {
+        let mock = MockBackend::new();
+        mock.set_tty(false);
+        let term = mock.term(terminfo::TermInfoBuf::new());
+        term.set_env_overrides(::EnvOverrides::from_map(Vec::<(String, String)>::new()));
+
+        assert!(!term.is_tty_out());
+        assert!(term.translates_newlines());
+        match term.size() {
+            Err(Error::NotATty) => (),
+            r => panic!("expected NotATty, got {:?}", r),
+        }
+        let settings = term.settings();
+        assert!(term.update(settings).is_err());
+    }
+}
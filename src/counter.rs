@@ -0,0 +1,78 @@
+//! Counting what's written to the terminal, see `Term::io_stats`.
+use std::io;
+use term::IoStats;
+
+/// A stream that counts the writes made to it, and how much they wrote.
+pub(crate) struct Counted<W> {
+    inner: W,
+    stats: IoStats,
+}
+
+impl<W> Counted<W> {
+    pub(crate) fn new(inner: W) -> Counted<W> {
+        Counted {
+            inner,
+            stats: IoStats::default(),
+        }
+    }
+
+    pub(crate) fn stats(&self) -> IoStats {
+        self.stats
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.stats = IoStats::default();
+    }
+
+    fn count(&mut self, written: &io::Result<usize>) {
+        self.stats.writes += 1;
+        if let Ok(n) = *written {
+            self.stats.bytes += n as u64;
+        }
+    }
+}
+
+impl<W: io::Write> io::Write for Counted<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf);
+        self.count(&written);
+        written
+    }
+
+    fn write_vectored(&mut self, bufs: &[io::IoSlice]) -> io::Result<usize> {
+        let written = self.inner.write_vectored(bufs);
+        self.count(&written);
+        self.stats.vectored_writes += 1;
+        written
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use counter::*;
+    use std::io::Write;
+
+    #[test]
+    fn counts() {
+        let mut out = Counted::new(Vec::new());
+        out.write_all(b"abc").unwrap();
+        out.write_vectored(&[io::IoSlice::new(b"de"), io::IoSlice::new(b"f")])
+            .unwrap();
+        assert_eq!(
+            out.stats(),
+            IoStats {
+                writes: 2,
+                vectored_writes: 1,
+                bytes: 6,
+            }
+        );
+        assert_eq!(out.inner, b"abcdef");
+
+        out.reset();
+        assert_eq!(out.stats(), IoStats::default());
+    }
+}
@@ -0,0 +1,412 @@
+//! Moving the cursor in as few bytes as the terminal allows, like curses' `mvcur`.
+//!
+//! Sending `cup` for every move works, but it's usually the longest way there. Moving along a row can be a few
+//! `cub1` backspaces, starting a line can be a `cr` or a `cud1` newline, and moving along one axis can be `hpa` or
+//! `vpa`. `CursorOptimizer` tries every way the entry has, and picks the shortest.
+//!
+//! Some moves depend on more than the entry:
+//!
+//! - xterm's `cud1` is a newline, which the tty turns into `\r\n` if `ONLCR` is set. A `cud1` like that is only used
+//!   once `CursorOptimizer::translates_newlines` says which one happens.
+//! - Text that reaches the right margin may leave the cursor on the next line (`am`), or in a half-wrapped state where
+//!   no relative move is safe (`xenl`). `CursorOptimizer::after_text` works out which.
+use terminfo::lang::{Argument, Program};
+use terminfo::wrap::WrapBehavior;
+use terminfo::TermInfoBuf;
+use terminfo::{CarriageReturn, ColumnAddress, CursorAddress, CursorHome, RowAddress};
+use terminfo::{CursorDown, CursorLeft, CursorRight, CursorUp};
+use terminfo::{ParmDownCursor, ParmLeftCursor, ParmRightCursor, ParmUpCursor};
+
+/// Picks the shortest way to move the cursor, out of the capabilities an entry has.
+///
+/// ```
+/// use nixterm::cursor::CursorOptimizer;
+/// use nixterm::terminfo::{CarriageReturn, CursorAddress, CursorLeft, TermInfoBuilder};
+///
+/// let info = TermInfoBuilder::new()
+///     .name("example")
+///     .set_str(CursorAddress, "\x1b[%i%p1%d;%p2%dH")
+///     .set_str(CarriageReturn, "\r")
+///     .set_str(CursorLeft, "\x08")
+///     .build()
+///     .unwrap();
+/// let moves = CursorOptimizer::new(&info);
+/// assert_eq!(moves.movement(Some((10, 4)), (8, 4)).unwrap(), b"\x08\x08");
+/// assert_eq!(moves.movement(Some((10, 4)), (0, 4)).unwrap(), b"\r");
+/// assert_eq!(moves.movement(None, (0, 4)).unwrap(), b"\x1b[5;1H");
+/// ```
+pub struct CursorOptimizer<'a> {
+    cup: Option<Program<'a>>,
+    hpa: Option<Program<'a>>,
+    vpa: Option<Program<'a>>,
+    cud: Option<Program<'a>>,
+    cuu: Option<Program<'a>>,
+    cuf: Option<Program<'a>>,
+    cub: Option<Program<'a>>,
+    cud1: Option<&'a [u8]>,
+    cuu1: Option<&'a [u8]>,
+    cuf1: Option<&'a [u8]>,
+    cub1: Option<&'a [u8]>,
+    cr: Option<&'a [u8]>,
+    home: Option<&'a [u8]>,
+    /// Whether the tty turns `\n` into `\r\n`, if it's known.
+    newline: Option<bool>,
+    wrap: WrapBehavior,
+}
+
+impl<'a> CursorOptimizer<'a> {
+    /// Use the movement capabilities in `info`. Whether the tty translates newlines isn't known yet, so a `cud1`
+    /// that's a newline isn't used.
+    pub fn new(info: &'a TermInfoBuf) -> CursorOptimizer<'a> {
+        let program = |field| {
+            info.string_bytes(field)
+                .and_then(|s| Program::compile(s).ok())
+        };
+        CursorOptimizer {
+            cup: program(CursorAddress),
+            hpa: program(ColumnAddress),
+            vpa: program(RowAddress),
+            cud: program(ParmDownCursor),
+            cuu: program(ParmUpCursor),
+            cuf: program(ParmRightCursor),
+            cub: program(ParmLeftCursor),
+            cud1: info.string_bytes(CursorDown),
+            cuu1: info.string_bytes(CursorUp),
+            cuf1: info.string_bytes(CursorRight),
+            cub1: info.string_bytes(CursorLeft),
+            cr: info.string_bytes(CarriageReturn),
+            home: info.string_bytes(CursorHome),
+            newline: None,
+            wrap: info.wrap_behavior(),
+        }
+    }
+
+    /// Say whether the tty turns `\n` into `\r\n`, see `Term::translates_newlines`.
+    pub fn translates_newlines(mut self, translates: bool) -> Self {
+        self.newline = Some(translates);
+        self
+    }
+
+    /// The shortest bytes that move the cursor from `from` to `to`, both (column, row) counted from 0. `from` is
+    /// `None` if where the cursor is isn't known, then only `cup`, or `home` and moves from the top left, are used.
+    ///
+    /// Returns `None` if the entry has no way to get there.
+    pub fn movement(&self, from: Option<(usize, usize)>, to: (usize, usize)) -> Option<Vec<u8>> {
+        self.plan(from, to).map(|plan| plan.bytes())
+    }
+
+    /// How many bytes `movement` would be, without expanding any of it.
+    pub fn movement_cost(&self, from: Option<(usize, usize)>, to: (usize, usize)) -> Option<usize> {
+        self.plan(from, to).map(|plan| plan.cost)
+    }
+
+    /// Where the cursor is after drawing `width` columns of text at `at`, on a screen `size` (columns, rows) big.
+    ///
+    /// Returns `None` if it isn't known: when text runs past the margin, or with `xenl` where the terminal waits
+    /// for the next character to decide whether to wrap, or when wrapping off the bottom row scrolls the screen. With
+    /// `sam` it isn't known either.
+    pub fn after_text(
+        &self,
+        at: (usize, usize),
+        width: usize,
+        size: (usize, usize),
+    ) -> Option<(usize, usize)> {
+        let (x, y) = at;
+        let (cols, rows) = size;
+        match x + width {
+            end if end < cols => Some((end, y)),
+            end if end > cols => None,
+            _ if !self.wrap.auto_margins => Some((cols - 1, y)),
+            _ if self.wrap.eat_newline || self.wrap.semi_auto_margins || y + 1 >= rows => None,
+            _ => Some((0, y + 1)),
+        }
+    }
+
+    /// The cheapest way from `from` to `to`, see `movement`.
+    fn plan(&self, from: Option<(usize, usize)>, to: (usize, usize)) -> Option<Plan<'_>> {
+        if from == Some(to) {
+            return Some(Plan::default());
+        }
+        let (x, y) = to;
+        cheapest(vec![
+            Plan::param(&self.cup, &[y, x]),
+            from.and_then(|from| self.relative(from, to)),
+            self.home.and_then(|home| {
+                self.relative((0, 0), to)
+                    .map(|rest| Plan::repeat(home, 1).then(rest))
+            }),
+        ])
+    }
+
+    /// The cheapest way from `from` to `to` that doesn't start from the top left.
+    fn relative(&self, from: (usize, usize), to: (usize, usize)) -> Option<Plan<'_>> {
+        let ((fx, fy), (tx, ty)) = (from, to);
+        // each way of getting to the row, with the column it leaves the cursor in
+        let mut rows = Vec::new();
+        if fy == ty {
+            rows.push((Some(Plan::default()), fx));
+        } else {
+            rows.push((Plan::param(&self.vpa, &[ty]), fx));
+        }
+        if ty > fy {
+            let n = ty - fy;
+            rows.push((Plan::param(&self.cud, &[n]), fx));
+            match (self.cud1, self.newline) {
+                (Some(b"\n"), None) => (),
+                (Some(b"\n"), Some(true)) => rows.push((self.cud1.map(|s| Plan::repeat(s, n)), 0)),
+                (cud1, _) => rows.push((cud1.map(|s| Plan::repeat(s, n)), fx)),
+            }
+        }
+        if ty < fy {
+            let n = fy - ty;
+            rows.push((Plan::param(&self.cuu, &[n]), fx));
+            rows.push((self.cuu1.map(|s| Plan::repeat(s, n)), fx));
+        }
+
+        cheapest(
+            rows.into_iter()
+                .map(|(row, x)| {
+                    let column = self.column(x, tx)?;
+                    row.map(|row| row.then(column))
+                })
+                .collect(),
+        )
+    }
+
+    /// The cheapest way from column `from` to column `to`, along the same row.
+    fn column(&self, from: usize, to: usize) -> Option<Plan<'_>> {
+        if from == to {
+            return Some(Plan::default());
+        }
+        let mut moves = vec![Plan::param(&self.hpa, &[to])];
+        if let Some(cr) = self.cr {
+            let forward = if to == 0 {
+                Some(Plan::default())
+            } else {
+                self.forward(to)
+            };
+            moves.push(forward.map(|forward| Plan::repeat(cr, 1).then(forward)));
+        }
+        if to > from {
+            moves.push(self.forward(to - from));
+        } else {
+            moves.push(cheapest(vec![
+                Plan::param(&self.cub, &[from - to]),
+                self.cub1.map(|s| Plan::repeat(s, from - to)),
+            ]));
+        }
+        cheapest(moves)
+    }
+
+    fn forward(&self, n: usize) -> Option<Plan<'_>> {
+        cheapest(vec![
+            Plan::param(&self.cuf, &[n]),
+            self.cuf1.map(|s| Plan::repeat(s, n)),
+        ])
+    }
+}
+
+/// A way of moving the cursor, as the capabilities to send and what they cost together. Only the one that's picked
+/// is expanded.
+#[derive(Clone, Default)]
+struct Plan<'p> {
+    steps: Vec<Step<'p>>,
+    cost: usize,
+}
+
+#[derive(Clone)]
+enum Step<'p> {
+    /// A parameterized capability, with its arguments.
+    Param(&'p Program<'p>, Vec<Argument>),
+    /// A string, sent some number of times.
+    Repeat(&'p [u8], usize),
+}
+
+impl<'p> Plan<'p> {
+    /// Send `program` with `args`. One that fails to expand is treated like it's missing.
+    fn param(program: &'p Option<Program<'p>>, args: &[usize]) -> Option<Plan<'p>> {
+        let program = program.as_ref()?;
+        let args: Vec<Argument> = args.iter().map(|&n| Argument::Integer(n as i64)).collect();
+        let cost = Some(program.cost(&args)).filter(|&cost| cost != usize::MAX)?;
+        Some(Plan {
+            steps: vec![Step::Param(program, args)],
+            cost,
+        })
+    }
+
+    /// Send `s` `n` times.
+    fn repeat(s: &'p [u8], n: usize) -> Plan<'p> {
+        Plan {
+            steps: vec![Step::Repeat(s, n)],
+            cost: s.len() * n,
+        }
+    }
+
+    /// This, then `next`.
+    fn then(mut self, next: Plan<'p>) -> Plan<'p> {
+        self.steps.extend(next.steps);
+        self.cost += next.cost;
+        self
+    }
+
+    fn bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.cost);
+        for step in &self.steps {
+            match *step {
+                // it's already been expanded once to work out the cost, so this can't fail
+                Step::Param(program, ref args) => {
+                    let _ = program.exec_into(args, &mut out);
+                }
+                Step::Repeat(s, n) => out.extend_from_slice(&s.repeat(n)),
+            }
+        }
+        out
+    }
+}
+
+/// The cheapest of `moves`, the first one if there's a tie.
+fn cheapest(moves: Vec<Option<Plan>>) -> Option<Plan> {
+    moves.into_iter().flatten().min_by_key(|m| m.cost)
+}
+
+#[cfg(test)]
+mod test {
+    use cursor::*;
+    use terminfo::{AutoRightMargin, TermInfo, TermInfoBuilder};
+
+    const XTERM_256_INFO: &'static [u8] = include_bytes!("../test-data/xterm-256color");
+
+    type Move = (Option<(usize, usize)>, (usize, usize), &'static [u8]);
+
+    fn check(moves: &CursorOptimizer, table: &[Move]) {
+        for &(from, to, expected) in table {
+            assert_eq!(
+                moves.movement(from, to).as_ref().map(|m| &m[..]),
+                Some(expected),
+                "{:?} to {:?}",
+                from,
+                to
+            );
+            assert_eq!(moves.movement_cost(from, to), Some(expected.len()));
+        }
+    }
+
+    #[test]
+    fn xterm() {
+        let info = TermInfoBuf::from(TermInfo::parse(XTERM_256_INFO).unwrap());
+        let moves = CursorOptimizer::new(&info).translates_newlines(true);
+        check(
+            &moves,
+            &[
+                (None, (0, 0), b"\x1b[H"),
+                (None, (10, 5), b"\x1b[6;11H"),
+                (None, (0, 2), b"\x1b[H\n\n"),
+                (Some((0, 5)), (0, 5), b""),
+                (Some((5, 5)), (6, 5), b"\x1b[C"),
+                (Some((5, 5)), (3, 5), b"\x08\x08"),
+                (Some((5, 5)), (0, 5), b"\r"),
+                (Some((5, 5)), (0, 6), b"\n"),
+                (Some((5, 5)), (0, 8), b"\n\n\n"),
+                (Some((5, 5)), (2, 6), b"\n\x1b[3G"),
+                (Some((5, 5)), (5, 2), b"\x1b[3d"),
+                (Some((5, 5)), (5, 4), b"\x1b[A"),
+                (Some((70, 5)), (2, 5), b"\x1b[3G"),
+                (Some((70, 5)), (65, 5), b"\x1b[5D"),
+                (Some((79, 20)), (0, 0), b"\x1b[H"),
+                (Some((3, 0)), (3, 23), b"\x1b[24d"),
+                (Some((40, 2)), (10, 20), b"\x1b[21;11H"),
+            ],
+        );
+
+        // without ONLCR a newline keeps the column
+        let raw = CursorOptimizer::new(&info).translates_newlines(false);
+        check(
+            &raw,
+            &[
+                (Some((5, 5)), (5, 6), b"\n"),
+                (Some((5, 5)), (0, 6), b"\n\r"),
+                (Some((5, 5)), (4, 7), b"\n\n\x08"),
+            ],
+        );
+
+        // and if it isn't known, whether it does, newlines aren't used at all
+        let unknown = CursorOptimizer::new(&info);
+        check(
+            &unknown,
+            &[
+                (Some((5, 5)), (5, 6), b"\x1b[7d"),
+                (Some((5, 5)), (0, 6), b"\x1b[7d\r"),
+            ],
+        );
+    }
+
+    #[test]
+    fn sparse() {
+        let info = TermInfoBuilder::new()
+            .name("sparse")
+            .set_bool(AutoRightMargin, true)
+            .set_str(CursorAddress, "\x1b[%i%p1%d;%p2%dH")
+            .set_str(CarriageReturn, "\r")
+            .set_str(CursorRight, "\x1b[C")
+            .set_str(CursorDown, "\n")
+            .build()
+            .unwrap();
+        let moves = CursorOptimizer::new(&info).translates_newlines(true);
+        check(
+            &moves,
+            &[
+                (None, (0, 0), b"\x1b[1;1H"),
+                (Some((5, 5)), (0, 5), b"\r"),
+                (Some((5, 5)), (6, 5), b"\x1b[C"),
+                // a tie goes to `cup`
+                (Some((5, 5)), (7, 5), b"\x1b[6;8H"),
+                (Some((5, 5)), (9, 5), b"\x1b[6;10H"),
+                (Some((5, 5)), (3, 5), b"\x1b[6;4H"),
+                (Some((5, 5)), (1, 5), b"\r\x1b[C"),
+                (Some((5, 5)), (0, 6), b"\n"),
+                (Some((5, 5)), (1, 7), b"\n\n\x1b[C"),
+                (Some((5, 5)), (5, 4), b"\x1b[5;6H"),
+            ],
+        );
+
+        // with nothing but `cr`, only the start of the row can be reached
+        let info = TermInfoBuilder::new()
+            .name("sparser")
+            .set_str(CarriageReturn, "\r")
+            .build()
+            .unwrap();
+        let moves = CursorOptimizer::new(&info);
+        assert_eq!(moves.movement(Some((5, 5)), (0, 5)), Some(b"\r".to_vec()));
+        assert_eq!(moves.movement(Some((5, 5)), (5, 5)), Some(vec![]));
+        assert_eq!(moves.movement(Some((5, 5)), (1, 5)), None);
+        assert_eq!(moves.movement(Some((5, 5)), (0, 6)), None);
+        assert_eq!(moves.movement(None, (0, 0)), None);
+    }
+
+    #[test]
+    fn after_text() {
+        let xterm = TermInfoBuf::from(TermInfo::parse(XTERM_256_INFO).unwrap());
+        let xterm = CursorOptimizer::new(&xterm);
+        let size = (80, 24);
+        assert_eq!(xterm.after_text((10, 3), 5, size), Some((15, 3)));
+        assert_eq!(xterm.after_text((78, 3), 1, size), Some((79, 3)));
+        // xterm waits to wrap until the next character
+        assert_eq!(xterm.after_text((78, 3), 2, size), None);
+        assert_eq!(xterm.after_text((79, 3), 2, size), None);
+
+        let wraps = TermInfoBuilder::new()
+            .name("wraps")
+            .set_bool(AutoRightMargin, true)
+            .build()
+            .unwrap();
+        let wraps = CursorOptimizer::new(&wraps);
+        assert_eq!(wraps.after_text((78, 3), 2, size), Some((0, 4)));
+        // wrapping off the bottom scrolls
+        assert_eq!(wraps.after_text((78, 23), 2, size), None);
+
+        let stops = TermInfoBuf::new();
+        let stops = CursorOptimizer::new(&stops);
+        assert_eq!(stops.after_text((78, 3), 2, size), Some((79, 3)));
+        assert_eq!(stops.after_text((78, 23), 2, size), Some((79, 23)));
+    }
+}
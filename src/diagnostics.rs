@@ -0,0 +1,354 @@
+//! Gathering what's known about the terminal, for bug reports.
+//!
+//! Problems like "colors look wrong" depend on the terminal, its terminfo entry and the environment, none of which
+//! the person triaging the issue can see. `probe` collects all of it, and `ProbeReport`'s `Display` output is plain
+//! text meant to be pasted into an issue as it is.
+//!
+//! ```no_run
+//! use nixterm::{diagnostics, Term};
+//!
+//! let term = Term::new().unwrap();
+//! println!("{}", diagnostics::probe(&term));
+//! ```
+use ansi::{self, ColorDepth};
+use query::{self, ModeState, TerminalId};
+use std::fmt;
+use std::io;
+use std::os::unix::io::AsRawFd;
+use std::path::PathBuf;
+use std::time::Duration;
+use term::Term;
+use terminfo::{self, BooleanField, NumericField, StringField};
+
+/// How long `probe` waits for each reply.
+pub const PROBE_TIMEOUT: Duration = Duration::from_millis(200);
+
+const VARS: &[&str] = &["TERM", "COLORTERM", "TERM_PROGRAM", "TERM_PROGRAM_VERSION"];
+
+const NUMBERS: &[NumericField] = &[
+    terminfo::Columns,
+    terminfo::Lines,
+    terminfo::MaxColors,
+    terminfo::MaxPairs,
+    terminfo::InitTabs,
+];
+
+const FLAGS: &[BooleanField] = &[
+    terminfo::AutoRightMargin,
+    terminfo::BackColorErase,
+    terminfo::CanChange,
+];
+
+const STRINGS: &[StringField] = &[
+    terminfo::CursorAddress,
+    terminfo::SetAForeground,
+    terminfo::SetABackground,
+    terminfo::SetAttributes,
+    terminfo::InitializeColor,
+    terminfo::EnterCaMode,
+    terminfo::KeypadXmit,
+];
+
+/// The extended capabilities for truecolor and the other things terminfo(5) doesn't cover.
+const EXTENDED: &[&str] = &[
+    "Tc", "RGB", "setrgbf", "setrgbb", "Smulx", "Setulc", "Ss", "Se", "Sync", "XT",
+];
+
+/// The private modes nixterm uses.
+const MODES: &[(u32, &str)] = &[
+    (ansi::MODE_REVERSE_VIDEO, "reverse video"),
+    (ansi::MODE_ALT_SCREEN, "alternate screen"),
+    (ansi::MODE_BRACKETED_PASTE, "bracketed paste"),
+    (ansi::MODE_SYNCHRONIZED_OUTPUT, "synchronized output"),
+];
+
+/// Everything `probe` found out, see the module documentation.
+#[derive(Debug, Clone)]
+pub struct ProbeReport {
+    /// `$TERM`, `$COLORTERM`, `$TERM_PROGRAM` and `$TERM_PROGRAM_VERSION`, through the `Term`'s `EnvOverrides`.
+    pub vars: Vec<(&'static str, Option<String>)>,
+    /// The terminfo file `$TERM` names, which is the one `Term::new` loads.
+    pub database: Option<PathBuf>,
+    /// The names of the `Term`'s terminfo entry.
+    pub entry_names: Vec<String>,
+    pub numbers: Vec<(&'static str, Option<u32>)>,
+    pub flags: Vec<(&'static str, bool)>,
+    /// Whether some important strings are defined.
+    pub strings: Vec<(&'static str, bool)>,
+    /// Whether some extended capabilities are defined, whatever their type.
+    pub extended: Vec<(&'static str, bool)>,
+    pub color_depth: ColorDepth,
+    pub is_tty: bool,
+    /// The terminal's size as (columns, rows), or why it couldn't be found.
+    pub size: Result<(usize, usize), String>,
+    /// The attributes from the primary device attributes reply, or `None` if there wasn't one.
+    pub primary_da: Option<Vec<u32>>,
+    pub secondary_da: Option<(u32, u32, u32)>,
+    pub xtversion: Option<String>,
+    pub identity: TerminalId,
+    /// The state of each mode nixterm uses, or `None` if the terminal didn't answer (or wasn't asked).
+    pub modes: Vec<(u32, &'static str, Option<ModeState>)>,
+}
+
+/// Find out everything about `term` that's useful in a bug report.
+///
+/// Queries are only sent if the output is a tty, and each one waits at most `PROBE_TIMEOUT`. The modes are only
+/// asked about if the terminal answered the device attributes query, so a terminal that doesn't answer anything
+/// costs a single timeout. Replies to the queries are read from `term`'s input, anything else read in the meantime
+/// is kept for the next read.
+pub fn probe<I, O>(term: &Term<I, O>) -> ProbeReport
+where
+    I: io::Read + AsRawFd,
+    O: io::Write + AsRawFd,
+{
+    let info = &term.info;
+    let is_tty = term.is_tty_out();
+    let answers = if is_tty {
+        term.query_identity(Some(PROBE_TIMEOUT)).unwrap_or_default()
+    } else {
+        query::Answers::default()
+    };
+    let modes = MODES
+        .iter()
+        .map(|&(mode, name)| {
+            let state = answers
+                .primary
+                .as_ref()
+                .and_then(|_| term.query_private_mode(mode, PROBE_TIMEOUT).ok());
+            (mode, name, state)
+        })
+        .collect();
+
+    let var = |name: &str| term.env_var(name);
+    let database = var("TERM").and_then(|name| terminfo::find(&name));
+    ProbeReport {
+        vars: VARS.iter().map(|&name| (name, var(name))).collect(),
+        database,
+        entry_names: info.names.clone(),
+        numbers: NUMBERS
+            .iter()
+            .map(|&field| (field.info().terminfo_name, info.number(field)))
+            .collect(),
+        flags: FLAGS
+            .iter()
+            .map(|&field| (field.info().terminfo_name, info.boolean(field)))
+            .collect(),
+        strings: STRINGS
+            .iter()
+            .map(|&field| (field.info().terminfo_name, info.string(field).is_some()))
+            .collect(),
+        extended: EXTENDED
+            .iter()
+            .map(|&name| {
+                let defined = info.ext_boolean(name)
+                    || info.ext_number(name).is_some()
+                    || info.ext_string(name).is_some();
+                (name, defined)
+            })
+            .collect(),
+        color_depth: term.color_depth(),
+        is_tty,
+        size: term.size().map_err(|e| e.to_string()),
+        identity: query::identify(
+            &answers,
+            var("TERM").as_deref(),
+            var("TERM_PROGRAM").as_deref(),
+            var("TERM_PROGRAM_VERSION").as_deref(),
+        ),
+        primary_da: answers.primary,
+        secondary_da: answers.secondary,
+        xtversion: answers.version,
+        modes,
+    }
+}
+
+/// Write the names in `caps` that are (or aren't) defined, separated by spaces.
+fn names(f: &mut fmt::Formatter, caps: &[(&str, bool)], defined: bool) -> fmt::Result {
+    let mut any = false;
+    for &(name, _) in caps.iter().filter(|&&(_, d)| d == defined) {
+        write!(f, " {}", name)?;
+        any = true;
+    }
+    if !any {
+        write!(f, " (none)")?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for ProbeReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "nixterm {} terminal report", env!("CARGO_PKG_VERSION"))?;
+        for &(name, ref value) in &self.vars {
+            match *value {
+                Some(ref value) => writeln!(f, "${}: {:?}", name, value)?,
+                None => writeln!(f, "${}: (unset)", name)?,
+            }
+        }
+        match self.database {
+            Some(ref path) => writeln!(f, "terminfo file: {}", path.display())?,
+            None => writeln!(f, "terminfo file: (not found)")?,
+        }
+        writeln!(f, "entry: {}", self.entry_names.join("|"))?;
+
+        write!(f, "numbers:")?;
+        for &(name, value) in &self.numbers {
+            match value {
+                Some(n) => write!(f, " {}#{}", name, n)?,
+                None => write!(f, " {}@", name)?,
+            }
+        }
+        write!(f, "\nflags:")?;
+        names(f, &self.flags, true)?;
+        write!(f, "\nstrings:")?;
+        names(f, &self.strings, true)?;
+        write!(f, "\nmissing strings:")?;
+        names(f, &self.strings, false)?;
+        write!(f, "\nextended:")?;
+        names(f, &self.extended, true)?;
+        writeln!(f)?;
+
+        writeln!(f, "color depth: {:?}", self.color_depth)?;
+        writeln!(
+            f,
+            "output is a tty: {}",
+            if self.is_tty { "yes" } else { "no" }
+        )?;
+        match self.size {
+            Ok((cols, rows)) => writeln!(f, "size: {}x{}", cols, rows)?,
+            Err(ref e) => writeln!(f, "size: unknown ({})", e)?,
+        }
+
+        match self.primary_da {
+            Some(ref attrs) => {
+                let attrs: Vec<String> = attrs.iter().map(|a| a.to_string()).collect();
+                writeln!(f, "DA1: {}", attrs.join(";"))?
+            }
+            None => writeln!(f, "DA1: (no reply)")?,
+        }
+        match self.secondary_da {
+            Some((kind, version, rom)) => writeln!(f, "DA2: {};{};{}", kind, version, rom)?,
+            None => writeln!(f, "DA2: (no reply)")?,
+        }
+        match self.xtversion {
+            Some(ref v) => writeln!(f, "XTVERSION: {:?}", v)?,
+            None => writeln!(f, "XTVERSION: (no reply)")?,
+        }
+        write!(f, "identified as: {:?}", self.identity.family)?;
+        match self.identity.version {
+            Some(ref v) => writeln!(f, " {}", v)?,
+            None => writeln!(f)?,
+        }
+
+        for &(mode, name, state) in &self.modes {
+            match state {
+                Some(state) => writeln!(f, "mode {} ({}): {:?}", mode, name, state)?,
+                None => writeln!(f, "mode {} ({}): (no reply)", mode, name)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use backend::MockBackend;
+    use diagnostics::*;
+    use terminfo;
+
+    const TERMINFO: &'static [u8] = include_bytes!("../test-data/xterm");
+
+    fn mock_tty() -> MockBackend {
+        let mock = MockBackend::new();
+        mock.set_size(120, 40);
+        mock
+    }
+
+    fn env() -> ::EnvOverrides {
+        ::EnvOverrides::from_map(vec![("TERM", "xterm"), ("COLORTERM", "truecolor")])
+    }
+
+    #[test]
+    fn scripted() {
+        let mock = mock_tty();
+        let term = mock.term(terminfo::TermInfo::parse(TERMINFO).unwrap().into());
+        term.set_env_overrides(env());
+        mock.push_input(b"\x1bP>|XTerm(354)\x1b\\\x1b[>41;354;0c\x1b[?64;1;4;22c");
+        mock.push_input(b"\x1b[?5;2$y\x1b[?1049;1$y\x1b[?2004;2$y\x1b[?2026;0$y");
+        mock.push_input(b"q");
+
+        let report = probe(&term);
+        assert_eq!(report.primary_da, Some(vec![1, 4, 22]));
+        assert_eq!(report.secondary_da, Some((41, 354, 0)));
+        assert_eq!(report.xtversion.as_ref().unwrap(), "XTerm(354)");
+        assert_eq!(report.identity.version.as_ref().unwrap(), "354");
+        assert_eq!(report.size, Ok((120, 40)));
+        assert_eq!(report.color_depth, ansi::ColorDepth::TrueColor);
+        assert_eq!(
+            report.modes.iter().map(|m| m.2).collect::<Vec<_>>(),
+            vec![
+                Some(ModeState::Reset),
+                Some(ModeState::Set),
+                Some(ModeState::Reset),
+                Some(ModeState::NotRecognized),
+            ]
+        );
+        // the queries were sent, and the key that came with the replies is still there to read
+        assert!(mock.written_contains(ansi::REQUEST_PRIMARY_DA));
+        assert!(mock.written_contains(b"\x1b[?2026$p"));
+        let mut buf = [0u8; 8];
+        assert_eq!(term.read(&mut buf), 1);
+        assert_eq!(buf[0], b'q');
+
+        let text = report.to_string();
+        for line in &[
+            "$TERM: \"xterm\"",
+            "$TERM_PROGRAM: (unset)",
+            "entry: xterm|xterm terminal emulator (X Window System)",
+            "numbers: cols#80 lines#24 colors#8 pairs#64 it#8",
+            "size: 120x40",
+            "DA1: 1;4;22",
+            "DA2: 41;354;0",
+            "XTVERSION: \"XTerm(354)\"",
+            "identified as: Xterm 354",
+            "mode 1049 (alternate screen): Set",
+            "mode 2026 (synchronized output): NotRecognized",
+        ] {
+            assert!(
+                text.lines().any(|l| l == *line),
+                "{:?} isn't in\n{}",
+                line,
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn unresponsive() {
+        let mock = mock_tty();
+        let term = mock.term(terminfo::TermInfoBuf::new());
+        term.set_env_overrides(env());
+
+        let report = probe(&term);
+        assert_eq!(report.primary_da, None);
+        assert!(report.modes.iter().all(|m| m.2.is_none()));
+        // nothing answered the device attributes, so the modes weren't asked about
+        assert!(!mock.written_contains(b"$p"));
+        let text = report.to_string();
+        assert!(text.contains("DA1: (no reply)"), "{}", text);
+        assert!(
+            text.contains("mode 2004 (bracketed paste): (no reply)"),
+            "{}",
+            text
+        );
+
+        // and nothing is asked at all when the output isn't a tty
+        let pipe = mock_tty();
+        pipe.set_tty(false);
+        let term = pipe.term(terminfo::TermInfoBuf::new());
+        term.set_env_overrides(env());
+        let report = probe(&term);
+        assert!(pipe.written().is_empty());
+        assert!(!report.is_tty);
+        assert!(report.size.is_err());
+    }
+}
@@ -0,0 +1,435 @@
+//! Writing text to terminals that aren't UTF-8.
+//!
+//! Rust strings are UTF-8, and writing them as-is to a terminal in some other charset (like a Linux console in a
+//! latin-1 locale, or anything under `LANG=C`) shows every character outside ASCII as garbage. An `Encoding` says
+//! what the terminal's charset is, and what to do with the characters it doesn't have: transliterate them (box
+//! drawing becomes `+`, `-` and `|`, curly quotes become straight ones) or replace them with `?`.
+//!
+//! `Term::set_encoding` turns this on for `Term::print` and `StyledWriter`, which can also draw box characters
+//! through the terminal's alternate character set when transliterating.
+//!
+//! ```
+//! use nixterm::encoding::{Charset, Encoding, Fallback};
+//!
+//! let encoding = Encoding::new(Charset::from_locale("C"), Fallback::Transliterate);
+//! assert_eq!(encoding.encode_to_vec("┌─┐ “hi”"), b"+-+ \"hi\"");
+//! ```
+use acs::{Acs, AcsChar};
+use ansi;
+use overrides::EnvOverrides;
+#[cfg(unix)]
+use std::borrow::Cow;
+use std::cell::Cell;
+#[cfg(unix)]
+use std::cell::RefCell;
+#[cfg(unix)]
+use std::mem;
+use std::str;
+
+/// A charset text can be written to the terminal in.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Charset {
+    Utf8,
+    /// ISO-8859-1, which has the first 256 Unicode characters.
+    Latin1,
+    /// Plain 7-bit ASCII, which is what the C locale has.
+    Ascii,
+}
+
+/// What to do with a character the terminal's charset doesn't have.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Fallback {
+    /// Use something that looks like it, like `-` for an em dash, `e` for `é` and `+` for a box corner. Characters
+    /// with no look-alike are replaced.
+    Transliterate,
+    /// Write `?` instead, or `??` for a wide character, so columns still line up.
+    Replace,
+}
+
+/// How text is written to a terminal, see the module documentation.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Encoding {
+    pub charset: Charset,
+    pub fallback: Fallback,
+}
+
+/// The ASCII look-alikes of the latin-1 characters from U+00C0 on.
+const LATIN1_LETTERS: [&str; 64] = [
+    "A", "A", "A", "A", "A", "A", "AE", "C", "E", "E", "E", "E", "I", "I", "I", "I", "D", "N", "O",
+    "O", "O", "O", "O", "x", "O", "U", "U", "U", "U", "Y", "TH", "ss", "a", "a", "a", "a", "a",
+    "a", "ae", "c", "e", "e", "e", "e", "i", "i", "i", "i", "d", "n", "o", "o", "o", "o", "o", "/",
+    "o", "u", "u", "u", "u", "y", "th", "y",
+];
+
+impl Charset {
+    /// The charset of a locale name like `en_US.UTF-8` or `de_DE.ISO-8859-1@euro`.
+    ///
+    /// Locales without a charset (like `C`, `POSIX` and `en_US`), and charsets other than UTF-8 and latin-1, are
+    /// treated as ASCII, which every charset they could be has.
+    pub fn from_locale(locale: &str) -> Charset {
+        let charset = match locale.find('.') {
+            Some(i) => &locale[i + 1..],
+            None => return Charset::Ascii,
+        };
+        let charset: String = charset
+            .split('@')
+            .next()
+            .unwrap_or("")
+            .chars()
+            .filter(|&c| c != '-' && c != '_')
+            .flat_map(char::to_lowercase)
+            .collect();
+        match &charset[..] {
+            "utf8" => Charset::Utf8,
+            "iso88591" | "latin1" | "l1" => Charset::Latin1,
+            _ => Charset::Ascii,
+        }
+    }
+
+    /// The charset of the locale in `LC_ALL`, `LC_CTYPE` or `LANG`, whichever is set first, or ASCII if none are.
+    pub fn from_env(env: &EnvOverrides) -> Charset {
+        ["LC_ALL", "LC_CTYPE", "LANG"]
+            .iter()
+            .filter_map(|name| env.var(name))
+            .find(|v| !v.is_empty())
+            .map_or(Charset::Ascii, |v| Charset::from_locale(&v))
+    }
+
+    /// The charset of the C library's current locale, from `nl_langinfo(CODESET)`.
+    ///
+    /// The locale is only read from the environment once the program has called `setlocale(LC_CTYPE, "")`, before
+    /// that this is always ASCII.
+    #[cfg(all(feature = "langinfo", unix))]
+    pub fn from_langinfo() -> Charset {
+        use nix::libc;
+        use std::ffi::CStr;
+
+        let codeset = unsafe { libc::nl_langinfo(libc::CODESET) };
+        if codeset.is_null() {
+            return Charset::Ascii;
+        }
+        let codeset = unsafe { CStr::from_ptr(codeset) }.to_string_lossy();
+        Charset::from_locale(&format!(".{}", codeset))
+    }
+
+    /// True if `c` can be written in this charset.
+    pub fn contains(self, c: char) -> bool {
+        match self {
+            Charset::Utf8 => true,
+            Charset::Latin1 => (c as u32) < 0x100,
+            Charset::Ascii => c.is_ascii(),
+        }
+    }
+}
+
+/// Append an ASCII look-alike for `c` to `out`, or return false if there isn't one.
+///
+/// Line-drawing characters become the same ASCII as `AcsChar::ascii`.
+fn transliterate(c: char, out: &mut Vec<u8>) -> bool {
+    let s = match c {
+        '\u{a0}' | '\u{2002}'..='\u{200a}' | '\u{202f}' => " ",
+        '\u{a9}' => "(C)",
+        '\u{ae}' => "(R)",
+        '\u{2122}' => "TM",
+        '\u{ab}' => "<<",
+        '\u{bb}' => ">>",
+        '\u{b1}' => "+/-",
+        '\u{b7}' | '\u{2022}' | '\u{2219}' => "*",
+        '\u{c0}'..='\u{ff}' => LATIN1_LETTERS[c as usize - 0xc0],
+        '\u{2010}'..='\u{2015}' | '\u{2212}' => "-",
+        '\u{2018}' | '\u{2019}' | '\u{201a}' | '\u{201b}' | '\u{2032}' => "'",
+        '\u{201c}' | '\u{201d}' | '\u{201e}' | '\u{201f}' | '\u{2033}' => "\"",
+        '\u{2039}' => "<",
+        '\u{203a}' => ">",
+        '\u{2026}' => "...",
+        _ => match AcsChar::from_unicode(c) {
+            Some(acs) => {
+                out.push(acs.ascii() as u8);
+                return true;
+            }
+            None => return false,
+        },
+    };
+    out.extend_from_slice(s.as_bytes());
+    true
+}
+
+/// How `Encoding::encode_with_acs` draws line-drawing characters in the alternate character set.
+pub(crate) struct AltCharset {
+    /// The terminal's `acsc` table, not in UTF-8 mode.
+    pub acs: Acs,
+    /// `smacs` and `rmacs`.
+    pub enter: Vec<u8>,
+    pub exit: Vec<u8>,
+    /// `enacs`, which is sent before the first line is drawn unless the set's been `enabled` already.
+    pub enable: Option<Vec<u8>>,
+    pub enabled: Cell<bool>,
+}
+
+/// A `Term`'s encoding, with its alternate character set, for its writers.
+#[cfg(unix)]
+pub(crate) struct OutputEncoding {
+    pub encoding: Encoding,
+    pub alt: Option<AltCharset>,
+    /// The start of a character at the end of the last chunk, see `encode_chunk`.
+    pub pending: RefCell<Vec<u8>>,
+}
+
+#[cfg(unix)]
+impl OutputEncoding {
+    /// `text` in this encoding, leaving any bytes that aren't UTF-8 as they are.
+    pub fn encode<'b>(&self, mut text: &'b [u8]) -> Cow<'b, [u8]> {
+        // every charset has ASCII, and nothing in it is drawn in the alternate set
+        if self.encoding.charset == Charset::Utf8 || text.is_ascii() {
+            return Cow::Borrowed(text);
+        }
+
+        let mut out = Vec::with_capacity(text.len());
+        while !text.is_empty() {
+            let (valid, bad, rest) = match str::from_utf8(text) {
+                Ok(s) => (s, &text[text.len()..], &text[text.len()..]),
+                Err(e) => {
+                    let (valid, rest) = text.split_at(e.valid_up_to());
+                    let (bad, rest) = rest.split_at(e.error_len().unwrap_or(rest.len()));
+                    (str::from_utf8(valid).unwrap(), bad, rest)
+                }
+            };
+            self.encoding
+                .encode_with_acs(valid, self.alt.as_ref(), &mut out);
+            out.extend_from_slice(bad);
+            text = rest;
+        }
+        Cow::Owned(out)
+    }
+
+    /// Like `encode`, for text written a chunk at a time: a character cut off at the end of `text` is held back
+    /// until the rest of it comes with the next chunk, rather than written as bytes that aren't UTF-8.
+    pub fn encode_chunk<'b>(&self, text: &'b [u8]) -> Cow<'b, [u8]> {
+        let mut pending = self.pending.borrow_mut();
+        if pending.is_empty() {
+            let (head, tail) = text.split_at(text.len() - incomplete_tail(text));
+            pending.extend_from_slice(tail);
+            return self.encode(head);
+        }
+        let mut joined = mem::take(&mut *pending);
+        joined.extend_from_slice(text);
+        *pending = joined.split_off(joined.len() - incomplete_tail(&joined));
+        Cow::Owned(self.encode(&joined).into_owned())
+    }
+
+    /// Take what `encode_chunk` is holding back, the start of a character the rest of which never came.
+    pub fn take_pending(&self) -> Vec<u8> {
+        mem::take(&mut *self.pending.borrow_mut())
+    }
+}
+
+/// How many bytes at the end of `text` are a character that's been cut off, at most 3.
+#[cfg(unix)]
+fn incomplete_tail(text: &[u8]) -> usize {
+    for (i, &c) in text.iter().rev().take(3).enumerate() {
+        // a continuation byte, the start is further back
+        if c & 0xc0 == 0x80 {
+            continue;
+        }
+        let len = match c {
+            0xc2..=0xdf => 2,
+            0xe0..=0xef => 3,
+            0xf0..=0xf4 => 4,
+            _ => 0,
+        };
+        return if len > i + 1 { i + 1 } else { 0 };
+    }
+    0
+}
+
+impl Encoding {
+    pub fn new(charset: Charset, fallback: Fallback) -> Encoding {
+        Encoding { charset, fallback }
+    }
+
+    /// The encoding of the locale in the environment (see `Charset::from_env`), with `fallback`.
+    pub fn from_env(env: &EnvOverrides, fallback: Fallback) -> Encoding {
+        Encoding::new(Charset::from_env(env), fallback)
+    }
+
+    /// Append `s` to `out` in this encoding.
+    pub fn encode(&self, s: &str, out: &mut Vec<u8>) {
+        self.encode_with_acs(s, None, out);
+    }
+
+    /// `s` in this encoding.
+    pub fn encode_to_vec(&self, s: &str) -> Vec<u8> {
+        let mut out = Vec::with_capacity(s.len());
+        self.encode(s, &mut out);
+        out
+    }
+
+    /// Like `encode`, but when transliterating the line-drawing characters `alt` has are drawn with it.
+    pub(crate) fn encode_with_acs(&self, s: &str, alt: Option<&AltCharset>, out: &mut Vec<u8>) {
+        if self.charset == Charset::Utf8 {
+            out.extend_from_slice(s.as_bytes());
+            return;
+        }
+
+        let mut in_acs = false;
+        for c in s.chars() {
+            let drawn = match alt {
+                Some(alt)
+                    if self.fallback == Fallback::Transliterate && !self.charset.contains(c) =>
+                {
+                    AcsChar::from_unicode(c)
+                        .filter(|&a| alt.acs.supports(a))
+                        .map(|a| alt.acs.get(a) as u8)
+                }
+                _ => None,
+            };
+            if let Some(alt) = alt {
+                if drawn.is_some() != in_acs {
+                    if !alt.enabled.replace(true) {
+                        out.extend_from_slice(alt.enable.as_ref().map_or(&[][..], |e| &e[..]));
+                    }
+                    out.extend_from_slice(if in_acs { &alt.exit } else { &alt.enter });
+                    in_acs = !in_acs;
+                }
+            }
+            if let Some(byte) = drawn {
+                out.push(byte);
+                continue;
+            }
+
+            if self.charset.contains(c) {
+                // ASCII and latin-1 characters are the Unicode ones with the same numbers
+                out.push(c as u32 as u8);
+                continue;
+            }
+            if self.fallback == Fallback::Transliterate {
+                // combining accents and the like are left out, there's nothing to put them on
+                if transliterate(c, out) || ansi::char_width(c) == 0 {
+                    continue;
+                }
+            }
+            for _ in 0..ansi::char_width(c).max(1) {
+                out.push(b'?');
+            }
+        }
+        if let (true, Some(alt)) = (in_acs, alt) {
+            out.extend_from_slice(&alt.exit);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use encoding::*;
+    use overrides::EnvOverrides;
+    use terminfo;
+
+    #[test]
+    fn locales() {
+        assert_eq!(Charset::from_locale("en_US.UTF-8"), Charset::Utf8);
+        assert_eq!(Charset::from_locale("de_DE.utf8"), Charset::Utf8);
+        assert_eq!(Charset::from_locale("sr_RS.UTF-8@latin"), Charset::Utf8);
+        assert_eq!(Charset::from_locale("C.UTF-8"), Charset::Utf8);
+        assert_eq!(Charset::from_locale("fr_FR.ISO-8859-1"), Charset::Latin1);
+        assert_eq!(Charset::from_locale("de_DE.iso88591@euro"), Charset::Latin1);
+        assert_eq!(Charset::from_locale("C"), Charset::Ascii);
+        assert_eq!(Charset::from_locale("POSIX"), Charset::Ascii);
+        assert_eq!(Charset::from_locale("en_US"), Charset::Ascii);
+        assert_eq!(Charset::from_locale("ru_RU.KOI8-R"), Charset::Ascii);
+        assert_eq!(Charset::from_locale(""), Charset::Ascii);
+
+        let env = |vars: Vec<(&str, &str)>| Charset::from_env(&EnvOverrides::from_map(vars));
+        assert_eq!(env(vec![("LANG", "en_US.UTF-8")]), Charset::Utf8);
+        assert_eq!(
+            env(vec![("LANG", "en_US.UTF-8"), ("LC_CTYPE", "C")]),
+            Charset::Ascii
+        );
+        assert_eq!(
+            env(vec![
+                ("LC_ALL", "fr_FR.ISO-8859-1"),
+                ("LC_CTYPE", "en_US.UTF-8")
+            ]),
+            Charset::Latin1
+        );
+        // empty variables don't count
+        assert_eq!(
+            env(vec![("LC_ALL", ""), ("LANG", "en_US.UTF-8")]),
+            Charset::Utf8
+        );
+        assert_eq!(env(vec![]), Charset::Ascii);
+    }
+
+    #[test]
+    fn encode() {
+        let text = "“café” — ┌─┐ 中 e\u{301}";
+        let encode = |charset, fallback| {
+            String::from_utf8(Encoding::new(charset, fallback).encode_to_vec(text)).unwrap()
+        };
+
+        assert_eq!(encode(Charset::Utf8, Fallback::Replace), text);
+        assert_eq!(
+            encode(Charset::Ascii, Fallback::Transliterate),
+            "\"cafe\" - +-+ ?? e"
+        );
+        assert_eq!(
+            encode(Charset::Ascii, Fallback::Replace),
+            "?caf?? ? ??? ?? e?"
+        );
+
+        let latin1 = Encoding::new(Charset::Latin1, Fallback::Transliterate).encode_to_vec(text);
+        assert_eq!(latin1, b"\"caf\xe9\" - +-+ ?? e");
+        assert_eq!(
+            Encoding::new(Charset::Latin1, Fallback::Replace).encode_to_vec("\u{a0}±"),
+            b"\xa0\xb1"
+        );
+    }
+
+    #[test]
+    fn encode_with_acs() {
+        const RXVT_INFO: &'static [u8] = include_bytes!("../test-data/rxvt");
+        let rxvt: terminfo::TermInfoBuf = terminfo::TermInfo::parse(RXVT_INFO).unwrap().into();
+        let alt = AltCharset {
+            acs: Acs::with_utf8(&rxvt, false),
+            enter: b"<".to_vec(),
+            exit: b">".to_vec(),
+            enable: Some(b"!".to_vec()),
+            enabled: Cell::new(false),
+        };
+
+        let mut out = Vec::new();
+        Encoding::new(Charset::Ascii, Fallback::Transliterate).encode_with_acs(
+            "┌─x─┐←",
+            Some(&alt),
+            &mut out,
+        );
+        // rxvt has no arrows in its alternate character set, and it only has to be enabled once
+        assert_eq!(out, b"!<lq>x<qk><");
+
+        // replacing doesn't use it
+        let mut out = Vec::new();
+        Encoding::new(Charset::Ascii, Fallback::Replace).encode_with_acs(
+            "┌─┐",
+            Some(&alt),
+            &mut out,
+        );
+        assert_eq!(out, b"???");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn encode_chunk() {
+        // a character split between chunks is encoded once it's all there
+        let latin1 = OutputEncoding {
+            encoding: Encoding::new(Charset::Latin1, Fallback::Replace),
+            alt: None,
+            pending: RefCell::new(Vec::new()),
+        };
+        let chunks: Vec<Vec<u8>> = [&b"caf\xc3"[..], b"\xa9 \xe2\x94", b"\x8c\xff\xf0"]
+            .iter()
+            .map(|chunk| latin1.encode_chunk(chunk).into_owned())
+            .collect();
+        assert_eq!(chunks, vec![b"caf".to_vec(), b"\xe9 ".to_vec(), b"?\xff".to_vec()]);
+        assert_eq!(latin1.take_pending(), b"\xf0");
+        assert_eq!(latin1.encode_chunk(b"\xe9!"), &b"\xe9!"[..]);
+    }
+}
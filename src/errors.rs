@@ -1,125 +1,484 @@
-use failure;
-use std::{fmt, result};
+#[cfg(unix)]
+use nix;
+#[cfg(unix)]
+use nix::errno::Errno;
+use std::error::Error as StdError;
+use std::string::FromUtf8Error;
+use std::time::Duration;
+use std::{fmt, io, result};
 use terminfo;
 
 pub type Result<T> = result::Result<T, Error>;
+
+/// Everything that can go wrong in nixterm, split up by what went wrong so callers can decide what to do about it.
+///
+/// Errors that come from somewhere else (like a write failing) also carry an `ErrorKind`, which says what nixterm was
+/// doing at the time.
 #[derive(Debug)]
-pub struct Error {
-    inner: failure::Context<ErrorKind>,
-}
+#[non_exhaustive]
+pub enum Error {
+    /// The terminfo entry couldn't be found or loaded, or one of its capabilities couldn't be run.
+    Terminfo {
+        kind: ErrorKind,
+        source: terminfo::Error,
+    },
 
-#[derive(Eq, PartialEq, Debug, Clone, Fail)]
-pub enum ErrorKind {
-    #[fail(display = "failed to put the terminal in raw mode")]
-    InitRawModeFailed,
+    /// Reading from or writing to the terminal failed.
+    Io { kind: ErrorKind, source: io::Error },
 
-    #[fail(display = "failed to take terminal out of raw mode")]
-    ExitRawModeFailed,
+    /// Some text, like a color, couldn't be parsed.
+    Parse(ParseError),
 
-    #[fail(display = "failed to get the next character")]
-    GetCharFailed,
+    /// The terminal can't do something it was asked to.
+    Unsupported(Capability),
 
-    #[fail(display = "failed to read the next keystroke")]
-    ReadKeyFailed,
+    /// The terminal didn't reply to `query` in time, after waiting `waited` altogether (including any retries, see
+    /// `query::QueryConfig`).
+    Timeout { query: &'static str, waited: Duration },
 
-    #[fail(display = "Invalid number")]
-    InvalidNumber,
+    /// The terminal's input isn't a tty, so it can't be put in raw mode or have its settings changed.
+    NotATty,
 
-    #[fail(display = "Invalid color")]
-    InvalidColor,
+    /// The terminal is missing too much of what was asked for to even start, see `Term::session`. The report says
+    /// everything that's missing, not just the first thing.
+    Unsuitable(terminfo::validate::ValidationReport),
 
-    #[fail(display = "Failed to write OS Command escape code")]
-    OscFailed,
+    /// The arguments to a function don't make sense together.
+    InvalidInput(&'static str),
 
-    #[fail(display = "Failed to create terminal")]
-    TermInitFailed,
+    /// A control sequence couldn't be put together, see `ansi::csi`.
+    InvalidSequence(SequenceError),
+}
 
-    #[fail(display = "Failed to write Control Sequence")]
+/// What nixterm was doing when an `Error::Io` or `Error::Terminfo` happened.
+#[derive(Eq, PartialEq, Debug, Clone)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    InitRawModeFailed,
+    ExitRawModeFailed,
+    GetCharFailed,
+    ReadKeyFailed,
+    OscFailed,
+    TermInitFailed,
     CsiFailed,
+    FailedToGetTabWidth,
+    ReadLineFailed,
+    FailedWriteToStdout,
+    FailedToAlignRight,
+    FailedToAlignCenter,
+    FailedToRunTerminfo(terminfo::StringField),
+    FailedToRunExtended(terminfo::ExtCapability),
+    FailedToCreateTermInstance,
+    ReadFailed,
+    WriteFailed,
+    FailedToSetTermios,
+    FailedToGetTermios,
+    ApcFailed,
+    DcsFailed,
+    FailedToGetSize,
+    FailedToSetSize,
+    FailedToOpenPty,
+    FailedToSpawn,
+    RecordingFailed,
+    FailedToAskMultiplexer,
+}
 
-    #[fail(display = "Expect fg:/bg: inside [+] block")]
+/// Text that couldn't be parsed.
+#[derive(Eq, PartialEq, Debug, Clone)]
+#[non_exhaustive]
+pub enum ParseError {
+    InvalidNumber(String),
+    InvalidColor(String),
+    UnknownColorName(String),
+    /// An X11 color spec, like the ones in `OSC 4` replies.
+    InvalidXColor(String),
     InvalidColorLocation,
-
-    #[fail(display = "Expect fg/bg inside [-] block")]
     InvalidResetSpecifier,
+    InvalidCursorPosition,
+    /// A line of an asciicast recording, see `TestScreen::replay`.
+    InvalidRecording(String),
+    /// An asciicast recording's screen, columns by rows, is bigger than `record::MAX_SIZE` on a side.
+    RecordingTooLarge(usize, usize),
+    /// A reply to a query that was garbled or cut short, see `query::QueryResult::Malformed`.
+    MalformedReply(Vec<u8>),
+}
 
-    #[fail(display = "Unknown color \"{}\"", _0)]
-    UnknownColorName(String),
+/// Why a control sequence couldn't be put together.
+#[derive(Eq, PartialEq, Debug, Clone)]
+#[non_exhaustive]
+pub enum SequenceError {
+    /// The final byte isn't between 0x40 and 0x7e.
+    InvalidFinalByte(u8),
+    /// An intermediate byte isn't between 0x20 and 0x2f.
+    InvalidIntermediate(u8),
+    /// A private marker isn't one of `<`, `=`, `>` or `?`.
+    InvalidPrivateMarker(u8),
+    /// There are more than `ansi::MAX_PARAMS` parameters.
+    TooManyParameters(usize),
+    /// A parameter is bigger than 65535.
+    ParameterOutOfRange(usize),
+    /// A string's payload has a control character in it, which could end the string early.
+    ControlInPayload(char),
+}
 
-    #[fail(
-        display = "Failed to get the cursor position. The terminal did not return a valid escape sequence."
-    )]
-    InvalidCursorPosition,
+/// Something a terminal might not be able to do.
+#[derive(Eq, PartialEq, Debug, Copy, Clone, Ord, PartialOrd, Hash)]
+#[non_exhaustive]
+pub enum Capability {
+    /// The terminfo entry is missing this string.
+    String(terminfo::StringField),
+    /// Showing images.
+    Images,
+    /// Answering a query, like `DECRQM` or `XTGETTCAP`.
+    Query(&'static str),
+}
 
-    #[fail(display = "Failed to find the with of a tab character in this terminal")]
-    FailedToGetTabWidth,
+impl Error {
+    /// What nixterm was doing when this error happened, if it came from somewhere else.
+    pub fn kind(&self) -> Option<&ErrorKind> {
+        match *self {
+            Error::Terminfo { ref kind, .. } | Error::Io { ref kind, .. } => Some(kind),
+            _ => None,
+        }
+    }
 
-    #[fail(display = "Failed to read a line from standard in")]
-    ReadLineFailed,
+    /// True if the terminal can't do what it was asked to; see `Error::Unsupported`.
+    pub fn is_unsupported(&self) -> bool {
+        matches!(*self, Error::Unsupported(_))
+    }
 
-    #[fail(display = "Failed to write to standard out")]
-    FailedWriteToStdout,
+    /// True if the terminal didn't reply in time.
+    pub fn is_timeout(&self) -> bool {
+        matches!(*self, Error::Timeout { .. })
+    }
 
-    #[fail(display = "Failed to align line right")]
-    FailedToAlignRight,
+    /// True if the terminal isn't a tty.
+    pub fn is_not_a_tty(&self) -> bool {
+        matches!(*self, Error::NotATty)
+    }
 
-    #[fail(display = "Failed to align line center")]
-    FailedToAlignCenter,
+    /// True if this came from terminfo, for example because the entry couldn't be found.
+    pub fn is_terminfo(&self) -> bool {
+        matches!(*self, Error::Terminfo { .. })
+    }
 
-    #[fail(display = "A terminfo field is missing!")]
-    MissingTermInfoField(terminfo::StringField),
+    /// True if reading or writing failed.
+    pub fn is_io(&self) -> bool {
+        matches!(*self, Error::Io { .. })
+    }
 
-    #[fail(display = "Failed to execute a terminfo string")]
-    FailedToRunTerminfo(terminfo::StringField),
+    /// True if some text couldn't be parsed.
+    pub fn is_parse(&self) -> bool {
+        matches!(*self, Error::Parse(_))
+    }
+}
 
-    #[fail(display = "Failed to get a handle to the user's terminal")]
-    FailedToCreateTermInstance,
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match *self {
+            Error::Terminfo { ref source, .. } => Some(source),
+            Error::Io { ref source, .. } => Some(source),
+            Error::Parse(ref e) => Some(e),
+            Error::InvalidSequence(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
 
-    #[fail(display = "Terminal failed to read from the input stream")]
-    ReadFailed,
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Terminfo { ref kind, .. } | Error::Io { ref kind, .. } => {
+                fmt::Display::fmt(kind, f)
+            }
+            Error::Parse(ref e) => fmt::Display::fmt(e, f),
+            Error::Unsupported(ref c) => write!(f, "The terminal doesn't support {}", c),
+            Error::Timeout { query, waited } => {
+                write!(f, "The terminal didn't reply to the {} query within {:?}", query, waited)
+            }
+            Error::NotATty => write!(f, "The terminal isn't a tty"),
+            Error::Unsuitable(ref report) => write!(f, "Unsuitable terminal: {}", report),
+            Error::InvalidInput(why) => write!(f, "Invalid input: {}", why),
+            Error::InvalidSequence(ref e) => fmt::Display::fmt(e, f),
+        }
+    }
+}
 
-    #[fail(display = "Terminal failed to write to the output stream")]
-    WriteFailed,
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ErrorKind::InitRawModeFailed => write!(f, "failed to put the terminal in raw mode"),
+            ErrorKind::ExitRawModeFailed => write!(f, "failed to take terminal out of raw mode"),
+            ErrorKind::GetCharFailed => write!(f, "failed to get the next character"),
+            ErrorKind::ReadKeyFailed => write!(f, "failed to read the next keystroke"),
+            ErrorKind::OscFailed => write!(f, "Failed to write OS Command escape code"),
+            ErrorKind::TermInitFailed => write!(f, "Failed to create terminal"),
+            ErrorKind::CsiFailed => write!(f, "Failed to write Control Sequence"),
+            ErrorKind::FailedToGetTabWidth => write!(
+                f,
+                "Failed to find the with of a tab character in this terminal"
+            ),
+            ErrorKind::ReadLineFailed => write!(f, "Failed to read a line from standard in"),
+            ErrorKind::FailedWriteToStdout => write!(f, "Failed to write to standard out"),
+            ErrorKind::FailedToAlignRight => write!(f, "Failed to align line right"),
+            ErrorKind::FailedToAlignCenter => write!(f, "Failed to align line center"),
+            ErrorKind::FailedToRunTerminfo(field) => {
+                write!(f, "Failed to execute the terminfo string {:?}", field)
+            }
+            ErrorKind::FailedToRunExtended(cap) => {
+                write!(f, "Failed to execute the extended capability {}", cap.name())
+            }
+            ErrorKind::FailedToCreateTermInstance => {
+                write!(f, "Failed to get a handle to the user's terminal")
+            }
+            ErrorKind::ReadFailed => write!(f, "Terminal failed to read from the input stream"),
+            ErrorKind::WriteFailed => write!(f, "Terminal failed to write to the output stream"),
+            ErrorKind::FailedToSetTermios => write!(f, "Failed to update termios"),
+            ErrorKind::FailedToGetTermios => write!(f, "Failed to get termios"),
+            ErrorKind::ApcFailed => write!(f, "Failed to write Application Program Command"),
+            ErrorKind::DcsFailed => write!(f, "Failed to write Device Control String"),
+            ErrorKind::FailedToGetSize => write!(f, "Failed to get the size of the terminal"),
+            ErrorKind::FailedToSetSize => write!(f, "Failed to set the size of the terminal"),
+            ErrorKind::FailedToOpenPty => write!(f, "Failed to open a pseudo terminal"),
+            ErrorKind::FailedToSpawn => write!(f, "Failed to run a command on a pseudo terminal"),
+            ErrorKind::RecordingFailed => write!(f, "Failed to write the recording of the terminal's output"),
+            ErrorKind::FailedToAskMultiplexer => write!(f, "Failed to ask the terminal multiplexer what it's doing"),
+        }
+    }
+}
 
-    #[fail(display = "Failed to update termios")]
-    FailedToSetTermios,
+impl StdError for ParseError {}
 
-    #[fail(display = "Failed to get termios")]
-    FailedToGetTermios,
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseError::InvalidNumber(ref s) => write!(f, "Invalid number \"{}\"", s),
+            ParseError::InvalidColor(ref s) => write!(
+                f,
+                "Invalid color \"{}\", expected a color name, a palette index (0-255), #rgb, #rrggbb, or rgb(r, g, b)",
+                s
+            ),
+            ParseError::UnknownColorName(ref s) => write!(
+                f,
+                "Unknown color \"{}\", expected a color name, a palette index (0-255), #rgb, #rrggbb, or rgb(r, g, b)",
+                s
+            ),
+            ParseError::InvalidXColor(ref s) => write!(
+                f,
+                "Invalid color \"{}\", expected rgb:r/g/b, rgba:r/g/b/a (with 1-4 hex digits each), or #rgb",
+                s
+            ),
+            ParseError::InvalidColorLocation => write!(f, "Expect fg:/bg: inside [+] block"),
+            ParseError::InvalidResetSpecifier => write!(f, "Expect fg/bg inside [-] block"),
+            ParseError::InvalidCursorPosition => write!(
+                f,
+                "Failed to get the cursor position. The terminal did not return a valid escape sequence."
+            ),
+            ParseError::InvalidRecording(ref s) => write!(f, "Invalid asciicast line \"{}\"", s),
+            ParseError::RecordingTooLarge(cols, rows) => {
+                write!(f, "The asciicast recording's {}x{} screen is too large to replay", cols, rows)
+            }
+            ParseError::MalformedReply(ref s) => {
+                write!(f, "The terminal's reply \"{}\" couldn't be read", s.escape_ascii())
+            }
+        }
+    }
 }
 
-impl Error {
-    pub fn kind(&self) -> &ErrorKind {
-        self.inner.get_context()
+impl StdError for SequenceError {}
+
+impl fmt::Display for SequenceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SequenceError::InvalidFinalByte(c) => write!(f, "Invalid final byte {:#04x}, expected 0x40-0x7e", c),
+            SequenceError::InvalidIntermediate(c) => {
+                write!(f, "Invalid intermediate byte {:#04x}, expected 0x20-0x2f", c)
+            }
+            SequenceError::InvalidPrivateMarker(c) => {
+                write!(f, "Invalid private marker {:#04x}, expected one of <, =, > or ?", c)
+            }
+            SequenceError::TooManyParameters(n) => write!(f, "Too many parameters, {} is more than 16", n),
+            SequenceError::ParameterOutOfRange(n) => write!(f, "Parameter {} is too big, the most is 65535", n),
+            SequenceError::ControlInPayload(c) => write!(f, "The control character {:?} can't be in a string", c),
+        }
     }
 }
 
-impl failure::Fail for Error {
-    fn cause(&self) -> Option<&failure::Fail> {
-        self.inner.cause()
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Capability::String(field) => write!(f, "the terminfo string {:?}", field),
+            Capability::Images => write!(f, "images"),
+            Capability::Query(name) => write!(f, "the {} query", name),
+        }
     }
+}
 
-    fn backtrace(&self) -> Option<&failure::Backtrace> {
-        self.inner.backtrace()
+impl From<ParseError> for Error {
+    fn from(e: ParseError) -> Error {
+        Error::Parse(e)
     }
 }
 
-impl fmt::Display for Error {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        fmt::Display::fmt(&self.inner, f)
+impl From<SequenceError> for Error {
+    fn from(e: SequenceError) -> Error {
+        Error::InvalidSequence(e)
     }
 }
 
-impl From<ErrorKind> for Error {
-    fn from(kind: ErrorKind) -> Error {
-        Error {
-            inner: failure::Context::new(kind),
+impl From<Capability> for Error {
+    fn from(c: Capability) -> Error {
+        Error::Unsupported(c)
+    }
+}
+
+/// Turn an error from somewhere else into an `Error`, recording what nixterm was doing when it happened.
+pub(crate) trait Context {
+    type Output;
+
+    fn context(self, kind: ErrorKind) -> Self::Output;
+}
+
+impl Context for Error {
+    type Output = Error;
+
+    /// Errors from nixterm already say what went wrong, so they're left alone.
+    fn context(self, _: ErrorKind) -> Error {
+        self
+    }
+}
+
+impl Context for io::Error {
+    type Output = Error;
+
+    fn context(self, kind: ErrorKind) -> Error {
+        #[cfg(unix)]
+        {
+            if self.raw_os_error() == Some(Errno::ENOTTY as i32) {
+                return Error::NotATty;
+            }
+        }
+        Error::Io { kind, source: self }
+    }
+}
+
+#[cfg(unix)]
+impl Context for nix::Error {
+    type Output = Error;
+
+    fn context(self, kind: ErrorKind) -> Error {
+        match self {
+            nix::Error::Sys(errno) => io::Error::from_raw_os_error(errno as i32).context(kind),
+            e => io::Error::other(e).context(kind),
         }
     }
 }
 
-impl From<failure::Context<ErrorKind>> for Error {
-    fn from(inner: failure::Context<ErrorKind>) -> Error {
-        Error { inner: inner }
+impl Context for fmt::Error {
+    type Output = Error;
+
+    fn context(self, kind: ErrorKind) -> Error {
+        io::Error::other(self).context(kind)
+    }
+}
+
+impl Context for FromUtf8Error {
+    type Output = Error;
+
+    fn context(self, kind: ErrorKind) -> Error {
+        io::Error::new(io::ErrorKind::InvalidData, self).context(kind)
+    }
+}
+
+impl Context for terminfo::Error {
+    type Output = Error;
+
+    fn context(self, kind: ErrorKind) -> Error {
+        Error::Terminfo { kind, source: self }
+    }
+}
+
+impl<T, E: Context<Output = Error>> Context for result::Result<T, E> {
+    type Output = Result<T>;
+
+    fn context(self, kind: ErrorKind) -> Result<T> {
+        self.map_err(|e| e.context(kind))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use errors::*;
+    #[cfg(unix)]
+    use nix::errno::Errno;
+    use std::error::Error as StdError;
+    use std::io;
+    use std::time::Duration;
+    use terminfo;
+
+    fn assert_send_sync<T: Send + Sync + 'static>() {}
+
+    #[test]
+    fn context() {
+        assert_send_sync::<Error>();
+
+        let e = io::Error::new(io::ErrorKind::BrokenPipe, "gone").context(ErrorKind::WriteFailed);
+        match e {
+            Error::Io {
+                kind: ErrorKind::WriteFailed,
+                ref source,
+            } => {
+                assert_eq!(source.kind(), io::ErrorKind::BrokenPipe)
+            }
+            ref e => panic!("{:?}", e),
+        }
+        assert!(e.is_io());
+        assert_eq!(e.kind(), Some(&ErrorKind::WriteFailed));
+        assert_eq!(e.source().unwrap().to_string(), "gone");
+
+        let e = terminfo::Error::from(terminfo::ErrorKind::FailedToFindTermInfo)
+            .context(ErrorKind::FailedToCreateTermInstance);
+        match e {
+            Error::Terminfo { ref source, .. } => {
+                assert_eq!(*source.kind(), terminfo::ErrorKind::FailedToFindTermInfo)
+            }
+            ref e => panic!("{:?}", e),
+        }
+        assert!(e.is_terminfo());
+        assert_eq!(e.kind(), Some(&ErrorKind::FailedToCreateTermInstance));
+
+        // errors that are already nixterm's keep what they say
+        let e = Error::from(Capability::String(terminfo::ParmDch)).context(ErrorKind::WriteFailed);
+        assert!(e.is_unsupported());
+        assert_eq!(e.kind(), None);
+        let e = Error::Timeout {
+            query: "DECRQM",
+            waited: Duration::from_millis(1500),
+        };
+        assert_eq!(e.to_string(), "The terminal didn't reply to the DECRQM query within 1.5s");
+        assert!(e.context(ErrorKind::ReadFailed).is_timeout());
+
+        let e = Error::from(ParseError::InvalidNumber("x".to_owned()));
+        assert!(e.is_parse());
+        assert!(e.source().is_some());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn nix_context() {
+        let e =
+            Err::<(), _>(::nix::Error::Sys(Errno::ENOTTY)).context(ErrorKind::FailedToSetTermios);
+        assert!(e.unwrap_err().is_not_a_tty());
+
+        let e = ::nix::Error::Sys(Errno::EIO).context(ErrorKind::ReadFailed);
+        match e {
+            Error::Io {
+                kind: ErrorKind::ReadFailed,
+                ref source,
+            } => {
+                assert_eq!(source.raw_os_error(), Some(Errno::EIO as i32))
+            }
+            ref e => panic!("{:?}", e),
+        }
     }
 }
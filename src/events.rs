@@ -1,11 +1,24 @@
+#[cfg(unix)]
 use errors::*;
+use std::collections::BTreeSet;
 use std::collections::HashMap;
 use std::collections::VecDeque;
+#[cfg(unix)]
 use std::io;
+#[cfg(unix)]
 use std::os::unix::io::AsRawFd;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+#[cfg(unix)]
 use term;
 use terminfo;
 
+/// How long to wait for the rest of an escape sequence, before deciding the escape key was pressed.
+pub const ESCAPE_TIMEOUT: Duration = Duration::from_millis(50);
+
+/// The shortest interval between ticks, shorter ones are made this long so a loop never ticks without waiting.
+pub const MIN_TICK_INTERVAL: Duration = Duration::from_millis(1);
+
 const FUNC_KEYS_KEY: [terminfo::StringField; 64] = [
     terminfo::StringField::KeyF0,
     terminfo::StringField::KeyF1,
@@ -73,7 +86,34 @@ const FUNC_KEYS_KEY: [terminfo::StringField; 64] = [
     terminfo::StringField::KeyF63,
 ];
 
-#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+/// The keys other than the function keys, with the capability for the code each one sends.
+const KEY_CAPABILITIES: [(Key, terminfo::StringField); 22] = [
+    (Key::Backspace, terminfo::KeyBackspace),
+    (Key::Backtab, terminfo::KeyBtab),
+    (Key::Begin, terminfo::KeyBeg),
+    (Key::End, terminfo::KeyEnd),
+    (Key::Clear, terminfo::KeyClear),
+    (Key::Exit, terminfo::KeyExit),
+    (Key::Enter, terminfo::KeyEnter),
+    (Key::Delete, terminfo::KeyDc),
+    (Key::KeypadC1, terminfo::KeyC1),
+    (Key::KeypadC3, terminfo::KeyC3),
+    (Key::KeypadB2, terminfo::KeyB2),
+    (Key::KeypadA3, terminfo::KeyA3),
+    (Key::KeypadA1, terminfo::KeyA1),
+    (Key::Up, terminfo::KeyUp),
+    (Key::Down, terminfo::KeyDown),
+    (Key::Left, terminfo::KeyLeft),
+    (Key::Right, terminfo::KeyRight),
+    (Key::Home, terminfo::KeyHome),
+    (Key::Insert, terminfo::KeyIc),
+    (Key::PageUp, terminfo::KeyPpage),
+    (Key::PageDown, terminfo::KeyNpage),
+    (Key::Mouse, terminfo::KeyMouse),
+];
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[non_exhaustive]
 pub enum Key {
     /// the value of Fn may be between 0 - 63.
     Fn(usize),
@@ -97,29 +137,373 @@ pub enum Key {
     KeypadB2,
     KeypadC1,
     KeypadC3,
+    Home,
+    Insert,
+    PageUp,
+    PageDown,
+    /// A mouse report, which starts with `kmous`. The whole report is read, but which button it was and where
+    /// aren't decoded.
+    Mouse,
     Control(char),
     Invalid(u8),
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+impl Key {
+    /// The capability for the code this key sends, if the key has one.
+    ///
+    /// Keys that send a single byte, like `Char`, `Tab` and `Control`, don't.
+    pub fn capability(&self) -> Option<terminfo::StringField> {
+        if let Key::Fn(n) = *self {
+            return FUNC_KEYS_KEY.get(n).cloned();
+        }
+        KEY_CAPABILITIES
+            .iter()
+            .find(|(key, _)| key == self)
+            .map(|&(_, field)| field)
+    }
+
+    /// The terminfo name of `capability`, like `kf5` or `kich1`.
+    pub fn capability_name(&self) -> Option<&'static str> {
+        self.capability().map(|field| field.info().terminfo_name)
+    }
+}
+
+/// Every key with a capability, along with it.
+fn key_capabilities() -> impl Iterator<Item = (Key, terminfo::StringField)> {
+    KEY_CAPABILITIES.iter().cloned().chain(
+        FUNC_KEYS_KEY
+            .iter()
+            .enumerate()
+            .map(|(i, &field)| (Key::Fn(i), field)),
+    )
+}
+
+/// The keys a terminal has codes for, from `TermInfo::supported_keys`.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+pub struct KeySet {
+    keys: BTreeSet<Key>,
+}
+
+impl KeySet {
+    pub fn contains(&self, key: &Key) -> bool {
+        self.keys.contains(key)
+    }
+
+    /// The keys, in order.
+    pub fn iter(&self) -> impl Iterator<Item = &Key> {
+        self.keys.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// The keys in this set that aren't in `other`.
+    pub fn difference<'a>(&'a self, other: &'a KeySet) -> impl Iterator<Item = &'a Key> {
+        self.keys.difference(&other.keys)
+    }
+}
+
+impl<'a> IntoIterator for &'a KeySet {
+    type Item = &'a Key;
+    type IntoIter = ::std::collections::btree_set::Iter<'a, Key>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.keys.iter()
+    }
+}
+
+fn supported_keys<'a, F>(string: F) -> KeySet
+where
+    F: Fn(terminfo::StringField) -> Option<&'a [u8]>,
+{
+    KeySet {
+        keys: key_capabilities()
+            .filter(|&(_, field)| string(field).is_some_and(|s| !s.is_empty()))
+            .map(|(key, _)| key)
+            .collect(),
+    }
+}
+
+impl<'a> terminfo::TermInfo<'a> {
+    /// The keys the entry has a code for, so help text can mention F5 only if there's an F5 to press.
+    ///
+    /// Keys that send a single byte (`Char`, `Tab`, `Control` and so on) aren't in the set, every terminal has them.
+    pub fn supported_keys(&self) -> KeySet {
+        supported_keys(|field| self.string_bytes(field))
+    }
+}
+
+impl terminfo::TermInfoBuf {
+    /// The keys the entry has a code for, so help text can mention F5 only if there's an F5 to press.
+    ///
+    /// Keys that send a single byte (`Char`, `Tab`, `Control` and so on) aren't in the set, every terminal has them.
+    pub fn supported_keys(&self) -> KeySet {
+        supported_keys(|field| self.string_bytes(field))
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[non_exhaustive]
 pub enum Event {
     ScrollUp(usize),
     ScrollDonw(usize),
     Key(Key),
+    /// The terminal was resized, it's now (columns, rows).
+    Resize(usize, usize),
+    /// The event loop's heartbeat, see `EventLoopOptions::tick`.
+    Tick,
+}
+
+/// An event, with the bytes it was decoded from, see `Decoder::next_record`.
+///
+/// Events that don't come from the input, like ticks, have no bytes. Nor do the replies to queries (like
+/// `Term::cursor_position`): those are read by the query, and never get to the decoder.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct EventRecord {
+    pub event: Event,
+    pub raw: Vec<u8>,
+}
+
+impl From<Event> for EventRecord {
+    /// An event that wasn't read from the input.
+    fn from(event: Event) -> EventRecord {
+        EventRecord {
+            event,
+            raw: Vec::new(),
+        }
+    }
+}
+
+/// Collapses the stream of resize notifications into `Event::Resize`s, for event loops.
+///
+/// Dragging a window's corner sends `SIGWINCH` dozens of times a second, and redrawing for each one makes a program
+/// lag behind. Tell this about each one with `Resizes::notify` and ask `Resizes::poll` for what to report: however
+/// many notifications arrive between polls there's only one event, with the latest size, and none if the size ended
+/// up where it was (or was never for this terminal). With a debounce the event waits until the size has stayed the
+/// same that long, `Resizes::deadline` says when to poll again.
+#[derive(Debug, Copy, Clone)]
+pub struct Resizes {
+    debounce: Duration,
+    /// The size that was last reported, or that the terminal started out as.
+    reported: Option<(usize, usize)>,
+    /// The size that hasn't been reported yet, and when the terminal changed to it.
+    pending: Option<((usize, usize), Instant)>,
+}
+
+impl Resizes {
+    /// Start out with the terminal at `size`, if it's known, so a notification that doesn't change it isn't
+    /// reported.
+    pub fn new(size: Option<(usize, usize)>) -> Resizes {
+        Resizes {
+            debounce: Duration::from_secs(0),
+            reported: size,
+            pending: None,
+        }
+    }
+
+    /// Only report a resize once the size has stayed the same for `debounce`. By default it's reported at the next
+    /// poll.
+    pub fn debounce(mut self, debounce: Duration) -> Resizes {
+        self.debounce = debounce;
+        self
+    }
+
+    /// The terminal might have been resized at `now`, and it's now `size`.
+    pub fn notify(&mut self, size: (usize, usize), now: Instant) {
+        if Some(size) == self.reported {
+            // it's back where it started, or it was never resized at all
+            self.pending = None;
+        } else if self.pending.map(|(pending, _)| pending) != Some(size) {
+            self.pending = Some((size, now));
+        }
+    }
+
+    /// The resize to report at `now`, if there is one.
+    pub fn poll(&mut self, now: Instant) -> Option<Event> {
+        match self.pending {
+            Some(((cols, rows), since)) if now >= since + self.debounce => {
+                self.pending = None;
+                self.reported = Some((cols, rows));
+                Some(Event::Resize(cols, rows))
+            }
+            _ => None,
+        }
+    }
+
+    /// When the waiting resize will be reported, if one is waiting.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.pending.map(|(_, since)| since + self.debounce)
+    }
+}
+
+/// Where an event loop gets the time from. Any `Fn() -> Instant` is one, the default is `Instant::now`.
+///
+/// Tests can give `EventLoopOptions::clock` a clock they move along themselves, to check what's reported when
+/// without waiting for it.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+}
+
+impl<F: Fn() -> Instant + Send + Sync> Clock for F {
+    fn now(&self) -> Instant {
+        self()
+    }
+}
+
+/// How an `EventLoop` (see `Term::event_loop`) or an `EventStream` reports events, besides the keys it reads.
+#[derive(Clone)]
+pub struct EventLoopOptions {
+    tick: Option<Duration>,
+    idle_only: bool,
+    clock: Arc<dyn Clock>,
+}
+
+impl EventLoopOptions {
+    /// Report keys, and nothing else.
+    pub fn new() -> EventLoopOptions {
+        EventLoopOptions {
+            tick: None,
+            idle_only: false,
+            clock: Arc::new(Instant::now as fn() -> Instant),
+        }
+    }
+
+    /// Report an `Event::Tick` every `interval` when no other event is ready, to animate a spinner or blink a
+    /// cursor. See `Ticks` for how they're timed, an `interval` shorter than `MIN_TICK_INTERVAL` is made that long.
+    pub fn tick(mut self, interval: Duration) -> EventLoopOptions {
+        self.tick = Some(interval);
+        self
+    }
+
+    /// Only tick while the terminal is idle: each event puts the next tick off until `interval` after it. By
+    /// default ticks keep their cadence however many events come between them.
+    pub fn tick_when_idle(mut self, idle_only: bool) -> EventLoopOptions {
+        self.idle_only = idle_only;
+        self
+    }
+
+    /// Get the time from `clock`, rather than from `Instant::now`. An `EventStream` always uses tokio's clock.
+    pub fn clock<C: Clock + 'static>(mut self, clock: C) -> EventLoopOptions {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// The ticks these options ask for, starting at `now`.
+    pub fn ticks(&self, now: Instant) -> Option<Ticks> {
+        self.tick
+            .map(|interval| Ticks::new(interval, now).idle_only(self.idle_only))
+    }
+
+    pub(crate) fn now(&self) -> Instant {
+        self.clock.now()
+    }
 }
 
+impl Default for EventLoopOptions {
+    fn default() -> EventLoopOptions {
+        EventLoopOptions::new()
+    }
+}
+
+/// Works out when an event loop's `Event::Tick`s are due.
+///
+/// Each tick is due an interval after the one before was due, not after it was reported, so a loop that's slow to
+/// ask doesn't fall behind. Ticks that were missed altogether aren't made up for in a burst, the next one is the
+/// next that's still to come. When they're only wanted while idle, `Ticks::activity` puts the next one off.
+#[derive(Debug, Copy, Clone)]
+pub struct Ticks {
+    interval: Duration,
+    idle_only: bool,
+    next: Instant,
+}
+
+impl Ticks {
+    /// Tick every `interval`, the first one `interval` after `start`. It's at least `MIN_TICK_INTERVAL`, with no
+    /// interval at all every poll would tick and the loop would never wait.
+    pub fn new(interval: Duration, start: Instant) -> Ticks {
+        let interval = ::std::cmp::max(interval, MIN_TICK_INTERVAL);
+        Ticks {
+            interval,
+            idle_only: false,
+            next: start + interval,
+        }
+    }
+
+    /// Only tick when there's been no other event for `interval`, see `Ticks::activity`.
+    pub fn idle_only(mut self, idle_only: bool) -> Ticks {
+        self.idle_only = idle_only;
+        self
+    }
+
+    /// Another event was reported at `now`. If ticks are only wanted while idle, the next one waits until
+    /// `interval` after it.
+    pub fn activity(&mut self, now: Instant) {
+        if self.idle_only {
+            self.next = now + self.interval;
+        }
+    }
+
+    /// The tick to report at `now`, if one's due.
+    pub fn poll(&mut self, now: Instant) -> Option<Event> {
+        if now < self.next {
+            return None;
+        }
+        // skip the ones that were missed, but keep to the cadence
+        while self.next <= now {
+            self.next += self.interval;
+        }
+        Some(Event::Tick)
+    }
+
+    /// When the next tick is due.
+    pub fn deadline(&self) -> Instant {
+        self.next
+    }
+}
+
+/// Turns the bytes read from a terminal into keys, using the key codes from its terminfo entry.
+///
+/// `Keys` decodes with one of these, when the terminal is read some other way (like from a `mio` event loop) feed
+/// what's read to `Decoder::push`. Escape sequences can be split across reads, the start of one is held on to until
+/// the rest arrives.
+#[derive(Debug, Clone)]
+pub struct Decoder {
+    map: HashMap<Vec<u8>, Key>,
+    pending: VecDeque<u8>,
+}
+
+/// How many bytes of a mouse report come after its `kmous`, which ends with `last`, or `None` if they haven't all
+/// come yet.
+///
+/// After xterm's `CSI M` there are three bytes, the button and position. After the SGR form's `CSI <` the
+/// parameters go up to an `M` or an `m`, anything else ends the report early.
+fn mouse_report_len(last: u8, rest: &[u8]) -> Option<usize> {
+    if last != b'<' {
+        return if rest.len() >= 3 { Some(3) } else { None };
+    }
+    match rest.iter().position(|&c| !c.is_ascii_digit() && c != b';') {
+        Some(i) if rest[i] == b'M' || rest[i] == b'm' => Some(i + 1),
+        Some(i) => Some(i),
+        None => None,
+    }
+}
+
+#[cfg(unix)]
 pub struct Keys<'a, I, O>
 where
     I: io::Read + AsRawFd + 'a,
     O: io::Write + AsRawFd + 'a,
 {
-    // Keys may need to be buffered if we have to back out of an escape code
-    buffer: VecDeque<Key>,
-    unread: VecDeque<u8>,
-    map: HashMap<&'a str, Key>,
+    decoder: Decoder,
     tty: &'a term::Term<I, O>,
 }
 
+#[cfg(unix)]
 impl<'a, I, O> Iterator for Keys<'a, I, O>
 where
     I: io::Read + AsRawFd + 'a,
@@ -128,29 +512,21 @@ where
     type Item = Result<Key>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        // if a key is in the buffer then return it
-        match self.buffer.pop_front() {
-            Some(v) => return Some(Ok(v)),
-            None => (),
-        };
-
         Some(self.getkey())
     }
 }
 
+#[cfg(unix)]
 impl<'a, I, O> Keys<'a, I, O>
 where
     I: io::Read + AsRawFd + 'a,
     O: io::Write + AsRawFd + 'a,
 {
     pub fn new(t: &'a term::Term<I, O>) -> Keys<'a, I, O> {
-        let mut keys = Keys {
-            buffer: VecDeque::new(),
-            unread: VecDeque::new(),
+        let keys = Keys {
+            decoder: Decoder::new(&t.info),
             tty: t,
-            map: HashMap::new(),
         };
-        keys.make_keymap();
         if let Some(v) = keys.tty.info.string(terminfo::KeypadXmit) {
             keys.tty.writer().write_bytes(v.as_bytes());
             keys.tty.flush();
@@ -158,102 +534,276 @@ where
         keys
     }
 
-    fn string_to_key(&mut self, key: Key, field: terminfo::StringField) {
-        self.map
-            .insert(self.tty.info.string(field).unwrap_or(""), key);
-    }
-
-    fn make_keymap(&mut self) {
-        self.string_to_key(Key::Backspace, terminfo::KeyBackspace);
-        self.string_to_key(Key::Backtab, terminfo::BackTab);
-        self.string_to_key(Key::Begin, terminfo::KeyBeg);
-        self.string_to_key(Key::End, terminfo::KeyEnd);
-        self.string_to_key(Key::Clear, terminfo::KeyClear);
-        self.string_to_key(Key::Exit, terminfo::KeyExit);
-        self.string_to_key(Key::KeypadC1, terminfo::KeyC1);
-        self.string_to_key(Key::KeypadC3, terminfo::KeyC3);
-        self.string_to_key(Key::KeypadB2, terminfo::KeyB2);
-        self.string_to_key(Key::KeypadA3, terminfo::KeyA3);
-        self.string_to_key(Key::KeypadA1, terminfo::KeyA1);
-        self.string_to_key(Key::Up, terminfo::KeyUp);
-        self.string_to_key(Key::Down, terminfo::KeyDown);
-        self.string_to_key(Key::Left, terminfo::KeyLeft);
-        self.string_to_key(Key::Right, terminfo::KeyRight);
-        self.string_to_key(Key::Up, terminfo::ScrollForward);
-        self.string_to_key(Key::Down, terminfo::ScrollReverse);
-
-        FUNC_KEYS_KEY.iter().enumerate().for_each(|(i, &x)| {
-            self.map
-                .insert(self.tty.info.string(x).unwrap_or(""), Key::Fn(i));
-        });
-    }
-
-    fn getch(&mut self) -> Option<u8> {
-        if let Some(v) = self.unread.pop_front() {
-            return Some(v);
+    /// True if there's a key waiting, or part of one: input that hasn't been decoded, or that hasn't been read from
+    /// the terminal yet (see `Term::input_pending`).
+    pub fn input_pending(&self) -> Result<bool> {
+        if self.decoder.has_pending() {
+            return Ok(true);
         }
+        self.tty.input_pending()
+    }
+
+    fn getkey(&mut self) -> Result<Key> {
+        self.tty.err()?;
 
         let mut c: [u8; 1] = [0; 1];
-        let read = self.tty.read(&mut c);
+        loop {
+            if let Some(key) = self.decoder.next_key() {
+                return Ok(key);
+            }
+            // the start of a key code, the rest of which isn't coming
+            if self.decoder.has_pending() && !self.tty.wait_input(ESCAPE_TIMEOUT)? {
+                if let Some(EventRecord { event: Event::Key(key), .. }) = self.decoder.flush_pending() {
+                    return Ok(key);
+                }
+            }
+            if self.tty.read(&mut c) == 1 {
+                self.decoder.push(&c);
+            }
+        }
+    }
+}
+
+/// Reads the terminal's keys like `Keys`, interleaved with the other events its `EventLoopOptions` ask for, see
+/// `Term::event_loop`.
+#[cfg(unix)]
+pub struct EventLoop<'a, I, O>
+where
+    I: io::Read + AsRawFd + 'a,
+    O: io::Write + AsRawFd + 'a,
+{
+    keys: Keys<'a, I, O>,
+    options: EventLoopOptions,
+    ticks: Option<Ticks>,
+}
 
-        if read == 0 {
-            None
-        } else {
-            Some(c[0])
+#[cfg(unix)]
+impl<'a, I, O> EventLoop<'a, I, O>
+where
+    I: io::Read + AsRawFd + 'a,
+    O: io::Write + AsRawFd + 'a,
+{
+    pub fn new(t: &'a term::Term<I, O>, options: EventLoopOptions) -> EventLoop<'a, I, O> {
+        EventLoop {
+            keys: Keys::new(t),
+            ticks: options.ticks(options.now()),
+            options,
         }
     }
 
-    fn getkey_esc(&mut self) -> Result<Key> {
-        let mut read = 1;
-        let mut possible_keys = self.map.clone();
-        let mut c: [u8; 1] = [0; 1];
+    /// Wait up to `timeout` (or for ever, with `None`) for the next event.
+    ///
+    /// A key that's ready is reported before a tick that's due. Returns `None` if nothing happened in time, or if
+    /// there was input but none of it could be read, like once it's closed.
+    pub fn poll_event(&mut self, timeout: Option<Duration>) -> Result<Option<Event>> {
+        Ok(self.poll_record(timeout)?.map(|record| record.event))
+    }
 
-        while possible_keys.len() > 0 {
-            let mut read_len = self.tty.read(&mut c);
+    /// Wait for the next event like `poll_event`, along with the bytes it was decoded from (see `EventRecord`).
+    pub fn poll_record(&mut self, timeout: Option<Duration>) -> Result<Option<EventRecord>> {
+        self.keys.tty.err()?;
+        let give_up = timeout.map(|timeout| self.options.now() + timeout);
+        loop {
+            let now = self.options.now();
+            if let Some(record) = self.keys.decoder.next_record() {
+                if let Some(ref mut ticks) = self.ticks {
+                    ticks.activity(now);
+                }
+                return Ok(Some(record));
+            }
+            if self.keys.tty.input_pending()? {
+                if !self.read()? {
+                    return Ok(self.keys.decoder.flush_pending());
+                }
+                continue;
+            }
+            if let Some(tick) = self.ticks.as_mut().and_then(|ticks| ticks.poll(now)) {
+                return Ok(Some(tick.into()));
+            }
+
+            let deadline = match (self.ticks.map(|ticks| ticks.deadline()), give_up) {
+                (Some(tick), Some(give_up)) => Some(tick.min(give_up)),
+                (tick, give_up) => tick.or(give_up),
+            };
+            let wait = deadline.map_or(Duration::from_secs(3600), |deadline| {
+                deadline.saturating_duration_since(now)
+            });
+            let escape = self.keys.decoder.has_pending();
+            if self.keys.tty.wait_input(if escape { wait.min(ESCAPE_TIMEOUT) } else { wait })? {
+                if !self.read()? {
+                    return Ok(self.keys.decoder.flush_pending());
+                }
+                continue;
+            }
+            // the rest of a key code didn't come in time
+            if escape && wait >= ESCAPE_TIMEOUT {
+                if let Some(record) = self.keys.decoder.flush_pending() {
+                    if let Some(ref mut ticks) = self.ticks {
+                        ticks.activity(now);
+                    }
+                    return Ok(Some(record));
+                }
+            }
 
-            while read_len < 1 {
-                read_len = self.tty.read(&mut c);
+            // the wait ran out, so it's at least the deadline now, whatever the clock says
+            if let Some(deadline) = deadline {
+                let now = now.max(deadline);
+                if let Some(tick) = self.ticks.as_mut().and_then(|ticks| ticks.poll(now)) {
+                    return Ok(Some(tick.into()));
+                }
+                if give_up.is_some_and(|give_up| now >= give_up) {
+                    return Ok(None);
+                }
             }
+        }
+    }
+
+    /// Read the input that's waiting into the decoder, returns false if there wasn't any after all.
+    fn read(&mut self) -> Result<bool> {
+        let mut buf = [0u8; 256];
+        let n = self.keys.tty.read(&mut buf);
+        self.keys.tty.err()?;
+        self.keys.decoder.push(&buf[..n]);
+        Ok(n > 0)
+    }
+}
+
+impl Decoder {
+    /// Create a decoder for the keys the terminal `info` describes.
+    pub fn new(info: &terminfo::TermInfoBuf) -> Decoder {
+        let mut decoder = Decoder {
+            map: HashMap::new(),
+            pending: VecDeque::new(),
+        };
+        decoder.make_keymap(info);
+        decoder
+    }
+
+    /// Add bytes read from the terminal.
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.pending.extend(bytes);
+    }
+
+    /// Every escape sequence the decoder knows, with the key it decodes to, sorted by the sequence.
+    ///
+    /// These are the entry's key codes that start with an escape, other bytes are decoded on their own.
+    pub fn sequences(&self) -> Vec<(&[u8], &Key)> {
+        let mut sequences: Vec<_> = self.map.iter().map(|(k, v)| (&k[..], v)).collect();
+        sequences.sort();
+        sequences
+    }
 
-            possible_keys.retain(|k, _| k.bytes().nth(read) == Some(c[0]));
+    /// True if there are bytes that haven't been decoded yet.
+    pub fn has_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Decode the next key, or `None` if more bytes are needed.
+    ///
+    /// An escape that doesn't start any of the terminal's key codes is `Key::Escape`, and the bytes after it are
+    /// decoded on their own.
+    pub fn next_key(&mut self) -> Option<Key> {
+        let (key, len) = self.decode()?;
+        self.pending.drain(..len);
+        Some(key)
+    }
+
+    /// Decode the next key like `next_key`, along with the bytes it was decoded from.
+    ///
+    /// Every byte that's pushed ends up in one record, in the order they came, so joining up the records' `raw`
+    /// gives back exactly what was read. A multiplexer can pass that on to the program it's running, verbatim.
+    pub fn next_record(&mut self) -> Option<EventRecord> {
+        let (key, len) = self.decode()?;
+        Some(EventRecord {
+            event: Event::Key(key),
+            raw: self.pending.drain(..len).collect(),
+        })
+    }
+
+    /// Decode the next record like `next_record`, without waiting for the rest of a key code: call this when no
+    /// more input has come for a while (see `ESCAPE_TIMEOUT`), or at the end of the input. The escape a key code
+    /// starts with is `Key::Escape` then, and the bytes after it are decoded on their own, so a lone escape is the
+    /// escape key.
+    ///
+    /// Returns `None` once nothing is pending.
+    pub fn flush_pending(&mut self) -> Option<EventRecord> {
+        if let Some(record) = self.next_record() {
+            return Some(record);
+        }
+        let ch = self.pending.pop_front()?;
+        Some(EventRecord {
+            event: Event::Key(Key::Escape),
+            raw: vec![ch],
+        })
+    }
 
-            read += 1;
-            self.unread.push_back(c[0]);
+    /// Take the bytes that haven't been decoded yet, like the start of a key code the rest of which hasn't come.
+    pub fn take_pending(&mut self) -> Vec<u8> {
+        self.pending.drain(..).collect()
+    }
 
-            if let Some(k) = possible_keys
-                .iter()
-                .filter(|(k, _)| k.len() == read)
-                .map(|(_, v)| v)
-                .next()
-            {
-                self.unread.clear();
-                return Ok(k.clone());
+    /// The key at the front of what's pending, and how many bytes it is.
+    fn decode(&mut self) -> Option<(Key, usize)> {
+        let ch = *self.pending.front()?;
+        if ch != 27 {
+            return Some((byte_to_key(ch), 1));
+        }
+
+        let pending = self.pending.make_contiguous();
+        for len in 2..=pending.len() {
+            if let Some(key) = self.map.get(&pending[..len]) {
+                let key = *key;
+                let len = match key {
+                    Key::Mouse => len + mouse_report_len(pending[len - 1], &pending[len..])?,
+                    _ => len,
+                };
+                #[cfg(feature = "trace")]
+                ::trace::trace_input(&pending[..len], &key);
+                return Some((key, len));
             }
         }
+        if self
+            .map
+            .keys()
+            .any(|k| k.len() > pending.len() && k.starts_with(pending))
+        {
+            return None;
+        }
 
-        Ok(Key::Escape)
+        Some((Key::Escape, 1))
     }
 
-    fn getkey(&mut self) -> Result<Key> {
-        self.tty.err()?;
+    fn string_to_key(&mut self, info: &terminfo::TermInfoBuf, key: Key, field: terminfo::StringField) {
+        if let Some(s) = info.string(field) {
+            if s.len() > 1 && s.starts_with('\x1b') {
+                self.map.insert(s.as_bytes().to_vec(), key);
+            }
+        }
+    }
 
-        let mut c = self.getch();
-        while c.is_none() {
-            c = self.getch();
+    fn make_keymap(&mut self, info: &terminfo::TermInfoBuf) {
+        self.string_to_key(info, Key::Up, terminfo::ScrollForward);
+        self.string_to_key(info, Key::Down, terminfo::ScrollReverse);
+        for (key, field) in key_capabilities() {
+            self.string_to_key(info, key, field);
         }
-        let ch = c.unwrap();
-        Ok(match ch {
-            0...8 | 10...12 | 14...26 | 28...31 => Key::Control((ch + 64) as char),
-            9 => Key::Tab,
-            10 | 13 => Key::Enter,
-            27 => self.getkey_esc()?,
-            127 => Key::Delete,
-            32...126 => Key::Char(ch as char),
-            _ => Key::Invalid(ch),
-        })
     }
 }
 
+/// Decode a byte that isn't part of an escape sequence.
+pub(crate) fn byte_to_key(ch: u8) -> Key {
+    match ch {
+        9 => Key::Tab,
+        // a tty that turns `\r` into `\n` (`ICRNL`) sends Enter as `\n`
+        10 | 13 => Key::Enter,
+        0..=8 | 11..=12 | 14..=26 | 28..=31 => Key::Control((ch + 64) as char),
+        127 => Key::Delete,
+        32..=126 => Key::Char(ch as char),
+        _ => Key::Invalid(ch),
+    }
+}
+
+#[cfg(unix)]
 impl<'a, I, O> Drop for Keys<'a, I, O>
 where
     I: io::Read + AsRawFd + 'a,
@@ -266,3 +816,321 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use events::*;
+
+    const XTERM_INFO: &'static [u8] = include_bytes!("../test-data/xterm");
+    const XTERM_256_INFO: &'static [u8] = include_bytes!("../test-data/xterm-256color");
+    const LINUX_INFO: &'static [u8] = include_bytes!("../test-data/linux-16color");
+
+    #[test]
+    fn supported_keys() {
+        let linux = terminfo::TermInfo::parse(LINUX_INFO).unwrap();
+        let xterm = terminfo::TermInfo::parse(XTERM_256_INFO).unwrap();
+        let linux_keys = linux.supported_keys();
+        let xterm_keys = xterm.supported_keys();
+
+        for key in &[
+            Key::Home,
+            Key::Insert,
+            Key::Delete,
+            Key::PageUp,
+            Key::Mouse,
+            Key::Fn(20),
+        ] {
+            assert!(linux_keys.contains(key), "{:?}", key);
+            assert!(xterm_keys.contains(key), "{:?}", key);
+        }
+        // the console stops at F20, and only has the middle of the keypad
+        assert!(!linux_keys.contains(&Key::Fn(21)));
+        assert!(xterm_keys.contains(&Key::Fn(63)));
+        assert_eq!(
+            xterm_keys
+                .difference(&linux_keys)
+                .filter(|k| match **k {
+                    Key::Fn(_) => false,
+                    _ => true,
+                })
+                .collect::<Vec<_>>(),
+            vec![
+                &Key::Enter,
+                &Key::Begin,
+                &Key::KeypadA1,
+                &Key::KeypadA3,
+                &Key::KeypadC1,
+                &Key::KeypadC3,
+            ]
+        );
+        assert_eq!(linux_keys.difference(&xterm_keys).count(), 0);
+        let buf: terminfo::TermInfoBuf = terminfo::TermInfo::parse(LINUX_INFO).unwrap().into();
+        assert_eq!(buf.supported_keys(), linux_keys);
+
+        assert_eq!(Key::Fn(5).capability_name(), Some("kf5"));
+        assert_eq!(Key::Insert.capability_name(), Some("kich1"));
+        assert_eq!(Key::Mouse.capability(), Some(terminfo::KeyMouse));
+        assert_eq!(Key::Fn(64).capability_name(), None);
+        assert_eq!(Key::Char('a').capability_name(), None);
+        for (key, field) in key_capabilities() {
+            assert_eq!(key.capability(), Some(field));
+        }
+    }
+
+    #[test]
+    fn sequences() {
+        let info: terminfo::TermInfoBuf = terminfo::TermInfo::parse(LINUX_INFO).unwrap().into();
+        let decoder = Decoder::new(&info);
+        let sequences = decoder.sequences();
+        assert!(sequences.windows(2).all(|w| w[0].0 < w[1].0));
+        assert!(sequences.contains(&(&b"\x1b[2~"[..], &Key::Insert)));
+        assert!(sequences.contains(&(&b"\x1b[[A"[..], &Key::Fn(1))));
+        // kbs is ^? on the console, which is decoded without the table
+        assert!(sequences.iter().all(|&(_, key)| *key != Key::Backspace));
+
+        let mut decoder = decoder;
+        decoder.push(b"\x1b[1~\x1b[6~\x1b[M !");
+        assert_eq!(decoder.next_key(), Some(Key::Home));
+        assert_eq!(decoder.next_key(), Some(Key::PageDown));
+        // the button and position haven't all come yet
+        assert_eq!(decoder.next_key(), None);
+        decoder.push(b"!x");
+        assert_eq!(decoder.next_key(), Some(Key::Mouse));
+        assert_eq!(decoder.next_key(), Some(Key::Char('x')));
+
+        // xterm's is the SGR form, whose parameters end with `M` or `m`
+        let info: terminfo::TermInfoBuf = terminfo::TermInfo::parse(XTERM_INFO).unwrap().into();
+        let mut decoder = Decoder::new(&info);
+        decoder.push(b"\x1b[<0;12;3");
+        assert_eq!(decoder.next_key(), None);
+        decoder.push(b"Mx\x1b[<0;12;3mx\x1b[<0;1\x1b[2~");
+        for &key in [Key::Mouse, Key::Char('x'), Key::Mouse, Key::Char('x'), Key::Mouse, Key::Insert].iter() {
+            assert_eq!(decoder.next_key(), Some(key));
+        }
+    }
+
+    #[test]
+    fn decoder() {
+        let info: terminfo::TermInfoBuf = terminfo::TermInfo::parse(XTERM_INFO).unwrap().into();
+        let mut decoder = Decoder::new(&info);
+        assert_eq!(decoder.next_key(), None);
+
+        decoder.push(b"a\x01\t\r\n\x0b\x7f");
+        assert_eq!(decoder.next_key(), Some(Key::Char('a')));
+        assert_eq!(decoder.next_key(), Some(Key::Control('A')));
+        assert_eq!(decoder.next_key(), Some(Key::Tab));
+        assert_eq!(decoder.next_key(), Some(Key::Enter));
+        assert_eq!(decoder.next_key(), Some(Key::Enter));
+        assert_eq!(decoder.next_key(), Some(Key::Control('K')));
+        assert_eq!(decoder.next_key(), Some(Key::Delete));
+        assert!(!decoder.has_pending());
+
+        // a key split across reads is held until the rest arrives
+        decoder.push(b"\x1bO");
+        assert_eq!(decoder.next_key(), None);
+        assert!(decoder.has_pending());
+        decoder.push(b"P\x1b[15~");
+        assert_eq!(decoder.next_key(), Some(Key::Fn(1)));
+        assert_eq!(decoder.next_key(), Some(Key::Fn(5)));
+
+        // an escape that isn't a key
+        decoder.push(b"\x1bx\x1b\x1b");
+        assert_eq!(decoder.next_key(), Some(Key::Escape));
+        assert_eq!(decoder.next_key(), Some(Key::Char('x')));
+        assert_eq!(decoder.next_key(), Some(Key::Escape));
+        assert_eq!(decoder.next_key(), None);
+
+        // a bare escape is held, until there's nothing more coming
+        assert!(decoder.has_pending());
+        let record = decoder.flush_pending().unwrap();
+        assert_eq!((record.event, record.raw), (Event::Key(Key::Escape), b"\x1b".to_vec()));
+        assert!(decoder.flush_pending().is_none());
+        // and what's been read of a cut off key code isn't lost
+        decoder.push(b"\x1b[1");
+        assert_eq!(decoder.next_key(), None);
+        let keys: Vec<_> = ::std::iter::from_fn(|| decoder.flush_pending()).map(|r| r.event).collect();
+        let expected = vec![Key::Escape, Key::Char('['), Key::Char('1')];
+        assert_eq!(keys, expected.into_iter().map(Event::Key).collect::<Vec<_>>());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn resizes() {
+        use backend::MockBackend;
+        use std::time::{Duration, Instant};
+
+        let mock = MockBackend::new();
+        let term = mock.term(terminfo::TermInfo::parse(XTERM_INFO).unwrap().into());
+        let start = Instant::now();
+        let ms = |n| start + Duration::from_millis(n);
+
+        // a burst while the window's dragged is one resize, to where it ended up
+        let mut resizes = Resizes::new(term.size().ok());
+        for (i, &(cols, rows)) in [(81, 24), (85, 26), (90, 30), (100, 40)].iter().enumerate() {
+            mock.set_size(cols, rows);
+            resizes.notify(term.size().unwrap(), ms(i as u64));
+        }
+        assert_eq!(resizes.poll(ms(3)), Some(Event::Resize(100, 40)));
+        assert_eq!(resizes.poll(ms(3)), None);
+        // another terminal's resize, or one that ends up where it started, isn't reported
+        resizes.notify(term.size().unwrap(), ms(4));
+        mock.set_size(120, 40);
+        resizes.notify(term.size().unwrap(), ms(5));
+        mock.set_size(100, 40);
+        resizes.notify(term.size().unwrap(), ms(6));
+        assert_eq!(resizes.poll(ms(6)), None);
+        assert_eq!(resizes.deadline(), None);
+
+        // with a debounce it waits for the size to settle
+        let mut resizes = Resizes::new(term.size().ok()).debounce(Duration::from_millis(50));
+        for (i, &(cols, rows)) in [(90, 40), (80, 30), (80, 24)].iter().enumerate() {
+            mock.set_size(cols, rows);
+            resizes.notify(term.size().unwrap(), ms(i as u64 * 20));
+            assert_eq!(resizes.poll(ms(i as u64 * 20 + 10)), None);
+        }
+        // the same size again doesn't start the wait over
+        resizes.notify(term.size().unwrap(), ms(60));
+        assert_eq!(resizes.deadline(), Some(ms(90)));
+        assert_eq!(resizes.poll(ms(89)), None);
+        assert_eq!(resizes.poll(ms(90)), Some(Event::Resize(80, 24)));
+        assert_eq!(resizes.deadline(), None);
+    }
+
+    #[test]
+    fn ticks() {
+        let start = Instant::now();
+        let ms = |n| start + Duration::from_millis(n);
+
+        let mut ticks = Ticks::new(Duration::from_millis(100), start);
+        assert_eq!(ticks.poll(ms(99)), None);
+        assert_eq!(ticks.poll(ms(100)), Some(Event::Tick));
+        assert_eq!(ticks.poll(ms(100)), None);
+        // a tick that's reported late doesn't push the next one back
+        assert_eq!(ticks.poll(ms(230)), Some(Event::Tick));
+        assert_eq!(ticks.deadline(), ms(300));
+        // ticks that were missed are skipped, not reported all at once
+        assert_eq!(ticks.poll(ms(650)), Some(Event::Tick));
+        assert_eq!(ticks.poll(ms(650)), None);
+        assert_eq!(ticks.deadline(), ms(700));
+        // other events don't matter
+        ticks.activity(ms(690));
+        assert_eq!(ticks.poll(ms(700)), Some(Event::Tick));
+
+        // unless it only ticks while idle
+        let mut ticks = Ticks::new(Duration::from_millis(100), start).idle_only(true);
+        ticks.activity(ms(50));
+        assert_eq!(ticks.poll(ms(100)), None);
+        assert_eq!(ticks.poll(ms(150)), Some(Event::Tick));
+        assert_eq!(ticks.deadline(), ms(250));
+
+        // no interval would tick on every poll, it's made as short as it can be instead
+        let mut ticks = Ticks::new(Duration::from_secs(0), start);
+        assert_eq!(ticks.poll(start), None);
+        assert_eq!(ticks.deadline(), start + MIN_TICK_INTERVAL);
+        assert_eq!(ticks.poll(ms(5)), Some(Event::Tick));
+        assert_eq!(ticks.poll(ms(5)), None);
+        assert_eq!(ticks.deadline(), ms(6));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn event_loop() {
+        use backend::MockBackend;
+        use std::sync::Mutex;
+
+        let mock = MockBackend::new();
+        let term = mock.term(terminfo::TermInfo::parse(XTERM_INFO).unwrap().into());
+        let start = Instant::now();
+        let ms = |n| start + Duration::from_millis(n);
+        let now = Arc::new(Mutex::new(start));
+        let clock = {
+            let now = now.clone();
+            move || *now.lock().unwrap()
+        };
+        let set = |n| *now.lock().unwrap() = ms(n);
+        let options = EventLoopOptions::new()
+            .tick(Duration::from_millis(100))
+            .clock(clock);
+        let poll = |events: &mut EventLoop<_, _>, timeout| {
+            events
+                .poll_event(Some(Duration::from_millis(timeout)))
+                .unwrap()
+        };
+
+        let mut events = term.event_loop(options.clone());
+        assert_eq!(poll(&mut events, 0), None);
+        // the mock doesn't wait, but a wait for input that runs out means the tick is due
+        assert_eq!(poll(&mut events, 150), Some(Event::Tick));
+        assert_eq!(poll(&mut events, 50), None);
+        set(130);
+        assert_eq!(poll(&mut events, 0), None);
+        set(200);
+        assert_eq!(poll(&mut events, 0), Some(Event::Tick));
+        // keys that are ready come first
+        set(350);
+        mock.push_input(b"a\x1bOA");
+        assert_eq!(poll(&mut events, 0), Some(Event::Key(Key::Char('a'))));
+        assert_eq!(poll(&mut events, 0), Some(Event::Key(Key::Up)));
+        assert_eq!(poll(&mut events, 0), Some(Event::Tick));
+        assert_eq!(poll(&mut events, 0), None);
+        drop(events);
+
+        // only while idle, keys put it off
+        set(0);
+        let mut events = term.event_loop(options.tick_when_idle(true));
+        set(50);
+        mock.push_input(b"a");
+        assert_eq!(poll(&mut events, 0), Some(Event::Key(Key::Char('a'))));
+        set(120);
+        assert_eq!(poll(&mut events, 0), None);
+        assert_eq!(poll(&mut events, 100), Some(Event::Tick));
+        set(150);
+        mock.push_input(b"b");
+        assert_eq!(poll(&mut events, 0), Some(Event::Key(Key::Char('b'))));
+        set(249);
+        assert_eq!(poll(&mut events, 0), None);
+        set(250);
+        assert_eq!(poll(&mut events, 0), Some(Event::Tick));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn records() {
+        use backend::MockBackend;
+
+        // keys, split up wherever the reads happened to end
+        let info: terminfo::TermInfoBuf = terminfo::TermInfo::parse(XTERM_INFO).unwrap().into();
+        let input: &[u8] = b"ls\r\x1bOA\x1b[3~\x1bx\x1b\x1b[15~\x7fq\x1bO";
+        for split in 1..input.len() {
+            let mut decoder = Decoder::new(&info);
+            let mut keys = Decoder::new(&info);
+            keys.push(input);
+            let mut raw = Vec::new();
+            for chunk in input.chunks(split) {
+                decoder.push(chunk);
+                while let Some(record) = decoder.next_record() {
+                    assert_eq!(Some(record.event), keys.next_key().map(Event::Key));
+                    raw.extend(record.raw);
+                }
+            }
+            assert_eq!(keys.next_key(), None);
+            assert_eq!(decoder.take_pending(), b"\x1bO");
+            assert!(!decoder.has_pending());
+            raw.extend(b"\x1bO");
+            assert_eq!(raw, input, "{}", split);
+        }
+
+        // a query's reply isn't part of them
+        let mock = MockBackend::new();
+        let term = mock.term(info);
+        mock.push_input(b"a\x1b[5;9Rb");
+        assert_eq!(term.cursor_position(Duration::from_secs(1)).unwrap(), (8, 4));
+        let mut events = term.event_loop(EventLoopOptions::new().tick(Duration::from_secs(9)));
+        let mut raw = Vec::new();
+        while let Some(record) = events.poll_record(Some(Duration::from_millis(0))).unwrap() {
+            raw.extend(record.raw);
+        }
+        assert_eq!(raw, b"ab");
+        assert_eq!(EventRecord::from(Event::Tick).raw, b"");
+    }
+}
@@ -0,0 +1,261 @@
+//! Holding the terminal's output back, see `Term::pause_output`, and batching it up, see `Term::frame`.
+use std::io;
+use term::OverflowPolicy;
+
+/// How many bytes are held back while output is paused, unless `Term::set_pause_buffer` says otherwise.
+pub(crate) const DEFAULT_CAP: usize = 64 * 1024;
+
+/// Writes made up of more than this many bytes are sent as a vectored write, rather than copied into one buffer.
+/// Copying a small frame costs less than building the `iovec`s does.
+pub(crate) const VECTORED_THRESHOLD: usize = 16 * 1024;
+
+/// A stream that buffers what's written to it while it's paused, and sends it on when it's resumed.
+///
+/// It also collects a batch of writes (like a whole frame) so they're sent at once.
+pub(crate) struct Gate<W> {
+    inner: W,
+    paused: bool,
+    held: Vec<u8>,
+    cap: usize,
+    policy: OverflowPolicy,
+    batch: Option<Vec<u8>>,
+}
+
+impl<W: io::Write> Gate<W> {
+    pub(crate) fn new(inner: W) -> Gate<W> {
+        Gate {
+            inner,
+            paused: false,
+            held: Vec::new(),
+            cap: DEFAULT_CAP,
+            policy: OverflowPolicy::default(),
+            batch: None,
+        }
+    }
+
+    pub(crate) fn set_limit(&mut self, cap: usize, policy: OverflowPolicy) {
+        self.cap = cap;
+        self.policy = policy;
+    }
+
+    pub(crate) fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    pub(crate) fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub(crate) fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    /// Stop holding output back, and send what was.
+    pub(crate) fn resume(&mut self) -> io::Result<()> {
+        self.paused = false;
+        self.release()?;
+        self.inner.flush()
+    }
+
+    /// Collect what's written until `end_batch`. Returns false, and does nothing, if a batch has already begun.
+    pub(crate) fn begin_batch(&mut self) -> bool {
+        if self.batch.is_some() {
+            return false;
+        }
+        self.batch = Some(Vec::new());
+        true
+    }
+
+    /// Stop collecting writes, and return what was written since `begin_batch`.
+    pub(crate) fn end_batch(&mut self) -> Vec<u8> {
+        self.batch.take().unwrap_or_default()
+    }
+
+    /// Write `buf` past the batch that's being collected, for something that has to reach the terminal now, like a
+    /// query. What's been collected so far is sent first, so everything arrives in order, and the batch carries on
+    /// collecting afterwards. It's flushed, unless output is paused.
+    pub(crate) fn write_through(&mut self, buf: &[u8]) -> io::Result<()> {
+        let batch = self.batch.take();
+        if let Some(ref collected) = batch {
+            if let Err(e) = io::Write::write_all(self, collected) {
+                // it's still sent at the end of the batch
+                self.batch = batch;
+                return Err(e);
+            }
+        }
+        let sent = io::Write::write_all(self, buf).and_then(|()| io::Write::flush(self));
+        self.batch = batch.map(|_| Vec::new());
+        sent
+    }
+
+    /// Write all of `parts` in as few writes as the inner stream takes: copied into one buffer when they're small,
+    /// and as vectored writes when they add up to more than `VECTORED_THRESHOLD`.
+    ///
+    /// While paused or collecting a batch they're held back like any other write.
+    pub(crate) fn submit(&mut self, parts: &[&[u8]]) -> io::Result<()> {
+        let len: usize = parts.iter().map(|part| part.len()).sum();
+        if self.paused || self.batch.is_some() || len <= VECTORED_THRESHOLD {
+            let mut buf = Vec::with_capacity(len);
+            for part in parts {
+                buf.extend_from_slice(part);
+            }
+            return io::Write::write_all(self, &buf);
+        }
+
+        let mut slices: Vec<io::IoSlice> = parts
+            .iter()
+            .filter(|part| !part.is_empty())
+            .map(|part| io::IoSlice::new(part))
+            .collect();
+        let mut slices = &mut slices[..];
+        while !slices.is_empty() {
+            match self.inner.write_vectored(slices) {
+                Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(n) => io::IoSlice::advance_slices(&mut slices, n),
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Send what's held back. It stays held back if the inner stream fails, so nothing is lost.
+    fn release(&mut self) -> io::Result<()> {
+        let held = ::std::mem::take(&mut self.held);
+        self.inner.write_all(&held).inspect_err(|_| self.held = held)
+    }
+}
+
+impl<W: io::Write> io::Write for Gate<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Some(ref mut batch) = self.batch {
+            batch.extend_from_slice(buf);
+            return Ok(buf.len());
+        }
+        if !self.paused {
+            return self.inner.write(buf);
+        }
+        if self.held.len() + buf.len() <= self.cap {
+            self.held.extend_from_slice(buf);
+            return Ok(buf.len());
+        }
+
+        // a write is taken or refused whole, so an escape sequence is never cut in two
+        match self.policy {
+            OverflowPolicy::Fail => Err(io::Error::new(
+                io::ErrorKind::WouldBlock,
+                "output is paused and its buffer is full",
+            )),
+            OverflowPolicy::Discard => Ok(buf.len()),
+            OverflowPolicy::Send => {
+                self.release()?;
+                self.inner.write_all(buf)?;
+                Ok(buf.len())
+            }
+        }
+    }
+
+    fn write_vectored(&mut self, bufs: &[io::IoSlice]) -> io::Result<usize> {
+        if self.paused || self.batch.is_some() {
+            // taken or refused whole, like any other write
+            let buf: Vec<u8> = bufs.iter().flat_map(|buf| buf.iter().cloned()).collect();
+            return self.write(&buf);
+        }
+        self.inner.write_vectored(bufs)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.paused || self.batch.is_some() {
+            Ok(())
+        } else {
+            self.inner.flush()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use gate::*;
+    use std::io::Write;
+
+    #[test]
+    fn held_in_order() {
+        let mut gate = Gate::new(Vec::new());
+        gate.write_all(b"a").unwrap();
+        gate.pause();
+        gate.write_all(b"\x1b[1m").unwrap();
+        gate.write_all(b"b").unwrap();
+        gate.flush().unwrap();
+        assert_eq!(gate.inner, b"a");
+        assert_eq!(gate.held.len(), 5);
+
+        gate.resume().unwrap();
+        gate.write_all(b"c").unwrap();
+        assert_eq!(gate.inner, b"a\x1b[1mbc");
+        assert_eq!(gate.held.len(), 0);
+    }
+
+    #[test]
+    fn overflow() {
+        let paused = |policy| {
+            let mut gate = Gate::new(Vec::new());
+            gate.set_limit(4, policy);
+            gate.pause();
+            gate.write_all(b"abc").unwrap();
+            gate
+        };
+
+        let mut gate = paused(OverflowPolicy::Fail);
+        gate.write_all(b"d").unwrap();
+        let err = gate.write_all(b"e").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+        gate.resume().unwrap();
+        assert_eq!(gate.inner, b"abcd");
+
+        let mut gate = paused(OverflowPolicy::Discard);
+        gate.write_all(b"\x1b[1m").unwrap();
+        gate.write_all(b"d").unwrap();
+        gate.resume().unwrap();
+        assert_eq!(gate.inner, b"abcd");
+
+        let mut gate = paused(OverflowPolicy::Send);
+        gate.write_all(b"de").unwrap();
+        assert_eq!(gate.inner, b"abcde");
+        assert!(gate.is_paused());
+        gate.write_all(b"f").unwrap();
+        gate.resume().unwrap();
+        assert_eq!(gate.inner, b"abcdef");
+    }
+
+    #[test]
+    fn batch() {
+        let mut gate = Gate::new(Vec::new());
+        assert!(gate.begin_batch());
+        assert!(!gate.begin_batch());
+        gate.write_all(b"ab").unwrap();
+        gate.flush().unwrap();
+        assert_eq!(gate.inner, b"");
+        let frame = gate.end_batch();
+        gate.submit(&[b"<", &frame, b"", b">"]).unwrap();
+        assert_eq!(gate.inner, b"<ab>");
+
+        // held back while paused, however big it is
+        gate.pause();
+        let big = vec![b'x'; VECTORED_THRESHOLD];
+        gate.submit(&[b"<", &big, b">"]).unwrap();
+        assert_eq!(gate.inner.len(), 4);
+        gate.resume().unwrap();
+        assert_eq!(gate.inner.len(), 4 + VECTORED_THRESHOLD + 2);
+
+        // a query gets past the batch, after what's been collected
+        gate.inner.clear();
+        gate.begin_batch();
+        gate.write_all(b"ab").unwrap();
+        gate.write_through(b"?").unwrap();
+        gate.write_all(b"c").unwrap();
+        assert_eq!(gate.inner, b"ab?");
+        let frame = gate.end_batch();
+        gate.submit(&[&frame, b">"]).unwrap();
+        assert_eq!(gate.inner, b"ab?c>");
+    }
+}
@@ -1,16 +1,74 @@
-#[macro_use]
-extern crate failure;
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+#[cfg(not(any(feature = "std", test)))]
+extern crate alloc;
+#[cfg(feature = "async-tokio")]
+extern crate futures_core;
+#[cfg(feature = "trace")]
+extern crate log;
 extern crate memchr;
+#[cfg(feature = "mio")]
+extern crate mio;
+#[cfg(all(feature = "std", unix))]
 extern crate nix;
+#[cfg(feature = "async-tokio")]
+extern crate tokio;
+#[cfg(feature = "unicode")]
+extern crate unicode_segmentation;
+
+#[cfg(not(any(feature = "std", test)))]
+#[path = "no_std.rs"]
+mod std;
 
+#[cfg(feature = "std")]
+pub mod acs;
+#[cfg(feature = "std")]
 #[macro_use]
 pub mod ansi;
+#[cfg(all(feature = "std", unix))]
+pub mod backend;
+#[cfg(feature = "std")]
+pub mod cursor;
+#[cfg(all(feature = "std", unix))]
+mod counter;
+#[cfg(all(feature = "std", unix))]
+pub mod diagnostics;
+#[cfg(feature = "std")]
+pub mod encoding;
+#[cfg(feature = "std")]
 mod errors;
+#[cfg(feature = "std")]
 pub mod events;
+#[cfg(all(feature = "std", unix))]
+mod gate;
+#[cfg(feature = "std")]
+mod overrides;
+#[cfg(all(feature = "pty", unix))]
+pub mod pty;
+#[cfg(feature = "std")]
+pub mod query;
+#[cfg(all(feature = "std", unix))]
+pub mod quick;
+#[cfg(feature = "std")]
+pub mod record;
+#[cfg(feature = "std")]
+pub mod screen;
+#[cfg(all(feature = "async-tokio", unix))]
+pub mod stream;
+#[cfg(all(feature = "std", unix))]
 pub mod term;
 pub mod terminfo;
+#[cfg(feature = "std")]
+mod trace;
 mod util;
+#[cfg(feature = "windows")]
+pub mod windows;
+#[cfg(feature = "std")]
 pub mod xterm;
 
+#[cfg(feature = "std")]
 pub use self::errors::*;
+#[cfg(feature = "std")]
+pub use overrides::EnvOverrides;
+#[cfg(all(feature = "std", unix))]
 pub use term::Term;
@@ -0,0 +1,83 @@
+//! The parts of `std` the terminfo parser uses, built from `core` and `alloc`.
+//!
+//! Without the `std` feature this is mounted as the crate's `std` module, so `use std::...` means the same thing
+//! with or without an OS.
+
+pub use alloc::{borrow, boxed, collections, fmt, slice, str, string, vec};
+pub use core::*;
+
+/// Just enough of `std::io` to write expanded terminfo strings into a buffer.
+pub mod io {
+    use alloc::vec::Vec;
+    use core::{fmt, mem, result};
+
+    pub type Result<T> = result::Result<T, Error>;
+
+    /// Writing failed.
+    #[derive(Debug)]
+    pub struct Error;
+
+    impl fmt::Display for Error {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "failed to write")
+        }
+    }
+
+    impl ::core::error::Error for Error {}
+
+    /// The part of `std::io::Write` terminfo strings are written with.
+    pub trait Write {
+        fn write(&mut self, buf: &[u8]) -> Result<usize>;
+
+        fn write_all(&mut self, mut buf: &[u8]) -> Result<()> {
+            while !buf.is_empty() {
+                match self.write(buf)? {
+                    0 => return Err(Error),
+                    n => buf = &buf[n..],
+                }
+            }
+            Ok(())
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Write for Vec<u8> {
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            self.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+    }
+
+    /// Like `std`, writing to a slice fills it from the front and moves it past what was written.
+    impl Write for &mut [u8] {
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            let n = buf.len().min(self.len());
+            let (head, tail) = mem::take(self).split_at_mut(n);
+            head.copy_from_slice(&buf[..n]);
+            *self = tail;
+            Ok(n)
+        }
+    }
+
+    impl<W: Write + ?Sized> Write for &mut W {
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            (**self).write(buf)
+        }
+    }
+
+    /// Throws away everything written to it, see `sink`.
+    pub struct Sink;
+
+    pub fn sink() -> Sink {
+        Sink
+    }
+
+    impl Write for Sink {
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            Ok(buf.len())
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+use std::env;
+
+/// The environment variables that override what the tty and the terminfo entry say about the terminal.
+///
+/// Like curses, `$COLUMNS` and `$LINES` take the place of the terminal's size, and `$COLORTERM=truecolor` (or
+/// `24bit`) means the terminal can show any 24-bit color, even though hardly any terminfo entries say so.
+/// `NO_COLOR` and `CLICOLOR_FORCE` are read from here too, see `ColorChoice::resolve`.
+///
+/// Something asked for explicitly (like `ColorChoice::Never`, or the size passed to `Screen::new`) always wins over
+/// the environment, which wins over the tty and the terminfo entry.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct EnvOverrides {
+    /// The variables to use instead of the process's environment.
+    vars: Option<HashMap<String, String>>,
+}
+
+impl EnvOverrides {
+    /// Read the process's environment, every time an override is looked up.
+    pub fn from_env() -> EnvOverrides {
+        EnvOverrides { vars: None }
+    }
+
+    /// Use `vars` instead of the process's environment, so it's left alone (and nothing in it is overridden) when
+    /// `vars` is empty.
+    pub fn from_map<I, K, V>(vars: I) -> EnvOverrides
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        EnvOverrides {
+            vars: Some(
+                vars.into_iter()
+                    .map(|(k, v)| (k.into(), v.into()))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Look up the variable `name`.
+    pub fn var(&self, name: &str) -> Option<String> {
+        match self.vars {
+            Some(ref vars) => vars.get(name).cloned(),
+            None => env::var(name).ok(),
+        }
+    }
+
+    /// The terminal's width from `$COLUMNS`, if it's set to a positive number.
+    pub fn columns(&self) -> Option<usize> {
+        self.size_var("COLUMNS")
+    }
+
+    /// The terminal's height from `$LINES`, if it's set to a positive number.
+    pub fn lines(&self) -> Option<usize> {
+        self.size_var("LINES")
+    }
+
+    /// True if `$COLORTERM` says the terminal can show 24-bit colors.
+    pub fn truecolor(&self) -> bool {
+        self.var("COLORTERM")
+            .is_some_and(|v| v == "truecolor" || v == "24bit")
+    }
+
+    /// The number of colors the terminal can show, given the terminfo entry's `colors`.
+    pub fn max_colors(&self, max_colors: Option<u32>) -> Option<u32> {
+        if self.truecolor() {
+            Some(max_colors.map_or(1 << 24, |n| n.max(1 << 24)))
+        } else {
+            max_colors
+        }
+    }
+
+    fn size_var(&self, name: &str) -> Option<usize> {
+        self.var(name)
+            .and_then(|v| v.trim().parse().ok())
+            .filter(|&n| n > 0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use overrides::EnvOverrides;
+    use std::env;
+
+    #[test]
+    fn overrides() {
+        let vars = EnvOverrides::from_map(vec![
+            ("COLUMNS", "120"),
+            ("LINES", " 40 "),
+            ("COLORTERM", "truecolor"),
+        ]);
+        assert_eq!(vars.columns(), Some(120));
+        assert_eq!(vars.lines(), Some(40));
+        assert!(vars.truecolor());
+        assert_eq!(vars.max_colors(Some(8)), Some(1 << 24));
+        assert_eq!(vars.max_colors(None), Some(1 << 24));
+        assert_eq!(vars.var("HOME"), None);
+
+        // nonsense is ignored
+        let vars = EnvOverrides::from_map(vec![
+            ("COLUMNS", "0"),
+            ("LINES", "many"),
+            ("COLORTERM", "yes"),
+        ]);
+        assert_eq!(vars.columns(), None);
+        assert_eq!(vars.lines(), None);
+        assert!(!vars.truecolor());
+        assert_eq!(vars.max_colors(Some(256)), Some(256));
+        assert_eq!(vars.max_colors(None), None);
+
+        let vars = EnvOverrides::from_map(Vec::<(String, String)>::new());
+        assert_eq!(vars.columns(), None);
+        assert_eq!(vars.max_colors(Some(8)), Some(8));
+
+        assert_eq!(EnvOverrides::from_env().var("PATH"), env::var("PATH").ok());
+    }
+}
@@ -0,0 +1,209 @@
+//! Pseudo terminals, for testing programs that need a terminal, and for embedding them.
+//!
+//! `openpty` opens a pair of ends, and `Pty::spawn` runs a command with a new pseudo terminal as its controlling
+//! terminal and its stdio, like `login_tty(3)` does, handing back the master end to read its output from and type
+//! its input into. The slave end is a real tty, so it can be given to `Term::from_streams`.
+use errors::*;
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+use nix::fcntl::{self, FcntlArg, FdFlag};
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use nix::fcntl::OFlag;
+use nix::libc;
+use nix::pty;
+use std::convert::TryFrom;
+use std::fs::File;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use std::fs::OpenOptions;
+use std::io;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, RawFd};
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command, Stdio};
+
+/// The master end of a pseudo terminal, which reads what's written to the terminal and writes what's typed into it.
+///
+/// Once every copy of the slave end has been closed, reads return 0. Linux fails them with `EIO` instead, which is
+/// turned into the end of the file.
+#[derive(Debug)]
+pub struct Master {
+    file: File,
+}
+
+/// The slave end of a pseudo terminal, the terminal a program sees.
+#[derive(Debug)]
+pub struct Slave {
+    file: File,
+}
+
+/// A command running on its own pseudo terminal, returned by `Pty::spawn`.
+#[derive(Debug)]
+pub struct Pty {
+    pub master: Master,
+    pub child: Child,
+}
+
+/// Open a new pseudo terminal, its size is 0x0 until `Master::resize` is called.
+///
+/// Neither end is inherited by the commands this process runs, `Pty::spawn` passes the slave end on explicitly.
+///
+/// On Linux both ends are opened close-on-exec. Elsewhere they're made close-on-exec straight after `openpty(3)`, so a
+/// command another thread starts in between can still inherit them.
+pub fn openpty() -> Result<(Master, Slave)> {
+    let (master, slave) = open_pair()?;
+    Ok((Master { file: master }, Slave { file: slave }))
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn open_pair() -> Result<(File, File)> {
+    let flags = OFlag::O_RDWR | OFlag::O_NOCTTY | OFlag::O_CLOEXEC;
+    let master = pty::posix_openpt(flags).context(ErrorKind::FailedToOpenPty)?;
+    pty::grantpt(&master).context(ErrorKind::FailedToOpenPty)?;
+    pty::unlockpt(&master).context(ErrorKind::FailedToOpenPty)?;
+    let name = pty::ptsname_r(&master).context(ErrorKind::FailedToOpenPty)?;
+    // `File` always opens close-on-exec
+    let slave = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .custom_flags(libc::O_NOCTTY)
+        .open(name)
+        .context(ErrorKind::FailedToOpenPty)?;
+    Ok((unsafe { File::from_raw_fd(master.into_raw_fd()) }, slave))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn open_pair() -> Result<(File, File)> {
+    let pty = pty::openpty(None, None).context(ErrorKind::FailedToOpenPty)?;
+    let pair = unsafe { (File::from_raw_fd(pty.master), File::from_raw_fd(pty.slave)) };
+    for fd in &[pty.master, pty.slave] {
+        fcntl::fcntl(*fd, FcntlArg::F_SETFD(FdFlag::FD_CLOEXEC))
+            .context(ErrorKind::FailedToOpenPty)?;
+    }
+    Ok(pair)
+}
+
+impl Pty {
+    /// Run `command` on a new pseudo terminal.
+    ///
+    /// The command gets a session of its own, with the slave end as its controlling terminal (so it can open
+    /// `/dev/tty`, and gets `SIGWINCH` when the master end is resized) and as its stdin, stdout and stderr.
+    pub fn spawn(mut command: Command) -> Result<Pty> {
+        let (master, slave) = openpty()?;
+        let stdio = |slave: &Slave| {
+            slave
+                .try_clone()
+                .map(|slave| Stdio::from(slave.file))
+                .context(ErrorKind::FailedToSpawn)
+        };
+        command
+            .stdin(stdio(&slave)?)
+            .stdout(stdio(&slave)?)
+            .stderr(Stdio::from(slave.file));
+        unsafe {
+            // runs in the child, after stdio has been set up, so only async-signal-safe calls
+            command.pre_exec(|| {
+                if libc::setsid() < 0 || libc::ioctl(0, libc::TIOCSCTTY, 0) < 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+
+        // the `Command` owns the parent's copies of the slave end, which are closed when it's dropped here
+        let child = command.spawn().context(ErrorKind::FailedToSpawn)?;
+        Ok(Pty { master, child })
+    }
+}
+
+impl Master {
+    /// Set the terminal's size to `cols` columns and `rows` rows, which sends `SIGWINCH` to the program running on
+    /// it.
+    ///
+    /// Fails with `Error::InvalidInput` if either doesn't fit in a `winsize`.
+    pub fn resize(&self, cols: usize, rows: usize) -> Result<()> {
+        let dimension = |n| libc::c_ushort::try_from(n).map_err(|_| Error::InvalidInput("the size doesn't fit"));
+        let size = libc::winsize {
+            ws_row: dimension(rows)?,
+            ws_col: dimension(cols)?,
+            ws_xpixel: 0,
+            ws_ypixel: 0,
+        };
+        if unsafe { libc::ioctl(self.file.as_raw_fd(), libc::TIOCSWINSZ, &size) } < 0 {
+            return Err(io::Error::last_os_error().context(ErrorKind::FailedToSetSize));
+        }
+        Ok(())
+    }
+
+    pub fn try_clone(&self) -> io::Result<Master> {
+        Ok(Master {
+            file: self.file.try_clone()?,
+        })
+    }
+}
+
+impl Slave {
+    pub fn try_clone(&self) -> io::Result<Slave> {
+        Ok(Slave {
+            file: self.file.try_clone()?,
+        })
+    }
+}
+
+impl io::Read for Master {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.file.read(buf) {
+            Err(ref e) if e.raw_os_error() == Some(libc::EIO) => Ok(0),
+            r => r,
+        }
+    }
+}
+
+impl io::Write for Master {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl io::Read for Slave {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.file.read(buf)
+    }
+}
+
+impl io::Write for Slave {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl AsRawFd for Master {
+    fn as_raw_fd(&self) -> RawFd {
+        self.file.as_raw_fd()
+    }
+}
+
+impl AsRawFd for Slave {
+    fn as_raw_fd(&self) -> RawFd {
+        self.file.as_raw_fd()
+    }
+}
+
+impl IntoRawFd for Master {
+    fn into_raw_fd(self) -> RawFd {
+        self.file.into_raw_fd()
+    }
+}
+
+impl IntoRawFd for Slave {
+    fn into_raw_fd(self) -> RawFd {
+        self.file.into_raw_fd()
+    }
+}
@@ -0,0 +1,1411 @@
+//! Asking the terminal about itself.
+//!
+//! Queries are escape sequences the terminal answers by writing a reply into the input stream, mixed in with
+//! whatever the user happens to be typing. `Term` sends the query, reads until a matching reply shows up (or the
+//! timeout runs out), and pushes every other byte back so it can still be read as keys.
+//!
+//! Not every terminal gets its replies right. Each query's outcome is a `QueryResult`, which tells an answer apart
+//! from a terminal saying it doesn't know the query, a reply that's garbled or cut short, and no reply at all.
+// the matchers are only used by `Term`, which isn't built off Unix
+#![cfg_attr(not(unix), allow(dead_code))]
+use ansi::{self, Parser, Perform, Rgb};
+use errors::*;
+use std::time::Duration;
+use xterm::NotificationStyle;
+
+/// The state of a DEC private mode, as reported by DECRPM.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum ModeState {
+    /// The terminal doesn't know about this mode.
+    NotRecognized,
+    Set,
+    Reset,
+    /// The mode is always on, and can't be changed.
+    PermanentlySet,
+    /// The mode is always off, and can't be changed.
+    PermanentlyReset,
+}
+
+impl ModeState {
+    /// Map a DECRPM status code to a `ModeState`.
+    pub fn from_code(code: i64) -> Option<ModeState> {
+        match code {
+            0 => Some(ModeState::NotRecognized),
+            1 => Some(ModeState::Set),
+            2 => Some(ModeState::Reset),
+            3 => Some(ModeState::PermanentlySet),
+            4 => Some(ModeState::PermanentlyReset),
+            _ => None,
+        }
+    }
+
+    /// True if the mode is currently on.
+    pub fn is_set(self) -> bool {
+        self == ModeState::Set || self == ModeState::PermanentlySet
+    }
+
+    /// True if the terminal recognizes the mode, and it's possible for it to be turned on.
+    pub fn is_supported(self) -> bool {
+        self == ModeState::Set || self == ModeState::Reset || self == ModeState::PermanentlySet
+    }
+}
+
+/// The kinds of escape sequence `Term::probe_sequence_safety` tries, to find out whether the terminal prints them
+/// rather than reading them.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum SequenceFamily {
+    /// Operating system commands, like the `OSC 8` of a hyperlink.
+    Osc,
+    /// Setting DEC private modes, like synchronized output's `CSI ? 2026 h`.
+    PrivateMode,
+    /// Application program commands, like the kitty graphics protocol's.
+    Apc,
+}
+
+impl SequenceFamily {
+    /// Every family, in the order the probe tries them.
+    pub const ALL: [SequenceFamily; 3] = [
+        SequenceFamily::Osc,
+        SequenceFamily::PrivateMode,
+        SequenceFamily::Apc,
+    ];
+
+    /// The sequence the probe sends, which does nothing on a terminal that reads it: an empty hyperlink, turning off
+    /// synchronized output, and an empty APC.
+    pub(crate) fn probe(self) -> &'static [u8] {
+        match self {
+            SequenceFamily::Osc => b"\x1b]8;;\x1b\\",
+            SequenceFamily::PrivateMode => b"\x1b[?2026l",
+            SequenceFamily::Apc => b"\x1b_\x1b\\",
+        }
+    }
+}
+
+/// What a terminal did with a `SequenceFamily`'s sequence.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Handling {
+    /// It read the sequence, the cursor didn't move.
+    Consumed,
+    /// It printed some of the sequence, the cursor moved.
+    Echoed,
+}
+
+/// What `Term::probe_sequence_safety` found out about each `SequenceFamily`.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub struct SequenceSafety {
+    osc: Option<Handling>,
+    private_mode: Option<Handling>,
+    apc: Option<Handling>,
+}
+
+impl SequenceSafety {
+    /// What the terminal did with `family`'s sequence, or `None` if it didn't answer in time to tell.
+    pub fn handling(&self, family: SequenceFamily) -> Option<Handling> {
+        match family {
+            SequenceFamily::Osc => self.osc,
+            SequenceFamily::PrivateMode => self.private_mode,
+            SequenceFamily::Apc => self.apc,
+        }
+    }
+
+    /// True unless the terminal was seen printing `family`'s sequence, one that couldn't be told is assumed safe.
+    pub fn is_safe(&self, family: SequenceFamily) -> bool {
+        self.handling(family) != Some(Handling::Echoed)
+    }
+
+    pub(crate) fn set(&mut self, family: SequenceFamily, handling: Handling) {
+        match family {
+            SequenceFamily::Osc => self.osc = Some(handling),
+            SequenceFamily::PrivateMode => self.private_mode = Some(handling),
+            SequenceFamily::Apc => self.apc = Some(handling),
+        }
+    }
+}
+
+/// A reply read from the terminal.
+#[derive(Debug)]
+pub(crate) enum Reply<'a> {
+    Csi {
+        params: &'a [i64],
+        intermediates: &'a [u8],
+        action: u8,
+    },
+    Osc {
+        params: &'a [&'a [u8]],
+    },
+    Dcs {
+        params: &'a [i64],
+        intermediates: &'a [u8],
+        action: u8,
+        data: &'a [u8],
+    },
+}
+
+struct Dcs {
+    params: Vec<i64>,
+    intermediates: Vec<u8>,
+    action: u8,
+    data: Vec<u8>,
+}
+
+struct Collector<T, F> {
+    matcher: F,
+    found: Option<T>,
+    dcs: Option<Dcs>,
+}
+
+impl<T, F> Collector<T, F>
+where
+    F: FnMut(Reply) -> Option<T>,
+{
+    fn check(&mut self, reply: Reply) {
+        if self.found.is_none() {
+            self.found = (self.matcher)(reply);
+        }
+    }
+}
+
+impl<T, F> Perform for Collector<T, F>
+where
+    F: FnMut(Reply) -> Option<T>,
+{
+    fn print(&mut self, _: char) {}
+
+    fn csi_dispatch(&mut self, params: &[i64], intermediates: &[u8], action: u8) {
+        self.check(Reply::Csi {
+            params,
+            intermediates,
+            action,
+        });
+    }
+
+    fn osc_dispatch(&mut self, params: &[&[u8]], _: bool) {
+        self.check(Reply::Osc { params });
+    }
+
+    fn hook(&mut self, params: &[i64], intermediates: &[u8], action: u8) {
+        self.dcs = Some(Dcs {
+            params: params.to_vec(),
+            intermediates: intermediates.to_vec(),
+            action,
+            data: Vec::new(),
+        });
+    }
+
+    fn put(&mut self, byte: u8) {
+        if let Some(ref mut dcs) = self.dcs {
+            dcs.data.push(byte);
+        }
+    }
+
+    fn unhook(&mut self) {
+        if let Some(dcs) = self.dcs.take() {
+            self.check(Reply::Dcs {
+                params: &dcs.params,
+                intermediates: &dcs.intermediates,
+                action: dcs.action,
+                data: &dcs.data,
+            });
+        }
+    }
+}
+
+/// Search `input` for the first complete reply accepted by `matcher`.
+///
+/// Returns the reply's byte range in `input` along with the value `matcher` produced for it.
+pub(crate) fn find_reply<T, F>(input: &[u8], matcher: F) -> Option<(usize, usize, T)>
+where
+    F: FnMut(Reply) -> Option<T>,
+{
+    let mut parser = Parser::new();
+    let mut collector = Collector {
+        matcher,
+        found: None,
+        dcs: None,
+    };
+    let mut start = 0;
+
+    for (i, &b) in input.iter().enumerate() {
+        if b == 0x1b && parser.is_ground() {
+            start = i;
+        }
+
+        parser.advance(&mut collector, b);
+
+        if let Some(v) = collector.found.take() {
+            // strings terminated by `ST` are dispatched on its `ESC`
+            let end = if b == 0x1b {
+                match input.get(i + 1) {
+                    Some(&b'\\') => i + 2,
+                    _ => return None,
+                }
+            } else {
+                i + 1
+            };
+            return Some((start, end, v));
+        }
+    }
+    None
+}
+
+/// How many bytes a query reads while it waits for its reply before it gives up, unless `Term::set_query_budget`
+/// says otherwise. A terminal that keeps sending something other than the reply can't keep it waiting.
+pub const DEFAULT_BUDGET: usize = 4096;
+
+/// How long each query waits for its reply, unless `QueryConfig::timeout` says otherwise.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// An adaptive timeout is this many round trips, see `QueryConfig::adaptive`.
+pub const ADAPTIVE_ROUND_TRIPS: u32 = 4;
+
+/// The shortest an adaptive timeout gets, however quick the terminal is, so a busy one still has time to answer.
+pub const ADAPTIVE_MIN_TIMEOUT: Duration = Duration::from_millis(20);
+
+/// How a `Term` waits for the replies to its queries, see `Term::set_query_config`.
+///
+/// A query that's given a timeout of its own waits that long; one given `None` waits `timeout`, or with `adaptive`
+/// a few round trips. Either way it's sent again up to `retries` times if no reply comes back in time.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct QueryConfig {
+    timeout: Duration,
+    retries: u32,
+    adaptive: bool,
+}
+
+impl QueryConfig {
+    /// Wait `DEFAULT_TIMEOUT` for each reply, and don't retry.
+    pub fn new() -> QueryConfig {
+        QueryConfig {
+            timeout: DEFAULT_TIMEOUT,
+            retries: 0,
+            adaptive: false,
+        }
+    }
+
+    /// Wait up to `timeout` for each reply. Over a slow link (like SSH across the world) this has to be longer than
+    /// the round trip, or nothing ever gets an answer.
+    pub fn timeout(mut self, timeout: Duration) -> QueryConfig {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Send a query again if it isn't answered in time, up to `retries` more times.
+    ///
+    /// A reply that was just slow can still turn up after the query's been sent again, and it answers the new query
+    /// just as well, but the reply to the new one is then read as input.
+    pub fn retries(mut self, retries: u32) -> QueryConfig {
+        self.retries = retries;
+        self
+    }
+
+    /// Measure how long the first answered query took, and from then on wait `ADAPTIVE_ROUND_TRIPS` times that
+    /// (but at least `ADAPTIVE_MIN_TIMEOUT`, and at most `timeout`). Queries a terminal doesn't know are never
+    /// answered, and this makes them give up sooner when the terminal is close by.
+    pub fn adaptive(mut self, adaptive: bool) -> QueryConfig {
+        self.adaptive = adaptive;
+        self
+    }
+
+    /// How many more times a query is sent if it isn't answered.
+    pub fn retry_count(&self) -> u32 {
+        self.retries
+    }
+
+    /// How long a query waits for its reply, given the measured `round_trip`.
+    pub fn window(&self, round_trip: Option<Duration>) -> Duration {
+        match round_trip {
+            Some(round_trip) if self.adaptive => (round_trip * ADAPTIVE_ROUND_TRIPS)
+                .max(ADAPTIVE_MIN_TIMEOUT)
+                .min(self.timeout),
+            _ => self.timeout,
+        }
+    }
+}
+
+impl Default for QueryConfig {
+    fn default() -> QueryConfig {
+        QueryConfig::new()
+    }
+}
+
+/// How a query turned out.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum QueryResult<T> {
+    Answered(T),
+    /// The terminal replied, but to say it doesn't know the query or the thing it asked about, like a DECRPM status
+    /// of 0 or an XTGETTCAP reply starting with 0.
+    Unsupported,
+    /// The terminal sent something that was meant to be the reply, but it's garbled or it was cut short. This is the
+    /// reply, or as much of it as came.
+    Malformed(Vec<u8>),
+    /// Nothing like a reply came before the timeout, or the query's byte budget, ran out.
+    TimedOut,
+}
+
+impl<T> QueryResult<T> {
+    /// The answer, if there was one.
+    pub fn answered(self) -> Option<T> {
+        match self {
+            QueryResult::Answered(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// The answer, or an error saying what happened instead: `Error::Unsupported` with `Capability::Query(query)`,
+    /// `ParseError::MalformedReply`, or `Error::Timeout` after `waited`.
+    pub fn into_result(self, query: &'static str, waited: Duration) -> Result<T> {
+        match self {
+            QueryResult::Answered(v) => Ok(v),
+            QueryResult::Unsupported => Err(Capability::Query(query).into()),
+            QueryResult::Malformed(reply) => Err(ParseError::MalformedReply(reply).into()),
+            QueryResult::TimedOut => Err(Error::Timeout { query, waited }),
+        }
+    }
+}
+
+/// What a matcher made of a reply to its query, which `decode` turns into a `QueryResult`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) enum Verdict<T> {
+    Answer(T),
+    Unsupported,
+    Malformed,
+}
+
+impl<T> Verdict<T> {
+    pub(crate) fn answer(self) -> Option<T> {
+        match self {
+            Verdict::Answer(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    fn map<U, F: FnOnce(T) -> U>(self, f: F) -> Verdict<U> {
+        match self {
+            Verdict::Answer(v) => Verdict::Answer(f(v)),
+            Verdict::Unsupported => Verdict::Unsupported,
+            Verdict::Malformed => Verdict::Malformed,
+        }
+    }
+}
+
+/// Work out how a query turned out from `input`, everything read while waiting for its reply. `find` looks for the
+/// reply, and `done` is true once no more input is coming, because the timeout or the byte budget ran out.
+///
+/// The reply is taken out of `input`, leaving what should be pushed back. Returns `None` if there's no reply yet, but
+/// there might still be one.
+pub(crate) fn decode<T, F>(input: &mut Vec<u8>, done: bool, mut find: F) -> Option<QueryResult<T>>
+where
+    F: FnMut(&[u8]) -> Option<(usize, usize, Verdict<T>)>,
+{
+    if let Some((start, end, verdict)) = find(input) {
+        let reply: Vec<u8> = input.drain(start..end).collect();
+        return Some(match verdict {
+            Verdict::Answer(v) => QueryResult::Answered(v),
+            Verdict::Unsupported => QueryResult::Unsupported,
+            Verdict::Malformed => QueryResult::Malformed(reply),
+        });
+    }
+    if !done {
+        return None;
+    }
+
+    // a sequence that never ended is a reply cut short, but a lone `ESC` is more likely the escape key
+    match unfinished(input) {
+        Some(start) if input.len() - start > 1 => Some(QueryResult::Malformed(input.drain(start..).collect())),
+        _ => Some(QueryResult::TimedOut),
+    }
+}
+
+struct Ignore;
+
+impl Perform for Ignore {
+    fn print(&mut self, _: char) {}
+}
+
+/// Where the escape sequence that `input` ends in the middle of starts, if it does.
+fn unfinished(input: &[u8]) -> Option<usize> {
+    let mut parser = Parser::new();
+    let mut start = 0;
+    for (i, &b) in input.iter().enumerate() {
+        if b == 0x1b && parser.is_ground() {
+            start = i;
+        }
+        parser.advance(&mut Ignore, b);
+    }
+    if parser.is_ground() {
+        None
+    } else {
+        Some(start)
+    }
+}
+
+/// The terminal emulator (or multiplexer) on the other end of the tty.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum TerminalFamily {
+    Xterm,
+    Kitty,
+    ITerm2,
+    AppleTerminal,
+    /// Terminals built on VTE, like GNOME Terminal.
+    Vte,
+    Konsole,
+    Alacritty,
+    WezTerm,
+    Foot,
+    Mintty,
+    Rxvt,
+    Tmux,
+    Screen,
+    LinuxConsole,
+    Unknown,
+}
+
+impl TerminalFamily {
+    /// Guess the family from a terminal's name, as found in `$TERM_PROGRAM` or an XTVERSION reply.
+    fn from_program(name: &str) -> TerminalFamily {
+        match name.to_lowercase().as_str() {
+            "xterm" => TerminalFamily::Xterm,
+            "kitty" => TerminalFamily::Kitty,
+            "iterm2" | "iterm.app" => TerminalFamily::ITerm2,
+            "apple_terminal" => TerminalFamily::AppleTerminal,
+            "vte" => TerminalFamily::Vte,
+            "konsole" => TerminalFamily::Konsole,
+            "alacritty" => TerminalFamily::Alacritty,
+            "wezterm" => TerminalFamily::WezTerm,
+            "foot" => TerminalFamily::Foot,
+            "mintty" => TerminalFamily::Mintty,
+            "tmux" => TerminalFamily::Tmux,
+            "screen" => TerminalFamily::Screen,
+            _ => TerminalFamily::Unknown,
+        }
+    }
+
+    /// Guess the family from `$TERM`.
+    ///
+    /// Plenty of terminals claim to be `xterm-256color`, so this is the weakest hint there is.
+    fn from_term(term: &str) -> TerminalFamily {
+        let prefixes = [
+            ("xterm-kitty", TerminalFamily::Kitty),
+            ("alacritty", TerminalFamily::Alacritty),
+            ("wezterm", TerminalFamily::WezTerm),
+            ("foot", TerminalFamily::Foot),
+            ("mintty", TerminalFamily::Mintty),
+            ("konsole", TerminalFamily::Konsole),
+            ("vte", TerminalFamily::Vte),
+            ("gnome", TerminalFamily::Vte),
+            ("tmux", TerminalFamily::Tmux),
+            ("screen", TerminalFamily::Screen),
+            ("linux", TerminalFamily::LinuxConsole),
+            ("rxvt", TerminalFamily::Rxvt),
+            ("xterm", TerminalFamily::Xterm),
+        ];
+        prefixes
+            .iter()
+            .find(|&&(prefix, _)| term.starts_with(prefix))
+            .map_or(TerminalFamily::Unknown, |&(_, family)| family)
+    }
+
+    /// Guess the family from the terminal type in a secondary device attributes reply.
+    fn from_secondary_da(da: (u32, u32, u32)) -> TerminalFamily {
+        match da {
+            (1, v, _) if v >= 4000 => TerminalFamily::Kitty,
+            (41, _, _) => TerminalFamily::Xterm,
+            (65, _, _) => TerminalFamily::Vte,
+            (77, _, _) => TerminalFamily::Mintty,
+            (83, _, _) => TerminalFamily::Screen,
+            (84, _, _) => TerminalFamily::Tmux,
+            (85, _, _) => TerminalFamily::Rxvt,
+            _ => TerminalFamily::Unknown,
+        }
+    }
+
+    /// The way this terminal shows desktop notifications.
+    ///
+    /// Terminals that aren't known to support `OSC 777` get `OSC 9`, since most terminals quietly ignore OSC
+    /// sequences they don't understand. The Linux console prints them instead, and multiplexers swallow them
+    /// before they reach the real terminal, so those get nothing at all.
+    pub fn notification_style(self) -> NotificationStyle {
+        match self {
+            TerminalFamily::Rxvt | TerminalFamily::Vte | TerminalFamily::Foot | TerminalFamily::WezTerm => {
+                NotificationStyle::Osc777
+            }
+            TerminalFamily::LinuxConsole | TerminalFamily::Tmux | TerminalFamily::Screen => {
+                NotificationStyle::Disabled
+            }
+            _ => NotificationStyle::Osc9,
+        }
+    }
+
+    /// True if the terminal is known to support the kitty graphics protocol.
+    pub fn supports_kitty_graphics(self) -> bool {
+        self == TerminalFamily::Kitty || self == TerminalFamily::WezTerm || self == TerminalFamily::Konsole
+    }
+
+    /// True for multiplexers, which sit between the application and the real terminal.
+    pub fn is_multiplexer(self) -> bool {
+        self == TerminalFamily::Tmux || self == TerminalFamily::Screen
+    }
+}
+
+/// A best-effort guess at what the terminal is, put together from its replies to the device attribute queries and
+/// the environment.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct TerminalId {
+    pub family: TerminalFamily,
+    /// The terminal's version, in whatever format it reports it.
+    pub version: Option<String>,
+    /// The attributes from the primary device attributes reply, e.g. 4 for sixel graphics or 22 for ANSI color.
+    pub capabilities_hinted: Vec<u32>,
+}
+
+impl TerminalId {
+    /// True if the terminal listed attribute `attr` in its primary device attributes.
+    pub fn hints(&self, attr: u32) -> bool {
+        self.capabilities_hinted.contains(&attr)
+    }
+
+    /// True if the terminal claims to support sixel graphics.
+    pub fn hints_sixel(&self) -> bool {
+        self.hints(4)
+    }
+}
+
+/// Replies to the queries sent by `Term::identify`.
+#[derive(Debug, Default)]
+pub(crate) struct Answers {
+    pub primary: Option<Vec<u32>>,
+    pub secondary: Option<(u32, u32, u32)>,
+    pub version: Option<String>,
+}
+
+/// One of the replies to the queries sent by `Term::identify`.
+#[derive(Debug, PartialEq)]
+pub(crate) enum Answer {
+    Primary(Vec<u32>),
+    Secondary((u32, u32, u32)),
+    Version(String),
+}
+
+pub(crate) fn match_identity(reply: Reply) -> Option<Verdict<Answer>> {
+    match_primary_da(&reply)
+        .map(|v| v.map(Answer::Primary))
+        .or_else(|| match_secondary_da(&reply).map(|v| v.map(Answer::Secondary)))
+        .or_else(|| match_xtversion(&reply).map(|v| v.map(Answer::Version)))
+}
+
+fn match_primary_da(reply: &Reply) -> Option<Verdict<Vec<u32>>> {
+    match *reply {
+        Reply::Csi {
+            params,
+            intermediates: b"?",
+            action: b'c',
+        } => Some(match params.split_first() {
+            // the first parameter is the conformance level, the rest are attributes
+            Some((_, attrs)) => {
+                Verdict::Answer(attrs.iter().filter(|&&p| p > 0).map(|&p| p as u32).collect())
+            }
+            None => Verdict::Malformed,
+        }),
+        _ => None,
+    }
+}
+
+fn match_secondary_da(reply: &Reply) -> Option<Verdict<(u32, u32, u32)>> {
+    match *reply {
+        Reply::Csi {
+            params,
+            intermediates: b">",
+            action: b'c',
+        } => Some(if params.is_empty() || params.len() > 3 {
+            Verdict::Malformed
+        } else {
+            let p = |i: usize| params.get(i).map_or(0, |&v| v as u32);
+            Verdict::Answer((p(0), p(1), p(2)))
+        }),
+        _ => None,
+    }
+}
+
+fn match_xtversion(reply: &Reply) -> Option<Verdict<String>> {
+    match *reply {
+        Reply::Dcs {
+            intermediates: b">",
+            action: b'|',
+            data,
+            ..
+        } => Some(if data.is_empty() {
+            Verdict::Malformed
+        } else {
+            Verdict::Answer(String::from_utf8_lossy(data).into_owned())
+        }),
+        _ => None,
+    }
+}
+
+/// Parse a primary device attributes reply (`CSI ? <level> ; <attr>... c`), returning the attributes.
+pub fn parse_primary_da(reply: &[u8]) -> Option<Vec<u32>> {
+    find_reply(reply, |r| match_primary_da(&r).and_then(Verdict::answer)).map(|(_, _, v)| v)
+}
+
+/// Parse a secondary device attributes reply (`CSI > <type> ; <version> ; <rom> c`).
+pub fn parse_secondary_da(reply: &[u8]) -> Option<(u32, u32, u32)> {
+    find_reply(reply, |r| match_secondary_da(&r).and_then(Verdict::answer)).map(|(_, _, v)| v)
+}
+
+/// Parse an XTVERSION reply (`DCS > | <name and version> ST`), returning the terminal's name and version.
+pub fn parse_xtversion(reply: &[u8]) -> Option<String> {
+    find_reply(reply, |r| match_xtversion(&r).and_then(Verdict::answer)).map(|(_, _, v)| v)
+}
+
+/// Split an XTVERSION string like `XTerm(354)` or `tmux 3.3a` into a name and version.
+fn split_version(s: &str) -> (&str, Option<&str>) {
+    match s.find(['(', ' ']) {
+        Some(i) => {
+            let version = s[i + 1..].trim_end_matches(')').trim();
+            (&s[..i], if version.is_empty() { None } else { Some(version) })
+        }
+        None => (s, None),
+    }
+}
+
+/// Put together a `TerminalId` from the terminal's replies, and the values of `$TERM`, `$TERM_PROGRAM`, and
+/// `$TERM_PROGRAM_VERSION`.
+pub(crate) fn identify(
+    answers: &Answers,
+    term: Option<&str>,
+    program: Option<&str>,
+    program_version: Option<&str>,
+) -> TerminalId {
+    let xtversion = answers.version.as_ref().map(|v| split_version(v));
+    let secondary = answers
+        .secondary
+        .map_or(TerminalFamily::Unknown, TerminalFamily::from_secondary_da);
+
+    // replies come from whatever is actually on the other end of the tty, while the environment might have been
+    // inherited from outside a multiplexer, so a multiplexer that identifies itself wins over `$TERM_PROGRAM`.
+    let mut candidates = vec![];
+    if let Some((name, version)) = xtversion {
+        candidates.push((TerminalFamily::from_program(name), version.map(str::to_owned)));
+    }
+    if secondary.is_multiplexer() {
+        candidates.push((secondary, None));
+    }
+    if let Some(program) = program {
+        candidates.push((
+            TerminalFamily::from_program(program),
+            program_version.map(str::to_owned),
+        ));
+    }
+    if let Some((_, version, rom)) = answers.secondary {
+        let version = match secondary {
+            TerminalFamily::Xterm => Some(version.to_string()),
+            TerminalFamily::Kitty => Some(format!("{}.{}", version - 4000, rom)),
+            TerminalFamily::Vte | TerminalFamily::Screen => Some(format!(
+                "{}.{}.{}",
+                version / 10000,
+                version / 100 % 100,
+                version % 100
+            )),
+            _ => None,
+        };
+        candidates.push((secondary, version));
+    }
+    if let Some(term) = term {
+        candidates.push((TerminalFamily::from_term(term), None));
+    }
+
+    let (family, version) = candidates
+        .into_iter()
+        .find(|&(family, _)| family != TerminalFamily::Unknown)
+        .unwrap_or((TerminalFamily::Unknown, None));
+
+    TerminalId {
+        family,
+        version,
+        capabilities_hinted: answers.primary.clone().unwrap_or_default(),
+    }
+}
+
+/// Parse the color in an `OSC 4`, `OSC 10` or `OSC 11` reply, like `rgb:ffff/8080/0000`, see `ansi::parse_x_color`.
+pub fn parse_color_spec(spec: &[u8]) -> Option<Rgb> {
+    ansi::parse_x_color(::std::str::from_utf8(spec).ok()?).ok()
+}
+
+/// Match an `OSC 4` reply (`OSC 4 ; <index> ; <color> ST`) for the palette entry `index`.
+pub(crate) fn match_palette_color(reply: Reply, index: u8) -> Option<Verdict<Rgb>> {
+    match reply {
+        Reply::Osc {
+            params: &[b"4", i, ref rest @ ..],
+        } if i == index.to_string().as_bytes() => Some(match *rest {
+            // some terminals echo the query back instead of answering it
+            [b"?"] => Verdict::Unsupported,
+            [spec] => parse_color_spec(spec).map_or(Verdict::Malformed, Verdict::Answer),
+            _ => Verdict::Malformed,
+        }),
+        _ => None,
+    }
+}
+
+/// Parse an `OSC 4` reply, returning the palette index and its color.
+pub fn parse_palette_color(reply: &[u8]) -> Option<(u8, Rgb)> {
+    find_reply(reply, |r| match r {
+        Reply::Osc {
+            params: &[b"4", i, spec],
+        } => {
+            let i = ::std::str::from_utf8(i).ok()?.parse().ok()?;
+            parse_color_spec(spec).map(|c| (i, c))
+        }
+        _ => None,
+    }).map(|(_, _, v)| v)
+}
+
+/// Match a DECRPM reply (`CSI ? <mode> ; <state> $ y`) for the private mode `mode`. A state of 0 means the terminal
+/// doesn't know the mode, which is `Verdict::Unsupported`.
+pub(crate) fn match_decrpm(reply: Reply, mode: u32) -> Option<Verdict<ModeState>> {
+    match reply {
+        Reply::Csi {
+            params,
+            intermediates: b"?$",
+            action: b'y',
+        } => match *params {
+            [m, _] if m != i64::from(mode) => None,
+            [_, 0] => Some(Verdict::Unsupported),
+            [_, state] => Some(ModeState::from_code(state).map_or(Verdict::Malformed, Verdict::Answer)),
+            _ => Some(Verdict::Malformed),
+        },
+        _ => None,
+    }
+}
+
+/// Parse a DECRPM reply for a private mode, returning the mode number and its state.
+pub fn parse_decrpm(reply: &[u8]) -> Option<(u32, ModeState)> {
+    find_reply(reply, |r| match r {
+        Reply::Csi {
+            params: &[m, state],
+            intermediates: b"?$",
+            action: b'y',
+        } => ModeState::from_code(state).map(|s| (m as u32, s)),
+        _ => None,
+    }).map(|(_, _, v)| v)
+}
+
+/// Match a cursor position report (`CSI <row> ; <column> R`), as (column, row) counted from 0.
+pub(crate) fn match_cursor_position(reply: Reply) -> Option<Verdict<(usize, usize)>> {
+    match reply {
+        Reply::Csi {
+            params,
+            intermediates: b"",
+            action: b'R',
+        } => Some(match *params {
+            [row, col] if row > 0 && col > 0 => Verdict::Answer((col as usize - 1, row as usize - 1)),
+            _ => Verdict::Malformed,
+        }),
+        _ => None,
+    }
+}
+
+/// Parse a cursor position report, returning the cursor's (column, row) counted from 0.
+pub fn parse_cursor_position(reply: &[u8]) -> Option<(usize, usize)> {
+    find_reply(reply, |r| match_cursor_position(r).and_then(Verdict::answer)).map(|(_, _, v)| v)
+}
+
+/// Match an XTGETTCAP reply (`DCS 1 + r <name> = <value> ST`, with both in hex) for the capability `name`, returning
+/// its value. Booleans have no value, and a terminal without the capability replies `DCS 0 + r <name> ST`.
+pub(crate) fn match_termcap(reply: Reply, name: &str) -> Option<Verdict<Vec<u8>>> {
+    let (params, data) = match reply {
+        Reply::Dcs {
+            params,
+            intermediates: b"+",
+            action: b'r',
+            data,
+        } => (params, data),
+        _ => return None,
+    };
+    let mut parts = data.splitn(2, |&c| c == b'=');
+    let asked = parts.next().map(from_hex).unwrap_or_default();
+    let value = parts.next();
+    if asked.as_ref().is_some_and(|n| !n.is_empty() && n != name.as_bytes()) {
+        // the reply to some other capability
+        return None;
+    }
+
+    Some(match (params, asked, value) {
+        // xterm sends the name back, but some terminals leave it out
+        (&[0], Some(_), None) => Verdict::Unsupported,
+        (&[1], Some(ref n), None) if !n.is_empty() => Verdict::Answer(Vec::new()),
+        (&[1], Some(ref n), Some(value)) if !n.is_empty() => {
+            from_hex(value).map_or(Verdict::Malformed, Verdict::Answer)
+        }
+        _ => Verdict::Malformed,
+    })
+}
+
+/// Parse an XTGETTCAP reply, returning the capability's name and value, or `None` as the value if the terminal doesn't
+/// have it.
+pub fn parse_termcap(reply: &[u8]) -> Option<(String, Option<Vec<u8>>)> {
+    find_reply(reply, |r| {
+        let name = match r {
+            Reply::Dcs { data, .. } => from_hex(data.split(|&c| c == b'=').next()?)?,
+            _ => return None,
+        };
+        let name = String::from_utf8(name).ok()?;
+        match match_termcap(r, &name)? {
+            Verdict::Answer(value) => Some((name, Some(value))),
+            Verdict::Unsupported => Some((name, None)),
+            Verdict::Malformed => None,
+        }
+    }).map(|(_, _, v)| v)
+}
+
+/// Decode a hex string, like the ones in XTGETTCAP replies.
+fn from_hex(hex: &[u8]) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.iter().all(u8::is_ascii_hexdigit) {
+        return None;
+    }
+    hex.chunks(2)
+        .map(|pair| u8::from_str_radix(::std::str::from_utf8(pair).ok()?, 16).ok())
+        .collect()
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum ReportPart {
+    Literal(u8),
+    /// The row (0) or column (1).
+    Number(usize),
+}
+
+/// How a terminal reports where its cursor is, from its `u6` capability.
+///
+/// `u6` is written like a parameterized string run backwards: `%d` is where a number goes, the row and then the
+/// column unless `%p1` and `%p2` say otherwise, and `%i` means they're counted from 1. xterm's is `\E[%i%d;%dR`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CursorReportFormat {
+    parts: Vec<ReportPart>,
+    one_based: bool,
+}
+
+impl CursorReportFormat {
+    /// Read a `u6` string, or `None` if it doesn't have both a row and a column, or uses anything but literal text,
+    /// `%%`, `%i`, `%p1`, `%p2` and `%d` (which can have a width, like `%3d`).
+    pub fn parse(u6: &[u8]) -> Option<CursorReportFormat> {
+        let mut parts = Vec::new();
+        let mut one_based = false;
+        let mut param = None;
+        let mut next = 0;
+        let mut i = 0;
+        while i < u6.len() {
+            if u6[i] != b'%' {
+                parts.push(ReportPart::Literal(u6[i]));
+                i += 1;
+                continue;
+            }
+            let spec = &u6[i + 1..];
+            let width = spec.iter().take_while(|c| c.is_ascii_digit()).count();
+            match (spec.first(), spec.get(width)) {
+                (Some(&b'%'), _) => parts.push(ReportPart::Literal(b'%')),
+                (Some(&b'i'), _) => one_based = true,
+                (Some(&b'p'), _) => match spec.get(1) {
+                    Some(&b'1') => param = Some(0),
+                    Some(&b'2') => param = Some(1),
+                    _ => return None,
+                },
+                (_, Some(&b'd')) => {
+                    let n = param.take().unwrap_or(next);
+                    if n > 1 {
+                        return None;
+                    }
+                    next = n + 1;
+                    parts.push(ReportPart::Number(n));
+                    i += width + 2;
+                    continue;
+                }
+                _ => return None,
+            }
+            i += if spec.first() == Some(&b'p') { 3 } else { 2 };
+        }
+
+        // a report has to start with something that tells it apart from typing
+        let numbers = |n| parts.iter().filter(|&&p| p == ReportPart::Number(n)).count();
+        match parts.first() {
+            Some(&ReportPart::Literal(_)) if numbers(0) == 1 && numbers(1) == 1 => (),
+            _ => return None,
+        }
+        Some(CursorReportFormat { parts, one_based })
+    }
+
+    /// Find the first report in `input`, returning where it starts and ends, and the cursor's (column, row) counted
+    /// from 0.
+    pub(crate) fn find(&self, input: &[u8]) -> Option<(usize, usize, (usize, usize))> {
+        (0..input.len()).find_map(|start| {
+            let mut numbers = [0usize; 2];
+            let mut at = start;
+            for &part in &self.parts {
+                match part {
+                    ReportPart::Literal(c) if input.get(at) == Some(&c) => at += 1,
+                    ReportPart::Literal(_) => return None,
+                    ReportPart::Number(n) => {
+                        let digits = input[at..].iter().take_while(|c| c.is_ascii_digit()).count();
+                        if digits == 0 || digits > 5 {
+                            return None;
+                        }
+                        let s = ::std::str::from_utf8(&input[at..at + digits]).ok()?;
+                        numbers[n] = s.parse().ok()?;
+                        at += digits;
+                    }
+                }
+            }
+            let [row, col] = numbers;
+            if self.one_based {
+                if row == 0 || col == 0 {
+                    return None;
+                }
+                return Some((start, at, (col - 1, row - 1)));
+            }
+            Some((start, at, (col, row)))
+        })
+    }
+
+    /// Parse a report in this format, returning the cursor's (column, row) counted from 0.
+    pub fn parse_report(&self, reply: &[u8]) -> Option<(usize, usize)> {
+        self.find(reply).map(|(_, _, v)| v)
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum AnswerPart {
+    Literal(u8),
+    /// `%d`, a number.
+    Number,
+    /// `%[...]`, a run of the characters between the brackets.
+    Set(Vec<u8>),
+}
+
+/// How a terminal answers its `u9` (the request for what it is), from its `u8` capability.
+///
+/// `u8` is written in scanf's syntax: `%d` is a number, `%[...]` is one or more of the characters between the
+/// brackets, `%%` is a `%`, and everything else is literal. xterm's is `\E[?%[;0123456789]c`, its primary device
+/// attributes.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct AnswerFormat {
+    parts: Vec<AnswerPart>,
+}
+
+impl AnswerFormat {
+    /// Read a `u8` string, or `None` if it uses any conversion but `%d`, `%[...]` and `%%`, or doesn't start with
+    /// literal text.
+    pub fn parse(u8: &[u8]) -> Option<AnswerFormat> {
+        let mut parts = Vec::new();
+        let mut i = 0;
+        while i < u8.len() {
+            if u8[i] != b'%' {
+                parts.push(AnswerPart::Literal(u8[i]));
+                i += 1;
+                continue;
+            }
+            match u8.get(i + 1) {
+                Some(&b'%') => parts.push(AnswerPart::Literal(b'%')),
+                Some(&b'd') => parts.push(AnswerPart::Number),
+                Some(&b'[') => {
+                    // like scanf, a `]` straight after the `[` is one of the characters
+                    let set = &u8[i + 2..];
+                    let len = set.iter().skip(1).position(|&c| c == b']')? + 1;
+                    parts.push(AnswerPart::Set(set[..len].to_vec()));
+                    i += len + 3;
+                    continue;
+                }
+                _ => return None,
+            }
+            i += 2;
+        }
+
+        // like a cursor report, an answer has to start with something that tells it apart from typing
+        match parts.first() {
+            Some(&AnswerPart::Literal(_)) => Some(AnswerFormat { parts }),
+            _ => None,
+        }
+    }
+
+    /// Find the first answer in `input`, returning where it starts and ends, and what each of its conversions
+    /// matched.
+    pub(crate) fn find(&self, input: &[u8]) -> Option<(usize, usize, Vec<Vec<u8>>)> {
+        (0..input.len()).find_map(|start| {
+            let mut fields = Vec::new();
+            let mut at = start;
+            for part in &self.parts {
+                let len = match *part {
+                    AnswerPart::Literal(c) if input.get(at) == Some(&c) => {
+                        at += 1;
+                        continue;
+                    }
+                    AnswerPart::Literal(_) => return None,
+                    AnswerPart::Number => input[at..].iter().take_while(|c| c.is_ascii_digit()).count(),
+                    AnswerPart::Set(ref set) => input[at..].iter().take_while(|c| set.contains(c)).count(),
+                };
+                if len == 0 {
+                    return None;
+                }
+                fields.push(input[at..at + len].to_vec());
+                at += len;
+            }
+            Some((start, at, fields))
+        })
+    }
+
+    /// Parse an answer in this format, returning what each of its conversions matched.
+    pub fn parse_answer(&self, reply: &[u8]) -> Option<Vec<Vec<u8>>> {
+        self.find(reply).map(|(_, _, v)| v)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use query::*;
+
+    #[test]
+    fn cursor_position() {
+        assert_eq!(parse_cursor_position(b"\x1b[1;1R"), Some((0, 0)));
+        assert_eq!(parse_cursor_position(b"typed\x1b[24;80R"), Some((79, 23)));
+        assert_eq!(parse_cursor_position(b"\x1b[0;1R"), None);
+        assert_eq!(parse_cursor_position(b"\x1b[5R"), None);
+        assert_eq!(parse_cursor_position(b"\x1b[?5;1R"), None);
+    }
+
+    #[test]
+    fn cursor_report_format() {
+        let xterm = CursorReportFormat::parse(b"\x1b[%i%d;%dR").unwrap();
+        assert_eq!(xterm.parse_report(b"\x1b[1;1R"), Some((0, 0)));
+        assert_eq!(xterm.parse_report(b"typed\x1b[24;80R"), Some((79, 23)));
+        assert_eq!(xterm.find(b"ab\x1b[3;4Rcd"), Some((2, 8, (3, 2))));
+        assert_eq!(xterm.parse_report(b"\x1b[0;1R"), None);
+        assert_eq!(xterm.parse_report(b"\x1b[5R"), None);
+        assert_eq!(xterm.parse_report(b"\x1b[24;8"), None);
+
+        // counted from 0, column first, with a fixed width
+        let odd = CursorReportFormat::parse(b"\x1b=%p2%3d,%p1%3d.").unwrap();
+        assert_eq!(odd.parse_report(b"\x1b=012,005."), Some((12, 5)));
+        assert_eq!(odd.parse_report(b"\x1b=0,0."), Some((0, 0)));
+        let percent = CursorReportFormat::parse(b"\x1b%%%d:%d").unwrap();
+        assert_eq!(percent.parse_report(b"\x1b%3:4"), Some((4, 3)));
+
+        // u6 strings that can't be matched
+        assert_eq!(CursorReportFormat::parse(b"\x1b[%dR"), None);
+        assert_eq!(CursorReportFormat::parse(b"%d;%d"), None);
+        assert_eq!(CursorReportFormat::parse(b"\x1b[%d;%d;%dR"), None);
+        assert_eq!(CursorReportFormat::parse(b"\x1b[%i%p1%p3%d;%dR"), None);
+        assert_eq!(CursorReportFormat::parse(b"\x1b[%s;%dR"), None);
+    }
+
+    #[test]
+    fn answer_format() {
+        let xterm = AnswerFormat::parse(b"\x1b[?%[;0123456789]c").unwrap();
+        assert_eq!(xterm.parse_answer(b"\x1b[?64;1;22c"), Some(vec![b"64;1;22".to_vec()]));
+        assert_eq!(xterm.find(b"ab\x1b[?6cd"), Some((2, 7, vec![b"6".to_vec()])));
+        assert_eq!(xterm.parse_answer(b"\x1b[?c"), None);
+        assert_eq!(xterm.parse_answer(b"\x1b[>41;354;0c"), None);
+
+        let numbers = AnswerFormat::parse(b"\x1b/%d,%d%%").unwrap();
+        assert_eq!(numbers.parse_answer(b"\x1b/12,3%"), Some(vec![b"12".to_vec(), b"3".to_vec()]));
+        let bracket = AnswerFormat::parse(b"\x1b%[]a]!").unwrap();
+        assert_eq!(bracket.parse_answer(b"\x1b]a]!"), Some(vec![b"]a]".to_vec()]));
+
+        assert_eq!(AnswerFormat::parse(b"%d"), None);
+        assert_eq!(AnswerFormat::parse(b"\x1b%s"), None);
+        assert_eq!(AnswerFormat::parse(b"\x1b%[ab"), None);
+    }
+
+    #[test]
+    fn decrpm() {
+        assert_eq!(parse_decrpm(b"\x1b[?2026;0$y"), Some((2026, ModeState::NotRecognized)));
+        assert_eq!(parse_decrpm(b"\x1b[?2026;1$y"), Some((2026, ModeState::Set)));
+        assert_eq!(parse_decrpm(b"\x1b[?2004;2$y"), Some((2004, ModeState::Reset)));
+        assert_eq!(parse_decrpm(b"\x1b[?1049;3$y"), Some((1049, ModeState::PermanentlySet)));
+        assert_eq!(parse_decrpm(b"\x1b[?7;4$y"), Some((7, ModeState::PermanentlyReset)));
+
+        assert_eq!(parse_decrpm(b"\x1b[?7;5$y"), None);
+        // an ANSI mode reply, not a private one
+        assert_eq!(parse_decrpm(b"\x1b[4;1$y"), None);
+        assert_eq!(parse_decrpm(b"\x1b[?2026;1$"), None);
+    }
+
+    #[test]
+    fn mode_state() {
+        assert!(ModeState::Set.is_set());
+        assert!(ModeState::PermanentlySet.is_set());
+        assert!(!ModeState::Reset.is_set());
+        assert!(ModeState::Reset.is_supported());
+        assert!(!ModeState::NotRecognized.is_supported());
+        assert!(!ModeState::PermanentlyReset.is_supported());
+    }
+
+    #[test]
+    fn device_attributes() {
+        assert_eq!(
+            parse_primary_da(b"\x1b[?64;1;2;6;9;15;16;17;18;21;22;28c"),
+            Some(vec![1, 2, 6, 9, 15, 16, 17, 18, 21, 22, 28])
+        );
+        assert_eq!(parse_primary_da(b"\x1b[?62;4c"), Some(vec![4]));
+        assert_eq!(parse_primary_da(b"\x1b[?62;c"), Some(vec![]));
+        assert_eq!(parse_primary_da(b"\x1b[?1;2c"), Some(vec![2]));
+        assert_eq!(parse_primary_da(b"\x1b[?6c"), Some(vec![]));
+        assert_eq!(parse_primary_da(b"\x1b[>41;354;0c"), None);
+
+        assert_eq!(parse_secondary_da(b"\x1b[>41;354;0c"), Some((41, 354, 0)));
+        assert_eq!(parse_secondary_da(b"\x1b[>0;95;0c"), Some((0, 95, 0)));
+        assert_eq!(parse_secondary_da(b"\x1b[>1;4000;31c"), Some((1, 4000, 31)));
+        assert_eq!(parse_secondary_da(b"\x1b[>84;0;0c"), Some((84, 0, 0)));
+        assert_eq!(parse_secondary_da(b"\x1b[>65;6800;1c"), Some((65, 6800, 1)));
+        assert_eq!(parse_secondary_da(b"\x1b[?6c"), None);
+
+        assert_eq!(parse_xtversion(b"\x1bP>|XTerm(354)\x1b\\"), Some("XTerm(354)".to_owned()));
+        assert_eq!(parse_xtversion(b"\x1bP>|kitty(0.31.0)\x1b\\"), Some("kitty(0.31.0)".to_owned()));
+        assert_eq!(parse_xtversion(b"\x1bP>|tmux 3.3a\x1b\\"), Some("tmux 3.3a".to_owned()));
+        assert_eq!(parse_xtversion(b"\x1bP>|iTerm2 3.4.19\x1b\\"), Some("iTerm2 3.4.19".to_owned()));
+        assert_eq!(parse_xtversion(b"\x1bP>|kitty(0.31.0)"), None);
+    }
+
+    fn answers(input: &[u8]) -> Answers {
+        let mut answers = Answers::default();
+        let mut rest = input.to_vec();
+        while let Some((start, end, answer)) = find_reply(&rest, match_identity) {
+            match answer {
+                Verdict::Answer(Answer::Primary(v)) => answers.primary = Some(v),
+                Verdict::Answer(Answer::Secondary(v)) => answers.secondary = Some(v),
+                Verdict::Answer(Answer::Version(v)) => answers.version = Some(v),
+                _ => {}
+            }
+            rest.drain(start..end);
+        }
+        answers
+    }
+
+    #[test]
+    fn identify_terminals() {
+        let xterm = answers(b"\x1bP>|XTerm(354)\x1b\\\x1b[>41;354;0c\x1b[?64;1;2;6;9;15;16;17;18;21;22;28c");
+        let id = identify(&xterm, Some("xterm-256color"), None, None);
+        assert_eq!(id.family, TerminalFamily::Xterm);
+        assert_eq!(id.version.as_deref(), Some("354"));
+        // xterm only lists sixel when it was built with it and runs as a VT340
+        assert!(!id.hints_sixel());
+        assert!(id.hints(22));
+
+        // iTerm2 doesn't answer XTVERSION unless asked to, and its DA2 says nothing useful
+        let iterm = answers(b"\x1b[>0;95;0c\x1b[?62;4c");
+        let id = identify(&iterm, Some("xterm-256color"), Some("iTerm.app"), Some("3.4.19"));
+        assert_eq!(id.family, TerminalFamily::ITerm2);
+        assert_eq!(id.version.as_deref(), Some("3.4.19"));
+        assert_eq!(id.capabilities_hinted, vec![4]);
+        assert!(id.hints_sixel());
+
+        let kitty = answers(b"\x1bP>|kitty(0.31.0)\x1b\\\x1b[>1;4000;31c\x1b[?62;c");
+        let id = identify(&kitty, Some("xterm-kitty"), None, None);
+        assert_eq!(id.family, TerminalFamily::Kitty);
+        assert_eq!(id.version.as_deref(), Some("0.31.0"));
+        let kitty = answers(b"\x1b[>1;4000;31c\x1b[?62;c");
+        let id = identify(&kitty, Some("xterm-256color"), None, None);
+        assert_eq!(id.family, TerminalFamily::Kitty);
+        assert_eq!(id.version.as_deref(), Some("0.31"));
+        assert!(!id.hints_sixel());
+
+        // tmux running inside iTerm2 still has iTerm2's `$TERM_PROGRAM`
+        let tmux = answers(b"\x1b[>84;0;0c\x1b[?1;2c");
+        let id = identify(&tmux, Some("screen-256color"), Some("iTerm.app"), Some("3.4.19"));
+        assert_eq!(id.family, TerminalFamily::Tmux);
+        assert_eq!(id.version, None);
+        let tmux = answers(b"\x1bP>|tmux 3.3a\x1b\\\x1b[>84;0;0c\x1b[?1;2c");
+        let id = identify(&tmux, Some("tmux-256color"), Some("tmux"), Some("3.3a"));
+        assert_eq!(id.family, TerminalFamily::Tmux);
+        assert_eq!(id.version.as_deref(), Some("3.3a"));
+
+        let linux = answers(b"\x1b[?6c");
+        let id = identify(&linux, Some("linux"), None, None);
+        assert_eq!(id.family, TerminalFamily::LinuxConsole);
+        assert!(id.capabilities_hinted.is_empty());
+
+        let vte = answers(b"\x1b[>65;6800;1c\x1b[?65;1;9c");
+        let id = identify(&vte, Some("xterm-256color"), None, None);
+        assert_eq!(id.family, TerminalFamily::Vte);
+        assert_eq!(id.version.as_deref(), Some("0.68.0"));
+
+        // nothing answered
+        let id = identify(&Answers::default(), Some("rxvt-unicode-256color"), None, None);
+        assert_eq!(id.family, TerminalFamily::Rxvt);
+        let id = identify(&Answers::default(), None, None, None);
+        assert_eq!(id.family, TerminalFamily::Unknown);
+    }
+
+    #[test]
+    fn palette_replies() {
+        // xterm uses 4 digits per channel, and may end the reply with BEL
+        assert_eq!(
+            parse_palette_color(b"\x1b]4;1;rgb:cdcd/0000/0000\x1b\\"),
+            Some((1, Rgb::new(0xcd, 0, 0)))
+        );
+        assert_eq!(
+            parse_palette_color(b"\x1b]4;255;rgb:eeee/eeee/eeee\x07"),
+            Some((255, Rgb::new(0xee, 0xee, 0xee)))
+        );
+        assert_eq!(
+            parse_palette_color(b"\x1b]4;12;rgb:80/ff/0\x07"),
+            Some((12, Rgb::new(0x80, 0xff, 0)))
+        );
+        assert_eq!(parse_color_spec(b"rgb:8/f/0"), Some(Rgb::new(0x88, 0xff, 0)));
+        assert_eq!(parse_color_spec(b"rgb:0808/ffff/0000"), Some(Rgb::new(8, 0xff, 0)));
+        assert_eq!(parse_color_spec(b"rgb:fff/000/800"), Some(Rgb::new(0xff, 0, 0x80)));
+
+        assert_eq!(parse_color_spec(b"rgb:ffff/0000"), None);
+        assert_eq!(parse_color_spec(b"rgb:fffff/0/0"), None);
+        assert_eq!(parse_color_spec(b"rgb:123456789/0/0"), None);
+        assert_eq!(parse_color_spec(b"#ff0000"), Some(Rgb::new(0xff, 0, 0)));
+        assert_eq!(parse_color_spec(b"ff0000"), None);
+        assert_eq!(parse_palette_color(b"\x1b]4;1;?\x1b\\"), None);
+        assert_eq!(parse_palette_color(b"\x1b]10;rgb:ffff/ffff/ffff\x1b\\"), None);
+
+        let reply = b"\x1b]4;3;rgb:0000/0000/ffff\x1b\\";
+        assert!(find_reply(reply, |r| match_palette_color(r, 2)).is_none());
+        assert!(find_reply(reply, |r| match_palette_color(r, 3)).is_some());
+    }
+
+    #[test]
+    fn find() {
+        let input = b"ab\x1b[A\x1b[?2026;2$ycd";
+        let (start, end, state) = find_reply(input, |r| match_decrpm(r, 2026)).unwrap();
+        assert_eq!(&input[start..end], b"\x1b[?2026;2$y");
+        assert_eq!(state, Verdict::Answer(ModeState::Reset));
+
+        // replies for other modes are skipped
+        assert!(find_reply(b"\x1b[?2004;2$y", |r| match_decrpm(r, 2026)).is_none());
+
+        let input = b"x\x1bP>|kitty(0.31)\x1b\\y";
+        let (start, end, data) = find_reply(input, |r| match r {
+            Reply::Dcs { data, .. } => Some(data.to_vec()),
+            _ => None,
+        }).unwrap();
+        assert_eq!((start, end), (1, input.len() - 1));
+        assert_eq!(data, b"kitty(0.31)");
+
+        // the string terminator hasn't arrived yet
+        assert!(find_reply(b"\x1b]11;rgb:0/0/0\x1b", |_| Some(())).is_none());
+    }
+
+    /// Decode `input` as if the timeout ran out once it was read, returning the outcome and what's pushed back.
+    fn decoded<T, F>(input: &[u8], mut matcher: F) -> (QueryResult<T>, Vec<u8>)
+    where
+        F: FnMut(Reply) -> Option<Verdict<T>>,
+    {
+        let mut input = input.to_vec();
+        let result = decode(&mut input, true, |i| find_reply(i, &mut matcher)).unwrap();
+        (result, input)
+    }
+
+    fn malformed<T>(reply: &[u8]) -> QueryResult<T> {
+        QueryResult::Malformed(reply.to_vec())
+    }
+
+    #[test]
+    fn decode_cursor_position() {
+        let cpr = |input| decoded(input, match_cursor_position);
+        assert_eq!(cpr(b"a\x1b[24;80Rb"), (QueryResult::Answered((79, 23)), b"ab".to_vec()));
+        assert_eq!(cpr(b"\x1b[0;1R"), (malformed(b"\x1b[0;1R"), vec![]));
+        assert_eq!(cpr(b"\x1b[5R"), (malformed(b"\x1b[5R"), vec![]));
+        assert_eq!(cpr(b"x\x1b[24;8"), (malformed(b"\x1b[24;8"), b"x".to_vec()));
+
+        // keys aren't replies, even the escape key on its own
+        assert_eq!(cpr(b"\x1b[A"), (QueryResult::TimedOut, b"\x1b[A".to_vec()));
+        assert_eq!(cpr(b"x\x1b"), (QueryResult::TimedOut, b"x\x1b".to_vec()));
+        assert_eq!(cpr(b""), (QueryResult::TimedOut, vec![]));
+
+        // the rest of a reply might still be coming
+        let mut input = b"\x1b[24;8".to_vec();
+        assert_eq!(decode(&mut input, false, |i| find_reply(i, match_cursor_position)), None);
+        assert_eq!(input, b"\x1b[24;8");
+    }
+
+    #[test]
+    fn decode_decrpm() {
+        let decrpm = |input| decoded(input, |r| match_decrpm(r, 2026)).0;
+        assert_eq!(decrpm(b"\x1b[?2026;1$y"), QueryResult::Answered(ModeState::Set));
+        assert_eq!(decrpm(b"\x1b[?2026;0$y"), QueryResult::Unsupported);
+        assert_eq!(decrpm(b"\x1b[?2026;7$y"), malformed(b"\x1b[?2026;7$y"));
+        assert_eq!(decrpm(b"\x1b[?2026$y"), malformed(b"\x1b[?2026$y"));
+        assert_eq!(decrpm(b"\x1b[?2026;1$"), malformed(b"\x1b[?2026;1$"));
+        assert_eq!(
+            decoded(b"\x1b[?2004;1$y", |r| match_decrpm(r, 2026)),
+            (QueryResult::TimedOut, b"\x1b[?2004;1$y".to_vec())
+        );
+    }
+
+    #[test]
+    fn decode_palette_color() {
+        let color = |input| decoded(input, |r| match_palette_color(r, 12)).0;
+        assert_eq!(
+            color(b"\x1b]4;12;rgb:8080/ffff/0000\x07"),
+            QueryResult::Answered(Rgb::new(0x80, 0xff, 0))
+        );
+        assert_eq!(color(b"\x1b]4;12;?\x1b\\"), QueryResult::Unsupported);
+        assert_eq!(color(b"\x1b]4;12;rgb:zz/0/0\x07"), malformed(b"\x1b]4;12;rgb:zz/0/0\x07"));
+        assert_eq!(color(b"\x1b]4;12\x1b\\"), malformed(b"\x1b]4;12\x1b\\"));
+        assert_eq!(color(b"\x1b]4;12;rgb:80/ff/00"), malformed(b"\x1b]4;12;rgb:80/ff/00"));
+        // only the `ESC` of the terminator came
+        assert_eq!(color(b"\x1b]4;12;rgb:80/ff/00\x1b"), malformed(b"\x1b]4;12;rgb:80/ff/00\x1b"));
+        assert_eq!(color(b"\x1b]4;1;rgb:80/ff/00\x07"), QueryResult::TimedOut);
+    }
+
+    #[test]
+    fn decode_termcap() {
+        let co = |input| decoded(input, |r| match_termcap(r, "Co"));
+        assert_eq!(co(b"\x1bP1+r436F=323536\x1b\\").0, QueryResult::Answered(b"256".to_vec()));
+        assert_eq!(co(b"\x1bP0+r436F\x1b\\").0, QueryResult::Unsupported);
+        assert_eq!(co(b"\x1bP0+r\x1b\\").0, QueryResult::Unsupported);
+        assert_eq!(co(b"\x1bP1+r436F=3G\x1b\\").0, malformed(b"\x1bP1+r436F=3G\x1b\\"));
+        assert_eq!(co(b"\x1bP1+r\x1b\\").0, malformed(b"\x1bP1+r\x1b\\"));
+        assert_eq!(co(b"\x1bP1+r436F=32").0, malformed(b"\x1bP1+r436F=32"));
+        assert_eq!(
+            co(b"\x1bP1+r544E=78\x1b\\"),
+            (QueryResult::TimedOut, b"\x1bP1+r544E=78\x1b\\".to_vec())
+        );
+        assert_eq!(
+            decoded(b"\x1bP1+r5463\x1b\\", |r| match_termcap(r, "Tc")).0,
+            QueryResult::Answered(vec![])
+        );
+
+        assert_eq!(
+            parse_termcap(b"\x1bP1+r544E=787465726D\x1b\\"),
+            Some(("TN".to_owned(), Some(b"xterm".to_vec())))
+        );
+        assert_eq!(parse_termcap(b"\x1bP0+r5463\x1b\\"), Some(("Tc".to_owned(), None)));
+    }
+
+    #[test]
+    fn decode_device_attributes() {
+        let da = |input| decoded(input, match_identity).0;
+        assert_eq!(da(b"\x1b[?62;4c"), QueryResult::Answered(Answer::Primary(vec![4])));
+        assert_eq!(
+            da(b"\x1b[>1;4000;31c"),
+            QueryResult::Answered(Answer::Secondary((1, 4000, 31)))
+        );
+        assert_eq!(da(b"\x1b[>1;2;3;4c"), malformed(b"\x1b[>1;2;3;4c"));
+        assert_eq!(da(b"\x1b[?62;"), malformed(b"\x1b[?62;"));
+        assert_eq!(da(b"\x1bP>|\x1b\\"), malformed(b"\x1bP>|\x1b\\"));
+        assert_eq!(da(b"\x1bP>|XTerm(3"), malformed(b"\x1bP>|XTerm(3"));
+        assert_eq!(da(b"\x1b[c"), QueryResult::TimedOut);
+    }
+    #[test]
+    fn query_config() {
+        let ms = Duration::from_millis;
+        let config = QueryConfig::new().timeout(ms(800));
+        assert_eq!(config.window(None), ms(800));
+        assert_eq!(config.window(Some(ms(5))), ms(800));
+
+        let config = config.adaptive(true);
+        assert_eq!(config.window(None), ms(800));
+        assert_eq!(config.window(Some(ms(30))), ms(120));
+        assert_eq!(config.window(Some(ms(1))), ADAPTIVE_MIN_TIMEOUT);
+        assert_eq!(config.window(Some(ms(300))), ms(800));
+
+        let err = QueryResult::TimedOut::<()>.into_result("DECRQM", ms(100)).unwrap_err();
+        match err {
+            Error::Timeout { query, waited } => assert_eq!((query, waited), ("DECRQM", ms(100))),
+            _ => panic!("{:?}", err),
+        }
+    }
+}
@@ -0,0 +1,141 @@
+//! The quickest way to use the terminal, for small tools that only need a thing or two.
+//!
+//! These work like `tput`: each one looks up the terminfo entry for `$TERM` (loading it once per process, see
+//! `terminfo::cached`) and does one thing with it. Nothing here fails, when `$TERM` isn't set, or there's no entry
+//! for it, the strings are empty (or the text is left alone) and the numbers are the usual defaults. For anything
+//! more, like reading keys or drawing a whole screen, use `Term`.
+//!
+//! ```
+//! use nixterm::quick;
+//!
+//! quick::clear();
+//! println!(
+//!     "{} is {} by {}, with {} colors",
+//!     quick::bold("this terminal"),
+//!     quick::columns(),
+//!     quick::lines(),
+//!     quick::colors()
+//! );
+//! ```
+use nix::unistd;
+use overrides::EnvOverrides;
+use std::io::{self, Write};
+use std::sync::Arc;
+use term::window_size;
+use terminfo;
+use terminfo::padding::find_delay;
+
+/// Clear the screen and move the cursor to the top left corner.
+///
+/// This does nothing when stdout isn't a tty, so the escape codes don't end up in a file or a pipe.
+pub fn clear() {
+    if !unistd::isatty(1).unwrap_or(false) {
+        return;
+    }
+    if let Some(clear) = expand(terminfo::ClearScreen, &[]) {
+        let mut stdout = io::stdout();
+        let _ = stdout.write_all(&clear).and_then(|_| stdout.flush());
+    }
+}
+
+/// The width of the terminal.
+///
+/// Like curses, this is `$COLUMNS` if it's set, then what the tty says, then the terminfo entry's `cols`, and 80 if
+/// none of them know.
+pub fn columns() -> usize {
+    let env = EnvOverrides::from_env().columns();
+    dimension(env, terminfo::Columns, |(cols, _)| cols, 80)
+}
+
+/// The height of the terminal.
+///
+/// Like curses, this is `$LINES` if it's set, then what the tty says, then the terminfo entry's `lines`, and 24 if
+/// none of them know.
+pub fn lines() -> usize {
+    let env = EnvOverrides::from_env().lines();
+    dimension(env, terminfo::Lines, |(_, rows)| rows, 24)
+}
+
+/// The number of colors the terminal can show, or 0 if it can't show any (or isn't known).
+///
+/// `$COLORTERM=truecolor`, and `Tc` or `RGB` in the terminfo entry, count as 2^24 colors, whatever `colors` says.
+pub fn colors() -> usize {
+    let max_colors = entry().and_then(|info| {
+        if info.has_truecolor() {
+            Some(1 << 24)
+        } else {
+            info.max_colors()
+        }
+    });
+    EnvOverrides::from_env()
+        .max_colors(max_colors)
+        .map_or(0, |n| n as usize)
+}
+
+/// `text` in bold, followed by turning all the attributes off again.
+///
+/// If the terminal can't do bold `text` is returned as it is.
+pub fn bold(text: &str) -> String {
+    match (
+        expand(terminfo::EnterBoldMode, &[]),
+        expand(terminfo::ExitAttributeMode, &[]),
+    ) {
+        (Some(bold), Some(reset)) => {
+            let mut s = String::from_utf8_lossy(&bold).into_owned();
+            s.push_str(text);
+            s.push_str(&String::from_utf8_lossy(&reset));
+            s
+        }
+        _ => text.to_string(),
+    }
+}
+
+/// The string that moves the cursor to `row` and `col`, both counted from 0.
+///
+/// The arguments are in the same order as `tput cup`, and the opposite order to `Term::move_to`. If the terminal
+/// can't move the cursor, this is empty.
+pub fn move_to(row: usize, col: usize) -> String {
+    expand(terminfo::CursorAddress, &[row as i64, col as i64])
+        .map(|s| String::from_utf8_lossy(&s).into_owned())
+        .unwrap_or_default()
+}
+
+fn entry() -> Option<Arc<terminfo::TermInfoBuf>> {
+    terminfo::cached_from_env().ok()
+}
+
+/// Expand the capability `field` with `args`, leaving out its padding.
+fn expand(field: terminfo::StringField, args: &[i64]) -> Option<Vec<u8>> {
+    let info = entry()?;
+    let mut exe = info.exec(field)?;
+    for &arg in args {
+        exe = exe.arg(arg);
+    }
+    let mut bytes = exe.vec().ok()?;
+    while let Some((start, end, _)) = find_delay(&bytes) {
+        bytes.drain(start..end);
+    }
+    Some(bytes)
+}
+
+fn dimension<F>(
+    from_env: Option<usize>,
+    field: terminfo::NumericField,
+    tty: F,
+    default: usize,
+) -> usize
+where
+    F: FnOnce((usize, usize)) -> usize,
+{
+    let from_tty = || window_size(1).ok().map(tty);
+    let from_terminfo = || {
+        entry()
+            .and_then(|info| info.number(field))
+            .map(|n| n as usize)
+    };
+
+    from_env
+        .or_else(|| from_tty().filter(|&n| n > 0))
+        .or_else(|| from_terminfo().filter(|&n| n > 0))
+        .unwrap_or(default)
+}
@@ -0,0 +1,569 @@
+//! Recording what's written to a terminal, see `Term::record_to`.
+//!
+//! Asciicast recordings are asciinema's v2 format: a JSON header on the first line, then one JSON array per write,
+//! `[time, "o", data]`, with the time in seconds since the recording started. They can be played back with
+//! `asciinema play`, and replayed onto a `TestScreen` with `TestScreen::replay`.
+// only `Term` records, which is only built on Unix
+#![cfg_attr(not(unix), allow(dead_code))]
+use std::fmt::Write as FmtWrite;
+use std::io;
+use std::str;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use util;
+use {ParseError, Result};
+
+/// How `Term::record_to` writes what's sent to the terminal.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum RecordFormat {
+    /// The bytes as they are, with no timing.
+    Raw,
+    /// An asciinema v2 recording, see the module documentation.
+    Asciicast,
+}
+
+/// The terminal's output stream, which copies everything written to it to a recording, once one's started.
+pub(crate) struct Tap<W> {
+    inner: W,
+    recorder: Option<Recorder>,
+}
+
+struct Recorder {
+    sink: Box<dyn io::Write + Send>,
+    format: RecordFormat,
+    start: Instant,
+    /// The start of a UTF-8 character that was cut off at the end of the last write.
+    partial: Vec<u8>,
+    /// The first error writing to `sink`, after which nothing more is recorded.
+    err: Option<io::Error>,
+}
+
+impl<W> Tap<W> {
+    pub(crate) fn new(inner: W) -> Tap<W> {
+        Tap {
+            inner,
+            recorder: None,
+        }
+    }
+
+    pub(crate) fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Start recording to `sink`, an asciicast recording starts with a header for a `size` terminal (columns, then
+    /// rows) called `term`. A recording that was already going is finished first.
+    pub(crate) fn start(
+        &mut self,
+        sink: Box<dyn io::Write + Send>,
+        format: RecordFormat,
+        size: (usize, usize),
+        term: Option<&str>,
+    ) -> io::Result<()> {
+        self.stop()?;
+        let mut recorder = Recorder {
+            sink,
+            format,
+            start: Instant::now(),
+            partial: Vec::new(),
+            err: None,
+        };
+        if format == RecordFormat::Asciicast {
+            let timestamp = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs());
+            let mut header = format!(
+                "{{\"version\": 2, \"width\": {}, \"height\": {}, \"timestamp\": {}",
+                size.0, size.1, timestamp
+            );
+            if let Some(term) = term {
+                header.push_str(", \"env\": {\"TERM\": ");
+                json_string(&mut header, term);
+                header.push('}');
+            }
+            header.push_str("}\n");
+            recorder.sink.write_all(header.as_bytes())?;
+        }
+        self.recorder = Some(recorder);
+        Ok(())
+    }
+
+    /// Finish the recording, returning the first error writing it.
+    pub(crate) fn stop(&mut self) -> io::Result<()> {
+        match self.recorder.take() {
+            Some(mut recorder) => {
+                if !recorder.partial.is_empty() {
+                    let partial = ::std::mem::take(&mut recorder.partial);
+                    recorder.event(&String::from_utf8_lossy(&partial));
+                }
+                if let Some(e) = recorder.err {
+                    return Err(e);
+                }
+                recorder.sink.flush()
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+impl Recorder {
+    fn record(&mut self, bytes: &[u8]) {
+        if self.err.is_some() || bytes.is_empty() {
+            return;
+        }
+        match self.format {
+            RecordFormat::Raw => {
+                if let Err(e) = self.sink.write_all(bytes) {
+                    self.err = Some(e);
+                }
+            }
+            RecordFormat::Asciicast => {
+                let mut bytes = bytes;
+                let joined;
+                if !self.partial.is_empty() {
+                    self.partial.extend_from_slice(bytes);
+                    joined = ::std::mem::take(&mut self.partial);
+                    bytes = &joined;
+                }
+                let text = self.decode(bytes);
+                if !text.is_empty() {
+                    self.event(&text);
+                }
+            }
+        }
+    }
+
+    /// Decode `bytes` as UTF-8, replacing what isn't and keeping a character cut off at the end for the next write.
+    fn decode(&mut self, mut bytes: &[u8]) -> String {
+        let mut text = String::new();
+        loop {
+            match str::from_utf8(bytes) {
+                Ok(s) => {
+                    text.push_str(s);
+                    return text;
+                }
+                Err(e) => {
+                    let (valid, rest) = bytes.split_at(e.valid_up_to());
+                    text.push_str(str::from_utf8(valid).unwrap());
+                    match e.error_len() {
+                        Some(len) => {
+                            text.push('\u{fffd}');
+                            bytes = &rest[len..];
+                        }
+                        None => {
+                            self.partial.extend_from_slice(rest);
+                            return text;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn event(&mut self, text: &str) {
+        let elapsed = self.start.elapsed();
+        let mut line = format!(
+            "[{}.{:06}, \"o\", ",
+            elapsed.as_secs(),
+            elapsed.subsec_micros()
+        );
+        json_string(&mut line, text);
+        line.push_str("]\n");
+        if let Err(e) = self.sink.write_all(line.as_bytes()) {
+            self.err = Some(e);
+        }
+    }
+}
+
+/// Append `s` to `out` as a JSON string.
+fn json_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+impl<W: io::Write> io::Write for Tap<W> {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        if let Some(ref mut recorder) = self.recorder {
+            recorder.record(&buf[..n]);
+        }
+        Ok(n)
+    }
+
+    #[inline]
+    fn write_vectored(&mut self, bufs: &[io::IoSlice]) -> io::Result<usize> {
+        let n = self.inner.write_vectored(bufs)?;
+        if let Some(ref mut recorder) = self.recorder {
+            for buf in util::written(bufs, n) {
+                recorder.record(buf);
+            }
+        }
+        Ok(n)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// The most columns or rows a recording's screen can have, so a corrupt (or hostile) one can't make a replay
+/// allocate a screen that doesn't fit in memory.
+pub const MAX_SIZE: usize = 1000;
+
+/// Fail with `ParseError::RecordingTooLarge` if a `cols` by `rows` screen is bigger than `MAX_SIZE` on a side.
+fn check_size(cols: usize, rows: usize) -> Result<()> {
+    if cols > MAX_SIZE || rows > MAX_SIZE {
+        return Err(ParseError::RecordingTooLarge(cols, rows).into());
+    }
+    Ok(())
+}
+
+/// The first line of an asciicast recording.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) struct Header {
+    pub(crate) width: usize,
+    pub(crate) height: usize,
+    /// `$TERM` where it was recorded.
+    pub(crate) term: Option<String>,
+}
+
+/// Something that happened in an asciicast recording.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Event {
+    /// Output from the program.
+    Output(String),
+    /// The terminal was resized, to columns by rows.
+    Resize(usize, usize),
+    /// Input, markers and the other events that don't change the screen.
+    Other,
+}
+
+/// Read an asciicast recording, into its header and its events with their times.
+pub(crate) fn read_asciicast(recording: &str) -> Result<(Header, Vec<(f64, Event)>)> {
+    let invalid = |line: &str| ParseError::InvalidRecording(line.to_string());
+    let mut lines = recording.lines().filter(|line| !line.trim().is_empty());
+    let first = lines.next().unwrap_or("");
+    let header = json::parse(first).ok_or_else(|| invalid(first))?;
+    let size = |key| match header.get(key) {
+        Some(&json::Value::Number(n)) if n >= 1.0 => Some(n as usize),
+        _ => None,
+    };
+    let header = match (header.get("version"), size("width"), size("height")) {
+        (Some(&json::Value::Number(2.0)), Some(width), Some(height)) => Header {
+            width,
+            height,
+            term: match header.get("env").and_then(|env| env.get("TERM")) {
+                Some(json::Value::String(term)) => Some(term.clone()),
+                _ => None,
+            },
+        },
+        _ => return Err(invalid(first).into()),
+    };
+    check_size(header.width, header.height)?;
+
+    let mut events = Vec::new();
+    for line in lines {
+        let event = match json::parse(line) {
+            Some(json::Value::Array(event)) => event,
+            _ => return Err(invalid(line).into()),
+        };
+        match (event.first(), event.get(1), event.get(2)) {
+            (
+                Some(&json::Value::Number(time)),
+                Some(json::Value::String(kind)),
+                Some(json::Value::String(data)),
+            ) => {
+                let event = match &kind[..] {
+                    "o" => Event::Output(data.clone()),
+                    "r" => {
+                        let mut size = data.splitn(2, 'x').map(|n| n.parse::<usize>());
+                        match (size.next(), size.next()) {
+                            (Some(Ok(cols)), Some(Ok(rows))) if cols > 0 && rows > 0 => {
+                                check_size(cols, rows)?;
+                                Event::Resize(cols, rows)
+                            }
+                            _ => return Err(invalid(line).into()),
+                        }
+                    }
+                    _ => Event::Other,
+                };
+                events.push((time, event));
+            }
+            _ => return Err(invalid(line).into()),
+        }
+    }
+    Ok((header, events))
+}
+
+/// Just enough JSON to read asciicast recordings.
+mod json {
+    use std::iter::Peekable;
+    use std::str::Chars;
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub(crate) enum Value {
+        Null,
+        Bool(bool),
+        Number(f64),
+        String(String),
+        Array(Vec<Value>),
+        Object(Vec<(String, Value)>),
+    }
+
+    impl Value {
+        pub(crate) fn get(&self, key: &str) -> Option<&Value> {
+            match *self {
+                Value::Object(ref fields) => fields.iter().find(|f| f.0 == key).map(|f| &f.1),
+                _ => None,
+            }
+        }
+    }
+
+    /// Parse `s`, which has to be exactly one value.
+    pub(crate) fn parse(s: &str) -> Option<Value> {
+        let mut chars = s.chars().peekable();
+        let v = value(&mut chars)?;
+        skip_space(&mut chars);
+        match chars.next() {
+            None => Some(v),
+            Some(_) => None,
+        }
+    }
+
+    fn skip_space(chars: &mut Peekable<Chars>) {
+        while chars.peek().is_some_and(|c| c.is_whitespace()) {
+            chars.next();
+        }
+    }
+
+    /// Take `c` if it's next.
+    fn eat(chars: &mut Peekable<Chars>, c: char) -> bool {
+        skip_space(chars);
+        if chars.peek() == Some(&c) {
+            chars.next();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn value(chars: &mut Peekable<Chars>) -> Option<Value> {
+        skip_space(chars);
+        match *chars.peek()? {
+            '"' => string(chars).map(Value::String),
+            '[' => {
+                chars.next();
+                let mut items = Vec::new();
+                if eat(chars, ']') {
+                    return Some(Value::Array(items));
+                }
+                loop {
+                    items.push(value(chars)?);
+                    if eat(chars, ']') {
+                        return Some(Value::Array(items));
+                    }
+                    if !eat(chars, ',') {
+                        return None;
+                    }
+                }
+            }
+            '{' => {
+                chars.next();
+                let mut fields = Vec::new();
+                if eat(chars, '}') {
+                    return Some(Value::Object(fields));
+                }
+                loop {
+                    skip_space(chars);
+                    let key = string(chars)?;
+                    if !eat(chars, ':') {
+                        return None;
+                    }
+                    fields.push((key, value(chars)?));
+                    if eat(chars, '}') {
+                        return Some(Value::Object(fields));
+                    }
+                    if !eat(chars, ',') {
+                        return None;
+                    }
+                }
+            }
+            _ => {
+                let mut word = String::new();
+                while chars
+                    .peek()
+                    .is_some_and(|&c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+                {
+                    word.push(chars.next().unwrap());
+                }
+                match &word[..] {
+                    "null" => Some(Value::Null),
+                    "true" => Some(Value::Bool(true)),
+                    "false" => Some(Value::Bool(false)),
+                    n => n.parse().ok().map(Value::Number),
+                }
+            }
+        }
+    }
+
+    fn string(chars: &mut Peekable<Chars>) -> Option<String> {
+        if chars.next()? != '"' {
+            return None;
+        }
+        let mut s = String::new();
+        loop {
+            match chars.next()? {
+                '"' => return Some(s),
+                '\\' => match chars.next()? {
+                    'n' => s.push('\n'),
+                    'r' => s.push('\r'),
+                    't' => s.push('\t'),
+                    'b' => s.push('\u{8}'),
+                    'f' => s.push('\u{c}'),
+                    'u' => {
+                        let hex: String = chars.by_ref().take(4).collect();
+                        let c = u32::from_str_radix(&hex, 16).ok()?;
+                        // a surrogate pair is two escapes, which only ever come from text outside the BMP
+                        let c = if (0xd800..0xdc00).contains(&c) {
+                            if chars.next()? != '\\' || chars.next()? != 'u' {
+                                return None;
+                            }
+                            let low: String = chars.by_ref().take(4).collect();
+                            let low = u32::from_str_radix(&low, 16).ok()?;
+                            0x10000 + ((c - 0xd800) << 10) + (low.checked_sub(0xdc00)?)
+                        } else {
+                            c
+                        };
+                        s.push(::std::char::from_u32(c).unwrap_or('\u{fffd}'));
+                    }
+                    c => s.push(c),
+                },
+                c => s.push(c),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use record::*;
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+
+    /// A sink the test can still read once the `Tap` has it, which `Term`'s tests record to as well.
+    #[derive(Clone)]
+    pub(crate) struct Shared(pub(crate) Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn recording(format: RecordFormat, writes: &[&[u8]]) -> (Vec<u8>, Vec<u8>) {
+        let sink = Shared(Arc::new(Mutex::new(Vec::new())));
+        let mut tap = Tap::new(Vec::new());
+        tap.write_all(b"before").unwrap();
+        tap.start(Box::new(sink.clone()), format, (80, 24), Some("xterm"))
+            .unwrap();
+        for w in writes {
+            tap.write_all(w).unwrap();
+        }
+        tap.stop().unwrap();
+        tap.write_all(b"after").unwrap();
+        let recorded = sink.0.lock().unwrap().clone();
+        (tap.inner, recorded)
+    }
+
+    #[test]
+    fn raw() {
+        let (sent, recorded) = recording(RecordFormat::Raw, &[b"\x1b[1mhi", b"\xc3"]);
+        assert_eq!(sent, b"before\x1b[1mhi\xc3after");
+        assert_eq!(recorded, b"\x1b[1mhi\xc3");
+    }
+
+    #[test]
+    fn asciicast() {
+        // "ü" is split between writes, and 0xff is never valid
+        let writes: &[&[u8]] = &[b"\x1b[1m\"a\"\r\n", b"\xc3", b"\xbc\\", b"\xff\t"];
+        let (sent, recorded) = recording(RecordFormat::Asciicast, writes);
+        assert_eq!(sent, &b"before\x1b[1m\"a\"\r\n\xc3\xbc\\\xff\tafter"[..]);
+
+        let recorded = String::from_utf8(recorded).unwrap();
+        assert!(recorded.contains("\\u001b[1m\\\"a\\\"\\r\\n"));
+        let (header, events) = read_asciicast(&recorded).unwrap();
+        assert_eq!(
+            header,
+            Header {
+                width: 80,
+                height: 24,
+                term: Some("xterm".into()),
+            }
+        );
+        let data: Vec<Event> = events.iter().map(|e| e.1.clone()).collect();
+        let output = |s: &str| Event::Output(s.into());
+        assert_eq!(data, [output("\x1b[1m\"a\"\r\n"), output("ü\\"), output("\u{fffd}\t")]);
+        assert!(events.windows(2).all(|w| w[0].0 <= w[1].0));
+    }
+
+    #[test]
+    fn reader() {
+        let recording = "{\"version\": 2, \"width\": 10, \"height\": 2, \"title\": null, \"env\": {}}\n\
+                         [0.5, \"o\", \"\\ud83d\\ude00\"]\n\
+                         \n\
+                         [1.25, \"r\", \"20x5\"]\n\
+                         [2, \"m\", \"\"]\n";
+        let (header, events) = read_asciicast(recording).unwrap();
+        assert_eq!(
+            header,
+            Header {
+                width: 10,
+                height: 2,
+                term: None,
+            }
+        );
+        assert_eq!(
+            events,
+            [
+                (0.5, Event::Output("\u{1f600}".into())),
+                (1.25, Event::Resize(20, 5)),
+                (2.0, Event::Other),
+            ]
+        );
+
+        let bad = |recording: &str| match read_asciicast(recording) {
+            Err(::Error::Parse(ParseError::InvalidRecording(line))) => line,
+            r => panic!("{:?}", r.map(|_| ())),
+        };
+        assert_eq!(bad("{\"version\": 1, \"width\": 10, \"height\": 2}"), "{\"version\": 1, \"width\": 10, \"height\": 2}");
+        assert_eq!(bad(""), "");
+        let header = "{\"version\": 2, \"width\": 10, \"height\": 2}\n";
+        assert_eq!(bad(&format!("{}[1.0, \"r\", \"20\"]", header)), "[1.0, \"r\", \"20\"]");
+        assert_eq!(bad(&format!("{}[1.0, \"o\"", header)), "[1.0, \"o\"");
+
+        // screens too big to replay
+        let too_large = |recording: &str| match read_asciicast(recording) {
+            Err(::Error::Parse(ParseError::RecordingTooLarge(cols, rows))) => (cols, rows),
+            r => panic!("{:?}", r.map(|_| ())),
+        };
+        assert_eq!(too_large("{\"version\": 2, \"width\": 100000, \"height\": 2}"), (100_000, 2));
+        assert_eq!(too_large(&format!("{}[1.0, \"r\", \"20x1001\"]", header)), (20, 1001));
+        assert!(read_asciicast(&format!("{}[1.0, \"r\", \"1000x1000\"]", header)).is_ok());
+    }
+}
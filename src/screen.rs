@@ -0,0 +1,1009 @@
+//! Double-buffered drawing, for redrawing full screen programs without flicker.
+//!
+//! A `Screen` keeps two grids of cells: the front one is what's on the terminal, and the back one is what should be.
+//! Programs draw the whole frame into the back buffer, then `Screen::flush` compares the two and sends only the runs
+//! of cells that changed, each with a single cursor movement.
+//!
+//! With `Screen::set_typeahead`, a flush stops early when the user has typed something, like curses does, so a
+//! program that redraws after every key doesn't fall behind the keyboard. What wasn't sent is still different from
+//! the terminal, so the next flush sends it.
+//!
+//! Rectangles of cells can be filled, cleared, boxed in and copied, see `Rect`. Everything is clipped to the screen,
+//! drawing off the edge is never an error.
+use ansi;
+#[cfg(unix)]
+use errors::*;
+#[cfg(unix)]
+use std::io;
+#[cfg(unix)]
+use std::os::unix::io::AsRawFd;
+#[cfg(unix)]
+use term::{Renderer, Term};
+
+/// One column of a `Screen`.
+///
+/// The column after a wide character holds `'\0'`, with the wide character's style.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Cell {
+    pub c: char,
+    pub style: ansi::Style,
+}
+
+/// A rectangle of cells, `width` columns by `height` rows with its top left corner at column `x` and row `y`.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub struct Rect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Rect {
+    pub fn new(x: usize, y: usize, width: usize, height: usize) -> Rect {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// True if the rectangle has no cells in it.
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    /// The part of the rectangle that's on a screen `cols` wide and `rows` high, which might be empty.
+    pub fn clip(&self, cols: usize, rows: usize) -> Rect {
+        let x = self.x.min(cols);
+        let y = self.y.min(rows);
+        Rect {
+            x,
+            y,
+            width: self.width.min(cols - x),
+            height: self.height.min(rows - y),
+        }
+    }
+}
+
+/// The lines `Screen::draw_box` draws with.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum BoxStyle {
+    /// `┌─┐`
+    Single,
+    /// `╔═╗`
+    Double,
+    /// `┏━┓`
+    Heavy,
+    /// `╭─╮`
+    Rounded,
+    /// `+-+`, for terminals that can't draw lines at all.
+    Ascii,
+}
+
+impl BoxStyle {
+    /// The top left, top right, bottom left and bottom right corners, then the horizontal and vertical lines.
+    fn chars(self) -> [char; 6] {
+        match self {
+            BoxStyle::Single => ['┌', '┐', '└', '┘', '─', '│'],
+            BoxStyle::Double => ['╔', '╗', '╚', '╝', '═', '║'],
+            BoxStyle::Heavy => ['┏', '┓', '┗', '┛', '━', '┃'],
+            BoxStyle::Rounded => ['╭', '╮', '╰', '╯', '─', '│'],
+            BoxStyle::Ascii => ['+', '+', '+', '+', '-', '|'],
+        }
+    }
+}
+
+/// A front and back buffer of cells, see the module documentation.
+#[derive(Debug, Clone)]
+pub struct Screen {
+    cols: usize,
+    rows: usize,
+    /// What's on the terminal, by row then column.
+    front: Vec<Cell>,
+    /// What's been drawn since the last flush.
+    back: Vec<Cell>,
+    /// The terminal's contents are unknown, so the next flush clears it and redraws everything.
+    invalid: bool,
+    /// Stop flushing when there's input waiting, see `set_typeahead`.
+    typeahead: bool,
+}
+
+impl Default for Cell {
+    fn default() -> Cell {
+        Cell {
+            c: ' ',
+            style: ansi::Style::default(),
+        }
+    }
+}
+
+impl Screen {
+    /// Create a blank screen `cols` wide and `rows` high.
+    ///
+    /// Nothing is known about what's on the terminal, so the first flush clears it.
+    pub fn new(cols: usize, rows: usize) -> Screen {
+        Screen {
+            cols,
+            rows,
+            front: vec![Cell::default(); cols * rows],
+            back: vec![Cell::default(); cols * rows],
+            invalid: true,
+            typeahead: false,
+        }
+    }
+
+    /// Create a blank screen the size of `term`.
+    #[cfg(unix)]
+    pub fn for_term<I, O>(term: &Term<I, O>) -> Result<Screen>
+    where
+        I: io::Read + AsRawFd,
+        O: io::Write + AsRawFd,
+    {
+        let (cols, rows) = term.size()?;
+        Ok(Screen::new(cols, rows))
+    }
+
+    /// The screen's size, as (columns, rows).
+    pub fn size(&self) -> (usize, usize) {
+        (self.cols, self.rows)
+    }
+
+    /// Change the screen's size, keeping as much of the back buffer as still fits.
+    ///
+    /// The terminal rearranges its own contents when it's resized, so the next flush redraws everything.
+    pub fn resize(&mut self, cols: usize, rows: usize) {
+        if (cols, rows) == (self.cols, self.rows) {
+            return;
+        }
+
+        let mut back = vec![Cell::default(); cols * rows];
+        let keep = cols.min(self.cols);
+        for y in 0..rows.min(self.rows) {
+            back[y * cols..y * cols + keep]
+                .copy_from_slice(&self.back[y * self.cols..y * self.cols + keep]);
+            // don't keep half of a wide character
+            if keep > 0 && keep < self.cols && self.back[y * self.cols + keep].c == '\0' {
+                back[y * cols + keep - 1].c = ' ';
+            }
+        }
+
+        self.cols = cols;
+        self.rows = rows;
+        self.back = back;
+        self.front = vec![Cell::default(); cols * rows];
+        self.invalid = true;
+    }
+
+    /// Get the cell at column `x` and row `y` of the back buffer, or `None` if it's off the screen.
+    pub fn cell(&self, x: usize, y: usize) -> Option<&Cell> {
+        if x < self.cols && y < self.rows {
+            Some(&self.back[y * self.cols + x])
+        } else {
+            None
+        }
+    }
+
+    /// Put `c` at column `x` and row `y`, both counted from 0.
+    ///
+    /// Wide characters take up two columns, if there's only one left on the row a space is drawn instead. Drawing
+    /// over half of a wide character replaces the other half with a space. Zero width characters, and anything off
+    /// the screen, are ignored.
+    pub fn set_cell(&mut self, x: usize, y: usize, c: char, style: ansi::Style) {
+        if x >= self.cols || y >= self.rows {
+            return;
+        }
+
+        let (c, width) = match ansi::char_width(c) {
+            0 => return,
+            2 if x + 1 >= self.cols => (' ', 1),
+            width => (c, width),
+        };
+
+        let row = y * self.cols;
+        if self.back[row + x].c == '\0' && x > 0 {
+            self.back[row + x - 1].c = ' ';
+        }
+        let last = x + width - 1;
+        if last + 1 < self.cols && self.back[row + last + 1].c == '\0' {
+            self.back[row + last + 1].c = ' ';
+        }
+
+        self.back[row + x] = Cell { c, style };
+        if width == 2 {
+            self.back[row + x + 1] = Cell { c: '\0', style };
+        }
+    }
+
+    /// Draw `s` from column `x` of row `y`, cutting it off at the edge of the screen.
+    ///
+    /// Returns the column after the text.
+    pub fn print(&mut self, x: usize, y: usize, s: &str, style: ansi::Style) -> usize {
+        let mut x = x;
+        for c in s.chars() {
+            if x >= self.cols {
+                break;
+            }
+            self.set_cell(x, y, c, style);
+            x += ansi::char_width(c);
+        }
+        x
+    }
+
+    /// Fill `rect` with copies of `cell`.
+    pub fn fill_rect(&mut self, rect: Rect, cell: Cell) {
+        let step = ansi::char_width(cell.c);
+        if step == 0 {
+            return;
+        }
+
+        let rect = rect.clip(self.cols, self.rows);
+        let right = rect.x + rect.width;
+        for y in rect.y..rect.y + rect.height {
+            let mut col = rect.x;
+            while col + step <= right {
+                self.set_cell(col, y, cell.c, cell.style);
+                col += step;
+            }
+            // a wide character that doesn't fit leaves a space
+            if col < right {
+                self.set_cell(col, y, ' ', cell.style);
+            }
+        }
+    }
+
+    /// Blank `rect`.
+    pub fn clear_rect(&mut self, rect: Rect) {
+        self.fill_rect(rect, Cell::default());
+    }
+
+    /// Draw a box around the edge of `rect`, with `lines`. A rectangle one row high is a horizontal line, and one a
+    /// column wide is a vertical line.
+    ///
+    /// The lines are Unicode box drawing characters. When the terminal's encoding isn't UTF-8 (see
+    /// `Term::set_encoding`) and it transliterates, `flush` draws them in the terminal's alternate character set.
+    pub fn draw_box(&mut self, rect: Rect, lines: BoxStyle, style: ansi::Style) {
+        // nothing of it would be on the screen, which might have no cells at all
+        if rect.clip(self.cols, self.rows).is_empty() {
+            return;
+        }
+        let [top_left, top_right, bottom_left, bottom_right, horizontal, vertical] = lines.chars();
+        let right = rect.x.saturating_add(rect.width - 1);
+        let bottom = rect.y.saturating_add(rect.height - 1);
+        let columns = rect.x..right.min(self.cols - 1).saturating_add(1);
+        let rows = rect.y..bottom.min(self.rows - 1).saturating_add(1);
+
+        if rect.height == 1 || rect.width > 1 {
+            for x in columns {
+                self.set_cell(x, rect.y, horizontal, style);
+                self.set_cell(x, bottom, horizontal, style);
+            }
+        }
+        if rect.height > 1 {
+            for y in rows {
+                self.set_cell(rect.x, y, vertical, style);
+                self.set_cell(right, y, vertical, style);
+            }
+        }
+        if rect.width > 1 && rect.height > 1 {
+            self.set_cell(rect.x, rect.y, top_left, style);
+            self.set_cell(right, rect.y, top_right, style);
+            self.set_cell(rect.x, bottom, bottom_left, style);
+            self.set_cell(right, bottom, bottom_right, style);
+        }
+    }
+
+    /// Copy the cells in `src` so its top left corner is at `to`, as (column, row). The two can overlap, what's
+    /// copied is what was in `src` before any of it was drawn over.
+    ///
+    /// Whatever would land off the screen is left out, and a wide character cut in half at either side of the copy
+    /// becomes a space.
+    pub fn copy_rect(&mut self, src: Rect, to: (usize, usize)) {
+        let src = src.clip(self.cols, self.rows);
+        let dst = Rect::new(to.0, to.1, src.width, src.height).clip(self.cols, self.rows);
+        if dst.is_empty() {
+            return;
+        }
+
+        let mut cells = Vec::with_capacity(dst.width * dst.height);
+        for y in src.y..src.y + dst.height {
+            let row = y * self.cols + src.x;
+            cells.extend_from_slice(&self.back[row..row + dst.width]);
+        }
+        for (y, cells) in (dst.y..).zip(cells.chunks(dst.width)) {
+            let row = y * self.cols;
+            self.back[row + dst.x..row + dst.x + dst.width].copy_from_slice(cells);
+            self.split_wide(y, dst.x);
+            self.split_wide(y, dst.x + dst.width);
+        }
+    }
+
+    /// Blank what's left of a wide character that's been split at column `x` of row `y`, by replacing the cells
+    /// on one side of it but not the other.
+    fn split_wide(&mut self, y: usize, x: usize) {
+        let row = &mut self.back[y * self.cols..(y + 1) * self.cols];
+        let wide = |cell: &Cell| ansi::char_width(cell.c) == 2;
+        if x > 0 && wide(&row[x - 1]) && row.get(x).is_none_or(|cell| cell.c != '\0') {
+            row[x - 1].c = ' ';
+        }
+        if x < row.len() && row[x].c == '\0' && (x == 0 || !wide(&row[x - 1])) {
+            row[x].c = ' ';
+        }
+    }
+
+    /// Blank the whole back buffer.
+    pub fn clear(&mut self) {
+        for cell in &mut self.back {
+            *cell = Cell::default();
+        }
+    }
+
+    /// Choose whether `flush` stops early when there's input waiting to be read, by default it doesn't.
+    ///
+    /// Input is checked for before each row is drawn. Whatever a flush has started is always finished, so the
+    /// terminal is never left in the middle of a row or in the wrong style, and clearing an invalidated screen is
+    /// never skipped.
+    pub fn set_typeahead(&mut self, typeahead: bool) {
+        self.typeahead = typeahead;
+    }
+
+    /// True if the terminal shows everything that's been drawn, false if there's something left for `flush` to send
+    /// (like after a flush was cut short by typeahead).
+    pub fn is_flushed(&self) -> bool {
+        !self.invalid && self.front == self.back
+    }
+
+    /// Forget what's on the terminal, so the next flush clears it and redraws everything.
+    ///
+    /// Call this after anything else draws on the screen.
+    pub fn invalidate(&mut self) {
+        self.invalid = true;
+    }
+
+    /// Send the cells that changed since the last flush to `term`.
+    ///
+    /// Each run of changed cells is drawn with one cursor movement, and wide characters are always redrawn whole.
+    /// With typeahead on this stops early when there's input, see `set_typeahead`.
+    ///
+    /// On terminals where writing the bottom right cell scrolls the screen (see `terminfo::wrap`) that cell is never
+    /// written, it's drawn by inserting the cell before it instead. If the terminal can't insert characters either
+    /// it's left as it is, and the screen never counts as flushed.
+    #[cfg(unix)]
+    pub fn flush<I, O>(&mut self, term: &Term<I, O>) -> Result<()>
+    where
+        I: io::Read + AsRawFd,
+        O: io::Write + AsRawFd,
+    {
+        if self.invalid {
+            term.clear_screen()?;
+            for cell in &mut self.front {
+                *cell = Cell::default();
+            }
+            self.invalid = false;
+        }
+
+        let corner = term.wrap_behavior().scrolls_at_bottom_right();
+        let mut renderer = term.renderer();
+        if let Some((top, bottom, n)) = self.find_scroll() {
+            if renderer.scroll(top, bottom, n)? {
+                self.scroll_front(top, bottom, n);
+            }
+        }
+        let mut buf = [0u8; 4];
+        for y in 0..self.rows {
+            if self.typeahead && term.input_pending()? {
+                break;
+            }
+            let row = y * self.cols;
+            let front = &mut self.front[row..row + self.cols];
+            let back = &self.back[row..row + self.cols];
+
+            let mut x = 0;
+            while x < self.cols {
+                if front[x] == back[x] {
+                    x += 1;
+                    continue;
+                }
+
+                let mut start = x;
+                if start > 0 && (front[start].c == '\0' || back[start].c == '\0') {
+                    start -= 1;
+                }
+                let mut end = x + 1;
+                while end < self.cols
+                    && (front[end] != back[end] || front[end].c == '\0' || back[end].c == '\0')
+                {
+                    end += 1;
+                }
+
+                // writing the bottom right cell would scroll the screen, so it's drawn another way, along with a wide
+                // character that ends there
+                let corner = corner && y + 1 == self.rows && end == self.cols;
+                let plain = match (corner, back[end - 1].c) {
+                    (false, _) => end,
+                    (true, '\0') => end - 2,
+                    (true, _) => end - 1,
+                };
+                renderer.move_to(start, y);
+                let mut i = start;
+                while i < plain {
+                    // a run of the same cell can be drawn with `rep` or `ech`
+                    let cell = back[i];
+                    let n = back[i..plain].iter().take_while(|&&c| c == cell).count();
+                    renderer.set_style(cell.style);
+                    match (cell.c, n) {
+                        ('\0', _) => (),
+                        (c, 1) => renderer.put_str(c.encode_utf8(&mut buf))?,
+                        (c, n) => renderer.repeat(c, n)?,
+                    }
+                    i += n;
+                }
+                front[start..plain].copy_from_slice(&back[start..plain]);
+                if corner && write_corner(&mut renderer, back, y)? {
+                    front[plain..end].copy_from_slice(&back[plain..end]);
+                }
+                x = end;
+            }
+        }
+        renderer.flush()
+    }
+
+    #[cfg(unix)]
+    fn row<'a>(&self, cells: &'a [Cell], y: usize) -> &'a [Cell] {
+        &cells[y * self.cols..(y + 1) * self.cols]
+    }
+
+    /// The rows (top, bottom) that changed, if what's drawn in them is what's on the terminal moved up by some
+    /// number of rows (or down, if it's negative), as after a log view gets a new line. The smallest such move is
+    /// found, and only ones that leave some row right that wasn't before.
+    #[cfg(unix)]
+    fn find_scroll(&self) -> Option<(usize, usize, isize)> {
+        let changed = |y: &usize| self.row(&self.front, *y) != self.row(&self.back, *y);
+        let top = (0..self.rows).find(&changed)?;
+        let bottom = (0..self.rows).rev().find(&changed)?;
+        let moved = |from: usize, to: usize| {
+            self.row(&self.back, to) == self.row(&self.front, from)
+        };
+        for n in 1..bottom - top + 1 {
+            let up = top..bottom + 1 - n;
+            if up.clone().all(|y| moved(y + n, y)) && up.clone().any(|y| changed(&y)) {
+                return Some((top, bottom, n as isize));
+            }
+            let down = top + n..bottom + 1;
+            if down.clone().all(|y| moved(y - n, y)) && down.clone().any(|y| changed(&y)) {
+                return Some((top, bottom, -(n as isize)));
+            }
+        }
+        None
+    }
+
+    /// The terminal scrolled rows `top` to `bottom` by `n`, like `find_scroll` says.
+    #[cfg(unix)]
+    fn scroll_front(&mut self, top: usize, bottom: usize, n: isize) {
+        let cols = self.cols;
+        let count = n.unsigned_abs();
+        let (from, to, blank) = if n > 0 {
+            (top + count..bottom + 1, top, bottom + 1 - count..bottom + 1)
+        } else {
+            (top..bottom + 1 - count, top + count, top..top + count)
+        };
+        self.front
+            .copy_within(from.start * cols..from.end * cols, to * cols);
+        for cell in &mut self.front[blank.start * cols..blank.end * cols] {
+            *cell = Cell::default();
+        }
+    }
+}
+
+/// Draw the last character of `row`, the bottom row, without writing to its last cell: it's written where the
+/// character before it goes, then that's inserted in front of it, pushing it into place. The last character can be
+/// a wide one, taking up the last two cells.
+///
+/// Returns false if the terminal can't insert characters, then the character isn't drawn.
+#[cfg(unix)]
+fn write_corner<I, O>(renderer: &mut Renderer<I, O>, row: &[Cell], y: usize) -> Result<bool>
+where
+    I: io::Read + AsRawFd,
+    O: io::Write + AsRawFd,
+{
+    let mut last = row.len() - 1;
+    if row[last].c == '\0' {
+        last -= 1;
+    }
+    // the cell before it, or the wide character that ends there
+    let before = match last.checked_sub(1) {
+        Some(before) if row[before].c == '\0' && before > 0 => before - 1,
+        Some(before) if row[before].c != '\0' => before,
+        _ => return Ok(false),
+    };
+    if !renderer.can_insert() {
+        return Ok(false);
+    }
+
+    let mut buf = [0u8; 4];
+    renderer.move_to(before, y);
+    renderer.set_style(row[last].style);
+    renderer.put_str(row[last].c.encode_utf8(&mut buf))?;
+    renderer.move_to(before, y);
+    renderer.set_style(row[before].style);
+    renderer.insert_str(row[before].c.encode_utf8(&mut buf))?;
+    Ok(true)
+}
+
+#[cfg(all(test, unix))]
+mod test {
+    use ansi::{self, Style, TestScreen};
+    use backend::MockBackend;
+    use encoding::{Charset, Encoding, Fallback};
+    use screen::*;
+    use term::test::output;
+    use terminfo;
+
+    const XTERM_INFO: &'static [u8] = include_bytes!("../test-data/xterm");
+
+    /// Flush `screen` to an xterm, returning everything that was written.
+    fn flush(screen: &mut Screen) -> Vec<u8> {
+        flush_to(terminfo::TermInfo::parse(XTERM_INFO).unwrap().into(), screen)
+    }
+
+    /// Flush `screen` to a terminal described by `info`, returning everything that was written.
+    fn flush_to(info: terminfo::TermInfoBuf, screen: &mut Screen) -> Vec<u8> {
+        output(info, |term| {
+            term.set_color_choice(ansi::ColorChoice::Always);
+            screen.flush(term).unwrap();
+        })
+    }
+
+    fn mock_term(mock: &MockBackend) -> Term<MockBackend, MockBackend> {
+        let term = mock.term(terminfo::TermInfo::parse(XTERM_INFO).unwrap().into());
+        term.set_color_choice(ansi::ColorChoice::Always);
+        // like a full-screen program would, `TestScreen` doesn't turn newlines into `\r\n` the way a cooked tty does
+        term.update(term.settings().raw()).unwrap();
+        term
+    }
+
+    #[test]
+    fn screen() {
+        let bold = Style {
+            bold: true,
+            ..Style::default()
+        };
+        let mut screen = Screen::new(10, 3);
+        let mut term = TestScreen::new(3, 10);
+
+        screen.print(0, 0, "hello", Style::default());
+        screen.print(0, 1, "world", bold);
+        let out = flush(&mut screen);
+        assert!(out.starts_with(b"\x1b[H\x1b[2J"));
+        term.feed(&out);
+        term.assert_snapshot("hello\nworld");
+        assert!(term.cell(1, 0).attrs.bold);
+        assert!(!term.cell(0, 0).attrs.bold);
+
+        // nothing changed, nothing is sent
+        screen.print(0, 0, "hello", Style::default());
+        assert_eq!(flush(&mut screen), b"");
+
+        // adjacent cells share a move, separate runs get their own
+        screen.print(1, 0, "EL", Style::default());
+        screen.set_cell(4, 1, 'D', bold);
+        let out = flush(&mut screen);
+        assert_eq!(
+            String::from_utf8_lossy(&out),
+            "\x1b[1;2HEL\x1b[2;5H\x1b[1mD\x1b[0m"
+        );
+        term.feed(&out);
+        term.assert_snapshot("hELlo\nworlD");
+
+        let hash = Cell {
+            c: '#',
+            style: Style::default(),
+        };
+        screen.fill_rect(Rect::new(8, 0, 5, 5), hash);
+        screen.clear();
+        screen.print(0, 2, "bye", Style::default());
+        term.feed(&flush(&mut screen));
+        term.assert_snapshot("\n\nbye");
+    }
+
+    #[test]
+    fn wide() {
+        let mut screen = Screen::new(6, 2);
+        let mut term = TestScreen::new(2, 6);
+
+        assert_eq!(screen.print(0, 0, "a\u{4e2d}b", Style::default()), 4);
+        assert_eq!(screen.cell(2, 0).unwrap().c, '\0');
+        term.feed(&flush(&mut screen));
+        term.assert_snapshot("a\u{4e2d}b");
+        assert_eq!(term.cell(0, 2).c, '\0');
+
+        // drawing over half of it redraws the whole pair
+        screen.set_cell(2, 0, 'x', Style::default());
+        assert_eq!(screen.cell(1, 0).unwrap().c, ' ');
+        let out = flush(&mut screen);
+        assert_eq!(out, b"\x1b[1;2H x");
+        term.feed(&out);
+        term.assert_snapshot("a xb");
+
+        // and putting it back does too
+        screen.set_cell(1, 0, '\u{4e2d}', Style::default());
+        let out = flush(&mut screen);
+        assert_eq!(String::from_utf8_lossy(&out), "\x1b[1;2H\u{4e2d}");
+        term.feed(&out);
+        term.assert_snapshot("a\u{4e2d}b");
+
+        // there isn't room for it in the last column
+        screen.set_cell(5, 1, '\u{4e2d}', Style::default());
+        assert_eq!(screen.cell(5, 1).unwrap().c, ' ');
+        let wide = Cell {
+            c: '\u{4e2d}',
+            style: Style::default(),
+        };
+        screen.fill_rect(Rect::new(0, 1, 5, 1), wide);
+        term.feed(&flush(&mut screen));
+        term.assert_snapshot("a\u{4e2d}b\n\u{4e2d}\u{4e2d}");
+    }
+
+    #[test]
+    fn resize() {
+        let mut screen = Screen::new(6, 2);
+        screen.print(0, 0, "abcd\u{4e2d}", Style::default());
+        screen.print(0, 1, "efgh", Style::default());
+        flush(&mut screen);
+
+        screen.resize(5, 3);
+        assert_eq!(screen.size(), (5, 3));
+        assert_eq!(screen.cell(4, 0).unwrap().c, ' ');
+        assert!(screen.cell(5, 0).is_none());
+
+        let out = flush(&mut screen);
+        assert!(out.starts_with(b"\x1b[H\x1b[2J"));
+        let mut term = TestScreen::new(3, 5);
+        term.feed(&out);
+        term.assert_snapshot("abcd\nefgh");
+
+        screen.invalidate();
+        assert!(flush(&mut screen).starts_with(b"\x1b[H\x1b[2J"));
+    }
+
+    #[test]
+    fn typeahead() {
+        let mock = MockBackend::new();
+        let term = mock_term(&mock);
+        let mut screen = Screen::new(4, 3);
+        screen.set_typeahead(true);
+        screen.print(0, 0, "ab", Style::default());
+        screen.print(0, 1, "cd", Style::default());
+        assert!(!screen.is_flushed());
+
+        // clearing the screen still happens, drawing doesn't
+        mock.push_input(b"q");
+        assert!(term.input_pending().unwrap());
+        screen.flush(&term).unwrap();
+        assert_eq!(mock.take_written(), b"\x1b[H\x1b[2J");
+        assert!(!screen.is_flushed());
+
+        // once the input's been read, the next flush catches up
+        let mut buf = [0u8; 4];
+        assert_eq!(term.read(&mut buf), 1);
+        assert!(!term.input_pending().unwrap());
+        screen.flush(&term).unwrap();
+        let mut out = TestScreen::new(3, 4);
+        out.feed(b"\x1b[H\x1b[2J");
+        out.feed(&mock.take_written());
+        out.assert_snapshot("ab\ncd");
+        assert!(screen.is_flushed());
+
+        // input that's been read from the terminal but not returned yet counts too
+        screen.print(0, 2, "ef", Style::default());
+        mock.push_input(b"xy");
+        assert_eq!(term.read(&mut buf[..1]), 1);
+        screen.flush(&term).unwrap();
+        assert_eq!(mock.take_written(), b"");
+        assert_eq!(term.read(&mut buf), 1);
+
+        // without typeahead it's all drawn anyway
+        mock.push_input(b"q");
+        screen.set_typeahead(false);
+        screen.flush(&term).unwrap();
+        out.feed(&mock.take_written());
+        out.assert_snapshot("ab\ncd\nef");
+        assert!(screen.is_flushed());
+    }
+
+    #[test]
+    fn bottom_right() {
+        use terminfo::*;
+
+        // a terminal that wraps as soon as the last column is written
+        let eager = || {
+            TermInfoBuilder::new()
+                .name("eager")
+                .set_bool(AutoRightMargin, true)
+                .set_str(ClearScreen, "\x1b[H\x1b[2J")
+                .set_str(CursorAddress, "\x1b[%i%p1%d;%p2%dH")
+                .set_str(CursorLeft, "\x08")
+        };
+        let draw = |info: TermInfoBuf, deferred: bool| {
+            let mut screen = Screen::new(5, 2);
+            let mut term = TestScreen::new(2, 5);
+            term.set_deferred_wrap(deferred);
+            screen.print(0, 0, "abcde", Style::default());
+            screen.print(0, 1, "fghij", Style::default());
+            term.feed(&flush_to(info.clone(), &mut screen));
+            term.assert_snapshot("abcde\nfghij");
+
+            // just the corner
+            screen.set_cell(4, 1, 'J', Style::default());
+            term.feed(&flush_to(info.clone(), &mut screen));
+            term.assert_snapshot("abcde\nfghiJ");
+
+            // with a wide character before it
+            screen.print(2, 1, "\u{4e2d}", Style::default());
+            screen.set_cell(4, 1, 'k', Style::default());
+            term.feed(&flush_to(info.clone(), &mut screen));
+            term.assert_snapshot("abcde\nfg\u{4e2d}k");
+
+            // and a wide character in the corner
+            screen.print(2, 1, "h\u{4e2d}", Style::default());
+            term.feed(&flush_to(info, &mut screen));
+            term.assert_snapshot("abcde\nfgh\u{4e2d}");
+            assert!(screen.is_flushed());
+            assert_eq!(term.scrollback(), Vec::<String>::new());
+        };
+
+        // with insert mode, `ich1`, or `ich`
+        draw(
+            eager()
+                .set_str(EnterInsertMode, "\x1b[4h")
+                .set_str(ExitInsertMode, "\x1b[4l")
+                .build()
+                .unwrap(),
+            false,
+        );
+        draw(eager().set_str(InsertCharacter, "\x1b[@").build().unwrap(), false);
+        draw(
+            eager().set_str(ParmIch, "\x1b[%p1%d@").build().unwrap(),
+            false,
+        );
+        // xterm waits to wrap, so the corner can just be written
+        draw(terminfo::TermInfo::parse(XTERM_INFO).unwrap().into(), true);
+
+        // a terminal that can't insert never has its corner drawn
+        let mut screen = Screen::new(5, 2);
+        let mut term = TestScreen::new(2, 5);
+        term.set_deferred_wrap(false);
+        screen.print(0, 1, "fghij", Style::default());
+        let out = flush_to(eager().build().unwrap(), &mut screen);
+        assert!(!out.contains(&b'j'));
+        term.feed(&out);
+        term.assert_snapshot("\nfghi");
+        assert!(!screen.is_flushed());
+        assert_eq!(term.scrollback(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn scroll() {
+        use terminfo::*;
+
+        let log = |screen: &mut Screen, first: usize, rows: usize| {
+            for y in 0..rows {
+                screen.print(0, y, &format!("line {}", first + y), Style::default());
+            }
+        };
+        let lines = |first: usize, rows: usize| {
+            (first..first + rows)
+                .map(|n| format!("line {}", n))
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+
+        // a full screen log gets a new line, which is all that's drawn
+        let mut screen = Screen::new(80, 24);
+        let mut term = TestScreen::new(24, 80);
+        log(&mut screen, 0, 24);
+        term.feed(&flush(&mut screen));
+        screen.clear();
+        log(&mut screen, 1, 24);
+        let out = flush(&mut screen);
+        assert_eq!(
+            String::from_utf8_lossy(&out),
+            "\x1b[24;1H\x1b[1S\x1b[24;1Hline\x1b[C24"
+        );
+        term.feed(&out);
+        term.assert_snapshot(&lines(1, 24));
+        assert!(screen.is_flushed());
+
+        // scrolled back, and the other way above a status line, with a scroll region
+        screen.clear();
+        log(&mut screen, 1, 23);
+        screen.print(0, 23, "status", Style::default());
+        term.feed(&flush(&mut screen));
+        screen.clear();
+        log(&mut screen, 0, 23);
+        screen.print(0, 23, "status", Style::default());
+        let out = flush(&mut screen);
+        assert!(
+            out.starts_with(b"\x1b[1;23r\x1b[H\x1b[1T\x1b[1;24r"),
+            "{:?}",
+            String::from_utf8_lossy(&out)
+        );
+        assert!(out.len() < 40);
+        term.feed(&out);
+        term.assert_snapshot(&[lines(0, 23), "status".into()].join("\n"));
+
+        screen.clear();
+        log(&mut screen, 3, 23);
+        screen.print(0, 23, "status", Style::default());
+        term.feed(&flush(&mut screen));
+        term.assert_snapshot(&[lines(3, 23), "status".into()].join("\n"));
+
+        // without `csr` lines are deleted and inserted, and without those too everything's redrawn
+        let basic = || {
+            TermInfoBuilder::new()
+                .name("basic")
+                .set_num(Lines, 24)
+                .set_num(Columns, 80)
+                .set_str(ClearScreen, "\x1b[H\x1b[2J")
+                .set_str(CursorAddress, "\x1b[%i%p1%d;%p2%dH")
+        };
+        let with_lines = basic()
+            .set_str(DeleteLine, "\x1b[M")
+            .set_str(ParmInsertLine, "\x1b[%p1%dL")
+            .build()
+            .unwrap();
+        let mut sizes = vec![];
+        for info in &[with_lines, basic().build().unwrap()] {
+            let mut screen = Screen::new(80, 24);
+            let mut term = TestScreen::new(24, 80);
+            log(&mut screen, 0, 23);
+            screen.print(0, 23, "status", Style::default());
+            term.feed(&flush_to(info.clone(), &mut screen));
+            screen.clear();
+            log(&mut screen, 2, 23);
+            screen.print(0, 23, "status", Style::default());
+            let out = flush_to(info.clone(), &mut screen);
+            sizes.push(out.len());
+            term.feed(&out);
+            term.assert_snapshot(&[lines(2, 23), "status".into()].join("\n"));
+        }
+        assert!(sizes[0] * 2 < sizes[1], "{:?}", sizes);
+    }
+
+    /// The characters in `screen`'s back buffer, a line for each row.
+    fn rows(screen: &Screen) -> String {
+        let (cols, rows) = screen.size();
+        (0..rows)
+            .map(|y| (0..cols).map(|x| screen.cell(x, y).unwrap().c).collect())
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn rect_clipping() {
+        let hash = Cell {
+            c: '#',
+            style: Style::default(),
+        };
+        let fill = |rect| {
+            let mut screen = Screen::new(4, 3);
+            screen.fill_rect(rect, hash);
+            rows(&screen)
+        };
+
+        assert_eq!(Rect::new(3, 1, 5, 5).clip(4, 3), Rect::new(3, 1, 1, 2));
+        assert!(Rect::new(9, 0, 5, 5).clip(4, 3).is_empty());
+        assert_eq!(fill(Rect::new(2, 1, 9, 1)), "    \n  ##\n    ");
+        assert_eq!(fill(Rect::new(1, 2, 2, 9)), "    \n    \n ## ");
+        assert_eq!(fill(Rect::new(0, 0, 1, 2)), "#   \n#   \n    ");
+        assert_eq!(fill(Rect::new(0, 0, usize::MAX, usize::MAX)), "####\n####\n####");
+        assert_eq!(fill(Rect::new(usize::MAX, 1, 2, 2)), "    \n    \n    ");
+        assert_eq!(fill(Rect::new(1, 1, 0, 2)), "    \n    \n    ");
+
+        let mut screen = Screen::new(4, 3);
+        screen.fill_rect(Rect::new(0, 0, 4, 3), hash);
+        screen.clear_rect(Rect::new(3, 2, 5, 5));
+        screen.clear_rect(Rect::new(0, 0, 1, 9));
+        screen.clear_rect(Rect::new(1, 0, 9, 1));
+        assert_eq!(rows(&screen), "    \n ###\n ## ");
+    }
+
+    #[test]
+    fn copy_rect() {
+        let copy = |src, to| {
+            let mut screen = Screen::new(5, 4);
+            for (y, line) in ["abcde", "fghij", "klmno", "pqrst"].iter().enumerate() {
+                screen.print(0, y, line, Style::default());
+            }
+            screen.copy_rect(src, to);
+            rows(&screen)
+        };
+
+        // overlapping in every direction
+        let square = Rect::new(1, 1, 2, 2);
+        assert_eq!(copy(square, (1, 0)), "aghde\nflmij\nklmno\npqrst");
+        assert_eq!(copy(square, (1, 2)), "abcde\nfghij\nkghno\nplmst");
+        assert_eq!(copy(square, (0, 1)), "abcde\nghhij\nlmmno\npqrst");
+        assert_eq!(copy(square, (2, 1)), "abcde\nfgghj\nkllmo\npqrst");
+        assert_eq!(copy(Rect::new(0, 0, 5, 3), (1, 1)), "abcde\nfabcd\nkfghi\npklmn");
+
+        // clipped at the source and the destination
+        assert_eq!(copy(Rect::new(3, 3, 9, 9), (0, 0)), "stcde\nfghij\nklmno\npqrst");
+        assert_eq!(copy(Rect::new(0, 0, 2, 2), (4, 3)), "abcde\nfghij\nklmno\npqrsa");
+        assert_eq!(copy(Rect::new(0, 0, 2, 2), (9, 0)), "abcde\nfghij\nklmno\npqrst");
+
+        // halves of wide characters are blanked
+        let mut screen = Screen::new(6, 2);
+        screen.print(0, 0, "a\u{4e2d}\u{4e2d}b", Style::default());
+        screen.copy_rect(Rect::new(2, 0, 3, 1), (0, 1));
+        screen.copy_rect(Rect::new(0, 0, 2, 1), (3, 0));
+        assert_eq!(rows(&screen), "a\u{4e2d}\0a b\n \u{4e2d}\0   ");
+    }
+
+    #[test]
+    fn draw_box() {
+        let draw = |rect, lines| {
+            let mut screen = Screen::new(5, 3);
+            screen.draw_box(rect, lines, Style::default());
+            rows(&screen)
+        };
+
+        assert_eq!(draw(Rect::new(0, 0, 4, 3), BoxStyle::Single), "┌──┐ \n│  │ \n└──┘ ");
+        assert_eq!(draw(Rect::new(1, 0, 2, 2), BoxStyle::Rounded), " ╭╮  \n ╰╯  \n     ");
+        assert_eq!(draw(Rect::new(2, 1, 9, 9), BoxStyle::Ascii), "     \n  +--\n  |  ");
+        assert_eq!(draw(Rect::new(0, 1, 5, 1), BoxStyle::Double), "     \n═════\n     ");
+        assert_eq!(draw(Rect::new(4, 0, 1, 3), BoxStyle::Heavy), "    ┃\n    ┃\n    ┃");
+        assert_eq!(draw(Rect::new(9, 9, 2, 2), BoxStyle::Single), "     \n     \n     ");
+
+        // a screen with no columns or no rows has nowhere to draw it
+        for &(cols, rows) in &[(0, 3), (5, 0)] {
+            let mut screen = Screen::new(cols, rows);
+            screen.draw_box(Rect::new(0, 0, 4, 3), BoxStyle::Single, Style::default());
+        }
+
+        // drawn in the alternate character set when the terminal can't take UTF-8
+        let mut screen = Screen::new(3, 2);
+        screen.draw_box(Rect::new(0, 0, 3, 2), BoxStyle::Single, Style::default());
+        let out = output(terminfo::TermInfo::parse(XTERM_INFO).unwrap().into(), |term| {
+            let latin1 = Encoding::new(Charset::Latin1, Fallback::Transliterate);
+            term.set_encoding(Some(latin1));
+            screen.flush(term).unwrap();
+        });
+        assert!(out.is_ascii());
+        let mut term = TestScreen::new(2, 3);
+        term.feed(&out);
+        term.assert_snapshot("┌─┐\n└─┘");
+    }
+
+    #[test]
+    fn repeat() {
+        let mut screen = Screen::new(40, 2);
+        let mut term = TestScreen::new(2, 40);
+        screen.print(0, 0, &"=".repeat(30), Style::default());
+        screen.print(0, 1, &"x".repeat(40), Style::default());
+        let out = flush(&mut screen);
+        assert!(out.windows(6).any(|w| w == b"=\x1b[29b"), "{:?}", String::from_utf8_lossy(&out));
+        term.feed(&out);
+
+        // a run of blanks is erased, leaving the cursor where it was
+        screen.clear_rect(Rect::new(5, 1, 30, 1));
+        let out = flush(&mut screen);
+        assert_eq!(out, b"\x1b[2;6H\x1b[30X");
+        term.feed(&out);
+        term.assert_snapshot(&format!("{}\nxxxxx{}xxxxx", "=".repeat(30), " ".repeat(30)));
+
+        // short runs are just written
+        screen.print(0, 0, "aab", Style::default());
+        let out = flush(&mut screen);
+        assert_eq!(out, b"\x1b[Haab");
+        term.feed(&out);
+
+        // and the cursor is where the erasing started, for the next move
+        screen.clear_rect(Rect::new(3, 0, 27, 1));
+        screen.set_cell(3, 1, 'Z', Style::default());
+        let out = flush(&mut screen);
+        assert_eq!(out, b"\x1b[1;4H\x1b[27X\x1b[2dZ");
+        term.feed(&out);
+        term.assert_snapshot(&format!("aab\nxxxZx{}xxxxx", " ".repeat(30)));
+    }
+}
@@ -0,0 +1,294 @@
+//! Reading events in a tokio runtime.
+//!
+//! `EventStream` waits for the terminal's input with tokio's reactor instead of blocking a thread, and decodes it
+//! with the same `events::Decoder` that `Term::read_keys` uses. Resizes are delivered as `Event::Resize`, from
+//! `SIGWINCH`. Every stream gets every `SIGWINCH`, whichever terminal it was for, so a stream only reports one when
+//! its own terminal's size has changed. A burst of them is reported as one resize, to the latest size, and with
+//! `EventStream::resize_debounce` only once the size has settled (see `events::Resizes`). It ticks like an
+//! `events::EventLoop` does with `EventStream::options`.
+use errors::*;
+use events::{Decoder, Event, EventLoopOptions, Resizes, Ticks, ESCAPE_TIMEOUT};
+use futures_core::Stream;
+use nix::fcntl::{self, FcntlArg, OFlag};
+use nix::libc;
+use std::future::Future;
+use std::io;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::pin::Pin;
+use std::task::{Context as TaskContext, Poll};
+use std::time::{Duration, Instant};
+use term::Term;
+use terminfo;
+use tokio::io::unix::AsyncFd;
+use tokio::signal::unix::{signal, Signal, SignalKind};
+use tokio::time::{self, Sleep};
+
+/// A stream of the terminal's events, see the module documentation.
+///
+/// The terminal's input is switched to non-blocking mode while the stream exists, so don't read from the `Term`
+/// until it's dropped. Everything that's been read but not decoded yet is kept in the stream rather than in the
+/// future waiting for it, so dropping an unfinished `next_event` (like when it loses a `select!`) loses nothing.
+pub struct EventStream<'a, I, O>
+where
+    I: io::Read + AsRawFd + 'a,
+    O: io::Write + AsRawFd + 'a,
+{
+    term: &'a Term<I, O>,
+    input: AsyncFd<Input>,
+    /// The input's flags from before it was made non-blocking.
+    flags: OFlag,
+    resize: Signal,
+    resizes: Resizes,
+    /// Wakes the stream up when a debounced resize is due.
+    timer: Option<Pin<Box<Sleep>>>,
+    ticks: Option<Ticks>,
+    /// Wakes the stream up when a tick is due.
+    tick_timer: Option<Pin<Box<Sleep>>>,
+    decoder: Decoder,
+    /// Gives up on the rest of a key code, when the start of one is all that's been read.
+    escape_timer: Option<Pin<Box<Sleep>>>,
+    eof: bool,
+}
+
+/// The future returned by `EventStream::next_event`.
+pub struct NextEvent<'b, 'a: 'b, I, O>
+where
+    I: io::Read + AsRawFd + 'a,
+    O: io::Write + AsRawFd + 'a,
+{
+    stream: &'b mut EventStream<'a, I, O>,
+}
+
+/// The terminal's input descriptor, which is owned by the `Term`.
+struct Input(RawFd);
+
+impl AsRawFd for Input {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0
+    }
+}
+
+impl<'a, I, O> EventStream<'a, I, O>
+where
+    I: io::Read + AsRawFd + 'a,
+    O: io::Write + AsRawFd + 'a,
+{
+    /// Start reading `term`'s events, this has to be called from inside a tokio runtime.
+    ///
+    /// Like `Term::read_keys`, this turns on the keypad's application mode (`smkx`) until the stream is dropped.
+    pub fn new(term: &'a Term<I, O>) -> Result<EventStream<'a, I, O>> {
+        let fd = term.as_raw_fd();
+        let flags = fcntl::fcntl(fd, FcntlArg::F_GETFL).context(ErrorKind::ReadFailed)?;
+        let flags = OFlag::from_bits_truncate(flags);
+        fcntl::fcntl(fd, FcntlArg::F_SETFL(flags | OFlag::O_NONBLOCK))
+            .context(ErrorKind::ReadFailed)?;
+
+        let stream = AsyncFd::new(Input(fd))
+            .and_then(|input| Ok((input, signal(SignalKind::window_change())?)));
+        let (input, resize) = match stream {
+            Ok(v) => v,
+            Err(e) => {
+                let _ = fcntl::fcntl(fd, FcntlArg::F_SETFL(flags));
+                return Err(e.context(ErrorKind::ReadFailed));
+            }
+        };
+
+        if let Some(v) = term.info.string(terminfo::KeypadXmit) {
+            term.writer().write_bytes(v.as_bytes());
+            term.flush();
+        }
+        Ok(EventStream {
+            term,
+            input,
+            flags,
+            resize,
+            resizes: Resizes::new(term.size().ok()),
+            timer: None,
+            ticks: None,
+            tick_timer: None,
+            decoder: Decoder::new(&term.info),
+            escape_timer: None,
+            eof: false,
+        })
+    }
+
+    /// Only report a resize once the terminal's size has stayed the same for `debounce`, keys are still reported as
+    /// soon as they're read. By default a resize is reported straight away.
+    pub fn resize_debounce(mut self, debounce: Duration) -> EventStream<'a, I, O> {
+        self.resizes = self.resizes.debounce(debounce);
+        self
+    }
+
+    /// Report the ticks `options` ask for, see `EventLoopOptions::tick`. The time comes from tokio, not from the
+    /// options' clock, so tokio's paused clock works with it.
+    pub fn options(mut self, options: EventLoopOptions) -> EventStream<'a, I, O> {
+        self.ticks = options.ticks(time::Instant::now().into_std());
+        self.tick_timer = None;
+        self
+    }
+
+    /// Wait for the next event, or `None` once the input is closed.
+    pub fn next_event<'b>(&'b mut self) -> NextEvent<'b, 'a, I, O> {
+        NextEvent { stream: self }
+    }
+
+    /// Poll for the next event, or `None` once the input is closed.
+    pub fn poll_next_event(&mut self, cx: &mut TaskContext) -> Poll<Option<Result<Event>>> {
+        let mut buf = [0u8; 1024];
+        loop {
+            if let Some(key) = self.decoder.next_key() {
+                self.escape_timer = None;
+                return Poll::Ready(Some(Ok(self.reported(Event::Key(key)))));
+            }
+            // however many signals are waiting, the size only has to be asked for once
+            let mut resized = false;
+            while let Poll::Ready(Some(())) = self.resize.poll_recv(cx) {
+                resized = true;
+            }
+            if resized {
+                match self.term.size() {
+                    Ok(size) => self.resizes.notify(size, Instant::now()),
+                    Err(e) => return Poll::Ready(Some(Err(e))),
+                }
+            }
+            if let Some(event) = self.resizes.poll(Instant::now()) {
+                self.timer = None;
+                return Poll::Ready(Some(Ok(self.reported(event))));
+            }
+            if let Some(deadline) = self.resizes.deadline() {
+                let deadline = time::Instant::from_std(deadline);
+                let timer = self
+                    .timer
+                    .get_or_insert_with(|| Box::pin(time::sleep_until(deadline)));
+                if timer.deadline() != deadline {
+                    timer.as_mut().reset(deadline);
+                }
+                if timer.as_mut().poll(cx).is_ready() {
+                    self.timer = None;
+                    continue;
+                }
+            }
+            if let Some(ref mut ticks) = self.ticks {
+                if let Some(tick) = ticks.poll(time::Instant::now().into_std()) {
+                    return Poll::Ready(Some(Ok(tick)));
+                }
+                let deadline = time::Instant::from_std(ticks.deadline());
+                let timer = self
+                    .tick_timer
+                    .get_or_insert_with(|| Box::pin(time::sleep_until(deadline)));
+                if timer.deadline() != deadline {
+                    timer.as_mut().reset(deadline);
+                }
+                if timer.as_mut().poll(cx).is_ready() {
+                    // it's due, even if the clock is a little behind the timer
+                    let deadline = ticks.deadline();
+                    return Poll::Ready(ticks.poll(deadline).map(Ok));
+                }
+            }
+            if self.eof {
+                // what's left of a key code that was cut off
+                return match self.decoder.flush_pending() {
+                    Some(record) => Poll::Ready(Some(Ok(self.reported(record.event)))),
+                    None => Poll::Ready(None),
+                };
+            }
+
+            let n = self.term.read_buffered(&mut buf);
+            if n > 0 {
+                self.decoder.push(&buf[..n]);
+                continue;
+            }
+
+            let mut ready = match self.input.poll_read_ready(cx) {
+                Poll::Ready(Ok(ready)) => ready,
+                Poll::Ready(Err(e)) => {
+                    return Poll::Ready(Some(Err(e.context(ErrorKind::ReadFailed))))
+                }
+                Poll::Pending if self.decoder.has_pending() => {
+                    let timer = self
+                        .escape_timer
+                        .get_or_insert_with(|| Box::pin(time::sleep(ESCAPE_TIMEOUT)));
+                    if timer.as_mut().poll(cx).is_pending() {
+                        return Poll::Pending;
+                    }
+                    // the rest of the key code didn't come in time
+                    self.escape_timer = None;
+                    match self.decoder.flush_pending() {
+                        Some(record) => return Poll::Ready(Some(Ok(self.reported(record.event)))),
+                        None => continue,
+                    }
+                }
+                Poll::Pending => return Poll::Pending,
+            };
+            match ready.try_io(|input| read(input.get_ref().0, &mut buf)) {
+                Ok(Ok(0)) => self.eof = true,
+                Ok(Ok(n)) => self.decoder.push(&buf[..n]),
+                Ok(Err(ref e)) if e.kind() == io::ErrorKind::Interrupted => (),
+                Ok(Err(e)) => return Poll::Ready(Some(Err(e.context(ErrorKind::ReadFailed)))),
+                // not actually ready, `try_io` has cleared the readiness so the next poll waits
+                Err(_) => (),
+            }
+        }
+    }
+}
+
+impl<'a, I, O> EventStream<'a, I, O>
+where
+    I: io::Read + AsRawFd + 'a,
+    O: io::Write + AsRawFd + 'a,
+{
+    /// `event` is about to be reported, which puts off a tick that only happens while idle.
+    fn reported(&mut self, event: Event) -> Event {
+        if let Some(ref mut ticks) = self.ticks {
+            ticks.activity(time::Instant::now().into_std());
+        }
+        event
+    }
+}
+
+impl<'a, I, O> Stream for EventStream<'a, I, O>
+where
+    I: io::Read + AsRawFd + 'a,
+    O: io::Write + AsRawFd + 'a,
+{
+    type Item = Result<Event>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut TaskContext) -> Poll<Option<Result<Event>>> {
+        self.get_mut().poll_next_event(cx)
+    }
+}
+
+impl<'b, 'a, I, O> Future for NextEvent<'b, 'a, I, O>
+where
+    I: io::Read + AsRawFd + 'a,
+    O: io::Write + AsRawFd + 'a,
+{
+    type Output = Option<Result<Event>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut TaskContext) -> Poll<Option<Result<Event>>> {
+        self.stream.poll_next_event(cx)
+    }
+}
+
+impl<'a, I, O> Drop for EventStream<'a, I, O>
+where
+    I: io::Read + AsRawFd + 'a,
+    O: io::Write + AsRawFd + 'a,
+{
+    fn drop(&mut self) {
+        let _ = fcntl::fcntl(self.term.as_raw_fd(), FcntlArg::F_SETFL(self.flags));
+        if let Some(v) = self.term.info.string(terminfo::KeypadLocal) {
+            self.term.writer().write_bytes(v.as_bytes());
+            self.term.flush();
+        }
+    }
+}
+
+fn read(fd: RawFd, buf: &mut [u8]) -> io::Result<usize> {
+    let n = unsafe { libc::read(fd, buf.as_mut_ptr() as *mut libc::c_void, buf.len()) };
+    if n < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(n as usize)
+    }
+}
@@ -1,17 +1,50 @@
+use acs::{Acs, AcsChar};
 use ansi;
+use ansi::kitty::{ImageWriter, Placement};
+use backend::{TermBackend, UnixBackend};
+use cursor::CursorOptimizer;
+use encoding::{AltCharset, Charset, Encoding, Fallback, OutputEncoding};
 use errors::*;
-use events::Keys;
-use failure::Fail;
-use failure::ResultExt;
+use events::{EventLoop, EventLoopOptions, Keys, ESCAPE_TIMEOUT};
+use nix;
+use nix::errno::Errno;
+use nix::libc;
 use nix::sys::termios;
-use std::cell::RefCell;
+use overrides::EnvOverrides;
+use query;
+use query::{
+    Answer, Handling, ModeState, QueryConfig, QueryResult, SequenceFamily, SequenceSafety, TerminalId, Verdict,
+};
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::env;
+use std::fmt;
 use std::io;
-use std::io::{BufRead, BufReader, Read};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::mem;
 use std::ops::DerefMut;
+use std::process;
 use std::os::unix::io::{AsRawFd, RawFd};
-use std::sync::{Mutex, MutexGuard};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+use counter::Counted;
+use gate::Gate;
+use record::{RecordFormat, Tap};
 use terminfo;
+use terminfo::lang;
+use terminfo::ExtCapability;
+use terminfo::padding::{find_delay, without_delays, Padding};
+use terminfo::sgr::SgrAttrs;
+use terminfo::validate::{Profile, Requirement, Suggestion, ValidationReport};
+use trace::Output;
 use util;
+use xterm;
+use xterm::NotificationStyle;
+
+/// The terminal's output stream, with everything that's layered on it.
+type Stdout<O> = Gate<Tap<Output<Counted<O>>>>;
 
 macro_rules! terminfo_setter {
     (@imp $name:ident($field:ident) -> $enum:ident::$flag:ident) => {
@@ -74,6 +107,28 @@ pub struct Settings {
     termios: termios::Termios,
 }
 
+/// A terminal, described by its terminfo entry, with its input and output streams.
+///
+/// # Positions
+///
+/// Every position `Term` (and `Renderer`, `Screen` and `ansi`) takes is counted from 0, so the top left corner is
+/// `(0, 0)` and the bottom right one is `(cols - 1, rows - 1)` for the `(cols, rows)` from `Term::size`. Methods
+/// that take both take the column first, like `move_to(x, y)`. Converting to what the terminal wants is done for
+/// you: terminfo strings like `cup` add the 1 themselves with `%i`, and the ANSI fallbacks add it before sending.
+///
+/// Positions past the edge of the screen aren't clamped, they're sent as they are, and terminals put the cursor on
+/// the last row or column instead. Use `Term::size` to stay inside the screen.
+///
+/// # Several terminals
+///
+/// A process can drive as many `Term`s at once as it likes, like a multiplexer does, each with its own terminfo
+/// entry and tty. Everything a `Term` changes (its tty's settings, the modes it turns on) is kept in the `Term`, its
+/// `Session`s and its `ModeGuard`s, and undoing it only touches that tty, so dropping one leaves the others alone.
+/// The only state shared between them is `terminfo::cache`, which is read-only once an entry is loaded.
+///
+/// Nothing installs a panic hook or a signal handler. Sessions are put back as a panic unwinds past them (but not
+/// with `panic = "abort"`), and every `stream::EventStream` listens for `SIGWINCH` itself, reporting a resize
+/// only when its own terminal's size has changed.
 pub struct Term<I, O>
 where
     I: io::Read + AsRawFd,
@@ -81,9 +136,42 @@ where
 {
     pub info: terminfo::TermInfoBuf,
     stdin_fd: RawFd,
+    backend: Box<dyn TermBackend + Send>,
     stdin: Mutex<BufReader<I>>,
-    stdout: Mutex<O>,
+    stdout: Mutex<Stdout<O>>,
     err: RefCell<Option<Error>>,
+    acs_enabled: Cell<bool>,
+    encoding: Cell<Option<Encoding>>,
+    /// Input that was read while waiting for a query's reply, and should be returned by the next read.
+    pushback: RefCell<VecDeque<u8>>,
+    /// How many bytes a query reads before it gives up on its reply.
+    query_budget: Cell<usize>,
+    query_config: Cell<QueryConfig>,
+    /// How long the first answered query took, for an adaptive `QueryConfig`.
+    round_trip: Cell<Option<Duration>>,
+    /// The result of the last `identify`.
+    identity: RefCell<Option<TerminalId>>,
+    /// The result of `probe_sequence_safety`.
+    sequence_safety: Cell<Option<SequenceSafety>>,
+    notification_style: Cell<Option<NotificationStyle>>,
+    /// Wrap sequences for the outer terminal in a multiplexer's passthrough, see `Term::set_passthrough`.
+    passthrough: Cell<bool>,
+    /// Don't ring the bell, see `Term::set_quiet`.
+    quiet: Cell<bool>,
+    degradation: Cell<Degradation>,
+    /// Whether the tty turns `\n` into `\r\n` on output, as of the last `update`.
+    translates_newlines: Cell<Option<bool>>,
+    /// Whether the tty handles XOFF and XON itself, as of the last `update`.
+    handles_xon_xoff: Cell<Option<bool>>,
+    /// Pause and resume output on XOFF and XON read from the input, see `Term::set_flow_control`.
+    flow_control: Cell<bool>,
+    /// The tty's output baud rate, as of the last `update`.
+    baud_rate: Cell<Option<u32>>,
+    color_choice: Cell<ansi::ColorChoice>,
+    /// What `color_choice` came out as, see `Term::color_depth`.
+    color_depth: Cell<Option<ansi::ColorDepth>>,
+    env: RefCell<EnvOverrides>,
+    exec_options: RefCell<ExecOptions>,
 }
 
 pub struct TermWriter<'a, O>
@@ -93,7 +181,12 @@ where
     info: &'a terminfo::TermInfoBuf,
     err: Option<Error>,
     written: usize,
-    stdout: MutexGuard<'a, O>,
+    stdout: MutexGuard<'a, Stdout<O>>,
+    /// The terminal's degradation policy, and whether it's dumb, see `Term::set_degradation`.
+    degradation: Degradation,
+    dumb: bool,
+    /// The terminal's encoding, see `Term::set_encoding`.
+    encoding: Option<OutputEncoding>,
 
     bold: bool,
     blink: bool,
@@ -108,6 +201,246 @@ where
     background: Option<ansi::Color>,
 }
 
+/// Switches a terminal mode back off when dropped.
+///
+/// Returned by methods like `Term::keypad_transmit`, which turn a mode on for as long as the guard is alive.
+pub struct ModeGuard<'a, I, O>
+where
+    I: io::Read + AsRawFd + 'a,
+    O: io::Write + AsRawFd + 'a,
+{
+    term: &'a Term<I, O>,
+    restore: terminfo::StringField,
+    fallback: &'static [u8],
+}
+
+/// What `Term::session` should set up.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct SessionOptions {
+    raw: bool,
+    alternate_screen: bool,
+    hide_cursor: bool,
+    keypad_transmit: bool,
+}
+
+/// How `Term` waits out the `$<ms>` delays in the capabilities it writes, and for `visual_bell`, see
+/// `Term::set_exec_options`.
+#[derive(Clone)]
+pub struct ExecOptions {
+    sleep: Arc<dyn Fn(Duration) + Send + Sync>,
+    max_sleep: Duration,
+}
+
+/// What `Term`'s methods do when the terminal can't do what they're asked, see `Term::set_degradation`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub enum Degradation {
+    /// Fail with `Error::Unsupported` when a capability is missing, rather than falling back to ANSI sequences.
+    Strict,
+    /// Fall back to ANSI sequences when a capability is missing, and on dumb terminals write nothing instead.
+    Silent,
+    /// Like `Silent`, but on dumb terminals write plain text that comes close where there is some: moving the
+    /// cursor starts a new line (and pads it with spaces), and clearing the screen scrolls it away with newlines.
+    #[default]
+    BestEffort,
+}
+
+/// What happens to a write that doesn't fit in what's held back while output is paused, see
+/// `Term::set_pause_buffer`.
+///
+/// A write is taken or refused whole, so escape sequences are never cut in two.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub enum OverflowPolicy {
+    /// Fail the write with `ErrorKind::WriteFailed`.
+    #[default]
+    Fail,
+    /// Drop the write, as if it had been sent.
+    Discard,
+    /// Send what's held back and the write, even though output is paused. Output stays paused afterwards.
+    Send,
+}
+
+/// How much has been written to the terminal since it was made, or since `Term::reset_io_stats`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub struct IoStats {
+    /// Writes made to the output stream. Each is one syscall when it's a tty, a file or a pipe, though `io::Stdout`
+    /// buffers lines itself.
+    pub writes: u64,
+    /// How many of `writes` were vectored, see `Term::write_vectored`.
+    pub vectored_writes: u64,
+    /// Bytes the output stream took, across all of `writes`.
+    pub bytes: u64,
+}
+
+/// The frame `Term::frame` is collecting, which is sent when this is dropped, even if the closure panics.
+struct FrameBatch<'a, I, O>
+where
+    I: io::Read + AsRawFd + 'a,
+    O: io::Write + AsRawFd + 'a,
+{
+    term: &'a Term<I, O>,
+    /// What's sent after the frame, or `None` for a frame inside another one. What's sent before it starts the
+    /// batch.
+    end: Option<Vec<u8>>,
+}
+
+/// A full-screen session, which puts the terminal back the way it was when dropped.
+pub struct Session<'a, I, O>
+where
+    I: io::Read + AsRawFd + 'a,
+    O: io::Write + AsRawFd + 'a,
+{
+    term: &'a Term<I, O>,
+    settings: Option<Settings>,
+    guards: Vec<ModeGuard<'a, I, O>>,
+}
+
+/// Writes text in a `Style`, returned by `Term::styled_writer`.
+///
+/// Style changes are sent with the next write, downsampled to the terminal's `color_depth`, and the style is reset
+/// when the writer is dropped. In raw mode, where the tty stops doing it, `\n` is written as `\r\n`.
+///
+/// For text that can't be trusted, like a file's contents, `set_sanitize` makes the writer replace control
+/// characters, so the terminal shows escape sequences instead of carrying them out.
+///
+/// The writer also works around the output glitches the terminal's entry has: a `~` is written as `-` on terminals
+/// with `hz`, standout is sent again after spaces with `xhp`, and with `ul` but no `smul` underlined text is
+/// overstruck with `_`. `set_quirks(false)` turns this off.
+pub struct StyledWriter<'a, I, O>
+where
+    I: io::Read + AsRawFd + 'a,
+    O: io::Write + AsRawFd + 'a,
+{
+    term: &'a Term<I, O>,
+    style: ansi::Style,
+    /// The style the terminal is actually in.
+    current: ansi::Style,
+    sanitize: Option<ansi::SanitizePolicy>,
+    encoding: Option<OutputEncoding>,
+    quirks: OutputQuirks,
+    /// True if the text is being underlined by overstriking, rather than with the terminal's underline mode.
+    overstrike: bool,
+    /// True if the last byte written was a space.
+    after_space: bool,
+}
+
+/// Writes ordinary lines of text, like log messages, to a terminal that's in raw mode, returned by
+/// `Term::cooked_writer`.
+///
+/// In raw mode the tty stops turning `\n` into `\r\n`, so lines printed while reading keys would each start where the
+/// last one ended, and stagger across the screen. This writer puts the `\r` back in, and starts writing with one too
+/// in case the cursor was left part way along a line (by a prompt, say), erasing the rest of that line. Out of raw
+/// mode it's a `StyledWriter`.
+///
+/// The text is written in the writer's own style (plain unless `set_style` says otherwise). When the writer's
+/// flushed or dropped the terminal is switched back to the active style, the one the rest of the application's
+/// output is in (see `set_active_style`), and left in it.
+pub struct CookedWriter<'a, I, O>
+where
+    I: io::Read + AsRawFd + 'a,
+    O: io::Write + AsRawFd + 'a,
+{
+    writer: StyledWriter<'a, I, O>,
+    /// The style the terminal is put back in after each write.
+    active: ansi::Style,
+    /// True if the last byte written was a `\n`, or nothing has been written yet.
+    line_start: bool,
+}
+
+/// The output glitches in a terminal's entry that `StyledWriter` works around.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+struct OutputQuirks {
+    /// `hz`, the terminal can't show `~`, so it's written as `-`.
+    tilde: bool,
+    /// `xhp`, standout can't be counted on to carry on past spaces, so it's sent again after them.
+    standout: bool,
+    /// `ul` without `smul`, the terminal underlines by overstriking, so underlined text is written as `_`, a
+    /// backspace, and the character.
+    overstrike: bool,
+}
+
+impl OutputQuirks {
+    fn new(info: &terminfo::TermInfoBuf) -> OutputQuirks {
+        OutputQuirks {
+            tilde: info.boolean(terminfo::TildeGlitch),
+            standout: info.boolean(terminfo::CeolStandoutGlitch),
+            overstrike: info.boolean(terminfo::TransparentUnderline)
+                && info.string(terminfo::EnterUnderlineMode).is_none(),
+        }
+    }
+}
+
+/// Draws text at positions on the screen, sending as little as it can, returned by `Term::renderer`.
+///
+/// The renderer remembers where it left the cursor, the style the terminal is in, and what it drew on each cell. So
+/// moving to where the cursor already is sends nothing, other moves take the shortest way there (see
+/// `cursor::CursorOptimizer`), switching to the style the terminal's already in sends nothing, and text that's already
+/// on the screen is skipped. Drawing an unchanged frame a second time sends nothing at
+/// all. Output is collected in a buffer until `Renderer::flush`, and the style is reset when the renderer is dropped.
+///
+/// Anything else that moves the cursor or draws on the screen (like clearing it) leaves the renderer out of date, call
+/// `Renderer::invalidate` afterwards.
+pub struct Renderer<'a, I, O>
+where
+    I: io::Read + AsRawFd + 'a,
+    O: io::Write + AsRawFd + 'a,
+{
+    term: &'a Term<I, O>,
+    moves: CursorOptimizer<'a>,
+    /// The screen's size, if it's known, for working out where text that reaches the margin leaves the cursor.
+    size: Option<(usize, usize)>,
+    buffer: Vec<u8>,
+    /// Where the next text is drawn, as (column, row).
+    target: (usize, usize),
+    /// Where the cursor really is, if it's known.
+    cursor: Option<(usize, usize)>,
+    style: ansi::Style,
+    /// The style the terminal is actually in.
+    current: ansi::Style,
+    /// What's been drawn, by row then column.
+    cells: Vec<Vec<RenderedCell>>,
+    encoding: Option<OutputEncoding>,
+}
+
+/// One column of the screen, as drawn by a `Renderer`. The columns after a wide character have no text.
+#[derive(Debug, Clone, Default)]
+struct RenderedCell {
+    text: String,
+    style: ansi::Style,
+}
+
+/// Rows set aside below the cursor for a UI that doesn't take over the screen, returned by `Term::inline_viewport`.
+///
+/// Positions in the viewport are counted from its own top left corner. When it's dropped its rows are erased, and
+/// the cursor goes back to where it was, so the UI leaves nothing behind and the scrollback above it is untouched.
+///
+/// If the terminal is resized the viewport is kept on the screen: it's pulled up if the screen got shorter than its
+/// bottom, and made shorter if the screen got shorter than it.
+pub struct Viewport<'a, I, O>
+where
+    I: io::Read + AsRawFd + 'a,
+    O: io::Write + AsRawFd + 'a,
+{
+    term: &'a Term<I, O>,
+    /// The height that was asked for, which the viewport grows back to if the screen does.
+    requested: usize,
+    height: usize,
+    /// The screen row the viewport starts on.
+    top: usize,
+    /// Where the cursor was before the viewport, as (column, row), moved up by any scrolling.
+    cursor: (usize, usize),
+    /// The screen's size as (columns, rows) when the viewport was last laid out.
+    size: (usize, usize),
+}
+
+/// The terminal's status line, returned by `Term::status_line`.
+pub struct StatusLine<'a, I, O>
+where
+    I: io::Read + AsRawFd + 'a,
+    O: io::Write + AsRawFd + 'a,
+{
+    term: &'a Term<I, O>,
+}
+
 impl Settings {
     /// Convenience method to configure the terminal to be in "raw" mode
     ///
@@ -138,6 +471,75 @@ impl Settings {
         return self;
     }
 
+    /// Configure the terminal to be in "cbreak" mode, where input is read a byte at a time without being echoed, but
+    /// the interrupt and suspend characters still send their signals and output is still processed.
+    pub fn cbreak(mut self) -> Self {
+        use nix::libc;
+        use nix::sys::termios::LocalFlags;
+        use nix::sys::termios::SpecialCharacterIndices;
+
+        self.termios.local_flags &= !(LocalFlags::ICANON | LocalFlags::ECHO | LocalFlags::ECHONL);
+        self.termios.control_chars[SpecialCharacterIndices::VMIN as usize] = 1 as libc::cc_t;
+        self.termios.control_chars[SpecialCharacterIndices::VTIME as usize] = 0 as libc::cc_t;
+        self
+    }
+
+    /// True if the tty turns `\n` into `\r\n` on output (`OPOST` and `ONLCR`), which raw mode turns off.
+    pub fn translates_newlines(&self) -> bool {
+        use nix::sys::termios::OutputFlags;
+
+        self.termios
+            .output_flags
+            .contains(OutputFlags::OPOST | OutputFlags::ONLCR)
+    }
+
+    /// True if the tty stops and starts output itself when XOFF and XON are typed (`IXON`), which raw mode turns off.
+    pub fn handles_xon_xoff(&self) -> bool {
+        use nix::sys::termios::InputFlags;
+
+        self.termios.input_flags.contains(InputFlags::IXON)
+    }
+
+    pub(crate) fn from_termios(termios: termios::Termios) -> Settings {
+        Settings { termios }
+    }
+
+    pub(crate) fn termios(&self) -> &termios::Termios {
+        &self.termios
+    }
+
+    /// The settings a tty usually starts out with, for `MockBackend`.
+    pub(crate) fn cooked() -> Settings {
+        use nix::sys::termios::ControlFlags;
+        use nix::sys::termios::InputFlags;
+        use nix::sys::termios::LocalFlags;
+        use nix::sys::termios::OutputFlags;
+
+        let mut termios = termios::Termios::from(unsafe { mem::zeroed::<libc::termios>() });
+        termios.input_flags = InputFlags::ICRNL | InputFlags::IXON;
+        termios.output_flags = OutputFlags::OPOST | OutputFlags::ONLCR;
+        termios.control_flags = ControlFlags::CS8 | ControlFlags::CREAD;
+        termios.local_flags = LocalFlags::ICANON | LocalFlags::ECHO | LocalFlags::ISIG | LocalFlags::IEXTEN;
+        let _ = termios::cfsetospeed(&mut termios, termios::BaudRate::B38400);
+        Settings { termios }
+    }
+
+    /// The output baud rate, 0 if it's unknown (or the line is being hung up).
+    pub fn output_speed(&self) -> u32 {
+        let speed = unsafe { libc::cfgetospeed(&*self.termios.get_libc_termios()) };
+        // Linux has codes for the speeds, the BSDs use the baud rate itself
+        BAUD_RATES
+            .iter()
+            .find(|&&(code, _)| code == speed)
+            .map_or(speed as u32, |&(_, baud)| baud)
+    }
+
+    /// Set the output baud rate, which has to be one of the rates in `termios`, like `B9600`.
+    pub fn speed(mut self, speed: termios::BaudRate) -> Self {
+        let _ = termios::cfsetospeed(&mut self.termios, speed);
+        self
+    }
+
     /// Set the character size, `x` must be in the range 5-8 otherwise this method will panic
     pub fn char_size(mut self, x: u8) -> Self {
         if x < 5 || x > 8 {
@@ -249,92 +651,6 @@ fn seta_to_set_pallet(x: u8) -> u8 {
     }
 }
 
-/// Convert r, g and b values into a 3-bit pallet based color
-///
-/// Expected Color Pallet:
-/// 0. black
-/// 1. red
-/// 2. green
-/// 3. yellow
-/// 4. blue
-/// 5. magenta
-/// 6. cyan
-/// 7. grey
-fn index_from_rgb3(r: u8, g: u8, b: u8) -> u8 {
-    let ir = r as isize;
-    let ig = g as isize;
-    let ib = b as isize;
-
-    if ir > 200 && ig > 200 && ib > 200 {
-        7
-    } else if ir > (ig + ib) {
-        1
-    } else if ig > (ir + ib) {
-        2
-    } else if ib > (ig + ir) {
-        4
-    } else if (ir - ig).abs() < ib {
-        3
-    } else if (ib - ig).abs() < ir {
-        6
-    } else if (ib - ir).abs() < ig {
-        5
-    } else {
-        0
-    }
-}
-
-/// Same as index_from_rgb3 but with any extra bit to tell if the color should be "bright"
-fn index_from_rgb4(r: u8, g: u8, b: u8) -> u8 {
-    let ir = r as isize;
-    let ig = g as isize;
-    let ib = b as isize;
-
-    if ir > 200 && ig > 200 && ib > 200 {
-        15
-    } else if ir > 150 && ig > 150 && ib > 150 {
-        8
-    } else if ir > (ig + ib) {
-        if ir / 2 > (ig + ib) {
-            9
-        } else {
-            1
-        }
-    } else if ig > (ir + ib) {
-        if ig > (ir + ib) {
-            10
-        } else {
-            2
-        }
-    } else if ib > (ig + ir) {
-        if ib > (ig + ir) {
-            12
-        } else {
-            4
-        }
-    } else if (ir - ig).abs() < ib {
-        if (ir - ig).abs() < ib / 2 {
-            11
-        } else {
-            3
-        }
-    } else if (ib - ig).abs() < ir {
-        if (ib - ig).abs() < ir / 2 {
-            14
-        } else {
-            6
-        }
-    } else if (ib - ir).abs() < ig {
-        if (ib - ir).abs() < ig / 2 {
-            13
-        } else {
-            5
-        }
-    } else {
-        0
-    }
-}
-
 impl<'a, O> TermWriter<'a, O>
 where
     O: io::Write + AsRawFd + 'a,
@@ -342,7 +658,7 @@ where
     fn exec<'b>(&'b self, field: terminfo::StringField) -> Result<terminfo::lang::Executor<'a>> {
         match self.info.exec(field) {
             Some(v) => Ok(v),
-            None => Err(ErrorKind::MissingTermInfoField(field).into()),
+            None => Err(Capability::String(field).into()),
         }
     }
 
@@ -357,7 +673,7 @@ where
                     .write(self.stdout.deref_mut())
                     .context(ErrorKind::FailedToRunTerminfo(field))
                     .unwrap_or_else(|e| {
-                        self.err = Some(e.into());
+                        self.err = Some(e);
                         0
                     });
                 self
@@ -368,51 +684,12 @@ where
 
     /// Try to map the color into its closest equivalent supported by this terminal.
     fn scrunch_color(&self, color: ansi::Color) -> ansi::Color {
-        match self.info.number(terminfo::MaxColors).unwrap_or(2) {
-            8..=15 => match color {
-                ansi::Color::Index(x @ 0..=7) => x,
-                ansi::Color::Index(x @ 8..=15) => (x - 8),
-                ansi::Color::Index(16) => 0,
-                ansi::Color::Index(x @ 17..=232) => {
-                    index_from_rgb3((x % 6) * 51, ((x / 6) % 6) * 51, (x / 36) * 51)
-                }
-                ansi::Color::Index(x) => {
-                    if x > 233 + (255 - 233) / 2 {
-                        7
-                    } else {
-                        0
-                    }
-                }
-                ansi::Color::Rgb(r, g, b) => index_from_rgb3(r, g, b),
-            }.into(),
-            16..=87 => match color {
-                ansi::Color::Index(x @ 0..=15) => x,
-                ansi::Color::Index(16) => 0,
-                ansi::Color::Index(x @ 17..=232) => {
-                    index_from_rgb4((x % 6) * 51, ((x / 6) % 6) * 51, (x / 36) * 51)
-                }
-                ansi::Color::Index(x) => {
-                    let y = 233 + (255 - 233);
-                    if x > (y / 3) * 2 {
-                        15
-                    } else if x > y / 3 {
-                        0
-                    } else {
-                        7
-                    }
-                }
-                ansi::Color::Rgb(r, g, b) => index_from_rgb4(r, g, b),
-            }.into(),
-            88..=255 => match color.into() {
-                ansi::Color::Index(x @ 0..=15) => x,
-                ansi::Color::Index(x) => (x as f64 * 0.3451171875) as u8,
-                ansi::Color::Rgb(r, g, b) => (r * 4 + g) * 4 + b + 16,
-            }.into(),
-            256 => match color.into() {
-                ansi::Color::Index(x) => x,
-                ansi::Color::Rgb(r, g, b) => (r * 16 + g) * 16 + b + 16,
-            }.into(),
-            _ => unimplemented!(),
+        let colors = self.info.max_colors().unwrap_or(2) as usize;
+        match color {
+            ansi::Color::Index(x) if (x as usize) < colors => color,
+            _ if colors >= 1 << 24 => color,
+            _ if colors >= 256 => color.downsample(ansi::ColorDepth::Ansi256).unwrap(),
+            _ => ansi::Color::Index(color.nearest_indexed(&ansi::XTERM_PALETTE[..colors])),
         }
     }
 
@@ -423,7 +700,7 @@ where
 
         match util::write_u8_ansi(self.stdout.deref_mut(), x) {
             Ok(v) => self.written += v,
-            Err(e) => self.err = Some(e.context(ErrorKind::WriteFailed).into()),
+            Err(e) => self.err = Some(e.context(ErrorKind::WriteFailed)),
         };
 
         self
@@ -442,9 +719,16 @@ where
     }
 
     fn set_sgr(&mut self) {
-        match self
-            .exec(terminfo::SetAttributes)
-            .unwrap()
+        let exe = match self.exec(terminfo::SetAttributes) {
+            Ok(exe) => exe,
+            Err(e) => {
+                if self.degradation == Degradation::Strict {
+                    self.err = Some(e);
+                }
+                return;
+            }
+        };
+        match exe
             .arg(self.standout)
             .arg(self.underline)
             .arg(self.invert)
@@ -456,10 +740,7 @@ where
         {
             Ok(v) => self.written += v,
             Err(e) => {
-                self.err = Some(
-                    e.context(ErrorKind::FailedToRunTerminfo(terminfo::SetAAttributes))
-                        .into(),
-                );
+                self.err = Some(e.context(ErrorKind::FailedToRunTerminfo(terminfo::SetAAttributes)));
             }
         }
     }
@@ -471,24 +752,28 @@ where
 
         self.set_sgr();
         if let Err(e) = self.write_fg_bg() {
-            self.err = Some(
-                e.context(ErrorKind::FailedToRunTerminfo(terminfo::SetAAttributes))
-                    .into(),
-            );
+            self.err = Some(e.context(ErrorKind::FailedToRunTerminfo(terminfo::SetAAttributes)));
             return self;
         }
 
         match self.stdout.write(buf) {
             Ok(v) => self.written += v,
-            Err(e) => self.err = Some(e.context(ErrorKind::WriteFailed).into()),
+            Err(e) => self.err = Some(e.context(ErrorKind::WriteFailed)),
         };
 
         self.wipe_formatting();
         self
     }
 
-    pub fn print<T: AsRef<str>>(self, s: T) -> Self {
-        self.write_bytes(s.as_ref().as_bytes())
+    /// Write `s`, in the terminal's encoding (see `Term::set_encoding`).
+    pub fn print<T: AsRef<str>>(mut self, s: T) -> Self {
+        let encoding = self.encoding.take();
+        let mut w = match encoding {
+            Some(ref encoding) => self.write_bytes(&encoding.encode(s.as_ref().as_bytes())),
+            None => self.write_bytes(s.as_ref().as_bytes()),
+        };
+        w.encoding = encoding;
+        w
     }
 
     pub fn println<T: AsRef<str>>(self, s: T) -> Self {
@@ -636,22 +921,22 @@ where
                     Ok(e) => e
                         .arg(x as usize)
                         .write(self.stdout.deref_mut())
-                        .context(ErrorKind::FailedToRunTerminfo(set))
-                        .map_err(|e| e.into()),
-                    Err(_) => self.exec(set).map(|exe| {
-                        exe.arg(seta_to_set_pallet(x) as usize)
+                        .context(ErrorKind::FailedToRunTerminfo(set)),
+                    Err(_) => match self.exec(set) {
+                        Ok(exe) => Ok(exe
+                            .arg(seta_to_set_pallet(x) as usize)
                             .write(self.stdout.deref_mut())
                             .unwrap_or_else(|e| {
-                                self.err =
-                                    Some(e.context(ErrorKind::FailedToRunTerminfo(set)).into());
+                                self.err = Some(e.context(ErrorKind::FailedToRunTerminfo(set)));
                                 0
-                            })
-                    }),
+                            })),
+                        Err(e) if self.degradation == Degradation::Strict => Err(e),
+                        Err(_) => Ok(0),
+                    },
                 }.context(ErrorKind::WriteFailed)?
             }
+            Some(ansi::Color::Rgb(..)) if self.dumb && self.degradation != Degradation::Strict => (),
             Some(ansi::Color::Rgb(r, g, b)) => {
-                use std::io::Write;
-
                 self.written += self.write(rgb_prefix).context(ErrorKind::WriteFailed)?
                     + util::write_u8_ansi(self, r).context(ErrorKind::WriteFailed)?
                     + self.write(b";").context(ErrorKind::WriteFailed)?
@@ -726,6 +1011,71 @@ where
     }
 }
 
+/// The longest `ExecOptions` waits for one delay, unless it's told otherwise.
+pub const MAX_SLEEP: Duration = Duration::from_secs(1);
+
+/// How long the screen stays inverted when `Term::visual_bell` has to fake `flash`.
+const FLASH_DURATION: Duration = Duration::from_millis(100);
+
+/// The speeds `cfgetospeed` can return, and their baud rates.
+const BAUD_RATES: &[(libc::speed_t, u32)] = &[
+    (libc::B0, 0),
+    (libc::B50, 50),
+    (libc::B75, 75),
+    (libc::B110, 110),
+    (libc::B134, 134),
+    (libc::B150, 150),
+    (libc::B200, 200),
+    (libc::B300, 300),
+    (libc::B600, 600),
+    (libc::B1200, 1200),
+    (libc::B1800, 1800),
+    (libc::B2400, 2400),
+    (libc::B4800, 4800),
+    (libc::B9600, 9600),
+    (libc::B19200, 19200),
+    (libc::B38400, 38400),
+    (libc::B57600, 57600),
+    (libc::B115200, 115200),
+    (libc::B230400, 230400),
+    #[cfg(any(target_os = "android", target_os = "linux"))]
+    (libc::B460800, 460800),
+    #[cfg(any(target_os = "android", target_os = "linux"))]
+    (libc::B921600, 921600),
+    #[cfg(any(target_os = "android", target_os = "linux"))]
+    (libc::B4000000, 4000000),
+];
+
+/// True if `style` only has attributes `sgr` can set.
+fn only_sgr_attributes(style: &ansi::Style) -> bool {
+    style.foreground.is_none() && style.background.is_none() && !style.italic && !style.strike
+}
+
+/// Ask the tty `fd` for its size, as (columns, rows). A tty that doesn't know its size says it's 0x0.
+pub(crate) fn window_size(fd: RawFd) -> nix::Result<(usize, usize)> {
+    let mut size: libc::winsize = unsafe { mem::zeroed() };
+    Errno::result(unsafe { libc::ioctl(fd, libc::TIOCGWINSZ, &mut size) })?;
+    Ok((size.ws_col as usize, size.ws_row as usize))
+}
+
+/// Scale a color channel from 0-255 to the 0-1000 range `initc` takes.
+///
+/// This rounds up, so entries that scale back down with a truncating `v * 255 / 1000` get back the same channel.
+fn initc_scale(v: u8) -> u32 {
+    (u32::from(v) * 1000).div_ceil(255)
+}
+
+/// Guess the terminal from `answers` and the `$TERM`, `$TERM_PROGRAM`, and `$TERM_PROGRAM_VERSION` variables.
+fn identify_from_env(answers: &query::Answers) -> TerminalId {
+    let var = |name| env::var(name).ok();
+    query::identify(
+        answers,
+        var("TERM").as_deref(),
+        var("TERM_PROGRAM").as_deref(),
+        var("TERM_PROGRAM_VERSION").as_deref(),
+    )
+}
+
 /// Term represents the user's terminal.
 /// It has two channels, `I` (input), and `O` (output).
 /// Each terminal is accompanied by a "terminfo" file, (represented by the `TermInfoBuf` struct).
@@ -735,12 +1085,212 @@ where
     O: io::Write + AsRawFd,
 {
     pub fn from_streams(tib: terminfo::TermInfoBuf, stdin: I, stdout: O) -> Term<I, O> {
+        let backend = UnixBackend::new(stdin.as_raw_fd(), stdout.as_raw_fd());
+        Term::with_backend(tib, stdin, stdout, backend)
+    }
+
+    /// Like `from_streams`, but the terminal's settings and size come from `backend` instead of the streams' file
+    /// descriptors, see `backend::TermBackend`.
+    pub fn with_backend<B>(tib: terminfo::TermInfoBuf, stdin: I, stdout: O, backend: B) -> Term<I, O>
+    where
+        B: TermBackend + Send + 'static,
+    {
         Term {
             info: tib,
             stdin_fd: stdin.as_raw_fd(),
+            backend: Box::new(backend),
             stdin: Mutex::new(BufReader::new(stdin)),
-            stdout: Mutex::new(stdout),
+            stdout: Mutex::new(Gate::new(Tap::new(Output::new(Counted::new(stdout))))),
             err: RefCell::new(None),
+            acs_enabled: Cell::new(false),
+            encoding: Cell::new(None),
+            pushback: RefCell::new(VecDeque::new()),
+            query_budget: Cell::new(query::DEFAULT_BUDGET),
+            query_config: Cell::new(QueryConfig::default()),
+            round_trip: Cell::new(None),
+            identity: RefCell::new(None),
+            sequence_safety: Cell::new(None),
+            notification_style: Cell::new(None),
+            passthrough: Cell::new(false),
+            quiet: Cell::new(false),
+            degradation: Cell::new(Degradation::default()),
+            translates_newlines: Cell::new(None),
+            handles_xon_xoff: Cell::new(None),
+            flow_control: Cell::new(false),
+            baud_rate: Cell::new(None),
+            color_choice: Cell::new(ansi::ColorChoice::Auto),
+            color_depth: Cell::new(None),
+            env: RefCell::new(EnvOverrides::from_env()),
+            exec_options: RefCell::new(ExecOptions::default()),
+        }
+    }
+
+    /// True if the terminal's output is a tty, rather than a pipe or a file.
+    pub fn is_tty_out(&self) -> bool {
+        self.backend.is_tty()
+    }
+
+    /// Choose whether output should use colors, by default this is `ColorChoice::Auto`.
+    pub fn set_color_choice(&self, choice: ansi::ColorChoice) {
+        self.color_choice.set(choice);
+        self.color_depth.set(None);
+    }
+
+    pub fn color_choice(&self) -> ansi::ColorChoice {
+        self.color_choice.get()
+    }
+
+    /// Choose where `$COLUMNS`, `$LINES`, `$COLORTERM` and the other overrides are read from, by default this is
+    /// the process's environment.
+    pub fn set_env_overrides(&self, env: EnvOverrides) {
+        *self.env.borrow_mut() = env;
+        self.color_depth.set(None);
+    }
+
+    /// Choose the charset text is written in, and what happens to the characters it doesn't have, for
+    /// `Term::print`, `TermWriter::print` and `StyledWriter`. By default this is `None`, and text is written as it is
+    /// (which is only right for UTF-8 terminals).
+    ///
+    /// When transliterating, line-drawing characters are drawn in the terminal's alternate character set if it has
+    /// one, instead of with `+`, `-` and `|`. Writers pick the encoding up when they're made.
+    ///
+    /// ```no_run
+    /// use nixterm::encoding::Fallback;
+    /// use nixterm::Term;
+    ///
+    /// let term = Term::new().unwrap();
+    /// term.set_encoding(Some(term.locale_encoding(Fallback::Transliterate)));
+    /// term.print("┌──┐\n└──┘\n").unwrap();
+    /// ```
+    pub fn set_encoding(&self, encoding: Option<Encoding>) {
+        self.encoding.set(encoding);
+    }
+
+    pub fn encoding(&self) -> Option<Encoding> {
+        self.encoding.get()
+    }
+
+    /// The encoding of the user's locale, from `LC_ALL`, `LC_CTYPE` or `LANG` (through the `EnvOverrides`), see
+    /// `Charset::from_env`.
+    pub fn locale_encoding(&self, fallback: Fallback) -> Encoding {
+        Encoding::from_env(&self.env.borrow(), fallback)
+    }
+
+    /// The encoding for a new writer, or `None` if text is written as it is.
+    fn output_encoding(&self) -> Option<OutputEncoding> {
+        let encoding = match self.encoding.get() {
+            Some(encoding) if encoding.charset != Charset::Utf8 => encoding,
+            _ => return None,
+        };
+        let enter = self.info.string_bytes(terminfo::EnterAltCharsetMode);
+        let exit = self.info.string_bytes(terminfo::ExitAltCharsetMode);
+        let alt = match (enter, exit) {
+            (Some(enter), Some(exit))
+                if encoding.fallback == Fallback::Transliterate && self.info.string(terminfo::AcsChars).is_some() =>
+            {
+                Some(AltCharset {
+                    acs: Acs::with_utf8(&self.info, false),
+                    enter: without_delays(enter),
+                    exit: without_delays(exit),
+                    enable: self.info.string_bytes(terminfo::EnaAcs).map(without_delays),
+                    enabled: Cell::new(self.acs_enabled.get()),
+                })
+            }
+            _ => None,
+        };
+        Some(OutputEncoding {
+            encoding,
+            alt,
+            pending: RefCell::new(Vec::new()),
+        })
+    }
+
+    /// Look up the environment variable `name`, through the overrides.
+    pub(crate) fn env_var(&self, name: &str) -> Option<String> {
+        self.env.borrow().var(name)
+    }
+
+    /// How many colors output can use, resolving the color choice against `NO_COLOR`, `CLICOLOR_FORCE`,
+    /// `COLORTERM`, whether output is a tty, and the terminfo entry.
+    ///
+    /// `StyledWriter` and `Renderer` downsample their styles to this, and on dumb terminals it's always
+    /// `ColorDepth::None` (which leaves out bold and the other attributes too) unless colors are `Always` on.
+    pub fn color_depth(&self) -> ansi::ColorDepth {
+        if let Some(depth) = self.color_depth.get() {
+            return depth;
+        }
+        let env = self.env.borrow();
+        let depth = if self.is_dumb() && self.color_choice() != ansi::ColorChoice::Always {
+            ansi::ColorDepth::None
+        } else {
+            self.color_choice().resolve(
+                |name| env.var(name),
+                self.is_tty_out(),
+                env.max_colors(if self.info.has_truecolor() {
+                    Some(1 << 24)
+                } else {
+                    self.info.max_colors()
+                }),
+            )
+        };
+        self.color_depth.set(Some(depth));
+        depth
+    }
+
+    /// Get a writer that keeps track of the text's `Style`, for use with `write!` and `writeln!`.
+    pub fn styled_writer<'a>(&'a self) -> StyledWriter<'a, I, O> {
+        StyledWriter {
+            term: self,
+            style: ansi::Style::default(),
+            current: ansi::Style::default(),
+            sanitize: None,
+            encoding: self.output_encoding(),
+            quirks: OutputQuirks::new(&self.info),
+            overstrike: false,
+            after_space: false,
+        }
+    }
+
+    /// Get a writer for printing lines of text while the terminal is in raw mode, see `CookedWriter`.
+    pub fn cooked_writer<'a>(&'a self) -> CookedWriter<'a, I, O> {
+        CookedWriter {
+            writer: self.styled_writer(),
+            active: ansi::Style::default(),
+            line_start: true,
+        }
+    }
+
+    /// Print `args` and a newline with a `CookedWriter`, so it starts at the left margin and `\n` works even in raw
+    /// mode, like `term.cooked_println(format_args!("loaded {} files", n))`.
+    pub fn cooked_println(&self, args: fmt::Arguments) -> Result<()> {
+        {
+            let mut writer = self.cooked_writer();
+            io::Write::write_fmt(&mut writer, args)
+                .and_then(|_| io::Write::write_all(&mut writer, b"\n"))
+                .context(ErrorKind::WriteFailed)?;
+        }
+        self.err()
+    }
+
+    /// Get a `Renderer`, for redrawing the screen with as little output as possible.
+    pub fn renderer<'a>(&'a self) -> Renderer<'a, I, O> {
+        let moves = CursorOptimizer::new(&self.info);
+        Renderer {
+            term: self,
+            // `translates_newlines` guesses when the tty can't be asked, but a wrong guess here would move the cursor
+            // to the wrong column
+            moves: match self.backend.attributes() {
+                Ok(settings) => moves.translates_newlines(settings.translates_newlines()),
+                Err(_) => moves,
+            },
+            size: self.size().ok(),
+            buffer: Vec::new(),
+            target: (0, 0),
+            cursor: None,
+            style: ansi::Style::default(),
+            current: ansi::Style::default(),
+            cells: Vec::new(),
+            encoding: self.output_encoding(),
         }
     }
 
@@ -763,11 +1313,15 @@ where
     /// }
     /// ```
     pub fn writer<'a>(&'a self) -> TermWriter<'a, O> {
+        let encoding = self.output_encoding();
         TermWriter {
             info: &self.info,
             stdout: self.stdout.lock().unwrap(),
             written: 0,
             err: None,
+            degradation: self.degradation(),
+            dumb: self.is_dumb(),
+            encoding,
 
             bold: false,
             dim: false,
@@ -808,8 +1362,14 @@ where
     /// }
     /// ```
     pub fn read(&self, buffer: &mut [u8]) -> usize {
-        if self.err.borrow().is_none() {
-            self.stdin
+        let len = self.read_pushback(buffer);
+        if len > 0 {
+            return len;
+        }
+
+        while self.err.borrow().is_none() {
+            let len = self
+                .stdin
                 .lock()
                 .unwrap()
                 .read(buffer)
@@ -817,203 +1377,4472 @@ where
                 .unwrap_or_else(|e| {
                     self.set_err(e);
                     0
-                })
-        } else {
-            0
+                });
+            // when all that was read was XOFF or XON, wait for something else
+            if len == 0 {
+                return 0;
+            }
+            let len = self.take_xon_xoff(&mut buffer[..len]);
+            if len > 0 {
+                return len;
+            }
         }
+        0
     }
 
-    pub fn readline(&self) -> Result<String> {
-        let mut buf = String::new();
-        self.stdin
-            .lock()
-            .unwrap()
-            .read_line(&mut buf)
-            .context(ErrorKind::ReadFailed)?;
-        Ok(buf)
-    }
+    /// Pause or resume output for the XOFF and XON in `input` when `Term::set_flow_control` is on and the tty doesn't
+    /// do it itself, and take them out. It returns how much of `input` is left.
+    fn take_xon_xoff(&self, input: &mut [u8]) -> usize {
+        const XON: u8 = 0x11;
+        const XOFF: u8 = 0x13;
 
-    pub(crate) fn set_err<T: Into<Error>>(&self, e: T) {
-        self.err.replace(Some(e.into()));
+        if !self.flow_control.get() || self.handles_xon_xoff() {
+            return input.len();
+        }
+        let mut len = 0;
+        for i in 0..input.len() {
+            match input[i] {
+                XOFF => self.pause_output(),
+                XON => {
+                    if let Err(e) = self.resume_output() {
+                        self.set_err(e);
+                    }
+                }
+                c => {
+                    input[len] = c;
+                    len += 1;
+                }
+            }
+        }
+        len
     }
 
-    pub(crate) fn take_err(&self) -> Error {
-        self.err.replace(None).unwrap()
+    /// Read what's in the pushback buffer.
+    fn read_pushback(&self, buffer: &mut [u8]) -> usize {
+        let mut pushback = self.pushback.borrow_mut();
+        let len = buffer.len().min(pushback.len());
+        for (i, c) in pushback.drain(..len).enumerate() {
+            buffer[i] = c;
+        }
+        len
     }
 
-    pub(crate) fn has_err(&self) -> bool {
-        self.err.borrow().is_some()
+    /// Read input that's already been read from the terminal (pushed back, or in the `BufReader`), without
+    /// reading any more.
+    #[cfg(feature = "async-tokio")]
+    pub(crate) fn read_buffered(&self, buffer: &mut [u8]) -> usize {
+        let len = self.read_pushback(buffer);
+        if len > 0 {
+            return len;
+        }
+
+        let len = {
+            let mut stdin = self.stdin.lock().unwrap();
+            let len = buffer.len().min(stdin.buffer().len());
+            buffer[..len].copy_from_slice(&stdin.buffer()[..len]);
+            stdin.consume(len);
+            len
+        };
+        self.take_xon_xoff(&mut buffer[..len])
     }
 
-    pub fn err(&self) -> Result<()> {
-        if self.has_err() {
-            Err(self.take_err())
-        } else {
-            Ok(())
+    pub fn readline(&self) -> Result<String> {
+        let mut line = Vec::new();
+        {
+            let mut pushback = self.pushback.borrow_mut();
+            match pushback.iter().position(|&c| c == b'\n') {
+                Some(i) => line.extend(pushback.drain(..=i)),
+                None => line.extend(pushback.drain(..)),
+            }
         }
-    }
 
-    /// Execute a string field
-    fn exec<'a>(&'a self, field: terminfo::StringField) -> Result<terminfo::lang::Executor<'a>> {
-        match self.info.exec(field) {
-            Some(v) => Ok(v),
-            None => Err(ErrorKind::MissingTermInfoField(field).into()),
+        if line.last() != Some(&b'\n') {
+            self.stdin
+                .lock()
+                .unwrap()
+                .read_until(b'\n', &mut line)
+                .context(ErrorKind::ReadFailed)?;
         }
+        String::from_utf8(line).context(ErrorKind::ReadLineFailed)
     }
 
-    /// Wrapper around exec, which immediately runs the string with no args and writes it to `O`.
-    fn write_info_str(&self, field: terminfo::StringField) -> usize {
-        match self.exec(field) {
-            Ok(mut v) => v
-                .write(self.stdout.lock().unwrap().deref_mut())
+    /// Show `prompt`, then read a line the user can edit, returning it without the newline.
+    ///
+    /// This puts the terminal in cbreak mode (see `Settings::cbreak`) until the line is done, and does the echoing and
+    /// editing itself: backspace deletes a character, `^W` a word and `^U` the whole line. Escape sequences, like the
+    /// arrow keys, are ignored. Pressing `^D` on an empty line is the end of the input, and an error.
+    ///
+    /// The prompt and the echo go to the terminal even when only the input is one, like when the output is
+    /// redirected to a file. When the input isn't a terminal either the prompt is written to the output and a line
+    /// is read as it is, like `readline`.
+    pub fn read_line(&self, prompt: &str) -> Result<String> {
+        self.prompt_line(prompt, true)
+    }
+
+    /// Like `read_line`, but nothing the user types is shown.
+    ///
+    /// Echoing is turned back on even if reading fails, and a newline is printed after the line (which the user
+    /// didn't see being typed).
+    pub fn read_password(&self, prompt: &str) -> Result<String> {
+        self.prompt_line(prompt, false)
+    }
+
+    fn prompt_line(&self, prompt: &str, echo: bool) -> Result<String> {
+        self.err()?;
+        let settings = match self.backend.attributes() {
+            Ok(settings) => settings,
+            Err(_) => {
+                self.print(prompt)?;
+                self.flush();
+                self.err()?;
+                let mut line = self.readline()?;
+                if line.ends_with('\n') {
+                    line.pop();
+                    if line.ends_with('\r') {
+                        line.pop();
+                    }
+                }
+                return Ok(line);
+            }
+        };
+
+        // whatever was typed ahead is part of the line, or of the next one
+        self.apply(settings.clone().cbreak(), true)?;
+        let line = self
+            .echo(prompt.as_bytes())
+            .and_then(|_| self.edit_line(echo));
+        let newline = self.echo(if settings.translates_newlines() { b"\n" } else { b"\r\n" });
+        let restored = self.apply(settings, true);
+        let line = line?;
+        newline?;
+        restored?;
+        Ok(line)
+    }
+
+    /// Read and edit a line for `prompt_line`, in cbreak mode.
+    fn edit_line(&self, echo: bool) -> Result<String> {
+        let mut line = String::new();
+        // the start of a character that hasn't all been read yet
+        let mut partial = Vec::new();
+        loop {
+            let c = match self.read_byte()? {
+                Some(c) => c,
+                None if line.is_empty() => {
+                    let eof = io::Error::new(io::ErrorKind::UnexpectedEof, "the input ended");
+                    return Err(eof.context(ErrorKind::ReadFailed));
+                }
+                None => return Ok(line),
+            };
+
+            let mut erase = 0;
+            match c {
+                b'\r' | b'\n' => return Ok(line),
+                // ^H and DEL
+                0x08 | 0x7f => erase = line.pop().map_or(0, ansi::char_width),
+                // ^U
+                0x15 => erase = line.drain(..).map(ansi::char_width).sum(),
+                // ^W
+                0x17 => {
+                    while line.ends_with(char::is_whitespace) {
+                        erase += line.pop().map_or(0, ansi::char_width);
+                    }
+                    while line.chars().next_back().is_some_and(|c| !c.is_whitespace()) {
+                        erase += line.pop().map_or(0, ansi::char_width);
+                    }
+                }
+                // ^D, only the end of the input on an empty line
+                0x04 if line.is_empty() => {
+                    let eof = io::Error::new(io::ErrorKind::UnexpectedEof, "the input ended");
+                    return Err(eof.context(ErrorKind::ReadFailed));
+                }
+                0x1b => self.skip_escape()?,
+                0..=0x1f => (),
+                _ => {
+                    partial.push(c);
+                    match ::std::str::from_utf8(&partial) {
+                        Ok(s) => {
+                            line.push_str(s);
+                            if echo {
+                                self.echo(s.as_bytes())?;
+                            }
+                            partial.clear();
+                        }
+                        Err(e) if e.error_len().is_some() => partial.clear(),
+                        Err(_) => (),
+                    }
+                }
+            }
+
+            if echo && erase > 0 {
+                self.echo(&b"\x08 \x08".repeat(erase))?;
+            }
+        }
+    }
+
+    /// Skip the rest of an escape sequence that's been started, for `edit_line`.
+    fn skip_escape(&self) -> Result<()> {
+        // the escape key on its own
+        if !self.input_pending()? && !self.backend.poll_input(ESCAPE_TIMEOUT).context(ErrorKind::ReadFailed)? {
+            return Ok(());
+        }
+        match self.read_byte()? {
+            Some(b'[') => {
+                // parameters, then a final byte
+                while let Some(c) = self.read_byte()? {
+                    if (0x40..=0x7e).contains(&c) {
+                        break;
+                    }
+                }
+            }
+            Some(b'O') => {
+                self.read_byte()?;
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    /// Read one byte of input, or `None` at the end of the input.
+    fn read_byte(&self) -> Result<Option<u8>> {
+        let mut c = [0u8];
+        if self.read(&mut c) == 1 {
+            return Ok(Some(c[0]));
+        }
+        self.err()?;
+        Ok(None)
+    }
+
+    /// Write `bytes` straight to the terminal for `prompt_line`, which is the input if the output isn't a tty.
+    fn echo(&self, bytes: &[u8]) -> Result<()> {
+        if self.is_tty_out() {
+            let mut stdout = self.stdout.lock().unwrap();
+            stdout
+                .write_all(bytes)
+                .and_then(|_| stdout.flush())
+                .context(ErrorKind::WriteFailed)?;
+            return Ok(());
+        }
+
+        let fd = self.stdin.lock().unwrap().get_ref().as_raw_fd();
+        let mut bytes = bytes;
+        while !bytes.is_empty() {
+            match nix::unistd::write(fd, bytes) {
+                Ok(n) => bytes = &bytes[n..],
+                Err(nix::Error::Sys(Errno::EINTR)) => (),
+                Err(e) => return Err(e.context(ErrorKind::WriteFailed)),
+            }
+        }
+        Ok(())
+    }
+
+    /// True if there's input waiting to be read, without waiting for any.
+    ///
+    /// This counts input that's already been read from the terminal but not returned yet, like keys that arrived
+    /// while waiting for a query's reply. `Keys::input_pending` also counts what its decoder hasn't decoded.
+    pub fn input_pending(&self) -> Result<bool> {
+        self.wait_input(Duration::from_secs(0))
+    }
+
+    /// Wait up to `timeout` for input, like `input_pending`. Returns false if the timeout runs out first.
+    pub(crate) fn wait_input(&self, timeout: Duration) -> Result<bool> {
+        if !self.pushback.borrow().is_empty() || !self.stdin.lock().unwrap().buffer().is_empty() {
+            return Ok(true);
+        }
+        self.backend
+            .poll_input(timeout)
+            .context(ErrorKind::ReadFailed)
+    }
+
+    /// Wait up to `timeout` for input, then read it into `buffer`. Returns 0 if the timeout runs out first.
+    ///
+    /// This bypasses the pushback buffer.
+    fn read_timeout(&self, buffer: &mut [u8], timeout: Duration) -> Result<usize> {
+        let mut stdin = self.stdin.lock().unwrap();
+
+        if stdin.buffer().is_empty()
+            && !self
+                .backend
+                .poll_input(timeout)
+                .context(ErrorKind::ReadFailed)?
+        {
+            return Ok(0);
+        }
+
+        stdin.read(buffer).context(ErrorKind::ReadFailed)
+    }
+
+    /// Send `request` to the terminal, and wait for a reply accepted by `matcher`, see `exchange`.
+    pub(crate) fn query<T, F>(
+        &self,
+        request: &[u8],
+        timeout: Option<Duration>,
+        mut matcher: F,
+    ) -> Result<(QueryResult<T>, Duration)>
+    where
+        F: FnMut(query::Reply) -> Option<Verdict<T>>,
+    {
+        self.exchange(request, timeout, |input| query::find_reply(input, &mut matcher))
+    }
+
+    /// Send `request` to the terminal, and wait for `find` to find its reply in the input, returning where it starts
+    /// and ends. Each attempt waits `timeout`, or `query_timeout` if that's `None`, and the query is sent again as
+    /// many times as the `QueryConfig` says if it isn't answered.
+    ///
+    /// Anything read that isn't the reply (like keys the user pressed in the meantime) is pushed back, so it's
+    /// returned by the next `read`. Only failing to read or write is an error; everything else is a `QueryResult`,
+    /// which comes with how long this waited altogether.
+    fn exchange<T, F>(
+        &self,
+        request: &[u8],
+        timeout: Option<Duration>,
+        mut find: F,
+    ) -> Result<(QueryResult<T>, Duration)>
+    where
+        F: FnMut(&[u8]) -> Option<(usize, usize, Verdict<T>)>,
+    {
+        let window = timeout.unwrap_or_else(|| self.query_timeout());
+        let mut retries = self.query_config.get().retry_count();
+        let start = Instant::now();
+        loop {
+            self.send_query(request)?;
+            let result = self.wait_for(Instant::now() + window, &mut find)?;
+            match result {
+                QueryResult::TimedOut if retries > 0 => retries -= 1,
+                // a reply cut short by the timeout doesn't say how long a whole one takes
+                QueryResult::TimedOut | QueryResult::Malformed(_) => return Ok((result, start.elapsed())),
+                _ => {
+                    let waited = start.elapsed();
+                    self.answered(waited);
+                    return Ok((result, waited));
+                }
+            }
+        }
+    }
+
+    /// A query was answered `waited` after it was first sent. The first one is the round trip an adaptive
+    /// `QueryConfig` goes by.
+    fn answered(&self, waited: Duration) {
+        if self.round_trip.get().is_none() {
+            self.round_trip.set(Some(waited));
+        }
+    }
+
+    /// Write `request` to the terminal and flush it, so it's seen right away.
+    fn send_query(&self, request: &[u8]) -> Result<()> {
+        // inside a `frame` this sends what the frame has written so far too, the reply would never come otherwise
+        self.stdout
+            .lock()
+            .unwrap()
+            .write_through(request)
+            .context(ErrorKind::WriteFailed)
+    }
+
+    /// Wait until `deadline` for a reply accepted by `matcher`, starting with any input that was pushed back.
+    fn wait_reply<T, F>(&self, deadline: Instant, mut matcher: F) -> Result<QueryResult<T>>
+    where
+        F: FnMut(query::Reply) -> Option<Verdict<T>>,
+    {
+        self.wait_for(deadline, |input| query::find_reply(input, &mut matcher))
+    }
+
+    /// Wait until `deadline` for `find` to find a reply in the input, returning where it starts and ends. This also
+    /// gives up once the query's byte budget has been read, see `set_query_budget`.
+    fn wait_for<T, F>(&self, deadline: Instant, mut find: F) -> Result<QueryResult<T>>
+    where
+        F: FnMut(&[u8]) -> Option<(usize, usize, Verdict<T>)>,
+    {
+        let mut input: Vec<u8> = self.pushback.borrow_mut().drain(..).collect();
+        let mut buffer = [0u8; 256];
+        let mut read = 0;
+        let mut timed_out = false;
+        let result = loop {
+            let now = Instant::now();
+            let done = timed_out || now >= deadline || read >= self.query_budget.get();
+            if let Some(result) = query::decode(&mut input, done, &mut find) {
+                break Ok(result);
+            }
+
+            match self.read_timeout(&mut buffer, deadline - now) {
+                Ok(0) => timed_out = true,
+                Ok(n) => {
+                    read += n;
+                    input.extend_from_slice(&buffer[..n]);
+                }
+                Err(e) => break Err(e),
+            }
+        };
+
+        self.pushback.borrow_mut().extend(input);
+        result
+    }
+
+    /// Give up on a query's reply after reading `bytes` of other input, even if its timeout hasn't run out.
+    ///
+    /// Terminals that don't know a query usually ignore it, but some answer with garbage, or with a reply that never
+    /// ends. `query::DEFAULT_BUDGET` is enough for a reply with a lot of typing around it.
+    pub fn set_query_budget(&self, bytes: usize) {
+        self.query_budget.set(bytes);
+    }
+
+    /// Change how long queries wait for their replies, and how many times they're sent again, see `QueryConfig`.
+    ///
+    /// Every method that queries the terminal takes a timeout, which can be a `Duration` to wait exactly that long,
+    /// or `None` to go by this.
+    pub fn set_query_config(&self, config: QueryConfig) {
+        self.query_config.set(config);
+    }
+
+    /// How queries wait for their replies, see `set_query_config`.
+    pub fn query_config(&self) -> QueryConfig {
+        self.query_config.get()
+    }
+
+    /// How long a query that's given a timeout of `None` waits for its reply, see `QueryConfig::window`.
+    pub fn query_timeout(&self) -> Duration {
+        self.query_config.get().window(self.round_trip.get())
+    }
+
+    /// How long the terminal took to answer the first query it answered, if it's answered one.
+    pub fn round_trip(&self) -> Option<Duration> {
+        self.round_trip.get()
+    }
+
+    /// Work out which terminal this is, waiting up to `timeout` for its replies.
+    ///
+    /// This sends the XTVERSION, secondary, and primary device attributes queries at once. Nearly every terminal
+    /// answers DA1, and does so in order, so its reply means there are no more replies coming. The answers are
+    /// combined with `$TERM` and `$TERM_PROGRAM` into a best guess; a terminal that doesn't reply at all is
+    /// identified from the environment alone.
+    pub fn identify<D: Into<Option<Duration>>>(&self, timeout: D) -> Result<TerminalId> {
+        let answers = self.query_identity(timeout.into())?;
+        let id = identify_from_env(&answers);
+        self.identity.replace(Some(id.clone()));
+        Ok(id)
+    }
+
+    /// Send the queries for `identify`, and collect whichever replies come back within `timeout` (see `exchange`).
+    /// They're only sent again if none of them were answered.
+    pub(crate) fn query_identity(&self, timeout: Option<Duration>) -> Result<query::Answers> {
+        let mut request = Vec::new();
+        request.extend_from_slice(ansi::REQUEST_XTVERSION);
+        request.extend_from_slice(ansi::REQUEST_SECONDARY_DA);
+        request.extend_from_slice(ansi::REQUEST_PRIMARY_DA);
+        let window = timeout.unwrap_or_else(|| self.query_timeout());
+        let mut retries = self.query_config.get().retry_count();
+        let start = Instant::now();
+        self.send_query(&request)?;
+
+        let mut deadline = Instant::now() + window;
+        let mut answers = query::Answers::default();
+        while answers.primary.is_none() {
+            let answer = match self.wait_reply(deadline, query::match_identity)? {
+                QueryResult::Answered(answer) => answer,
+                // a garbled reply is as good as none, but there may be others still to come
+                QueryResult::Malformed(_) | QueryResult::Unsupported => continue,
+                QueryResult::TimedOut if retries > 0 && answers.secondary.is_none() && answers.version.is_none() => {
+                    retries -= 1;
+                    self.send_query(&request)?;
+                    deadline = Instant::now() + window;
+                    continue;
+                }
+                QueryResult::TimedOut => break,
+            };
+            self.answered(start.elapsed());
+            match answer {
+                Answer::Primary(v) => answers.primary = Some(v),
+                Answer::Secondary(v) => answers.secondary = Some(v),
+                Answer::Version(v) => answers.version = Some(v),
+            }
+        }
+        Ok(answers)
+    }
+
+    /// Override how `notify` shows notifications, or go back to picking based on the terminal with `None`.
+    pub fn set_notification_style(&self, style: Option<NotificationStyle>) {
+        self.notification_style.set(style);
+    }
+
+    /// The way `notify` will show notifications.
+    ///
+    /// Unless it's been overridden, this is based on the result of the last `identify`, or the environment if the
+    /// terminal hasn't been identified yet.
+    pub fn notification_style(&self) -> NotificationStyle {
+        self.notification_style
+            .get()
+            .unwrap_or_else(|| self.identity().family.notification_style())
+    }
+
+    /// The result of the last `identify`, or a guess from the environment if the terminal hasn't been identified.
+    fn identity(&self) -> TerminalId {
+        match *self.identity.borrow() {
+            Some(ref id) => id.clone(),
+            None => identify_from_env(&query::Answers::default()),
+        }
+    }
+
+    /// Show a desktop notification, if the terminal supports them.
+    ///
+    /// Control characters and `;` are removed from `title` and `body` first, so they can't end the sequence early.
+    pub fn notify(&self, title: &str, body: &str) -> Result<()> {
+        let mut seq = String::new();
+        match self.notification_style() {
+            NotificationStyle::Osc777 => xterm::notify(&mut seq, title, body)?,
+            NotificationStyle::Osc9 => xterm::notify_message(&mut seq, title, body)?,
+            NotificationStyle::Disabled => return Ok(()),
+        }
+        self.write_to_host(seq.as_bytes())
+    }
+
+    /// Print `text` as a hyperlink to `url`, with `OSC 8`. Terminals that don't support hyperlinks show just the
+    /// text, and on one that's dumb or that `probe_sequence_safety` saw printing OSCs only the text is sent.
+    pub fn hyperlink(&self, url: &str, text: &str) -> Result<usize> {
+        if self.is_dumb() || !self.sequence_safe(SequenceFamily::Osc) {
+            return self.print(text);
+        }
+        let (mut start, mut end) = (String::new(), String::new());
+        xterm::start_hyperlink(&mut start, url)?;
+        xterm::end_hyperlink(&mut end)?;
+        self.writer()
+            .write_bytes(start.as_bytes())
+            .print(text)
+            .write_bytes(end.as_bytes())
+            .done()
+    }
+
+    /// Put `data` on the system clipboard with `OSC 52`. Terminals that don't support it ignore the sequence, and
+    /// many only allow it once the user has turned it on.
+    pub fn copy_to_clipboard(&self, data: &[u8]) -> Result<()> {
+        let encoded = String::from_utf8_lossy(&util::base64_encode(data)).into_owned();
+        let args = [lang::Argument::from("c"), lang::Argument::from(encoded)];
+        match self.info.ext_format(ExtCapability::Ms, &args) {
+            Some(seq) => self.write_to_host(&seq.context(ErrorKind::FailedToRunExtended(ExtCapability::Ms))?),
+            None => {
+                let mut seq = String::new();
+                xterm::set_clipboard(&mut seq, "c", data)?;
+                self.write_to_host(seq.as_bytes())
+            }
+        }
+    }
+
+    /// Choose whether the sequences `notify`, `copy_to_clipboard` and `draw_image` send are wrapped in the
+    /// passthrough of the multiplexer the program is running in (see `ansi::passthrough`), so they get to the
+    /// terminal outside it. This is off by default, and does nothing outside a multiplexer.
+    pub fn set_passthrough(&self, v: bool) {
+        self.passthrough.set(v);
+    }
+
+    /// The multiplexer sequences for the outer terminal are passed through, if passthrough is on and the
+    /// environment (see `set_env_overrides`) says there is one.
+    pub fn passthrough(&self) -> Option<ansi::Mux> {
+        if !self.passthrough.get() {
+            return None;
+        }
+        let env = self.env.borrow();
+        ansi::Mux::detect_with(|name| env.var(name))
+    }
+
+    /// Write and flush `seq`, which is meant for the terminal itself rather than a multiplexer it's running.
+    fn write_to_host(&self, seq: &[u8]) -> Result<()> {
+        let wrapped;
+        let seq = match self.passthrough() {
+            Some(mux) => {
+                wrapped = ansi::passthrough(seq, mux);
+                &wrapped[..]
+            }
+            None => seq,
+        };
+        let mut stdout = self.stdout.lock().unwrap();
+        stdout.write_all(seq).context(ErrorKind::WriteFailed)?;
+        stdout.flush().context(ErrorKind::WriteFailed)?;
+        Ok(())
+    }
+
+    /// Write `enter` (or `enter_fallback` if the terminal doesn't have it), and return a guard that writes `exit`
+    /// (or `exit_fallback`) when it's dropped.
+    fn mode_guard<'a>(
+        &'a self,
+        enter: terminfo::StringField,
+        enter_fallback: &'static [u8],
+        exit: terminfo::StringField,
+        exit_fallback: &'static [u8],
+    ) -> Result<ModeGuard<'a, I, O>> {
+        self.write_info_str_or(enter, enter_fallback)?;
+        Ok(ModeGuard {
+            term: self,
+            restore: exit,
+            fallback: exit_fallback,
+        })
+    }
+
+    /// Turn on keypad transmit mode (`smkx`) until the guard is dropped.
+    ///
+    /// Without `smkx` this falls back to turning on application cursor keys (DECCKM) and the application keypad
+    /// (DECKPAM).
+    pub fn keypad_transmit<'a>(&'a self) -> Result<ModeGuard<'a, I, O>> {
+        self.mode_guard(
+            terminfo::KeypadXmit,
+            b"\x1b[?1h\x1b=",
+            terminfo::KeypadLocal,
+            b"\x1b[?1l\x1b>",
+        )
+    }
+
+    /// Turn on meta mode (`smm`), where the meta key sets the 8th bit, until the guard is dropped.
+    ///
+    /// Without `smm` this falls back to xterm's `eightBitInput` mode.
+    pub fn meta_mode<'a>(&'a self) -> Result<ModeGuard<'a, I, O>> {
+        self.mode_guard(
+            terminfo::MetaOn,
+            b"\x1b[?1034h",
+            terminfo::MetaOff,
+            b"\x1b[?1034l",
+        )
+    }
+
+    /// Turn off automatic margins (`rmam`) until the guard is dropped, so writing to the last column doesn't wrap.
+    ///
+    /// Without `rmam` this falls back to resetting DECAWM.
+    pub fn disable_auto_margins<'a>(&'a self) -> Result<ModeGuard<'a, I, O>> {
+        self.mode_guard(
+            terminfo::ExitAmMode,
+            b"\x1b[?7l",
+            terminfo::EnterAmMode,
+            b"\x1b[?7h",
+        )
+    }
+
+    /// Set the terminal up for a full-screen application, as described by `options`.
+    ///
+    /// Everything is undone, in reverse order, when the `Session` is dropped. If part of the setup fails, the parts
+    /// that succeeded are undone before the error is returned.
+    ///
+    /// Under `Degradation::Strict` a session with the alternate screen is checked against `Profile::FullScreen` (see
+    /// `validate`) before anything is set up, and fails with `Error::Unsuitable` if there's no alternate screen or
+    /// something else is missing with nothing to stand in for it.
+    pub fn session<'a>(&'a self, options: SessionOptions) -> Result<Session<'a, I, O>> {
+        if options.alternate_screen && self.degradation() == Degradation::Strict {
+            let report = self.validate(Profile::FullScreen);
+            if report.lacks(Requirement::AlternateScreen) || !report.is_usable() {
+                return Err(Error::Unsuitable(report));
+            }
+        }
+        let mut session = Session {
+            term: self,
+            settings: None,
+            guards: Vec::new(),
+        };
+
+        if options.raw {
+            let settings = self.settings();
+            self.update(settings.clone().raw())?;
+            session.settings = Some(settings);
+        }
+        if options.alternate_screen {
+            session.guards.push(self.mode_guard(
+                terminfo::EnterCaMode,
+                b"\x1b[?1049h",
+                terminfo::ExitCaMode,
+                b"\x1b[?1049l",
+            )?);
+        }
+        if options.hide_cursor {
+            session.guards.push(self.mode_guard(
+                terminfo::CursorInvisible,
+                b"\x1b[?25l",
+                terminfo::CursorNormal,
+                b"\x1b[?25h",
+            )?);
+        }
+        if options.keypad_transmit {
+            session.guards.push(self.keypad_transmit()?);
+        }
+
+        self.flush();
+        self.err()?;
+        Ok(session)
+    }
+
+    /// Get the terminal's status line, if it has one (`hs`).
+    ///
+    /// When this is `None` the window title (see `xterm::set_title`) is the usual place to put status text.
+    pub fn status_line<'a>(&'a self) -> Option<StatusLine<'a, I, O>> {
+        if self.info.boolean(terminfo::HasStatusLine)
+            && self.info.string(terminfo::ToStatusLine).is_some()
+            && self.info.string(terminfo::FromStatusLine).is_some()
+        {
+            Some(StatusLine { term: self })
+        } else {
+            None
+        }
+    }
+
+    /// Set aside `height` rows below the cursor for an inline UI, like fzf's, without switching to the alternate
+    /// screen. `timeout` is how long to wait for the terminal to say where the cursor is, see `cursor_position`.
+    ///
+    /// The viewport starts on the cursor's row, or the row after it if there's text before the cursor on its row.
+    /// If there isn't room below, the screen is scrolled up to make some, and a screen shorter than `height` gets a
+    /// viewport as tall as the screen. See `Viewport` for what happens when it's dropped.
+    ///
+    /// A `height` of 0 is an empty viewport, which doesn't ask where the cursor is, has no rows to move to or erase,
+    /// and leaves the cursor where it is when it's dropped.
+    pub fn inline_viewport<'a, D>(&'a self, height: usize, timeout: D) -> Result<Viewport<'a, I, O>>
+    where
+        D: Into<Option<Duration>>,
+    {
+        let size = self.size()?;
+        if height == 0 {
+            return Ok(Viewport {
+                term: self,
+                requested: 0,
+                height: 0,
+                top: 0,
+                cursor: (0, 0),
+                size,
+            });
+        }
+        let (x, y) = self.cursor_position(timeout)?;
+        let rows = size.1;
+        let top = if x > 0 { y + 1 } else { y };
+        let scroll = (top + height.min(rows)).saturating_sub(rows);
+        if scroll > 0 {
+            self.move_to(0, rows - 1)?;
+            for _ in 0..scroll {
+                self.write_info_str_or(terminfo::ScrollForward, b"\n")?;
+            }
+        }
+        self.flush();
+
+        let mut viewport = Viewport {
+            term: self,
+            requested: height,
+            height: height.min(rows),
+            top: top - scroll,
+            // if the cursor's own row had to scroll away it can only go back to the top
+            cursor: if y >= scroll { (x, y - scroll) } else { (0, 0) },
+            size,
+        };
+        viewport.move_to(0, 0)?;
+        Ok(viewport)
+    }
+
+    /// Run `f` as a single frame: everything it writes is shown at once, when it returns.
+    ///
+    /// This wraps the output in a synchronized update, with the terminal's `Sync` if it has one and mode 2026
+    /// otherwise, which terminals that don't support it ignore. The frame is collected while `f` runs and sent in one
+    /// write, or one vectored write when it's big (see `io_stats`). A frame inside another is part of the outer one.
+    ///
+    /// A query made in `f`, like `cursor_position`, sends what's been collected so far along with it, so the
+    /// terminal can answer. The rest of the frame is still sent when `f` returns.
+    pub fn frame<F, T>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce(&Self) -> T,
+    {
+        let begin = self.synchronized_update(true)?;
+        let end = self.synchronized_update(false)?;
+        let v = {
+            let outer = {
+                let mut stdout = self.stdout.lock().unwrap();
+                let outer = stdout.begin_batch();
+                if outer {
+                    // at the start of the batch, so a query in the frame sends it along with what's been collected.
+                    // Writes to a batch can't fail.
+                    let _ = stdout.write_all(&begin);
+                }
+                outer
+            };
+            let _batch = FrameBatch {
+                term: self,
+                end: if outer { Some(end) } else { None },
+            };
+            f(self)
+        };
+        self.flush();
+        self.err()?;
+        Ok(v)
+    }
+
+    /// The sequence that begins or ends a synchronized update.
+    fn synchronized_update(&self, begin: bool) -> Result<Vec<u8>> {
+        let arg = lang::Argument::Integer(if begin { 1 } else { 2 });
+        match self.info.ext_format(ExtCapability::Sync, &[arg]) {
+            Some(seq) => Ok(seq.context(ErrorKind::FailedToRunExtended(ExtCapability::Sync))?),
+            None if self.is_dumb() && self.degradation() != Degradation::Strict => Ok(Vec::new()),
+            // it would be printed at the start and the end of every frame
+            None if !self.sequence_safe(SequenceFamily::PrivateMode) => Ok(Vec::new()),
+            None => {
+                let mut seq = Vec::new();
+                ansi::set_private_mode(&mut seq, ansi::MODE_SYNCHRONIZED_OUTPUT, begin)?;
+                Ok(seq)
+            }
+        }
+    }
+
+    /// Write all of `bufs` to the terminal, returning how many bytes that was.
+    ///
+    /// Together they're small enough to copy into one buffer, or they're sent as a vectored write (one `writev` on a
+    /// tty or pipe), so something like a frame buffer with capability strings around it isn't copied first. Inside a
+    /// `frame`, or while output is paused, they're held back with everything else.
+    pub fn write_vectored(&self, bufs: &[io::IoSlice]) -> Result<usize> {
+        let parts: Vec<&[u8]> = bufs.iter().map(|buf| &buf[..]).collect();
+        self.stdout
+            .lock()
+            .unwrap()
+            .submit(&parts)
+            .context(ErrorKind::WriteFailed)?;
+        Ok(parts.iter().map(|part| part.len()).sum())
+    }
+
+    /// How many writes have been made to the output stream, and how much they wrote, since the terminal was made or
+    /// `reset_io_stats` was last called. This shows whether output is being batched up the way it should be.
+    pub fn io_stats(&self) -> IoStats {
+        self.stdout.lock().unwrap().get_mut().get_mut().get_mut().stats()
+    }
+
+    /// Set every count in `io_stats` back to 0.
+    pub fn reset_io_stats(&self) {
+        self.stdout.lock().unwrap().get_mut().get_mut().get_mut().reset();
+    }
+
+    /// Stop `bell` and `visual_bell` from doing anything, for applications that let the user turn the bell off.
+    pub fn set_quiet(&self, quiet: bool) {
+        self.quiet.set(quiet);
+    }
+
+    pub fn is_quiet(&self) -> bool {
+        self.quiet.get()
+    }
+
+    /// Change how delays are waited out, see `ExecOptions`.
+    pub fn set_exec_options(&self, options: ExecOptions) {
+        self.exec_options.replace(options);
+    }
+
+    /// How delays are waited out, see `set_exec_options`.
+    pub fn exec_options(&self) -> ExecOptions {
+        self.exec_options.borrow().clone()
+    }
+
+    /// Choose what happens when the terminal can't do what a method asks, by default this is
+    /// `Degradation::BestEffort`.
+    pub fn set_degradation(&self, degradation: Degradation) {
+        self.degradation.set(degradation);
+    }
+
+    pub fn degradation(&self) -> Degradation {
+        self.degradation.get()
+    }
+
+    /// Check the terminal against `profile`, like `TermInfo::validate` but for this terminal as it is: the size
+    /// isn't missing if `size` can find it some other way, and there's nothing to find it with if it can't. ANSI
+    /// sequences aren't suggested under `Degradation::Strict`, since they won't be sent.
+    pub fn validate(&self, profile: Profile) -> ValidationReport {
+        let mut report = self.info.validate(profile);
+        let sized = self.size().is_ok();
+        let strict = self.degradation() == Degradation::Strict;
+        report
+            .missing
+            .retain(|m| !(sized && m.requirement == Requirement::Size));
+        for m in report.missing.iter_mut() {
+            match m.suggestion {
+                Some(Suggestion::Ansi(_)) if strict => m.suggestion = None,
+                Some(_) if m.requirement == Requirement::Size => m.suggestion = None,
+                _ => (),
+            }
+        }
+        report
+    }
+
+    /// Hold back everything written to the terminal until `resume_output`, for when it can't take any more, like a
+    /// serial console that sent XOFF or a multiplexer in copy mode (see `in_copy_mode`).
+    ///
+    /// At most 64KiB is held back, see `set_pause_buffer`. `flush` does nothing while output is paused.
+    pub fn pause_output(&self) {
+        self.stdout.lock().unwrap().pause();
+    }
+
+    /// Send what was held back since `pause_output`, in the order it was written, and stop holding output back.
+    pub fn resume_output(&self) -> Result<()> {
+        self.stdout
+            .lock()
+            .unwrap()
+            .resume()
+            .context(ErrorKind::WriteFailed)?;
+        Ok(())
+    }
+
+    /// True if the terminal is a tmux pane in copy mode (or another of tmux's modes, like choosing a buffer), where
+    /// output still lands in the pane but the user scrolling through its history doesn't see it. Output can be
+    /// paused with `pause_output` until it isn't.
+    ///
+    /// tmux is asked with `tmux display-message`, on the server and pane `$TMUX` and `$TMUX_PANE` say. Outside tmux
+    /// this is false, since screen can't be asked.
+    pub fn in_copy_mode(&self) -> Result<bool> {
+        let socket = match self.env_var("TMUX") {
+            Some(ref tmux) if !tmux.is_empty() => tmux.split(',').next().unwrap_or("").to_string(),
+            _ => return Ok(false),
+        };
+        let mut tmux = process::Command::new("tmux");
+        tmux.arg("-S").arg(socket).args(["display-message", "-p"]);
+        if let Some(pane) = self.env_var("TMUX_PANE") {
+            tmux.arg("-t").arg(pane);
+        }
+        let out = tmux
+            .arg("#{pane_in_mode}")
+            .stdin(process::Stdio::null())
+            .stderr(process::Stdio::null())
+            .output()
+            .context(ErrorKind::FailedToAskMultiplexer)?;
+        if !out.status.success() {
+            let failed = io::Error::other(format!("tmux exited with {}", out.status));
+            return Err(failed.context(ErrorKind::FailedToAskMultiplexer));
+        }
+        Ok(out.stdout.starts_with(b"1"))
+    }
+
+    pub fn output_paused(&self) -> bool {
+        self.stdout.lock().unwrap().is_paused()
+    }
+
+    /// Hold back at most `cap` bytes while output is paused, `policy` says what happens to writes past that.
+    pub fn set_pause_buffer(&self, cap: usize, policy: OverflowPolicy) {
+        self.stdout.lock().unwrap().set_limit(cap, policy);
+    }
+
+    /// Pause output when XOFF (`^S`) is read, and resume it when XON (`^Q`) is, taking them out of the input.
+    ///
+    /// The tty does this itself, unless `IXON` is off (`Settings::raw` turns it off), so it's only done then. It's off
+    /// by default, since it keeps `^S` and `^Q` from being read as keys. Serial consoles want it though, as the far
+    /// end loses what's sent after its XOFF.
+    pub fn set_flow_control(&self, v: bool) {
+        self.flow_control.set(v);
+    }
+
+    pub fn flow_control(&self) -> bool {
+        self.flow_control.get()
+    }
+
+    /// Copy everything sent to the terminal from now on to `sink`, as `format` says, until `stop_recording`.
+    ///
+    /// What's sent doesn't change, and output that's held back (see `pause_output`) is recorded when it's sent. An
+    /// asciicast recording's header has the terminal's size (or 80x24 if that isn't known) and `$TERM`. A recording
+    /// that was already going is finished first.
+    pub fn record_to<W>(&self, sink: W, format: RecordFormat) -> Result<()>
+    where
+        W: io::Write + Send + 'static,
+    {
+        let size = self.size().unwrap_or((80, 24));
+        let term = self.env_var("TERM");
+        self.stdout
+            .lock()
+            .unwrap()
+            .get_mut()
+            .start(Box::new(sink), format, size, term.as_deref())
+            .context(ErrorKind::RecordingFailed)?;
+        Ok(())
+    }
+
+    /// Finish the recording `record_to` started, failing if anything couldn't be written to it.
+    pub fn stop_recording(&self) -> Result<()> {
+        self.stdout
+            .lock()
+            .unwrap()
+            .get_mut()
+            .stop()
+            .context(ErrorKind::RecordingFailed)?;
+        Ok(())
+    }
+
+    /// True if the terminfo entry is `dumb`, a terminal that can only print text (and ring the bell).
+    pub fn is_dumb(&self) -> bool {
+        self.info.names.first().is_some_and(|name| name == "dumb")
+    }
+
+    /// Ring the terminal's bell (`bel`).
+    pub fn bell(&self) -> Result<()> {
+        if self.is_quiet() {
+            return Ok(());
+        }
+        self.write_info_str_or(terminfo::Bell, b"\x07")?;
+        self.flush();
+        self.err()
+    }
+
+    /// Flash the screen (`flash`) instead of making a sound.
+    ///
+    /// The delays in `flash` (usually a mandatory one, like xterm's `\E[?5h$<100/>\E[?5l`) are slept rather than
+    /// padded, since padding goes by in no time on a terminal that isn't on a serial line, and the flash would never
+    /// be seen. Sleeping goes through the `ExecOptions`, which bound how long it can be.
+    ///
+    /// Without `flash` this inverts the whole screen for a moment. That's done with reverse video mode (DECSCNM),
+    /// which leaves the current graphic rendition alone.
+    pub fn visual_bell(&self) -> Result<()> {
+        if self.is_quiet() {
+            return Ok(());
+        }
+        if let Ok(mut exe) = self.exec(terminfo::FlashScreen) {
+            let flash = exe
+                .vec()
+                .context(ErrorKind::FailedToRunTerminfo(terminfo::FlashScreen))?;
+            return self.write_with_delays(&flash, true, true);
+        }
+
+        self.frame(|t| t.set_private_mode(ansi::MODE_REVERSE_VIDEO, true))??;
+        self.exec_options().sleep(FLASH_DURATION);
+        self.frame(|t| t.set_private_mode(ansi::MODE_REVERSE_VIDEO, false))?
+    }
+
+    /// Turn a DEC private mode on (DECSET) or off (DECRST).
+    ///
+    /// Dumb terminals don't have any modes, so unless the degradation policy is `Strict` nothing is sent to them.
+    pub fn set_private_mode(&self, mode: u32, on: bool) -> Result<()> {
+        if self.is_dumb() && self.degradation() != Degradation::Strict {
+            return Ok(());
+        }
+        ansi::set_private_mode(self.stdout.lock().unwrap().deref_mut(), mode, on)
+    }
+
+    /// Ask the terminal for the state of a DEC private mode (DECRQM), waiting up to `timeout` for its reply, or as
+    /// long as the `QueryConfig` says if it's `None` (see `set_query_config`).
+    ///
+    /// Terminals that don't implement DECRQM won't reply at all, in which case this fails with `Error::Timeout`, and a
+    /// garbled reply is a `ParseError::MalformedReply`. A mode the terminal doesn't know is `ModeState::NotRecognized`.
+    pub fn query_private_mode<D: Into<Option<Duration>>>(&self, mode: u32, timeout: D) -> Result<ModeState> {
+        let mut request = Vec::new();
+        ansi::request_private_mode(&mut request, mode)?;
+        match self.query(&request, timeout.into(), |r| query::match_decrpm(r, mode))? {
+            (QueryResult::Unsupported, _) => Ok(ModeState::NotRecognized),
+            (result, waited) => result.into_result("DECRQM", waited),
+        }
+    }
+
+    /// Ask the terminal where the cursor is, waiting up to `timeout` for its reply. The position is (column, row),
+    /// both counted from 0.
+    ///
+    /// The request is the entry's `u7`, and the reply is read with its `u6` (see `query::CursorReportFormat`). Without
+    /// them, or with a `u6` that can't be read, this sends DSR 6 and expects `CSI <row> ; <column> R`.
+    pub fn cursor_position<D: Into<Option<Duration>>>(&self, timeout: D) -> Result<(usize, usize)> {
+        let request = self
+            .info
+            .string_bytes(terminfo::User7)
+            .unwrap_or(ansi::REQUEST_CURSOR_POSITION);
+        let format = self
+            .info
+            .string_bytes(terminfo::User6)
+            .and_then(query::CursorReportFormat::parse);
+        let (result, waited) = match format {
+            Some(format) => self.exchange(request, timeout.into(), |input| {
+                format
+                    .find(input)
+                    .map(|(start, end, v)| (start, end, Verdict::Answer(v)))
+            })?,
+            None => self.query(request, timeout.into(), query::match_cursor_position)?,
+        };
+        result.into_result("cursor position", waited)
+    }
+
+    /// Ask the terminal what it is with the entry's `u9`, waiting up to `timeout` for its answer, and return what
+    /// each of the conversions in its `u8` matched (see `query::AnswerFormat`).
+    ///
+    /// Without them, or with a `u8` that can't be read, this asks for the primary device attributes like xterm's do,
+    /// and the answer is its one field, the attributes separated by `;`.
+    pub fn terminal_answer<D: Into<Option<Duration>>>(&self, timeout: D) -> Result<Vec<Vec<u8>>> {
+        let request = self
+            .info
+            .string_bytes(terminfo::User9)
+            .unwrap_or(ansi::REQUEST_PRIMARY_DA);
+        let format = self
+            .info
+            .string_bytes(terminfo::User8)
+            .and_then(query::AnswerFormat::parse)
+            .unwrap_or_else(|| query::AnswerFormat::parse(b"\x1b[?%[;0123456789]c").unwrap());
+        let (result, waited) = self.exchange(request, timeout.into(), |input| {
+            format
+                .find(input)
+                .map(|(start, end, v)| (start, end, Verdict::Answer(v)))
+        })?;
+        result.into_result("terminal answer", waited)
+    }
+
+    /// Find out whether the terminal reads each `SequenceFamily`, or prints them on the screen like some old or
+    /// minimal terminals do with sequences they don't know. Each family's harmless sequence (see
+    /// `SequenceFamily::ALL`) is followed by a cursor position query: if the cursor moved, it was printed.
+    ///
+    /// The probe is a synchronized update (see `frame`), and the cursor is moved back to where it was afterwards
+    /// rather than saved, so the application's saved cursor is left alone. If anything was printed the rest of the
+    /// line (and the screen below it, if it wrapped) is erased. A family the terminal didn't answer for within
+    /// `timeout` (all of the queries together, or each one as the `QueryConfig` says if it's `None`) is unknown.
+    /// Once every family is known the result is kept, so later calls don't probe, and `frame` and `hyperlink` leave
+    /// out the sequences of a family that was printed.
+    pub fn probe_sequence_safety<D: Into<Option<Duration>>>(&self, timeout: D) -> Result<SequenceSafety> {
+        if let Some(safety) = self.sequence_safety.get() {
+            return Ok(safety);
+        }
+
+        let deadline = timeout.into().map(|timeout| Instant::now() + timeout);
+        let left = || deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+        let mut safety = SequenceSafety::default();
+        // the first error is returned once everything's been cleaned up
+        let mut failed = None;
+        let mut position = || match self.cursor_position(left()) {
+            Ok(at) => Some(at),
+            Err(Error::Timeout { .. }) => None,
+            Err(e) => {
+                failed = Some(e);
+                None
+            }
+        };
+
+        // whatever a terminal that doesn't read the sequences prints stays hidden until it's erased, as long as the
+        // terminal has synchronized output
+        let (begin, end) = (self.synchronized_update(true)?, self.synchronized_update(false)?);
+        let origin = position();
+        if origin.is_some() {
+            self.stdout
+                .lock()
+                .unwrap()
+                .write_all(&begin)
+                .context(ErrorKind::WriteFailed)?;
+        }
+        // the beginning might have been printed as well
+        let start = origin.and_then(|_| position());
+        let mut at = start;
+        for &family in &SequenceFamily::ALL {
+            let before = match at {
+                Some(before) => before,
+                None => break,
+            };
+            self.stdout
+                .lock()
+                .unwrap()
+                .write_all(family.probe())
+                .context(ErrorKind::WriteFailed)?;
+            let now = match position() {
+                Some(now) => now,
+                None => break,
+            };
+            let handling = if now == before {
+                Handling::Consumed
+            } else {
+                Handling::Echoed
+            };
+            safety.set(family, handling);
+            at = Some(now);
+        }
+
+        if let Some((x, y)) = origin {
+            self.move_to(x, y)?;
+            match (origin, at) {
+                (Some((_, top)), Some((_, bottom))) if bottom > top => {
+                    self.write_info_str_or(terminfo::ClrEos, b"\x1b[J")?
+                }
+                (origin, at) if origin != at => self.write_info_str_or(terminfo::ClrEol, b"\x1b[K")?,
+                _ => (),
+            }
+            // a terminal that printed the beginning would print the end too
+            if start == origin {
+                self.stdout
+                    .lock()
+                    .unwrap()
+                    .write_all(&end)
+                    .context(ErrorKind::WriteFailed)?;
+            }
+        }
+        self.flush();
+        self.err()?;
+        if let Some(e) = failed {
+            return Err(e);
+        }
+        // a family that timed out is probed again next time
+        if SequenceFamily::ALL.iter().all(|&family| safety.handling(family).is_some()) {
+            self.sequence_safety.set(Some(safety));
+        }
+        Ok(safety)
+    }
+
+    /// What `probe_sequence_safety` found, if it's been called.
+    pub fn sequence_safety(&self) -> Option<SequenceSafety> {
+        self.sequence_safety.get()
+    }
+
+    /// True unless `probe_sequence_safety` saw the terminal print `family`'s sequences.
+    fn sequence_safe(&self, family: SequenceFamily) -> bool {
+        self.sequence_safety
+            .get()
+            .is_none_or(|safety| safety.is_safe(family))
+    }
+
+    /// Ask the terminal for the value of one of its termcap or terminfo capabilities (XTGETTCAP), like `Co` or `RGB`,
+    /// waiting up to `timeout` for its reply. Booleans have an empty value.
+    ///
+    /// Fails with `Error::Unsupported` if the terminal says it doesn't have the capability, or `Error::Timeout` if it
+    /// doesn't know XTGETTCAP at all. Only xterm, kitty, foot and a few others do.
+    pub fn query_termcap<D: Into<Option<Duration>>>(&self, name: &str, timeout: D) -> Result<Vec<u8>> {
+        let mut request = Vec::new();
+        ansi::request_termcap(&mut request, name)?;
+        let (result, waited) = self.query(&request, timeout.into(), |r| query::match_termcap(r, name))?;
+        result.into_result("XTGETTCAP", waited)
+    }
+
+    /// True if the terminal reports that it supports the DEC private mode `mode`.
+    ///
+    /// If the terminal doesn't answer within `timeout` the mode is assumed to be unsupported.
+    pub fn supports_private_mode<D: Into<Option<Duration>>>(&self, mode: u32, timeout: D) -> bool {
+        self.query_private_mode(mode, timeout)
+            .map(|s| s.is_supported())
+            .unwrap_or(false)
+    }
+
+    /// True if the terminal supports synchronized output (mode 2026).
+    pub fn supports_synchronized_output<D: Into<Option<Duration>>>(&self, timeout: D) -> bool {
+        self.supports_private_mode(ansi::MODE_SYNCHRONIZED_OUTPUT, timeout)
+    }
+
+    /// True if the terminal supports bracketed paste (mode 2004).
+    pub fn supports_bracketed_paste<D: Into<Option<Duration>>>(&self, timeout: D) -> bool {
+        self.supports_private_mode(ansi::MODE_BRACKETED_PASTE, timeout)
+    }
+
+    /// True if the terminal supports the alternate screen (mode 1049).
+    ///
+    /// If the terminal doesn't answer the query, this falls back to checking for `smcup` in its terminfo entry.
+    pub fn supports_alt_screen<D: Into<Option<Duration>>>(&self, timeout: D) -> bool {
+        match self.query_private_mode(ansi::MODE_ALT_SCREEN, timeout) {
+            Ok(s) => s.is_supported(),
+            Err(_) => self.info.string(terminfo::EnterCaMode).is_some(),
+        }
+    }
+
+    pub(crate) fn set_err<T: Into<Error>>(&self, e: T) {
+        self.err.replace(Some(e.into()));
+    }
+
+    pub(crate) fn take_err(&self) -> Error {
+        self.err.replace(None).unwrap()
+    }
+
+    pub(crate) fn has_err(&self) -> bool {
+        self.err.borrow().is_some()
+    }
+
+    pub fn err(&self) -> Result<()> {
+        if self.has_err() {
+            Err(self.take_err())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Execute a string field
+    fn exec<'a>(&'a self, field: terminfo::StringField) -> Result<terminfo::lang::Executor<'a>> {
+        match self.info.exec(field) {
+            Some(v) => Ok(v),
+            None => Err(Capability::String(field).into()),
+        }
+    }
+
+    /// Wrapper around exec, which immediately runs the string with no args and writes it to `O`.
+    fn write_info_str(&self, field: terminfo::StringField) -> usize {
+        match self.exec(field) {
+            Ok(mut v) => v
+                .write(self.stdout.lock().unwrap().deref_mut())
                 .context(ErrorKind::FailedToRunTerminfo(field))
                 .unwrap_or_else(|e| {
                     self.set_err(e);
                     0
                 }),
             Err(e) => {
-                self.err.replace(Some(
-                    e.context(ErrorKind::FailedToRunTerminfo(field)).into(),
-                ));
+                self.err
+                    .replace(Some(e.context(ErrorKind::FailedToRunTerminfo(field))));
                 0
             }
         }
     }
 
-    pub fn settings(&self) -> Settings {
-        Settings {
-            termios: match termios::tcgetattr(self.as_raw_fd()) {
-                Ok(v) => v,
-                Err(e) => {
-                    // This should be caught on the next `update`;
-                    self.set_err(e.context(ErrorKind::FailedToSetTermios));
-                    unsafe { termios::Termios::default_uninit() }
-                }
-            },
-        }
+    pub fn settings(&self) -> Settings {
+        match self.backend.attributes() {
+            Ok(v) => v,
+            Err(e) => {
+                // This should be caught on the next `update`;
+                self.set_err(e.context(ErrorKind::FailedToSetTermios));
+                Settings {
+                    termios: unsafe { termios::Termios::default_uninit() },
+                }
+            }
+        }
+    }
+
+    pub fn update(&self, settings: Settings) -> Result<()> {
+        self.apply(settings, false)
+    }
+
+    /// Change the tty's settings like `update`, and with `keep_input` without throwing away what's been typed.
+    fn apply(&self, settings: Settings, keep_input: bool) -> Result<()> {
+        self.err()?;
+
+        if keep_input {
+            self.backend.set_attributes_keeping_input(&settings)
+        } else {
+            self.backend.set_attributes(&settings)
+        }
+        .context(ErrorKind::FailedToSetTermios)?;
+        self.translates_newlines
+            .set(Some(settings.translates_newlines()));
+        self.handles_xon_xoff.set(Some(settings.handles_xon_xoff()));
+        self.baud_rate.set(Some(settings.output_speed()));
+        Ok(())
+    }
+
+    /// True if the tty stops and starts output for XOFF and XON itself, see `Settings::handles_xon_xoff`.
+    ///
+    /// When the terminal isn't a tty nothing does, so this is false.
+    pub fn handles_xon_xoff(&self) -> bool {
+        if let Some(v) = self.handles_xon_xoff.get() {
+            return v;
+        }
+        let v = self
+            .backend
+            .attributes()
+            .map(|settings| settings.handles_xon_xoff())
+            .unwrap_or(false);
+        self.handles_xon_xoff.set(Some(v));
+        v
+    }
+
+    /// True if the tty turns `\n` into `\r\n` on output, see `Settings::translates_newlines`.
+    ///
+    /// When the terminal isn't a tty there's nothing to translate, so this is true.
+    pub fn translates_newlines(&self) -> bool {
+        if let Some(v) = self.translates_newlines.get() {
+            return v;
+        }
+        let v = self
+            .backend
+            .attributes()
+            .map(|settings| settings.translates_newlines())
+            .unwrap_or(true);
+        self.translates_newlines.set(Some(v));
+        v
+    }
+
+    /// What the terminal does when text reaches the right margin, from its terminfo entry.
+    pub fn wrap_behavior(&self) -> terminfo::wrap::WrapBehavior {
+        self.info.wrap_behavior()
+    }
+
+    /// The shortest sequence that switches the terminal from the style `from` to `to`, or nothing if they're the same.
+    ///
+    /// This is `ansi::Style::transition`, unless the terminfo entry's `sgr` (or `sgr0`, for going back to the default
+    /// style) is shorter. `sgr` doesn't say what it does to colors, italic or strike, so it's only tried when neither
+    /// style has any of them. An entry with `Smulx` turns the underline on and off with it, unless going back to the
+    /// default style turns it off anyway.
+    pub fn style_transition(&self, from: &ansi::Style, to: &ansi::Style) -> Vec<u8> {
+        if from.underline != to.underline && !to.is_plain() {
+            let arg = lang::Argument::Integer(i64::from(to.underline));
+            if let Some(Ok(smulx)) = self.info.ext_format(ExtCapability::Smulx, &[arg]) {
+                let rest = ansi::Style {
+                    underline: from.underline,
+                    ..*to
+                };
+                let mut seq = self.style_transition(from, &rest);
+                seq.extend_from_slice(&smulx);
+                return seq;
+            }
+        }
+        let ansi = from.transition(to);
+        if ansi.is_empty() || !only_sgr_attributes(from) || !only_sgr_attributes(to) {
+            return ansi;
+        }
+        let sgr = if to.is_plain() {
+            self.info.sgr0()
+        } else {
+            self.info.sgr(SgrAttrs {
+                bold: to.bold,
+                dim: to.dim,
+                underline: to.underline,
+                blink: to.blink,
+                reverse: to.invert,
+                invisible: to.invisible,
+                ..SgrAttrs::default()
+            })
+        };
+        match sgr.map(|sgr| without_delays(&sgr)) {
+            Ok(ref sgr) if sgr.len() < ansi.len() => sgr.clone(),
+            _ => ansi,
+        }
+    }
+
+    /// The tty's output baud rate, which decides how much padding delays need, see `Settings::output_speed`.
+    ///
+    /// When the terminal isn't a tty its speed is unknown, and this is 0.
+    pub fn baud_rate(&self) -> u32 {
+        if let Some(v) = self.baud_rate.get() {
+            return v;
+        }
+        let v = self
+            .backend
+            .attributes()
+            .map(|settings| settings.output_speed())
+            .unwrap_or(0);
+        self.baud_rate.set(Some(v));
+        v
+    }
+
+    pub fn flush(&self) {
+        match self.stdout.lock().unwrap().flush() {
+            Ok(_) => (),
+            Err(e) => self.set_err(e.context(ErrorKind::WriteFailed)),
+        }
+    }
+
+    pub fn read_keys<'a>(&'a self) -> Keys<'a, I, O> {
+        Keys::new(self)
+    }
+
+    /// Read keys like `read_keys`, interleaved with the other events `options` ask for, like ticks.
+    pub fn event_loop<'a>(&'a self, options: EventLoopOptions) -> EventLoop<'a, I, O> {
+        EventLoop::new(self, options)
+    }
+
+    /// Erase from the cursor to the end of the line, with `el` or else `CSI K` as the degradation policy allows.
+    pub fn clear_line_after_cursor(&self) {
+        if let Err(e) = self.write_info_str_or(terminfo::ClrEol, b"\x1b[K") {
+            self.set_err(e);
+        }
+    }
+
+    pub fn save_cursor(&self) {
+        self.write_info_str(terminfo::SaveCursor);
+    }
+
+    pub fn restore_cursor(&self) {
+        self.write_info_str(terminfo::RestoreCursor);
+    }
+
+    /// Expand the capability `field` once, then write it `n` times.
+    fn repeat_info_str(&self, field: terminfo::StringField, n: usize) -> Result<()> {
+        let bytes = self
+            .exec(field)?
+            .vec()
+            .context(ErrorKind::FailedToRunTerminfo(field))?;
+
+        let mut stdout = self.stdout.lock().unwrap();
+        for _ in 0..n {
+            stdout.write_all(&bytes).context(ErrorKind::WriteFailed)?;
+        }
+        Ok(())
+    }
+
+    /// Write the capability `field`, or if the terminal doesn't define it, `fallback` as the degradation policy
+    /// allows. An empty `fallback` means `field` is optional, and nothing is written whatever the policy.
+    fn write_info_str_or(&self, field: terminfo::StringField, fallback: &[u8]) -> Result<()> {
+        match self.exec(field) {
+            Ok(mut v) => {
+                v.write(self.stdout.lock().unwrap().deref_mut())
+                    .context(ErrorKind::FailedToRunTerminfo(field))?;
+            }
+            Err(_) if fallback.is_empty() => (),
+            Err(_) => self.degrade(field, b"", |w| {
+                w.write_all(fallback).context(ErrorKind::WriteFailed)
+            })?,
+        }
+        Ok(())
+    }
+
+    /// Stand in for the missing capability `field`, as the degradation policy says: fail, write the ANSI
+    /// sequence with `ansi`, or on a dumb terminal write `plain` (or nothing, if the policy is `Silent`).
+    fn degrade<F>(&self, field: terminfo::StringField, plain: &[u8], ansi: F) -> Result<()>
+    where
+        F: FnOnce(&mut Stdout<O>) -> Result<()>,
+    {
+        let mut stdout = self.stdout.lock().unwrap();
+        match self.degradation() {
+            Degradation::Strict => Err(Capability::String(field).into()),
+            _ if !self.is_dumb() => ansi(stdout.deref_mut()),
+            Degradation::Silent => Ok(()),
+            Degradation::BestEffort => stdout.write_all(plain).context(ErrorKind::WriteFailed),
+        }
+    }
+
+    /// Write the expanded capability `bytes`, carrying out its `$<ms>` delays as `terminfo::padding` decides instead
+    /// of writing them. `always` is whether the capability is `bel` or `flash`, and with `sleep` every delay is
+    /// slept, see `visual_bell`.
+    fn write_with_delays(&self, mut bytes: &[u8], always: bool, sleep: bool) -> Result<()> {
+        let rules = self.info.padding_rules();
+        let baud = self.baud_rate();
+        let options = self.exec_options();
+        let mut stdout = self.stdout.lock().unwrap();
+        while let Some((start, end, delay)) = find_delay(bytes) {
+            stdout
+                .write_all(&bytes[..start])
+                .context(ErrorKind::WriteFailed)?;
+            let mandatory = bytes[start..end].contains(&b'/');
+            let padding = if sleep {
+                Padding::Sleep(delay)
+            } else {
+                rules.decide(delay, baud, mandatory, always)
+            };
+            match padding {
+                Padding::Skip => (),
+                Padding::Send { byte, count } => {
+                    stdout
+                        .write_all(&vec![byte; count])
+                        .context(ErrorKind::WriteFailed)?;
+                }
+                Padding::Sleep(delay) => {
+                    stdout.flush().context(ErrorKind::WriteFailed)?;
+                    options.sleep(delay);
+                }
+            }
+            bytes = &bytes[end..];
+        }
+        stdout
+            .write_all(bytes)
+            .and_then(|_| stdout.flush())
+            .context(ErrorKind::WriteFailed)?;
+        Ok(())
+    }
+
+    /// Run the parameterized capability `parm` with `n` as its argument, or if the terminal doesn't support it,
+    /// write the single-shot capability `single` `n` times.
+    ///
+    /// There's no ANSI fallback, so when neither capability is available this fails if the degradation policy is
+    /// `Strict`, and does nothing otherwise.
+    fn write_parm_or_repeat(
+        &self,
+        parm: terminfo::StringField,
+        single: Option<terminfo::StringField>,
+        n: usize,
+    ) -> Result<()> {
+        if n == 0 {
+            return Ok(());
+        }
+
+        if let Ok(exe) = self.exec(parm) {
+            exe.arg(n)
+                .write(self.stdout.lock().unwrap().deref_mut())
+                .context(ErrorKind::FailedToRunTerminfo(parm))?;
+            return Ok(());
+        }
+
+        match single {
+            Some(field) if self.info.string(field).is_some() => self.repeat_info_str(field, n),
+            _ => self.degrade(parm, b"", |_| Ok(())),
+        }
+    }
+
+    /// Insert `n` blank lines at the cursor, pushing the lines below it down.
+    ///
+    /// Uses `il` when the terminal supports it, and repeats `il1` otherwise.
+    pub fn insert_lines(&self, n: usize) -> Result<()> {
+        self.write_parm_or_repeat(terminfo::ParmInsertLine, Some(terminfo::InsertLine), n)
+    }
+
+    /// Delete `n` lines starting at the cursor, pulling the lines below it up.
+    ///
+    /// Uses `dl` when the terminal supports it, and repeats `dl1` otherwise.
+    pub fn delete_lines(&self, n: usize) -> Result<()> {
+        self.write_parm_or_repeat(terminfo::ParmDeleteLine, Some(terminfo::DeleteLine), n)
+    }
+
+    /// Insert `n` blank characters at the cursor, shifting the rest of the line right.
+    ///
+    /// Uses `ich` when the terminal supports it, and repeats `ich1` otherwise.
+    pub fn insert_chars(&self, n: usize) -> Result<()> {
+        self.write_parm_or_repeat(terminfo::ParmIch, Some(terminfo::InsertCharacter), n)
+    }
+
+    /// Delete `n` characters at the cursor, shifting the rest of the line left.
+    ///
+    /// Uses `dch` when the terminal supports it, and repeats `dch1` otherwise.
+    pub fn delete_chars(&self, n: usize) -> Result<()> {
+        self.write_parm_or_repeat(terminfo::ParmDch, Some(terminfo::DeleteCharacter), n)
+    }
+
+    /// Erase `n` characters starting at the cursor, without moving the cursor or the rest of the line.
+    ///
+    /// `ech` has no single-shot equivalent, so this does nothing (or fails, see `Degradation`) if the terminal
+    /// doesn't support it.
+    pub fn erase_chars(&self, n: usize) -> Result<()> {
+        self.write_parm_or_repeat(terminfo::EraseChars, None, n)
+    }
+
+    /// Program function key `n` to send `sends` to the computer when it's pressed.
+    ///
+    /// Uses `pfx` when the terminal supports it, and `pfkey` (which sends it as if it had been typed) otherwise.
+    /// Without either this does nothing, or fails if the degradation policy is `Strict`.
+    pub fn program_function_key(&self, n: u8, sends: &str) -> Result<()> {
+        let field = match [terminfo::PkeyXmit, terminfo::PkeyKey]
+            .iter()
+            .cloned()
+            .find(|&field| self.info.string(field).is_some())
+        {
+            Some(field) => field,
+            None => return self.degrade(terminfo::PkeyXmit, b"", |_| Ok(())),
+        };
+        self.exec(field)?
+            .arg(i64::from(n))
+            .arg(sends)
+            .write(self.stdout.lock().unwrap().deref_mut())
+            .context(ErrorKind::FailedToRunTerminfo(field))?;
+        Ok(())
+    }
+
+    /// Set a hardware tab stop at the cursor's current column.
+    pub fn set_tab_stop(&self) -> Result<()> {
+        self.write_info_str_or(terminfo::SetTab, ansi::SET_TAB_STOP)
+    }
+
+    /// Clear the tab stop at the cursor's current column, if there is one.
+    pub fn clear_tab_stop(&self) -> Result<()> {
+        self.stdout
+            .lock()
+            .unwrap()
+            .write_all(ansi::CLEAR_TAB_STOP)
+            .context(ErrorKind::WriteFailed)?;
+        Ok(())
+    }
+
+    /// Clear every tab stop on the screen.
+    pub fn clear_all_tabs(&self) -> Result<()> {
+        self.write_info_str_or(terminfo::ClearAllTabs, ansi::CLEAR_ALL_TAB_STOPS)
+    }
+
+    /// Move the cursor forward `n` tab stops.
+    ///
+    /// Repeats `ht` when the terminal defines it, otherwise falls back to `CSI n I`.
+    pub fn forward_tabs(&self, n: usize) -> Result<()> {
+        if n == 0 {
+            return Ok(());
+        }
+
+        if self.info.string(terminfo::Tab).is_some() {
+            self.repeat_info_str(terminfo::Tab, n)
+        } else {
+            self.degrade(terminfo::Tab, &b"\t".repeat(n), |w| {
+                ansi::cursor_forward_tabs(w, n)
+            })
+        }
+    }
+
+    /// Move the cursor back `n` tab stops.
+    ///
+    /// Repeats `cbt` when the terminal defines it, otherwise falls back to `CSI n Z`.
+    pub fn backward_tabs(&self, n: usize) -> Result<()> {
+        if n == 0 {
+            return Ok(());
+        }
+
+        if self.info.string(terminfo::BackTab).is_some() {
+            self.repeat_info_str(terminfo::BackTab, n)
+        } else {
+            self.degrade(terminfo::BackTab, b"", |w| ansi::cursor_backward_tabs(w, n))
+        }
+    }
+
+    /// Write the character `c` `n` times.
+    ///
+    /// If the terminal supports `rep` and using it is shorter than writing every cell, only the character and
+    /// a count are sent. Otherwise the characters are written out in a single buffer.
+    pub fn repeat_char(&self, c: char, n: usize) -> Result<()> {
+        if n == 0 {
+            return Ok(());
+        }
+
+        // `rep` is only defined for single byte characters
+        if c.is_ascii() {
+            if let Ok(mut exe) = self.exec(terminfo::RepeatChar).map(|e| e.arg(c).arg(n)) {
+                let rep = exe
+                    .vec()
+                    .context(ErrorKind::FailedToRunTerminfo(terminfo::RepeatChar))?;
+                if rep.len() < n {
+                    self.stdout
+                        .lock()
+                        .unwrap()
+                        .write_all(&rep)
+                        .context(ErrorKind::WriteFailed)?;
+                    return Ok(());
+                }
+            }
+        }
+
+        let mut utf8 = [0u8; 4];
+        let utf8 = c.encode_utf8(&mut utf8).as_bytes();
+        let mut buf = Vec::with_capacity(utf8.len() * n);
+        for _ in 0..n {
+            buf.extend_from_slice(utf8);
+        }
+
+        self.stdout
+            .lock()
+            .unwrap()
+            .write_all(&buf)
+            .context(ErrorKind::WriteFailed)?;
+        Ok(())
+    }
+
+    /// Move the cursor to column `x` (counted from 0) without changing its row.
+    ///
+    /// Uses `hpa` when the terminal supports it. Otherwise it asks the terminal which row the cursor is on (see
+    /// `cursor_position`) and uses `cup`, failing if there's no answer. Without `cup` either it sends the ANSI `CHA`
+    /// sequence.
+    pub fn move_col(&self, x: usize) -> Result<()> {
+        match self.exec(terminfo::ColumnAddress) {
+            Ok(exe) => {
+                exe.arg(x)
+                    .write(self.stdout.lock().unwrap().deref_mut())
+                    .context(ErrorKind::FailedToRunTerminfo(terminfo::ColumnAddress))?;
+                Ok(())
+            }
+            Err(_) if self.info.string(terminfo::CursorAddress).is_some() => {
+                let (_, y) = self.cursor_position(None)?;
+                self.move_to(x, y)
+            }
+            Err(_) => {
+                let plain = [&b"\r"[..], &b" ".repeat(x)].concat();
+                self.degrade(terminfo::ColumnAddress, &plain, |w| {
+                    ansi::cursor_set_column(w, x)
+                })
+            }
+        }
+    }
+
+    /// Move the cursor to row `y` (counted from 0) without changing its column.
+    ///
+    /// Uses `vpa` when the terminal supports it. Otherwise it asks the terminal which column the cursor is in (see
+    /// `cursor_position`) and uses `cup`, failing if there's no answer. Without `cup` either it sends the ANSI `VPA`
+    /// sequence.
+    pub fn move_row(&self, y: usize) -> Result<()> {
+        match self.exec(terminfo::RowAddress) {
+            Ok(exe) => {
+                exe.arg(y)
+                    .write(self.stdout.lock().unwrap().deref_mut())
+                    .context(ErrorKind::FailedToRunTerminfo(terminfo::RowAddress))?;
+                Ok(())
+            }
+            Err(_) if self.info.string(terminfo::CursorAddress).is_some() => {
+                let (x, _) = self.cursor_position(None)?;
+                self.move_to(x, y)
+            }
+            Err(_) => self.degrade(terminfo::RowAddress, b"\n", |w| ansi::cursor_row(w, y)),
+        }
+    }
+
+    /// Move the cursor to column `x` and row `y`, both counted from 0.
+    ///
+    /// Uses `cup` when the terminal supports it, and falls back to the ANSI `CUP` sequence otherwise.
+    pub fn move_to(&self, x: usize, y: usize) -> Result<()> {
+        match self.exec(terminfo::CursorAddress) {
+            Ok(exe) => {
+                exe.arg(y)
+                    .arg(x)
+                    .write(self.stdout.lock().unwrap().deref_mut())
+                    .context(ErrorKind::FailedToRunTerminfo(terminfo::CursorAddress))?;
+                Ok(())
+            }
+            Err(_) => {
+                let plain = [&b"\r\n"[..], &b" ".repeat(x)].concat();
+                self.degrade(terminfo::CursorAddress, &plain, |w| {
+                    ansi::cursor_move(w, x, y)
+                })
+            }
+        }
+    }
+
+    /// Expand any string capability with `args`, and write it, carrying out its `$<ms>` delays as
+    /// `terminfo::padding` describes.
+    ///
+    /// In debug builds `args` are checked against what the capability expects first, so calling `cup` with one
+    /// argument fails with `terminfo::ErrorKind::ArityMismatch` rather than moving the cursor somewhere odd. See
+    /// `terminfo::check_arguments`.
+    pub fn write_cap(&self, field: terminfo::StringField, args: &[terminfo::lang::Argument]) -> Result<()> {
+        let bytes = self
+            .info
+            .format(field, args)
+            .ok_or(Capability::String(field))?
+            .context(ErrorKind::FailedToRunTerminfo(field))?;
+        self.write_with_delays(&bytes, field == terminfo::Bell || field == terminfo::FlashScreen, false)
+    }
+
+    /// Only scroll rows `top` to `bottom`, both counted from 0 and both included, so `(0, rows - 1)` is the whole
+    /// screen again. Most terminals move the cursor to the top left corner afterwards.
+    ///
+    /// Uses `csr` when the terminal supports it, and falls back to the ANSI `DECSTBM` sequence otherwise.
+    pub fn set_scroll_region(&self, top: usize, bottom: usize) -> Result<()> {
+        match self.exec(terminfo::ChangeScrollRegion) {
+            Ok(exe) => {
+                exe.arg(top)
+                    .arg(bottom)
+                    .write(self.stdout.lock().unwrap().deref_mut())
+                    .context(ErrorKind::FailedToRunTerminfo(terminfo::ChangeScrollRegion))?;
+                Ok(())
+            }
+            Err(_) => self.degrade(terminfo::ChangeScrollRegion, b"", |w| {
+                ansi::set_scroll_region(w, top, bottom)
+            }),
+        }
+    }
+
+    /// Erase the whole screen and move the cursor to the top left corner.
+    ///
+    /// Uses `clear` when the terminal supports it, and falls back to the ANSI `CUP` and `ED` sequences otherwise.
+    /// On dumb terminals the screen is scrolled away with a newline for each row instead.
+    pub fn clear_screen(&self) -> Result<()> {
+        if self.exec(terminfo::ClearScreen).is_ok() {
+            return self.write_info_str_or(terminfo::ClearScreen, ansi::CLEAR_SCREEN);
+        }
+        let rows = self.size().map(|(_, rows)| rows).unwrap_or(24);
+        self.degrade(terminfo::ClearScreen, &b"\n".repeat(rows), |w| {
+            w.write_all(ansi::CLEAR_SCREEN).context(ErrorKind::WriteFailed)
+        })
+    }
+
+    /// Get the size of the terminal, as (columns, rows).
+    ///
+    /// `$COLUMNS` and `$LINES` come first (see `EnvOverrides`), then what the tty says, and if the output isn't a
+    /// tty (or the tty doesn't know) the terminfo entry's `cols` and `lines`.
+    pub fn size(&self) -> Result<(usize, usize)> {
+        let res = self.backend.size();
+        let tty = *res.as_ref().unwrap_or(&(0, 0));
+        let env = self.env.borrow();
+        let pick = |env: Option<usize>, tty: usize, field| {
+            env.or_else(|| Some(tty).filter(|&n| n > 0)).or_else(|| {
+                self.info
+                    .number(field)
+                    .map(|n| n as usize)
+                    .filter(|&n| n > 0)
+            })
+        };
+
+        match (
+            pick(env.columns(), tty.0, terminfo::Columns),
+            pick(env.lines(), tty.1, terminfo::Lines),
+        ) {
+            (Some(cols), Some(rows)) => Ok((cols, rows)),
+            // a tty that says it's 0x0 doesn't know its size either
+            _ => Err(res
+                .err()
+                .unwrap_or_else(|| io::Error::from_raw_os_error(libc::EINVAL))
+                .context(ErrorKind::FailedToGetSize)),
+        }
+    }
+
+    /// Change the palette entry `index` to `rgb`.
+    ///
+    /// This uses `initc` if the terminal says it can change its colors (`ccc`), and `OSC 4` otherwise.
+    pub fn set_palette_color(&self, index: u8, rgb: ansi::Rgb) -> Result<()> {
+        let initc = if self.info.boolean(terminfo::CanChange) {
+            self.info.exec(terminfo::InitializeColor)
+        } else {
+            None
+        };
+
+        let mut stdout = self.stdout.lock().unwrap();
+        match initc {
+            Some(exe) => {
+                exe.arg(u32::from(index))
+                    .arg(initc_scale(rgb.r))
+                    .arg(initc_scale(rgb.g))
+                    .arg(initc_scale(rgb.b))
+                    .write(stdout.deref_mut())
+                    .context(ErrorKind::FailedToRunTerminfo(terminfo::InitializeColor))?;
+            }
+            None => {
+                let mut seq = String::new();
+                xterm::map_color(&mut seq, index, rgb.into())?;
+                stdout.write_all(seq.as_bytes()).context(ErrorKind::WriteFailed)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Reset the whole palette to the terminal's defaults, with `oc` or `OSC 104`.
+    pub fn reset_palette(&self) -> Result<()> {
+        let mut seq = String::new();
+        xterm::reset_colors(&mut seq)?;
+        self.write_info_str_or(terminfo::OrigColors, seq.as_bytes())
+    }
+
+    /// Change the underline's style, with the entry's `Smulx` if it has one and kitty's `CSI 4 : <style> m`
+    /// otherwise.
+    pub fn set_underline(&self, u: xterm::kitty::Underline) -> Result<()> {
+        let arg = lang::Argument::Integer(i64::from(u.code()));
+        let seq = match self.info.ext_format(ExtCapability::Smulx, &[arg]) {
+            Some(seq) => seq.context(ErrorKind::FailedToRunExtended(ExtCapability::Smulx))?,
+            None => {
+                let mut seq = String::new();
+                xterm::kitty::set_underline(&mut seq, u)?;
+                seq.into_bytes()
+            }
+        };
+        self.stdout
+            .lock()
+            .unwrap()
+            .write_all(&seq)
+            .context(ErrorKind::WriteFailed)
+    }
+
+    /// Change the underline's color, with the entry's `Setulc` if it has one and kitty's `CSI 58 : ... m` otherwise.
+    /// `Setulc` only takes RGB colors, so a palette color always uses kitty's sequence.
+    pub fn set_underline_color<T: Into<ansi::Color>>(&self, color: T) -> Result<()> {
+        let color = color.into();
+        let setulc = match color {
+            ansi::Color::Rgb(r, g, b) => {
+                let rgb = i64::from(r) << 16 | i64::from(g) << 8 | i64::from(b);
+                self.info
+                    .ext_format(ExtCapability::Setulc, &[lang::Argument::Integer(rgb)])
+            }
+            ansi::Color::Index(_) => None,
+        };
+        let seq = match setulc {
+            Some(seq) => seq.context(ErrorKind::FailedToRunExtended(ExtCapability::Setulc))?,
+            None => {
+                let mut seq = String::new();
+                xterm::kitty::set_underline_color(&mut seq, color)?;
+                seq.into_bytes()
+            }
+        };
+        self.stdout
+            .lock()
+            .unwrap()
+            .write_all(&seq)
+            .context(ErrorKind::WriteFailed)
+    }
+
+    /// Ask the terminal for the color of palette entry `index`, waiting up to `timeout` for its reply.
+    pub fn query_palette_color<D: Into<Option<Duration>>>(&self, index: u8, timeout: D) -> Result<ansi::Rgb> {
+        let mut request = String::new();
+        xterm::query_color(&mut request, index)?;
+        let (result, waited) = self.query(request.as_bytes(), timeout.into(), |r| {
+            query::match_palette_color(r, index)
+        })?;
+        result.into_result("OSC 4", waited)
+    }
+
+    /// Draw `image` with its top left corner in the cell at `at` (row, column).
+    ///
+    /// The image is sent using the kitty graphics protocol if the terminal (as found by the last `identify`, or
+    /// guessed from the environment) is known to support it, or as sixels if it listed sixel graphics in its
+    /// device attributes. Otherwise this fails with `ImagesNotSupported`.
+    pub fn draw_image(&self, image: &ansi::Image, at: (usize, usize)) -> Result<()> {
+        let identity = self.identity();
+        let seq = if identity.family.supports_kitty_graphics() {
+            let mut writer = ImageWriter::new(Vec::new());
+            writer.display(image, None, &Placement::default())?;
+            writer.into_inner()
+        } else if identity.hints_sixel() {
+            ansi::sixel::encode(image, ansi::sixel::MAX_COLORS)
+        } else {
+            return Err(Capability::Images.into());
+        };
+
+        self.move_to(at.1, at.0)?;
+        self.write_to_host(&seq)
+    }
+
+    /// Draw a horizontal line `n` cells long, starting at the cursor.
+    pub fn hline(&self, n: usize) -> Result<()> {
+        let acs = self.acs();
+        if acs.supports(AcsChar::HLine) {
+            self.write_with_acs(&acs, |acs| self.repeat_char(acs.get(AcsChar::HLine), n))?
+        } else {
+            // in the alternate character set the ASCII would be drawn as something else
+            self.repeat_char(AcsChar::HLine.ascii(), n)
+        }
+    }
+
+    /// Get this terminal's line-drawing characters.
+    ///
+    /// If the user's locale is UTF-8 the characters will be Unicode box drawing characters, otherwise they have
+    /// to be written inside the alternate character set (see `Term::with_acs`).
+    pub fn acs(&self) -> Acs {
+        Acs::new(&self.info)
+    }
+
+    /// Run `f` with the terminal switched to its alternate character set, so the characters returned by
+    /// `Acs::get` draw lines. When the terminal is in UTF-8 mode `f` is just called directly.
+    ///
+    /// The ASCII `Acs::get` returns for a character the terminal has no mapping for (see `Acs::supports`) has to be
+    /// written outside of `f`, since the alternate character set would draw it as something else.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use nixterm::acs::AcsChar;
+    /// use nixterm::term::Term;
+    ///
+    /// let term = Term::new().unwrap();
+    /// term.with_acs(|acs| {
+    ///     let line: String = (0..10).map(|_| acs.get(AcsChar::HLine)).collect();
+    ///     term.print(line).unwrap();
+    /// }).unwrap();
+    /// ```
+    pub fn with_acs<F, T>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce(&Acs) -> T,
+    {
+        self.write_with_acs(&self.acs(), f)
+    }
+
+    fn write_with_acs<F, T>(&self, acs: &Acs, f: F) -> Result<T>
+    where
+        F: FnOnce(&Acs) -> T,
+    {
+        if acs.is_utf8() {
+            return Ok(f(acs));
+        }
+
+        if !self.acs_enabled.get() {
+            if self.info.string(terminfo::EnaAcs).is_some() {
+                self.write_info_str_or(terminfo::EnaAcs, b"")?;
+            }
+            self.acs_enabled.set(true);
+        }
+
+        self.write_info_str_or(terminfo::EnterAltCharsetMode, b"")?;
+        let v = f(acs);
+        self.write_info_str_or(terminfo::ExitAltCharsetMode, b"")?;
+        Ok(v)
+    }
+
+    pub fn prompt<T: AsRef<str>>(&self, prompt: T) -> Result<String> {
+        self.writer().print(prompt).done()?;
+        self.readline()
+    }
+
+    #[inline]
+    pub fn colors(&self) -> usize {
+        // There has to be at least two colors... right???
+        self.env
+            .borrow()
+            .max_colors(self.info.number(terminfo::MaxColors))
+            .unwrap_or(2) as usize
+    }
+}
+
+impl<'a, I, O> Drop for FrameBatch<'a, I, O>
+where
+    I: io::Read + AsRawFd,
+    O: io::Write + AsRawFd,
+{
+    fn drop(&mut self) {
+        if let Some(ref end) = self.end {
+            let mut stdout = self.term.stdout.lock().unwrap();
+            let frame = stdout.end_batch();
+            let sent = stdout.submit(&[&frame, end]);
+            drop(stdout);
+            if let Err(e) = sent {
+                self.term.set_err(e.context(ErrorKind::WriteFailed));
+            }
+        }
+    }
+}
+
+impl<'a, I, O> Drop for ModeGuard<'a, I, O>
+where
+    I: io::Read + AsRawFd,
+    O: io::Write + AsRawFd,
+{
+    fn drop(&mut self) {
+        if let Err(e) = self.term.write_info_str_or(self.restore, self.fallback) {
+            self.term.set_err(e);
+        }
+        self.term.flush();
+    }
+}
+
+impl SessionOptions {
+    pub fn new() -> SessionOptions {
+        SessionOptions::default()
+    }
+
+    /// Put the terminal in raw mode, see `Settings::raw`.
+    pub fn raw(mut self, v: bool) -> Self {
+        self.raw = v;
+        self
+    }
+
+    /// Switch to the alternate screen (`smcup`), so the shell's screen comes back afterwards.
+    pub fn alternate_screen(mut self, v: bool) -> Self {
+        self.alternate_screen = v;
+        self
+    }
+
+    /// Hide the cursor (`civis`).
+    pub fn hide_cursor(mut self, v: bool) -> Self {
+        self.hide_cursor = v;
+        self
+    }
+
+    /// Turn on keypad transmit mode (`smkx`).
+    ///
+    /// Most terminfo entries describe the arrow and function keys as they're sent in this mode, so turn it on
+    /// if keys are going to be looked up in terminfo.
+    pub fn keypad_transmit(mut self, v: bool) -> Self {
+        self.keypad_transmit = v;
+        self
+    }
+}
+
+impl ExecOptions {
+    /// Sleep with `thread::sleep`, for at most `MAX_SLEEP` at a time.
+    pub fn new() -> ExecOptions {
+        ExecOptions {
+            sleep: Arc::new(thread::sleep),
+            max_sleep: MAX_SLEEP,
+        }
+    }
+
+    /// Wait with `sleep` rather than `thread::sleep`, like a test that records the delays instead of waiting.
+    pub fn sleep_fn<F: Fn(Duration) + Send + Sync + 'static>(mut self, sleep: F) -> ExecOptions {
+        self.sleep = Arc::new(sleep);
+        self
+    }
+
+    /// Never wait longer than `max` for one delay. An entry asking for seconds is more likely broken than slow.
+    pub fn max_sleep(mut self, max: Duration) -> ExecOptions {
+        self.max_sleep = max;
+        self
+    }
+
+    /// Wait for `delay`, or `max_sleep` if that's shorter.
+    pub fn sleep(&self, delay: Duration) {
+        (self.sleep)(delay.min(self.max_sleep));
+    }
+}
+
+impl Default for ExecOptions {
+    fn default() -> ExecOptions {
+        ExecOptions::new()
+    }
+}
+
+impl<'a, I, O> StatusLine<'a, I, O>
+where
+    I: io::Read + AsRawFd,
+    O: io::Write + AsRawFd,
+{
+    /// The number of columns in the status line (`wsl`), if the terminal says.
+    pub fn width(&self) -> Option<usize> {
+        self.term
+            .info
+            .number(terminfo::WidthStatusLine)
+            .map(|n| n as usize)
+    }
+
+    /// Replace the status line's text with `text`.
+    ///
+    /// The text is cut down to `width` columns, and unless the terminal allows escape sequences in the status line
+    /// (`eslok`), they're stripped out. `tsl` saves the cursor and `fsl` puts it back, so the cursor ends up where
+    /// it started.
+    pub fn set(&mut self, text: &str) -> Result<()> {
+        let text = if self.term.info.boolean(terminfo::StatusLineEscOk) {
+            text.to_owned()
+        } else {
+            String::from_utf8_lossy(&ansi::strip(text.as_bytes()))
+                .chars()
+                .filter(|c| !c.is_control())
+                .collect()
+        };
+        let text = match self.width() {
+            Some(width) => ansi::truncate_to_width(&text, width),
+            None => &text,
+        };
+
+        let mut stdout = self.term.stdout.lock().unwrap();
+        // tsl's parameter is the column to start at
+        self.term
+            .exec(terminfo::ToStatusLine)?
+            .arg(0)
+            .write(stdout.deref_mut())
+            .context(ErrorKind::FailedToRunTerminfo(terminfo::ToStatusLine))?;
+        stdout
+            .write_all(text.as_bytes())
+            .context(ErrorKind::WriteFailed)?;
+        self.term
+            .exec(terminfo::FromStatusLine)?
+            .write(stdout.deref_mut())
+            .context(ErrorKind::FailedToRunTerminfo(terminfo::FromStatusLine))?;
+        stdout.flush().context(ErrorKind::WriteFailed)?;
+        Ok(())
+    }
+
+    /// Clear the status line with `dsl`, or by setting it to an empty string if the terminal doesn't have `dsl`.
+    pub fn clear(&mut self) -> Result<()> {
+        if self.term.info.string(terminfo::DisStatusLine).is_none() {
+            return self.set("");
+        }
+        self.term.write_info_str_or(terminfo::DisStatusLine, b"")?;
+        self.term.flush();
+        self.term.err()
+    }
+}
+
+impl<'a, I, O> Viewport<'a, I, O>
+where
+    I: io::Read + AsRawFd,
+    O: io::Write + AsRawFd,
+{
+    /// The viewport's size, as (columns, rows).
+    pub fn size(&self) -> (usize, usize) {
+        (self.size.0, self.height)
+    }
+
+    /// The screen row the viewport starts on.
+    pub fn top(&self) -> usize {
+        self.top
+    }
+
+    /// Move the cursor to column `x` and row `y` of the viewport, both counted from 0. Rows below the viewport are
+    /// `InvalidInput`.
+    pub fn move_to(&mut self, x: usize, y: usize) -> Result<()> {
+        self.fit()?;
+        if y >= self.height {
+            return Err(Error::InvalidInput("the row is below the viewport"));
+        }
+        self.term.move_to(x, self.top + y)
+    }
+
+    /// Erase the viewport, leaving the cursor in its top left corner.
+    pub fn clear(&mut self) -> Result<()> {
+        self.fit()?;
+        for y in (0..self.height).rev() {
+            self.term.move_to(0, self.top + y)?;
+            self.term.write_info_str_or(terminfo::ClrEol, b"\x1b[K")?;
+        }
+        self.term.flush();
+        Ok(())
+    }
+
+    /// Lay the viewport out again if the screen's size changed.
+    fn fit(&mut self) -> Result<()> {
+        let size = self.term.size()?;
+        if size != self.size {
+            let rows = size.1;
+            self.height = self.requested.min(rows);
+            self.top = self.top.min(rows - self.height);
+            self.cursor.1 = self.cursor.1.min(rows.saturating_sub(1));
+            self.size = size;
+        }
+        Ok(())
+    }
+}
+
+impl<'a, I, O> StyledWriter<'a, I, O>
+where
+    I: io::Read + AsRawFd,
+    O: io::Write + AsRawFd,
+{
+    pub fn style(&self) -> ansi::Style {
+        self.style
+    }
+
+    /// Write everything after this in `style`.
+    pub fn set_style(&mut self, style: ansi::Style) {
+        self.style = style;
+    }
+
+    /// Go back to the terminal's default style.
+    pub fn reset_style(&mut self) {
+        self.style = ansi::Style::default();
+    }
+
+    /// Replace the control characters in everything written after this as `policy` says, see `ansi::sanitize`. By
+    /// default text is written as it is.
+    pub fn set_sanitize(&mut self, policy: Option<ansi::SanitizePolicy>) {
+        self.sanitize = policy;
+    }
+
+    /// Work around the output glitches in the terminal's entry, or don't. This is on by default.
+    pub fn set_quirks(&mut self, enabled: bool) {
+        self.quirks = if enabled {
+            OutputQuirks::new(&self.term.info)
+        } else {
+            OutputQuirks::default()
+        };
+    }
+
+    /// Switch the terminal into `self.style`, as far as the color depth allows, if it isn't already.
+    fn sync_style(&mut self, stdout: &mut Stdout<O>) -> io::Result<()> {
+        let mut style = self.style.downsample(self.term.color_depth());
+        self.overstrike = self.quirks.overstrike && style.underline;
+        if self.overstrike {
+            style.underline = false;
+        }
+        let transition = self.term.style_transition(&self.current, &style);
+        if !transition.is_empty() {
+            self.after_space = false;
+        }
+        stdout.write_all(&transition)?;
+        self.current = style;
+        Ok(())
+    }
+
+    /// `text` with the terminal's output glitches worked around.
+    fn apply_quirks(&mut self, text: &[u8]) -> Vec<u8> {
+        let restyle = if self.quirks.standout && self.current.invert {
+            self.term.style_transition(&ansi::Style::default(), &self.current)
+        } else {
+            Vec::new()
+        };
+        let mut out = Vec::with_capacity(text.len());
+        for &c in text {
+            if self.after_space && c != b' ' {
+                out.extend_from_slice(&restyle);
+            }
+            self.after_space = c == b' ';
+            let c = if self.quirks.tilde && c == b'~' { b'-' } else { c };
+            if self.overstrike && c == b' ' {
+                out.push(b'_');
+            } else if self.overstrike && (c.is_ascii_graphic() || c >= 0xc0) {
+                // `c` is a character, or the first byte of one
+                out.extend_from_slice(&[b'_', b'\x08', c]);
+            } else {
+                out.push(c);
+            }
+        }
+        out
+    }
+}
+
+impl<'a, I, O> Renderer<'a, I, O>
+where
+    I: io::Read + AsRawFd,
+    O: io::Write + AsRawFd,
+{
+    /// Draw the next text at column `x` and row `y`, both counted from 0.
+    ///
+    /// The cursor is only moved when something is drawn, and only if it isn't already there.
+    #[inline]
+    pub fn move_to(&mut self, x: usize, y: usize) {
+        self.target = (x, y);
+    }
+
+    /// Draw the next text in `style`, as far as the color depth allows.
+    #[inline]
+    pub fn set_style(&mut self, style: ansi::Style) {
+        self.style = style.downsample(self.term.color_depth());
+    }
+
+    /// Draw `s`, which shouldn't go past the end of the line, and move along by its display width, a grapheme
+    /// cluster at a time (see `ansi::graphemes`).
+    ///
+    /// Only the characters that differ from what's already on the screen are sent.
+    #[inline]
+    pub fn put_str(&mut self, s: &str) -> Result<()> {
+        let mut result = Ok(());
+        ansi::clusters(s, 0, |start, end, width| {
+            result = self.put_cluster(&s[start..end], width);
+            result.is_ok()
+        });
+        result
+    }
+
+    /// Draw `c` `n` times, like `put_str` with it repeated.
+    ///
+    /// When it's shorter, that's done with `rep`, or for spaces in the default style with `ech` (which leaves the
+    /// cursor where it was). Otherwise the characters are sent together, so a line of line-drawing characters only
+    /// switches to the alternate character set once.
+    pub fn repeat(&mut self, c: char, n: usize) -> Result<()> {
+        let width = ansi::char_width(c);
+        if n == 0 || width == 0 {
+            return Ok(());
+        }
+
+        let (x, y) = self.target;
+        let info = &self.term.info;
+        let program = |field| {
+            info.string_bytes(field)
+                .and_then(|s| lang::Program::compile(s).ok())
+        };
+        let erase_args = [lang::Argument::from(n)];
+        let erase = program(terminfo::EraseChars)
+            .filter(|_| c == ' ' && self.style == ansi::Style::default());
+        // after erasing, the cursor has to be moved past what was erased
+        let skip = self
+            .moves
+            .movement_cost(Some((x, y)), (x + n, y))
+            .unwrap_or(n);
+        let erase = erase.filter(|ech| ech.cost(&erase_args).saturating_add(skip) < n);
+        let rep_args = [lang::Argument::from(c), lang::Argument::from(n)];
+        let rep = program(terminfo::RepeatChar)
+            .filter(|rep| (c == ' ' || c.is_ascii_graphic()) && rep.cost(&rep_args) < n);
+
+        self.start_drawing()?;
+        if let Some(ech) = erase {
+            ech.exec_append(&erase_args, &mut self.buffer)
+                .context(ErrorKind::FailedToRunTerminfo(terminfo::EraseChars))?;
+        } else {
+            match rep {
+                Some(rep) => rep
+                    .exec_append(&rep_args, &mut self.buffer)
+                    .context(ErrorKind::FailedToRunTerminfo(terminfo::RepeatChar))?,
+                None => {
+                    let text: String = ::std::iter::repeat_n(c, n).collect();
+                    let encoded = self.encode(&text);
+                    self.buffer.extend_from_slice(&encoded);
+                }
+            }
+            self.cursor = match self.size {
+                Some(size) => self.moves.after_text((x, y), n * width, size),
+                None => Some((x + n * width, y)),
+            };
+        }
+
+        let mut buf = [0u8; 4];
+        let text = c.encode_utf8(&mut buf);
+        for i in 0..n {
+            self.target = (x + i * width, y);
+            self.set_cells(text, width);
+        }
+        self.target = (x + n * width, y);
+        Ok(())
+    }
+
+    /// True if the terminal can insert characters, so `insert_str` works.
+    pub fn can_insert(&self) -> bool {
+        self.insertion(1).is_some()
+    }
+
+    /// Draw `s` like `put_str`, but push what's after it on the row to the right, rather than drawing over it.
+    /// Whatever's pushed past the last column is lost.
+    ///
+    /// Uses insert mode (`smir` and `rmir`) when the terminal has it, then `ich`, then `ich1`. If it has none of
+    /// them nothing is drawn, and this fails with `Error::Unsupported`.
+    pub fn insert_str(&mut self, s: &str) -> Result<()> {
+        let width = ansi::display_width(s);
+        if width == 0 {
+            return Ok(());
+        }
+        let (before, after) = self
+            .insertion(width)
+            .ok_or(Capability::String(terminfo::ParmIch))?;
+        if self.cursor != Some(self.target) {
+            self.write_move()?;
+            self.cursor = Some(self.target);
+        }
+
+        let (x, y) = self.target;
+        if let Some(row) = self.cells.get_mut(y).filter(|row| row.len() > x) {
+            for _ in 0..width {
+                row.insert(x, RenderedCell::default());
+            }
+            if let Some((cols, _)) = self.size {
+                row.truncate(cols);
+            }
+        }
+        self.buffer.extend_from_slice(&before);
+        self.put_str(s)?;
+        self.buffer.extend_from_slice(after);
+        Ok(())
+    }
+
+    /// Scroll rows `top` to `bottom` (counted from 0, both included) up by `n` rows, or down by `-n`, leaving the
+    /// rows that come in blank and the rest of the screen as it is. Redrawing a log view that's grown a line is then
+    /// just the new line.
+    ///
+    /// Uses `csr` with `ind` (or `indn`) to scroll up and `ri` (or `rin`) to scroll down when the terminal has them,
+    /// and `dl` and `il` (or `dl1` and `il1`) otherwise. If it has neither, or `n` would scroll the whole region
+    /// away, nothing is sent and this returns false. The cursor ends up somewhere unknown, and what the renderer
+    /// remembers drawing in the region is forgotten.
+    pub fn scroll(&mut self, top: usize, bottom: usize, n: isize) -> Result<bool> {
+        let count = n.unsigned_abs();
+        let rows = match self.size {
+            Some((_, rows)) if bottom < rows => rows,
+            _ => return Ok(false),
+        };
+        if count == 0 || top > bottom || count > bottom - top {
+            return Ok(false);
+        }
+
+        let bytes = match self.scroll_bytes(top, bottom, n, rows) {
+            Some(bytes) => bytes,
+            None => return Ok(false),
+        };
+        // the lines that come in take the current background on some terminals
+        if self.current != ansi::Style::default() {
+            let transition = self.term.style_transition(&self.current, &ansi::Style::default());
+            self.buffer.extend_from_slice(&transition);
+            self.current = ansi::Style::default();
+        }
+        self.buffer.extend_from_slice(&bytes);
+        self.cursor = None;
+        for row in self.cells.iter_mut().take(bottom + 1).skip(top) {
+            row.clear();
+        }
+        Ok(true)
+    }
+
+    /// What `scroll` sends, if the terminal can do it.
+    fn scroll_bytes(&self, top: usize, bottom: usize, n: isize, rows: usize) -> Option<Vec<u8>> {
+        let info = &self.term.info;
+        let count = n.unsigned_abs();
+        let expand = |parm, single, count: usize| -> Option<Vec<u8>> {
+            match info.exec(parm) {
+                Some(exe) => exe.arg(count).vec().ok(),
+                None => info.string_bytes(single).map(|s| s.repeat(count)),
+            }
+        };
+        let move_to = |y: usize| self.moves.movement(None, (0, y));
+
+        let mut out = Vec::new();
+        let whole = top == 0 && bottom + 1 == rows;
+        let (parm, single, at) = if n > 0 {
+            (terminfo::ParmIndex, terminfo::ScrollForward, bottom)
+        } else {
+            (terminfo::ParmRindex, terminfo::ScrollReverse, top)
+        };
+        let region = |top: usize, bottom: usize| {
+            info.exec(terminfo::ChangeScrollRegion)?
+                .arg(top)
+                .arg(bottom)
+                .vec()
+                .ok()
+        };
+        if let (Some(set), Some(reset)) = (region(top, bottom), region(0, rows - 1)) {
+            if let Some(lines) = expand(parm, single, count) {
+                if !whole {
+                    out.extend_from_slice(&set);
+                }
+                out.extend(move_to(at)?);
+                out.extend(lines);
+                if !whole {
+                    out.extend_from_slice(&reset);
+                }
+                return Some(out);
+            }
+        }
+
+        let delete = expand(terminfo::ParmDeleteLine, terminfo::DeleteLine, count)?;
+        let insert = expand(terminfo::ParmInsertLine, terminfo::InsertLine, count)?;
+        // delete lines at one end of the region and insert as many at the other, unless that's the bottom of the
+        // screen, where there's nothing below to pull up
+        let (first, second) = if n > 0 {
+            ((top, delete), (bottom + 1 - count, insert))
+        } else {
+            ((bottom + 1 - count, delete), (top, insert))
+        };
+        for (i, &(y, ref lines)) in [first, second].iter().enumerate() {
+            let at_bottom = bottom + 1 == rows && (i == 1) == (n > 0);
+            if !at_bottom {
+                out.extend(move_to(y)?);
+                out.extend_from_slice(lines);
+            }
+        }
+        Some(out)
+    }
+
+    /// What to send before and after `width` columns of text to insert them, rather than draw over what's there.
+    fn insertion(&self, width: usize) -> Option<(Vec<u8>, &'a [u8])> {
+        let info = &self.term.info;
+        if let (Some(enter), Some(exit)) = (
+            info.string_bytes(terminfo::EnterInsertMode),
+            info.string_bytes(terminfo::ExitInsertMode),
+        ) {
+            return Some((enter.to_vec(), exit));
+        }
+        let ich = info
+            .string_bytes(terminfo::ParmIch)
+            .and_then(|ich| terminfo::lang::Program::compile(ich).ok());
+        let mut blanks = Vec::new();
+        match (ich, info.string_bytes(terminfo::InsertCharacter)) {
+            (Some(ich), _) => ich
+                .exec_into(&[terminfo::lang::Argument::Integer(width as i64)], &mut blanks)
+                .ok()?,
+            (None, Some(ich1)) => blanks = ich1.repeat(width),
+            (None, None) => return None,
+        }
+        Some((blanks, b""))
+    }
+
+    /// Write everything that's been drawn to the terminal.
+    pub fn flush(&mut self) -> Result<()> {
+        let mut stdout = self.term.stdout.lock().unwrap();
+        stdout
+            .write_all(&self.buffer)
+            .context(ErrorKind::WriteFailed)?;
+        self.buffer.clear();
+        stdout.flush().context(ErrorKind::WriteFailed)
+    }
+
+    /// Forget what's on the screen and where the cursor is, so the next frame is drawn in full. Call it after the
+    /// screen's been resized too.
+    pub fn invalidate(&mut self) {
+        self.cells.clear();
+        self.cursor = None;
+        self.size = self.term.size().ok();
+    }
+
+    fn put_cluster(&mut self, text: &str, width: usize) -> Result<()> {
+        let (x, y) = self.target;
+        let unchanged = match self.cells.get(y).and_then(|row| row.get(x)) {
+            Some(cell) => width > 0 && cell.text == text && cell.style == self.style,
+            None => false,
+        };
+
+        if !unchanged {
+            self.start_drawing()?;
+            let encoded = self.encode(text);
+            self.buffer.extend_from_slice(&encoded);
+            if width > 0 {
+                self.set_cells(text, width);
+            }
+            self.cursor = match self.size {
+                Some(size) => self.moves.after_text((x, y), width, size),
+                None => Some((x + width, y)),
+            };
+        }
+
+        self.target = (x + width, y);
+        Ok(())
+    }
+
+    /// Move the cursor to the target and switch to the style, if they aren't already, to draw something there.
+    fn start_drawing(&mut self) -> Result<()> {
+        if self.cursor != Some(self.target) {
+            self.write_move()?;
+            self.cursor = Some(self.target);
+        }
+        if self.current != self.style {
+            let transition = self.term.style_transition(&self.current, &self.style);
+            self.buffer.extend_from_slice(&transition);
+            self.current = self.style;
+        }
+        Ok(())
+    }
+
+    /// `text` in the terminal's encoding, see `Term::set_encoding`.
+    fn encode<'t>(&self, text: &'t str) -> Cow<'t, [u8]> {
+        match self.encoding {
+            Some(ref encoding) => {
+                let encoded = encoding.encode(text.as_bytes());
+                if encoding.alt.as_ref().is_some_and(|alt| alt.enabled.get()) {
+                    self.term.acs_enabled.set(true);
+                }
+                encoded
+            }
+            None => Cow::Borrowed(text.as_bytes()),
+        }
+    }
+
+    fn write_move(&mut self) -> Result<()> {
+        let (x, y) = self.target;
+        match self.moves.movement(self.cursor, self.target) {
+            Some(bytes) => {
+                self.buffer.extend_from_slice(&bytes);
+                Ok(())
+            }
+            None => match self.term.degradation() {
+                Degradation::Strict => Err(Capability::String(terminfo::CursorAddress).into()),
+                _ if !self.term.is_dumb() => ansi::cursor_move(&mut self.buffer, x, y),
+                Degradation::Silent => Ok(()),
+                Degradation::BestEffort => {
+                    self.write_plain_move();
+                    Ok(())
+                }
+            },
+        }
+    }
+
+    /// Get to `target` on a dumb terminal, which can only go forward: along the row with spaces, or down with
+    /// newlines. Anything else starts a new line.
+    fn write_plain_move(&mut self) {
+        let (x, y) = self.target;
+        let (cx, cy) = self.cursor.unwrap_or((0, 0));
+        let (newlines, from) = match () {
+            _ if y == cy && x >= cx => (0, cx),
+            _ if y > cy => (y - cy, 0),
+            _ => (1, 0),
+        };
+        for _ in 0..newlines {
+            self.buffer.extend_from_slice(b"\r\n");
+        }
+        self.buffer.resize(self.buffer.len() + x - from, b' ');
+    }
+
+    fn set_cells(&mut self, text: &str, width: usize) {
+        let (x, y) = self.target;
+        if self.cells.len() <= y {
+            self.cells.resize(y + 1, Vec::new());
+        }
+        let row = &mut self.cells[y];
+        if row.len() < x + width {
+            row.resize(x + width, RenderedCell::default());
+        }
+
+        for (i, cell) in row[x..x + width].iter_mut().enumerate() {
+            // reuse the cell's string, so redrawing doesn't allocate
+            cell.text.clear();
+            if i == 0 {
+                cell.text.push_str(text);
+            }
+            cell.style = self.style;
+        }
+    }
+}
+
+impl<'a, I, O> Drop for Renderer<'a, I, O>
+where
+    I: io::Read + AsRawFd,
+    O: io::Write + AsRawFd,
+{
+    fn drop(&mut self) {
+        let reset = self.term.style_transition(&self.current, &ansi::Style::default());
+        self.buffer.extend_from_slice(&reset);
+        if let Err(e) = self.flush() {
+            self.term.set_err(e);
+        }
+    }
+}
+
+#[cfg(test)]
+thread_local! {
+    /// How many times `StyledWriter::write` has written the text it was given as it was, in one go, so tests can see
+    /// the fast path's taken.
+    static BULK_WRITES: Cell<usize> = Cell::new(0);
+}
+
+impl<'a, I, O> io::Write for StyledWriter<'a, I, O>
+where
+    I: io::Read + AsRawFd,
+    O: io::Write + AsRawFd,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let term = self.term;
+        let mut stdout = term.stdout.lock().unwrap();
+        self.sync_style(stdout.deref_mut())?;
+
+        let sanitized;
+        let text = match self.sanitize {
+            Some(policy) if ansi::needs_sanitizing(buf, policy) => {
+                sanitized = ansi::sanitize(buf, policy);
+                &sanitized[..]
+            }
+            _ => buf,
+        };
+        let quirked;
+        let text = if self.quirks != OutputQuirks::default() {
+            quirked = self.apply_quirks(text);
+            &quirked[..]
+        } else {
+            text
+        };
+        let encoded;
+        let text = match self.encoding {
+            Some(ref encoding) => {
+                encoded = encoding.encode_chunk(text);
+                if encoding.alt.as_ref().is_some_and(|alt| alt.enabled.get()) {
+                    term.acs_enabled.set(true);
+                }
+                &encoded[..]
+            }
+            None => text,
+        };
+        // the common case, with nothing left to translate, is one write
+        if term.translates_newlines() || memchr::memchr(b'\n', text).is_none() {
+            // only when it's `buf` itself, not a sanitized or encoded copy
+            #[cfg(test)]
+            {
+                if ::std::ptr::eq(text, buf) {
+                    BULK_WRITES.with(|n| n.set(n.get() + 1));
+                }
+            }
+            stdout.write_all(text)?;
+            return Ok(buf.len());
+        }
+        for (i, line) in text.split(|&c| c == b'\n').enumerate() {
+            if i > 0 {
+                stdout.write_all(b"\r\n")?;
+            }
+            stdout.write_all(line)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.term.stdout.lock().unwrap().flush()
+    }
+}
+
+impl<'a, I, O> fmt::Write for StyledWriter<'a, I, O>
+where
+    I: io::Read + AsRawFd,
+    O: io::Write + AsRawFd,
+{
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        io::Write::write_all(self, s.as_bytes()).map_err(|_| fmt::Error)
+    }
+}
+
+impl<'a, I, O> Drop for StyledWriter<'a, I, O>
+where
+    I: io::Read + AsRawFd,
+    O: io::Write + AsRawFd,
+{
+    fn drop(&mut self) {
+        let mut stdout = self.term.stdout.lock().unwrap();
+        // a character that was never finished is written as it is, like other bytes that aren't UTF-8
+        let pending = self.encoding.as_ref().map_or(Vec::new(), |encoding| encoding.take_pending());
+        let reset = self.term.style_transition(&self.current, &ansi::Style::default());
+        let written = stdout.write_all(&pending).and_then(|_| stdout.write_all(&reset));
+        if let Err(e) = written.and_then(|_| stdout.flush()) {
+            self.term.set_err(e.context(ErrorKind::WriteFailed));
+        }
+    }
+}
+
+impl<'a, I, O> CookedWriter<'a, I, O>
+where
+    I: io::Read + AsRawFd,
+    O: io::Write + AsRawFd,
+{
+    /// Write the text that comes after this in `style`.
+    pub fn set_style(&mut self, style: ansi::Style) {
+        self.writer.set_style(style);
+    }
+
+    /// Say that the terminal is in `style`, because that's what the rest of the application's output uses. It's
+    /// switched back to when the writer's flushed or dropped.
+    pub fn set_active_style(&mut self, style: ansi::Style) {
+        self.active = style;
+        self.writer.current = style.downsample(self.writer.term.color_depth());
+    }
+
+    /// Replace the control characters in the text, see `StyledWriter::set_sanitize`.
+    pub fn set_sanitize(&mut self, policy: Option<ansi::SanitizePolicy>) {
+        self.writer.set_sanitize(policy);
+    }
+
+    /// Switch the terminal back to the active style.
+    fn restore_style(&mut self) -> io::Result<()> {
+        let style = self.writer.style;
+        self.writer.style = self.active;
+        let synced = self
+            .writer
+            .sync_style(self.writer.term.stdout.lock().unwrap().deref_mut());
+        self.writer.style = style;
+        synced
+    }
+}
+
+impl<'a, I, O> io::Write for CookedWriter<'a, I, O>
+where
+    I: io::Read + AsRawFd,
+    O: io::Write + AsRawFd,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let term = self.writer.term;
+        if self.line_start && !term.translates_newlines() {
+            term.stdout.lock().unwrap().write_all(b"\r")?;
+            // a line shorter than the prompt it's written over would leave the end of the prompt showing
+            match term.exec(terminfo::ClrEol) {
+                Ok(mut el) => {
+                    if let Err(e) = el.write(term.stdout.lock().unwrap().deref_mut()) {
+                        term.set_err(e.context(ErrorKind::FailedToRunTerminfo(terminfo::ClrEol)));
+                    }
+                }
+                Err(_) if term.is_dumb() || term.degradation() == Degradation::Strict => (),
+                Err(_) => term.stdout.lock().unwrap().write_all(b"\x1b[K")?,
+            }
+        }
+        // the writer takes care of the `\n`s
+        self.writer.write_all(buf)?;
+        self.line_start = buf.ends_with(b"\n");
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.restore_style()?;
+        self.writer.flush()
+    }
+}
+
+impl<'a, I, O> fmt::Write for CookedWriter<'a, I, O>
+where
+    I: io::Read + AsRawFd,
+    O: io::Write + AsRawFd,
+{
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        io::Write::write_all(self, s.as_bytes()).map_err(|_| fmt::Error)
+    }
+}
+
+impl<'a, I, O> Drop for CookedWriter<'a, I, O>
+where
+    I: io::Read + AsRawFd,
+    O: io::Write + AsRawFd,
+{
+    fn drop(&mut self) {
+        if let Err(e) = self.restore_style() {
+            self.writer.term.set_err(e.context(ErrorKind::WriteFailed));
+        }
+        // the terminal's left in the active style, so there's nothing for the `StyledWriter` to reset
+        self.writer.current = ansi::Style::default();
+    }
+}
+
+impl<'a, I, O> Drop for Viewport<'a, I, O>
+where
+    I: io::Read + AsRawFd,
+    O: io::Write + AsRawFd,
+{
+    fn drop(&mut self) {
+        // an empty viewport never moved the cursor
+        if self.requested == 0 {
+            return;
+        }
+        let (x, y) = self.cursor;
+        if let Err(e) = self.clear().and_then(|_| self.term.move_to(x, y)) {
+            self.term.set_err(e);
+        }
+        self.term.flush();
+    }
+}
+
+impl<'a, I, O> Drop for Session<'a, I, O>
+where
+    I: io::Read + AsRawFd,
+    O: io::Write + AsRawFd,
+{
+    fn drop(&mut self) {
+        // undo everything in the reverse order it was done
+        while let Some(guard) = self.guards.pop() {
+            drop(guard);
+        }
+        if let Some(settings) = self.settings.take() {
+            if let Err(e) = self.term.update(settings) {
+                self.term.set_err(e);
+            }
+        }
+    }
+}
+
+impl<I, O> AsRawFd for Term<I, O>
+where
+    I: io::Read + AsRawFd,
+    O: io::Write + AsRawFd,
+{
+    fn as_raw_fd(&self) -> RawFd {
+        self.stdin_fd
+    }
+}
+
+/// The terminal's input can be registered with `mio`, decode what's read from it with `events::Decoder`.
+#[cfg(feature = "mio")]
+impl<I, O> mio::event::Source for Term<I, O>
+where
+    I: io::Read + AsRawFd,
+    O: io::Write + AsRawFd,
+{
+    fn register(&mut self, registry: &mio::Registry, token: mio::Token, interests: mio::Interest) -> io::Result<()> {
+        mio::unix::SourceFd(&self.stdin_fd).register(registry, token, interests)
+    }
+
+    fn reregister(
+        &mut self,
+        registry: &mio::Registry,
+        token: mio::Token,
+        interests: mio::Interest,
+    ) -> io::Result<()> {
+        mio::unix::SourceFd(&self.stdin_fd).reregister(registry, token, interests)
+    }
+
+    fn deregister(&mut self, registry: &mio::Registry) -> io::Result<()> {
+        mio::unix::SourceFd(&self.stdin_fd).deregister(registry)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use backend::MockBackend;
+    use nix::pty;
+    use nix::unistd;
+    use std::fs::File;
+    use std::io;
+    use std::os::unix::io::FromRawFd;
+    use term::*;
+    use terminfo;
+
+    const TERMINFO: &'static [u8] = include_bytes!("../test-data/rxvt");
+    const XTERM_TERMINFO: &'static [u8] = include_bytes!("../test-data/xterm");
+    const LINUX_TERMINFO: &'static [u8] = include_bytes!("../test-data/linux-16color");
+    const TMUX_TERMINFO: &'static [u8] = include_bytes!("../test-data/tmux");
+    const DUMB_TERMINFO: &'static [u8] = include_bytes!("../test-data/dumb");
+    const GLITCHY_TERMINFO: &'static [u8] = include_bytes!("../test-data/glitchy");
+    const PADDED_TERMINFO: &'static [u8] = include_bytes!("../test-data/padded");
+    const PADDED_STAR_TERMINFO: &'static [u8] = include_bytes!("../test-data/padded-star");
+    const PADDED_NPC_TERMINFO: &'static [u8] = include_bytes!("../test-data/padded-npc");
+    const KITTYISH_TERMINFO: &'static [u8] = include_bytes!("../test-data/kittyish");
+    const VT100_TERMINFO: &'static [u8] = include_bytes!("../test-data/vt100");
+
+    pub(crate) struct FakeStdin {
+        buffer: Vec<u8>,
+    }
+
+    pub(crate) struct FakeStdout {
+        buffer: Vec<u8>,
+    }
+
+    impl FakeStdin {
+        fn new() -> FakeStdin {
+            FakeStdin { buffer: Vec::new() }
+        }
+    }
+
+    impl<'a> AsRawFd for &'a mut FakeStdin {
+        fn as_raw_fd(&self) -> RawFd {
+            0
+        }
+    }
+
+    impl FakeStdout {
+        fn new() -> FakeStdout {
+            FakeStdout { buffer: Vec::new() }
+        }
+    }
+
+    impl<'a> AsRawFd for &'a mut FakeStdout {
+        fn as_raw_fd(&self) -> RawFd {
+            1
+        }
+    }
+
+    impl<'a> io::Read for &'a mut FakeStdin {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = if buf.len() > self.buffer.len() {
+                self.buffer.len()
+            } else {
+                buf.len()
+            };
+
+            self.buffer
+                .drain(..len)
+                .enumerate()
+                .for_each(|(i, c)| buf[i] = c);
+            Ok(len)
+        }
+    }
+
+    impl<'a> io::Write for &'a mut FakeStdout {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.buffer.extend(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Run `f` against a terminal backed by `info`, returning everything it wrote.
+    pub(crate) fn output<F>(info: terminfo::TermInfoBuf, f: F) -> Vec<u8>
+    where
+        F: FnOnce(&Term<&mut FakeStdin, &mut FakeStdout>),
+    {
+        let mut stdin = FakeStdin::new();
+        let mut stdout = FakeStdout::new();
+        {
+            let term = Term::from_streams(info, &mut stdin, &mut stdout);
+            f(&term);
+        }
+        stdout.buffer
+    }
+
+    fn rxvt() -> terminfo::TermInfoBuf {
+        terminfo::TermInfo::parse(TERMINFO).unwrap().into()
+    }
+
+    fn xterm() -> terminfo::TermInfoBuf {
+        terminfo::TermInfo::parse(XTERM_TERMINFO).unwrap().into()
+    }
+
+    fn linux() -> terminfo::TermInfoBuf {
+        terminfo::TermInfo::parse(LINUX_TERMINFO).unwrap().into()
+    }
+
+    fn tmux() -> terminfo::TermInfoBuf {
+        terminfo::TermInfo::parse(TMUX_TERMINFO).unwrap().into()
+    }
+
+    fn dumb() -> terminfo::TermInfoBuf {
+        terminfo::TermInfo::parse(DUMB_TERMINFO).unwrap().into()
+    }
+
+    /// Run `f` against a terminal reading from a pipe that `input` has already been written to, returning its
+    /// result and everything the terminal wrote.
+    fn with_input<F, T>(info: terminfo::TermInfoBuf, input: &[u8], f: F) -> (T, Vec<u8>)
+    where
+        F: FnOnce(&Term<File, &mut FakeStdout>) -> T,
+    {
+        use std::io::Write;
+
+        let (r, w) = unistd::pipe().unwrap();
+        let (r, mut w) = unsafe { (File::from_raw_fd(r), File::from_raw_fd(w)) };
+        w.write_all(input).unwrap();
+
+        let mut stdout = FakeStdout::new();
+        let v = {
+            let term = Term::from_streams(info, r, &mut stdout);
+            f(&term)
+        };
+        // keep the pipe open until `f` is done, so reads time out rather than hitting EOF
+        drop(w);
+        (v, stdout.buffer)
+    }
+
+    /// Run `f` against a mock tty, so its settings can be changed, returning everything it wrote.
+    fn with_tty<F>(info: terminfo::TermInfoBuf, f: F) -> Vec<u8>
+    where
+        F: FnOnce(&Term<MockBackend, MockBackend>),
+    {
+        let mock = MockBackend::new();
+        f(&mock.term(info));
+        mock.written()
+    }
+
+    /// Read everything the terminal has pushed back.
+    fn pushed_back(t: &Term<File, &mut FakeStdout>) -> Vec<u8> {
+        let mut buf = [0u8; 256];
+        let n = t.read(&mut buf);
+        buf[..n].to_vec()
+    }
+
+    #[test]
+    fn insert_delete_parameterized() {
+        assert_eq!(output(rxvt(), |t| t.insert_lines(1).unwrap()), b"\x1b[1L");
+        assert_eq!(output(rxvt(), |t| t.insert_lines(5).unwrap()), b"\x1b[5L");
+        assert_eq!(output(rxvt(), |t| t.delete_lines(1).unwrap()), b"\x1b[1M");
+        assert_eq!(output(rxvt(), |t| t.delete_lines(5).unwrap()), b"\x1b[5M");
+        assert_eq!(output(rxvt(), |t| t.insert_chars(1).unwrap()), b"\x1b[1@");
+        assert_eq!(output(rxvt(), |t| t.insert_chars(5).unwrap()), b"\x1b[5@");
+        assert_eq!(output(rxvt(), |t| t.insert_lines(0).unwrap()), b"");
+    }
+
+    #[test]
+    fn insert_delete_repeated() {
+        let mut info = terminfo::TermInfoBuf::new();
+        info.set_string(terminfo::InsertLine, "\x1b[L").unwrap();
+        info.set_string(terminfo::DeleteLine, "\x1b[M").unwrap();
+        info.set_string(terminfo::InsertCharacter, "\x1b[@").unwrap();
+        info.set_string(terminfo::DeleteCharacter, "\x1b[P").unwrap();
+
+        assert_eq!(output(info.clone(), |t| t.insert_lines(1).unwrap()), b"\x1b[L");
+        assert_eq!(
+            output(info.clone(), |t| t.insert_lines(5).unwrap()),
+            b"\x1b[L\x1b[L\x1b[L\x1b[L\x1b[L"
+        );
+        assert_eq!(output(info.clone(), |t| t.delete_lines(1).unwrap()), b"\x1b[M");
+        assert_eq!(
+            output(info.clone(), |t| t.delete_lines(5).unwrap()),
+            b"\x1b[M\x1b[M\x1b[M\x1b[M\x1b[M"
+        );
+        assert_eq!(output(info.clone(), |t| t.insert_chars(1).unwrap()), b"\x1b[@");
+        assert_eq!(
+            output(info.clone(), |t| t.insert_chars(5).unwrap()),
+            b"\x1b[@\x1b[@\x1b[@\x1b[@\x1b[@"
+        );
+        assert_eq!(output(info.clone(), |t| t.delete_chars(1).unwrap()), b"\x1b[P");
+        assert_eq!(
+            output(info, |t| t.delete_chars(5).unwrap()),
+            b"\x1b[P\x1b[P\x1b[P\x1b[P\x1b[P"
+        );
+    }
+
+    #[test]
+    fn write_cap() {
+        use std::error::Error as StdError;
+        use terminfo::lang::Argument;
+
+        assert_eq!(
+            output(xterm(), |t| t
+                .write_cap(terminfo::CursorAddress, &[Argument::Integer(2), Argument::Integer(5)])
+                .unwrap()),
+            b"\x1b[3;6H"
+        );
+        output(xterm(), |t| {
+            match t.write_cap(terminfo::ToStatusLine, &[]).unwrap_err() {
+                Error::Unsupported(c) => assert_eq!(c, Capability::String(terminfo::ToStatusLine)),
+                e => panic!("{:?}", e),
+            }
+            if cfg!(debug_assertions) {
+                let e = t
+                    .write_cap(terminfo::SetAForeground, &[Argument::from("red")])
+                    .unwrap_err();
+                let source = StdError::source(&e)
+                    .and_then(|e| e.downcast_ref::<terminfo::Error>())
+                    .unwrap();
+                assert_eq!(
+                    *source.kind(),
+                    terminfo::ErrorKind::ArgumentTypeMismatch {
+                        cap: terminfo::SetAForeground,
+                        index: 0,
+                        expected: terminfo::Param::Integer,
+                    }
+                );
+            }
+        });
+    }
+
+    #[test]
+    fn padding() {
+        let written = |info: &terminfo::TermInfoBuf, speed| {
+            let mock = MockBackend::new();
+            let term = mock.term(info.clone());
+            term.update(Settings::cooked().speed(speed)).unwrap();
+            term.write_cap(terminfo::ClearScreen, &[]).unwrap();
+            mock.take_written()
+        };
+        let padded = |pad, n: usize| {
+            let mut clear = b"\x1b[H".to_vec();
+            clear.resize(3 + n, pad);
+            clear.extend_from_slice(b"\x1b[J");
+            clear
+        };
+        let nuls = |n| padded(0, n);
+
+        let mut info = terminfo::TermInfoBuf::new();
+        info.set_string(terminfo::ClearScreen, "\x1b[H$<45>\x1b[J").unwrap();
+        info.set_number(terminfo::PaddingBaudRate, 9600).unwrap();
+        assert_eq!(MockBackend::new().term(info.clone()).baud_rate(), 38400);
+        assert_eq!(written(&info, termios::BaudRate::B38400), nuls(192));
+        assert_eq!(written(&info, termios::BaudRate::B9600), nuls(48));
+        assert_eq!(written(&info, termios::BaudRate::B2400), nuls(0));
+
+        info.set_string(terminfo::PadChar, "*").unwrap();
+        assert_eq!(written(&info, termios::BaudRate::B9600), padded(b'*', 48));
+
+        info.set_string(terminfo::PadChar, "\0").unwrap();
+        info.set_boolean(terminfo::XonXoff, true).unwrap();
+        assert_eq!(written(&info, termios::BaudRate::B38400), nuls(0));
+        info.set_string(terminfo::ClearScreen, "\x1b[H$<45/>\x1b[J").unwrap();
+        assert_eq!(written(&info, termios::BaudRate::B2400), nuls(12));
+
+        info.set_boolean(terminfo::NoPadChar, true).unwrap();
+        assert_eq!(written(&info, termios::BaudRate::B38400), nuls(0));
+    }
+
+    #[test]
+    fn pad_entries() {
+        let written = |entry, field| {
+            let mock = MockBackend::new();
+            let term = mock.term(terminfo::TermInfo::parse(entry).unwrap().into());
+            term.update(Settings::cooked().speed(termios::BaudRate::B9600))
+                .unwrap();
+            let start = Instant::now();
+            term.write_cap(field, &[]).unwrap();
+            (mock.take_written(), start.elapsed())
+        };
+        let padded = |pad, n: usize, before: &[u8], after: &[u8]| {
+            [before, &vec![pad; n][..], after].concat()
+        };
+
+        // `clear` has 45ms of padding, and the mandatory 20ms in `flash` are padded too
+        for &(entry, pad) in &[(PADDED_TERMINFO, 0), (PADDED_STAR_TERMINFO, b'*')] {
+            assert_eq!(
+                written(entry, terminfo::ClearScreen).0,
+                padded(pad, 48, b"\x1b[H", b"\x1b[J")
+            );
+            assert_eq!(
+                written(entry, terminfo::FlashScreen).0,
+                padded(pad, 21, b"\x1b[?5h", b"\x1b[?5l")
+            );
+        }
+
+        // with `npc` the delays are slept instead
+        let (out, took) = written(PADDED_NPC_TERMINFO, terminfo::ClearScreen);
+        assert_eq!(out, b"\x1b[H\x1b[J");
+        assert!(took >= Duration::from_millis(45), "{:?}", took);
+        let (out, took) = written(PADDED_NPC_TERMINFO, terminfo::FlashScreen);
+        assert_eq!(out, b"\x1b[?5h\x1b[?5l");
+        assert!(took >= Duration::from_millis(20), "{:?}", took);
+    }
+
+    #[test]
+    fn program_function_key() {
+        // from the HP 2621 family, which say how long the string is, then the string itself
+        let mut info = terminfo::TermInfoBuf::new();
+        info.set_string(terminfo::PkeyKey, "\x1b&f0a%p1%dk%p2%l%dL%p2%s")
+            .unwrap();
+        assert_eq!(
+            output(info.clone(), |t| t.program_function_key(3, "ls -l\r").unwrap()),
+            &b"\x1b&f0a3k6Lls -l\r"[..]
+        );
+
+        // `pfx` comes first, and the string isn't expanded itself
+        info.set_string(terminfo::PkeyXmit, "\x1b&f2a%p1%dk%p2%l%dL%p2%s")
+            .unwrap();
+        assert_eq!(
+            output(info, |t| t.program_function_key(12, "100%d").unwrap()),
+            &b"\x1b&f2a12k5L100%d"[..]
+        );
+
+        assert_eq!(output(rxvt(), |t| t.program_function_key(1, "x").unwrap()), b"");
+        output(rxvt(), |t| {
+            t.set_degradation(Degradation::Strict);
+            match t.program_function_key(1, "x").unwrap_err() {
+                Error::Unsupported(c) => assert_eq!(c, Capability::String(terminfo::PkeyXmit)),
+                e => panic!("{:?}", e),
+            }
+        });
+    }
+
+    #[test]
+    fn not_a_tty() {
+        // stdin is a pipe, so it has no termios
+        let (err, _) = with_input(rxvt(), b"", |t| {
+            t.settings();
+            t.err().unwrap_err()
+        });
+        assert!(err.is_not_a_tty(), "{:?}", err);
+    }
+
+    #[test]
+    fn insert_delete_missing() {
+        // rxvt defines neither `dch` nor `dch1`, and has no `ech`.
+        output(rxvt(), |t| {
+            t.delete_chars(5).unwrap();
+            t.erase_chars(1).unwrap();
+            t.set_degradation(Degradation::Strict);
+            match t.delete_chars(5).unwrap_err() {
+                Error::Unsupported(c) => assert_eq!(c, Capability::String(terminfo::ParmDch)),
+                e => panic!("{:?}", e),
+            }
+            match t.erase_chars(1).unwrap_err() {
+                Error::Unsupported(c) => assert_eq!(c, Capability::String(terminfo::EraseChars)),
+                e => panic!("{:?}", e),
+            }
+        });
+
+        let mut info = terminfo::TermInfoBuf::new();
+        info.set_string(terminfo::EraseChars, "\x1b[%p1%dX").unwrap();
+        assert_eq!(output(info.clone(), |t| t.erase_chars(1).unwrap()), b"\x1b[1X");
+        assert_eq!(output(info, |t| t.erase_chars(5).unwrap()), b"\x1b[5X");
+    }
+
+    #[test]
+    fn tab_stops() {
+        let xterm: terminfo::TermInfoBuf = terminfo::TermInfo::parse(XTERM_TERMINFO)
+            .unwrap()
+            .into();
+
+        assert_eq!(output(rxvt(), |t| t.set_tab_stop().unwrap()), b"\x1bH");
+        assert_eq!(output(rxvt(), |t| t.clear_tab_stop().unwrap()), b"\x1b[0g");
+        assert_eq!(output(rxvt(), |t| t.clear_all_tabs().unwrap()), b"\x1b[3g");
+        assert_eq!(output(rxvt(), |t| t.forward_tabs(3).unwrap()), b"\t\t\t");
+        assert_eq!(output(xterm.clone(), |t| t.backward_tabs(1).unwrap()), b"\x1b[Z");
+        assert_eq!(
+            output(xterm, |t| t.backward_tabs(3).unwrap()),
+            b"\x1b[Z\x1b[Z\x1b[Z"
+        );
+
+        // rxvt has no `cbt`.
+        assert_eq!(output(rxvt(), |t| t.backward_tabs(1).unwrap()), b"\x1b[1Z");
+        assert_eq!(output(rxvt(), |t| t.backward_tabs(4).unwrap()), b"\x1b[4Z");
+
+        let empty = terminfo::TermInfoBuf::new();
+        assert_eq!(output(empty.clone(), |t| t.set_tab_stop().unwrap()), b"\x1bH");
+        assert_eq!(output(empty.clone(), |t| t.clear_all_tabs().unwrap()), b"\x1b[3g");
+        assert_eq!(output(empty, |t| t.forward_tabs(2).unwrap()), b"\x1b[2I");
+    }
+
+    #[test]
+    fn with_acs() {
+        use acs::AcsChar;
+        use std::io::Write;
+
+        let out = output(rxvt(), |t| {
+            let acs = Acs::with_utf8(&t.info, false);
+            for _ in 0..2 {
+                t.write_with_acs(&acs, |a| {
+                    write!(t.stdout.lock().unwrap(), "{}", a.get(AcsChar::HLine)).unwrap();
+                }).unwrap();
+            }
+        });
+        // `enacs` is only sent the first time.
+        assert_eq!(&out, b"\x1b(B\x1b)0\x0eq\x0f\x0eq\x0f");
+
+        // without a mapping the ASCII line is drawn outside the alternate character set
+        let mut info = rxvt();
+        info.set_string(terminfo::AcsChars, "aaxx").unwrap();
+        assert_eq!(output(info, |t| t.hline(2).unwrap()), b"--");
+
+        let out = output(rxvt(), |t| {
+            let acs = Acs::with_utf8(&t.info, true);
+            t.write_with_acs(&acs, |a| {
+                write!(t.stdout.lock().unwrap(), "{}", a.get(AcsChar::HLine)).unwrap();
+            }).unwrap();
+        });
+        assert_eq!(String::from_utf8(out).unwrap(), "─");
+    }
+
+    #[test]
+    fn private_modes() {
+        assert_eq!(output(rxvt(), |t| t.set_private_mode(2004, true).unwrap()), b"\x1b[?2004h");
+        assert_eq!(output(rxvt(), |t| t.set_private_mode(25, false).unwrap()), b"\x1b[?25l");
+
+        let timeout = Duration::from_millis(500);
+        let replies = [
+            (&b"\x1b[?2026;0$y"[..], ModeState::NotRecognized),
+            (b"\x1b[?2026;1$y", ModeState::Set),
+            (b"\x1b[?2026;2$y", ModeState::Reset),
+            (b"\x1b[?2026;3$y", ModeState::PermanentlySet),
+            (b"\x1b[?2026;4$y", ModeState::PermanentlyReset),
+        ];
+        for &(reply, state) in replies.iter() {
+            let (v, out) = with_input(rxvt(), reply, |t| t.query_private_mode(2026, timeout).unwrap());
+            assert_eq!(v, state);
+            assert_eq!(out, b"\x1b[?2026$p");
+        }
+
+        let (v, _) = with_input(rxvt(), b"\x1b[?2004;2$y", |t| t.supports_bracketed_paste(timeout));
+        assert!(v);
+        let (v, _) = with_input(rxvt(), b"\x1b[?2026;4$y", |t| t.supports_synchronized_output(timeout));
+        assert!(!v);
+    }
+
+    #[test]
+    fn query_pushback() {
+        let timeout = Duration::from_millis(500);
+
+        // keys typed around the reply aren't lost
+        let ((state, rest), _) = with_input(rxvt(), b"ab\x1b[A\x1b[?2026;1$ycd", |t| {
+            (t.query_private_mode(2026, timeout).unwrap(), pushed_back(t))
+        });
+        assert_eq!(state, ModeState::Set);
+        assert_eq!(rest, b"ab\x1b[Acd");
+
+        // a reply to some other query is left alone
+        let ((err, rest), _) = with_input(rxvt(), b"\x1b[?2004;1$y", |t| {
+            let err = t.query_private_mode(2026, Duration::from_millis(50)).unwrap_err();
+            (err, pushed_back(t))
+        });
+        assert!(err.is_timeout(), "{:?}", err);
+        assert_eq!(rest, b"\x1b[?2004;1$y");
+    }
+
+    #[test]
+    fn query_timeout() {
+        let timeout = Duration::from_millis(50);
+        let ((err, elapsed), out) = with_input(rxvt(), b"", |t| {
+            let start = Instant::now();
+            let err = t.query_private_mode(1049, timeout).unwrap_err();
+            (err, start.elapsed())
+        });
+        assert!(err.is_timeout(), "{:?}", err);
+        assert!(elapsed >= timeout);
+        assert_eq!(out, b"\x1b[?1049$p");
+
+        // rxvt has `smcup`, so the alternate screen is assumed to work
+        let (v, _) = with_input(rxvt(), b"", |t| t.supports_alt_screen(timeout));
+        assert!(v);
+        let (v, _) = with_input(terminfo::TermInfoBuf::new(), b"", |t| t.supports_alt_screen(timeout));
+        assert!(!v);
+        let (v, _) = with_input(rxvt(), b"x", |t| (t.supports_bracketed_paste(timeout), pushed_back(t)));
+        assert_eq!(v, (false, b"x".to_vec()));
+    }
+
+    #[test]
+    fn query_retries() {
+        let ms = Duration::from_millis;
+
+        // a reply that turns up after the first attempt gave up, but in time for the second
+        let mock = MockBackend::new();
+        let term = mock.term(rxvt());
+        term.set_query_config(QueryConfig::new().timeout(ms(100)).retries(1));
+        mock.push_input_after(ms(150), b"\x1b[?2026;1$y");
+        assert_eq!(term.query_private_mode(2026, None).unwrap(), ModeState::Set);
+        assert_eq!(mock.written(), b"\x1b[?2026$p\x1b[?2026$p");
+        // the round trip counts from when it was first sent
+        assert!(term.round_trip().unwrap() >= ms(150));
+
+        // one that's too late for every attempt
+        let mock = MockBackend::new();
+        let term = mock.term(rxvt());
+        term.set_query_config(QueryConfig::new().timeout(ms(50)).retries(2));
+        mock.push_input_after(ms(400), b"\x1b[3;3R");
+        match term.cursor_position(None).unwrap_err() {
+            Error::Timeout { query, waited } => {
+                assert_eq!(query, "cursor position");
+                assert!(waited >= ms(150) && waited < ms(400), "{:?}", waited);
+            }
+            err => panic!("{:?}", err),
+        }
+        assert_eq!(term.round_trip(), None);
+        // a timeout of its own is long enough
+        assert_eq!(term.cursor_position(ms(500)).unwrap(), (2, 2));
+    }
+
+    #[test]
+    fn adaptive_timeout() {
+        let ms = Duration::from_millis;
+        let mock = MockBackend::new();
+        let term = mock.term(rxvt());
+        term.set_query_config(QueryConfig::new().timeout(ms(2000)).adaptive(true));
+        assert_eq!(term.query_timeout(), ms(2000));
+
+        mock.push_input_after(ms(30), b"\x1b[?2004;2$y");
+        assert_eq!(term.query_private_mode(2004, None).unwrap(), ModeState::Reset);
+        let round_trip = term.round_trip().unwrap();
+        assert!(round_trip >= ms(30) && round_trip < ms(500), "{:?}", round_trip);
+        assert_eq!(term.query_timeout(), round_trip * query::ADAPTIVE_ROUND_TRIPS);
+
+        // a query that's never answered gives up after a few round trips, not the whole timeout
+        mock.push_input_after(ms(1500), b"x");
+        let start = Instant::now();
+        let err = term.query_termcap("Co", None).unwrap_err();
+        assert!(err.is_timeout(), "{:?}", err);
+        assert!(start.elapsed() >= term.query_timeout() && start.elapsed() < ms(1500));
+    }
+
+    #[test]
+    fn query_garbage() {
+        let timeout = Duration::from_millis(500);
+
+        // a terminal that keeps sending something else gives up once the budget is used
+        let ((err, elapsed, rest), _) = with_input(rxvt(), b"garbage garbage garbage", |t| {
+            t.set_query_budget(8);
+            let start = Instant::now();
+            let err = t.query_private_mode(2026, timeout).unwrap_err();
+            (err, start.elapsed(), pushed_back(t))
+        });
+        assert!(err.is_timeout(), "{:?}", err);
+        assert!(elapsed < timeout);
+        assert_eq!(rest, b"garbage garbage garbage");
+
+        let ((err, rest), _) = with_input(rxvt(), b"ab\x1b[24;", |t| {
+            (t.cursor_position(timeout).unwrap_err(), pushed_back(t))
+        });
+        match err {
+            Error::Parse(ParseError::MalformedReply(ref reply)) => assert_eq!(reply, b"\x1b[24;"),
+            _ => panic!("{:?}", err),
+        }
+        assert_eq!(rest, b"ab");
+
+        let (err, _) = with_input(rxvt(), b"\x1b]4;12;rgb:zz/0/0\x07", |t| {
+            t.query_palette_color(12, timeout).unwrap_err()
+        });
+        assert!(err.is_parse(), "{:?}", err);
+
+        let (v, out) = with_input(rxvt(), b"\x1bP1+r436F=323536\x1b\\", |t| t.query_termcap("Co", timeout).unwrap());
+        assert_eq!(v, b"256");
+        assert_eq!(out, b"\x1bP+q436F\x1b\\");
+        let (err, _) = with_input(rxvt(), b"\x1bP0+r436F\x1b\\", |t| t.query_termcap("Co", timeout).unwrap_err());
+        assert!(err.is_unsupported(), "{:?}", err);
+    }
+
+    #[test]
+    fn identify() {
+        let timeout = Duration::from_millis(500);
+        let replies = b"\x1bP>|kitty(0.31.0)\x1b\\\x1b[>1;4000;31c\x1b[?62;4cq";
+        let ((id, rest), out) = with_input(rxvt(), replies, |t| (t.identify(timeout).unwrap(), pushed_back(t)));
+        assert_eq!(out, b"\x1b[>0q\x1b[>c\x1b[c");
+        assert_eq!(id.family, query::TerminalFamily::Kitty);
+        assert_eq!(id.version, Some("0.31.0".to_owned()));
+        assert_eq!(id.capabilities_hinted, vec![4]);
+        assert_eq!(rest, b"q");
+
+        // the terminal never answers, so all we have is the environment
+        let (id, _) = with_input(rxvt(), b"", |t| t.identify(Duration::from_millis(50)).unwrap());
+        assert!(id.capabilities_hinted.is_empty());
+    }
+
+    #[test]
+    fn notify() {
+        use xterm::NotificationStyle;
+
+        let out = output(rxvt(), |t| {
+            t.set_notification_style(Some(NotificationStyle::Osc777));
+            t.notify("Build", "done; 0 errors").unwrap();
+        });
+        assert_eq!(out, b"\x1b]777;notify;Build;done, 0 errors\x1b\\");
+
+        let out = output(rxvt(), |t| {
+            t.set_notification_style(Some(NotificationStyle::Osc9));
+            t.notify("Build", "done").unwrap();
+        });
+        assert_eq!(out, b"\x1b]9;Build: done\x1b\\");
+
+        let out = output(rxvt(), |t| {
+            t.set_notification_style(Some(NotificationStyle::Disabled));
+            t.notify("Build", "done").unwrap();
+        });
+        assert!(out.is_empty());
+
+        // picked based on `identify`
+        let (style, _) = with_input(rxvt(), b"\x1b[>84;0;0c\x1b[?1;2c", |t| {
+            t.identify(Duration::from_millis(500)).unwrap();
+            t.notification_style()
+        });
+        assert_eq!(style, NotificationStyle::Disabled);
+        let (style, _) = with_input(rxvt(), b"\x1bP>|foot(1.16.2)\x1b\\\x1b[?62;4;22c", |t| {
+            t.identify(Duration::from_millis(500)).unwrap();
+            t.notification_style()
+        });
+        assert_eq!(style, NotificationStyle::Osc777);
+    }
+
+    #[test]
+    fn passthrough() {
+        let tmux = || EnvOverrides::from_map(vec![("TMUX", "/tmp/tmux-1000/default,1,0")]);
+
+        // off by default, and only wraps anything in a multiplexer
+        let out = output(rxvt(), |t| {
+            t.set_env_overrides(tmux());
+            t.copy_to_clipboard(b"hi").unwrap();
+            t.set_passthrough(true);
+            t.set_env_overrides(EnvOverrides::from_map(vec![("TERM", "xterm")]));
+            assert_eq!(t.passthrough(), None);
+            t.copy_to_clipboard(b"hi").unwrap();
+        });
+        assert_eq!(out, &b"\x1b]52;c;aGk=\x1b\\\x1b]52;c;aGk=\x1b\\"[..]);
+
+        let out = output(rxvt(), |t| {
+            t.set_env_overrides(tmux());
+            t.set_passthrough(true);
+            t.set_notification_style(Some(NotificationStyle::Osc9));
+            t.copy_to_clipboard(b"hi").unwrap();
+            t.notify("Build", "done").unwrap();
+        });
+        assert_eq!(
+            out,
+            &b"\x1bPtmux;\x1b\x1b]52;c;aGk=\x1b\x1b\\\x1b\\\x1bPtmux;\x1b\x1b]9;Build: done\x1b\x1b\\\x1b\\"[..]
+        );
+
+        // the cursor's moved by the multiplexer, only the image goes through
+        let image = ansi::Image::new(1, 1, ansi::PixelFormat::Rgb, vec![255, 0, 0]).unwrap();
+        let ((), out) = with_input(xterm(), b"\x1bP>|kitty(0.31.0)\x1b\\\x1b[>1;4000;31c\x1b[?62;c", |t| {
+            t.identify(Duration::from_millis(500)).unwrap();
+            t.set_env_overrides(EnvOverrides::from_map(vec![("STY", "1.pts-0.host")]));
+            t.set_passthrough(true);
+            t.draw_image(&image, (2, 5)).unwrap();
+        });
+        assert!(out.ends_with(b"\x1b[3;6H\x1bP\x1b_Ga=T,f=24,s=1,v=1,q=2;/wAA\x1b\x1b\\\x1bP\\\x1b\\"));
+    }
+
+    #[test]
+    fn in_copy_mode() {
+        let mock = MockBackend::new();
+        let t = mock.term(tmux());
+        t.set_env_overrides(EnvOverrides::from_map(vec![("STY", "1.pts-0.host")]));
+        assert!(!t.in_copy_mode().unwrap());
+        // there's no tmux server there to ask, or no tmux at all
+        let tmux = vec![("TMUX", "/nonexistent/tmux-1000/default,1,0"), ("TMUX_PANE", "%1")];
+        t.set_env_overrides(EnvOverrides::from_map(tmux));
+        assert!(t.in_copy_mode().is_err());
+    }
+
+    #[test]
+    fn pause_output() {
+        let write = |t: &Term<MockBackend, MockBackend>| {
+            t.print("b").unwrap();
+            t.writer().bold().print("c").done().unwrap();
+            t.flush();
+        };
+        let mock = MockBackend::new();
+        let t = mock.term(rxvt());
+        write(&t);
+        let expected = mock.take_written();
+
+        t.pause_output();
+        write(&t);
+        assert!(t.output_paused());
+        assert_eq!(mock.take_written(), b"");
+        t.resume_output().unwrap();
+        assert!(!t.output_paused());
+        assert_eq!(mock.take_written(), expected);
+
+        // the cap's reached by the second print, whose text isn't sent or held back
+        t.set_pause_buffer(16, OverflowPolicy::Fail);
+        t.pause_output();
+        t.print("abc").unwrap();
+        assert!(t.print("defgh").is_err());
+        t.resume_output().unwrap();
+        let out = mock.take_written();
+        assert!(!out.windows(5).any(|w| w == b"defgh"));
+        assert!(out.len() <= 16);
+
+        t.set_pause_buffer(16, OverflowPolicy::Discard);
+        t.pause_output();
+        t.print("abc").unwrap();
+        t.print("defgh").unwrap();
+        t.resume_output().unwrap();
+        assert!(!mock.take_written().windows(5).any(|w| w == b"defgh"));
+
+        t.set_pause_buffer(16, OverflowPolicy::Send);
+        t.pause_output();
+        t.print("abc").unwrap();
+        assert_eq!(mock.take_written(), b"");
+        t.print("defgh").unwrap();
+        assert!(mock.take_written().ends_with(b"defgh"));
+        assert!(t.output_paused());
+        t.resume_output().unwrap();
+    }
+
+    #[test]
+    fn read_line_redirected() {
+        // without a tty the line's read as it is, and a Windows line ending is taken off as well
+        let mock = MockBackend::new();
+        mock.set_tty(false);
+        let t = mock.term(dumb());
+        mock.push_input(b"hunter2\r\nnext\n");
+        assert_eq!(t.read_password("password> ").unwrap(), "hunter2");
+        assert_eq!(t.read_line("> ").unwrap(), "next");
+        assert_eq!(mock.take_written(), b"password> > ");
+    }
+
+    #[test]
+    fn flow_control() {
+        let mock = MockBackend::new();
+        let t = mock.term(rxvt());
+        t.set_flow_control(true);
+        let mut buf = [0u8; 8];
+
+        // the tty handles them in cooked mode
+        mock.push_input(b"\x13x");
+        assert_eq!(t.read(&mut buf), 2);
+        assert!(!t.output_paused());
+
+        t.update(t.settings().raw()).unwrap();
+        mock.push_input(b"x\x13");
+        assert_eq!(t.read(&mut buf), 1);
+        assert_eq!(buf[0], b'x');
+        assert!(t.output_paused());
+        t.print("held").unwrap();
+        assert_eq!(mock.take_written(), b"");
+
+        mock.push_input(b"\x11y");
+        assert_eq!(t.read(&mut buf), 1);
+        assert_eq!(buf[0], b'y');
+        assert!(!t.output_paused());
+        assert!(mock.take_written().ends_with(b"held"));
+
+        t.set_flow_control(false);
+        mock.push_input(b"\x13");
+        assert_eq!(t.read(&mut buf), 1);
+        assert!(!t.output_paused());
+    }
+
+    #[test]
+    fn ext_capabilities() {
+        let kittyish = || terminfo::TermInfo::parse(KITTYISH_TERMINFO).unwrap().into();
+        let depth = |info| {
+            let mock = MockBackend::new();
+            let t = mock.term(info);
+            t.set_env_overrides(EnvOverrides::from_map(vec![("TERM", "xterm-256color")]));
+            t.color_depth()
+        };
+        assert_eq!(depth(kittyish()), ansi::ColorDepth::TrueColor);
+        assert_eq!(depth(xterm()), ansi::ColorDepth::Ansi16);
+
+        // `Ms` is used as the entry has it, with its BEL rather than ST
+        let out = output(tmux(), |t| t.copy_to_clipboard(b"hi").unwrap());
+        assert_eq!(out, b"\x1b]52;c;aGk=\x07");
+
+        let out = output(kittyish(), |t| {
+            t.frame(|t| t.print("x").unwrap()).unwrap();
+        });
+        assert!(out.starts_with(b"\x1bP=1s\x1b\\"));
+        assert!(out.ends_with(b"x\x1bP=2s\x1b\\"));
+        let out = output(xterm(), |t| t.frame(|_| ()).unwrap());
+        assert_eq!(out, b"\x1b[?2026h\x1b[?2026l");
+
+        // `Smulx` and `Setulc`, and kitty's sequences without them
+        fn underline(t: &Term<&mut FakeStdin, &mut FakeStdout>) {
+            t.set_underline(xterm::kitty::Underline::Curly).unwrap();
+            t.set_underline_color(ansi::Color::Rgb(255, 128, 0)).unwrap();
+            t.set_underline_color(ansi::Color::Index(9)).unwrap();
+        }
+        assert_eq!(
+            output(kittyish(), underline),
+            &b"\x1b[4:3m\x1b[58:2:255:128:0m\x1b[58:5:9m"[..]
+        );
+        assert_eq!(
+            output(xterm(), underline),
+            &b"\x1b[4:3m\x1b[58:2:255:128:0m\x1b[58:5:9m"[..]
+        );
+        let out = output(kittyish(), |t| t.set_underline(xterm::kitty::Underline::Dotted).unwrap());
+        assert_eq!(out, b"\x1b[4:4m");
+    }
+
+    #[test]
+    fn io_stats() {
+        use gate::VECTORED_THRESHOLD;
+
+        let (r, w) = unistd::pipe().unwrap();
+        let (mut r, w) = unsafe { (File::from_raw_fd(r), File::from_raw_fd(w)) };
+        let mut stdin = FakeStdin::new();
+        let t = Term::from_streams(xterm(), &mut stdin, w);
+        assert_eq!(t.io_stats(), IoStats::default());
+
+        // however many pieces a frame is written in, it's sent at once
+        t.frame(|t| {
+            for y in 0..20 {
+                t.move_to(0, y).unwrap();
+                t.print("line").unwrap();
+            }
+        })
+        .unwrap();
+        let small = t.io_stats();
+        assert_eq!((small.writes, small.vectored_writes), (1, 0));
+
+        // and a big one isn't copied into one buffer first
+        t.reset_io_stats();
+        let text = "x".repeat(VECTORED_THRESHOLD);
+        t.frame(|t| t.print(&text).unwrap()).unwrap();
+        let big = t.io_stats();
+        assert_eq!((big.writes, big.vectored_writes), (1, 1));
+        assert!(big.bytes > VECTORED_THRESHOLD as u64);
+
+        let n = t
+            .write_vectored(&[io::IoSlice::new(b"ab"), io::IoSlice::new(b"cd")])
+            .unwrap();
+        assert_eq!(n, 4);
+        assert_eq!(t.io_stats().writes, 2);
+        drop(t);
+
+        let mut sent = Vec::new();
+        r.read_to_end(&mut sent).unwrap();
+        assert_eq!(sent.len() as u64, small.bytes + big.bytes + 4);
+        assert!(sent.ends_with(b"\x1b[?2026labcd"));
+    }
+
+    #[test]
+    fn record_to() {
+        use record::test::Shared;
+        use record::{self, RecordFormat};
+
+        let mock = MockBackend::new();
+        mock.set_size(100, 30);
+        let t = mock.term(xterm());
+        t.set_env_overrides(EnvOverrides::from_map(vec![("TERM", "xterm-256color")]));
+        let sink = Shared(Arc::new(Mutex::new(Vec::new())));
+        t.record_to(sink.clone(), RecordFormat::Asciicast).unwrap();
+        t.print("one").unwrap();
+        t.pause_output();
+        t.move_to(3, 2).unwrap();
+        t.print("two").unwrap();
+        t.resume_output().unwrap();
+        t.print("three").unwrap();
+        t.stop_recording().unwrap();
+        t.print("four").unwrap();
+
+        // the recording has exactly what was sent, in order
+        let sent = mock.take_written();
+        let recording = String::from_utf8(sink.0.lock().unwrap().clone()).unwrap();
+        let (header, events) = record::read_asciicast(&recording).unwrap();
+        assert_eq!((header.width, header.height), (100, 30));
+        assert_eq!(header.term.as_deref(), Some("xterm-256color"));
+        let recorded: String = events
+            .iter()
+            .map(|e| match e.1 {
+                record::Event::Output(ref s) => &s[..],
+                _ => "",
+            })
+            .collect();
+        assert!(String::from_utf8(sent).unwrap().starts_with(&recorded));
+        let one = recorded.find("one").unwrap();
+        let two = recorded.find("two").unwrap();
+        assert!(one < recorded.find("\x1b[3;4H").unwrap() && two < recorded.find("three").unwrap());
+        assert!(!recorded.contains("four"));
+        assert!(events.windows(2).all(|w| w[0].0 <= w[1].0));
+    }
+
+    #[test]
+    fn mode_guards() {
+        let out = output(xterm(), |t| {
+            let _keypad = t.keypad_transmit().unwrap();
+            let _meta = t.meta_mode().unwrap();
+            let _margins = t.disable_auto_margins().unwrap();
+        });
+        assert_eq!(
+            out,
+            &b"\x1b[?1h\x1b=\x1b[?1034h\x1b[?7l\x1b[?7h\x1b[?1034l\x1b[?1l\x1b>"[..]
+        );
+
+        // rxvt has `smkx` but none of the others
+        let out = output(rxvt(), |t| {
+            let _keypad = t.keypad_transmit().unwrap();
+            let _meta = t.meta_mode().unwrap();
+            let _margins = t.disable_auto_margins().unwrap();
+        });
+        assert_eq!(
+            out,
+            &b"\x1b=\x1b[?1034h\x1b[?7l\x1b[?7h\x1b[?1034l\x1b>"[..]
+        );
+
+        let out = output(terminfo::TermInfoBuf::new(), |t| drop(t.keypad_transmit().unwrap()));
+        assert_eq!(out, b"\x1b[?1h\x1b=\x1b[?1l\x1b>");
+    }
+
+    #[test]
+    fn session() {
+        let options = SessionOptions::new()
+            .alternate_screen(true)
+            .hide_cursor(true)
+            .keypad_transmit(true);
+
+        let out = output(xterm(), |t| {
+            drop(t.session(options).unwrap());
+        });
+        assert_eq!(
+            out,
+            &b"\x1b[?1049h\x1b[22;0;0t\x1b[?25l\x1b[?1h\x1b=\x1b[?1l\x1b>\x1b[?12l\x1b[?25h\x1b[?1049l\x1b[23;0;0t"[..]
+        );
+
+        let out = output(terminfo::TermInfoBuf::new(), |t| drop(t.session(options).unwrap()));
+        assert_eq!(
+            out,
+            &b"\x1b[?1049h\x1b[?25l\x1b[?1h\x1b=\x1b[?1l\x1b>\x1b[?25h\x1b[?1049l"[..]
+        );
+
+        // keypad transmit is opt-in
+        let out = output(rxvt(), |t| drop(t.session(SessionOptions::new().hide_cursor(true)).unwrap()));
+        assert_eq!(out, b"\x1b[?25l\x1b[?25h");
+    }
+
+    #[test]
+    fn validate() {
+        let no_env = || EnvOverrides::from_map(Vec::<(String, String)>::new());
+        let options = SessionOptions::new().alternate_screen(true).hide_cursor(true);
+
+        // linux has no alternate screen, and no `cols` or `lines` for a size that isn't a tty's
+        let out = output(linux(), |t| {
+            t.set_env_overrides(no_env());
+            let report = t.validate(Profile::FullScreen);
+            assert!(report.lacks(Requirement::AlternateScreen) && report.lacks(Requirement::Size));
+            assert!(!report.is_usable());
+
+            t.set_env_overrides(EnvOverrides::from_map(vec![("COLUMNS", "80"), ("LINES", "25")]));
+            assert!(t.validate(Profile::FullScreen).is_usable());
+            drop(t.session(options).unwrap());
+
+            // under `Strict` nothing is set up, and the error says why
+            t.set_degradation(Degradation::Strict);
+            match t.session(options).map(|_| ()).unwrap_err() {
+                Error::Unsuitable(report) => {
+                    assert_eq!(report.profile, Profile::FullScreen);
+                    assert_eq!(
+                        format!("{}", Error::Unsuitable(report)),
+                        "Unsuitable terminal: the terminal is missing what a full-screen application needs: the \
+                         alternate screen (smcup and rmcup), draw over the primary screen, and clear it afterwards \
+                         instead"
+                    );
+                }
+                e => panic!("{:?}", e),
+            }
+        });
+        assert_eq!(out, b"\x1b[?1049h\x1b[?25l\x1b[?1c\x1b[?25h\x1b[?0c\x1b[?1049l");
+
+        // dumb can't move its cursor, and without ANSI sequences under `Strict` nor can rxvt's missing `u7`
+        output(dumb(), |t| {
+            t.set_degradation(Degradation::Strict);
+            assert!(!t.validate(Profile::FullScreen).is_usable());
+            assert!(t.session(options).is_err());
+        });
+        output(rxvt(), |t| {
+            let report = t.validate(Profile::InlineUi);
+            assert!(report.is_usable(), "{}", report);
+            t.set_degradation(Degradation::Strict);
+            assert_eq!(t.validate(Profile::InlineUi).is_usable(), report.is_complete());
+        });
+    }
+
+    #[test]
+    fn draw_image() {
+        let image = ansi::Image::new(1, 1, ansi::PixelFormat::Rgb, vec![255, 0, 0]).unwrap();
+
+        let ((), out) = with_input(xterm(), b"\x1bP>|kitty(0.31.0)\x1b\\\x1b[>1;4000;31c\x1b[?62;c", |t| {
+            t.identify(Duration::from_millis(500)).unwrap();
+            t.draw_image(&image, (2, 5)).unwrap();
+        });
+        assert!(out.ends_with(b"\x1b[3;6H\x1b_Ga=T,f=24,s=1,v=1,q=2;/wAA\x1b\\"));
+
+        // foot speaks sixel, and says so in its primary device attributes
+        let ((), out) = with_input(xterm(), b"\x1bP>|foot(1.16.2)\x1b\\\x1b[>1;11602;0c\x1b[?62;4;22c", |t| {
+            t.identify(Duration::from_millis(500)).unwrap();
+            t.draw_image(&image, (0, 0)).unwrap();
+        });
+        assert!(out.ends_with(b"\x1b[1;1H\x1bP0;1;0q\"1;1;1;1#0;2;100;0;0#0@\x1b\\"));
+
+        let ((), out) = with_input(xterm(), b"\x1bP>|XTerm(354)\x1b\\\x1b[>41;354;0c\x1b[?64;1;2;22c", |t| {
+            t.identify(Duration::from_millis(500)).unwrap();
+            let err = t.draw_image(&image, (0, 0)).unwrap_err();
+            match err {
+                Error::Unsupported(Capability::Images) => (),
+                e => panic!("{:?}", e),
+            }
+        });
+        assert_eq!(out, b"\x1b[>0q\x1b[>c\x1b[c");
+    }
+
+    #[test]
+    fn initc_scaling() {
+        assert_eq!(initc_scale(0), 0);
+        assert_eq!(initc_scale(255), 1000);
+        assert_eq!(initc_scale(1), 4);
+        assert_eq!(initc_scale(51), 200);
+        assert_eq!(initc_scale(128), 502);
+        assert_eq!(initc_scale(254), 997);
+
+        // terminfo entries scale back down with `v * 255 / 1000`, which has to give back what we started with
+        for v in 0..=255u8 {
+            assert_eq!(initc_scale(v) * 255 / 1000, u32::from(v), "{}", v);
+        }
+    }
+
+    #[test]
+    fn palette() {
+        let red = ansi::Rgb::new(255, 0, 0);
+        let teal = ansi::Rgb::new(0, 128, 128);
+
+        // the linux console has `ccc` and `initc`
+        assert_eq!(output(linux(), |t| t.set_palette_color(1, red).unwrap()), b"\x1b]P1ff0000");
+        assert_eq!(output(linux(), |t| t.set_palette_color(12, teal).unwrap()), b"\x1b]Pc008080");
+        assert_eq!(output(linux(), |t| t.reset_palette().unwrap()), b"\x1b]R");
+
+        assert_eq!(
+            output(rxvt(), |t| t.set_palette_color(1, red).unwrap()),
+            &b"\x1b]4;1;rgb:ffff/0000/0000\x1b\\"[..]
+        );
+        assert_eq!(
+            output(rxvt(), |t| t.set_palette_color(200, teal).unwrap()),
+            &b"\x1b]4;200;rgb:0000/8080/8080\x1b\\"[..]
+        );
+        assert_eq!(output(rxvt(), |t| t.reset_palette().unwrap()), b"\x1b]104;\x1b\\");
+
+        let timeout = Duration::from_millis(500);
+        let (c, out) = with_input(rxvt(), b"\x1b]4;12;rgb:0000/8080/ffff\x07", |t| {
+            t.query_palette_color(12, timeout).unwrap()
+        });
+        assert_eq!(c, ansi::Rgb::new(0, 0x80, 0xff));
+        assert_eq!(out, b"\x1b]4;12;?\x1b\\");
     }
 
-    pub fn update(&self, settings: Settings) -> Result<()> {
-        self.err()?;
+    #[test]
+    fn status_line() {
+        // tmux sets the window title as its status line
+        let out = output(tmux(), |t| {
+            let mut status = t.status_line().unwrap();
+            assert_eq!(status.width(), None);
+            status.set("12:00 \x1b[1mmail\x1b[0m\n").unwrap();
+            status.clear().unwrap();
+        });
+        assert_eq!(out, &b"\x1b]0;12:00 mail\x07\x1b]0;\x07"[..]);
 
-        termios::tcsetattr(
-            self.as_raw_fd(),
-            termios::SetArg::TCSAFLUSH,
-            &settings.termios,
-        ).context(ErrorKind::FailedToSetTermios)?;
-        Ok(())
-    }
+        let mut narrow = tmux();
+        narrow.set_number(terminfo::WidthStatusLine, 5).unwrap();
+        let out = output(narrow, |t| {
+            t.status_line().unwrap().set("a long status").unwrap()
+        });
+        assert_eq!(out, &b"\x1b]0;a lon\x07"[..]);
 
-    pub fn flush(&self) {
-        match self.stdout.lock().unwrap().flush() {
-            Ok(_) => (),
-            Err(e) => self.set_err(e.context(ErrorKind::WriteFailed)),
-        }
+        assert!(output(xterm(), |t| assert!(t.status_line().is_none())).is_empty());
+        assert!(output(rxvt(), |t| assert!(t.status_line().is_none())).is_empty());
     }
 
-    pub fn read_keys<'a>(&'a self) -> Keys<'a, I, O> {
-        Keys::new(self)
+    #[test]
+    fn bell() {
+        assert_eq!(output(rxvt(), |t| t.bell().unwrap()), b"\x07");
+        assert_eq!(output(terminfo::TermInfoBuf::new(), |t| t.bell().unwrap()), b"\x07");
+        assert_eq!(output(tmux(), |t| t.visual_bell().unwrap()), b"\x1bg");
+
+        let start = Instant::now();
+        assert_eq!(output(xterm(), |t| t.visual_bell().unwrap()), b"\x1b[?5h\x1b[?5l");
+        assert!(start.elapsed() >= Duration::from_millis(100));
+
+        // no `flash`, so the screen is inverted and put back in two frames, without touching the SGR state
+        let start = Instant::now();
+        let out = output(terminfo::TermInfoBuf::new(), |t| t.visual_bell().unwrap());
+        assert!(start.elapsed() >= FLASH_DURATION);
+        assert_eq!(
+            out,
+            &b"\x1b[?2026h\x1b[?5h\x1b[?2026l\x1b[?2026h\x1b[?5l\x1b[?2026l"[..]
+        );
+
+        let out = output(xterm(), |t| {
+            t.set_quiet(true);
+            t.bell().unwrap();
+            t.visual_bell().unwrap();
+            t.set_quiet(false);
+            t.bell().unwrap();
+        });
+        assert_eq!(out, b"\x07");
     }
 
-    pub fn clear_line_after_cursor(&self) {
-        self.write_info_str(terminfo::ClrEol);
+    /// Run `f` against a terminal whose delays are recorded rather than slept, returning them.
+    fn sleeps<F>(info: terminfo::TermInfoBuf, f: F) -> Vec<Duration>
+    where
+        F: FnOnce(&Term<&mut FakeStdin, &mut FakeStdout>),
+    {
+        let slept = Arc::new(Mutex::new(Vec::new()));
+        let record = slept.clone();
+        output(info, |t| {
+            t.set_exec_options(ExecOptions::new().sleep_fn(move |d| record.lock().unwrap().push(d)));
+            f(t)
+        });
+        let slept = slept.lock().unwrap().clone();
+        slept
     }
 
-    pub fn save_cursor(&self) {
-        self.write_info_str(terminfo::SaveCursor);
+    #[test]
+    fn visual_bell_timing() {
+        let ms = Duration::from_millis;
+        let start = Instant::now();
+        assert_eq!(sleeps(xterm(), |t| t.visual_bell().unwrap()), vec![ms(100)]);
+        assert!(start.elapsed() < ms(100));
+        assert_eq!(sleeps(terminfo::TermInfoBuf::new(), |t| t.visual_bell().unwrap()), vec![FLASH_DURATION]);
+        assert!(sleeps(xterm(), |t| t.bell().unwrap()).is_empty());
+
+        // padding would be over before the flash could be seen, so it's slept even with a pad character
+        let mut info = terminfo::TermInfoBuf::new();
+        info.set_string(terminfo::FlashScreen, "\x1b[?5h$<20>\x1b[?5l$<5000/>").unwrap();
+        assert_eq!(sleeps(info.clone(), |t| t.visual_bell().unwrap()), vec![ms(20), MAX_SLEEP]);
+        let slept = sleeps(info.clone(), |t| {
+            t.set_exec_options(t.exec_options().max_sleep(ms(250)));
+            t.visual_bell().unwrap();
+        });
+        assert_eq!(slept, vec![ms(20), ms(250)]);
+        // but not when it's written like any other capability, where it's padded (at 0 baud, with nothing)
+        assert!(sleeps(info.clone(), |t| t.write_cap(terminfo::FlashScreen, &[]).unwrap()).is_empty());
+
+        // with `npc` the delays in other capabilities are slept too, with the same options
+        info.set_string(terminfo::ClearScreen, "\x1b[H$<45/>\x1b[J").unwrap();
+        info.set_boolean(terminfo::NoPadChar, true).unwrap();
+        assert_eq!(sleeps(info, |t| t.write_cap(terminfo::ClearScreen, &[]).unwrap()), vec![ms(45)]);
     }
 
-    pub fn restore_cursor(&self) {
-        self.write_info_str(terminfo::RestoreCursor);
+    #[test]
+    fn styled_writer() {
+        let out = with_tty(rxvt(), |t| {
+            t.set_color_choice(ansi::ColorChoice::Always);
+            let mut w = t.styled_writer();
+            w.set_style(ansi::Style::new().bold().foreground(1));
+            write!(w, "error").unwrap();
+            w.set_style(ansi::Style::new().foreground(1));
+            w.set_style(ansi::Style::new());
+            writeln!(w, ": {}", 42).unwrap();
+        });
+        assert_eq!(out, &b"\x1b[1;31merror\x1b[0m: 42\n"[..]);
+
+        // the style is put back when the writer goes away
+        let out = with_tty(rxvt(), |t| {
+            t.set_color_choice(ansi::ColorChoice::Always);
+            let mut w = t.styled_writer();
+            w.set_style(ansi::Style::new().underline());
+            fmt::Write::write_str(&mut w, "link").unwrap();
+        });
+        assert_eq!(out, &b"\x1b[4mlink\x1b[0m"[..]);
+        assert!(with_tty(rxvt(), |t| drop(t.styled_writer())).is_empty());
     }
 
-    pub fn prompt<T: AsRef<str>>(&self, prompt: T) -> Result<String> {
-        self.writer().print(prompt).done()?;
-        self.readline()
+    #[test]
+    fn styled_writer_quirks() {
+        let glitchy = || terminfo::TermInfoBuf::from(terminfo::TermInfo::parse(GLITCHY_TERMINFO).unwrap());
+        let out = with_tty(glitchy(), |t| {
+            t.set_color_choice(ansi::ColorChoice::Always);
+            let mut w = t.styled_writer();
+            write!(w, "~/src").unwrap();
+            w.set_style(ansi::Style::new().invert());
+            write!(w, "a  b ").unwrap();
+            write!(w, "c").unwrap();
+            w.set_style(ansi::Style::new().underline());
+            write!(w, "d e").unwrap();
+        });
+        assert_eq!(
+            out,
+            &b"-/src\x1b[7ma  \x1b[7mb \x1b[7mc\x1b[m_\x08d__\x08e"[..]
+        );
+
+        let out = with_tty(glitchy(), |t| {
+            t.set_color_choice(ansi::ColorChoice::Always);
+            let mut w = t.styled_writer();
+            w.set_quirks(false);
+            w.set_style(ansi::Style::new().invert());
+            write!(w, "~ a").unwrap();
+        });
+        assert_eq!(out, &b"\x1b[7m~ a\x1b[m"[..]);
+
+        // rxvt has none of the glitches
+        let out = with_tty(rxvt(), |t| {
+            t.set_color_choice(ansi::ColorChoice::Always);
+            let mut w = t.styled_writer();
+            w.set_style(ansi::Style::new().invert());
+            write!(w, "~ a").unwrap();
+        });
+        assert_eq!(out, &b"\x1b[7m~ a\x1b[0m"[..]);
     }
 
-    #[inline]
-    pub fn colors(&self) -> usize {
-        // There has to be at least two colors... right???
-        self.info.number(terminfo::MaxColors).unwrap_or(2) as usize
+    #[test]
+    fn styled_writer_sanitize() {
+        let out = with_tty(rxvt(), |t| {
+            t.set_color_choice(ansi::ColorChoice::Always);
+            let mut w = t.styled_writer();
+            w.set_style(ansi::Style::new().bold());
+            w.set_sanitize(Some(ansi::SanitizePolicy::caret().keep_whitespace(true)));
+            // a file name that sets the window title
+            writeln!(w, "a\x1b]0;pwned\x07b").unwrap();
+            w.set_sanitize(None);
+            write!(w, "\x07").unwrap();
+        });
+        assert_eq!(out, &b"\x1b[1ma^[]0;pwned^Gb\n\x07\x1b[0m"[..]);
     }
-}
 
-impl<I, O> AsRawFd for Term<I, O>
-where
-    I: io::Read + AsRawFd,
-    O: io::Write + AsRawFd,
-{
-    fn as_raw_fd(&self) -> RawFd {
-        self.stdin_fd
+    #[test]
+    fn encoding() {
+        use std::io::Write;
+
+        let out = with_tty(rxvt(), |t| {
+            t.set_color_choice(ansi::ColorChoice::Always);
+            t.set_env_overrides(EnvOverrides::from_map(vec![("LANG", "C")]));
+            t.set_encoding(Some(t.locale_encoding(Fallback::Transliterate)));
+            let mut w = t.styled_writer();
+            w.set_style(ansi::Style::new().bold());
+            write!(w, "┌─┐\n│é│\n└─┘").unwrap();
+            drop(w);
+            // the alternate character set only has to be enabled once
+            t.styled_writer().write_all("─".as_bytes()).unwrap();
+            t.print("“ok”").unwrap();
+        });
+        assert_eq!(
+            out,
+            &b"\x1b[1m\x1b(B\x1b)0\x0elqk\x0f\n\x0ex\x0fe\x0ex\x0f\n\x0emqj\x0f\x1b[0m\x0eq\x0f\x1b[0m\x0f\"ok\""[..]
+        );
+
+        // without an alternate character set the lines are ASCII
+        let out = with_tty(dumb(), |t| {
+            t.set_encoding(Some(Encoding::new(Charset::Ascii, Fallback::Transliterate)));
+            t.print("┌─┐ naïve").unwrap();
+        });
+        assert_eq!(out, b"+-+ naive");
+
+        let out = with_tty(dumb(), |t| {
+            t.set_env_overrides(EnvOverrides::from_map(vec![("LC_ALL", "fr_FR.ISO-8859-1")]));
+            t.set_encoding(Some(t.locale_encoding(Fallback::Replace)));
+            assert_eq!(t.encoding().unwrap().charset, Charset::Latin1);
+            t.print("café ┌").unwrap();
+            // bytes that aren't UTF-8 are written as they are, after the text before them
+            t.styled_writer().write_all(b"\xc3\xa9\xff").unwrap();
+            // and a character split between writes is put back together
+            let mut w = t.styled_writer();
+            w.write_all(b"\xc3").unwrap();
+            w.write_all(b"\xa9\xc3").unwrap();
+        });
+        assert_eq!(out, b"caf\xe9 ?\xe9\xff\xe9\xc3");
+
+        // UTF-8 is left alone
+        let out = with_tty(dumb(), |t| {
+            t.set_env_overrides(EnvOverrides::from_map(vec![("LANG", "en_US.UTF-8")]));
+            t.set_encoding(Some(t.locale_encoding(Fallback::Replace)));
+            t.print("┌─┐").unwrap();
+        });
+        assert_eq!(out, "┌─┐".as_bytes());
     }
-}
 
-#[cfg(test)]
-mod test {
-    use std::io;
-    use term::*;
-    use terminfo;
+    #[test]
+    fn styled_writer_colors() {
+        let styled = |t: &Term<MockBackend, MockBackend>| {
+            let mut w = t.styled_writer();
+            w.set_style(ansi::Style::new().bold().foreground(196).background((0, 0, 250)));
+            write!(w, "!").unwrap();
+        };
 
-    const TERMINFO: &'static [u8] = include_bytes!("../test-data/rxvt");
+        // rxvt only has 8 colors (and their bright versions)
+        let out = with_tty(rxvt(), |t| {
+            t.set_color_choice(ansi::ColorChoice::Always);
+            assert_eq!(t.color_depth(), ansi::ColorDepth::Ansi16);
+            styled(t);
+        });
+        assert_eq!(out, &b"\x1b[1;91;44m!\x1b[0m"[..]);
 
-    struct FakeStdin {
-        buffer: Vec<u8>,
+        let out = with_tty(rxvt(), |t| {
+            t.set_color_choice(ansi::ColorChoice::Never);
+            assert_eq!(t.color_depth(), ansi::ColorDepth::None);
+            styled(t);
+        });
+        assert_eq!(out, b"!");
     }
 
-    struct FakeStdout {
-        buffer: Vec<u8>,
+    #[test]
+    fn styled_writer_newlines() {
+        use std::io::Write;
+
+        let out = with_tty(rxvt(), |t| {
+            let settings = t.settings();
+            assert!(t.translates_newlines());
+            t.styled_writer().write_all(b"a\nb\n").unwrap();
+
+            t.update(settings.clone().raw()).unwrap();
+            assert!(!t.translates_newlines());
+            t.styled_writer().write_all(b"c\nd\n\n").unwrap();
+
+            t.update(settings).unwrap();
+            t.styled_writer().write_all(b"e\n").unwrap();
+        });
+        assert_eq!(out, &b"a\nb\nc\r\nd\r\n\r\ne\n"[..]);
+
+        // output to a pipe is never translated
+        let ((), out) = with_input(rxvt(), b"", |t| {
+            assert!(t.translates_newlines());
+            t.styled_writer().write_all(b"a\n").unwrap();
+        });
+        assert_eq!(out, b"a\n");
     }
 
-    impl FakeStdin {
-        fn new() -> FakeStdin {
-            FakeStdin { buffer: Vec::new() }
-        }
+    #[test]
+    fn styled_writer_bulk() {
+        use std::io::Write;
+
+        let bulk = |f: &dyn Fn(&mut StyledWriter<MockBackend, MockBackend>)| {
+            let mut n = 0;
+            let out = with_tty(rxvt(), |t| {
+                t.update(t.settings().raw()).unwrap();
+                t.set_env_overrides(EnvOverrides::from_map(vec![("LANG", "C")]));
+                t.set_encoding(Some(t.locale_encoding(Fallback::Replace)));
+                let mut w = t.styled_writer();
+                w.set_sanitize(Some(ansi::SanitizePolicy::caret()));
+                let before = BULK_WRITES.with(|n| n.get());
+                f(&mut w);
+                n = BULK_WRITES.with(|n| n.get()) - before;
+            });
+            (n, out)
+        };
+
+        // plain ASCII has nothing to sanitize, encode or translate
+        assert_eq!(bulk(&|w| w.write_all(b"plain text").unwrap()), (1, b"plain text".to_vec()));
+        // but text that's sanitized or encoded is a copy
+        assert_eq!(bulk(&|w| w.write_all(b"a\x07b").unwrap()), (0, b"a^Gb".to_vec()));
+        assert_eq!(bulk(&|w| w.write_all("é".as_bytes()).unwrap()), (0, b"?".to_vec()));
+        // newlines are sent as \r\n, a line at a time
+        let lines = bulk(&|w| {
+            w.set_sanitize(None);
+            w.write_all(b"a\nb").unwrap()
+        });
+        assert_eq!(lines, (0, b"a\r\nb".to_vec()));
     }
 
-    impl<'a> AsRawFd for &'a mut FakeStdin {
-        fn as_raw_fd(&self) -> RawFd {
-            0
-        }
+    #[test]
+    fn writer_colors() {
+        use std::io::Write;
+
+        // rxvt only has 8 colors
+        let out = output(rxvt(), |t| {
+            let mut w = t.writer().foreground((250, 128, 114));
+            w.write_fg_bg().unwrap();
+            w.flush().unwrap();
+        });
+        assert_eq!(out, b"\x1b[31m");
+
+        let out = output(rxvt(), |t| {
+            let mut w = t.writer().background(30);
+            w.write_fg_bg().unwrap();
+            w.flush().unwrap();
+        });
+        assert_eq!(out, b"\x1b[46m");
     }
 
-    impl FakeStdout {
-        fn new() -> FakeStdout {
-            FakeStdout { buffer: Vec::new() }
-        }
+    #[test]
+    fn repeat_char() {
+        let xterm: terminfo::TermInfoBuf = terminfo::TermInfo::parse(XTERM_TERMINFO).unwrap().into();
+
+        // xterm has `rep`, rxvt doesn't
+        let with_rep = output(xterm.clone(), |t| t.repeat_char(' ', 200).unwrap());
+        let without_rep = output(rxvt(), |t| t.repeat_char(' ', 200).unwrap());
+        assert_eq!(with_rep, b" \x1b[199b");
+        assert_eq!(without_rep.len(), 200);
+        assert!(without_rep.iter().all(|&c| c == b' '));
+
+        // writing short runs directly is cheaper than `rep`
+        assert_eq!(output(xterm.clone(), |t| t.repeat_char('-', 3).unwrap()), b"---");
+        // `rep` can't repeat multi-byte characters
+        assert_eq!(
+            String::from_utf8(output(xterm.clone(), |t| t.repeat_char('─', 10).unwrap())).unwrap(),
+            "──────────"
+        );
+        assert_eq!(output(xterm, |t| t.repeat_char('x', 0).unwrap()), b"");
     }
 
-    impl<'a> AsRawFd for &'a mut FakeStdout {
-        fn as_raw_fd(&self) -> RawFd {
-            1
-        }
+    #[test]
+    fn absolute_movement() {
+        let xterm: terminfo::TermInfoBuf = terminfo::TermInfo::parse(XTERM_TERMINFO).unwrap().into();
+        assert_eq!(output(xterm.clone(), |t| t.move_col(4).unwrap()), b"\x1b[5G");
+        assert_eq!(output(xterm.clone(), |t| t.move_row(9).unwrap()), b"\x1b[10d");
+        assert_eq!(output(xterm, |t| t.move_to(4, 9).unwrap()), b"\x1b[10;5H");
+
+        let empty = terminfo::TermInfoBuf::new();
+        assert_eq!(output(empty.clone(), |t| t.move_col(0).unwrap()), b"\x1b[1G");
+        assert_eq!(output(empty.clone(), |t| t.move_row(2).unwrap()), b"\x1b[3d");
+        assert_eq!(output(empty, |t| t.move_to(4, 9).unwrap()), b"\x1b[10;5H");
+
+        // without `hpa` and `vpa`, `cup` is used with where the cursor is
+        let vt100 = || terminfo::TermInfo::parse(VT100_TERMINFO).unwrap().into();
+        let (_, out) = with_input(vt100(), b"\x1b[5;3R", |t| t.move_col(7).unwrap());
+        assert!(out.starts_with(b"\x1b[6n\x1b[5;8H"), "{:?}", String::from_utf8_lossy(&out));
+        let (_, out) = with_input(vt100(), b"\x1b[5;3R", |t| t.move_row(0).unwrap());
+        assert!(out.starts_with(b"\x1b[6n\x1b[1;3H"), "{:?}", String::from_utf8_lossy(&out));
+        let (r, _) = with_input(vt100(), b"", |t| t.move_col(7));
+        assert!(r.is_err());
     }
 
-    impl<'a> io::Read for &'a mut FakeStdin {
-        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-            let len = if buf.len() > self.buffer.len() {
-                self.buffer.len()
-            } else {
-                buf.len()
+    #[test]
+    fn positions_at_the_edges() {
+        let entries = vec![
+            ("xterm", xterm()),
+            ("rxvt", rxvt()),
+            ("linux", linux()),
+            ("tmux", tmux()),
+            ("none", terminfo::TermInfoBuf::new()),
+        ];
+        for (name, info) in entries {
+            let mock = MockBackend::new();
+            mock.set_size(100, 30);
+            let t = mock.term(info);
+            t.set_env_overrides(::EnvOverrides::from_map(Vec::<(String, String)>::new()));
+            let (cols, rows) = t.size().unwrap();
+            let screen_after = |f: &dyn Fn()| {
+                let mut screen = ansi::TestScreen::new(rows, cols);
+                // start somewhere that isn't a corner, so not moving at all is noticed
+                screen.feed(b"\x1b[5;5H");
+                mock.take_written();
+                f();
+                screen.feed(&mock.take_written());
+                screen.cursor()
             };
 
-            self.buffer
-                .drain(..len)
-                .enumerate()
-                .for_each(|(i, c)| buf[i] = c);
-            Ok(len)
+            for &(x, y) in &[(0, 0), (cols - 1, 0), (0, rows - 1), (cols - 1, rows - 1)] {
+                assert_eq!(screen_after(&|| t.move_to(x, y).unwrap()), (y, x), "{}: move_to({}, {})", name, x, y);
+                assert_eq!(screen_after(&|| t.move_col(x).unwrap()), (4, x), "{}: move_col({})", name, x);
+                assert_eq!(screen_after(&|| t.move_row(y).unwrap()), (y, 4), "{}: move_row({})", name, y);
+            }
+
+            // past the edge is sent as it is, and the terminal stops at the edge
+            assert_eq!(screen_after(&|| t.move_to(cols + 5, rows + 5).unwrap()), (rows - 1, cols - 1), "{}", name);
+            t.move_to(cols + 5, rows + 5).unwrap();
+            assert_eq!(mock.take_written(), b"\x1b[36;106H", "{}", name);
         }
     }
 
-    impl<'a> io::Write for &'a mut FakeStdout {
-        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-            self.buffer.extend(buf);
-            Ok(buf.len())
+    #[test]
+    fn scroll_region() {
+        for info in vec![xterm(), terminfo::TermInfoBuf::new()] {
+            let mock = MockBackend::new();
+            let t = mock.term(info);
+            t.set_scroll_region(1, 22).unwrap();
+            assert_eq!(mock.take_written(), b"\x1b[2;23r");
+            t.set_scroll_region(0, 23).unwrap();
+            assert_eq!(mock.take_written(), b"\x1b[1;24r");
         }
 
-        fn flush(&mut self) -> io::Result<()> {
-            Ok(())
-        }
+        // only the rows in the region scroll
+        let mock = MockBackend::new();
+        let t = mock.term(xterm());
+        let mut screen = ansi::TestScreen::new(4, 10);
+        screen.feed(b"top\r\none\r\ntwo\r\nbottom");
+        t.set_scroll_region(1, 2).unwrap();
+        t.move_to(0, 2).unwrap();
+        screen.feed(&mock.take_written());
+        screen.feed(b"\nthree");
+        screen.assert_snapshot("top\ntwo\nthree\nbottom");
     }
 
     #[test]
@@ -1079,4 +5908,552 @@ mod test {
             "\x1b[1m\x1b[31mHi\x1b[39m\x1b[22m?"
         );
     }
+
+    #[test]
+    fn renderer() {
+        let out = output(xterm(), |t| {
+            t.set_color_choice(ansi::ColorChoice::Always);
+            let mut r = t.renderer();
+            r.move_to(2, 1);
+            r.set_style(ansi::Style::new().bold());
+            r.put_str("ab").unwrap();
+            r.set_style(ansi::Style::new().bold());
+            r.put_str("cd").unwrap();
+            // already there
+            r.move_to(6, 1);
+            r.put_str("\u{4e2d}e").unwrap();
+            r.move_to(0, 0);
+            r.set_style(ansi::Style::new());
+            r.put_str("x").unwrap();
+        });
+        assert_eq!(
+            out,
+            &b"\x1b[2;3H\x1b[1mabcd\xe4\xb8\xade\x1b[H\x1b[0mx"[..]
+        );
+
+        // short moves don't use `cup`
+        let out = output(xterm(), |t| {
+            let mut r = t.renderer();
+            r.move_to(10, 5);
+            r.put_str("abc").unwrap();
+            r.move_to(11, 5);
+            r.put_str("x").unwrap();
+            r.move_to(0, 5);
+            r.put_str("y").unwrap();
+            r.move_to(1, 2);
+            r.put_str("z").unwrap();
+        });
+        assert_eq!(out, &b"\x1b[6;11Habc\x08\x08x\ry\x1b[3dz"[..]);
+
+        // `ech` leaves the cursor where the erasing started, so the next move is from there
+        let out = output(xterm(), |t| {
+            let mut r = t.renderer();
+            r.move_to(0, 0);
+            r.put_str("ab").unwrap();
+            r.move_to(10, 0);
+            r.repeat(' ', 50).unwrap();
+            r.move_to(11, 0);
+            r.put_str("x").unwrap();
+        });
+        let mut screen = ansi::TestScreen::new(2, 80);
+        screen.feed(&out);
+        screen.assert_snapshot("ab         x");
+
+        // inserting uses insert mode
+        let out = output(xterm(), |t| {
+            let mut r = t.renderer();
+            assert!(r.can_insert());
+            r.move_to(3, 0);
+            r.insert_str("ab").unwrap();
+            r.put_str("c").unwrap();
+        });
+        assert_eq!(out, &b"\x1b[1;4H\x1b[4hab\x1b[4lc"[..]);
+        output(terminfo::TermInfoBuf::new(), |t| {
+            let mut r = t.renderer();
+            assert!(!r.can_insert());
+            assert!(r.insert_str("ab").is_err());
+        });
+
+        // without `cup` the ANSI sequence is used
+        let out = output(terminfo::TermInfoBuf::new(), |t| {
+            let mut r = t.renderer();
+            r.move_to(4, 2);
+            r.put_str("z").unwrap();
+        });
+        assert_eq!(out, b"\x1b[3;5Hz");
+    }
+
+    #[test]
+    fn renderer_graphemes() {
+        // each is followed by a `|` where the cursor should be after it, so there's no move between them
+        let text = [
+            ("👨\u{200d}👩\u{200d}👧", 2),
+            ("🇯🇵", 5),
+            ("👍🏽", 8),
+            ("क्ष", 11),
+            ("नमस्ते", 16),
+        ];
+        let out = output(xterm(), |t| {
+            let mut r = t.renderer();
+            r.move_to(0, 0);
+            for &(s, column) in &text {
+                r.put_str(s).unwrap();
+                r.move_to(column, 0);
+                r.put_str("|").unwrap();
+            }
+        });
+        let expected: String = text.iter().map(|&(s, _)| format!("{}|", s)).collect();
+        assert_eq!(out, format!("\x1b[H{}", expected).as_bytes());
+    }
+
+    #[test]
+    fn cursor_position() {
+        let timeout = Duration::from_millis(500);
+        let (pos, out) = with_input(xterm(), b"ab\x1b[24;80R", |t| t.cursor_position(timeout).unwrap());
+        assert_eq!((pos, out), ((79, 23), b"\x1b[6n".to_vec()));
+
+        // a terminal that reports column first, counted from 0
+        let mut info = terminfo::TermInfoBuf::new();
+        info.set_string(terminfo::User7, "\x1b?").unwrap();
+        info.set_string(terminfo::User6, "\x1b/%p2%d,%p1%d!").unwrap();
+        let (pos, out) = with_input(info.clone(), b"\x1b/5,2!", |t| t.cursor_position(timeout).unwrap());
+        assert_eq!((pos, out), ((5, 2), b"\x1b?".to_vec()));
+        // which doesn't answer in xterm's format
+        let (pos, _) = with_input(info, b"\x1b[3;3R", |t| t.cursor_position(Duration::from_millis(50)));
+        assert!(pos.is_err());
+
+        // without u6 and u7 the xterm formats are used
+        let (pos, out) = with_input(terminfo::TermInfoBuf::new(), b"\x1b[2;7R", |t| {
+            t.cursor_position(timeout).unwrap()
+        });
+        assert_eq!((pos, out), ((6, 1), b"\x1b[6n".to_vec()));
+
+        // inside a frame the query is sent straight away, along with what the frame wrote before it
+        let ((pos, sent), out) = with_input(xterm(), b"\x1b[5;10R", |t| {
+            t.frame(|t| {
+                t.print("x").unwrap();
+                let pos = t.cursor_position(timeout).unwrap();
+                t.print("y").unwrap();
+                (pos, t.io_stats().bytes)
+            })
+            .unwrap()
+        });
+        assert_eq!(pos, (9, 4));
+        let (sent, rest) = out.split_at(sent as usize);
+        assert!(sent.starts_with(b"\x1b[?2026h"));
+        assert!(sent.ends_with(b"x\x1b[6n"));
+        assert!(rest.ends_with(b"y\x1b[?2026l"));
+    }
+
+    #[test]
+    fn terminal_answer() {
+        let timeout = Duration::from_millis(500);
+        let (answer, out) = with_input(xterm(), b"ab\x1b[?64;22c", |t| t.terminal_answer(timeout).unwrap());
+        assert_eq!((answer, out), (vec![b"64;22".to_vec()], b"\x1b[c".to_vec()));
+
+        // a terminal with its own request and answer
+        let mut info = terminfo::TermInfoBuf::new();
+        info.set_string(terminfo::User9, "\x1bZ").unwrap();
+        info.set_string(terminfo::User8, "\x1b/%[XYZ]%d").unwrap();
+        let (answer, out) = with_input(info, b"\x1b/Z12", |t| t.terminal_answer(timeout).unwrap());
+        assert_eq!((answer, out), (vec![b"Z".to_vec(), b"12".to_vec()], b"\x1bZ".to_vec()));
+    }
+
+    #[test]
+    fn probe_sequence_safety() {
+        let timeout = Duration::from_millis(100);
+        // the terminal stops answering after the first family, which is probed again next time
+        let (safety, out) = with_input(xterm(), b"\x1b[1;3R\x1b[1;3R\x1b[1;3R", |t| {
+            let safety = t.probe_sequence_safety(timeout).unwrap();
+            assert_eq!(t.sequence_safety(), None);
+            safety
+        });
+        assert_eq!(safety.handling(SequenceFamily::Osc), Some(Handling::Consumed));
+        assert_eq!(safety.handling(SequenceFamily::PrivateMode), None);
+        assert_eq!(
+            out,
+            &b"\x1b[6n\x1b[?2026h\x1b[6n\x1b]8;;\x1b\\\x1b[6n\x1b[?2026l\x1b[6n\x1b[1;3H\x1b[?2026l"[..]
+        );
+
+        // one that printed the beginning of the update isn't sent the end, and the cursor's moved back over it
+        let answers = b"\x1b[1;3R\x1b[1;10R\x1b[1;10R\x1b[1;17R\x1b[1;17R";
+        let (safety, out) = with_input(xterm(), answers, |t| {
+            let safety = t.probe_sequence_safety(timeout).unwrap();
+            assert_eq!(t.sequence_safety(), Some(safety));
+            safety
+        });
+        assert_eq!(safety.handling(SequenceFamily::PrivateMode), Some(Handling::Echoed));
+        assert!(out.ends_with(b"\x1b[6n\x1b[1;3H\x1b[K"), "{:?}", String::from_utf8_lossy(&out));
+        assert!(!out.windows(2).any(|w| w == b"\x1b7"));
+    }
+
+    #[test]
+    fn inline_viewport() {
+        let env = |lines: &str| EnvOverrides::from_map(vec![("COLUMNS", "20"), ("LINES", lines)]);
+        let (_, out) = with_input(xterm(), b"\x1b[9;3R", |t| {
+            t.set_env_overrides(env("10"));
+            let mut vp = t.inline_viewport(4, Duration::from_millis(500)).unwrap();
+            assert_eq!((vp.size(), vp.top()), ((20, 4), 6));
+            assert!(vp.move_to(0, 4).is_err());
+
+            // the screen got shorter, so the viewport is pulled up with it
+            t.set_env_overrides(env("8"));
+            vp.move_to(0, 3).unwrap();
+            assert_eq!(vp.top(), 4);
+        });
+        assert_eq!(
+            out,
+            &b"\x1b[6n\x1b[10;1H\n\n\n\x1b[7;1H\x1b[8;1H\
+               \x1b[8;1H\x1b[K\x1b[7;1H\x1b[K\x1b[6;1H\x1b[K\x1b[5;1H\x1b[K\x1b[6;3H"[..]
+        );
+
+        // a screen shorter than the viewport gets one as tall as it is
+        let (size, _) = with_input(xterm(), b"\x1b[1;1R", |t| {
+            t.set_env_overrides(env("3"));
+            let vp = t.inline_viewport(10, Duration::from_millis(500)).unwrap();
+            (vp.size(), vp.top())
+        });
+        assert_eq!(size, ((20, 3), 0));
+
+        // an empty one does nothing at all
+        let (_, out) = with_input(xterm(), b"", |t| {
+            t.set_env_overrides(env("10"));
+            let mut vp = t.inline_viewport(0, Duration::from_millis(500)).unwrap();
+            assert_eq!(vp.size(), (20, 0));
+            assert!(vp.move_to(0, 0).is_err());
+            vp.clear().unwrap();
+        });
+        assert_eq!(out, b"");
+    }
+
+    #[test]
+    fn style_transition() {
+        let bold = ansi::Style::new().bold();
+        let red = ansi::Style::new().foreground(1);
+        // xterm's `sgr` and `sgr0` are longer than the ANSI sequences
+        output(xterm(), |t| {
+            assert_eq!(t.style_transition(&ansi::Style::new(), &bold), b"\x1b[1m");
+            assert_eq!(t.style_transition(&bold, &ansi::Style::new()), b"\x1b[0m");
+            assert!(t.style_transition(&bold, &bold).is_empty());
+        });
+
+        let info = terminfo::TermInfoBuilder::new()
+            .name("short-sgr0")
+            .set_str(terminfo::ExitAttributeMode, "\x1b[m$<2>")
+            .use_entry(&terminfo::TermInfo::parse(XTERM_TERMINFO).unwrap())
+            .build()
+            .unwrap();
+        let out = output(info, |t| {
+            assert_eq!(t.style_transition(&bold, &ansi::Style::new()), b"\x1b[m");
+            // `sgr0` might leave the colors alone
+            assert_eq!(t.style_transition(&red, &ansi::Style::new()), b"\x1b[0m");
+            t.set_color_choice(ansi::ColorChoice::Always);
+            let mut r = t.renderer();
+            r.set_style(bold);
+            r.put_str("a").unwrap();
+        });
+        assert_eq!(out, &b"\x1b[H\x1b[1ma\x1b[m"[..]);
+
+        // tmux's `Smulx` turns the underline on and off, but going back to the default style is still `sgr0`
+        let underline = ansi::Style::new().underline();
+        output(tmux(), |t| {
+            assert_eq!(t.style_transition(&ansi::Style::new(), &underline), b"\x1b[4:1m");
+            assert_eq!(t.style_transition(&underline, &bold), b"\x1b[1m\x1b[4:0m");
+            assert_eq!(t.style_transition(&bold, &underline.bold()), b"\x1b[4:1m");
+            assert_eq!(t.style_transition(&underline, &ansi::Style::new()), b"\x1b[0m");
+        });
+    }
+
+    #[test]
+    fn renderer_frames() {
+        fn frame<I, O>(r: &mut Renderer<I, O>, changed: bool)
+        where
+            I: io::Read + AsRawFd,
+            O: io::Write + AsRawFd,
+        {
+            for y in 0..24 {
+                r.move_to(0, y);
+                r.set_style(if y % 2 == 0 {
+                    ansi::Style::new()
+                } else {
+                    ansi::Style::new().foreground(1).bold()
+                });
+                // 80 columns, with a wide character in the middle
+                let line: String = format!("{:02}\u{4e2d}", y)
+                    .chars()
+                    .chain(::std::iter::repeat('.'))
+                    .take(78)
+                    .collect();
+                r.put_str(&line).unwrap();
+            }
+            if changed {
+                r.move_to(5, 3);
+                r.put_str("X").unwrap();
+            }
+            r.flush().unwrap();
+        }
+
+        let draw = |frames: &[bool]| {
+            output(xterm(), |t| {
+                t.set_color_choice(ansi::ColorChoice::Always);
+                let mut r = t.renderer();
+                for &changed in frames {
+                    frame(&mut r, changed);
+                }
+            })
+        };
+
+        let once = draw(&[false]);
+        assert!(once.starts_with(b"\x1b[H00\xe4\xb8\xad...."));
+        assert!(once.ends_with(b"\x1b[0m"));
+
+        // the same frame again doesn't send anything
+        assert_eq!(draw(&[false, false]), once);
+
+        // only what changed is drawn
+        let changed = draw(&[false, true]);
+        let reset = once.len() - b"\x1b[0m".len();
+        assert_eq!(&changed[..reset], &once[..reset]);
+        assert_eq!(&changed[reset..], &b"\x1b[4;6HX\x1b[0m"[..]);
+    }
+
+    /// Draw a bit of everything, in color, the way a full-screen program would.
+    fn styled_program<I, O>(t: &Term<I, O>) -> Result<()>
+    where
+        I: io::Read + AsRawFd,
+        O: io::Write + AsRawFd,
+    {
+        t.clear_screen()?;
+        {
+            let mut w = t.styled_writer();
+            w.set_style(ansi::Style::new().bold().foreground(1));
+            write!(w, "error:").unwrap();
+            w.reset_style();
+            writeln!(w, " it broke").unwrap();
+        }
+        t.move_col(2)?;
+        t.print("indented")?;
+        t.set_private_mode(ansi::MODE_BRACKETED_PASTE, true)?;
+        t.forward_tabs(1)?;
+        t.print("tabbed")?;
+        t.set_scroll_region(0, 10)?;
+        t.delete_chars(2)?;
+        {
+            let mut r = t.renderer();
+            r.move_to(0, 1);
+            r.set_style(ansi::Style::new().underline().foreground((0, 0, 250)));
+            r.put_str("one").unwrap();
+            r.move_to(4, 1);
+            r.put_str("two").unwrap();
+            r.move_to(2, 3);
+            r.put_str("three").unwrap();
+            r.move_to(0, 0);
+            r.put_str("four").unwrap();
+            r.flush()?;
+        }
+        t.flush();
+        t.err()
+    }
+
+    #[test]
+    fn degradation() {
+        let env = EnvOverrides::from_map(vec![("COLORTERM", "truecolor"), ("LINES", "3")]);
+        assert!(MockBackend::new().term(dumb()).is_dumb());
+        assert!(!MockBackend::new().term(rxvt()).is_dumb());
+        assert!(!MockBackend::new().term(terminfo::TermInfoBuf::new()).is_dumb());
+
+        let out = with_tty(dumb(), |t| {
+            t.set_env_overrides(env.clone());
+            assert_eq!(t.color_depth(), ansi::ColorDepth::None);
+            styled_program(t).unwrap();
+        });
+        assert!(!out.contains(&0x1b), "{:?}", String::from_utf8_lossy(&out));
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\n\n\nerror: it broke\n\r  indented\ttabbed\r\none two\r\n\r\n  three\r\nfour"
+        );
+
+        let out = with_tty(dumb(), |t| {
+            t.set_env_overrides(env.clone());
+            t.set_degradation(Degradation::Silent);
+            styled_program(t).unwrap();
+        });
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "error: it broke\nindentedtabbedonetwothreefour"
+        );
+
+        with_tty(dumb(), |t| {
+            t.set_degradation(Degradation::Strict);
+            match styled_program(t).unwrap_err() {
+                Error::Unsupported(c) => assert_eq!(c, Capability::String(terminfo::ClearScreen)),
+                e => panic!("{:?}", e),
+            }
+            match t.move_to(1, 1).unwrap_err() {
+                Error::Unsupported(c) => assert_eq!(c, Capability::String(terminfo::CursorAddress)),
+                e => panic!("{:?}", e),
+            }
+            let mut r = t.renderer();
+            r.move_to(1, 1);
+            assert!(r.put_str("x").is_err());
+        });
+
+        // terminals that aren't dumb still get the ANSI fallbacks, unless the policy is `Strict`
+        let out = with_tty(terminfo::TermInfoBuf::new(), |t| {
+            t.set_degradation(Degradation::Silent);
+            t.move_to(1, 1).unwrap();
+            t.clear_line_after_cursor();
+        });
+        assert_eq!(out, b"\x1b[2;2H\x1b[K");
+        assert_eq!(with_tty(dumb(), |t| t.clear_line_after_cursor()), b"");
+        with_tty(terminfo::TermInfoBuf::new(), |t| {
+            t.set_degradation(Degradation::Strict);
+            t.clear_line_after_cursor();
+            match t.err().unwrap_err() {
+                Error::Unsupported(c) => assert_eq!(c, Capability::String(terminfo::ClrEol)),
+                e => panic!("{:?}", e),
+            }
+        });
+    }
+
+    #[test]
+    fn two_terminals() {
+        fn flags(settings: &Settings) -> String {
+            let t = &settings.termios;
+            format!(
+                "{:?} {:?} {:?} {:?} {:?}",
+                t.input_flags, t.output_flags, t.control_flags, t.local_flags, t.control_chars
+            )
+        }
+
+        let (a, b) = (pty::openpty(None, None).unwrap(), pty::openpty(None, None).unwrap());
+        let tty = |fd| unsafe { File::from_raw_fd(fd) };
+        let first = Term::from_streams(xterm(), tty(a.slave), tty(unistd::dup(a.slave).unwrap()));
+        let second = Term::from_streams(rxvt(), tty(b.slave), tty(unistd::dup(b.slave).unwrap()));
+        let cooked = flags(&second.settings());
+
+        let options = SessionOptions::new().raw(true);
+        let session = first.session(options).unwrap();
+        let other = second.session(options).unwrap();
+        let raw = flags(&second.settings());
+        assert_ne!(raw, cooked);
+        assert_eq!(flags(&first.settings()), raw);
+
+        drop(session);
+        drop(first);
+        assert_eq!(flags(&second.settings()), raw);
+        drop(other);
+        assert_eq!(flags(&second.settings()), cooked);
+
+        unistd::close(a.master).unwrap();
+        unistd::close(b.master).unwrap();
+    }
+
+    #[test]
+    fn size() {
+        let winsize = pty::Winsize {
+            ws_row: 30,
+            ws_col: 100,
+            ws_xpixel: 0,
+            ws_ypixel: 0,
+        };
+        let pty = pty::openpty(&winsize, None).unwrap();
+        let mut stdin = FakeStdin::new();
+        {
+            let tty = unsafe { File::from_raw_fd(pty.slave) };
+            let t = Term::from_streams(xterm(), &mut stdin, tty);
+            assert_eq!(t.size().unwrap(), (100, 30));
+            // the environment comes before the tty
+            t.set_env_overrides(EnvOverrides::from_map(vec![("COLUMNS", "120")]));
+            assert_eq!(t.size().unwrap(), (120, 30));
+        }
+        unistd::close(pty.master).unwrap();
+
+        // not a tty, so it's up to terminfo
+        let (r, w) = unistd::pipe().unwrap();
+        let (_r, w) = unsafe { (File::from_raw_fd(r), File::from_raw_fd(w)) };
+        let t = Term::from_streams(xterm(), &mut stdin, w.try_clone().unwrap());
+        t.set_env_overrides(EnvOverrides::from_map(Vec::<(String, String)>::new()));
+        assert_eq!(t.size().unwrap(), (80, 24));
+        // and before terminfo
+        t.set_env_overrides(EnvOverrides::from_map(vec![("LINES", "50")]));
+        assert_eq!(t.size().unwrap(), (80, 50));
+        drop(t);
+        let t = Term::from_streams(terminfo::TermInfoBuf::new(), &mut stdin, w);
+        t.set_env_overrides(EnvOverrides::from_map(vec![("COLUMNS", "120")]));
+        match t.size() {
+            Err(Error::NotATty) => (),
+            r => panic!("expected NotATty, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn env_overrides() {
+        use ansi::ColorChoice::*;
+        use ansi::ColorDepth::*;
+
+        let depth = |choice, vars: Vec<(&str, &str)>| {
+            let mut depth = (None, 0);
+            output(xterm(), |t| {
+                t.set_env_overrides(EnvOverrides::from_map(vars));
+                t.set_color_choice(choice);
+                depth = (t.color_depth(), t.colors());
+            });
+            depth
+        };
+
+        // xterm's entry only has 8 colors
+        assert_eq!(depth(Always, vec![]), (Ansi16, 8));
+        assert_eq!(
+            depth(Always, vec![("COLORTERM", "truecolor")]),
+            (TrueColor, 1 << 24)
+        );
+
+        // asking explicitly beats the environment
+        assert_eq!(depth(Never, vec![("COLORTERM", "truecolor")]).0, None);
+        assert_eq!(depth(Never, vec![("CLICOLOR_FORCE", "1")]).0, None);
+        assert_eq!(depth(Always, vec![("NO_COLOR", "1")]).0, Ansi16);
+
+        // the output isn't a tty, so it's up to the environment
+        assert_eq!(depth(Auto, vec![("COLORTERM", "truecolor")]).0, None);
+        assert_eq!(
+            depth(Auto, vec![("CLICOLOR_FORCE", "1"), ("COLORTERM", "24bit")]).0,
+            TrueColor
+        );
+        assert_eq!(
+            depth(Auto, vec![("CLICOLOR_FORCE", "1"), ("NO_COLOR", "1")]).0,
+            None
+        );
+    }
+
+    #[cfg(feature = "mio")]
+    #[test]
+    fn mio_source() {
+        use events::{Decoder, Key};
+        use std::time::Duration;
+
+        let pty = pty::openpty(None, None).unwrap();
+        let mut master = unsafe { File::from_raw_fd(pty.master) };
+        let mut stdout = FakeStdout::new();
+        let tty = unsafe { File::from_raw_fd(pty.slave) };
+        let mut t = Term::from_streams(xterm(), tty, &mut stdout);
+        t.update(t.settings().raw()).unwrap();
+
+        let mut poll = mio::Poll::new().unwrap();
+        poll.registry()
+            .register(&mut t, mio::Token(7), mio::Interest::READABLE)
+            .unwrap();
+        io::Write::write_all(&mut master, b"\x1bOA").unwrap();
+
+        let mut events = mio::Events::with_capacity(4);
+        poll.poll(&mut events, Some(Duration::from_secs(5))).unwrap();
+        assert_eq!(events.iter().next().map(|e| e.token()), Some(mio::Token(7)));
+
+        let mut decoder = Decoder::new(&t.info);
+        let mut buf = [0u8; 16];
+        let n = t.read(&mut buf);
+        decoder.push(&buf[..n]);
+        assert_eq!(decoder.next_key(), Some(Key::Up));
+        poll.registry().deregister(&mut t).unwrap();
+    }
 }
@@ -0,0 +1,550 @@
+//! Putting a terminfo entry together in code, instead of writing a source file for `tic`.
+use std::string::String;
+use std::vec::Vec;
+use terminfo::errors::*;
+use terminfo::fields::*;
+use terminfo::names::{Field, ALL_STRINGS};
+use terminfo::{TermInfo, TermInfoBuf};
+use util::invalid;
+
+/// What a `TermInfoBuilder` knows about one capability.
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum Value<T> {
+    Unset,
+    Set(T),
+    /// Cancelled with `TermInfoBuilder::cancel`, so it isn't inherited either.
+    Cancelled,
+}
+
+impl<T> Value<T> {
+    fn get(&self) -> Option<&T> {
+        match *self {
+            Value::Set(ref v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Take `inherited`, if nothing's been said about this capability yet.
+    fn inherit(&mut self, inherited: Option<T>) {
+        if let Value::Unset = *self {
+            if let Some(v) = inherited {
+                *self = Value::Set(v);
+            }
+        }
+    }
+}
+
+/// An extended capability's value.
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum Ext {
+    Boolean(bool),
+    Number(u32),
+    String(Vec<u8>),
+}
+
+/// Builds a terminfo entry from its names and capabilities, like a terminfo source file would describe it.
+///
+/// `build` checks the entry and gives back a `TermInfoBuf`, and `compile` gives back the compiled file, the same
+/// format `tic` writes, ready to install in a terminfo database.
+///
+/// ```
+/// use nixterm::terminfo::{self, TermInfo, TermInfoBuilder};
+///
+/// let entry = TermInfoBuilder::new()
+///     .name("inhouse")
+///     .alias("inhouse-256color")
+///     .description("our terminal emulator")
+///     .set_bool(terminfo::AutoRightMargin, true)
+///     .set_num(terminfo::MaxColors, 256)
+///     .set_str(terminfo::CursorAddress, "\x1b[%i%p1%d;%p2%dH")
+///     .set_ext_str("Smulx", "\x1b[4:%p1%dm")
+///     .compile()
+///     .unwrap();
+/// let info = TermInfo::parse(&entry).unwrap();
+/// assert_eq!(info.max_colors(), Some(256));
+/// assert_eq!(info.ext_string("Smulx"), Some("\x1b[4:%p1%dm"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct TermInfoBuilder {
+    name: Option<String>,
+    aliases: Vec<String>,
+    description: Option<String>,
+    bools: Vec<Value<bool>>,
+    numbers: Vec<Value<u32>>,
+    strings: Vec<Value<Vec<u8>>>,
+    /// In the order they were first mentioned.
+    ext: Vec<(String, Value<Ext>)>,
+}
+
+impl TermInfoBuilder {
+    pub fn new() -> TermInfoBuilder {
+        let mut builder = TermInfoBuilder {
+            name: None,
+            aliases: Vec::new(),
+            description: None,
+            bools: Vec::new(),
+            numbers: Vec::new(),
+            strings: Vec::new(),
+            ext: Vec::new(),
+        };
+        builder
+            .bools
+            .resize(PREDEFINED_BOOLEANS_COUNT, Value::Unset);
+        builder
+            .numbers
+            .resize(PREDEFINED_NUMERICS_COUNT, Value::Unset);
+        builder
+            .strings
+            .resize(PREDEFINED_STRINGS_COUNT, Value::Unset);
+        builder
+    }
+
+    /// Set the entry's name, the one `$TERM` is set to.
+    pub fn name<T: Into<String>>(mut self, name: T) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Add another name the entry can be found by.
+    pub fn alias<T: Into<String>>(mut self, alias: T) -> Self {
+        self.aliases.push(alias.into());
+        self
+    }
+
+    /// Set the long description that goes after the names, like "xterm terminal emulator (X Window System)".
+    pub fn description<T: Into<String>>(mut self, description: T) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    pub fn set_bool(mut self, cap: BooleanField, v: bool) -> Self {
+        self.bools[cap as usize] = Value::Set(v);
+        self
+    }
+
+    /// Set a numeric capability, numbers over 32767 make `compile` write the 32-bit format.
+    pub fn set_num(mut self, cap: NumericField, v: u32) -> Self {
+        self.numbers[cap as usize] = Value::Set(v);
+        self
+    }
+
+    /// Set a string capability to its escaped bytes, `\x1b` rather than `\E`.
+    pub fn set_str<T: AsRef<[u8]>>(mut self, cap: StringField, v: T) -> Self {
+        self.strings[cap as usize] = Value::Set(v.as_ref().into());
+        self
+    }
+
+    pub fn set_ext_bool(self, name: &str, v: bool) -> Self {
+        self.set_ext(name, Value::Set(Ext::Boolean(v)))
+    }
+
+    pub fn set_ext_num(self, name: &str, v: u32) -> Self {
+        self.set_ext(name, Value::Set(Ext::Number(v)))
+    }
+
+    pub fn set_ext_str<T: AsRef<[u8]>>(self, name: &str, v: T) -> Self {
+        self.set_ext(name, Value::Set(Ext::String(v.as_ref().into())))
+    }
+
+    /// Leave the standard capability `cap` out, even if an entry from `use_entry` has it, like `cap@` in a source
+    /// file.
+    pub fn cancel<F: Into<Field>>(mut self, cap: F) -> Self {
+        match cap.into() {
+            Field::Boolean(cap) => self.bools[cap as usize] = Value::Cancelled,
+            Field::Number(cap) => self.numbers[cap as usize] = Value::Cancelled,
+            Field::String(cap) => self.strings[cap as usize] = Value::Cancelled,
+        }
+        self
+    }
+
+    /// Leave the extended capability `name` out, even if an entry from `use_entry` has it.
+    pub fn cancel_ext(self, name: &str) -> Self {
+        self.set_ext(name, Value::Cancelled)
+    }
+
+    /// Inherit every capability of `info` this entry doesn't set or cancel, like `use=` in a source file.
+    ///
+    /// Capabilities set here always win, whether they're set before or after this is called, and when several
+    /// entries are used the first one with a capability wins. The used entry's names aren't inherited.
+    pub fn use_entry(mut self, info: &TermInfo) -> Self {
+        for (value, v) in self.bools.iter_mut().zip(info.get_bools()) {
+            value.inherit(Some(v).filter(|&v| v));
+        }
+        for (value, n) in self.numbers.iter_mut().zip(info.get_numbers()) {
+            value.inherit(Some(n).filter(|&n| n != invalid()));
+        }
+        for (i, value) in self.strings.iter_mut().enumerate() {
+            value.inherit(info.bytes_at(i).map(Vec::from));
+        }
+        for name in info.ext_booleans() {
+            self.inherit_ext(name, Ext::Boolean(true));
+        }
+        for (name, v) in info.ext_numbers() {
+            self.inherit_ext(name, Ext::Number(v));
+        }
+        for (name, v) in info.ext_strings() {
+            self.inherit_ext(name, Ext::String(v.into()));
+        }
+        self
+    }
+
+    /// Check the entry, and make a `TermInfoBuf` of it.
+    ///
+    /// This fails if the entry has no name, has the same name twice, or has a name with a `|` in it, or if a
+    /// string capability has a NUL in it.
+    pub fn build(&self) -> Result<TermInfoBuf> {
+        let compiled = self.compile()?;
+        Ok(TermInfo::parse(&compiled)?.into())
+    }
+
+    /// Check the entry like `build` does, and compile it into a terminfo file.
+    ///
+    /// The file is in the 16-bit format unless a number needs more, and has an extended section if there are any
+    /// extended capabilities, which are sorted by name like `tic` sorts them.
+    pub fn compile(&self) -> Result<Vec<u8>> {
+        self.check()?;
+        let mut ext_bools = Vec::new();
+        let mut ext_numbers = Vec::new();
+        let mut ext_strings = Vec::new();
+        for (name, value) in &self.ext {
+            match value.get() {
+                Some(Ext::Boolean(true)) => ext_bools.push(name.as_str()),
+                Some(Ext::Boolean(false)) | None => (),
+                Some(Ext::Number(n)) => ext_numbers.push((name.as_str(), *n)),
+                Some(Ext::String(s)) => ext_strings.push((name.as_str(), &s[..])),
+            }
+        }
+        ext_bools.sort();
+        ext_numbers.sort();
+        ext_strings.sort();
+
+        let long = self
+            .numbers
+            .iter()
+            .filter_map(Value::get)
+            .chain(ext_numbers.iter().map(|(_, n)| n))
+            .any(|&n| n > i16::MAX as u32);
+
+        let mut names = String::new();
+        for name in self
+            .name
+            .iter()
+            .chain(&self.aliases)
+            .chain(&self.description)
+        {
+            if !names.is_empty() {
+                names.push('|');
+            }
+            names.push_str(name);
+        }
+
+        let bools = used(&self.bools, |&v| v);
+        let numbers = used(&self.numbers, |_| true);
+        let strings = used(&self.strings, |_| true);
+
+        let mut strtab = Vec::new();
+        let mut offsets = Vec::new();
+        for value in &self.strings[..strings] {
+            offsets.push(match value.get() {
+                Some(s) => Some(intern(&mut strtab, s)?),
+                None => None,
+            });
+        }
+
+        let mut out = Vec::new();
+        push_u16(&mut out, if long { 542 } else { 0o432 });
+        for &len in &[names.len() + 1, bools, numbers, strings, strtab.len()] {
+            push_u16(&mut out, len as u16);
+        }
+        out.extend_from_slice(names.as_bytes());
+        out.push(0);
+        out.extend(
+            self.bools[..bools]
+                .iter()
+                .map(|v| (v.get() == Some(&true)) as u8),
+        );
+        if out.len() % 2 != 0 {
+            out.push(0);
+        }
+        for value in &self.numbers[..numbers] {
+            push_number(&mut out, value.get().cloned(), long);
+        }
+        for &offset in &offsets {
+            push_offset(&mut out, offset);
+        }
+        out.extend_from_slice(&strtab);
+
+        if ext_bools.is_empty() && ext_numbers.is_empty() && ext_strings.is_empty() {
+            return Ok(out);
+        }
+        if out.len() % 2 != 0 {
+            out.push(0);
+        }
+
+        // the string values come first in the extended string table, then every name, counted from the first one
+        let mut strtab = Vec::new();
+        let mut offsets = Vec::new();
+        for &(_, s) in &ext_strings {
+            offsets.push(Some(intern(&mut strtab, s)?));
+        }
+        let names_start = strtab.len();
+        let names: Vec<&str> = ext_bools
+            .iter()
+            .cloned()
+            .chain(ext_numbers.iter().map(|&(name, _)| name))
+            .chain(ext_strings.iter().map(|&(name, _)| name))
+            .collect();
+        let mut name_offsets = Vec::new();
+        for name in &names {
+            name_offsets.push(Some(
+                intern(&mut strtab, name.as_bytes())? - names_start as u16,
+            ));
+        }
+
+        for &len in &[
+            ext_bools.len(),
+            ext_numbers.len(),
+            ext_strings.len(),
+            ext_strings.len() + names.len(),
+            strtab.len(),
+        ] {
+            push_u16(&mut out, len as u16);
+        }
+        out.extend(ext_bools.iter().map(|_| 1));
+        if ext_bools.len() % 2 != 0 {
+            out.push(0);
+        }
+        for &(_, n) in &ext_numbers {
+            push_number(&mut out, Some(n), long);
+        }
+        for offset in offsets.into_iter().chain(name_offsets) {
+            push_offset(&mut out, offset);
+        }
+        out.extend_from_slice(&strtab);
+        Ok(out)
+    }
+
+    /// Make sure the entry can be compiled, see `build`.
+    fn check(&self) -> Result<()> {
+        let name = self.name.as_ref().ok_or(ErrorKind::MissingName)?;
+        let names: Vec<&String> = Some(name).into_iter().chain(&self.aliases).collect();
+        for (i, name) in names.iter().enumerate() {
+            if name.is_empty() || name.contains('|') || name.contains('\0') {
+                return Err(ErrorKind::InvalidName((*name).clone()).into());
+            }
+            if names[..i].contains(name) {
+                return Err(ErrorKind::DuplicateName((*name).clone()).into());
+            }
+        }
+        if let Some(ref description) = self.description {
+            if description.contains('|') || description.contains('\0') {
+                return Err(ErrorKind::InvalidName(description.clone()).into());
+            }
+        }
+
+        for (value, info) in self.strings.iter().zip(ALL_STRINGS.iter()) {
+            if let Some(s) = value.get() {
+                if s.contains(&0) {
+                    return Err(ErrorKind::NulInString(info.terminfo_name.into()).into());
+                }
+            }
+        }
+        for (name, value) in &self.ext {
+            if let Some(Ext::String(s)) = value.get() {
+                if s.contains(&0) {
+                    return Err(ErrorKind::NulInString(name.clone()).into());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn set_ext(mut self, name: &str, value: Value<Ext>) -> Self {
+        match self.ext.iter().position(|(n, _)| n == name) {
+            Some(i) => self.ext[i].1 = value,
+            None => self.ext.push((name.into(), value)),
+        }
+        self
+    }
+
+    fn inherit_ext(&mut self, name: &str, v: Ext) {
+        match self.ext.iter().position(|(n, _)| n == name) {
+            Some(i) => self.ext[i].1.inherit(Some(v)),
+            None => self.ext.push((name.into(), Value::Set(v))),
+        }
+    }
+}
+
+impl Default for TermInfoBuilder {
+    fn default() -> TermInfoBuilder {
+        TermInfoBuilder::new()
+    }
+}
+
+/// How many of `values` have to be written, up to the last one that's set and `present`.
+fn used<T, F: Fn(&T) -> bool>(values: &[Value<T>], present: F) -> usize {
+    values
+        .iter()
+        .rposition(|v| v.get().is_some_and(&present))
+        .map_or(0, |i| i + 1)
+}
+
+/// Add `s` and its NUL to a string table, returning where it starts.
+fn intern(strtab: &mut Vec<u8>, s: &[u8]) -> Result<u16> {
+    let offset = strtab.len();
+    // 0xffff and 0xfffe mean absent and cancelled
+    if offset + s.len() + 1 >= 0xfffe {
+        return Err(ErrorKind::MaxStrTabSizeReached.into());
+    }
+    strtab.extend_from_slice(s);
+    strtab.push(0);
+    Ok(offset as u16)
+}
+
+fn push_u16(out: &mut Vec<u8>, n: u16) {
+    out.push(n as u8);
+    out.push((n >> 8) as u8);
+}
+
+/// Write a number, or -1 for an absent one.
+fn push_number(out: &mut Vec<u8>, n: Option<u32>, long: bool) {
+    let n = n.map_or(-1, |n| n as i32);
+    if long {
+        out.extend((0..4).map(|i| (n >> (i * 8)) as u8));
+    } else {
+        push_u16(out, n as u16);
+    }
+}
+
+/// Write a string table offset, or 0xffff for an absent string.
+fn push_offset(out: &mut Vec<u8>, offset: Option<u16>) {
+    push_u16(out, offset.unwrap_or(0xffff));
+}
+
+#[cfg(test)]
+mod test {
+    use terminfo::*;
+
+    // compiled by `tic -x` from the same capabilities as `inhouse()`
+    const INHOUSE_INFO: &'static [u8] = include_bytes!("../../test-data/inhouse");
+    const RXVT_INFO: &'static [u8] = include_bytes!("../../test-data/rxvt");
+
+    fn inhouse() -> TermInfoBuilder {
+        TermInfoBuilder::new()
+            .name("inhouse")
+            .alias("inhouse-256color")
+            .description("our terminal emulator")
+            .set_bool(AutoRightMargin, true)
+            .set_bool(BackColorErase, true)
+            .set_num(Columns, 80)
+            .set_num(MaxColors, 256)
+            .set_str(Bell, "\x07")
+            .set_str(CursorAddress, "\x1b[%i%p1%d;%p2%dH")
+            .set_str(ClearScreen, "\x1b[H\x1b[2J")
+            .set_ext_bool("XT", true)
+            .set_ext_bool("AX", true)
+            .set_ext_num("U8", 1)
+            .set_ext_str("Smulx", "\x1b[4:%p1%dm")
+            .set_ext_str("Ss", "\x1b[%p1%d q")
+    }
+
+    #[test]
+    fn from_scratch() {
+        let compiled = inhouse().compile().unwrap();
+        assert_eq!(&compiled[..], INHOUSE_INFO);
+
+        let info = TermInfo::parse(&compiled).unwrap();
+        assert_eq!(
+            info.names().collect::<Vec<_>>(),
+            vec!["inhouse", "inhouse-256color", "our terminal emulator"]
+        );
+        assert!(info.boolean(AutoRightMargin) && info.boolean(BackColorErase));
+        assert!(!info.boolean(XonXoff));
+        assert_eq!(info.number(Columns), Some(80));
+        assert_eq!(info.number(Lines), None);
+        assert_eq!(info.string(CursorAddress), Some("\x1b[%i%p1%d;%p2%dH"));
+        assert_eq!(info.string(ClearScreen), Some("\x1b[H\x1b[2J"));
+        assert_eq!(info.string(FlashScreen), None);
+        assert!(info.ext_boolean("AX") && info.ext_boolean("XT"));
+        assert_eq!(info.ext_numbers(), vec![("U8", 1)]);
+        assert_eq!(info.ext_string("Ss"), Some("\x1b[%p1%d q"));
+
+        let built = inhouse().build().unwrap();
+        assert_eq!(built.names, info.names().collect::<Vec<_>>());
+        assert_eq!(built.max_colors(), Some(256));
+        assert_eq!(built.string(Bell), Some("\x07"));
+        assert_eq!(built.ext_string("Smulx"), Some("\x1b[4:%p1%dm"));
+    }
+
+    #[test]
+    fn long_numbers() {
+        let compiled = inhouse().set_num(MaxPairs, 65536).compile().unwrap();
+        assert_eq!(&compiled[..2], &[0x1e, 0x02]);
+        let info = TermInfo::parse(&compiled).unwrap();
+        assert_eq!(info.number(MaxPairs), Some(65536));
+        assert_eq!(info.number(MaxColors), Some(256));
+        assert_eq!(info.number(Lines), None);
+        assert_eq!(info.ext_numbers(), vec![("U8", 1)]);
+        assert_eq!(info.ext_string("Smulx"), Some("\x1b[4:%p1%dm"));
+    }
+
+    #[test]
+    fn use_entry() {
+        let rxvt = TermInfo::parse(RXVT_INFO).unwrap();
+        let info = TermInfoBuilder::new()
+            .name("rxvt-custom")
+            .cancel(FlashScreen)
+            .use_entry(&rxvt)
+            .set_num(MaxColors, 256)
+            .cancel(BackColorErase)
+            .set_ext_str("Ss", "\x1b[%p1%d q")
+            .build()
+            .unwrap();
+
+        assert_eq!(info.names, vec!["rxvt-custom"]);
+        assert_eq!(info.max_colors(), Some(256));
+        assert_eq!(info.string(FlashScreen), None);
+        assert!(!info.boolean(BackColorErase));
+        assert_eq!(info.string(CursorAddress), rxvt.string(CursorAddress));
+        assert_eq!(info.number(Columns), rxvt.number(Columns));
+        assert_eq!(info.ext_string("Ss"), Some("\x1b[%p1%d q"));
+        assert_eq!(info.ext_string("kDC5"), rxvt.ext_string("kDC5"));
+
+        // the first entry used wins
+        let first = TermInfoBuilder::new()
+            .name("first")
+            .set_num(MaxColors, 16)
+            .compile()
+            .unwrap();
+        let info = TermInfoBuilder::new()
+            .name("both")
+            .use_entry(&TermInfo::parse(&first).unwrap())
+            .use_entry(&rxvt)
+            .build()
+            .unwrap();
+        assert_eq!(info.max_colors(), Some(16));
+        assert_eq!(info.string(CursorAddress), rxvt.string(CursorAddress));
+    }
+
+    #[test]
+    fn invalid() {
+        let kind = |builder: TermInfoBuilder| builder.build().unwrap_err().kind().clone();
+        assert_eq!(kind(TermInfoBuilder::new()), ErrorKind::MissingName);
+        assert_eq!(
+            kind(inhouse().alias("inhouse")),
+            ErrorKind::DuplicateName("inhouse".into())
+        );
+        assert_eq!(
+            kind(inhouse().alias("a|b")),
+            ErrorKind::InvalidName("a|b".into())
+        );
+        assert_eq!(
+            kind(inhouse().set_str(Bell, b"\x07\0")),
+            ErrorKind::NulInString("bel".into())
+        );
+        assert_eq!(
+            kind(inhouse().set_ext_str("Ms", b"\0")),
+            ErrorKind::NulInString("Ms".into())
+        );
+    }
+}
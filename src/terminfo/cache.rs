@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+use std::env;
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use terminfo::errors::*;
+use terminfo::{check_name, find, from_path, TermInfoBuf};
+
+/// An entry is locked while it's loaded, so callers asking for it at the same time wait instead of loading it too.
+type Entry = Arc<Mutex<Option<Arc<TermInfoBuf>>>>;
+
+fn cache() -> &'static RwLock<HashMap<String, Entry>> {
+    static CACHE: OnceLock<RwLock<HashMap<String, Entry>>> = OnceLock::new();
+    CACHE.get_or_init(Default::default)
+}
+
+/// Get the terminfo entry for the terminal called `name`, loading it at most once per process.
+///
+/// Everyone asking for the same terminal shares one `TermInfoBuf`, if it's being loaded on another thread this waits
+/// for that instead of loading it again. Failures aren't cached, so a missing entry is looked for again next time.
+pub fn cached(name: &str) -> Result<Arc<TermInfoBuf>> {
+    check_name(name)?;
+    cached_with(name, |name| match find(name) {
+        Some(path) => from_path(path),
+        None => Err(ErrorKind::FailedToFindTermInfo.into()),
+    })
+}
+
+/// Get the cached terminfo entry for the terminal named by `$TERM`.
+///
+/// `$TERM` is read on every call, so if it changes the new terminal's entry is returned. Changes to `$TERMINFO` or
+/// `$TERMINFO_DIRS` aren't noticed for entries that are already cached, call `cache_clear` after changing them.
+pub fn cached_from_env() -> Result<Arc<TermInfoBuf>> {
+    match env::var("TERM") {
+        Ok(ref name) if !name.is_empty() => cached(name),
+        _ => Err(ErrorKind::FailedToFindTermInfo.into()),
+    }
+}
+
+/// Forget every cached entry, they're loaded again the next time they're asked for.
+///
+/// Entries that have already been handed out stay valid.
+pub fn cache_clear() {
+    cache().write().unwrap_or_else(|e| e.into_inner()).clear()
+}
+
+/// `cached`, with `load` in place of looking the entry up in the terminfo databases.
+pub(crate) fn cached_with<F>(name: &str, load: F) -> Result<Arc<TermInfoBuf>>
+where
+    F: FnOnce(&str) -> Result<TermInfoBuf>,
+{
+    let entry = cache()
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(name)
+        .cloned();
+    let entry = match entry {
+        Some(entry) => entry,
+        None => cache()
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(name.to_string())
+            .or_default()
+            .clone(),
+    };
+
+    let mut info = entry.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(ref info) = *info {
+        return Ok(info.clone());
+    }
+
+    let loaded = Arc::new(load(name)?);
+    *info = Some(loaded.clone());
+    Ok(loaded)
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Barrier};
+    use std::thread;
+    use std::time::Duration;
+    use terminfo::cache::cached_with;
+    use terminfo::*;
+
+    const RXVT_INFO: &'static [u8] = include_bytes!("../../test-data/rxvt");
+
+    #[test]
+    fn cache() {
+        let loads = Arc::new(AtomicUsize::new(0));
+        let barrier = Arc::new(Barrier::new(16));
+        let load = |loads: &AtomicUsize| {
+            loads.fetch_add(1, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(20));
+            Ok(TermInfo::parse(RXVT_INFO)?.into())
+        };
+
+        let threads: Vec<_> = (0..16)
+            .map(|_| {
+                let loads = loads.clone();
+                let barrier = barrier.clone();
+                thread::spawn(move || {
+                    barrier.wait();
+                    cached_with("nixterm-test-cache", |_| load(&loads)).unwrap()
+                })
+            })
+            .collect();
+        let infos: Vec<_> = threads.into_iter().map(|t| t.join().unwrap()).collect();
+
+        assert_eq!(loads.load(Ordering::SeqCst), 1);
+        assert!(infos.iter().all(|info| Arc::ptr_eq(info, &infos[0])));
+        assert_eq!(infos[0].max_colors(), Some(8));
+
+        // failures are tried again
+        let e = cached_with("nixterm-test-cache-missing", |_| {
+            Err(ErrorKind::FailedToFindTermInfo.into())
+        })
+        .unwrap_err();
+        assert_eq!(*e.kind(), ErrorKind::FailedToFindTermInfo);
+        cached_with("nixterm-test-cache-missing", |_| load(&loads)).unwrap();
+        assert_eq!(loads.load(Ordering::SeqCst), 2);
+
+        cache_clear();
+        let info = cached_with("nixterm-test-cache", |_| load(&loads)).unwrap();
+        assert_eq!(loads.load(Ordering::SeqCst), 3);
+        assert!(!Arc::ptr_eq(&info, &infos[0]));
+
+        assert_eq!(
+            *cached("nixterm-no-such-terminal").unwrap_err().kind(),
+            ErrorKind::FailedToFindTermInfo
+        );
+    }
+}
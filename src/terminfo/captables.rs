@@ -0,0 +1,2999 @@
+//! This file was generated by "scripts/generate-terminfo-names.sh".
+//! Please do not modify it.
+
+use terminfo::names::CapInfo;
+
+/// Every standard boolean capability, in the order of `BooleanField`.
+pub const ALL_BOOLEANS: &[CapInfo] = &[
+    CapInfo {
+        terminfo_name: "bw",
+        termcap_code: "bw",
+        variable_name: "auto_left_margin",
+        description: "cub1 wraps from column 0 to last column",
+    },
+    CapInfo {
+        terminfo_name: "am",
+        termcap_code: "am",
+        variable_name: "auto_right_margin",
+        description: "terminal has automatic margins",
+    },
+    CapInfo {
+        terminfo_name: "xsb",
+        termcap_code: "xb",
+        variable_name: "no_esc_ctlc",
+        description: "beehive (f1=escape, f2=ctrl C)",
+    },
+    CapInfo {
+        terminfo_name: "xhp",
+        termcap_code: "xs",
+        variable_name: "ceol_standout_glitch",
+        description: "standout not erased by overwriting (hp)",
+    },
+    CapInfo {
+        terminfo_name: "xenl",
+        termcap_code: "xn",
+        variable_name: "eat_newline_glitch",
+        description: "newline ignored after 80 cols (concept)",
+    },
+    CapInfo {
+        terminfo_name: "eo",
+        termcap_code: "eo",
+        variable_name: "erase_overstrike",
+        description: "can erase overstrikes with a blank",
+    },
+    CapInfo {
+        terminfo_name: "gn",
+        termcap_code: "gn",
+        variable_name: "generic_type",
+        description: "generic line type",
+    },
+    CapInfo {
+        terminfo_name: "hc",
+        termcap_code: "hc",
+        variable_name: "hard_copy",
+        description: "hardcopy terminal",
+    },
+    CapInfo {
+        terminfo_name: "km",
+        termcap_code: "km",
+        variable_name: "has_meta_key",
+        description: "Has a meta key (i.e., sets 8th-bit)",
+    },
+    CapInfo {
+        terminfo_name: "hs",
+        termcap_code: "hs",
+        variable_name: "has_status_line",
+        description: "has extra status line",
+    },
+    CapInfo {
+        terminfo_name: "in",
+        termcap_code: "in",
+        variable_name: "insert_null_glitch",
+        description: "insert mode distinguishes nulls",
+    },
+    CapInfo {
+        terminfo_name: "da",
+        termcap_code: "da",
+        variable_name: "memory_above",
+        description: "display may be retained above the screen",
+    },
+    CapInfo {
+        terminfo_name: "db",
+        termcap_code: "db",
+        variable_name: "memory_below",
+        description: "display may be retained below the screen",
+    },
+    CapInfo {
+        terminfo_name: "mir",
+        termcap_code: "mi",
+        variable_name: "move_insert_mode",
+        description: "safe to move while in insert mode",
+    },
+    CapInfo {
+        terminfo_name: "msgr",
+        termcap_code: "ms",
+        variable_name: "move_standout_mode",
+        description: "safe to move while in standout mode",
+    },
+    CapInfo {
+        terminfo_name: "os",
+        termcap_code: "os",
+        variable_name: "over_strike",
+        description: "terminal can overstrike",
+    },
+    CapInfo {
+        terminfo_name: "eslok",
+        termcap_code: "es",
+        variable_name: "status_line_esc_ok",
+        description: "escape can be used on the status line",
+    },
+    CapInfo {
+        terminfo_name: "xt",
+        termcap_code: "xt",
+        variable_name: "dest_tabs_magic_smso",
+        description: "tabs destructive, magic so char (t1061)",
+    },
+    CapInfo {
+        terminfo_name: "hz",
+        termcap_code: "hz",
+        variable_name: "tilde_glitch",
+        description: "cannot print ~'s (Hazeltine)",
+    },
+    CapInfo {
+        terminfo_name: "ul",
+        termcap_code: "ul",
+        variable_name: "transparent_underline",
+        description: "underline character overstrikes",
+    },
+    CapInfo {
+        terminfo_name: "xon",
+        termcap_code: "xo",
+        variable_name: "xon_xoff",
+        description: "terminal uses xon/xoff handshaking",
+    },
+    CapInfo {
+        terminfo_name: "nxon",
+        termcap_code: "nx",
+        variable_name: "needs_xon_xoff",
+        description: "padding will not work, xon/xoff required",
+    },
+    CapInfo {
+        terminfo_name: "mc5i",
+        termcap_code: "5i",
+        variable_name: "prtr_silent",
+        description: "printer will not echo on screen",
+    },
+    CapInfo {
+        terminfo_name: "chts",
+        termcap_code: "HC",
+        variable_name: "hard_cursor",
+        description: "cursor is hard to see",
+    },
+    CapInfo {
+        terminfo_name: "nrrmc",
+        termcap_code: "NR",
+        variable_name: "non_rev_rmcup",
+        description: "smcup does not reverse rmcup",
+    },
+    CapInfo {
+        terminfo_name: "npc",
+        termcap_code: "NP",
+        variable_name: "no_pad_char",
+        description: "pad character does not exist",
+    },
+    CapInfo {
+        terminfo_name: "ndscr",
+        termcap_code: "ND",
+        variable_name: "non_dest_scroll_region",
+        description: "scrolling region is non-destructive",
+    },
+    CapInfo {
+        terminfo_name: "ccc",
+        termcap_code: "cc",
+        variable_name: "can_change",
+        description: "terminal can re-define existing colors",
+    },
+    CapInfo {
+        terminfo_name: "bce",
+        termcap_code: "ut",
+        variable_name: "back_color_erase",
+        description: "screen erased with background color",
+    },
+    CapInfo {
+        terminfo_name: "hls",
+        termcap_code: "hl",
+        variable_name: "hue_lightness_saturation",
+        description: "terminal uses only HLS color notation (Tektronix)",
+    },
+    CapInfo {
+        terminfo_name: "xhpa",
+        termcap_code: "YA",
+        variable_name: "col_addr_glitch",
+        description: "only positive motion for hpa/mhpa caps",
+    },
+    CapInfo {
+        terminfo_name: "crxm",
+        termcap_code: "YB",
+        variable_name: "cr_cancels_micro_mode",
+        description: "using cr turns off micro mode",
+    },
+    CapInfo {
+        terminfo_name: "daisy",
+        termcap_code: "YC",
+        variable_name: "has_print_wheel",
+        description: "printer needs operator to change character set",
+    },
+    CapInfo {
+        terminfo_name: "xvpa",
+        termcap_code: "YD",
+        variable_name: "row_addr_glitch",
+        description: "only positive motion for vpa/mvpa caps",
+    },
+    CapInfo {
+        terminfo_name: "sam",
+        termcap_code: "YE",
+        variable_name: "semi_auto_right_margin",
+        description: "printing in last column causes cr",
+    },
+    CapInfo {
+        terminfo_name: "cpix",
+        termcap_code: "YF",
+        variable_name: "cpi_changes_res",
+        description: "changing character pitch changes resolution",
+    },
+    CapInfo {
+        terminfo_name: "lpix",
+        termcap_code: "YG",
+        variable_name: "lpi_changes_res",
+        description: "changing line pitch changes resolution",
+    },
+    CapInfo {
+        terminfo_name: "OTbs",
+        termcap_code: "bs",
+        variable_name: "backspaces_with_bs",
+        description: "uses ^H to move left",
+    },
+    CapInfo {
+        terminfo_name: "OTns",
+        termcap_code: "ns",
+        variable_name: "crt_no_scrolling",
+        description: "crt cannot scroll",
+    },
+    CapInfo {
+        terminfo_name: "OTnc",
+        termcap_code: "nc",
+        variable_name: "no_correctly_working_cr",
+        description: "no way to go to start of line",
+    },
+    CapInfo {
+        terminfo_name: "OTMT",
+        termcap_code: "MT",
+        variable_name: "gnu_has_meta_key",
+        description: "has meta key",
+    },
+    CapInfo {
+        terminfo_name: "OTNL",
+        termcap_code: "NL",
+        variable_name: "linefeed_is_newline",
+        description: "move down with a newline",
+    },
+    CapInfo {
+        terminfo_name: "OTpt",
+        termcap_code: "pt",
+        variable_name: "has_hardware_tabs",
+        description: "has 8-char tabs invoked with ^I",
+    },
+    CapInfo {
+        terminfo_name: "OTxr",
+        termcap_code: "xr",
+        variable_name: "return_does_clr_eol",
+        description: "return clears the line",
+    },
+];
+
+/// Every standard numeric capability, in the order of `NumericField`.
+pub const ALL_NUMBERS: &[CapInfo] = &[
+    CapInfo {
+        terminfo_name: "cols",
+        termcap_code: "co",
+        variable_name: "columns",
+        description: "number of columns in a line",
+    },
+    CapInfo {
+        terminfo_name: "it",
+        termcap_code: "it",
+        variable_name: "init_tabs",
+        description: "tabs initially every # spaces",
+    },
+    CapInfo {
+        terminfo_name: "lines",
+        termcap_code: "li",
+        variable_name: "lines",
+        description: "number of lines on screen or page",
+    },
+    CapInfo {
+        terminfo_name: "lm",
+        termcap_code: "lm",
+        variable_name: "lines_of_memory",
+        description: "lines of memory if > line. 0 means varies",
+    },
+    CapInfo {
+        terminfo_name: "xmc",
+        termcap_code: "sg",
+        variable_name: "magic_cookie_glitch",
+        description: "number of blank characters left by smso or rmso",
+    },
+    CapInfo {
+        terminfo_name: "pb",
+        termcap_code: "pb",
+        variable_name: "padding_baud_rate",
+        description: "lowest baud rate where padding needed",
+    },
+    CapInfo {
+        terminfo_name: "vt",
+        termcap_code: "vt",
+        variable_name: "virtual_terminal",
+        description: "virtual terminal number (CB/unix)",
+    },
+    CapInfo {
+        terminfo_name: "wsl",
+        termcap_code: "ws",
+        variable_name: "width_status_line",
+        description: "number of columns in status line",
+    },
+    CapInfo {
+        terminfo_name: "nlab",
+        termcap_code: "Nl",
+        variable_name: "num_labels",
+        description: "number of labels on screen",
+    },
+    CapInfo {
+        terminfo_name: "lh",
+        termcap_code: "lh",
+        variable_name: "label_height",
+        description: "rows in each label",
+    },
+    CapInfo {
+        terminfo_name: "lw",
+        termcap_code: "lw",
+        variable_name: "label_width",
+        description: "columns in each label",
+    },
+    CapInfo {
+        terminfo_name: "ma",
+        termcap_code: "ma",
+        variable_name: "max_attributes",
+        description: "maximum combined attributes terminal can handle",
+    },
+    CapInfo {
+        terminfo_name: "wnum",
+        termcap_code: "MW",
+        variable_name: "maximum_windows",
+        description: "maximum number of definable windows",
+    },
+    CapInfo {
+        terminfo_name: "colors",
+        termcap_code: "Co",
+        variable_name: "max_colors",
+        description: "maximum number of colors on screen",
+    },
+    CapInfo {
+        terminfo_name: "pairs",
+        termcap_code: "pa",
+        variable_name: "max_pairs",
+        description: "maximum number of color-pairs on the screen",
+    },
+    CapInfo {
+        terminfo_name: "ncv",
+        termcap_code: "NC",
+        variable_name: "no_color_video",
+        description: "video attributes that cannot be used with colors",
+    },
+    CapInfo {
+        terminfo_name: "bufsz",
+        termcap_code: "Ya",
+        variable_name: "buffer_capacity",
+        description: "numbers of bytes buffered before printing",
+    },
+    CapInfo {
+        terminfo_name: "spinv",
+        termcap_code: "Yb",
+        variable_name: "dot_vert_spacing",
+        description: "spacing of pins vertically in pins per inch",
+    },
+    CapInfo {
+        terminfo_name: "spinh",
+        termcap_code: "Yc",
+        variable_name: "dot_horz_spacing",
+        description: "spacing of dots horizontally in dots per inch",
+    },
+    CapInfo {
+        terminfo_name: "maddr",
+        termcap_code: "Yd",
+        variable_name: "max_micro_address",
+        description: "maximum value in micro_..._address",
+    },
+    CapInfo {
+        terminfo_name: "mjump",
+        termcap_code: "Ye",
+        variable_name: "max_micro_jump",
+        description: "maximum value in parm_..._micro",
+    },
+    CapInfo {
+        terminfo_name: "mcs",
+        termcap_code: "Yf",
+        variable_name: "micro_col_size",
+        description: "character step size when in micro mode",
+    },
+    CapInfo {
+        terminfo_name: "mls",
+        termcap_code: "Yg",
+        variable_name: "micro_line_size",
+        description: "line step size when in micro mode",
+    },
+    CapInfo {
+        terminfo_name: "npins",
+        termcap_code: "Yh",
+        variable_name: "number_of_pins",
+        description: "numbers of pins in print-head",
+    },
+    CapInfo {
+        terminfo_name: "orc",
+        termcap_code: "Yi",
+        variable_name: "output_res_char",
+        description: "horizontal resolution in units per line",
+    },
+    CapInfo {
+        terminfo_name: "orl",
+        termcap_code: "Yj",
+        variable_name: "output_res_line",
+        description: "vertical resolution in units per line",
+    },
+    CapInfo {
+        terminfo_name: "orhi",
+        termcap_code: "Yk",
+        variable_name: "output_res_horz_inch",
+        description: "horizontal resolution in units per inch",
+    },
+    CapInfo {
+        terminfo_name: "orvi",
+        termcap_code: "Yl",
+        variable_name: "output_res_vert_inch",
+        description: "vertical resolution in units per inch",
+    },
+    CapInfo {
+        terminfo_name: "cps",
+        termcap_code: "Ym",
+        variable_name: "print_rate",
+        description: "print rate in characters per second",
+    },
+    CapInfo {
+        terminfo_name: "widcs",
+        termcap_code: "Yn",
+        variable_name: "wide_char_size",
+        description: "character step size when in double wide mode",
+    },
+    CapInfo {
+        terminfo_name: "btns",
+        termcap_code: "BT",
+        variable_name: "buttons",
+        description: "number of buttons on mouse",
+    },
+    CapInfo {
+        terminfo_name: "bitwin",
+        termcap_code: "Yo",
+        variable_name: "bit_image_entwining",
+        description: "number of passes for each bit-image row",
+    },
+    CapInfo {
+        terminfo_name: "bitype",
+        termcap_code: "Yp",
+        variable_name: "bit_image_type",
+        description: "type of bit-image device",
+    },
+    CapInfo {
+        terminfo_name: "OTug",
+        termcap_code: "ug",
+        variable_name: "magic_cookie_glitch_ul",
+        description: "number of blanks left by ul",
+    },
+    CapInfo {
+        terminfo_name: "OTdC",
+        termcap_code: "dC",
+        variable_name: "carriage_return_delay",
+        description: "pad needed for CR",
+    },
+    CapInfo {
+        terminfo_name: "OTdN",
+        termcap_code: "dN",
+        variable_name: "new_line_delay",
+        description: "pad needed for LF",
+    },
+    CapInfo {
+        terminfo_name: "OTdB",
+        termcap_code: "dB",
+        variable_name: "backspace_delay",
+        description: "padding required for ^H",
+    },
+    CapInfo {
+        terminfo_name: "OTdT",
+        termcap_code: "dT",
+        variable_name: "horizontal_tab_delay",
+        description: "padding required for ^I",
+    },
+    CapInfo {
+        terminfo_name: "OTkn",
+        termcap_code: "kn",
+        variable_name: "number_of_function_keys",
+        description: "count of function keys",
+    },
+];
+
+/// Every standard string capability, in the order of `StringField`.
+pub const ALL_STRINGS: &[CapInfo] = &[
+    CapInfo {
+        terminfo_name: "cbt",
+        termcap_code: "bt",
+        variable_name: "back_tab",
+        description: "back tab (P)",
+    },
+    CapInfo {
+        terminfo_name: "bel",
+        termcap_code: "bl",
+        variable_name: "bell",
+        description: "audible signal (bell) (P)",
+    },
+    CapInfo {
+        terminfo_name: "cr",
+        termcap_code: "cr",
+        variable_name: "carriage_return",
+        description: "carriage return (P*) (P*)",
+    },
+    CapInfo {
+        terminfo_name: "csr",
+        termcap_code: "cs",
+        variable_name: "change_scroll_region",
+        description: "change region to line #1 to line #2 (P)",
+    },
+    CapInfo {
+        terminfo_name: "tbc",
+        termcap_code: "ct",
+        variable_name: "clear_all_tabs",
+        description: "clear all tab stops (P)",
+    },
+    CapInfo {
+        terminfo_name: "clear",
+        termcap_code: "cl",
+        variable_name: "clear_screen",
+        description: "clear screen and home cursor (P*)",
+    },
+    CapInfo {
+        terminfo_name: "el",
+        termcap_code: "ce",
+        variable_name: "clr_eol",
+        description: "clear to end of line (P)",
+    },
+    CapInfo {
+        terminfo_name: "ed",
+        termcap_code: "cd",
+        variable_name: "clr_eos",
+        description: "clear to end of screen (P*)",
+    },
+    CapInfo {
+        terminfo_name: "hpa",
+        termcap_code: "ch",
+        variable_name: "column_address",
+        description: "horizontal position #1, absolute (P)",
+    },
+    CapInfo {
+        terminfo_name: "cmdch",
+        termcap_code: "CC",
+        variable_name: "command_character",
+        description: "terminal settable cmd character in prototype !?",
+    },
+    CapInfo {
+        terminfo_name: "cup",
+        termcap_code: "cm",
+        variable_name: "cursor_address",
+        description: "move to row #1 columns #2",
+    },
+    CapInfo {
+        terminfo_name: "cud1",
+        termcap_code: "do",
+        variable_name: "cursor_down",
+        description: "down one line",
+    },
+    CapInfo {
+        terminfo_name: "home",
+        termcap_code: "ho",
+        variable_name: "cursor_home",
+        description: "home cursor (if no cup)",
+    },
+    CapInfo {
+        terminfo_name: "civis",
+        termcap_code: "vi",
+        variable_name: "cursor_invisible",
+        description: "make cursor invisible",
+    },
+    CapInfo {
+        terminfo_name: "cub1",
+        termcap_code: "le",
+        variable_name: "cursor_left",
+        description: "move left one space",
+    },
+    CapInfo {
+        terminfo_name: "mrcup",
+        termcap_code: "CM",
+        variable_name: "cursor_mem_address",
+        description: "memory relative cursor addressing, move to row #1 columns #2",
+    },
+    CapInfo {
+        terminfo_name: "cnorm",
+        termcap_code: "ve",
+        variable_name: "cursor_normal",
+        description: "make cursor appear normal (undo civis/cvvis)",
+    },
+    CapInfo {
+        terminfo_name: "cuf1",
+        termcap_code: "nd",
+        variable_name: "cursor_right",
+        description: "non-destructive space (move right one space)",
+    },
+    CapInfo {
+        terminfo_name: "ll",
+        termcap_code: "ll",
+        variable_name: "cursor_to_ll",
+        description: "last line, first column (if no cup)",
+    },
+    CapInfo {
+        terminfo_name: "cuu1",
+        termcap_code: "up",
+        variable_name: "cursor_up",
+        description: "up one line",
+    },
+    CapInfo {
+        terminfo_name: "cvvis",
+        termcap_code: "vs",
+        variable_name: "cursor_visible",
+        description: "make cursor very visible",
+    },
+    CapInfo {
+        terminfo_name: "dch1",
+        termcap_code: "dc",
+        variable_name: "delete_character",
+        description: "delete character (P*)",
+    },
+    CapInfo {
+        terminfo_name: "dl1",
+        termcap_code: "dl",
+        variable_name: "delete_line",
+        description: "delete line (P*)",
+    },
+    CapInfo {
+        terminfo_name: "dsl",
+        termcap_code: "ds",
+        variable_name: "dis_status_line",
+        description: "disable status line",
+    },
+    CapInfo {
+        terminfo_name: "hd",
+        termcap_code: "hd",
+        variable_name: "down_half_line",
+        description: "half a line down",
+    },
+    CapInfo {
+        terminfo_name: "smacs",
+        termcap_code: "as",
+        variable_name: "enter_alt_charset_mode",
+        description: "start alternate character set (P)",
+    },
+    CapInfo {
+        terminfo_name: "blink",
+        termcap_code: "mb",
+        variable_name: "enter_blink_mode",
+        description: "turn on blinking",
+    },
+    CapInfo {
+        terminfo_name: "bold",
+        termcap_code: "md",
+        variable_name: "enter_bold_mode",
+        description: "turn on bold (extra bright) mode",
+    },
+    CapInfo {
+        terminfo_name: "smcup",
+        termcap_code: "ti",
+        variable_name: "enter_ca_mode",
+        description: "string to start programs using cup",
+    },
+    CapInfo {
+        terminfo_name: "smdc",
+        termcap_code: "dm",
+        variable_name: "enter_delete_mode",
+        description: "enter delete mode",
+    },
+    CapInfo {
+        terminfo_name: "dim",
+        termcap_code: "mh",
+        variable_name: "enter_dim_mode",
+        description: "turn on half-bright mode",
+    },
+    CapInfo {
+        terminfo_name: "smir",
+        termcap_code: "im",
+        variable_name: "enter_insert_mode",
+        description: "enter insert mode",
+    },
+    CapInfo {
+        terminfo_name: "invis",
+        termcap_code: "mk",
+        variable_name: "enter_secure_mode",
+        description: "turn on blank mode (characters invisible)",
+    },
+    CapInfo {
+        terminfo_name: "prot",
+        termcap_code: "mp",
+        variable_name: "enter_protected_mode",
+        description: "turn on protected mode",
+    },
+    CapInfo {
+        terminfo_name: "rev",
+        termcap_code: "mr",
+        variable_name: "enter_reverse_mode",
+        description: "turn on reverse video mode",
+    },
+    CapInfo {
+        terminfo_name: "smso",
+        termcap_code: "so",
+        variable_name: "enter_standout_mode",
+        description: "begin standout mode",
+    },
+    CapInfo {
+        terminfo_name: "smul",
+        termcap_code: "us",
+        variable_name: "enter_underline_mode",
+        description: "begin underline mode",
+    },
+    CapInfo {
+        terminfo_name: "ech",
+        termcap_code: "ec",
+        variable_name: "erase_chars",
+        description: "erase #1 characters (P)",
+    },
+    CapInfo {
+        terminfo_name: "rmacs",
+        termcap_code: "ae",
+        variable_name: "exit_alt_charset_mode",
+        description: "end alternate character set (P)",
+    },
+    CapInfo {
+        terminfo_name: "sgr0",
+        termcap_code: "me",
+        variable_name: "exit_attribute_mode",
+        description: "turn off all attributes",
+    },
+    CapInfo {
+        terminfo_name: "rmcup",
+        termcap_code: "te",
+        variable_name: "exit_ca_mode",
+        description: "strings to end programs using cup",
+    },
+    CapInfo {
+        terminfo_name: "rmdc",
+        termcap_code: "ed",
+        variable_name: "exit_delete_mode",
+        description: "end delete mode",
+    },
+    CapInfo {
+        terminfo_name: "rmir",
+        termcap_code: "ei",
+        variable_name: "exit_insert_mode",
+        description: "exit insert mode",
+    },
+    CapInfo {
+        terminfo_name: "rmso",
+        termcap_code: "se",
+        variable_name: "exit_standout_mode",
+        description: "exit standout mode",
+    },
+    CapInfo {
+        terminfo_name: "rmul",
+        termcap_code: "ue",
+        variable_name: "exit_underline_mode",
+        description: "exit underline mode",
+    },
+    CapInfo {
+        terminfo_name: "flash",
+        termcap_code: "vb",
+        variable_name: "flash_screen",
+        description: "visible bell (may not move cursor)",
+    },
+    CapInfo {
+        terminfo_name: "ff",
+        termcap_code: "ff",
+        variable_name: "form_feed",
+        description: "hardcopy terminal page eject (P*)",
+    },
+    CapInfo {
+        terminfo_name: "fsl",
+        termcap_code: "fs",
+        variable_name: "from_status_line",
+        description: "return from status line",
+    },
+    CapInfo {
+        terminfo_name: "is1",
+        termcap_code: "i1",
+        variable_name: "init_1string",
+        description: "initialization string",
+    },
+    CapInfo {
+        terminfo_name: "is2",
+        termcap_code: "is",
+        variable_name: "init_2string",
+        description: "initialization string",
+    },
+    CapInfo {
+        terminfo_name: "is3",
+        termcap_code: "i3",
+        variable_name: "init_3string",
+        description: "initialization string",
+    },
+    CapInfo {
+        terminfo_name: "if",
+        termcap_code: "if",
+        variable_name: "init_file",
+        description: "name of initialization file",
+    },
+    CapInfo {
+        terminfo_name: "ich1",
+        termcap_code: "ic",
+        variable_name: "insert_character",
+        description: "insert character (P)",
+    },
+    CapInfo {
+        terminfo_name: "il1",
+        termcap_code: "al",
+        variable_name: "insert_line",
+        description: "insert line (P*)",
+    },
+    CapInfo {
+        terminfo_name: "ip",
+        termcap_code: "ip",
+        variable_name: "insert_padding",
+        description: "insert padding after inserted character",
+    },
+    CapInfo {
+        terminfo_name: "kbs",
+        termcap_code: "kb",
+        variable_name: "key_backspace",
+        description: "backspace key",
+    },
+    CapInfo {
+        terminfo_name: "ktbc",
+        termcap_code: "ka",
+        variable_name: "key_catab",
+        description: "clear-all-tabs key",
+    },
+    CapInfo {
+        terminfo_name: "kclr",
+        termcap_code: "kC",
+        variable_name: "key_clear",
+        description: "clear-screen or erase key",
+    },
+    CapInfo {
+        terminfo_name: "kctab",
+        termcap_code: "kt",
+        variable_name: "key_ctab",
+        description: "clear-tab key",
+    },
+    CapInfo {
+        terminfo_name: "kdch1",
+        termcap_code: "kD",
+        variable_name: "key_dc",
+        description: "delete-character key",
+    },
+    CapInfo {
+        terminfo_name: "kdl1",
+        termcap_code: "kL",
+        variable_name: "key_dl",
+        description: "delete-line key",
+    },
+    CapInfo {
+        terminfo_name: "kcud1",
+        termcap_code: "kd",
+        variable_name: "key_down",
+        description: "down-arrow key",
+    },
+    CapInfo {
+        terminfo_name: "krmir",
+        termcap_code: "kM",
+        variable_name: "key_eic",
+        description: "sent by rmir or smir in insert mode",
+    },
+    CapInfo {
+        terminfo_name: "kel",
+        termcap_code: "kE",
+        variable_name: "key_eol",
+        description: "clear-to-end-of-line key",
+    },
+    CapInfo {
+        terminfo_name: "ked",
+        termcap_code: "kS",
+        variable_name: "key_eos",
+        description: "clear-to-end-of-screen key",
+    },
+    CapInfo {
+        terminfo_name: "kf0",
+        termcap_code: "k0",
+        variable_name: "key_f0",
+        description: "F0 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf1",
+        termcap_code: "k1",
+        variable_name: "key_f1",
+        description: "F1 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf10",
+        termcap_code: "k;",
+        variable_name: "key_f10",
+        description: "F10 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf2",
+        termcap_code: "k2",
+        variable_name: "key_f2",
+        description: "F2 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf3",
+        termcap_code: "k3",
+        variable_name: "key_f3",
+        description: "F3 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf4",
+        termcap_code: "k4",
+        variable_name: "key_f4",
+        description: "F4 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf5",
+        termcap_code: "k5",
+        variable_name: "key_f5",
+        description: "F5 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf6",
+        termcap_code: "k6",
+        variable_name: "key_f6",
+        description: "F6 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf7",
+        termcap_code: "k7",
+        variable_name: "key_f7",
+        description: "F7 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf8",
+        termcap_code: "k8",
+        variable_name: "key_f8",
+        description: "F8 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf9",
+        termcap_code: "k9",
+        variable_name: "key_f9",
+        description: "F9 function key",
+    },
+    CapInfo {
+        terminfo_name: "khome",
+        termcap_code: "kh",
+        variable_name: "key_home",
+        description: "home key",
+    },
+    CapInfo {
+        terminfo_name: "kich1",
+        termcap_code: "kI",
+        variable_name: "key_ic",
+        description: "insert-character key",
+    },
+    CapInfo {
+        terminfo_name: "kil1",
+        termcap_code: "kA",
+        variable_name: "key_il",
+        description: "insert-line key",
+    },
+    CapInfo {
+        terminfo_name: "kcub1",
+        termcap_code: "kl",
+        variable_name: "key_left",
+        description: "left-arrow key",
+    },
+    CapInfo {
+        terminfo_name: "kll",
+        termcap_code: "kH",
+        variable_name: "key_ll",
+        description: "lower-left key (home down)",
+    },
+    CapInfo {
+        terminfo_name: "knp",
+        termcap_code: "kN",
+        variable_name: "key_npage",
+        description: "next-page key",
+    },
+    CapInfo {
+        terminfo_name: "kpp",
+        termcap_code: "kP",
+        variable_name: "key_ppage",
+        description: "previous-page key",
+    },
+    CapInfo {
+        terminfo_name: "kcuf1",
+        termcap_code: "kr",
+        variable_name: "key_right",
+        description: "right-arrow key",
+    },
+    CapInfo {
+        terminfo_name: "kind",
+        termcap_code: "kF",
+        variable_name: "key_sf",
+        description: "scroll-forward key",
+    },
+    CapInfo {
+        terminfo_name: "kri",
+        termcap_code: "kR",
+        variable_name: "key_sr",
+        description: "scroll-backward key",
+    },
+    CapInfo {
+        terminfo_name: "khts",
+        termcap_code: "kT",
+        variable_name: "key_stab",
+        description: "set-tab key",
+    },
+    CapInfo {
+        terminfo_name: "kcuu1",
+        termcap_code: "ku",
+        variable_name: "key_up",
+        description: "up-arrow key",
+    },
+    CapInfo {
+        terminfo_name: "rmkx",
+        termcap_code: "ke",
+        variable_name: "keypad_local",
+        description: "leave 'keyboard_transmit' mode",
+    },
+    CapInfo {
+        terminfo_name: "smkx",
+        termcap_code: "ks",
+        variable_name: "keypad_xmit",
+        description: "enter 'keyboard_transmit' mode",
+    },
+    CapInfo {
+        terminfo_name: "lf0",
+        termcap_code: "l0",
+        variable_name: "lab_f0",
+        description: "label on function key f0 if not f0",
+    },
+    CapInfo {
+        terminfo_name: "lf1",
+        termcap_code: "l1",
+        variable_name: "lab_f1",
+        description: "label on function key f1 if not f1",
+    },
+    CapInfo {
+        terminfo_name: "lf10",
+        termcap_code: "la",
+        variable_name: "lab_f10",
+        description: "label on function key f10 if not f10",
+    },
+    CapInfo {
+        terminfo_name: "lf2",
+        termcap_code: "l2",
+        variable_name: "lab_f2",
+        description: "label on function key f2 if not f2",
+    },
+    CapInfo {
+        terminfo_name: "lf3",
+        termcap_code: "l3",
+        variable_name: "lab_f3",
+        description: "label on function key f3 if not f3",
+    },
+    CapInfo {
+        terminfo_name: "lf4",
+        termcap_code: "l4",
+        variable_name: "lab_f4",
+        description: "label on function key f4 if not f4",
+    },
+    CapInfo {
+        terminfo_name: "lf5",
+        termcap_code: "l5",
+        variable_name: "lab_f5",
+        description: "label on function key f5 if not f5",
+    },
+    CapInfo {
+        terminfo_name: "lf6",
+        termcap_code: "l6",
+        variable_name: "lab_f6",
+        description: "label on function key f6 if not f6",
+    },
+    CapInfo {
+        terminfo_name: "lf7",
+        termcap_code: "l7",
+        variable_name: "lab_f7",
+        description: "label on function key f7 if not f7",
+    },
+    CapInfo {
+        terminfo_name: "lf8",
+        termcap_code: "l8",
+        variable_name: "lab_f8",
+        description: "label on function key f8 if not f8",
+    },
+    CapInfo {
+        terminfo_name: "lf9",
+        termcap_code: "l9",
+        variable_name: "lab_f9",
+        description: "label on function key f9 if not f9",
+    },
+    CapInfo {
+        terminfo_name: "rmm",
+        termcap_code: "mo",
+        variable_name: "meta_off",
+        description: "turn off meta mode",
+    },
+    CapInfo {
+        terminfo_name: "smm",
+        termcap_code: "mm",
+        variable_name: "meta_on",
+        description: "turn on meta mode (8th-bit on)",
+    },
+    CapInfo {
+        terminfo_name: "nel",
+        termcap_code: "nw",
+        variable_name: "newline",
+        description: "newline (behave like cr followed by lf)",
+    },
+    CapInfo {
+        terminfo_name: "pad",
+        termcap_code: "pc",
+        variable_name: "pad_char",
+        description: "padding char (instead of null)",
+    },
+    CapInfo {
+        terminfo_name: "dch",
+        termcap_code: "DC",
+        variable_name: "parm_dch",
+        description: "delete #1 characters (P*)",
+    },
+    CapInfo {
+        terminfo_name: "dl",
+        termcap_code: "DL",
+        variable_name: "parm_delete_line",
+        description: "delete #1 lines (P*)",
+    },
+    CapInfo {
+        terminfo_name: "cud",
+        termcap_code: "DO",
+        variable_name: "parm_down_cursor",
+        description: "down #1 lines (P*)",
+    },
+    CapInfo {
+        terminfo_name: "ich",
+        termcap_code: "IC",
+        variable_name: "parm_ich",
+        description: "insert #1 characters (P*)",
+    },
+    CapInfo {
+        terminfo_name: "indn",
+        termcap_code: "SF",
+        variable_name: "parm_index",
+        description: "scroll forward #1 lines (P)",
+    },
+    CapInfo {
+        terminfo_name: "il",
+        termcap_code: "AL",
+        variable_name: "parm_insert_line",
+        description: "insert #1 lines (P*)",
+    },
+    CapInfo {
+        terminfo_name: "cub",
+        termcap_code: "LE",
+        variable_name: "parm_left_cursor",
+        description: "move #1 characters to the left (P)",
+    },
+    CapInfo {
+        terminfo_name: "cuf",
+        termcap_code: "RI",
+        variable_name: "parm_right_cursor",
+        description: "move #1 characters to the right (P*)",
+    },
+    CapInfo {
+        terminfo_name: "rin",
+        termcap_code: "SR",
+        variable_name: "parm_rindex",
+        description: "scroll back #1 lines (P)",
+    },
+    CapInfo {
+        terminfo_name: "cuu",
+        termcap_code: "UP",
+        variable_name: "parm_up_cursor",
+        description: "up #1 lines (P*)",
+    },
+    CapInfo {
+        terminfo_name: "pfkey",
+        termcap_code: "pk",
+        variable_name: "pkey_key",
+        description: "program function key #1 to type string #2",
+    },
+    CapInfo {
+        terminfo_name: "pfloc",
+        termcap_code: "pl",
+        variable_name: "pkey_local",
+        description: "program function key #1 to execute string #2",
+    },
+    CapInfo {
+        terminfo_name: "pfx",
+        termcap_code: "px",
+        variable_name: "pkey_xmit",
+        description: "program function key #1 to transmit string #2",
+    },
+    CapInfo {
+        terminfo_name: "mc0",
+        termcap_code: "ps",
+        variable_name: "print_screen",
+        description: "print contents of screen",
+    },
+    CapInfo {
+        terminfo_name: "mc4",
+        termcap_code: "pf",
+        variable_name: "prtr_off",
+        description: "turn off printer",
+    },
+    CapInfo {
+        terminfo_name: "mc5",
+        termcap_code: "po",
+        variable_name: "prtr_on",
+        description: "turn on printer",
+    },
+    CapInfo {
+        terminfo_name: "rep",
+        termcap_code: "rp",
+        variable_name: "repeat_char",
+        description: "repeat char #1 #2 times (P*)",
+    },
+    CapInfo {
+        terminfo_name: "rs1",
+        termcap_code: "r1",
+        variable_name: "reset_1string",
+        description: "reset string",
+    },
+    CapInfo {
+        terminfo_name: "rs2",
+        termcap_code: "r2",
+        variable_name: "reset_2string",
+        description: "reset string",
+    },
+    CapInfo {
+        terminfo_name: "rs3",
+        termcap_code: "r3",
+        variable_name: "reset_3string",
+        description: "reset string",
+    },
+    CapInfo {
+        terminfo_name: "rf",
+        termcap_code: "rf",
+        variable_name: "reset_file",
+        description: "name of reset file",
+    },
+    CapInfo {
+        terminfo_name: "rc",
+        termcap_code: "rc",
+        variable_name: "restore_cursor",
+        description: "restore cursor to position of last save_cursor",
+    },
+    CapInfo {
+        terminfo_name: "vpa",
+        termcap_code: "cv",
+        variable_name: "row_address",
+        description: "vertical position #1 absolute (P)",
+    },
+    CapInfo {
+        terminfo_name: "sc",
+        termcap_code: "sc",
+        variable_name: "save_cursor",
+        description: "save current cursor position (P)",
+    },
+    CapInfo {
+        terminfo_name: "ind",
+        termcap_code: "sf",
+        variable_name: "scroll_forward",
+        description: "scroll text up (P)",
+    },
+    CapInfo {
+        terminfo_name: "ri",
+        termcap_code: "sr",
+        variable_name: "scroll_reverse",
+        description: "scroll text down (P)",
+    },
+    CapInfo {
+        terminfo_name: "sgr",
+        termcap_code: "sa",
+        variable_name: "set_attributes",
+        description: "define video attributes #1-#9 (PG9)",
+    },
+    CapInfo {
+        terminfo_name: "hts",
+        termcap_code: "st",
+        variable_name: "set_tab",
+        description: "set a tab in every row, current columns",
+    },
+    CapInfo {
+        terminfo_name: "wind",
+        termcap_code: "wi",
+        variable_name: "set_window",
+        description: "current window is lines #1-#2 cols #3-#4",
+    },
+    CapInfo {
+        terminfo_name: "ht",
+        termcap_code: "ta",
+        variable_name: "tab",
+        description: "tab to next 8-space hardware tab stop",
+    },
+    CapInfo {
+        terminfo_name: "tsl",
+        termcap_code: "ts",
+        variable_name: "to_status_line",
+        description: "move to status line, column #1",
+    },
+    CapInfo {
+        terminfo_name: "uc",
+        termcap_code: "uc",
+        variable_name: "underline_char",
+        description: "underline char and move past it",
+    },
+    CapInfo {
+        terminfo_name: "hu",
+        termcap_code: "hu",
+        variable_name: "up_half_line",
+        description: "half a line up",
+    },
+    CapInfo {
+        terminfo_name: "iprog",
+        termcap_code: "iP",
+        variable_name: "init_prog",
+        description: "path name of program for initialization",
+    },
+    CapInfo {
+        terminfo_name: "ka1",
+        termcap_code: "K1",
+        variable_name: "key_a1",
+        description: "upper left of keypad",
+    },
+    CapInfo {
+        terminfo_name: "ka3",
+        termcap_code: "K3",
+        variable_name: "key_a3",
+        description: "upper right of keypad",
+    },
+    CapInfo {
+        terminfo_name: "kb2",
+        termcap_code: "K2",
+        variable_name: "key_b2",
+        description: "center of keypad",
+    },
+    CapInfo {
+        terminfo_name: "kc1",
+        termcap_code: "K4",
+        variable_name: "key_c1",
+        description: "lower left of keypad",
+    },
+    CapInfo {
+        terminfo_name: "kc3",
+        termcap_code: "K5",
+        variable_name: "key_c3",
+        description: "lower right of keypad",
+    },
+    CapInfo {
+        terminfo_name: "mc5p",
+        termcap_code: "pO",
+        variable_name: "prtr_non",
+        description: "turn on printer for #1 bytes",
+    },
+    CapInfo {
+        terminfo_name: "rmp",
+        termcap_code: "rP",
+        variable_name: "char_padding",
+        description: "like ip but when in insert mode",
+    },
+    CapInfo {
+        terminfo_name: "acsc",
+        termcap_code: "ac",
+        variable_name: "acs_chars",
+        description: "graphics charset pairs, based on vt100",
+    },
+    CapInfo {
+        terminfo_name: "pln",
+        termcap_code: "pn",
+        variable_name: "plab_norm",
+        description: "program label #1 to show string #2",
+    },
+    CapInfo {
+        terminfo_name: "kcbt",
+        termcap_code: "kB",
+        variable_name: "key_btab",
+        description: "back-tab key",
+    },
+    CapInfo {
+        terminfo_name: "smxon",
+        termcap_code: "SX",
+        variable_name: "enter_xon_mode",
+        description: "turn on xon/xoff handshaking",
+    },
+    CapInfo {
+        terminfo_name: "rmxon",
+        termcap_code: "RX",
+        variable_name: "exit_xon_mode",
+        description: "turn off xon/xoff handshaking",
+    },
+    CapInfo {
+        terminfo_name: "smam",
+        termcap_code: "SA",
+        variable_name: "enter_am_mode",
+        description: "turn on automatic margins",
+    },
+    CapInfo {
+        terminfo_name: "rmam",
+        termcap_code: "RA",
+        variable_name: "exit_am_mode",
+        description: "turn off automatic margins",
+    },
+    CapInfo {
+        terminfo_name: "xonc",
+        termcap_code: "XN",
+        variable_name: "xon_character",
+        description: "XON character",
+    },
+    CapInfo {
+        terminfo_name: "xoffc",
+        termcap_code: "XF",
+        variable_name: "xoff_character",
+        description: "XOFF character",
+    },
+    CapInfo {
+        terminfo_name: "enacs",
+        termcap_code: "eA",
+        variable_name: "ena_acs",
+        description: "enable alternate char set",
+    },
+    CapInfo {
+        terminfo_name: "smln",
+        termcap_code: "LO",
+        variable_name: "label_on",
+        description: "turn on soft labels",
+    },
+    CapInfo {
+        terminfo_name: "rmln",
+        termcap_code: "LF",
+        variable_name: "label_off",
+        description: "turn off soft labels",
+    },
+    CapInfo {
+        terminfo_name: "kbeg",
+        termcap_code: "@1",
+        variable_name: "key_beg",
+        description: "begin key",
+    },
+    CapInfo {
+        terminfo_name: "kcan",
+        termcap_code: "@2",
+        variable_name: "key_cancel",
+        description: "cancel key",
+    },
+    CapInfo {
+        terminfo_name: "kclo",
+        termcap_code: "@3",
+        variable_name: "key_close",
+        description: "close key",
+    },
+    CapInfo {
+        terminfo_name: "kcmd",
+        termcap_code: "@4",
+        variable_name: "key_command",
+        description: "command key",
+    },
+    CapInfo {
+        terminfo_name: "kcpy",
+        termcap_code: "@5",
+        variable_name: "key_copy",
+        description: "copy key",
+    },
+    CapInfo {
+        terminfo_name: "kcrt",
+        termcap_code: "@6",
+        variable_name: "key_create",
+        description: "create key",
+    },
+    CapInfo {
+        terminfo_name: "kend",
+        termcap_code: "@7",
+        variable_name: "key_end",
+        description: "end key",
+    },
+    CapInfo {
+        terminfo_name: "kent",
+        termcap_code: "@8",
+        variable_name: "key_enter",
+        description: "enter/send key",
+    },
+    CapInfo {
+        terminfo_name: "kext",
+        termcap_code: "@9",
+        variable_name: "key_exit",
+        description: "exit key",
+    },
+    CapInfo {
+        terminfo_name: "kfnd",
+        termcap_code: "@0",
+        variable_name: "key_find",
+        description: "find key",
+    },
+    CapInfo {
+        terminfo_name: "khlp",
+        termcap_code: "%1",
+        variable_name: "key_help",
+        description: "help key",
+    },
+    CapInfo {
+        terminfo_name: "kmrk",
+        termcap_code: "%2",
+        variable_name: "key_mark",
+        description: "mark key",
+    },
+    CapInfo {
+        terminfo_name: "kmsg",
+        termcap_code: "%3",
+        variable_name: "key_message",
+        description: "message key",
+    },
+    CapInfo {
+        terminfo_name: "kmov",
+        termcap_code: "%4",
+        variable_name: "key_move",
+        description: "move key",
+    },
+    CapInfo {
+        terminfo_name: "knxt",
+        termcap_code: "%5",
+        variable_name: "key_next",
+        description: "next key",
+    },
+    CapInfo {
+        terminfo_name: "kopn",
+        termcap_code: "%6",
+        variable_name: "key_open",
+        description: "open key",
+    },
+    CapInfo {
+        terminfo_name: "kopt",
+        termcap_code: "%7",
+        variable_name: "key_options",
+        description: "options key",
+    },
+    CapInfo {
+        terminfo_name: "kprv",
+        termcap_code: "%8",
+        variable_name: "key_previous",
+        description: "previous key",
+    },
+    CapInfo {
+        terminfo_name: "kprt",
+        termcap_code: "%9",
+        variable_name: "key_print",
+        description: "print key",
+    },
+    CapInfo {
+        terminfo_name: "krdo",
+        termcap_code: "%0",
+        variable_name: "key_redo",
+        description: "redo key",
+    },
+    CapInfo {
+        terminfo_name: "kref",
+        termcap_code: "&1",
+        variable_name: "key_reference",
+        description: "reference key",
+    },
+    CapInfo {
+        terminfo_name: "krfr",
+        termcap_code: "&2",
+        variable_name: "key_refresh",
+        description: "refresh key",
+    },
+    CapInfo {
+        terminfo_name: "krpl",
+        termcap_code: "&3",
+        variable_name: "key_replace",
+        description: "replace key",
+    },
+    CapInfo {
+        terminfo_name: "krst",
+        termcap_code: "&4",
+        variable_name: "key_restart",
+        description: "restart key",
+    },
+    CapInfo {
+        terminfo_name: "kres",
+        termcap_code: "&5",
+        variable_name: "key_resume",
+        description: "resume key",
+    },
+    CapInfo {
+        terminfo_name: "ksav",
+        termcap_code: "&6",
+        variable_name: "key_save",
+        description: "save key",
+    },
+    CapInfo {
+        terminfo_name: "kspd",
+        termcap_code: "&7",
+        variable_name: "key_suspend",
+        description: "suspend key",
+    },
+    CapInfo {
+        terminfo_name: "kund",
+        termcap_code: "&8",
+        variable_name: "key_undo",
+        description: "undo key",
+    },
+    CapInfo {
+        terminfo_name: "kBEG",
+        termcap_code: "&9",
+        variable_name: "key_sbeg",
+        description: "shifted begin key",
+    },
+    CapInfo {
+        terminfo_name: "kCAN",
+        termcap_code: "&0",
+        variable_name: "key_scancel",
+        description: "shifted cancel key",
+    },
+    CapInfo {
+        terminfo_name: "kCMD",
+        termcap_code: "*1",
+        variable_name: "key_scommand",
+        description: "shifted command key",
+    },
+    CapInfo {
+        terminfo_name: "kCPY",
+        termcap_code: "*2",
+        variable_name: "key_scopy",
+        description: "shifted copy key",
+    },
+    CapInfo {
+        terminfo_name: "kCRT",
+        termcap_code: "*3",
+        variable_name: "key_screate",
+        description: "shifted create key",
+    },
+    CapInfo {
+        terminfo_name: "kDC",
+        termcap_code: "*4",
+        variable_name: "key_sdc",
+        description: "shifted delete-character key",
+    },
+    CapInfo {
+        terminfo_name: "kDL",
+        termcap_code: "*5",
+        variable_name: "key_sdl",
+        description: "shifted delete-line key",
+    },
+    CapInfo {
+        terminfo_name: "kslt",
+        termcap_code: "*6",
+        variable_name: "key_select",
+        description: "select key",
+    },
+    CapInfo {
+        terminfo_name: "kEND",
+        termcap_code: "*7",
+        variable_name: "key_send",
+        description: "shifted end key",
+    },
+    CapInfo {
+        terminfo_name: "kEOL",
+        termcap_code: "*8",
+        variable_name: "key_seol",
+        description: "shifted clear-to-end-of-line key",
+    },
+    CapInfo {
+        terminfo_name: "kEXT",
+        termcap_code: "*9",
+        variable_name: "key_sexit",
+        description: "shifted exit key",
+    },
+    CapInfo {
+        terminfo_name: "kFND",
+        termcap_code: "*0",
+        variable_name: "key_sfind",
+        description: "shifted find key",
+    },
+    CapInfo {
+        terminfo_name: "kHLP",
+        termcap_code: "#1",
+        variable_name: "key_shelp",
+        description: "shifted help key",
+    },
+    CapInfo {
+        terminfo_name: "kHOM",
+        termcap_code: "#2",
+        variable_name: "key_shome",
+        description: "shifted home key",
+    },
+    CapInfo {
+        terminfo_name: "kIC",
+        termcap_code: "#3",
+        variable_name: "key_sic",
+        description: "shifted insert-character key",
+    },
+    CapInfo {
+        terminfo_name: "kLFT",
+        termcap_code: "#4",
+        variable_name: "key_sleft",
+        description: "shifted left-arrow key",
+    },
+    CapInfo {
+        terminfo_name: "kMSG",
+        termcap_code: "%a",
+        variable_name: "key_smessage",
+        description: "shifted message key",
+    },
+    CapInfo {
+        terminfo_name: "kMOV",
+        termcap_code: "%b",
+        variable_name: "key_smove",
+        description: "shifted move key",
+    },
+    CapInfo {
+        terminfo_name: "kNXT",
+        termcap_code: "%c",
+        variable_name: "key_snext",
+        description: "shifted next key",
+    },
+    CapInfo {
+        terminfo_name: "kOPT",
+        termcap_code: "%d",
+        variable_name: "key_soptions",
+        description: "shifted options key",
+    },
+    CapInfo {
+        terminfo_name: "kPRV",
+        termcap_code: "%e",
+        variable_name: "key_sprevious",
+        description: "shifted previous key",
+    },
+    CapInfo {
+        terminfo_name: "kPRT",
+        termcap_code: "%f",
+        variable_name: "key_sprint",
+        description: "shifted print key",
+    },
+    CapInfo {
+        terminfo_name: "kRDO",
+        termcap_code: "%g",
+        variable_name: "key_sredo",
+        description: "shifted redo key",
+    },
+    CapInfo {
+        terminfo_name: "kRPL",
+        termcap_code: "%h",
+        variable_name: "key_sreplace",
+        description: "shifted replace key",
+    },
+    CapInfo {
+        terminfo_name: "kRIT",
+        termcap_code: "%i",
+        variable_name: "key_sright",
+        description: "shifted right-arrow key",
+    },
+    CapInfo {
+        terminfo_name: "kRES",
+        termcap_code: "%j",
+        variable_name: "key_srsume",
+        description: "shifted resume key",
+    },
+    CapInfo {
+        terminfo_name: "kSAV",
+        termcap_code: "!1",
+        variable_name: "key_ssave",
+        description: "shifted save key",
+    },
+    CapInfo {
+        terminfo_name: "kSPD",
+        termcap_code: "!2",
+        variable_name: "key_ssuspend",
+        description: "shifted suspend key",
+    },
+    CapInfo {
+        terminfo_name: "kUND",
+        termcap_code: "!3",
+        variable_name: "key_sundo",
+        description: "shifted undo key",
+    },
+    CapInfo {
+        terminfo_name: "rfi",
+        termcap_code: "RF",
+        variable_name: "req_for_input",
+        description: "send next input char (for ptys)",
+    },
+    CapInfo {
+        terminfo_name: "kf11",
+        termcap_code: "F1",
+        variable_name: "key_f11",
+        description: "F11 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf12",
+        termcap_code: "F2",
+        variable_name: "key_f12",
+        description: "F12 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf13",
+        termcap_code: "F3",
+        variable_name: "key_f13",
+        description: "F13 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf14",
+        termcap_code: "F4",
+        variable_name: "key_f14",
+        description: "F14 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf15",
+        termcap_code: "F5",
+        variable_name: "key_f15",
+        description: "F15 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf16",
+        termcap_code: "F6",
+        variable_name: "key_f16",
+        description: "F16 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf17",
+        termcap_code: "F7",
+        variable_name: "key_f17",
+        description: "F17 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf18",
+        termcap_code: "F8",
+        variable_name: "key_f18",
+        description: "F18 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf19",
+        termcap_code: "F9",
+        variable_name: "key_f19",
+        description: "F19 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf20",
+        termcap_code: "FA",
+        variable_name: "key_f20",
+        description: "F20 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf21",
+        termcap_code: "FB",
+        variable_name: "key_f21",
+        description: "F21 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf22",
+        termcap_code: "FC",
+        variable_name: "key_f22",
+        description: "F22 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf23",
+        termcap_code: "FD",
+        variable_name: "key_f23",
+        description: "F23 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf24",
+        termcap_code: "FE",
+        variable_name: "key_f24",
+        description: "F24 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf25",
+        termcap_code: "FF",
+        variable_name: "key_f25",
+        description: "F25 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf26",
+        termcap_code: "FG",
+        variable_name: "key_f26",
+        description: "F26 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf27",
+        termcap_code: "FH",
+        variable_name: "key_f27",
+        description: "F27 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf28",
+        termcap_code: "FI",
+        variable_name: "key_f28",
+        description: "F28 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf29",
+        termcap_code: "FJ",
+        variable_name: "key_f29",
+        description: "F29 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf30",
+        termcap_code: "FK",
+        variable_name: "key_f30",
+        description: "F30 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf31",
+        termcap_code: "FL",
+        variable_name: "key_f31",
+        description: "F31 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf32",
+        termcap_code: "FM",
+        variable_name: "key_f32",
+        description: "F32 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf33",
+        termcap_code: "FN",
+        variable_name: "key_f33",
+        description: "F33 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf34",
+        termcap_code: "FO",
+        variable_name: "key_f34",
+        description: "F34 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf35",
+        termcap_code: "FP",
+        variable_name: "key_f35",
+        description: "F35 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf36",
+        termcap_code: "FQ",
+        variable_name: "key_f36",
+        description: "F36 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf37",
+        termcap_code: "FR",
+        variable_name: "key_f37",
+        description: "F37 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf38",
+        termcap_code: "FS",
+        variable_name: "key_f38",
+        description: "F38 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf39",
+        termcap_code: "FT",
+        variable_name: "key_f39",
+        description: "F39 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf40",
+        termcap_code: "FU",
+        variable_name: "key_f40",
+        description: "F40 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf41",
+        termcap_code: "FV",
+        variable_name: "key_f41",
+        description: "F41 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf42",
+        termcap_code: "FW",
+        variable_name: "key_f42",
+        description: "F42 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf43",
+        termcap_code: "FX",
+        variable_name: "key_f43",
+        description: "F43 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf44",
+        termcap_code: "FY",
+        variable_name: "key_f44",
+        description: "F44 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf45",
+        termcap_code: "FZ",
+        variable_name: "key_f45",
+        description: "F45 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf46",
+        termcap_code: "Fa",
+        variable_name: "key_f46",
+        description: "F46 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf47",
+        termcap_code: "Fb",
+        variable_name: "key_f47",
+        description: "F47 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf48",
+        termcap_code: "Fc",
+        variable_name: "key_f48",
+        description: "F48 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf49",
+        termcap_code: "Fd",
+        variable_name: "key_f49",
+        description: "F49 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf50",
+        termcap_code: "Fe",
+        variable_name: "key_f50",
+        description: "F50 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf51",
+        termcap_code: "Ff",
+        variable_name: "key_f51",
+        description: "F51 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf52",
+        termcap_code: "Fg",
+        variable_name: "key_f52",
+        description: "F52 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf53",
+        termcap_code: "Fh",
+        variable_name: "key_f53",
+        description: "F53 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf54",
+        termcap_code: "Fi",
+        variable_name: "key_f54",
+        description: "F54 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf55",
+        termcap_code: "Fj",
+        variable_name: "key_f55",
+        description: "F55 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf56",
+        termcap_code: "Fk",
+        variable_name: "key_f56",
+        description: "F56 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf57",
+        termcap_code: "Fl",
+        variable_name: "key_f57",
+        description: "F57 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf58",
+        termcap_code: "Fm",
+        variable_name: "key_f58",
+        description: "F58 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf59",
+        termcap_code: "Fn",
+        variable_name: "key_f59",
+        description: "F59 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf60",
+        termcap_code: "Fo",
+        variable_name: "key_f60",
+        description: "F60 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf61",
+        termcap_code: "Fp",
+        variable_name: "key_f61",
+        description: "F61 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf62",
+        termcap_code: "Fq",
+        variable_name: "key_f62",
+        description: "F62 function key",
+    },
+    CapInfo {
+        terminfo_name: "kf63",
+        termcap_code: "Fr",
+        variable_name: "key_f63",
+        description: "F63 function key",
+    },
+    CapInfo {
+        terminfo_name: "el1",
+        termcap_code: "cb",
+        variable_name: "clr_bol",
+        description: "Clear to beginning of line",
+    },
+    CapInfo {
+        terminfo_name: "mgc",
+        termcap_code: "MC",
+        variable_name: "clear_margins",
+        description: "clear right and left soft margins",
+    },
+    CapInfo {
+        terminfo_name: "smgl",
+        termcap_code: "ML",
+        variable_name: "set_left_margin",
+        description: "set left soft margin at current column. (ML is not in BSD termcap).",
+    },
+    CapInfo {
+        terminfo_name: "smgr",
+        termcap_code: "MR",
+        variable_name: "set_right_margin",
+        description: "set right soft margin at current column",
+    },
+    CapInfo {
+        terminfo_name: "fln",
+        termcap_code: "Lf",
+        variable_name: "label_format",
+        description: "label format",
+    },
+    CapInfo {
+        terminfo_name: "sclk",
+        termcap_code: "SC",
+        variable_name: "set_clock",
+        description: "set clock, #1 hrs #2 mins #3 secs",
+    },
+    CapInfo {
+        terminfo_name: "dclk",
+        termcap_code: "DK",
+        variable_name: "display_clock",
+        description: "display clock",
+    },
+    CapInfo {
+        terminfo_name: "rmclk",
+        termcap_code: "RC",
+        variable_name: "remove_clock",
+        description: "remove clock",
+    },
+    CapInfo {
+        terminfo_name: "cwin",
+        termcap_code: "CW",
+        variable_name: "create_window",
+        description: "define a window #1 from #2,#3 to #4,#5",
+    },
+    CapInfo {
+        terminfo_name: "wingo",
+        termcap_code: "WG",
+        variable_name: "goto_window",
+        description: "go to window #1",
+    },
+    CapInfo {
+        terminfo_name: "hup",
+        termcap_code: "HU",
+        variable_name: "hangup",
+        description: "hang-up phone",
+    },
+    CapInfo {
+        terminfo_name: "dial",
+        termcap_code: "DI",
+        variable_name: "dial_phone",
+        description: "dial number #1",
+    },
+    CapInfo {
+        terminfo_name: "qdial",
+        termcap_code: "QD",
+        variable_name: "quick_dial",
+        description: "dial number #1 without checking",
+    },
+    CapInfo {
+        terminfo_name: "tone",
+        termcap_code: "TO",
+        variable_name: "tone",
+        description: "select touch tone dialing",
+    },
+    CapInfo {
+        terminfo_name: "pulse",
+        termcap_code: "PU",
+        variable_name: "pulse",
+        description: "select pulse dialing",
+    },
+    CapInfo {
+        terminfo_name: "hook",
+        termcap_code: "fh",
+        variable_name: "flash_hook",
+        description: "flash switch hook",
+    },
+    CapInfo {
+        terminfo_name: "pause",
+        termcap_code: "PA",
+        variable_name: "fixed_pause",
+        description: "pause for 2-3 seconds",
+    },
+    CapInfo {
+        terminfo_name: "wait",
+        termcap_code: "WA",
+        variable_name: "wait_tone",
+        description: "wait for dial-tone",
+    },
+    CapInfo {
+        terminfo_name: "u0",
+        termcap_code: "u0",
+        variable_name: "user0",
+        description: "User string #0",
+    },
+    CapInfo {
+        terminfo_name: "u1",
+        termcap_code: "u1",
+        variable_name: "user1",
+        description: "User string #1",
+    },
+    CapInfo {
+        terminfo_name: "u2",
+        termcap_code: "u2",
+        variable_name: "user2",
+        description: "User string #2",
+    },
+    CapInfo {
+        terminfo_name: "u3",
+        termcap_code: "u3",
+        variable_name: "user3",
+        description: "User string #3",
+    },
+    CapInfo {
+        terminfo_name: "u4",
+        termcap_code: "u4",
+        variable_name: "user4",
+        description: "User string #4",
+    },
+    CapInfo {
+        terminfo_name: "u5",
+        termcap_code: "u5",
+        variable_name: "user5",
+        description: "User string #5",
+    },
+    CapInfo {
+        terminfo_name: "u6",
+        termcap_code: "u6",
+        variable_name: "user6",
+        description: "User string #6",
+    },
+    CapInfo {
+        terminfo_name: "u7",
+        termcap_code: "u7",
+        variable_name: "user7",
+        description: "User string #7",
+    },
+    CapInfo {
+        terminfo_name: "u8",
+        termcap_code: "u8",
+        variable_name: "user8",
+        description: "User string #8",
+    },
+    CapInfo {
+        terminfo_name: "u9",
+        termcap_code: "u9",
+        variable_name: "user9",
+        description: "User string #9",
+    },
+    CapInfo {
+        terminfo_name: "op",
+        termcap_code: "op",
+        variable_name: "orig_pair",
+        description: "Set default pair to its original value",
+    },
+    CapInfo {
+        terminfo_name: "oc",
+        termcap_code: "oc",
+        variable_name: "orig_colors",
+        description: "Set all color pairs to the original ones",
+    },
+    CapInfo {
+        terminfo_name: "initc",
+        termcap_code: "Ic",
+        variable_name: "initialize_color",
+        description: "initialize color #1 to (#2,#3,#4)",
+    },
+    CapInfo {
+        terminfo_name: "initp",
+        termcap_code: "Ip",
+        variable_name: "initialize_pair",
+        description: "Initialize color pair #1 to fg=(#2,#3,#4), bg=(#5,#6,#7)",
+    },
+    CapInfo {
+        terminfo_name: "scp",
+        termcap_code: "sp",
+        variable_name: "set_color_pair",
+        description: "Set current color pair to #1",
+    },
+    CapInfo {
+        terminfo_name: "setf",
+        termcap_code: "Sf",
+        variable_name: "set_foreground",
+        description: "Set foreground color #1",
+    },
+    CapInfo {
+        terminfo_name: "setb",
+        termcap_code: "Sb",
+        variable_name: "set_background",
+        description: "Set background color #1",
+    },
+    CapInfo {
+        terminfo_name: "cpi",
+        termcap_code: "ZA",
+        variable_name: "change_char_pitch",
+        description: "Change number of characters per inch to #1",
+    },
+    CapInfo {
+        terminfo_name: "lpi",
+        termcap_code: "ZB",
+        variable_name: "change_line_pitch",
+        description: "Change number of lines per inch to #1",
+    },
+    CapInfo {
+        terminfo_name: "chr",
+        termcap_code: "ZC",
+        variable_name: "change_res_horz",
+        description: "Change horizontal resolution to #1",
+    },
+    CapInfo {
+        terminfo_name: "cvr",
+        termcap_code: "ZD",
+        variable_name: "change_res_vert",
+        description: "Change vertical resolution to #1",
+    },
+    CapInfo {
+        terminfo_name: "defc",
+        termcap_code: "ZE",
+        variable_name: "define_char",
+        description: "Define a character #1, #2 dots wide, descender #3",
+    },
+    CapInfo {
+        terminfo_name: "swidm",
+        termcap_code: "ZF",
+        variable_name: "enter_doublewide_mode",
+        description: "Enter double-wide mode",
+    },
+    CapInfo {
+        terminfo_name: "sdrfq",
+        termcap_code: "ZG",
+        variable_name: "enter_draft_quality",
+        description: "Enter draft-quality mode",
+    },
+    CapInfo {
+        terminfo_name: "sitm",
+        termcap_code: "ZH",
+        variable_name: "enter_italics_mode",
+        description: "Enter italic mode",
+    },
+    CapInfo {
+        terminfo_name: "slm",
+        termcap_code: "ZI",
+        variable_name: "enter_leftward_mode",
+        description: "Start leftward carriage motion",
+    },
+    CapInfo {
+        terminfo_name: "smicm",
+        termcap_code: "ZJ",
+        variable_name: "enter_micro_mode",
+        description: "Start micro-motion mode",
+    },
+    CapInfo {
+        terminfo_name: "snlq",
+        termcap_code: "ZK",
+        variable_name: "enter_near_letter_quality",
+        description: "Enter NLQ mode",
+    },
+    CapInfo {
+        terminfo_name: "snrmq",
+        termcap_code: "ZL",
+        variable_name: "enter_normal_quality",
+        description: "Enter normal-quality mode",
+    },
+    CapInfo {
+        terminfo_name: "sshm",
+        termcap_code: "ZM",
+        variable_name: "enter_shadow_mode",
+        description: "Enter shadow-print mode",
+    },
+    CapInfo {
+        terminfo_name: "ssubm",
+        termcap_code: "ZN",
+        variable_name: "enter_subscript_mode",
+        description: "Enter subscript mode",
+    },
+    CapInfo {
+        terminfo_name: "ssupm",
+        termcap_code: "ZO",
+        variable_name: "enter_superscript_mode",
+        description: "Enter superscript mode",
+    },
+    CapInfo {
+        terminfo_name: "sum",
+        termcap_code: "ZP",
+        variable_name: "enter_upward_mode",
+        description: "Start upward carriage motion",
+    },
+    CapInfo {
+        terminfo_name: "rwidm",
+        termcap_code: "ZQ",
+        variable_name: "exit_doublewide_mode",
+        description: "End double-wide mode",
+    },
+    CapInfo {
+        terminfo_name: "ritm",
+        termcap_code: "ZR",
+        variable_name: "exit_italics_mode",
+        description: "End italic mode",
+    },
+    CapInfo {
+        terminfo_name: "rlm",
+        termcap_code: "ZS",
+        variable_name: "exit_leftward_mode",
+        description: "End left-motion mode",
+    },
+    CapInfo {
+        terminfo_name: "rmicm",
+        termcap_code: "ZT",
+        variable_name: "exit_micro_mode",
+        description: "End micro-motion mode",
+    },
+    CapInfo {
+        terminfo_name: "rshm",
+        termcap_code: "ZU",
+        variable_name: "exit_shadow_mode",
+        description: "End shadow-print mode",
+    },
+    CapInfo {
+        terminfo_name: "rsubm",
+        termcap_code: "ZV",
+        variable_name: "exit_subscript_mode",
+        description: "End subscript mode",
+    },
+    CapInfo {
+        terminfo_name: "rsupm",
+        termcap_code: "ZW",
+        variable_name: "exit_superscript_mode",
+        description: "End superscript mode",
+    },
+    CapInfo {
+        terminfo_name: "rum",
+        termcap_code: "ZX",
+        variable_name: "exit_upward_mode",
+        description: "End reverse character motion",
+    },
+    CapInfo {
+        terminfo_name: "mhpa",
+        termcap_code: "ZY",
+        variable_name: "micro_column_address",
+        description: "Like column_address in micro mode",
+    },
+    CapInfo {
+        terminfo_name: "mcud1",
+        termcap_code: "ZZ",
+        variable_name: "micro_down",
+        description: "Like cursor_down in micro mode",
+    },
+    CapInfo {
+        terminfo_name: "mcub1",
+        termcap_code: "Za",
+        variable_name: "micro_left",
+        description: "Like cursor_left in micro mode",
+    },
+    CapInfo {
+        terminfo_name: "mcuf1",
+        termcap_code: "Zb",
+        variable_name: "micro_right",
+        description: "Like cursor_right in micro mode",
+    },
+    CapInfo {
+        terminfo_name: "mvpa",
+        termcap_code: "Zc",
+        variable_name: "micro_row_address",
+        description: "Like row_address #1 in micro mode",
+    },
+    CapInfo {
+        terminfo_name: "mcuu1",
+        termcap_code: "Zd",
+        variable_name: "micro_up",
+        description: "Like cursor_up in micro mode",
+    },
+    CapInfo {
+        terminfo_name: "porder",
+        termcap_code: "Ze",
+        variable_name: "order_of_pins",
+        description: "Match software bits to print-head pins",
+    },
+    CapInfo {
+        terminfo_name: "mcud",
+        termcap_code: "Zf",
+        variable_name: "parm_down_micro",
+        description: "Like parm_down_cursor in micro mode",
+    },
+    CapInfo {
+        terminfo_name: "mcub",
+        termcap_code: "Zg",
+        variable_name: "parm_left_micro",
+        description: "Like parm_left_cursor in micro mode",
+    },
+    CapInfo {
+        terminfo_name: "mcuf",
+        termcap_code: "Zh",
+        variable_name: "parm_right_micro",
+        description: "Like parm_right_cursor in micro mode",
+    },
+    CapInfo {
+        terminfo_name: "mcuu",
+        termcap_code: "Zi",
+        variable_name: "parm_up_micro",
+        description: "Like parm_up_cursor in micro mode",
+    },
+    CapInfo {
+        terminfo_name: "scs",
+        termcap_code: "Zj",
+        variable_name: "select_char_set",
+        description: "Select character set, #1",
+    },
+    CapInfo {
+        terminfo_name: "smgb",
+        termcap_code: "Zk",
+        variable_name: "set_bottom_margin",
+        description: "Set bottom margin at current line",
+    },
+    CapInfo {
+        terminfo_name: "smgbp",
+        termcap_code: "Zl",
+        variable_name: "set_bottom_margin_parm",
+        description: "Set bottom margin at line #1 or (if smgtp is not given) #2 lines from bottom",
+    },
+    CapInfo {
+        terminfo_name: "smglp",
+        termcap_code: "Zm",
+        variable_name: "set_left_margin_parm",
+        description: "Set left (right) margin at column #1",
+    },
+    CapInfo {
+        terminfo_name: "smgrp",
+        termcap_code: "Zn",
+        variable_name: "set_right_margin_parm",
+        description: "Set right margin at column #1",
+    },
+    CapInfo {
+        terminfo_name: "smgt",
+        termcap_code: "Zo",
+        variable_name: "set_top_margin",
+        description: "Set top margin at current line",
+    },
+    CapInfo {
+        terminfo_name: "smgtp",
+        termcap_code: "Zp",
+        variable_name: "set_top_margin_parm",
+        description: "Set top (bottom) margin at row #1",
+    },
+    CapInfo {
+        terminfo_name: "sbim",
+        termcap_code: "Zq",
+        variable_name: "start_bit_image",
+        description: "Start printing bit image graphics",
+    },
+    CapInfo {
+        terminfo_name: "scsd",
+        termcap_code: "Zr",
+        variable_name: "start_char_set_def",
+        description: "Start character set definition #1, with #2 characters in the set",
+    },
+    CapInfo {
+        terminfo_name: "rbim",
+        termcap_code: "Zs",
+        variable_name: "stop_bit_image",
+        description: "Stop printing bit image graphics",
+    },
+    CapInfo {
+        terminfo_name: "rcsd",
+        termcap_code: "Zt",
+        variable_name: "stop_char_set_def",
+        description: "End definition of character set #1",
+    },
+    CapInfo {
+        terminfo_name: "subcs",
+        termcap_code: "Zu",
+        variable_name: "subscript_characters",
+        description: "List of subscriptable characters",
+    },
+    CapInfo {
+        terminfo_name: "supcs",
+        termcap_code: "Zv",
+        variable_name: "superscript_characters",
+        description: "List of superscriptable characters",
+    },
+    CapInfo {
+        terminfo_name: "docr",
+        termcap_code: "Zw",
+        variable_name: "these_cause_cr",
+        description: "Printing any of these characters causes CR",
+    },
+    CapInfo {
+        terminfo_name: "zerom",
+        termcap_code: "Zx",
+        variable_name: "zero_motion",
+        description: "No motion for subsequent character",
+    },
+    CapInfo {
+        terminfo_name: "csnm",
+        termcap_code: "Zy",
+        variable_name: "char_set_names",
+        description: "Produce #1'th item from list of character set names",
+    },
+    CapInfo {
+        terminfo_name: "kmous",
+        termcap_code: "Km",
+        variable_name: "key_mouse",
+        description: "Mouse event has occurred",
+    },
+    CapInfo {
+        terminfo_name: "minfo",
+        termcap_code: "Mi",
+        variable_name: "mouse_info",
+        description: "Mouse status information",
+    },
+    CapInfo {
+        terminfo_name: "reqmp",
+        termcap_code: "RQ",
+        variable_name: "req_mouse_pos",
+        description: "Request mouse position",
+    },
+    CapInfo {
+        terminfo_name: "getm",
+        termcap_code: "Gm",
+        variable_name: "get_mouse",
+        description: "Curses should get button events, parameter #1 not documented.",
+    },
+    CapInfo {
+        terminfo_name: "setaf",
+        termcap_code: "AF",
+        variable_name: "set_a_foreground",
+        description: "Set foreground color to #1, using ANSI escape",
+    },
+    CapInfo {
+        terminfo_name: "setab",
+        termcap_code: "AB",
+        variable_name: "set_a_background",
+        description: "Set background color to #1, using ANSI escape",
+    },
+    CapInfo {
+        terminfo_name: "pfxl",
+        termcap_code: "xl",
+        variable_name: "pkey_plab",
+        description: "Program function key #1 to type string #2 and show string #3",
+    },
+    CapInfo {
+        terminfo_name: "devt",
+        termcap_code: "dv",
+        variable_name: "device_type",
+        description: "Indicate language/codeset support",
+    },
+    CapInfo {
+        terminfo_name: "csin",
+        termcap_code: "ci",
+        variable_name: "code_set_init",
+        description: "Init sequence for multiple codesets",
+    },
+    CapInfo {
+        terminfo_name: "s0ds",
+        termcap_code: "s0",
+        variable_name: "set0_des_seq",
+        description: "Shift to codeset 0 (EUC set 0, ASCII)",
+    },
+    CapInfo {
+        terminfo_name: "s1ds",
+        termcap_code: "s1",
+        variable_name: "set1_des_seq",
+        description: "Shift to codeset 1",
+    },
+    CapInfo {
+        terminfo_name: "s2ds",
+        termcap_code: "s2",
+        variable_name: "set2_des_seq",
+        description: "Shift to codeset 2",
+    },
+    CapInfo {
+        terminfo_name: "s3ds",
+        termcap_code: "s3",
+        variable_name: "set3_des_seq",
+        description: "Shift to codeset 3",
+    },
+    CapInfo {
+        terminfo_name: "smglr",
+        termcap_code: "ML",
+        variable_name: "set_lr_margin",
+        description: "Set both left and right margins to #1, #2. (ML is not in BSD termcap).",
+    },
+    CapInfo {
+        terminfo_name: "smgtb",
+        termcap_code: "MT",
+        variable_name: "set_tb_margin",
+        description: "Sets both top and bottom margins to #1, #2",
+    },
+    CapInfo {
+        terminfo_name: "birep",
+        termcap_code: "Xy",
+        variable_name: "bit_image_repeat",
+        description: "Repeat bit image cell #1 #2 times",
+    },
+    CapInfo {
+        terminfo_name: "binel",
+        termcap_code: "Zz",
+        variable_name: "bit_image_newline",
+        description: "Move to next row of the bit image",
+    },
+    CapInfo {
+        terminfo_name: "bicr",
+        termcap_code: "Yv",
+        variable_name: "bit_image_carriage_return",
+        description: "Move to beginning of same row",
+    },
+    CapInfo {
+        terminfo_name: "colornm",
+        termcap_code: "Yw",
+        variable_name: "color_names",
+        description: "Give name for color #1",
+    },
+    CapInfo {
+        terminfo_name: "defbi",
+        termcap_code: "Yx",
+        variable_name: "define_bit_image_region",
+        description: "Define rectangular bit image region",
+    },
+    CapInfo {
+        terminfo_name: "endbi",
+        termcap_code: "Yy",
+        variable_name: "end_bit_image_region",
+        description: "End a bit-image region",
+    },
+    CapInfo {
+        terminfo_name: "setcolor",
+        termcap_code: "Yz",
+        variable_name: "set_color_band",
+        description: "Change to ribbon color #1",
+    },
+    CapInfo {
+        terminfo_name: "slines",
+        termcap_code: "YZ",
+        variable_name: "set_page_length",
+        description: "Set page length to #1 lines",
+    },
+    CapInfo {
+        terminfo_name: "dispc",
+        termcap_code: "S1",
+        variable_name: "display_pc_char",
+        description: "Display PC character #1",
+    },
+    CapInfo {
+        terminfo_name: "smpch",
+        termcap_code: "S2",
+        variable_name: "enter_pc_charset_mode",
+        description: "Enter PC character display mode",
+    },
+    CapInfo {
+        terminfo_name: "rmpch",
+        termcap_code: "S3",
+        variable_name: "exit_pc_charset_mode",
+        description: "Exit PC character display mode",
+    },
+    CapInfo {
+        terminfo_name: "smsc",
+        termcap_code: "S4",
+        variable_name: "enter_scancode_mode",
+        description: "Enter PC scancode mode",
+    },
+    CapInfo {
+        terminfo_name: "rmsc",
+        termcap_code: "S5",
+        variable_name: "exit_scancode_mode",
+        description: "Exit PC scancode mode",
+    },
+    CapInfo {
+        terminfo_name: "pctrm",
+        termcap_code: "S6",
+        variable_name: "pc_term_options",
+        description: "PC terminal options",
+    },
+    CapInfo {
+        terminfo_name: "scesc",
+        termcap_code: "S7",
+        variable_name: "scancode_escape",
+        description: "Escape for scancode emulation",
+    },
+    CapInfo {
+        terminfo_name: "scesa",
+        termcap_code: "S8",
+        variable_name: "alt_scancode_esc",
+        description: "Alternate escape for scancode emulation",
+    },
+    CapInfo {
+        terminfo_name: "ehhlm",
+        termcap_code: "Xh",
+        variable_name: "enter_horizontal_hl_mode",
+        description: "Enter horizontal highlight mode",
+    },
+    CapInfo {
+        terminfo_name: "elhlm",
+        termcap_code: "Xl",
+        variable_name: "enter_left_hl_mode",
+        description: "Enter left highlight mode",
+    },
+    CapInfo {
+        terminfo_name: "elohlm",
+        termcap_code: "Xo",
+        variable_name: "enter_low_hl_mode",
+        description: "Enter low highlight mode",
+    },
+    CapInfo {
+        terminfo_name: "erhlm",
+        termcap_code: "Xr",
+        variable_name: "enter_right_hl_mode",
+        description: "Enter right highlight mode",
+    },
+    CapInfo {
+        terminfo_name: "ethlm",
+        termcap_code: "Xt",
+        variable_name: "enter_top_hl_mode",
+        description: "Enter top highlight mode",
+    },
+    CapInfo {
+        terminfo_name: "evhlm",
+        termcap_code: "Xv",
+        variable_name: "enter_vertical_hl_mode",
+        description: "Enter vertical highlight mode",
+    },
+    CapInfo {
+        terminfo_name: "sgr1",
+        termcap_code: "sA",
+        variable_name: "set_a_attributes",
+        description: "Define second set of video attributes #1-#6",
+    },
+    CapInfo {
+        terminfo_name: "slength",
+        termcap_code: "YI",
+        variable_name: "set_pglen_inch",
+        description:
+            "Set page length to #1 hundredth of an inch (some implementations use sL for termcap).",
+    },
+    CapInfo {
+        terminfo_name: "OTi2",
+        termcap_code: "i2",
+        variable_name: "termcap_init2",
+        description: "secondary initialization string",
+    },
+    CapInfo {
+        terminfo_name: "OTrs",
+        termcap_code: "rs",
+        variable_name: "termcap_reset",
+        description: "terminal reset string",
+    },
+    CapInfo {
+        terminfo_name: "OTnl",
+        termcap_code: "nl",
+        variable_name: "linefeed_if_not_lf",
+        description: "use to move down",
+    },
+    CapInfo {
+        terminfo_name: "OTbc",
+        termcap_code: "bc",
+        variable_name: "backspace_if_not_bs",
+        description: "move left, if not ^H",
+    },
+    CapInfo {
+        terminfo_name: "OTko",
+        termcap_code: "ko",
+        variable_name: "other_non_function_keys",
+        description: "list of self-mapped keycaps",
+    },
+    CapInfo {
+        terminfo_name: "OTma",
+        termcap_code: "ma",
+        variable_name: "arrow_key_map",
+        description: "map motion-keys for vi version 2",
+    },
+    CapInfo {
+        terminfo_name: "OTG2",
+        termcap_code: "G2",
+        variable_name: "acs_ulcorner",
+        description: "single upper left",
+    },
+    CapInfo {
+        terminfo_name: "OTG3",
+        termcap_code: "G3",
+        variable_name: "acs_llcorner",
+        description: "single lower left",
+    },
+    CapInfo {
+        terminfo_name: "OTG1",
+        termcap_code: "G1",
+        variable_name: "acs_urcorner",
+        description: "single upper right",
+    },
+    CapInfo {
+        terminfo_name: "OTG4",
+        termcap_code: "G4",
+        variable_name: "acs_lrcorner",
+        description: "single lower right",
+    },
+    CapInfo {
+        terminfo_name: "OTGR",
+        termcap_code: "GR",
+        variable_name: "acs_ltee",
+        description: "tee pointing right",
+    },
+    CapInfo {
+        terminfo_name: "OTGL",
+        termcap_code: "GL",
+        variable_name: "acs_rtee",
+        description: "tee pointing left",
+    },
+    CapInfo {
+        terminfo_name: "OTGU",
+        termcap_code: "GU",
+        variable_name: "acs_btee",
+        description: "tee pointing up",
+    },
+    CapInfo {
+        terminfo_name: "OTGD",
+        termcap_code: "GD",
+        variable_name: "acs_ttee",
+        description: "tee pointing down",
+    },
+    CapInfo {
+        terminfo_name: "OTGH",
+        termcap_code: "GH",
+        variable_name: "acs_hline",
+        description: "single horizontal line",
+    },
+    CapInfo {
+        terminfo_name: "OTGV",
+        termcap_code: "GV",
+        variable_name: "acs_vline",
+        description: "single vertical line",
+    },
+    CapInfo {
+        terminfo_name: "OTGC",
+        termcap_code: "GC",
+        variable_name: "acs_plus",
+        description: "single intersection",
+    },
+    CapInfo {
+        terminfo_name: "meml",
+        termcap_code: "ml",
+        variable_name: "memory_lock",
+        description: "lock memory above cursor",
+    },
+    CapInfo {
+        terminfo_name: "memu",
+        termcap_code: "mu",
+        variable_name: "memory_unlock",
+        description: "unlock memory",
+    },
+    CapInfo {
+        terminfo_name: "box1",
+        termcap_code: "bx",
+        variable_name: "box_chars_1",
+        description: "box characters primary set",
+    },
+];
@@ -0,0 +1,135 @@
+//! Writing an entry's capabilities out as Rust constants, for embedding in a binary.
+//!
+//! A program that only ever runs on one kind of terminal, or wants a fallback for when there's no terminfo database,
+//! can expand the capabilities it needs once in a build script and include the result. Compile the entry's source
+//! with `tic` and check the compiled file in, then:
+//!
+//! ```no_run
+//! // build.rs
+//! use std::{env, fs, path::Path};
+//! use nixterm::terminfo::{codegen, TermInfo, TermInfoBuf};
+//! use nixterm::terminfo::{ClearScreen, CursorInvisible, ExitAttributeMode};
+//!
+//! let info: TermInfoBuf = TermInfo::parse(&fs::read("terminfo/x/xterm").unwrap()).unwrap().into();
+//! let mut out = String::new();
+//! codegen::emit_consts(&info, &[ClearScreen, ExitAttributeMode, CursorInvisible], &mut out).unwrap();
+//! fs::write(Path::new(&env::var("OUT_DIR").unwrap()).join("xterm.rs"), out).unwrap();
+//! ```
+//!
+//! and `include!(concat!(env!("OUT_DIR"), "/xterm.rs"));` in the program gives it `pub const CLEAR: &[u8]`,
+//! `SGR0` and `CIVIS`.
+use std::fmt::Write;
+use std::string::String;
+use terminfo::errors::*;
+use terminfo::padding::without_delays;
+use terminfo::signature::signature;
+use terminfo::{StringField, TermInfoBuf};
+
+/// Append a `pub const` for each of `caps` to `out`, named after its terminfo name in upper case and holding its
+/// expansion, like `pub const CLEAR: &[u8] = b"\x1b[H\x1b[2J";`.
+///
+/// Only capabilities that take no parameters can be expanded ahead of time, others fail with
+/// `ErrorKind::ArityMismatch`, and ones the entry doesn't have with `ErrorKind::MissingCapability`. Delays are left
+/// out, there's no one to wait for them when the constant is written.
+pub fn emit_consts(info: &TermInfoBuf, caps: &[StringField], out: &mut String) -> Result<()> {
+    let mut consts = String::new();
+    for &cap in caps {
+        if let Some(expected) = signature(cap).filter(|sig| sig.required > 0) {
+            return Err(ErrorKind::ArityMismatch {
+                cap,
+                expected,
+                got: 0,
+            }
+            .into());
+        }
+        let value = match info.format(cap, &[]) {
+            Some(value) => without_delays(&value?),
+            None => return Err(ErrorKind::MissingCapability(cap).into()),
+        };
+        let cap = cap.info();
+        // without terminfo(5)'s notes on padding, like "(P*)"
+        let description = match cap.description.rfind(" (P") {
+            Some(i) if cap.description.ends_with(')') => &cap.description[..i],
+            _ => cap.description,
+        };
+        let _ = writeln!(consts, "/// `{}`, {}.", cap.terminfo_name, description);
+        let _ = write!(
+            consts,
+            "pub const {}: &[u8] = b\"",
+            cap.terminfo_name.to_uppercase()
+        );
+        for &c in &value {
+            match c {
+                b'"' | b'\\' => {
+                    let _ = write!(consts, "\\{}", c as char);
+                }
+                0x20..=0x7e => consts.push(c as char),
+                _ => {
+                    let _ = write!(consts, "\\x{:02x}", c);
+                }
+            }
+        }
+        consts.push_str("\";\n");
+    }
+    out.push_str(&consts);
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use terminfo::codegen::*;
+    use terminfo::StringField::*;
+    use terminfo::TermInfo;
+
+    const XTERM_INFO: &'static [u8] = include_bytes!("../../test-data/xterm");
+    const VT100_INFO: &'static [u8] = include_bytes!("../../test-data/vt100");
+
+    /// What `emit_consts` writes for xterm, checked in so it can be compiled below.
+    const XTERM_CONSTS: &'static str = include_str!("../../test-data/xterm-consts.rs");
+
+    mod xterm {
+        include!("../../test-data/xterm-consts.rs");
+    }
+
+    fn xterm() -> TermInfoBuf {
+        TermInfo::parse(XTERM_INFO).unwrap().into()
+    }
+
+    #[test]
+    fn emit_consts() {
+        let mut out = String::new();
+        super::emit_consts(
+            &xterm(),
+            &[ClearScreen, ExitAttributeMode, CursorInvisible],
+            &mut out,
+        )
+        .unwrap();
+        assert_eq!(out, XTERM_CONSTS);
+
+        let info = xterm();
+        assert_eq!(xterm::CLEAR, info.string_bytes(ClearScreen).unwrap());
+        assert_eq!(xterm::SGR0, b"\x1b(B\x1b[m");
+        assert_eq!(xterm::CIVIS, b"\x1b[?25l");
+
+        // the delays are left out
+        let vt100: TermInfoBuf = TermInfo::parse(VT100_INFO).unwrap().into();
+        let mut out = String::new();
+        super::emit_consts(&vt100, &[ClearScreen], &mut out).unwrap();
+        assert!(!out.contains("$<"), "{}", out);
+    }
+
+    #[test]
+    fn errors() {
+        let mut out = String::new();
+        let e =
+            super::emit_consts(&xterm(), &[CursorInvisible, CursorAddress], &mut out).unwrap_err();
+        match *e.kind() {
+            ErrorKind::ArityMismatch { cap, got, .. } => assert_eq!((cap, got), (CursorAddress, 0)),
+            ref kind => panic!("{:?}", kind),
+        }
+        let e = super::emit_consts(&TermInfoBuf::new(), &[FlashScreen], &mut out).unwrap_err();
+        assert_eq!(*e.kind(), ErrorKind::MissingCapability(FlashScreen));
+        // nothing's written unless everything is
+        assert!(out.is_empty());
+    }
+}
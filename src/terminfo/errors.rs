@@ -1,127 +1,315 @@
-use failure;
+use std::boxed::Box;
+use std::error::Error as StdError;
+use std::string::String;
 use std::{fmt, result};
+use terminfo::fields::StringField;
+use terminfo::signature::{Param, Signature};
 
 pub type Result<T> = result::Result<T, Error>;
 
-/// TermInfo's failure type.
+/// TermInfo's error type.
 ///
-/// This is a thin wrapper around a `failure::Context`.
 /// To get what actually happened call `Error::kind` to get an `ErrorKind`,
-/// or `<Error as failure::Fail>::cause` to get the underlying error, if there is one (in the case of this library, there usually is).
+/// or `std::error::Error::source` to get the underlying error, if there is one (in the case of this library, there usually is).
 #[derive(Debug)]
 pub struct Error {
-    inner: failure::Context<ErrorKind>,
+    kind: ErrorKind,
+    source: Option<Box<dyn StdError + Send + Sync + 'static>>,
 }
 
 /// Outlines the various points where TermInfo routines may fail.
 ///
 /// ErrorKind will almost always be wrapped in an `Error`, and
-/// generally it will be won't make much sense without that error's cause.
-#[derive(Eq, PartialEq, Debug, Fail)]
+/// generally it will be won't make much sense without that error's source.
+#[derive(Eq, PartialEq, Debug, Clone)]
+#[non_exhaustive]
 pub enum ErrorKind {
-    #[fail(display = "could not find a valid path to the terminfo file.")]
     FailedToFindTermInfo,
-
-    #[fail(display = "failed to parse the terminfo file.")]
     FailedToParseFile,
-
-    #[fail(display = "this file is not a terminfo file (bad magic number)")]
     InvalidMagicNumber,
-
-    #[fail(display = "this file is not a terminfo file (too short)")]
     IncompleteTermInfo,
-
-    #[fail(display = "the string table has exceeded its maximum capacity for a 16bit file")]
     MaxStrTabSizeReached,
-
-    #[fail(display = "failed to read from index {}, there are only {} elements!", _0, _1)]
     OutOfRange(usize, usize),
-
-    #[fail(display = "the file is too short to fit a terminfo header")]
     IncompleteTermInfoHeader,
-
-    #[fail(display = "failed to read a string from a string table")]
     FailedToReadStringFromTable,
-
-    #[fail(
-        display = "The file is too short to fit any terminfo extended data, but too long to be only a standard terminfo file."
-    )]
     IncompleteExtendedTermInfo,
-
-    #[fail(
-        display = "The file is too short to fit any terminfo extended header, but too long to be only a standard terminfo file."
-    )]
     IncompleteExtendedHeader,
-
-    #[fail(
-        display = "maximum capability count exceeded, there can only be a maximum of 65535 capabilities in each array for 16bit files"
-    )]
     MaximumCapabilityCountExceeded,
-
-    #[fail(display = "invalid printf format specifier")]
     BadPrintfSpecifier,
-
-    #[fail(display = "invalid precision number in printf specifier")]
     BadPrecisionSpecified,
-
-    #[fail(display = "invalid digit in number")]
     InvalidDigit(u8),
-
-    #[fail(display = "failed to write a string argument")]
     FailedToWriteArgument,
-
-    #[fail(display = "invalid argument identifier")]
     InvalidArgumentIdentifier,
-
-    #[fail(display = "unexpected argument type, expected a {}, got a(n) {}", _0, _1)]
     UnexpectedArgumentType(&'static str, &'static str),
-
-    #[fail(display = "failed to write string literal")]
     FailedToWriteStringLiteral,
-
-    #[fail(display = "unexpected EOF")]
     UnexpectedEof,
-
-    #[fail(display = "invalid numeric literal")]
     InvalidNumber,
-
-    #[fail(display = "invalid character literal")]
     InvalidChar,
+    StackOverflow,
+    InvalidJumpTarget(usize),
+    ControlCharInArgument(char),
+    /// A standard capability was given too many or too few arguments, see `terminfo::check_arguments`.
+    ArityMismatch {
+        cap: StringField,
+        expected: Signature,
+        got: usize,
+    },
+    /// Argument `index` (counted from 0) of a standard capability was the wrong type.
+    ArgumentTypeMismatch {
+        cap: StringField,
+        index: usize,
+        expected: Param,
+    },
+    /// A `TermInfoBuilder` was built without a name, see `TermInfoBuilder::name`.
+    MissingName,
+    /// A `TermInfoBuilder` was given the same name (or alias) twice.
+    DuplicateName(String),
+    /// A name has a `|` or a NUL in it, or is empty.
+    InvalidName(String),
+    /// A terminal's name can't be looked up in a database: it's empty, has a path separator in it, or is `.` or `..`.
+    InvalidTerminalName(String),
+    /// A string capability, named by its terminfo name, has a NUL in it, which a compiled entry can't store.
+    NulInString(String),
+    /// The entry doesn't have a string capability that's needed, and there's no other way to get the same effect.
+    MissingCapability(StringField),
+    /// A string capability isn't valid UTF-8, from the byte at `valid_up_to` on. Entries written for 8-bit controls
+    /// (like 0x9b for CSI) usually aren't.
+    NotUtf8 { cap: StringField, valid_up_to: usize },
+    /// A capability's expansion isn't valid UTF-8, from the byte at `valid_up_to` on, see `lang::Executor::string`.
+    ExpansionNotUtf8 { valid_up_to: usize },
+    /// A capability string was longer than the parser's limit, see `lang::parser::Parser::max_len`.
+    ProgramTooLong { len: usize, max: usize },
+    /// A strict program pushed a parameter that wasn't given, see `lang::Program::strict`.
+    MissingArgument { index: usize },
+    /// A termcap `%` code with no terminfo equivalent, see `lang::from_termcap`.
+    UntranslatableTermcap(u8),
+}
+
+/// Wrap the error in a `Result` with an `ErrorKind`, keeping it as the new error's source.
+pub trait ResultExt<T> {
+    fn context(self, kind: ErrorKind) -> Result<T>;
 }
 
 impl Error {
+    /// Create an error of `kind`, which happened because of `source`.
+    pub fn with_source<E>(kind: ErrorKind, source: E) -> Error
+    where
+        E: StdError + Send + Sync + 'static,
+    {
+        Error {
+            kind,
+            source: Some(Box::new(source)),
+        }
+    }
+
     /// Get this error's specific kind.
     pub fn kind(&self) -> &ErrorKind {
-        self.inner.get_context()
+        &self.kind
     }
 }
 
-impl failure::Fail for Error {
-    fn cause(&self) -> Option<&failure::Fail> {
-        self.inner.cause()
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self.source {
+            Some(ref e) => Some(&**e),
+            None => None,
+        }
     }
+}
 
-    fn backtrace(&self) -> Option<&failure::Backtrace> {
-        self.inner.backtrace()
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.kind, f)
     }
 }
 
-impl fmt::Display for Error {
+impl StdError for ErrorKind {}
+
+impl fmt::Display for ErrorKind {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        fmt::Display::fmt(&self.inner, f)
+        match *self {
+            ErrorKind::FailedToFindTermInfo => write!(
+                f,
+                "could not find a terminfo file for this terminal, check that $TERM is set and that \
+                 $TERMINFO or $TERMINFO_DIRS point to a terminfo database"
+            ),
+            ErrorKind::FailedToParseFile => write!(f, "failed to parse the terminfo file"),
+            ErrorKind::InvalidMagicNumber => write!(f, "this file is not a terminfo file (bad magic number)"),
+            ErrorKind::IncompleteTermInfo => write!(f, "this file is not a terminfo file (too short)"),
+            ErrorKind::MaxStrTabSizeReached => write!(
+                f,
+                "the string table has exceeded its maximum capacity for a 16bit file"
+            ),
+            ErrorKind::OutOfRange(i, len) => write!(
+                f,
+                "failed to read from index {}, there are only {} elements",
+                i, len
+            ),
+            ErrorKind::IncompleteTermInfoHeader => write!(f, "the file is too short to fit a terminfo header"),
+            ErrorKind::FailedToReadStringFromTable => write!(f, "failed to read a string from a string table"),
+            ErrorKind::IncompleteExtendedTermInfo => write!(
+                f,
+                "the file is too short to fit any terminfo extended data, but too long to be only a standard \
+                 terminfo file"
+            ),
+            ErrorKind::IncompleteExtendedHeader => write!(
+                f,
+                "the file is too short to fit any terminfo extended header, but too long to be only a standard \
+                 terminfo file"
+            ),
+            ErrorKind::MaximumCapabilityCountExceeded => write!(
+                f,
+                "maximum capability count exceeded, there can only be a maximum of 65535 capabilities in each \
+                 array for 16bit files"
+            ),
+            ErrorKind::BadPrintfSpecifier => write!(
+                f,
+                "invalid printf format specifier, expected one of %d, %o, %x, %X, %s or %c"
+            ),
+            ErrorKind::BadPrecisionSpecified => write!(f, "invalid precision number in printf specifier"),
+            ErrorKind::InvalidDigit(c) => write!(f, "invalid digit {:?} in number", char::from(c)),
+            ErrorKind::FailedToWriteArgument => write!(f, "failed to write a string argument"),
+            ErrorKind::InvalidArgumentIdentifier => write!(
+                f,
+                "invalid argument identifier, expected %p1 through %p9"
+            ),
+            ErrorKind::UnexpectedArgumentType(expected, got) => write!(
+                f,
+                "unexpected argument type, expected a {}, got a(n) {}",
+                expected, got
+            ),
+            ErrorKind::FailedToWriteStringLiteral => write!(f, "failed to write string literal"),
+            ErrorKind::UnexpectedEof => write!(f, "unexpected end of capability string"),
+            ErrorKind::InvalidNumber => write!(f, "invalid numeric literal, expected %{{digits}}"),
+            ErrorKind::InvalidChar => write!(f, "invalid character literal, expected %'c'"),
+            ErrorKind::StackOverflow => write!(f, "the capability pushed too many values onto the stack"),
+            ErrorKind::InvalidJumpTarget(target) => write!(
+                f,
+                "a conditional jumps to op {}, past the end of the capability",
+                target
+            ),
+            ErrorKind::ControlCharInArgument(c) => write!(
+                f,
+                "a string argument contains the control character {:?}",
+                c
+            ),
+            ErrorKind::ArityMismatch { cap, expected, got } => write!(
+                f,
+                "{:?} takes {}, but was given {} argument(s)",
+                cap, expected, got
+            ),
+            ErrorKind::ArgumentTypeMismatch {
+                cap,
+                index,
+                expected,
+            } => write!(
+                f,
+                "argument %p{} of {:?} should be a(n) {}",
+                index + 1,
+                cap,
+                expected
+            ),
+            ErrorKind::MissingName => write!(f, "the entry doesn't have a name"),
+            ErrorKind::DuplicateName(ref name) => write!(f, "the entry is called {:?} more than once", name),
+            ErrorKind::InvalidTerminalName(ref name) => write!(
+                f,
+                "{:?} isn't a terminal that can be looked up, check that $TERM is set to a terminal's name",
+                name
+            ),
+            ErrorKind::InvalidName(ref name) => write!(
+                f,
+                "{:?} can't be a terminal's name, names can't be empty or have a '|' or a NUL in them",
+                name
+            ),
+            ErrorKind::NulInString(ref cap) => write!(f, "the string capability {} has a NUL in it", cap),
+            ErrorKind::MissingCapability(cap) => write!(f, "the terminal doesn't have {:?}", cap),
+            ErrorKind::NotUtf8 { cap, valid_up_to } => write!(
+                f,
+                "{:?} isn't valid UTF-8 (from byte {} on)",
+                cap, valid_up_to
+            ),
+            ErrorKind::ExpansionNotUtf8 { valid_up_to } => write!(
+                f,
+                "the expanded capability isn't valid UTF-8 (from byte {} on)",
+                valid_up_to
+            ),
+            ErrorKind::ProgramTooLong { len, max } => write!(
+                f,
+                "the capability is {} bytes long, longer than the limit of {}",
+                len, max
+            ),
+            ErrorKind::MissingArgument { index } => write!(f, "argument %p{} wasn't given", index + 1),
+            ErrorKind::UntranslatableTermcap(code) => write!(
+                f,
+                "the termcap code %{} has no terminfo equivalent",
+                char::from(code)
+            ),
+        }
     }
 }
 
 impl From<ErrorKind> for Error {
     fn from(kind: ErrorKind) -> Error {
-        Error {
-            inner: failure::Context::new(kind),
-        }
+        Error { kind, source: None }
+    }
+}
+
+impl<T, E> ResultExt<T> for result::Result<T, E>
+where
+    E: StdError + Send + Sync + 'static,
+{
+    fn context(self, kind: ErrorKind) -> Result<T> {
+        self.map_err(|e| Error::with_source(kind, e))
     }
 }
 
-impl From<failure::Context<ErrorKind>> for Error {
-    fn from(inner: failure::Context<ErrorKind>) -> Error {
-        Error { inner: inner }
+#[cfg(test)]
+mod test {
+    use std::error::Error as StdError;
+    use terminfo::strtab::StringTable;
+    use terminfo::*;
+
+    fn assert_send_sync<T: Send + Sync + 'static>() {}
+
+    #[test]
+    fn error_traits() {
+        assert_send_sync::<Error>();
+        assert_send_sync::<ErrorKind>();
+
+        let e: Box<dyn StdError + Send + Sync> =
+            Box::new(Error::from(ErrorKind::InvalidMagicNumber));
+        assert_eq!(
+            e.to_string(),
+            "this file is not a terminfo file (bad magic number)"
+        );
+        assert!(e.source().is_none());
+    }
+
+    #[test]
+    fn error_sources() {
+        let table = StringTable::from_slice(b"abc\0");
+        let e = table.get(10).unwrap_err();
+        assert_eq!(*e.kind(), ErrorKind::FailedToReadStringFromTable);
+        let source = e.source().unwrap();
+        assert_eq!(
+            source.downcast_ref::<ErrorKind>(),
+            Some(&ErrorKind::OutOfRange(10, 4))
+        );
+        assert_eq!(
+            source.to_string(),
+            "failed to read from index 10, there are only 4 elements"
+        );
+        assert!(source.source().is_none());
+
+        let e = "x"
+            .parse::<u8>()
+            .context(ErrorKind::InvalidNumber)
+            .unwrap_err();
+        assert_eq!(*e.kind(), ErrorKind::InvalidNumber);
+        assert!(e
+            .source()
+            .unwrap()
+            .downcast_ref::<::std::num::ParseIntError>()
+            .is_some());
     }
 }
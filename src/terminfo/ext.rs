@@ -0,0 +1,219 @@
+//! The well-known extended capabilities, which terminfo(5) doesn't define but modern terminals (and tmux) set.
+//!
+//! These are looked up by name like any extended capability, with `TermInfo::ext` and `TermInfoBuf::ext`, and
+//! expanded with `ext_format`. Others can still be looked up by their names, with `ext_boolean`, `ext_number` and
+//! `ext_string`.
+use std::vec::Vec;
+use terminfo::errors::*;
+use terminfo::lang::{Argument, Program};
+use terminfo::signature::{Param, Signature};
+
+/// A well-known extended capability, see the module documentation.
+///
+/// Each variant's documentation says what it means, and for strings what the parameters are.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum ExtCapability {
+    /// `Tc`, a boolean from tmux: the terminal takes 24-bit colors as `CSI 38;2;r;g;b m` and `CSI 48;2;r;g;b m`.
+    Tc,
+    /// `RGB`, ncurses' way of saying the terminal takes 24-bit colors. It's usually a boolean, but it can be the
+    /// number of bits for each of red, green and blue, or a string of the three numbers.
+    Rgb,
+    /// `Su`, a boolean from kitty and VTE: the terminal has styled and colored underlines, like `Smulx` and `Setulc`.
+    Su,
+    /// `Smulx`, the underline style, taking it as a number: 0 is none, then single, double, curly, dotted and dashed.
+    Smulx,
+    /// `Setulc`, the underline color, taking it as one number, `0xRRGGBB`.
+    Setulc,
+    /// `Sync`, a synchronized update, taking 1 to begin it and 2 to end it.
+    Sync,
+    /// `Ms`, setting the clipboard, taking which selection (like `c`) and the data in base64, as strings.
+    Ms,
+    /// `Cs`, the cursor's color, taking a color the terminal understands (like `#ff8000`) as a string.
+    Cs,
+    /// `Cr`, putting the cursor's color back.
+    Cr,
+    /// `Smol`, overlined text, which `Rmol` turns off again.
+    Smol,
+    /// `fullkbd`, a boolean from kitty: the terminal has kitty's keyboard protocol (`CSI > flags u`), which reports
+    /// every key and modifier without ambiguity.
+    Fullkbd,
+}
+
+/// The value of an extended capability.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ExtValue<'a> {
+    /// A boolean capability that's set.
+    Flag,
+    Number(u32),
+    /// A string, which `ext_format` expands.
+    String(&'a str),
+}
+
+/// Which type of value an extended capability is.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ExtKind {
+    Boolean,
+    String,
+}
+
+const INT: &[Param] = &[Param::Integer];
+const STR: &[Param] = &[Param::String];
+const STR2: &[Param] = &[Param::String, Param::String];
+
+impl ExtCapability {
+    /// Every well-known extended capability.
+    pub const ALL: [ExtCapability; 11] = [
+        ExtCapability::Tc,
+        ExtCapability::Rgb,
+        ExtCapability::Su,
+        ExtCapability::Smulx,
+        ExtCapability::Setulc,
+        ExtCapability::Sync,
+        ExtCapability::Ms,
+        ExtCapability::Cs,
+        ExtCapability::Cr,
+        ExtCapability::Smol,
+        ExtCapability::Fullkbd,
+    ];
+
+    /// The capability's name in terminfo entries.
+    pub fn name(self) -> &'static str {
+        match self {
+            ExtCapability::Tc => "Tc",
+            ExtCapability::Rgb => "RGB",
+            ExtCapability::Su => "Su",
+            ExtCapability::Smulx => "Smulx",
+            ExtCapability::Setulc => "Setulc",
+            ExtCapability::Sync => "Sync",
+            ExtCapability::Ms => "Ms",
+            ExtCapability::Cs => "Cs",
+            ExtCapability::Cr => "Cr",
+            ExtCapability::Smol => "Smol",
+            ExtCapability::Fullkbd => "fullkbd",
+        }
+    }
+
+    /// The capability called `name`, if it's one of these.
+    pub fn from_name(name: &str) -> Option<ExtCapability> {
+        ExtCapability::ALL.iter().cloned().find(|cap| cap.name() == name)
+    }
+
+    /// The type of value the capability usually has, `RGB` can be any of them.
+    pub fn kind(self) -> ExtKind {
+        match self {
+            ExtCapability::Tc | ExtCapability::Rgb | ExtCapability::Su | ExtCapability::Fullkbd => {
+                ExtKind::Boolean
+            }
+            _ => ExtKind::String,
+        }
+    }
+
+    /// The parameters a string capability takes, or `None` if it takes none (or isn't a string).
+    pub fn signature(self) -> Option<Signature> {
+        let params = match self {
+            ExtCapability::Smulx | ExtCapability::Setulc | ExtCapability::Sync => INT,
+            ExtCapability::Cs => STR,
+            ExtCapability::Ms => STR2,
+            _ => return None,
+        };
+        Some(Signature {
+            params,
+            required: params.len(),
+        })
+    }
+}
+
+/// Looking up extended capabilities by name, which is all `TermInfo` and `TermInfoBuf` need to share `ext`,
+/// `ext_format` and `has_truecolor`.
+pub(crate) trait Lookup {
+    fn boolean(&self, name: &str) -> bool;
+    fn number(&self, name: &str) -> Option<u32>;
+    fn string(&self, name: &str) -> Option<&str>;
+
+    fn value(&self, cap: ExtCapability) -> Option<ExtValue<'_>> {
+        let name = cap.name();
+        if self.boolean(name) {
+            return Some(ExtValue::Flag);
+        }
+        if let Some(n) = self.number(name) {
+            return Some(ExtValue::Number(n));
+        }
+        self.string(name).map(ExtValue::String)
+    }
+
+    fn format(&self, cap: ExtCapability, args: &[Argument]) -> Option<Result<Vec<u8>>> {
+        let src = self.string(cap.name())?;
+        let mut buf = Vec::new();
+        Some(
+            Program::compile(src.as_bytes())
+                .and_then(|program| program.exec_append(args, &mut buf))
+                .map(|_| buf),
+        )
+    }
+
+    fn truecolor(&self) -> bool {
+        self.value(ExtCapability::Tc).is_some() || self.value(ExtCapability::Rgb).is_some()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use terminfo::lang::Argument;
+    use terminfo::*;
+
+    const TMUX_INFO: &'static [u8] = include_bytes!("../../test-data/tmux-256color");
+    const KITTYISH_INFO: &'static [u8] = include_bytes!("../../test-data/kittyish");
+
+    #[test]
+    fn names() {
+        for &cap in &ExtCapability::ALL {
+            assert_eq!(ExtCapability::from_name(cap.name()), Some(cap));
+        }
+        assert_eq!(ExtCapability::from_name("Ss"), None);
+        assert_eq!(ExtCapability::Rgb.name(), "RGB");
+        assert_eq!(ExtCapability::Setulc.signature().unwrap().to_string(), "(integer)");
+        assert_eq!(ExtCapability::Cr.signature(), None);
+    }
+
+    #[test]
+    fn tmux() {
+        let info = TermInfo::parse(TMUX_INFO).unwrap();
+        assert_eq!(
+            info.ext(ExtCapability::Ms),
+            Some(ExtValue::String("\x1b]52;%p1%s;%p2%s\x07"))
+        );
+        assert_eq!(info.ext(ExtCapability::Tc), None);
+        assert_eq!(info.ext(ExtCapability::Setulc), None);
+        assert!(!info.has_truecolor());
+
+        let ms = info.ext_format(ExtCapability::Ms, &[Argument::from("c"), Argument::from("aGk=")]);
+        assert_eq!(ms.unwrap().unwrap(), b"\x1b]52;c;aGk=\x07");
+        let smulx = info.ext_format(ExtCapability::Smulx, &[Argument::Integer(3)]);
+        assert_eq!(smulx.unwrap().unwrap(), b"\x1b[4:3m");
+        assert!(info.ext_format(ExtCapability::Sync, &[Argument::Integer(1)]).is_none());
+    }
+
+    #[test]
+    fn kittyish() {
+        let info: TermInfoBuf = TermInfo::parse(KITTYISH_INFO).unwrap().into();
+        assert_eq!(info.ext(ExtCapability::Tc), Some(ExtValue::Flag));
+        assert_eq!(info.ext(ExtCapability::Fullkbd), Some(ExtValue::Flag));
+        assert_eq!(info.ext(ExtCapability::Rgb), Some(ExtValue::Number(8)));
+        assert_eq!(info.ext(ExtCapability::Smol), Some(ExtValue::String("\x1b[53m")));
+        assert!(info.has_truecolor());
+
+        let setulc = info.ext_format(ExtCapability::Setulc, &[Argument::Integer(0xff8000)]);
+        assert_eq!(setulc.unwrap().unwrap(), b"\x1b[58:2:255:128:0m");
+        let sync = |n| {
+            info.ext_format(ExtCapability::Sync, &[Argument::Integer(n)])
+                .unwrap()
+                .unwrap()
+        };
+        assert_eq!(sync(1), b"\x1bP=1s\x1b\\");
+        assert_eq!(sync(2), b"\x1bP=2s\x1b\\");
+        let cs = info.ext_format(ExtCapability::Cs, &[Argument::from("#ff8000")]);
+        assert_eq!(cs.unwrap().unwrap(), b"\x1b]12;#ff8000\x1b\\");
+        // the raw lookup still finds the rest
+        assert_eq!(info.ext_string("Rmol"), Some("\x1b[55m"));
+    }
+}
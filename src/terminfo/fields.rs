@@ -11,7 +11,7 @@ pub const PREDEFINED_NUMERICS_COUNT: usize = 39;
 pub const PREDEFINED_STRINGS_COUNT: usize = 414;
 
 #[repr(usize)]
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum BooleanField {
     AutoLeftMargin,
     AutoRightMargin,
@@ -60,7 +60,7 @@ pub enum BooleanField {
 }
 
 #[repr(usize)]
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum NumericField {
     Columns,
     InitTabs,
@@ -104,7 +104,7 @@ pub enum NumericField {
 }
 
 #[repr(usize)]
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum StringField {
     BackTab,
     Bell,
@@ -0,0 +1,265 @@
+#[cfg(all(feature = "mmap", unix))]
+use nix::libc::c_void;
+#[cfg(all(feature = "mmap", unix))]
+use nix::sys::mman;
+use std::fs::File;
+use std::io::Read;
+use std::ops::Deref;
+#[cfg(all(feature = "mmap", unix))]
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+use std::sync::Arc;
+#[cfg(all(feature = "mmap", unix))]
+use std::{ptr, slice};
+use terminfo::errors::*;
+use terminfo::terminfo::TermInfo;
+use terminfo::warnings::Warnings;
+
+/// The contents of a terminfo file, for a `TermInfo` to borrow from.
+///
+/// With the `mmap` feature the file is mapped into memory rather than read into a buffer, so parsing an entry
+/// doesn't copy it at all. Files that can't be mapped (empty files, or ones on filesystems that don't support it)
+/// are read instead, either way the bytes are the same. Off Unix they're always read.
+#[derive(Debug)]
+pub struct TermInfoFile {
+    data: Data,
+}
+
+#[derive(Debug)]
+enum Data {
+    Read(Vec<u8>),
+    #[cfg(all(feature = "mmap", unix))]
+    Mapped(Mmap),
+}
+
+/// A read-only, private mapping of a whole file.
+///
+/// If another process truncates the file while it's mapped, reading past the new end raises `SIGBUS`, terminfo
+/// databases are only replaced when they're reinstalled, which is an accepted risk (`ncurses` doesn't guard
+/// against it either).
+#[cfg(all(feature = "mmap", unix))]
+#[derive(Debug)]
+struct Mmap {
+    ptr: *mut c_void,
+    len: usize,
+}
+
+impl TermInfoFile {
+    /// Open a terminfo file, mapping it if the `mmap` feature is enabled (on Unix), and reading it otherwise.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<TermInfoFile> {
+        let mut file = File::open(path).context(ErrorKind::FailedToParseFile)?;
+
+        #[cfg(all(feature = "mmap", unix))]
+        {
+            if let Some(map) = Mmap::new(&file) {
+                return Ok(TermInfoFile {
+                    data: Data::Mapped(map),
+                });
+            }
+        }
+
+        TermInfoFile::read_from(&mut file)
+    }
+
+    /// Read a terminfo file into a buffer, without trying to map it.
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<TermInfoFile> {
+        TermInfoFile::read_from(&mut File::open(path).context(ErrorKind::FailedToParseFile)?)
+    }
+
+    fn read_from(file: &mut File) -> Result<TermInfoFile> {
+        let mut data = Vec::new();
+        file.read_to_end(&mut data)
+            .context(ErrorKind::FailedToParseFile)?;
+        Ok(TermInfoFile {
+            data: Data::Read(data),
+        })
+    }
+
+    /// True if the file is mapped into memory, rather than read into a buffer.
+    pub fn is_mapped(&self) -> bool {
+        match self.data {
+            Data::Read(_) => false,
+            #[cfg(all(feature = "mmap", unix))]
+            Data::Mapped(_) => true,
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        match self.data {
+            Data::Read(ref data) => data,
+            #[cfg(all(feature = "mmap", unix))]
+            Data::Mapped(ref map) => map.as_bytes(),
+        }
+    }
+
+    /// Parse the file, the `TermInfo` borrows its strings straight from the file's contents.
+    pub fn parse(&self) -> Result<TermInfo<'_>> {
+        TermInfo::parse(self.as_bytes())
+    }
+
+    /// Parse the file, and report the defects that were worked around while parsing it.
+    pub fn parse_with_warnings(&self) -> Result<(TermInfo<'_>, Warnings)> {
+        TermInfo::parse_with_warnings(self.as_bytes())
+    }
+}
+
+/// A `TermInfo` that borrows from the file it was parsed from, and keeps that file (mapped, if it could be) around
+/// for as long as it's needed. Clones share the file.
+///
+/// `from_path` and `from_env` copy the entry into a `TermInfoBuf`, `map_path` and `map_env` return one of these
+/// instead.
+#[derive(Debug, Clone)]
+pub struct MappedTermInfo {
+    // this borrows from `file`, not really for `'static`, and is dropped before it
+    info: TermInfo<'static>,
+    file: Option<Arc<TermInfoFile>>,
+}
+
+impl MappedTermInfo {
+    /// Open and parse a terminfo file, see `TermInfoFile::open`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<MappedTermInfo> {
+        let file = Arc::new(TermInfoFile::open(path)?);
+        // the bytes don't move while `file` is alive, they're either mapped or in a `Vec` that's never changed
+        let bytes = unsafe { &*(file.as_bytes() as *const [u8]) };
+        Ok(MappedTermInfo {
+            info: TermInfo::parse(bytes).context(ErrorKind::FailedToParseFile)?,
+            file: Some(file),
+        })
+    }
+
+    /// Parse an entry that's built into the program, like `BUILTIN_XTERM`.
+    pub(crate) fn from_static(bytes: &'static [u8]) -> Result<MappedTermInfo> {
+        Ok(MappedTermInfo {
+            info: TermInfo::parse(bytes)?,
+            file: None,
+        })
+    }
+
+    /// The entry, borrowing from the file.
+    pub fn info(&self) -> &TermInfo<'_> {
+        &self.info
+    }
+
+    /// True if the file is mapped into memory, see `TermInfoFile::is_mapped`.
+    pub fn is_mapped(&self) -> bool {
+        self.file.as_ref().is_some_and(|file| file.is_mapped())
+    }
+}
+
+impl Deref for TermInfoFile {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+#[cfg(all(feature = "mmap", unix))]
+impl Mmap {
+    /// Map all of `file`, or `None` if it can't be mapped.
+    fn new(file: &File) -> Option<Mmap> {
+        let len = file.metadata().ok()?.len() as usize;
+        // mapping nothing is an error
+        if len == 0 {
+            return None;
+        }
+
+        let ptr = unsafe {
+            mman::mmap(
+                ptr::null_mut(),
+                len,
+                mman::ProtFlags::PROT_READ,
+                mman::MapFlags::MAP_PRIVATE,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        ptr.ok().map(|ptr| Mmap { ptr, len })
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.ptr as *const u8, self.len) }
+    }
+}
+
+#[cfg(all(feature = "mmap", unix))]
+impl Drop for Mmap {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = mman::munmap(self.ptr, self.len);
+        }
+    }
+}
+
+// The mapping is read-only, and only ever unmapped by its owner.
+#[cfg(all(feature = "mmap", unix))]
+unsafe impl Send for Mmap {}
+#[cfg(all(feature = "mmap", unix))]
+unsafe impl Sync for Mmap {}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+    use std::fs::{self, File};
+    use terminfo::*;
+
+    #[test]
+    fn mapped_and_read() {
+        for name in &["rxvt", "xterm", "tmux", "linux-16color", "messy"] {
+            let path = format!("{}/test-data/{}", env!("CARGO_MANIFEST_DIR"), name);
+            let opened = TermInfoFile::open(&path).unwrap();
+            let read = TermInfoFile::read(&path).unwrap();
+
+            assert_eq!(opened.is_mapped(), cfg!(all(feature = "mmap", unix)));
+            assert!(!read.is_mapped());
+            assert_eq!(opened.as_bytes(), read.as_bytes());
+
+            let (opened_info, opened_warnings) = opened.parse_with_warnings().unwrap();
+            let (read_info, read_warnings) = read.parse_with_warnings().unwrap();
+            assert_eq!(format!("{:?}", opened_info), format!("{:?}", read_info));
+            assert_eq!(opened_warnings, read_warnings);
+            assert_eq!(
+                opened_info.string(CursorAddress),
+                read_info.string(CursorAddress)
+            );
+        }
+    }
+
+    #[test]
+    fn mapped_info() {
+        let path = format!("{}/test-data/xterm", env!("CARGO_MANIFEST_DIR"));
+        let mapped = MappedTermInfo::open(&path).unwrap();
+        assert_eq!(mapped.is_mapped(), cfg!(all(feature = "mmap", unix)));
+
+        let read = TermInfoFile::read(&path).unwrap();
+        let read = read.parse().unwrap();
+        assert_eq!(format!("{:?}", mapped.info()), format!("{:?}", read));
+
+        // a clone keeps the file alive after the original's gone
+        let clone = mapped.clone();
+        drop(mapped);
+        assert_eq!(
+            clone.info().string(CursorAddress),
+            read.string(CursorAddress)
+        );
+        assert_eq!(
+            format!("{:?}", TermInfoBuf::from(clone.info())),
+            format!("{:?}", from_path(&path).unwrap())
+        );
+    }
+
+    #[test]
+    fn empty_file() {
+        let path = env::temp_dir().join(format!("nixterm-empty-terminfo-{}", ::std::process::id()));
+        File::create(&path).unwrap();
+        let file = TermInfoFile::open(&path);
+        fs::remove_file(&path).unwrap();
+
+        let file = file.unwrap();
+        assert!(!file.is_mapped());
+        assert_eq!(
+            *file.parse().unwrap_err().kind(),
+            ErrorKind::IncompleteTermInfoHeader
+        );
+    }
+}
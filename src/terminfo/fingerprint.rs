@@ -0,0 +1,345 @@
+//! Identifying an entry by its contents, rather than the file it came from.
+use std::mem;
+use std::vec::Vec;
+use terminfo::fields::*;
+use terminfo::TermInfo;
+
+/// The first 32 bits of the fractional parts of the cube roots of the first 64 primes.
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// SHA-256, as in FIPS 180-4.
+struct Sha256 {
+    state: [u32; 8],
+    block: [u8; 64],
+    /// How much of `block` is filled.
+    used: usize,
+    /// How many bytes have been hashed, in total.
+    len: u64,
+}
+
+impl Sha256 {
+    fn new() -> Sha256 {
+        Sha256 {
+            state: [
+                0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+                0x5be0cd19,
+            ],
+            block: [0; 64],
+            used: 0,
+            len: 0,
+        }
+    }
+
+    fn update(&mut self, mut data: &[u8]) {
+        self.len += data.len() as u64;
+        while !data.is_empty() {
+            let n = (64 - self.used).min(data.len());
+            self.block[self.used..self.used + n].copy_from_slice(&data[..n]);
+            self.used += n;
+            data = &data[n..];
+            if self.used == 64 {
+                self.compress();
+                self.used = 0;
+            }
+        }
+    }
+
+    fn update_u32(&mut self, n: u32) {
+        self.update(&[n as u8, (n >> 8) as u8, (n >> 16) as u8, (n >> 24) as u8]);
+    }
+
+    fn compress(&mut self) {
+        let mut w = [0u32; 64];
+        for (w, b) in w.iter_mut().zip(self.block.chunks(4)) {
+            *w = (b[0] as u32) << 24 | (b[1] as u32) << 16 | (b[2] as u32) << 8 | b[3] as u32;
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+
+        let mut s = self.state;
+        for i in 0..64 {
+            let s1 = s[4].rotate_right(6) ^ s[4].rotate_right(11) ^ s[4].rotate_right(25);
+            let ch = (s[4] & s[5]) ^ (!s[4] & s[6]);
+            let t1 = s[7]
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(K[i])
+                .wrapping_add(w[i]);
+            let s0 = s[0].rotate_right(2) ^ s[0].rotate_right(13) ^ s[0].rotate_right(22);
+            let maj = (s[0] & s[1]) ^ (s[0] & s[2]) ^ (s[1] & s[2]);
+            let t2 = s0.wrapping_add(maj);
+            s = [
+                t1.wrapping_add(t2),
+                s[0],
+                s[1],
+                s[2],
+                s[3].wrapping_add(t1),
+                s[4],
+                s[5],
+                s[6],
+            ];
+        }
+        for (state, s) in self.state.iter_mut().zip(&s) {
+            *state = state.wrapping_add(*s);
+        }
+    }
+
+    fn finish(mut self) -> [u8; 32] {
+        let bits = self.len * 8;
+        self.update(&[0x80]);
+        while self.used != 56 {
+            self.update(&[0]);
+        }
+        for i in (0..8).rev() {
+            self.update(&[(bits >> (i * 8)) as u8]);
+        }
+
+        let mut digest = [0; 32];
+        for (i, word) in self.state.iter().enumerate() {
+            for j in 0..4 {
+                digest[i * 4 + j] = (word >> (24 - j * 8)) as u8;
+            }
+        }
+        digest
+    }
+}
+
+/// A capability's value.
+#[derive(Copy, Clone)]
+enum Value<'a> {
+    Boolean,
+    Number(u32),
+    String(&'a [u8]),
+}
+
+impl<'a> TermInfo<'a> {
+    /// A 64-bit hash of the entry's names and capabilities, for keying caches by the entry they came from.
+    ///
+    /// It's the first 8 bytes of `TermInfo::digest`, and just as stable, but with far fewer bits two different
+    /// entries are (slightly) more likely to collide.
+    pub fn fingerprint(&self) -> u64 {
+        let digest = self.digest();
+        (0..8).fold(0, |n, i| n | (digest[i] as u64) << (i * 8))
+    }
+
+    /// The SHA-256 of the entry's names and capabilities.
+    ///
+    /// Only the entry is hashed, not the file it came from, so the same entry has the same digest whichever order its
+    /// extended capabilities were written in, and whether it was compiled with 16-bit (0432) or 32-bit (0542) numbers.
+    ///
+    /// The digest is stable: an entry has the same digest in every version of this crate, so it can be kept on disk.
+    /// What's hashed is this encoding of the entry, with every length and number a little endian `u32`:
+    ///
+    /// - the bytes `nixterm terminfo 1\n`
+    /// - how many names the entry has, then each name's length and bytes
+    /// - every capability that's set, sorted by kind (booleans, then numbers, then strings) then by name, as a byte
+    ///   for its kind (0, 1 or 2), the length of its name, its terminfo name (`cup`, not `cursor_address`), then its
+    ///   value: nothing for a boolean, the number, or the string's length and bytes
+    ///
+    /// If that encoding ever has to change it will be under a new method, `digest` won't change.
+    pub fn digest(&self) -> [u8; 32] {
+        let mut caps = Vec::new();
+        for i in 0..PREDEFINED_BOOLEANS_COUNT {
+            // the fields are `repr(usize)`, with a variant for every index below their count
+            let field = unsafe { mem::transmute::<usize, BooleanField>(i) };
+            if self.boolean(field) {
+                caps.push((Value::Boolean, field.info().terminfo_name));
+            }
+        }
+        for i in 0..PREDEFINED_NUMERICS_COUNT {
+            let field = unsafe { mem::transmute::<usize, NumericField>(i) };
+            if let Some(n) = self.number(field) {
+                caps.push((Value::Number(n), field.info().terminfo_name));
+            }
+        }
+        for i in 0..PREDEFINED_STRINGS_COUNT {
+            let field = unsafe { mem::transmute::<usize, StringField>(i) };
+            if let Some(s) = self.string_bytes(field) {
+                caps.push((Value::String(s), field.info().terminfo_name));
+            }
+        }
+        caps.extend(
+            self.ext_booleans()
+                .into_iter()
+                .map(|name| (Value::Boolean, name)),
+        );
+        caps.extend(
+            self.ext_numbers()
+                .into_iter()
+                .map(|(name, n)| (Value::Number(n), name)),
+        );
+        caps.extend(
+            self.ext_strings()
+                .into_iter()
+                .map(|(name, s)| (Value::String(s), name)),
+        );
+        caps.sort_by(|a, b| kind(a.0).cmp(&kind(b.0)).then(a.1.cmp(b.1)));
+
+        let mut sha = Sha256::new();
+        sha.update(b"nixterm terminfo 1\n");
+        let names: Vec<&str> = self.names().collect();
+        sha.update_u32(names.len() as u32);
+        for name in names {
+            sha.update_u32(name.len() as u32);
+            sha.update(name.as_bytes());
+        }
+        for (value, name) in caps {
+            sha.update(&[kind(value)]);
+            sha.update_u32(name.len() as u32);
+            sha.update(name.as_bytes());
+            match value {
+                Value::Boolean => (),
+                Value::Number(n) => sha.update_u32(n),
+                Value::String(s) => {
+                    sha.update_u32(s.len() as u32);
+                    sha.update(s);
+                }
+            }
+        }
+        sha.finish()
+    }
+}
+
+fn kind(value: Value) -> u8 {
+    match value {
+        Value::Boolean => 0,
+        Value::Number(_) => 1,
+        Value::String(_) => 2,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use terminfo::fingerprint::*;
+    use terminfo::{ClearScreen, MaxColors};
+    use util::read_le_u16;
+
+    const RXVT_INFO: &'static [u8] = include_bytes!("../../test-data/rxvt");
+    const XTERM_INFO: &'static [u8] = include_bytes!("../../test-data/xterm");
+
+    fn hex(digest: [u8; 32]) -> String {
+        digest.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn sha256() {
+        let hash = |data: &[u8]| {
+            let mut sha = Sha256::new();
+            sha.update(data);
+            hex(sha.finish())
+        };
+        assert_eq!(
+            hash(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            hash(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            hash(&[b'a'; 1000]),
+            "41edece42d63e8d9bf515a9ba6932e1c20cbc9f5a5d134645adb5db1b9737ea3"
+        );
+    }
+
+    /// Recompile a file with 16-bit numbers with 32-bit ones instead, and its extended strings in reverse order.
+    fn recompile(legacy: &[u8]) -> Vec<u8> {
+        let header = |bytes: &[u8], i| read_le_u16(bytes, i) as usize;
+        let widen = |out: &mut Vec<u8>, numbers: &[u8]| {
+            for n in numbers.chunks(2) {
+                let n = (n[0] as u16 | (n[1] as u16) << 8) as i16 as i32;
+                out.extend((0..4).map(|i| (n >> (i * 8)) as u8));
+            }
+        };
+        let reverse = |out: &mut Vec<u8>, offsets: &[u8]| {
+            for offset in offsets.chunks(2).rev() {
+                out.extend_from_slice(offset);
+            }
+        };
+
+        let (names, bools, numbers, strings, strtab) = (
+            header(legacy, 1),
+            header(legacy, 2),
+            header(legacy, 3),
+            header(legacy, 4),
+            header(legacy, 5),
+        );
+        let mut out = vec![0x1e, 0x02];
+        out.extend_from_slice(&legacy[2..12]);
+        let mut at = 12 + names + bools + (names + bools) % 2;
+        out.extend_from_slice(&legacy[12..at]);
+        widen(&mut out, &legacy[at..at + numbers * 2]);
+        at += numbers * 2;
+        let end = at + strings * 2 + strtab + strtab % 2;
+        out.extend_from_slice(&legacy[at..end]);
+
+        let ext = &legacy[end..];
+        let (bools, numbers, strings) = (header(ext, 0), header(ext, 1), header(ext, 2));
+        out.extend_from_slice(&ext[..10]);
+        let mut at = 10 + bools + bools % 2;
+        out.extend_from_slice(&ext[10..at]);
+        widen(&mut out, &ext[at..at + numbers * 2]);
+        at += numbers * 2;
+        reverse(&mut out, &ext[at..at + strings * 2]);
+        at += strings * 2;
+        let string_names = at + (bools + numbers) * 2;
+        out.extend_from_slice(&ext[at..string_names]);
+        reverse(&mut out, &ext[string_names..string_names + strings * 2]);
+        out.extend_from_slice(&ext[string_names + strings * 2..]);
+        out
+    }
+
+    #[test]
+    fn layout_independent() {
+        let recompiled = recompile(XTERM_INFO);
+        assert_ne!(&recompiled[..], XTERM_INFO);
+        let legacy = TermInfo::parse(XTERM_INFO).unwrap();
+        let long = TermInfo::parse(&recompiled).unwrap();
+        assert_eq!(long.number(MaxColors), Some(8));
+        assert_eq!(long.ext_string("kUP7"), Some("\x1b[1;7A"));
+        assert_ne!(long.ext_strings(), legacy.ext_strings());
+
+        assert_eq!(long.digest(), legacy.digest());
+        assert_eq!(long.fingerprint(), legacy.fingerprint());
+    }
+
+    #[test]
+    fn changes() {
+        let xterm = TermInfo::parse(XTERM_INFO).unwrap();
+        let rxvt = TermInfo::parse(RXVT_INFO).unwrap();
+        assert_ne!(xterm.digest(), rxvt.digest());
+        assert_ne!(xterm.fingerprint(), rxvt.fingerprint());
+        assert_eq!(
+            xterm.digest(),
+            TermInfo::parse(XTERM_INFO).unwrap().digest()
+        );
+
+        // clear the screen with `\E[H\E[2K` instead
+        let clear = b"\x1b[H\x1b[2J";
+        let at = XTERM_INFO
+            .windows(clear.len())
+            .position(|w| w == clear)
+            .unwrap();
+        let mut changed = XTERM_INFO.to_vec();
+        changed[at + clear.len() - 1] = b'K';
+        let changed = TermInfo::parse(&changed).unwrap();
+        assert_eq!(changed.string(ClearScreen), Some("\x1b[H\x1b[2K"));
+        assert_ne!(changed.digest(), xterm.digest());
+        assert_ne!(changed.fingerprint(), xterm.fingerprint());
+    }
+}
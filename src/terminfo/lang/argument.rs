@@ -1,8 +1,31 @@
-#[derive(Debug, Clone, PartialEq, Eq)]
+use std::fmt;
+use std::string::String;
+use terminfo::errors::*;
+use terminfo::lang::escape;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Argument {
     Integer(i64),
     String(String),
     Char(u8),
+    /// A parameter that wasn't given, which is what `%p` pushes for one past the end of the arguments.
+    ///
+    /// Like in ncurses it's 0 to arithmetic and comparisons, an empty string to `%s` and `%l`, and false to `%t`, unless
+    /// the program is strict (see `Program::strict`), then pushing it fails with `ErrorKind::MissingArgument`.
+    Missing,
+}
+
+/// Shows numbers as they are, characters in single quotes and strings in double quotes, with control characters
+/// escaped the way terminfo sources write them.
+impl fmt::Display for Argument {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Argument::Integer(x) => write!(f, "{}", x),
+            Argument::String(ref s) => write!(f, "\"{}\"", escape(s.as_bytes())),
+            Argument::Char(c) => write!(f, "'{}'", escape(&[c])),
+            Argument::Missing => write!(f, "missing"),
+        }
+    }
 }
 
 impl From<String> for Argument {
@@ -70,3 +93,37 @@ impl From<bool> for Argument {
         Argument::Integer(if b { 1 } else { 0 })
     }
 }
+
+/// What an `Executor` does with control characters in string arguments.
+///
+/// A string printed with `%s` usually ends up inside an escape sequence, like the window title in `tsl` or an OSC
+/// string. A stray ESC, BEL or NUL in it ends that sequence early, and the rest of the string is then run as
+/// commands of its own, so strings from somewhere untrusted shouldn't be passed through as they are. The control
+/// characters are the C0 ones (below 0x20), DEL, and the C1 ones (U+0080 to U+009F).
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub enum ControlChars {
+    /// Print them as they are, like ncurses does. This is the default.
+    #[default]
+    Keep,
+    /// Leave them out. This happens before anything else, so `%l` counts what's printed.
+    Strip,
+    /// Fail with `ErrorKind::ControlCharInArgument`.
+    Reject,
+}
+
+impl ControlChars {
+    /// Do what the policy says to `s`.
+    pub fn apply(self, s: &mut String) -> Result<()> {
+        match self {
+            ControlChars::Keep => Ok(()),
+            ControlChars::Strip => {
+                s.retain(|c| !c.is_control());
+                Ok(())
+            }
+            ControlChars::Reject => match s.chars().find(|c| c.is_control()) {
+                Some(c) => Err(ErrorKind::ControlCharInArgument(c).into()),
+                None => Ok(()),
+            },
+        }
+    }
+}
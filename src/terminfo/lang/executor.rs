@@ -1,18 +1,32 @@
-use failure::ResultExt;
 use std::collections::VecDeque;
+use std::convert::TryFrom;
 use std::io;
+use std::str::FromStr;
+use std::string::String;
+use std::vec::Vec;
 use terminfo::errors::*;
 use terminfo::lang::parser::{Op, Parser};
-use terminfo::lang::Argument;
+use terminfo::lang::printf::NULL;
+use terminfo::lang::{from_termcap, to_seven_bit, Argument, ControlChars};
+
+/// How many values a `Program` can have on its stack at once, the same limit as ncurses.
+const STACK_SIZE: usize = 20;
+
+/// Every parameter's value for `Program::typical_cost`.
+const TYPICAL_ARGUMENT: Argument = Argument::Integer(10);
+
 pub struct Executor<'a> {
     src: &'a [u8],
     env: ExecutionEnvironment,
     argc: usize,
+    controls: ControlChars,
+    seven_bit: bool,
 }
 
 pub struct ExecutionEnvironment {
     stack: VecDeque<Argument>,
     arguments: [Option<Argument>; 9],
+    strict: bool,
 }
 
 impl<'a> Executor<'a> {
@@ -21,6 +35,8 @@ impl<'a> Executor<'a> {
             env: ExecutionEnvironment::new(),
             src: src,
             argc: 0,
+            controls: ControlChars::default(),
+            seven_bit: false,
         }
     }
 
@@ -43,18 +59,59 @@ impl<'a> Executor<'a> {
         self
     }
 
+    /// Choose what happens to control characters in string arguments, they're kept by default.
+    #[inline]
+    pub fn controls(mut self, controls: ControlChars) -> Executor<'a> {
+        self.controls = controls;
+        self
+    }
+
+    /// Write the 8-bit C1 controls in the output as their 7-bit equivalents, see `lang::to_seven_bit`. This is off
+    /// by default.
+    #[inline]
+    pub fn seven_bit(mut self, v: bool) -> Executor<'a> {
+        self.seven_bit = v;
+        self
+    }
+
+    /// Fail with `ErrorKind::MissingArgument` when the capability uses an argument that wasn't set, instead of
+    /// treating it as 0 or an empty string, see `Program::strict`. This is off by default.
+    #[inline]
+    pub fn strict(mut self, v: bool) -> Executor<'a> {
+        self.env.strict = v;
+        self
+    }
+
+    /// Expand the capability like `vec`, failing with `ErrorKind::ExpansionNotUtf8` if it isn't UTF-8, like one with
+    /// 8-bit controls.
     pub fn string(&mut self) -> Result<String> {
-        Ok(String::from_utf8(self.vec()?).unwrap())
+        String::from_utf8(self.vec()?).map_err(|e| {
+            ErrorKind::ExpansionNotUtf8 {
+                valid_up_to: e.utf8_error().valid_up_to(),
+            }
+            .into()
+        })
     }
 
     pub fn vec(&mut self) -> Result<Vec<u8>> {
+        for arg in self.env.arguments.iter_mut() {
+            if let Some(Argument::String(ref mut s)) = *arg {
+                self.controls.apply(s)?;
+            }
+        }
+
         let mut w = Vec::new();
-        self.env.write(&mut Parser::new(self.src), &mut w);
+        self.env.write(&mut Parser::new(self.src), &mut w)?;
+        if self.seven_bit {
+            return Ok(to_seven_bit(&w));
+        }
         Ok(w)
     }
 
     pub fn write<W: io::Write>(&mut self, w: &mut W) -> Result<usize> {
-        Ok(w.write(&self.vec()?).unwrap())
+        let v = self.vec()?;
+        w.write_all(&v).context(ErrorKind::FailedToWriteArgument)?;
+        Ok(v.len())
     }
 }
 
@@ -63,6 +120,7 @@ impl ExecutionEnvironment {
         ExecutionEnvironment {
             stack: VecDeque::new(),
             arguments: [None, None, None, None, None, None, None, None, None],
+            strict: false,
         }
     }
 
@@ -75,6 +133,7 @@ impl ExecutionEnvironment {
             Some(Argument::Char(_)) => {
                 Err(ErrorKind::UnexpectedArgumentType("string", "char").into())
             }
+            Some(Argument::Missing) => Ok(String::new()),
             None => Err(ErrorKind::UnexpectedArgumentType("string", "null").into()),
         }
     }
@@ -88,6 +147,7 @@ impl ExecutionEnvironment {
             Some(Argument::Char(_)) => {
                 Err(ErrorKind::UnexpectedArgumentType("integer", "char").into())
             }
+            Some(Argument::Missing) => Ok(0),
             None => Err(ErrorKind::UnexpectedArgumentType("string", "null").into()),
         }
     }
@@ -101,6 +161,7 @@ impl ExecutionEnvironment {
                 Err(ErrorKind::UnexpectedArgumentType("char", "string").into())
             }
             Some(Argument::Char(c)) => Ok(c),
+            Some(Argument::Missing) => Ok(0),
             None => Err(ErrorKind::UnexpectedArgumentType("char", "null").into()),
         }
     }
@@ -113,110 +174,451 @@ impl ExecutionEnvironment {
         self.stack.push_back(t.into())
     }
 
-    fn map_integer2<U: Into<Argument>, F: FnOnce(i64, i64) -> U>(&mut self, f: F) -> Result<()> {
-        let x = self.pop_integer()?;
-        let y = self.pop_integer()?;
+    /// Run what `parser` parses with this environment's arguments, the same way `Program::exec_into` does, and write
+    /// the result to `w`. Returns how many bytes were written.
+    pub fn write<'a, W: io::Write>(
+        &mut self,
+        parser: &'a mut Parser<'a>,
+        w: &mut W,
+    ) -> Result<usize> {
+        parser.parse()?;
+        let args: Vec<Argument> = self
+            .arguments
+            .iter()
+            .map(|arg| arg.clone().unwrap_or(Argument::Missing))
+            .collect();
+        let mut count = Count(0, w);
+        run(parser.ops(), self.strict, &args, &mut count, &mut ())?;
+        Ok(count.0)
+    }
+}
 
-        self.push(f(x, y));
-        Ok(())
+/// A capability string that's been parsed ahead of time.
+///
+/// Compile a string once, then expand it as many times as needed with `Program::exec_into`, which doesn't
+/// parse or allocate anything.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Program<'a> {
+    ops: Vec<Op<'a>>,
+    strict: bool,
+    /// `min_cost` and `typical_cost`, worked out when the program's compiled.
+    min_cost: usize,
+    typical_cost: usize,
+}
+
+/// Counts what's written through it, for `Program::cost` and `ExecutionEnvironment::write`.
+struct Count<W>(usize, W);
+
+impl<W: io::Write> io::Write for Count<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.1.write(buf)?;
+        self.0 += n;
+        Ok(n)
     }
 
-    fn map_integer<U: Into<Argument>, F: FnOnce(i64) -> U>(&mut self, f: F) -> Result<()> {
-        let x = self.pop_integer()?;
+    fn flush(&mut self) -> io::Result<()> {
+        self.1.flush()
+    }
+}
 
-        self.push(f(x));
-        Ok(())
+/// A value on a `Program`'s stack, strings are borrowed from the arguments or the program itself.
+#[derive(Debug, Copy, Clone)]
+pub(crate) enum Value<'a> {
+    Integer(i64),
+    String(&'a str),
+    Char(u8),
+    Missing,
+}
+
+struct Stack<'a> {
+    values: [Value<'a>; STACK_SIZE],
+    len: usize,
+}
+
+/// Watches a `Program` run, one op at a time, see `trace_exec`.
+pub(crate) trait Observer<W> {
+    /// The op at `ip` is about to run, with `stack` (bottom first).
+    fn before(&mut self, _ip: usize, _stack: &[Value]) {}
+
+    /// The op at `ip` ran, leaving `stack`, and `next` runs next. `w` has everything written so far.
+    fn after(&mut self, _ip: usize, _stack: &[Value], _next: usize, _w: &W) {}
+}
+
+/// Not watching.
+impl<W> Observer<W> for () {}
+
+impl<'a> Program<'a> {
+    /// Compile the capability string `src`, borrowing its literal text. See `Parser::new` for what it can be.
+    ///
+    /// ```
+    /// use nixterm::terminfo::lang::{Argument, Program};
+    ///
+    /// let src: Vec<u8> = b"\x1b[%p1%dm".to_vec();
+    /// let program = Program::compile(&src).unwrap();
+    /// let mut buf = Vec::new();
+    /// program.exec_append(&[Argument::Integer(1)], &mut buf).unwrap();
+    /// assert_eq!(buf, b"\x1b[1m");
+    /// ```
+    pub fn compile<S: AsRef<[u8]> + ?Sized>(src: &'a S) -> Result<Program<'a>> {
+        let mut parser = Parser::new(src);
+        parser.parse()?;
+        Ok(Program::new(parser.into_ops()))
     }
 
-    fn pop_bool(&mut self) -> bool {
-        match self.pop() {
-            Some(Argument::Integer(x)) => x != 0,
-            Some(Argument::String(s)) => !s.is_empty(),
-            Some(Argument::Char(c)) => c != 0,
-            None => false,
+    /// Compile the capability string `src`, like one from a config file. To know where in `src` an error is, parse
+    /// it with `Parser::from_str` instead.
+    ///
+    /// ```
+    /// use nixterm::terminfo::lang::{Argument, Program};
+    ///
+    /// let program = Program::compile_str("\x1b[%i%p1%d;%p2%dH").unwrap();
+    /// let mut buf = Vec::new();
+    /// program.exec_append(&[Argument::Integer(0), Argument::Integer(4)], &mut buf).unwrap();
+    /// assert_eq!(buf, b"\x1b[1;5H");
+    /// ```
+    pub fn compile_str(src: &'a str) -> Result<Program<'a>> {
+        Program::compile(src)
+    }
+
+    /// Compile a capability string written in termcap's `%` syntax, see `lang::from_termcap`.
+    pub fn from_termcap(src: &'a [u8]) -> Result<Program<'a>> {
+        Ok(Program::new(from_termcap(src)?))
+    }
+
+    /// Copy the program's literal text out of its source, so it can outlive it.
+    pub fn into_owned(self) -> Program<'static> {
+        Program {
+            ops: self.ops.into_iter().map(Op::into_owned).collect(),
+            strict: self.strict,
+            min_cost: self.min_cost,
+            typical_cost: self.typical_cost,
         }
     }
 
-    pub fn write<'a, W: io::Write>(
-        &mut self,
-        parser: &'a mut Parser<'a>,
-        w: &mut W,
-    ) -> Result<usize> {
-        let mut written = 0;
-        'exe: loop {
-            let op = match parser.next() {
-                Some(v) => v?,
-                None => break,
+    fn new(ops: Vec<Op<'a>>) -> Program<'a> {
+        let mut program = Program {
+            ops,
+            strict: false,
+            min_cost: 0,
+            typical_cost: 0,
+        };
+        program.min_cost = program.shortest_path();
+        program.typical_cost = program.cost(&[TYPICAL_ARGUMENT; 9]);
+        program
+    }
+
+    /// Fail with `ErrorKind::MissingArgument` when the program pushes a parameter past the end of the arguments
+    /// (or one given as `Argument::Missing`), instead of treating it as 0 or an empty string like ncurses. This is
+    /// off by default.
+    pub fn strict(mut self, v: bool) -> Program<'a> {
+        self.strict = v;
+        self
+    }
+
+    /// Guess how many bytes expanding the program writes, to reserve space for it ahead of time.
+    ///
+    /// This counts every literal in the program, whichever branch they're in, and the most each conversion can
+    /// print, so it's an upper bound. Except for strings: a `%s` without a precision could print anything, so if the
+    /// program has one there's no hint at all.
+    pub fn max_output_hint(&self) -> Option<usize> {
+        self.ops.iter().try_fold(0, |len, op| match *op {
+            Op::PrintSlice(ref slice) => Some(len + slice.len()),
+            Op::Print(ref p) => p.max_len().map(|n| len + n),
+            _ => Some(len),
+        })
+    }
+
+    /// How many bytes expanding the program with `args` writes, exactly, without writing them anywhere. Comparing
+    /// the costs of the ways a terminal has of doing something picks the shortest, without expanding every one.
+    ///
+    /// A program that fails to expand costs `usize::MAX`, so it's never the cheapest.
+    pub fn cost(&self, args: &[Argument]) -> usize {
+        let mut count = Count(0, io::sink());
+        match self.run(args, &mut count, &mut ()) {
+            Ok(()) => count.0,
+            Err(_) => usize::MAX,
+        }
+    }
+
+    /// The fewest bytes the program could write, whatever its arguments are. This counts the literals on the
+    /// shortest way through its conditionals, and the least each conversion prints.
+    pub fn min_cost(&self) -> usize {
+        self.min_cost
+    }
+
+    /// What the program usually costs, for planning before the arguments are known: its cost with every parameter
+    /// 10, a two digit number like most cursor positions and colors.
+    pub fn typical_cost(&self) -> usize {
+        self.typical_cost
+    }
+
+    /// Work out `min_cost`, from the end of the program back to the start. Jumps only go forward, one that doesn't
+    /// is counted as costing nothing, which is still a lower bound.
+    fn shortest_path(&self) -> usize {
+        let mut from: Vec<usize> = ::std::iter::repeat_n(0, self.ops.len() + 1).collect();
+        for ip in (0..self.ops.len()).rev() {
+            let at = |target: usize| match from.get(target) {
+                Some(&cost) if target > ip => cost,
+                _ => 0,
+            };
+            from[ip] = match self.ops[ip] {
+                Op::Jump(target) => at(target),
+                Op::BranchFalse(target) | Op::BranchTrue(target) => at(target).min(from[ip + 1]),
+                Op::PrintSlice(ref slice) => slice.len() + from[ip + 1],
+                Op::Print(ref p) => p.min_len() + from[ip + 1],
+                _ => from[ip + 1],
             };
+        }
+        from[0]
+    }
 
-            match op {
-                Op::NoOp => (),
-                Op::Push(arg) => self.push(arg),
-                Op::PushUserArg(arg) => {
-                    let val = self.arguments[arg].clone().unwrap_or(Argument::Integer(0));
-                    self.push(val)
+    /// Expand the program onto the end of `buf`.
+    ///
+    /// If expanding fails, `buf` is left as it was.
+    pub fn exec_append(&self, args: &[Argument], buf: &mut Vec<u8>) -> Result<()> {
+        let len = buf.len();
+        let result = self.exec_into(args, buf);
+        if result.is_err() {
+            buf.truncate(len);
+        }
+        result
+    }
+
+    /// Expand the program with `args` as `%p1` through `%p9`, and write the result to `w`.
+    ///
+    /// String arguments are borrowed rather than copied, numbers are formatted on the stack, and literal text is
+    /// written straight from the capability string, so expanding something like `cup` or `setaf` never allocates.
+    ///
+    /// String arguments are printed as they are, control characters included. To strip or reject those, run the
+    /// arguments through `ControlChars::apply` first.
+    pub fn exec_into<W: io::Write>(&self, args: &[Argument], w: &mut W) -> Result<()> {
+        self.run(args, w, &mut ())
+    }
+
+    pub(crate) fn ops(&self) -> &[Op<'a>] {
+        &self.ops
+    }
+
+    /// Expand the program like `Program::exec_into`, telling `observer` about every op.
+    pub(crate) fn run<W: io::Write, O: Observer<W>>(
+        &self,
+        args: &[Argument],
+        w: &mut W,
+        observer: &mut O,
+    ) -> Result<()> {
+        run(&self.ops, self.strict, args, w, observer)
+    }
+}
+
+/// Run `ops` with `args` as `%p1` through `%p9`, writing to `w` and telling `observer` about every op. Both
+/// `Program` and `Executor` expand capabilities with this.
+fn run<'a, W: io::Write, O: Observer<W>>(
+    ops: &'a [Op],
+    strict: bool,
+    args: &'a [Argument],
+    w: &mut W,
+    observer: &mut O,
+) -> Result<()> {
+    let mut stack = Stack::new();
+    let mut increment = 0;
+    let mut ip = 0;
+
+    while let Some(op) = ops.get(ip) {
+        observer.before(ip, stack.values());
+        let here = ip;
+        ip += 1;
+        match *op {
+            Op::NoOp => (),
+            Op::Push(ref arg) => stack.push(Value::from(arg))?,
+            Op::PushUserArg(i) => stack.push(match args.get(i) {
+                None | Some(&Argument::Missing) if strict => {
+                    return Err(ErrorKind::MissingArgument { index: i }.into())
                 }
-                Op::Jump(ip) => for _ in 0..ip {
-                    match parser.next() {
-                        Some(Err(e)) => return Err(e),
-                        Some(Ok(_)) => (),
-                        None => break 'exe,
-                    }
-                },
-                Op::BranchFalse(ip) => if !self.pop_bool() {
-                    for _ in 0..ip {
-                        match parser.next() {
-                            Some(Err(e)) => return Err(e),
-                            Some(Ok(_)) => (),
-                            None => break 'exe,
-                        }
-                    }
-                },
-                Op::BranchTrue(ip) => if self.pop_bool() {
-                    for _ in 0..ip {
-                        match parser.next() {
-                            Some(Err(e)) => return Err(e),
-                            Some(Ok(_)) => (),
-                            None => break 'exe,
-                        }
+                // `%i` only applies to the first two arguments, and only if they're numbers, a missing one is 0
+                Some(&Argument::Integer(x)) if i < 2 => Value::Integer(x + increment),
+                None | Some(&Argument::Missing) if i < 2 && increment > 0 => Value::Integer(increment),
+                Some(arg) => Value::from(arg),
+                None => Value::Missing,
+            })?,
+            Op::Jump(target) => ip = target,
+            Op::BranchFalse(target) => if !stack.pop_bool() {
+                ip = target
+            },
+            Op::BranchTrue(target) => if stack.pop_bool() {
+                ip = target
+            },
+            Op::Add => stack.map_integer2(|x, y| y.wrapping_add(x))?,
+            Op::Sub => stack.map_integer2(|x, y| y.wrapping_sub(x))?,
+            Op::Div => stack.map_integer2(|x, y| y.checked_div(x).unwrap_or(0))?,
+            Op::Mul => stack.map_integer2(|x, y| y.wrapping_mul(x))?,
+            Op::Mod => stack.map_integer2(|x, y| y.checked_rem(x).unwrap_or(0))?,
+            Op::BitAnd => stack.map_integer2(|x, y| x & y)?,
+            Op::BitOr => stack.map_integer2(|x, y| x | y)?,
+            Op::BitXor => stack.map_integer2(|x, y| x ^ y)?,
+            Op::Equal => stack.map_integer2(|x, y| (x == y) as i64)?,
+            Op::Greater => stack.map_integer2(|x, y| (y > x) as i64)?,
+            Op::Less => stack.map_integer2(|x, y| (y < x) as i64)?,
+            Op::Invert => stack.map_integer(|x| !x)?,
+            Op::Not => stack.map_integer(|x| (x == 0) as i64)?,
+            Op::IncrementArgs => increment += 1,
+            Op::StrLen => {
+                let len = stack.pop_string()?.len();
+                stack.push(Value::Integer(len as i64))?;
+            }
+            Op::Print(ref p) => {
+                match stack.pop() {
+                    Some(Value::Integer(x)) => p.write_number(w, x)?,
+                    Some(Value::String(s)) => p.write_string(w, s)?,
+                    Some(Value::Char(c)) => p.write_char(w, c)?,
+                    Some(Value::Missing) => p.write_missing(w)?,
+                    None => {
+                        w.write_all(NULL).context(ErrorKind::FailedToWriteArgument)?;
+                        NULL.len()
                     }
-                },
-                Op::Add => self.map_integer2(|x, y| x + y)?,
-                Op::Sub => self.map_integer2(|x, y| x - y)?,
-                Op::Div => self.map_integer2(|x, y| x / y)?,
-                Op::Mul => self.map_integer2(|x, y| x * y)?,
-                Op::Mod => self.map_integer2(|x, y| x % y)?,
-                Op::BitAnd => self.map_integer2(|x, y| x & y)?,
-                Op::BitOr => self.map_integer2(|x, y| x | y)?,
-                Op::BitXor => self.map_integer2(|x, y| x ^ y)?,
-                Op::Equal => self.map_integer2(|x, y| x == y)?,
-                Op::Greater => self.map_integer2(|x, y| y > x)?,
-                Op::Less => self.map_integer2(|x, y| y < x)?,
-                Op::Invert => self.map_integer(|x| !x)?,
-                Op::Not => self.map_integer(|x| if x != 0 { x == 0 } else { x == 1 })?,
-                Op::IncrementArgs => {
-                    match self.arguments[0] {
-                        Some(Argument::Integer(ref mut x)) => *x += 1,
-                        _ => (),
-                    };
-                    match self.arguments[1] {
-                        Some(Argument::Integer(ref mut x)) => *x += 1,
-                        _ => (),
-                    };
-                }
-                Op::StrLen => {
-                    let x = self.pop_string()?.len();
-                    self.push(x);
-                }
-                Op::Print(p) => {
-                    written += p.print(w, self.pop())?;
-                }
-                Op::PrintSlice(slice) => {
-                    written += w.write(slice).context(ErrorKind::FailedToWriteArgument)?;
-                }
+                };
             }
+            Op::PrintSlice(ref slice) => {
+                w.write_all(slice).context(ErrorKind::FailedToWriteArgument)?
+            }
+        }
+        observer.after(here, stack.values(), ip, w);
+    }
+
+    Ok(())
+}
+
+/// The same as `Program::compile`, for code that's generic over conversions.
+///
+/// ```
+/// use nixterm::terminfo::lang::Program;
+/// use std::convert::TryFrom;
+///
+/// let src: &[u8] = b"\x1b[%p1%dA";
+/// assert_eq!(Program::try_from(src).unwrap(), Program::compile(src).unwrap());
+/// ```
+impl<'a> TryFrom<&'a [u8]> for Program<'a> {
+    type Error = Error;
+
+    fn try_from(src: &'a [u8]) -> Result<Program<'a>> {
+        Program::compile(src)
+    }
+}
+
+/// Compile a program that owns its literal text, see `Program::into_owned`, so it can be parsed out of
+/// configuration that doesn't outlive it.
+///
+/// ```
+/// use nixterm::terminfo::lang::{Argument, Program};
+///
+/// let program: Program<'static> = String::from("\x1b[%p1%dA").parse().unwrap();
+/// let mut buf = Vec::new();
+/// program.exec_append(&[Argument::Integer(3)], &mut buf).unwrap();
+/// assert_eq!(buf, b"\x1b[3A");
+/// assert!("%?%p1%t".parse::<Program>().is_err());
+/// ```
+impl FromStr for Program<'static> {
+    type Err = Error;
+
+    fn from_str(src: &str) -> Result<Program<'static>> {
+        Program::compile_str(src).map(Program::into_owned)
+    }
+}
+
+impl<'a> From<&'a Argument> for Value<'a> {
+    fn from(arg: &'a Argument) -> Value<'a> {
+        match *arg {
+            Argument::Integer(x) => Value::Integer(x),
+            Argument::String(ref s) => Value::String(s),
+            Argument::Char(c) => Value::Char(c),
+            Argument::Missing => Value::Missing,
         }
+    }
+}
 
-        Ok(written)
+impl<'a> Value<'a> {
+    pub(crate) fn to_argument(self) -> Argument {
+        match self {
+            Value::Integer(x) => Argument::Integer(x),
+            Value::String(s) => Argument::String(s.into()),
+            Value::Char(c) => Argument::Char(c),
+            Value::Missing => Argument::Missing,
+        }
+    }
+
+    fn type_name(&self) -> &'static str {
+        match *self {
+            Value::Integer(_) => "integer",
+            Value::String(_) => "string",
+            Value::Char(_) => "char",
+            Value::Missing => "missing",
+        }
+    }
+}
+
+impl<'a> Stack<'a> {
+    fn new() -> Stack<'a> {
+        Stack {
+            values: [Value::Integer(0); STACK_SIZE],
+            len: 0,
+        }
+    }
+
+    fn values(&self) -> &[Value<'a>] {
+        &self.values[..self.len]
+    }
+
+    fn push(&mut self, v: Value<'a>) -> Result<()> {
+        if self.len == STACK_SIZE {
+            return Err(ErrorKind::StackOverflow.into());
+        }
+        self.values[self.len] = v;
+        self.len += 1;
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Option<Value<'a>> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        Some(self.values[self.len])
+    }
+
+    fn pop_integer(&mut self) -> Result<i64> {
+        match self.pop() {
+            Some(Value::Integer(x)) => Ok(x),
+            Some(Value::Missing) => Ok(0),
+            Some(v) => Err(ErrorKind::UnexpectedArgumentType("integer", v.type_name()).into()),
+            None => Err(ErrorKind::UnexpectedArgumentType("integer", "null").into()),
+        }
+    }
+
+    fn pop_string(&mut self) -> Result<&'a str> {
+        match self.pop() {
+            Some(Value::String(s)) => Ok(s),
+            Some(Value::Missing) => Ok(""),
+            Some(v) => Err(ErrorKind::UnexpectedArgumentType("string", v.type_name()).into()),
+            None => Err(ErrorKind::UnexpectedArgumentType("string", "null").into()),
+        }
+    }
+
+    fn pop_bool(&mut self) -> bool {
+        match self.pop() {
+            Some(Value::Integer(x)) => x != 0,
+            Some(Value::String(s)) => !s.is_empty(),
+            Some(Value::Char(c)) => c != 0,
+            Some(Value::Missing) | None => false,
+        }
+    }
+
+    fn map_integer2<F: FnOnce(i64, i64) -> i64>(&mut self, f: F) -> Result<()> {
+        let x = self.pop_integer()?;
+        let y = self.pop_integer()?;
+        self.push(Value::Integer(f(x, y)))
+    }
+
+    fn map_integer<F: FnOnce(i64) -> i64>(&mut self, f: F) -> Result<()> {
+        let x = self.pop_integer()?;
+        self.push(Value::Integer(f(x)))
     }
 }
@@ -2,12 +2,80 @@ mod argument;
 pub mod executor;
 pub mod parser;
 pub mod printf;
+mod termcap;
+mod trace;
 
 pub use self::argument::*;
-pub use self::executor::Executor;
+pub use self::executor::{Executor, Program};
+pub use self::termcap::from_termcap;
+pub use self::trace::{trace_exec, ExecTrace, Step};
+
+/// Without the `std` feature there's no `std::io::Write`, so expanded strings are written to this module's
+/// `Write` instead. It's implemented for `Vec<u8>`, and can be implemented for anything else bytes can go to, like a
+/// serial port.
+#[cfg(not(any(feature = "std", test)))]
+pub use std::io;
+
+use std::fmt::Write;
+use std::string::String;
+use std::vec::Vec;
+
+/// Write `bytes` the way they'd be written in a terminfo source file, like `\E[%p1%dm`.
+pub(crate) fn escape(bytes: &[u8]) -> String {
+    let mut s = String::new();
+    for &c in bytes {
+        let _ = match c {
+            0x1b => write!(s, "\\E"),
+            b'\\' | b',' | b'^' => write!(s, "\\{}", c as char),
+            0x7f => write!(s, "^?"),
+            0..=0x1f => write!(s, "^{}", (c + 0x40) as char),
+            0x80..=0xff => write!(s, "\\{:03o}", c),
+            _ => write!(s, "{}", c as char),
+        };
+    }
+    s
+}
+
+/// Replace the 8-bit C1 controls in `bytes` with their 7-bit equivalents, `ESC` followed by the control minus 0x40, so
+/// 0x9b (CSI) becomes `ESC [`. This is for sending an entry written for 8-bit controls over something that mangles
+/// bytes above 0x7f.
+///
+/// C1 controls written as UTF-8 (U+0080 to U+009F) are replaced too, but other UTF-8 characters that merely have a
+/// byte in that range (like the 0x9c in U+071C) are left alone.
+pub fn to_seven_bit(bytes: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i];
+        let len = match c {
+            0xc2..=0xdf => 2,
+            0xe0..=0xef => 3,
+            0xf0..=0xf4 => 4,
+            _ => 1,
+        };
+        let end = i + len;
+        if len > 1 && end <= bytes.len() && ::std::str::from_utf8(&bytes[i..end]).is_ok() {
+            match bytes[i..end] {
+                // a C1 control written as UTF-8
+                [0xc2, c @ 0x80..=0x9f] => out.extend_from_slice(&[0x1b, c - 0x40]),
+                ref character => out.extend_from_slice(character),
+            }
+            i = end;
+            continue;
+        }
+        match c {
+            0x80..=0x9f => out.extend_from_slice(&[0x1b, c - 0x40]),
+            _ => out.push(c),
+        }
+        i += 1;
+    }
+    out
+}
 
 #[cfg(test)]
 mod tests {
+    use std::borrow::Cow;
+    use terminfo::lang::parser::*;
     use terminfo::lang::printf::*;
     use terminfo::lang::*;
 
@@ -16,6 +84,7 @@ mod tests {
         let mut buffer = Vec::new();
         PrintfArgs::parse(b"s")
             .unwrap()
+            .0
             .print(&mut buffer, Some("Hello World!"))
             .unwrap();
         assert_eq!(&buffer, &b"Hello World!");
@@ -23,6 +92,7 @@ mod tests {
         buffer.clear();
         PrintfArgs::parse(b"d")
             .unwrap()
+            .0
             .print(&mut buffer, Some(12))
             .unwrap();
         assert_eq!(&buffer, &b"12");
@@ -30,6 +100,7 @@ mod tests {
         buffer.clear();
         PrintfArgs::parse(b": d")
             .unwrap()
+            .0
             .print(&mut buffer, Some(12))
             .unwrap();
         assert_eq!(&buffer, &b" 12");
@@ -37,6 +108,7 @@ mod tests {
         buffer.clear();
         PrintfArgs::parse(b"d")
             .unwrap()
+            .0
             .print(&mut buffer, Some(i64::max_value()))
             .unwrap();
         assert_eq!(&buffer, b"9223372036854775807");
@@ -44,20 +116,32 @@ mod tests {
 
         PrintfArgs::parse(b":-5d")
             .unwrap()
+            .0
             .print(&mut buffer, Some(21))
             .unwrap();
         assert_eq!(&buffer, b"21   ");
         buffer.clear();
 
+        // a precision is the fewest digits to print, it never cuts a number short
         PrintfArgs::parse(b".1d")
             .unwrap()
+            .0
             .print(&mut buffer, Some(21))
             .unwrap();
-        assert_eq!(&buffer, b"2");
+        assert_eq!(&buffer, b"21");
+        buffer.clear();
+
+        PrintfArgs::parse(b"5.3d")
+            .unwrap()
+            .0
+            .print(&mut buffer, Some(-21))
+            .unwrap();
+        assert_eq!(&buffer, b" -021");
         buffer.clear();
 
         PrintfArgs::parse(b".4s")
             .unwrap()
+            .0
             .print(&mut buffer, Some("Crop Me"))
             .unwrap();
         assert_eq!(&buffer, b"Crop");
@@ -65,6 +149,7 @@ mod tests {
 
         PrintfArgs::parse(b"9.4s")
             .unwrap()
+            .0
             .print(&mut buffer, Some("Crop Me"))
             .unwrap();
         assert_eq!(&buffer, b"     Crop");
@@ -72,16 +157,44 @@ mod tests {
 
         PrintfArgs::parse(b":-9.4d")
             .unwrap()
+            .0
             .print(&mut buffer, Some(99))
             .unwrap();
-        assert_eq!(&buffer, b"99       ");
+        assert_eq!(&buffer, b"0099     ");
         buffer.clear();
 
         PrintfArgs::parse(b":-9.4d")
             .unwrap()
+            .0
             .print(&mut buffer, Some(99999))
             .unwrap();
-        assert_eq!(&buffer, b"9999     ");
+        assert_eq!(&buffer, b"99999    ");
+    }
+
+    #[test]
+    fn printf_padding() {
+        let print = |spec: &[u8], v: i64| {
+            let mut buffer = Vec::new();
+            PrintfArgs::parse(spec)
+                .unwrap()
+                .0
+                .print(&mut buffer, Some(v))
+                .unwrap();
+            String::from_utf8(buffer).unwrap()
+        };
+
+        assert_eq!(print(b"d", 0), "0");
+        assert_eq!(print(b"x", 0), "0");
+        assert_eq!(print(b".0d", 0), "");
+        assert_eq!(print(b"12d", 7), "           7");
+        assert_eq!(print(b"02x", 10), "0a");
+        assert_eq!(print(b"02x", 255), "ff");
+        assert_eq!(print(b"02x", 0), "00");
+        assert_eq!(print(b"05d", -42), "-0042");
+        assert_eq!(print(b"2.2X", 205), "CD");
+        assert_eq!(print(b"2.2X", 5), "05");
+        assert_eq!(print(b":-6.3x", 5), "005   ");
+        assert_eq!(print(b":#06x", 5), "0x0005");
     }
 
     #[test]
@@ -101,12 +214,20 @@ mod tests {
         assert_eq!(&buffer, b"30c");
         buffer.clear();
 
+        Executor::new(b"%p1%c\x1b[%p2%{1}%-%db")
+            .arg(97)
+            .arg(5)
+            .write(&mut buffer)
+            .unwrap();
+        assert_eq!(&buffer, b"a\x1b[4b");
+        buffer.clear();
+
         Executor::new(b"%p1%p2%+/%d%p1%.1d")
             .arg(40)
             .arg(20)
             .write(&mut buffer)
             .unwrap();
-        assert_eq!(&buffer, b"/604");
+        assert_eq!(&buffer, b"/6040");
         buffer.clear();
 
         Executor::new(b"%i%p1%d.%p2%d")
@@ -157,4 +278,416 @@ mod tests {
             .unwrap();
         assert_eq!(&String::from_utf8(buffer).unwrap(), "\x1b[33m");
     }
+
+    #[test]
+    fn program() {
+        let cases: &[(&[u8], &[Argument])] = &[
+            (b"%p1%p2%+%d", &[Argument::Integer(30), Argument::Integer(2)]),
+            (b"%{8}%{22}%+%d%'c'%c", &[]),
+            (
+                b"%p1%p2%+/%d%p1%.1d",
+                &[Argument::Integer(40), Argument::Integer(20)],
+            ),
+            (b"%i%p1%d.%p2%d", &[Argument::Integer(1), Argument::Integer(2)]),
+            (
+                b"%p1%l%p1\"%s\" is %d characters long!",
+                &[Argument::String("Hello World".into())],
+            ),
+            (b"%?%p1%tyes!%eno!%;", &[Argument::Integer(0)]),
+            (b"%p1%c", &[Argument::Integer(65)]),
+            (b"%?%p1%!%tnot%;", &[Argument::Integer(0)]),
+            (b"%p1%!%d%p2%!%d", &[Argument::Integer(0), Argument::Integer(5)]),
+            // a missing argument counts as 0 for `%i`
+            (b"%i%p1%d;%p2%d", &[]),
+            (b"%i%i%p1%d;%p2%d", &[Argument::Integer(3)]),
+            (
+                b"%p1%03d%p2%s%p3%c",
+                &[
+                    Argument::Integer(7),
+                    Argument::String("ab".into()),
+                    Argument::Char(b'!'),
+                ],
+            ),
+            (
+                b"\x1b[%?%p1%{8}%<%t3%p1%d%e%p1%{16}%<%t9%p1%{8}%-%d%e38;5;%p1%d%;m",
+                &[Argument::Integer(12)],
+            ),
+        ];
+
+        for &(src, args) in cases {
+            let mut exe = Executor::new(src);
+            for arg in args {
+                exe = exe.arg(arg.clone());
+            }
+            let mut buffer = Vec::new();
+            Program::compile(src)
+                .unwrap()
+                .exec_into(args, &mut buffer)
+                .unwrap();
+            assert_eq!(buffer, exe.vec().unwrap());
+        }
+
+        let not = Executor::new(b"%p1%!%d%p2%!%d").arg(0).arg(5).vec().unwrap();
+        assert_eq!(not, b"10");
+
+        let mut buffer = Vec::new();
+        Program::compile(b"%p1%p2%/%d")
+            .unwrap()
+            .exec_into(&[Argument::Integer(1), Argument::Integer(0)], &mut buffer)
+            .unwrap();
+        assert_eq!(buffer, b"0");
+
+        let e = Program::compile(&b"%{1}".repeat(21))
+            .unwrap()
+            .exec_into(&[], &mut Vec::new())
+            .unwrap_err();
+        assert_eq!(*e.kind(), ::terminfo::ErrorKind::StackOverflow);
+    }
+
+    #[test]
+    fn seven_bit() {
+        assert_eq!(to_seven_bit(b"\x9b1;2H\x9bm"), b"\x1b[1;2H\x1b[m");
+        assert_eq!(to_seven_bit(b"\x9d0;title\x9c"), b"\x1b]0;title\x1b\\");
+        assert_eq!(to_seven_bit("\u{9b}2J".as_bytes()), b"\x1b[2J");
+        // characters that only have a C1 byte in them, and 7-bit output, are left alone
+        assert_eq!(to_seven_bit("\u{71c} caf\u{e9}".as_bytes()), "\u{71c} caf\u{e9}".as_bytes());
+        assert_eq!(to_seven_bit(b"\x1b[m\xc2"), b"\x1b[m\xc2");
+
+        let cup = b"\x9b%i%p1%d;%p2%dH";
+        assert_eq!(Executor::new(cup).arg(4).arg(9).vec().unwrap(), b"\x9b5;10H");
+        assert_eq!(
+            Executor::new(cup).arg(4).arg(9).seven_bit(true).vec().unwrap(),
+            b"\x1b[5;10H"
+        );
+    }
+
+    #[test]
+    fn missing_arguments() {
+        // %p2 is never given, it's 0 or an empty string like in ncurses
+        let cases: &[(&[u8], &[u8])] = &[
+            (b"%p1%p2%+%d", b"7"),
+            (b"%p1%p2%-%d", b"7"),
+            (b"%p1%p2%*%d", b"0"),
+            (b"%p1%p2%/%d", b"0"),
+            (b"%p1%p2%m%d", b"0"),
+            (b"%p1%p2%&%d", b"0"),
+            (b"%p1%p2%|%d", b"7"),
+            (b"%p1%p2%^%d", b"7"),
+            (b"%p2%{0}%=%d", b"1"),
+            (b"%p2%p1%<%d", b"1"),
+            (b"%p2%p1%>%d", b"0"),
+            (b"%p2%~%d", b"-1"),
+            (b"%p2%!%d", b"1"),
+            (b"%p2%l%d", b"0"),
+            (b"[%p2%s]", b"[]"),
+            (b"%p2%3d", b"  0"),
+            (b"%p2%x", b"0"),
+            (b"%?%p2%tyes%eno%;", b"no"),
+            (b"%i%p1%d;%p2%d", b"8;1"),
+        ];
+        for &(src, expected) in cases {
+            let program = Program::compile(src).unwrap();
+            let mut buffer = Vec::new();
+            program.exec_into(&[Argument::Integer(7)], &mut buffer).unwrap();
+            assert_eq!(buffer, expected, "{}", escape(src));
+            assert_eq!(Executor::new(src).arg(7).vec().unwrap(), expected, "{}", escape(src));
+
+            buffer.clear();
+            program
+                .exec_into(&[Argument::Integer(7), Argument::Missing], &mut buffer)
+                .unwrap();
+            assert_eq!(buffer, expected, "{}", escape(src));
+
+            let missing = ::terminfo::ErrorKind::MissingArgument { index: 1 };
+            let e = program
+                .clone()
+                .strict(true)
+                .exec_into(&[Argument::Integer(7)], &mut Vec::new())
+                .unwrap_err();
+            assert_eq!(*e.kind(), missing, "{}", escape(src));
+            let e = Executor::new(src).arg(7).strict(true).vec().unwrap_err();
+            assert_eq!(*e.kind(), missing, "{}", escape(src));
+        }
+
+        // given arguments are fine either way, and a 0 that was given is still a number to %s
+        let program = Program::compile(b"%p1%d").unwrap().strict(true);
+        let mut buffer = Vec::new();
+        program.exec_into(&[Argument::Integer(0)], &mut buffer).unwrap();
+        assert_eq!(buffer, b"0");
+        assert!(Executor::new(b"%p1%s").arg(0).vec().is_err());
+
+        // and traces show what wasn't given
+        let trace = trace_exec(&Program::compile(b"%p2%d").unwrap(), &[]);
+        assert_eq!(trace.steps()[0].stack_after, vec![Argument::Missing]);
+        assert_eq!(trace.output(), b"0");
+    }
+
+    #[test]
+    fn string_arguments() {
+        const TITLE: &[u8] = b"\x1b]0;%p1%s\x07";
+        let expand = |s: &str, controls| Executor::new(TITLE).arg(s).controls(controls).vec();
+
+        for &controls in &[ControlChars::Keep, ControlChars::Strip, ControlChars::Reject] {
+            assert_eq!(expand("vim ~/notes", controls).unwrap(), b"\x1b]0;vim ~/notes\x07");
+            assert_eq!(expand("", controls).unwrap(), b"\x1b]0;\x07");
+        }
+
+        let nasty = "a\x1b]2;b\x07\0c\u{9c}d";
+        assert_eq!(
+            expand(nasty, ControlChars::Keep).unwrap(),
+            b"\x1b]0;a\x1b]2;b\x07\0c\xc2\x9cd\x07"
+        );
+        assert_eq!(
+            expand(nasty, ControlChars::Strip).unwrap(),
+            b"\x1b]0;a]2;bcd\x07"
+        );
+        let e = expand(nasty, ControlChars::Reject).unwrap_err();
+        assert_eq!(*e.kind(), ::terminfo::ErrorKind::ControlCharInArgument('\x1b'));
+
+        // `Program` keeps them, like `Executor` does by default
+        let mut buffer = Vec::new();
+        Program::compile(TITLE)
+            .unwrap()
+            .exec_into(&[Argument::from(nasty)], &mut buffer)
+            .unwrap();
+        assert_eq!(buffer, expand(nasty, ControlChars::Keep).unwrap());
+
+        // `%l` counts what's left after stripping, and width and precision count bytes
+        let src = b"%p1%l%d:%p1%:-6.4s|%p2%5s";
+        assert_eq!(
+            Executor::new(src)
+                .arg("\x1bhello")
+                .arg("é")
+                .controls(ControlChars::Strip)
+                .vec()
+                .unwrap(),
+            "5:hell  |   é".as_bytes()
+        );
+        // a character that doesn't fit in the precision is left out, rather than cut in half
+        assert_eq!(Executor::new(b"%p1%.1s").arg("é").vec().unwrap(), b"");
+        assert_eq!(Executor::new(b"%p1%.3s").arg("aé").vec().unwrap(), "aé".as_bytes());
+        assert_eq!(Executor::new(b"%p1%.2s").arg("aé").vec().unwrap(), b"a");
+        let e = Executor::new(b"a\x9b0m").string().unwrap_err();
+        assert_eq!(*e.kind(), ::terminfo::ErrorKind::ExpansionNotUtf8 { valid_up_to: 1 });
+
+        let e = Executor::new(b"%p1%d").arg("x").vec().unwrap_err();
+        assert_eq!(
+            *e.kind(),
+            ::terminfo::ErrorKind::UnexpectedArgumentType("integer", "string")
+        );
+    }
+
+    #[test]
+    fn parser() {
+        let sources: &[&[u8]] = &[
+            b"%p1%p2%+%d",
+            b"%?%p1%tyes!%;",
+            b"%?%p1%tyes!%eno!%;",
+            b"%?%p1%{1}%=%tone%e%p1%{2}%=%ttwo%e%p1%{3}%=%tthree%eother%;!",
+            b"%?%p1%t%?%p2%tboth%eonly one%;%eneither%;",
+            b"\x1b[%?%p1%{8}%<%t3%p1%d%e%p1%{16}%<%t9%p1%{8}%-%d%e38;5;%p1%d%;m",
+        ];
+
+        for &src in sources {
+            let mut parser = Parser::new(src);
+            parser.parse().unwrap();
+            let iterated = Parser::new(src).collect::<::terminfo::Result<Vec<_>>>().unwrap();
+            assert_eq!(parser.ops(), &iterated[..]);
+        }
+
+        let mut parser = Parser::new(b"%?%p1%ta%eb%;c");
+        parser.parse().unwrap();
+        assert_eq!(parser.op_offsets(), &[2, 7, 7, 8, 10, 13]);
+        assert_eq!(
+            parser.into_ops(),
+            vec![
+                Op::PushUserArg(0),
+                Op::BranchFalse(4),
+                Op::PrintSlice(Cow::Borrowed(b"a")),
+                Op::Jump(5),
+                Op::PrintSlice(Cow::Borrowed(b"b")),
+                Op::PrintSlice(Cow::Borrowed(b"c")),
+            ]
+        );
+
+        let mut parser = Parser::new(b"ab%");
+        assert!(parser.next().unwrap().is_err());
+        assert!(parser.next().is_none());
+
+        let mut parser = Parser::new(b"ab%{x}c");
+        assert!(parser.parse().is_err());
+        assert_eq!(parser.offset(), 2);
+
+        // the offsets of a `str` source count its bytes, the same as the bytes would
+        let src = "→ %p1%d%{x}";
+        let mut parser = Parser::from_str(src);
+        assert!(parser.parse().is_err());
+        assert_eq!(&src[..parser.offset()], "→ %p1%d");
+        let mut bytes = Parser::new(src.as_bytes());
+        assert!(bytes.parse().is_err());
+        assert_eq!(bytes.offset(), parser.offset());
+
+        // an owned program is the same program
+        let program = Program::compile_str("%?%p1%ta%eb%;c").unwrap();
+        let owned: Program<'static> = program.clone().into_owned();
+        assert_eq!(owned, program);
+        assert_eq!(owned, "%?%p1%ta%eb%;c".parse().unwrap());
+
+        let expand = |src: &[u8], n: i64| {
+            let mut buffer = Vec::new();
+            Program::compile(src)
+                .unwrap()
+                .exec_into(&[Argument::Integer(n)], &mut buffer)
+                .unwrap();
+            assert_eq!(buffer, Executor::new(src).arg(n).vec().unwrap());
+            String::from_utf8(buffer).unwrap()
+        };
+        let src = sources[3];
+        assert_eq!(expand(src, 1), "one!");
+        assert_eq!(expand(src, 2), "two!");
+        assert_eq!(expand(src, 3), "three!");
+        assert_eq!(expand(src, 4), "other!");
+    }
+
+    #[test]
+    fn long_programs() {
+        // alternating literals and conditionals
+        let block: &[u8] = b"literal text %?%p1%{3}%=%tthree%e%p1%d%;";
+        let src = block.repeat(100 * 1024 / block.len() + 1);
+        assert!(src.len() > 100 * 1024);
+        let mut parser = Parser::new(&src);
+        parser.parse().unwrap();
+        let blocks = src.len() / block.len();
+        assert_eq!(parser.ops().len(), blocks * 9);
+        let out = Executor::new(&src).arg(3).vec().unwrap();
+        assert_eq!(out, b"literal text three".repeat(blocks));
+
+        // nested as deep as they go, which used to take a stack frame for each level
+        let depth = 100 * 1024 / 10;
+        let mut src = b"%?%p1%t".repeat(depth);
+        src.extend_from_slice(b"x");
+        src.extend_from_slice(&b"%;".repeat(depth));
+        assert_eq!(Executor::new(&src).arg(1).vec().unwrap(), b"x");
+        assert_eq!(Executor::new(&src).arg(0).vec().unwrap(), b"");
+        let e = Program::compile(&src[..src.len() - 2]).err().unwrap();
+        assert_eq!(*e.kind(), ::terminfo::ErrorKind::UnexpectedEof);
+
+        let mut parser = Parser::new(&src).max_len(1024);
+        assert_eq!(
+            *parser.parse().unwrap_err().kind(),
+            ::terminfo::ErrorKind::ProgramTooLong {
+                len: src.len(),
+                max: 1024
+            }
+        );
+        assert!(parser.ops().is_empty());
+        let e = Parser::new(&b"x".repeat(MAX_PROGRAM_LEN + 1)).parse().unwrap_err();
+        assert_eq!(
+            *e.kind(),
+            ::terminfo::ErrorKind::ProgramTooLong {
+                len: MAX_PROGRAM_LEN + 1,
+                max: MAX_PROGRAM_LEN
+            }
+        );
+    }
+
+    #[test]
+    fn printf_length() {
+        let len = |spec: &[u8]| PrintfArgs::parse(spec).unwrap().1;
+        assert_eq!(len(b"d"), 1);
+        assert_eq!(len(b"d5"), 1);
+        assert_eq!(len(b":-5d%d"), 4);
+        assert_eq!(len(b":+# 03xd"), 7);
+        assert_eq!(len(b"2.3d4"), 4);
+        assert_eq!(len(b".10s"), 4);
+
+        let spec = |s: &[u8]| Op::Print(PrintfArgs::parse(s).unwrap().0);
+        let src = b"%p1%2.3d5%p2%:-5d%%%p1%x9";
+        assert_eq!(
+            Parser::new(src).collect::<::terminfo::Result<Vec<_>>>().unwrap(),
+            vec![
+                Op::PushUserArg(0),
+                spec(b"2.3d"),
+                Op::PrintSlice(Cow::Borrowed(b"5")),
+                Op::PushUserArg(1),
+                spec(b":-5d"),
+                Op::PrintSlice(Cow::Borrowed(b"%")),
+                Op::PushUserArg(0),
+                spec(b"x"),
+                Op::PrintSlice(Cow::Borrowed(b"9")),
+            ]
+        );
+        assert_eq!(Executor::new(src).arg(7).arg(-4).vec().unwrap(), b"0075-4   %79");
+    }
+
+    #[test]
+    fn output_hint() {
+        let hint = |src: &[u8]| Program::compile(src).unwrap().max_output_hint();
+        assert_eq!(hint(b"abc"), Some(3));
+        assert_eq!(hint(b"\x1b[%i%p1%d;%p2%dH"), Some(2 + 20 + 1 + 20 + 1));
+        assert_eq!(hint(b"%?%p1%tyes%eno%;"), Some(5));
+        assert_eq!(hint(b"%p1%c%p1%30d"), Some(6 + 30));
+        assert_eq!(hint(b"%p1%.2s"), Some(6));
+        assert_eq!(hint(b"%p1%s"), None);
+
+        let cup = Program::compile(b"\x1b[%i%p1%d;%p2%dH").unwrap();
+        let mut buffer = b"before".to_vec();
+        let args = [Argument::Integer(i64::max_value() - 1), Argument::Integer(-10)];
+        buffer.reserve_exact(cup.max_output_hint().unwrap());
+        let capacity = buffer.capacity();
+        cup.exec_append(&args, &mut buffer).unwrap();
+        assert_eq!(buffer, b"before\x1b[9223372036854775807;-9H".to_vec());
+        assert_eq!(buffer.capacity(), capacity);
+
+        Program::compile(b"abc%p1%d")
+            .unwrap()
+            .exec_append(&[Argument::String("x".into())], &mut buffer)
+            .unwrap_err();
+        assert_eq!(buffer, b"before\x1b[9223372036854775807;-9H".to_vec());
+    }
+
+    #[test]
+    fn cost() {
+        use terminfo::*;
+
+        let xterm = include_bytes!("../../../test-data/xterm-256color");
+        let info = TermInfoBuf::from(TermInfo::parse(xterm).unwrap());
+        let fields = [
+            CursorAddress,
+            ChangeScrollRegion,
+            SetAForeground,
+            SetABackground,
+            SetAttributes,
+            ParmRightCursor,
+            ColumnAddress,
+            RepeatChar,
+            EraseChars,
+        ];
+        let values = [0, 1, 7, 9, 10, 99, 255, 1000, -3];
+        for &field in &fields {
+            let program = Program::compile(info.string_bytes(field).unwrap()).unwrap();
+            for &v in &values {
+                for &w in &values {
+                    let args: Vec<Argument> = (0..9)
+                        .map(|i| Argument::Integer(if i % 2 == 0 { v } else { w }))
+                        .collect();
+                    let mut out = Vec::new();
+                    program.exec_into(&args, &mut out).unwrap();
+                    assert_eq!(program.cost(&args), out.len(), "{:?} {:?}", field, args);
+                    assert!(program.min_cost() <= out.len(), "{:?} {:?}", field, args);
+                }
+            }
+            let typical = vec![Argument::Integer(10); 9];
+            assert_eq!(program.typical_cost(), program.cost(&typical));
+        }
+
+        let program = |src: &'static [u8]| Program::compile(src).unwrap();
+        assert_eq!(program(b"\x1b[%i%p1%d;%p2%dH").min_cost(), 6);
+        assert_eq!(program(b"\x1b[%i%p1%d;%p2%dH").typical_cost(), 8);
+        assert_eq!(program(b"%?%p1%tyes%eno%;").min_cost(), 2);
+        assert_eq!(program(b"%p1%.0d%p1%3s%p1%c").min_cost(), 4);
+        // one that fails to expand is never the cheapest
+        let args = [Argument::String("x".into())];
+        assert_eq!(program(b"abc%p1%d").cost(&args), usize::MAX);
+    }
 }
@@ -1,17 +1,49 @@
-use failure::ResultExt;
-use std::collections::VecDeque;
-use std::str;
+use std::borrow::Cow;
+use std::fmt;
 use std::str::FromStr;
+use std::str;
+use std::vec::Vec;
 use terminfo::errors::*;
 use terminfo::lang::printf::PrintfArgs;
-use terminfo::lang::Argument;
+use terminfo::lang::{escape, Argument};
+
+/// The longest source `Parser` accepts by default, see `Parser::max_len`. No real capability comes close.
+pub const MAX_PROGRAM_LEN: usize = 1 << 20;
 
 pub struct Parser<'a> {
     slice: &'a [u8],
-    buffer: VecDeque<Op<'a>>,
+    len: usize,
+    max_len: usize,
+    ops: Vec<Op<'a>>,
+    /// Where in the source each op came from.
+    offsets: Vec<usize>,
+    parsed: bool,
+    /// The index of the next op the iterator will return.
+    next: usize,
+    /// The conditionals that have been opened with `%?` but not closed yet, innermost last.
+    conditionals: Vec<Conditional>,
+}
+
+/// A `%?` without its `%;` yet.
+struct Conditional {
+    part: Part,
+    /// The placeholder for the `BranchFalse` at the last `%t`, it's filled in at the next `%e` or `%;`.
+    branch: usize,
+    /// The placeholders for the `Jump` at each `%e`, to after the `%;`.
+    end_jumps: Vec<usize>,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+enum Part {
+    /// Between `%?` (or an else-if's `%e`) and `%t`.
+    Condition,
+    /// Between `%t` and `%e` or `%;`.
+    Then,
+    /// Between `%e` and `%;`, or the `%t` of an else-if.
+    Else,
 }
 
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub enum Op<'a> {
     /// Push a user supplied argument onto the stack
     PushUserArg(usize),
@@ -40,67 +72,191 @@ pub enum Op<'a> {
     /// Pop the stack, if the result is a string push it's length, otherwise fail.
     StrLen,
 
-    /// Pop the stack, if the top value is non-empty string, a non-null char, or a non-zero number then jump
+    /// Pop the stack, if the top value is non-empty string, a non-null char, or a non-zero number then jump to the
+    /// op at this index
     BranchTrue(usize),
 
-    /// Pop the stack, if the top value is an empty string, a null char, or zero then jump
+    /// Pop the stack, if the top value is an empty string, a null char, or zero then jump to the op at this index
     BranchFalse(usize),
 
-    /// Ignore the next `x` ops
+    /// Continue from the op at this index, if it's the number of ops the program ends
     Jump(usize),
 
     /// Pop the stack and print
     Print(PrintfArgs),
 
-    /// Print a string literal
-    PrintSlice(&'a [u8]),
+    /// Print a string literal, borrowed from the source unless the op has been through `Op::into_owned`.
+    PrintSlice(Cow<'a, [u8]>),
+}
+
+impl<'a> Op<'a> {
+    /// Copy the literal text out of the source, so the op can outlive it.
+    pub fn into_owned(self) -> Op<'static> {
+        match self {
+            Op::PushUserArg(i) => Op::PushUserArg(i),
+            Op::Push(arg) => Op::Push(arg),
+            Op::NoOp => Op::NoOp,
+            Op::Add => Op::Add,
+            Op::Sub => Op::Sub,
+            Op::Mul => Op::Mul,
+            Op::Div => Op::Div,
+            Op::Mod => Op::Mod,
+            Op::BitAnd => Op::BitAnd,
+            Op::BitOr => Op::BitOr,
+            Op::BitXor => Op::BitXor,
+            Op::Less => Op::Less,
+            Op::Greater => Op::Greater,
+            Op::Equal => Op::Equal,
+            Op::Invert => Op::Invert,
+            Op::Not => Op::Not,
+            Op::IncrementArgs => Op::IncrementArgs,
+            Op::StrLen => Op::StrLen,
+            Op::BranchTrue(target) => Op::BranchTrue(target),
+            Op::BranchFalse(target) => Op::BranchFalse(target),
+            Op::Jump(target) => Op::Jump(target),
+            Op::Print(p) => Op::Print(p),
+            Op::PrintSlice(slice) => Op::PrintSlice(Cow::Owned(slice.into_owned())),
+        }
+    }
+}
+
+/// Shows the op as a short description, with arguments in `%p1` form and printf conversions as they'd be written in
+/// the capability string.
+impl<'a> fmt::Display for Op<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Op::PushUserArg(i) => write!(f, "push %p{}", i + 1),
+            Op::Push(ref arg) => write!(f, "push {}", arg),
+            Op::NoOp => write!(f, "nop"),
+            Op::Add => write!(f, "add"),
+            Op::Sub => write!(f, "subtract"),
+            Op::Mul => write!(f, "multiply"),
+            Op::Div => write!(f, "divide"),
+            Op::Mod => write!(f, "modulo"),
+            Op::BitAnd => write!(f, "bitwise and"),
+            Op::BitOr => write!(f, "bitwise or"),
+            Op::BitXor => write!(f, "bitwise xor"),
+            Op::Less => write!(f, "less than"),
+            Op::Greater => write!(f, "greater than"),
+            Op::Equal => write!(f, "equal"),
+            Op::Invert => write!(f, "invert"),
+            Op::Not => write!(f, "not"),
+            Op::IncrementArgs => write!(f, "increment %p1 and %p2"),
+            Op::StrLen => write!(f, "string length"),
+            Op::BranchTrue(target) => write!(f, "jump to {} if true", target),
+            Op::BranchFalse(target) => write!(f, "jump to {} if false", target),
+            Op::Jump(target) => write!(f, "jump to {}", target),
+            Op::Print(ref p) => write!(f, "print {}", p),
+            Op::PrintSlice(ref slice) => write!(f, "print \"{}\"", escape(slice)),
+        }
+    }
 }
 
 impl<'a> Parser<'a> {
-    pub fn new(src: &'a [u8]) -> Parser<'a> {
+    /// Parse `src`, anything that's a byte slice underneath, like a `[u8]`, `Vec<u8>` or `str`. Literal text in the
+    /// ops is borrowed from it rather than copied.
+    ///
+    /// ```
+    /// use nixterm::terminfo::lang::parser::Parser;
+    ///
+    /// let src = b"\x1b[%p1%dm".to_vec();
+    /// let mut parser = Parser::new(&src);
+    /// parser.parse().unwrap();
+    /// assert_eq!(parser.ops().len(), 4);
+    /// ```
+    pub fn new<S: AsRef<[u8]> + ?Sized>(src: &'a S) -> Parser<'a> {
+        let src = src.as_ref();
         Parser {
             slice: src,
-            buffer: VecDeque::with_capacity(4),
+            len: src.len(),
+            max_len: MAX_PROGRAM_LEN,
+            ops: Vec::new(),
+            offsets: Vec::new(),
+            parsed: false,
+            next: 0,
+            conditionals: Vec::new(),
         }
     }
 
+    /// Parse the text `src`, like a capability from a config file. Offsets (see `Parser::offset`) are in bytes of
+    /// `src`, so `&src[..parser.offset()]` is what parsed before an error.
+    ///
+    /// ```
+    /// use nixterm::terminfo::lang::parser::Parser;
+    ///
+    /// let src = "\x1b[%p1%dm%";
+    /// let mut parser = Parser::from_str(src);
+    /// assert!(parser.parse().is_err());
+    /// assert_eq!(&src[..parser.offset()], "\x1b[%p1%dm");
+    /// ```
+    // `FromStr` can't borrow from the string it's given
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(src: &'a str) -> Parser<'a> {
+        Parser::new(src)
+    }
+
+    /// Refuse to parse sources longer than `max_len` bytes, with `ErrorKind::ProgramTooLong`. The default is
+    /// `MAX_PROGRAM_LEN`.
+    pub fn max_len(mut self, max_len: usize) -> Parser<'a> {
+        self.max_len = max_len;
+        self
+    }
+
+    /// Parse the whole string, the ops are then available from `Parser::ops`.
+    ///
+    /// This takes time in proportion to the length of the source, however the conditionals in it are nested.
     pub fn parse(&mut self) -> Result<()> {
+        self.parsed = true;
+        if self.len > self.max_len {
+            return Err(ErrorKind::ProgramTooLong {
+                len: self.len,
+                max: self.max_len,
+            }.into());
+        }
         while self.slice.len() > 0 {
             self.next_instruction()?;
         }
+        if !self.conditionals.is_empty() {
+            // missing end of if-statement
+            return Err(ErrorKind::UnexpectedEof.into());
+        }
+
+        for op in &self.ops {
+            match *op {
+                Op::Jump(target) | Op::BranchFalse(target) | Op::BranchTrue(target)
+                    if target > self.ops.len() =>
+                {
+                    return Err(ErrorKind::InvalidJumpTarget(target).into())
+                }
+                _ => (),
+            }
+        }
         Ok(())
     }
 
-    fn add_instruction(&mut self, op: Op<'a>) {
-        self.buffer.push_back(op)
+    /// The ops parsed so far.
+    pub fn ops(&self) -> &[Op<'a>] {
+        &self.ops
     }
 
-    fn parse_until(&mut self, stop: &[u8]) -> Result<()> {
-        if self.slice[0] == b'%' {
-            for &c in stop {
-                if c == self.slice[1] {
-                    break;
-                }
-            }
-        }
+    pub fn into_ops(self) -> Vec<Op<'a>> {
+        self.ops
+    }
 
-        while self.slice.len() >= 2 {
-            // println!(
-            //     "{} ? {}",
-            //     self.slice.iter().map(|&c| c as char).collect::<String>(),
-            //     stop.iter().map(|&c| c as char).collect::<String>()
-            // );
-            if self.slice[0] == b'%' {
-                for &c in stop {
-                    if c == self.slice[1] {
-                        return Ok(());
-                    }
-                }
-            }
-            self.next_instruction()?;
-        }
+    /// The byte offset in the source each op came from, in the same order as `Parser::ops`.
+    pub fn op_offsets(&self) -> &[usize] {
+        &self.offsets
+    }
 
-        Err(ErrorKind::UnexpectedEof.into())
+    /// How many bytes of the source have been parsed. After `Parser::parse` fails this is where the instruction
+    /// it failed on starts.
+    pub fn offset(&self) -> usize {
+        self.len - self.slice.len()
+    }
+
+    fn add_instruction(&mut self, op: Op<'a>) {
+        self.offsets.push(self.offset());
+        self.ops.push(op)
     }
 
     /// Read up to the next instruction store it & exit.
@@ -112,7 +268,7 @@ impl<'a> Parser<'a> {
 
         if self.slice[0] != b'%' {
             let pos = self.slice.iter().take_while(|&&c| c != b'%').count();
-            self.add_instruction(Op::PrintSlice(&self.slice[..pos]));
+            self.add_instruction(Op::PrintSlice(Cow::Borrowed(&self.slice[..pos])));
             self.slice = &self.slice[pos..];
             return Ok(());
         }
@@ -126,7 +282,7 @@ impl<'a> Parser<'a> {
         let mut read = 2;
 
         match self.slice[1] {
-            b'%' => self.add_instruction(Op::PrintSlice(b"%")),
+            b'%' => self.add_instruction(Op::PrintSlice(Cow::Borrowed(b"%"))),
             b'p' => {
                 match self.slice.iter().skip(2).next() {
                     Some(i @ b'1'..=b'9') => {
@@ -180,74 +336,73 @@ impl<'a> Parser<'a> {
             b'>' => self.add_instruction(Op::Greater),
             b'~' => self.add_instruction(Op::Invert),
             b'!' => self.add_instruction(Op::Not),
-            b'?' => {
-                // add a placeholder for branch instruction, we will update it later
-                self.slice = &self.slice[read..];
-                self.parse_until(&[b't'])?;
-                let mut end_jumps = Vec::new();
-
-                while self.slice.len() > 1 && self.slice[1] == b't' {
-                    self.slice = &self.slice[2..];
-
-                    let branch_idx = self.buffer.len();
-                    self.add_instruction(Op::NoOp);
-                    self.parse_until(&[b'e', b';'])?;
-
-                    if self.slice.len() < 2 {
-                        // missing end of if-statement
-                        return Err(ErrorKind::UnexpectedEof.into());
-                    }
-
-                    if self.slice[1] == b'e' {
-                        // add a placeholder jump instruction, we will update it later
-                        end_jumps.push(self.buffer.len());
-                        self.add_instruction(Op::NoOp);
-                        self.buffer[branch_idx] =
-                            Op::BranchFalse(self.buffer.len() - 1 - branch_idx);
-
-                        self.slice = &self.slice[2..];
-                        self.parse_until(&[b';', b't'])?;
-                    } else {
-                        // if the condition fails jump to the after the %;
-                        self.buffer[branch_idx] =
-                            Op::BranchFalse(self.buffer.len() - 1 - branch_idx);
-                    }
+            b'?' => self.conditionals.push(Conditional {
+                part: Part::Condition,
+                branch: 0,
+                end_jumps: Vec::new(),
+            }),
+            b't' if self.in_part(&[Part::Condition, Part::Else]) => {
+                let branch = self.ops.len();
+                // a placeholder, filled in at the next %e or %;, at the start of what it skips
+                self.slice = &self.slice[2..];
+                read = 0;
+                self.add_instruction(Op::NoOp);
+                let cond = self.conditionals.last_mut().unwrap();
+                cond.part = Part::Then;
+                cond.branch = branch;
+            }
+            b'e' if self.in_part(&[Part::Then]) => {
+                let jump = self.ops.len();
+                self.add_instruction(Op::NoOp);
+                let cond = self.conditionals.last_mut().unwrap();
+                cond.end_jumps.push(jump);
+                cond.part = Part::Else;
+                self.ops[cond.branch] = Op::BranchFalse(self.ops.len());
+            }
+            b';' if self.in_part(&[Part::Then, Part::Else]) => {
+                let cond = self.conditionals.pop().unwrap();
+                if cond.part == Part::Then {
+                    // if the condition fails jump to after the %;
+                    self.ops[cond.branch] = Op::BranchFalse(self.ops.len());
                 }
-                for j in end_jumps {
-                    self.buffer[j] = Op::Jump(self.buffer.len() - j - 1);
+                for j in cond.end_jumps {
+                    self.ops[j] = Op::Jump(self.ops.len());
                 }
-                read = 2;
             }
             _ => {
-                self.add_instruction(Op::Print(PrintfArgs::parse(&self.slice[1..])?));
-                read += self.slice
-                    .iter()
-                    .skip(1)
-                    .take_while(|&&c| {
-                        c != b'x' && c != b'X' && c != b'c' && c != b'd' && c != b'o' && c != b's'
-                    })
-                    .count();
+                let (spec, len) = PrintfArgs::parse(&self.slice[1..])?;
+                self.add_instruction(Op::Print(spec));
+                read = 1 + len;
             }
         };
 
         self.slice = &self.slice[read..];
         Ok(())
     }
+
+    /// True if the innermost open conditional is in one of `parts`.
+    fn in_part(&self, parts: &[Part]) -> bool {
+        self.conditionals
+            .last()
+            .is_some_and(|cond| parts.contains(&cond.part))
+    }
 }
 
+/// Iterating parses the whole string first, then yields its ops, if parsing fails the only item is the error.
 impl<'a> Iterator for Parser<'a> {
     type Item = Result<Op<'a>>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        match self.buffer.pop_front() {
-            Some(v) => Some(Ok(v)),
-            None => match self.next_instruction() {
-                Ok(_) => Some(Ok(match self.buffer.pop_front() {
-                    Some(v) => v,
-                    None => return None,
-                })),
-                Err(e) => return Some(Err(e)),
-            },
+        if !self.parsed {
+            if let Err(e) = self.parse() {
+                self.ops.clear();
+                self.offsets.clear();
+                return Some(Err(e));
+            }
         }
+
+        let op = self.ops.get(self.next).cloned();
+        self.next += 1;
+        op.map(Ok)
     }
 }
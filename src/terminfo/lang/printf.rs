@@ -1,9 +1,8 @@
-use failure::ResultExt;
-use std::io;
+use std::{fmt, io};
 use terminfo::errors::*;
 use terminfo::lang::Argument;
 
-const NULL: &'static [u8] = &[b'(', b'n', b'u', b'l', b'l', b')'];
+pub(crate) const NULL: &[u8] = &[b'(', b'n', b'u', b'l', b'l', b')'];
 const NUM_CHARS: [u8; 16] = [
     b'0', b'1', b'2', b'3', b'4', b'5', b'6', b'7', b'8', b'9', b'a', b'b', b'c', b'd', b'e', b'f',
 ];
@@ -12,33 +11,65 @@ const UPPERCASE_NUM_CHARS: [u8; 16] = [
     b'0', b'1', b'2', b'3', b'4', b'5', b'6', b'7', b'8', b'9', b'A', b'B', b'C', b'D', b'E', b'F',
 ];
 
-#[derive(Debug, Clone, Default, Eq, PartialEq)]
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
 pub struct PrintfArgs {
     pub left_align: bool,
     pub show_sign: bool,
     pub pad_sign: bool,
     pub alt: bool,
+    /// Pad numbers to `width` with zeros instead of spaces (the `0` in `%02x`).
+    pub zero_pad: bool,
     pub width: Option<usize>,
     pub prec: Option<usize>,
     pub character: char,
 }
 
+/// Writes the conversion back the way a capability string would have it, like `%:-5d`.
+impl fmt::Display for PrintfArgs {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "%")?;
+        if self.left_align || self.show_sign || self.pad_sign || self.alt {
+            write!(f, ":")?;
+        }
+        for &(set, flag) in &[
+            (self.left_align, '-'),
+            (self.show_sign, '+'),
+            (self.pad_sign, ' '),
+            (self.alt, '#'),
+            (self.zero_pad, '0'),
+        ] {
+            if set {
+                write!(f, "{}", flag)?;
+            }
+        }
+        if let Some(width) = self.width {
+            write!(f, "{}", width)?;
+        }
+        if let Some(prec) = self.prec {
+            write!(f, ".{}", prec)?;
+        }
+        write!(f, "{}", self.character)
+    }
+}
+
 impl PrintfArgs {
-    pub fn parse(src: &[u8]) -> Result<PrintfArgs> {
+    /// Parse the conversion at the start of `src`, which is everything after the `%`, and how many bytes it took up,
+    /// with the conversion character. Anything after that is left alone.
+    pub fn parse(src: &[u8]) -> Result<(PrintfArgs, usize)> {
         let mut spec = PrintfArgs::default();
 
         if src.len() < 1 {
             return Err(ErrorKind::BadPrintfSpecifier.into());
         }
 
-        match src[0] {
+        let read = match src[0] {
             // flags are prefixed with a `:`
-            b':' => spec.parse_flags(&src[1..])?,
+            b':' => 1 + spec.parse_flags(&src[1..])?,
             b'0'..=b'9' | b'.' => spec.parse_width(src)?,
             _ => spec.parse_specifier(src)?,
-        }
+        };
 
-        Ok(spec)
+        Ok((spec, read))
     }
 
     fn pad<W: io::Write>(&self, w: &mut W, buf: &[u8]) -> Result<usize> {
@@ -65,6 +96,32 @@ impl PrintfArgs {
 
         Ok(written)
     }
+    /// The most this conversion can print, or `None` for a string with no precision, which could be any length.
+    pub fn max_len(&self) -> Option<usize> {
+        let len = match self.character {
+            // an `i64` is at most 19 decimal digits, 16 hex digits or 22 octal ones, after a sign and a `0`/`0x`
+            'd' => 1 + 19,
+            'x' | 'X' => 1 + 2 + 16,
+            'o' => 1 + 1 + 22,
+            'c' => 1,
+            _ => return Some(self.prec?.max(self.width.unwrap_or(0)).max(NULL.len())),
+        };
+        // a precision can ask for more digits than the number has
+        let len = len + self.prec.unwrap_or(0);
+        // with nothing to print "(null)" is printed instead
+        Some(len.max(self.width.unwrap_or(0)).max(NULL.len()))
+    }
+
+    /// The least this conversion can print, like an empty string or a number with no digits for `%.0d`.
+    pub fn min_len(&self) -> usize {
+        let len = match self.character {
+            'd' | 'x' | 'X' | 'o' => self.prec.unwrap_or(1),
+            'c' => 1,
+            _ => 0,
+        };
+        len.max(self.width.unwrap_or(0))
+    }
+
     pub fn write_number<W: io::Write>(&self, w: &mut W, num: i64) -> Result<usize> {
         let (radix, uppercase) = match self.character {
             'x' => (16, false),
@@ -72,7 +129,8 @@ impl PrintfArgs {
             'o' => (8, false),
             'd' => (10, false),
             's' => return Err(ErrorKind::UnexpectedArgumentType("string", "integer").into()),
-            'c' => return Err(ErrorKind::UnexpectedArgumentType("char", "integer").into()),
+            // like C's printf, `%c` of a number is the character with that code (as in `rep`)
+            'c' => return self.pad(w, &[num as u8]),
             _ => return Err(ErrorKind::UnexpectedArgumentType("", "integer").into()),
         };
         let mut num_buf = [0u8; 22];
@@ -122,14 +180,39 @@ impl PrintfArgs {
             num_buf_len += 1;
         }
 
+        // zero still has one digit, unless the precision asks for none
+        if num_buf_len == prefix_len && self.prec != Some(0) {
+            num_buf[num_buf_len] = b'0';
+            num_buf_len += 1;
+        }
+
         num_buf[prefix_len..num_buf_len].reverse();
 
-        if let Some(prec) = self.prec {
-            if num_buf_len - prefix_len > prec {
-                num_buf_len = prec + prefix_len;
-            }
+        // like C, the precision is the fewest digits to print, and `0` pads the number out to its width (unless
+        // there's a precision), both with zeros between the sign and the digits
+        let digits = num_buf_len - prefix_len;
+        let mut zeros = self.prec.map_or(0, |prec| prec.saturating_sub(digits));
+        if self.zero_pad && !self.left_align && self.prec.is_none() {
+            zeros = self.width.map_or(0, |width| width.saturating_sub(num_buf_len));
+        }
+        if zeros == 0 {
+            return self.pad(w, &num_buf[..num_buf_len]);
+        }
+
+        let len = num_buf_len + zeros;
+        let spaces = self.width.map_or(0, |width| width.saturating_sub(len));
+        if !self.left_align {
+            write_repeated(w, b' ', spaces)?;
+        }
+        w.write_all(&num_buf[..prefix_len])
+            .context(ErrorKind::FailedToWriteArgument)?;
+        write_repeated(w, b'0', zeros)?;
+        w.write_all(&num_buf[prefix_len..num_buf_len])
+            .context(ErrorKind::FailedToWriteArgument)?;
+        if self.left_align {
+            write_repeated(w, b' ', spaces)?;
         }
-        self.pad(w, &num_buf[..num_buf_len])
+        Ok(len + spaces)
     }
 
     pub fn write_string<W: io::Write>(&self, w: &mut W, s: &str) -> Result<usize> {
@@ -149,7 +232,19 @@ impl PrintfArgs {
             }
         }
 
-        self.pad(w, s[..slen].as_bytes())
+        // like C, the precision counts bytes, but it never cuts a character in half
+        while !s.is_char_boundary(slen) {
+            slen -= 1;
+        }
+        self.pad(w, &s.as_bytes()[..slen])
+    }
+
+    /// Print a parameter that wasn't given, as an empty string for `%s` and 0 for everything else, like ncurses.
+    pub fn write_missing<W: io::Write>(&self, w: &mut W) -> Result<usize> {
+        match self.character {
+            's' => self.write_string(w, ""),
+            _ => self.write_number(w, 0),
+        }
     }
 
     pub fn write_char<W: io::Write>(&self, w: &mut W, c: u8) -> Result<usize> {
@@ -173,11 +268,15 @@ impl PrintfArgs {
             Some(Argument::Integer(x)) => self.write_number(w, x)?,
             Some(Argument::String(s)) => self.write_string(w, &s)?,
             Some(Argument::Char(c)) => self.write_char(w, c)?,
-            None => w.write(NULL).context(ErrorKind::FailedToWriteArgument)?,
+            Some(Argument::Missing) => self.write_missing(w)?,
+            None => {
+                w.write_all(NULL).context(ErrorKind::FailedToWriteArgument)?;
+                NULL.len()
+            }
         })
     }
 
-    fn parse_specifier(&mut self, src: &[u8]) -> Result<()> {
+    fn parse_specifier(&mut self, src: &[u8]) -> Result<usize> {
         match src.iter().nth(0) {
             Some(b'x') => self.character = 'x',
             Some(b'o') => self.character = 'o',
@@ -187,10 +286,10 @@ impl PrintfArgs {
             Some(b'c') => self.character = 'c',
             _ => return Err(ErrorKind::BadPrintfSpecifier.into()),
         };
-        Ok(())
+        Ok(1)
     }
 
-    fn parse_flags(&mut self, src: &[u8]) -> Result<()> {
+    fn parse_flags(&mut self, src: &[u8]) -> Result<usize> {
         let flags = src.iter()
             .take_while(|&&c| c == b'+' || c == b'-' || c == b'#' || c == b' ')
             .fold(0, |x, flag| {
@@ -204,10 +303,13 @@ impl PrintfArgs {
                 x + 1
             });
 
-        self.parse_width(&src[flags..])
+        Ok(flags + self.parse_width(&src[flags..])?)
     }
 
-    fn parse_width(&mut self, src: &[u8]) -> Result<()> {
+    fn parse_width(&mut self, src: &[u8]) -> Result<usize> {
+        if src.first() == Some(&b'0') {
+            self.zero_pad = true;
+        }
         let width_width = src.iter().take_while(|&&c| c >= b'0' && c <= b'9').count();
 
         if width_width > 0 {
@@ -227,24 +329,31 @@ impl PrintfArgs {
                         .context(ErrorKind::BadPrecisionSpecified)?,
                 );
 
-                self.parse_specifier(&src[prec_width + 1 + width_width..])
+                let read = width_width + 1 + prec_width;
+                Ok(read + self.parse_specifier(&src[read..])?)
             } else {
                 Err(ErrorKind::BadPrecisionSpecified.into())
             }
         } else {
-            self.parse_specifier(&src[width_width..])
+            Ok(width_width + self.parse_specifier(&src[width_width..])?)
         }
     }
 }
 
 fn parse_usize(s: &[u8]) -> Result<usize> {
-    s.iter()
-        .try_fold((0_usize, 1_usize), |(num, pow), &c| {
-            if c >= b'0' && c <= b'9' {
-                Ok((num + (((c - b'0') as usize) * pow), pow * 10))
-            } else {
-                Err(ErrorKind::InvalidDigit(c).into())
-            }
-        })
-        .map(|(num, _)| num)
+    s.iter().try_fold(0_usize, |num, &c| {
+        if c.is_ascii_digit() {
+            Ok(num * 10 + (c - b'0') as usize)
+        } else {
+            Err(ErrorKind::InvalidDigit(c).into())
+        }
+    })
+}
+
+fn write_repeated<W: io::Write>(w: &mut W, byte: u8, n: usize) -> Result<()> {
+    for _ in 0..n {
+        w.write_all(&[byte])
+            .context(ErrorKind::FailedToWriteArgument)?;
+    }
+    Ok(())
 }
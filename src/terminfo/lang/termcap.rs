@@ -0,0 +1,186 @@
+//! Translating termcap's `%` codes into ops, see `from_termcap`.
+use std::borrow::Cow;
+use std::vec::Vec;
+use terminfo::errors::*;
+use terminfo::lang::parser::Op;
+use terminfo::lang::printf::PrintfArgs;
+use terminfo::lang::Argument;
+
+/// Translate a capability string written in termcap's `%` syntax, like `\E[%i%d;%dH`, into the ops terminfo's
+/// syntax would have for it. A `Program` can be made from them with `Program::from_termcap`.
+///
+/// Termcap codes don't name their parameters, each one that prints something takes the next one. These are
+/// understood:
+///
+/// - `%d`, `%2` and `%3` print the parameter in decimal, the last two padded to 2 or 3 columns with spaces.
+/// - `%.` prints the parameter as a byte, and `%+x` prints it plus the byte `x`.
+/// - `%>xy` adds `y` to the parameter if it's greater than `x`, `%B` turns it into BCD, and `%D` does the "reverse
+///   coding" of the Delta Data terminals, `p - 2 * (p % 16)`. None of them print anything, they change what the next
+///   code prints.
+/// - `%r` swaps the first two parameters, `%i` adds one to both of them, and `%%` prints a `%`.
+///
+/// The `%` codes only some termcaps had (like GNU's `%a` and `%n`) fail with `ErrorKind::UntranslatableTermcap`, as
+/// does changing a parameter that's already been changed, like `%>xy%B`. Escapes like `\E` aren't `%` codes, they
+/// should already be decoded.
+pub fn from_termcap(src: &[u8]) -> Result<Vec<Op<'_>>> {
+    let mut ops = Vec::new();
+    let mut next = 0;
+    let mut swapped = false;
+    // true if the next parameter has already been pushed, by a code that changes it
+    let mut pushed = false;
+    let mut slice = src;
+
+    while !slice.is_empty() {
+        if slice[0] != b'%' {
+            let len = slice.iter().take_while(|&&c| c != b'%').count();
+            ops.push(Op::PrintSlice(Cow::Borrowed(&slice[..len])));
+            slice = &slice[len..];
+            continue;
+        }
+
+        let code = *slice.get(1).ok_or(ErrorKind::UnexpectedEof)?;
+        // the bytes after the code that it takes, like the `x` in `%+x`
+        let operands = match code {
+            b'+' => 1,
+            b'>' => 2,
+            _ => 0,
+        };
+        if slice.len() < 2 + operands {
+            return Err(ErrorKind::UnexpectedEof.into());
+        }
+        let operand = |i: usize| Op::Push(Argument::Integer(i64::from(slice[2 + i])));
+
+        // the parameter a code takes, `%r` swaps the first two
+        let param = if swapped && next < 2 { 1 - next } else { next };
+        if param >= 9 && b"d23.+>BD".contains(&code) {
+            return Err(ErrorKind::InvalidArgumentIdentifier.into());
+        }
+
+        match code {
+            b'%' => ops.push(Op::PrintSlice(Cow::Borrowed(b"%"))),
+            b'i' => ops.push(Op::IncrementArgs),
+            b'r' => swapped = true,
+            b'd' | b'2' | b'3' | b'.' | b'+' => {
+                if !pushed {
+                    ops.push(Op::PushUserArg(param));
+                }
+                let spec = match code {
+                    b'2' => &b"2d"[..],
+                    b'3' => b"3d",
+                    b'd' => b"d",
+                    _ => b"c",
+                };
+                if code == b'+' {
+                    ops.push(operand(0));
+                    ops.push(Op::Add);
+                }
+                ops.push(Op::Print(PrintfArgs::parse(spec)?.0));
+                pushed = false;
+                next += 1;
+            }
+            b'>' | b'B' | b'D' if !pushed => {
+                let p = Op::PushUserArg(param);
+                let ten = Op::Push(Argument::Integer(10));
+                let sixteen = Op::Push(Argument::Integer(16));
+                match code {
+                    b'>' => {
+                        let end = ops.len() + 7;
+                        ops.extend_from_slice(&[
+                            p.clone(),
+                            p,
+                            operand(0),
+                            Op::Greater,
+                            Op::BranchFalse(end),
+                        ]);
+                        ops.extend_from_slice(&[operand(1), Op::Add]);
+                    }
+                    b'B' => ops.extend_from_slice(&[
+                        p.clone(),
+                        ten.clone(),
+                        Op::Div,
+                        sixteen,
+                        Op::Mul,
+                        p,
+                        ten,
+                        Op::Mod,
+                        Op::Add,
+                    ]),
+                    _ => ops.extend_from_slice(&[
+                        p.clone(),
+                        p,
+                        sixteen,
+                        Op::Mod,
+                        Op::Push(Argument::Integer(2)),
+                        Op::Mul,
+                        Op::Sub,
+                    ]),
+                }
+                pushed = true;
+            }
+            _ => return Err(ErrorKind::UntranslatableTermcap(code).into()),
+        }
+        slice = &slice[2 + operands..];
+    }
+
+    if pushed {
+        // changed, but never printed
+        return Err(ErrorKind::UnexpectedEof.into());
+    }
+    Ok(ops)
+}
+
+#[cfg(test)]
+mod test {
+    use terminfo::errors::ErrorKind;
+    use terminfo::lang::*;
+
+    fn expand(src: &[u8], args: &[i64]) -> Vec<u8> {
+        let args: Vec<Argument> = args.iter().map(|&x| Argument::from(x)).collect();
+        let mut buf = Vec::new();
+        Program::from_termcap(src)
+            .unwrap()
+            .exec_append(&args, &mut buf)
+            .unwrap();
+        buf
+    }
+
+    #[test]
+    fn vt100() {
+        // cm, rows then columns, both counted from 1
+        assert_eq!(expand(b"\x1b[%i%d;%dH", &[2, 5]), b"\x1b[3;6H");
+        // the same as `cup`
+        let cup = Program::compile(b"\x1b[%i%p1%d;%p2%dH").unwrap();
+        let mut buf = Vec::new();
+        cup.exec_append(&[Argument::from(2), Argument::from(5)], &mut buf)
+            .unwrap();
+        assert_eq!(buf, expand(b"\x1b[%i%d;%dH", &[2, 5]));
+        assert_eq!(expand(b"\x1b[%dA", &[12]), b"\x1b[12A");
+    }
+
+    #[test]
+    fn reversed() {
+        // the HP 2621's cm takes the column first
+        assert_eq!(expand(b"\x1b&a%r%dc%dY", &[2, 5]), b"\x1b&a5c2Y");
+        assert_eq!(expand(b"\x1b&a%r%i%2c%3Y", &[2, 5]), b"\x1b&a 6c  3Y");
+    }
+
+    #[test]
+    fn codes() {
+        // the ADM-3A's cm, an offset byte for each
+        assert_eq!(expand(b"\x1b=%+ %+ ", &[2, 5]), b"\x1b=\"%");
+        assert_eq!(expand(b"%.%%", &[65]), b"A%");
+        assert_eq!(expand(b"%>\x05\x10%.%>\x05\x10%.", &[4, 6]), b"\x04\x16");
+        assert_eq!(expand(b"%B%d", &[42]), b"66");
+        assert_eq!(expand(b"%D%d", &[0x25]), b"27");
+        assert_eq!(expand(b"%>\x05\x10%+ ", &[6]), b"6");
+    }
+
+    #[test]
+    fn untranslatable() {
+        let kind = |src| from_termcap(src).unwrap_err().kind().clone();
+        assert_eq!(kind(b"%n%d"), ErrorKind::UntranslatableTermcap(b'n'));
+        assert_eq!(kind(b"%B%B%d"), ErrorKind::UntranslatableTermcap(b'B'));
+        assert_eq!(kind(b"%+"), ErrorKind::UnexpectedEof);
+        assert_eq!(kind(b"%B"), ErrorKind::UnexpectedEof);
+    }
+}
@@ -0,0 +1,309 @@
+//! Stepping through a capability string, to see why it expands the way it does.
+use std::fmt;
+use std::mem;
+use std::string::{String, ToString};
+use std::vec::Vec;
+use terminfo::errors::*;
+use terminfo::lang::executor::{Observer, Value};
+use terminfo::lang::parser::Op;
+use terminfo::lang::{escape, Argument, Program};
+
+/// One op a traced program ran.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Step<'a> {
+    /// The op's index in the program.
+    pub ip: usize,
+    pub op: Op<'a>,
+    /// The stack before the op ran, bottom first.
+    pub stack_before: Vec<Argument>,
+    /// The stack after the op ran, bottom first.
+    pub stack_after: Vec<Argument>,
+    /// The index of the op that ran next, or the program's length if this was the last one.
+    pub next: usize,
+    /// Everything the program had written once the op was done.
+    pub output: Vec<u8>,
+}
+
+impl<'a> Step<'a> {
+    /// If the op was a branch, whether it jumped.
+    pub fn branched(&self) -> Option<bool> {
+        match self.op {
+            Op::BranchTrue(target) | Op::BranchFalse(target) => Some(self.next == target),
+            _ => None,
+        }
+    }
+}
+
+/// The op a traced program failed on.
+#[derive(Debug)]
+struct Failure<'a> {
+    ip: usize,
+    op: Op<'a>,
+    stack: Vec<Argument>,
+    error: Error,
+}
+
+/// Everything a program did while expanding, from `trace_exec`.
+///
+/// Displaying it gives a table with a row for every step, then the output or the error, like
+///
+/// ```text
+/// ip  op                     stack before  stack after  next  output
+/// 0   print "\E["            []            []           1     \E[
+/// 1   increment %p1 and %p2  []            []           2     \E[
+/// 2   push %p1               []            [6]          3     \E[
+/// 3   print %d               [6]           []           4     \E[6
+/// ...
+/// output: \E[6;11H
+/// ```
+#[derive(Debug)]
+pub struct ExecTrace<'a> {
+    steps: Vec<Step<'a>>,
+    output: Vec<u8>,
+    failure: Option<Failure<'a>>,
+}
+
+impl<'a> ExecTrace<'a> {
+    /// Every op that ran, in the order they ran in. If the program failed the op it failed on isn't included.
+    pub fn steps(&self) -> &[Step<'a>] {
+        &self.steps
+    }
+
+    /// What the program wrote, up to where it failed if it did.
+    pub fn output(&self) -> &[u8] {
+        &self.output
+    }
+
+    /// Why the program failed, if it did.
+    pub fn error(&self) -> Option<&Error> {
+        self.failure.as_ref().map(|f| &f.error)
+    }
+
+    /// The index of the op the program failed on, if it did.
+    pub fn failed_at(&self) -> Option<usize> {
+        self.failure.as_ref().map(|f| f.ip)
+    }
+}
+
+fn stack(values: &[Argument]) -> String {
+    let values: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+    ["[", &values.join(", "), "]"].concat()
+}
+
+impl<'a> fmt::Display for ExecTrace<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut rows: Vec<[String; 6]> = Vec::new();
+        rows.push([
+            "ip".into(),
+            "op".into(),
+            "stack before".into(),
+            "stack after".into(),
+            "next".into(),
+            "output".into(),
+        ]);
+        for step in &self.steps {
+            let next = match step.branched() {
+                Some(true) => [&step.next.to_string(), " (taken)"].concat(),
+                Some(false) => [&step.next.to_string(), " (not taken)"].concat(),
+                None => step.next.to_string(),
+            };
+            rows.push([
+                step.ip.to_string(),
+                step.op.to_string(),
+                stack(&step.stack_before),
+                stack(&step.stack_after),
+                next,
+                escape(&step.output),
+            ]);
+        }
+        if let Some(ref failure) = self.failure {
+            rows.push([
+                failure.ip.to_string(),
+                failure.op.to_string(),
+                stack(&failure.stack),
+                String::new(),
+                String::new(),
+                String::new(),
+            ]);
+        }
+
+        let mut widths = [0; 6];
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row.iter()) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+        for row in &rows {
+            let mut line = String::new();
+            for (i, cell) in row.iter().enumerate() {
+                line.push_str(cell);
+                if i + 1 < row.len() {
+                    line.extend((cell.chars().count()..widths[i] + 2).map(|_| ' '));
+                }
+            }
+            writeln!(f, "{}", line.trim_end())?;
+        }
+
+        match self.failure {
+            Some(ref failure) => write!(f, "error at {}: {}", failure.ip, failure.error),
+            None => write!(f, "output: {}", escape(&self.output)),
+        }
+    }
+}
+
+/// Records every step of a program.
+struct Tracer<'p, 'a: 'p> {
+    ops: &'p [Op<'a>],
+    steps: Vec<Step<'a>>,
+    /// The stack before the op that's running.
+    before: Vec<Argument>,
+}
+
+impl<'p, 'a> Observer<Vec<u8>> for Tracer<'p, 'a> {
+    fn before(&mut self, _ip: usize, stack: &[Value]) {
+        self.before = stack.iter().map(|v| v.to_argument()).collect();
+    }
+
+    fn after(&mut self, ip: usize, stack: &[Value], next: usize, w: &Vec<u8>) {
+        self.steps.push(Step {
+            ip,
+            op: self.ops[ip].clone(),
+            stack_before: mem::take(&mut self.before),
+            stack_after: stack.iter().map(|v| v.to_argument()).collect(),
+            next,
+            output: w.clone(),
+        });
+    }
+}
+
+/// Expand `program` with `args`, recording every op it runs: the stack before and after, where it went next (so
+/// which way each branch went), and everything written so far.
+///
+/// This runs exactly the same way as `Program::exec_into`, it's for finding out why a capability expanded to
+/// something unexpected. A program that fails is traced up to the op it failed on.
+///
+/// ```
+/// use nixterm::terminfo::lang::{trace_exec, Argument, Program};
+///
+/// let program = Program::compile(b"\x1b[%i%p1%d;%p2%dH").unwrap();
+/// let trace = trace_exec(&program, &[Argument::Integer(5), Argument::Integer(10)]);
+/// assert_eq!(trace.output(), b"\x1b[6;11H");
+/// println!("{}", trace);
+/// ```
+pub fn trace_exec<'a>(program: &Program<'a>, args: &[Argument]) -> ExecTrace<'a> {
+    let mut tracer = Tracer {
+        ops: program.ops(),
+        steps: Vec::new(),
+        before: Vec::new(),
+    };
+    let mut output = Vec::new();
+    let failure = match program.run(args, &mut output, &mut tracer) {
+        Ok(()) => None,
+        Err(error) => {
+            let ip = tracer.steps.last().map_or(0, |step| step.next);
+            Some(Failure {
+                ip,
+                op: program.ops()[ip].clone(),
+                stack: tracer.before,
+                error,
+            })
+        }
+    };
+    ExecTrace {
+        steps: tracer.steps,
+        output,
+        failure,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use terminfo::lang::*;
+
+    fn trace(src: &[u8], args: &[i64]) -> String {
+        let args: Vec<Argument> = args.iter().map(|&n| Argument::Integer(n)).collect();
+        trace_exec(&Program::compile(src).unwrap(), &args).to_string()
+    }
+
+    #[test]
+    fn cup() {
+        assert_eq!(
+            trace(b"\x1b[%i%p1%d;%p2%dH", &[5, 10]),
+            "\
+ip  op                     stack before  stack after  next  output
+0   print \"\\E[\"            []            []           1     \\E[
+1   increment %p1 and %p2  []            []           2     \\E[
+2   push %p1               []            [6]          3     \\E[
+3   print %d               [6]           []           4     \\E[6
+4   print \";\"              []            []           5     \\E[6;
+5   push %p2               []            [11]         6     \\E[6;
+6   print %d               [11]          []           7     \\E[6;11
+7   print \"H\"              []            []           8     \\E[6;11H
+output: \\E[6;11H"
+        );
+    }
+
+    #[test]
+    fn setaf() {
+        let setaf = b"\x1b[%?%p1%{8}%<%t3%p1%d%e%p1%{16}%<%t9%p1%{8}%-%d%e38;5;%p1%d%;m";
+        assert_eq!(
+            trace(setaf, &[1]),
+            "\
+ip  op                  stack before  stack after  next           output
+0   print \"\\E[\"         []            []           1              \\E[
+1   push %p1            []            [1]          2              \\E[
+2   push 8              [1]           [1, 8]       3              \\E[
+3   less than           [1, 8]        [1]          4              \\E[
+4   jump to 9 if false  [1]           []           5 (not taken)  \\E[
+5   print \"3\"           []            []           6              \\E[3
+6   push %p1            []            [1]          7              \\E[3
+7   print %d            [1]           []           8              \\E[31
+8   jump to 22          []            []           22             \\E[31
+22  print \"m\"           []            []           23             \\E[31m
+output: \\E[31m"
+        );
+        assert_eq!(
+            trace(setaf, &[200]),
+            "\
+ip  op                   stack before  stack after  next        output
+0   print \"\\E[\"          []            []           1           \\E[
+1   push %p1             []            [200]        2           \\E[
+2   push 8               [200]         [200, 8]     3           \\E[
+3   less than            [200, 8]      [0]          4           \\E[
+4   jump to 9 if false   [0]           []           9 (taken)   \\E[
+9   push %p1             []            [200]        10          \\E[
+10  push 16              [200]         [200, 16]    11          \\E[
+11  less than            [200, 16]     [0]          12          \\E[
+12  jump to 19 if false  [0]           []           19 (taken)  \\E[
+19  print \"38;5;\"        []            []           20          \\E[38;5;
+20  push %p1             []            [200]        21          \\E[38;5;
+21  print %d             [200]         []           22          \\E[38;5;200
+22  print \"m\"            []            []           23          \\E[38;5;200m
+output: \\E[38;5;200m"
+        );
+
+        let program = Program::compile(setaf).unwrap();
+        let trace = trace_exec(&program, &[Argument::Integer(200)]);
+        let branches: Vec<_> = trace.steps().iter().filter_map(|s| s.branched()).collect();
+        assert_eq!(branches, vec![true, true]);
+        assert_eq!(trace.steps()[3].stack_after, vec![Argument::Integer(0)]);
+        assert_eq!(trace.output(), b"\x1b[38;5;200m");
+        assert!(trace.error().is_none());
+    }
+
+    #[test]
+    fn failure() {
+        let program = Program::compile(b"ab%p1%lc").unwrap();
+        let trace = trace_exec(&program, &[Argument::Integer(3)]);
+        assert_eq!(trace.failed_at(), Some(2));
+        assert_eq!(trace.steps().len(), 2);
+        assert_eq!(trace.output(), b"ab");
+        assert_eq!(
+            trace.to_string().lines().rev().nth(1),
+            Some("2   string length  [3]")
+        );
+        assert!(trace
+            .to_string()
+            .ends_with(&format!("error at 2: {}", trace.error().unwrap())));
+    }
+}
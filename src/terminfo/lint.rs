@@ -0,0 +1,366 @@
+//! Finding mistakes in an entry's capability strings.
+//!
+//! Broken strings usually go unnoticed until something expands them, then they fail halfway through or print
+//! garbage. `check_entry` parses every string capability up front, and reports the ones that can't be parsed, the
+//! ones that use parameters their capability doesn't take, and constructs that are probably mistakes. Each `Lint`
+//! displays like a compiler's diagnostic, pointing at where in the string the problem is.
+//!
+//! ```no_run
+//! use std::fs;
+//! use nixterm::terminfo::{lint, TermInfo};
+//!
+//! let data = fs::read("/usr/share/terminfo/x/xterm").unwrap();
+//! for lint in lint::check_entry(&TermInfo::parse(&data).unwrap()) {
+//!     eprintln!("{}", lint);
+//! }
+//! ```
+use std::fmt::{self, Write};
+use std::mem;
+use std::string::String;
+use std::vec::Vec;
+use terminfo::errors::ErrorKind;
+use terminfo::fields::StringField::*;
+use terminfo::fields::{StringField, PREDEFINED_STRINGS_COUNT};
+use terminfo::lang::parser::{Op, Parser};
+use terminfo::lang::{escape, Argument};
+use terminfo::signature::signature;
+use terminfo::TermInfo;
+
+/// How bad a `Lint` is.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Severity {
+    /// The string works, but probably doesn't do what was meant.
+    Warning,
+    /// The string can't be expanded, or can't be expanded with the arguments its capability is given.
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A problem with one capability string.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Lint {
+    pub severity: Severity,
+    /// The capability's terminfo name, like `cup`, or its name if it's an extended capability.
+    pub cap: String,
+    /// The capability's string.
+    pub value: Vec<u8>,
+    /// Where in `value` the problem is, if it's anywhere in particular.
+    pub offset: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for Lint {
+    /// Shows the message, then the capability with a caret under the problem, like
+    ///
+    /// ```text
+    /// error: cup: takes 2 parameters, but uses %p3
+    ///   | cup=\E[%i%p1%d;%p2%d;%p3%dH
+    ///   |                      ^
+    /// ```
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}: {}", self.severity, self.cap, self.message)?;
+        if let Some(offset) = self.offset {
+            let column = self.cap.len() + 1 + escape(&self.value[..offset]).len();
+            write!(f, "\n  | {}={}", self.cap, escape(&self.value))?;
+            write!(f, "\n  | {:1$}^", "", column)?;
+        }
+        Ok(())
+    }
+}
+
+/// Check every string capability in `info`, standard and extended.
+pub fn check_entry(info: &TermInfo) -> Vec<Lint> {
+    let mut lints = Vec::new();
+    for i in 0..PREDEFINED_STRINGS_COUNT {
+        // `StringField` is `repr(usize)`, with a variant for every index below the count
+        let field = unsafe { mem::transmute::<usize, StringField>(i) };
+        match field {
+            // these describe the terminal's replies, in scanf's syntax rather than tparm's
+            User6 | User7 | User8 | User9 => continue,
+            // and these are never expanded, a `%` in them is just a `%`
+            AcsChars => continue,
+            _ if field.info().variable_name.starts_with("key_") => continue,
+            _ => (),
+        }
+        if let Some(value) = info.string_bytes(field) {
+            check(field.info().terminfo_name, Some(field), value, &mut lints);
+        }
+    }
+    // extended capabilities starting with a `k` are keys too
+    for (name, value) in info.ext_strings() {
+        if name.starts_with('k') {
+            continue;
+        }
+        check(name, None, value, &mut lints);
+    }
+    lints
+}
+
+/// Check one capability, `field` is the standard capability it is, if it is one.
+fn check(name: &str, field: Option<StringField>, value: &[u8], lints: &mut Vec<Lint>) {
+    let mut lint = |severity, offset, message| {
+        lints.push(Lint {
+            severity,
+            cap: name.into(),
+            value: value.into(),
+            offset: Some(offset),
+            message,
+        })
+    };
+
+    let mut parser = Parser::new(value);
+    if let Err(e) = parser.parse() {
+        let at = parser.offset();
+        match (unclosed_conditional(value), value.get(at + 1)) {
+            (Some(start), _) if *e.kind() == ErrorKind::UnexpectedEof => lint(
+                Severity::Error,
+                start,
+                "this `%?` is never closed with a `%;`".into(),
+            ),
+            // variables and the logical operators are fine, they just can't be expanded here
+            (_, Some(&c)) if b"PgAO".contains(&c) => {
+                let mut message = String::new();
+                let _ = write!(
+                    message,
+                    "uses `%{}`, which `TermInfo::format` doesn't support",
+                    c as char
+                );
+                lint(Severity::Warning, at, message)
+            }
+            _ => {
+                let mut message = String::new();
+                let _ = write!(message, "can't be parsed: {}", e);
+                lint(Severity::Error, at, message)
+            }
+        }
+        return;
+    }
+    let ops = parser.ops();
+    let offsets = parser.op_offsets();
+
+    // where each parameter is first used
+    let mut uses = [None; 9];
+    for (op, &offset) in ops.iter().zip(offsets) {
+        if let Op::PushUserArg(i) = *op {
+            uses[i] = uses[i].or(Some(offset));
+        }
+    }
+
+    match field.and_then(signature) {
+        Some(signature) => {
+            // terminals can ignore parameters they don't support, like `sgr`'s protected attribute, so only using
+            // too many is a mistake
+            let takes = signature.params.len();
+            if let Some(offset) = uses.iter().skip(takes).filter_map(|&u| u).next() {
+                let used = uses.iter().rposition(Option::is_some).unwrap() + 1;
+                let mut message = String::new();
+                let _ = write!(
+                    message,
+                    "takes {} parameter{}, but uses %p{}",
+                    takes,
+                    if takes == 1 { "" } else { "s" },
+                    used
+                );
+                lint(Severity::Error, offset, message)
+            }
+        }
+        None => {
+            if let Some(last) = uses.iter().rposition(Option::is_some) {
+                if let Some(missing) = uses[..last].iter().position(Option::is_none) {
+                    let mut message = String::new();
+                    let _ = write!(message, "uses %p{}, but never %p{}", last + 1, missing + 1);
+                    lint(Severity::Warning, uses[last].unwrap(), message)
+                }
+            }
+        }
+    }
+
+    let reached = reachable(ops);
+    for i in 0..ops.len() {
+        if !reached[i] && (i == 0 || reached[i - 1]) {
+            lint(
+                Severity::Warning,
+                offsets[i],
+                "can never be reached, the `%?` before it tests a constant".into(),
+            )
+        }
+    }
+}
+
+/// Find where the last `%?` that isn't closed with a `%;` starts.
+fn unclosed_conditional(value: &[u8]) -> Option<usize> {
+    let mut open = Vec::new();
+    let mut i = 0;
+    while i + 1 < value.len() {
+        if value[i] != b'%' {
+            i += 1;
+            continue;
+        }
+        match value[i + 1] {
+            // a character constant could be `%'?'` or `%';'`
+            b'\'' => i += 2,
+            b'?' => open.push(i),
+            b';' => {
+                open.pop();
+            }
+            _ => (),
+        }
+        i += 2;
+    }
+    open.pop()
+}
+
+/// Which ops can run, following both sides of every branch except the ones that test a constant.
+fn reachable(ops: &[Op]) -> Vec<bool> {
+    let mut targets = Vec::new();
+    targets.resize(ops.len() + 1, false);
+    for op in ops {
+        match *op {
+            Op::Jump(t) | Op::BranchFalse(t) | Op::BranchTrue(t) => targets[t] = true,
+            _ => (),
+        }
+    }
+
+    // the branch at `i` always goes the same way if the op before it pushes a constant, and it's only ever reached
+    // from that op
+    let constant = |i: usize| match ops.get(i.wrapping_sub(1)) {
+        Some(Op::Push(arg)) if !targets[i] => Some(match *arg {
+            Argument::Integer(n) => n != 0,
+            Argument::Char(c) => c != 0,
+            Argument::String(ref s) => !s.is_empty(),
+            Argument::Missing => false,
+        }),
+        _ => None,
+    };
+
+    let mut reached = Vec::new();
+    reached.resize(ops.len(), false);
+    let mut todo = Vec::new();
+    todo.push(0);
+    while let Some(i) = todo.pop() {
+        if i >= ops.len() || reached[i] {
+            continue;
+        }
+        reached[i] = true;
+        let (target, jumps_if) = match ops[i] {
+            Op::BranchFalse(t) => (t, false),
+            Op::BranchTrue(t) => (t, true),
+            Op::Jump(t) => {
+                todo.push(t);
+                continue;
+            }
+            _ => {
+                todo.push(i + 1);
+                continue;
+            }
+        };
+        match constant(i) {
+            Some(test) if test == jumps_if => todo.push(target),
+            Some(_) => todo.push(i + 1),
+            None => todo.extend_from_slice(&[target, i + 1]),
+        }
+    }
+    reached
+}
+
+#[cfg(test)]
+mod test {
+    use terminfo::lint::*;
+
+    const BROKEN_INFO: &'static [u8] = include_bytes!("../../test-data/broken");
+    const RXVT_INFO: &'static [u8] = include_bytes!("../../test-data/rxvt");
+    const XTERM_INFO: &'static [u8] = include_bytes!("../../test-data/xterm");
+
+    #[test]
+    fn broken() {
+        let lints = check_entry(&TermInfo::parse(BROKEN_INFO).unwrap());
+        let found: Vec<_> = lints
+            .iter()
+            .map(|l| {
+                (
+                    l.cap.as_str(),
+                    l.severity,
+                    l.offset.unwrap(),
+                    l.message.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (
+                    "hpa",
+                    Severity::Error,
+                    5,
+                    "can't be parsed: invalid numeric literal, expected %{digits}"
+                ),
+                (
+                    "cup",
+                    Severity::Error,
+                    16,
+                    "takes 2 parameters, but uses %p3"
+                ),
+                ("ech", Severity::Error, 8, "takes 1 parameter, but uses %p2"),
+                (
+                    "flash",
+                    Severity::Warning,
+                    8,
+                    "can never be reached, the `%?` before it tests a constant"
+                ),
+                (
+                    "setaf",
+                    Severity::Error,
+                    2,
+                    "this `%?` is never closed with a `%;`"
+                ),
+                ("setrgbf", Severity::Warning, 13, "uses %p3, but never %p2"),
+            ]
+        );
+
+        assert_eq!(
+            lints[1].to_string(),
+            "error: cup: takes 2 parameters, but uses %p3\n  \
+             | cup=\\E[%i%p1%d;%p2%d;%p3%dH\n  \
+             |                      ^"
+        );
+    }
+
+    #[test]
+    fn clean() {
+        for &data in &[RXVT_INFO, XTERM_INFO] {
+            assert_eq!(check_entry(&TermInfo::parse(data).unwrap()), vec![]);
+        }
+    }
+
+    #[test]
+    fn strings() {
+        let check = |value: &[u8]| {
+            let mut lints = Vec::new();
+            super::check("u0", None, value, &mut lints);
+            lints
+                .into_iter()
+                .map(|l| (l.severity, l.offset.unwrap()))
+                .collect::<Vec<_>>()
+        };
+        // constant tests
+        assert_eq!(check(b"%?%{1}%ta%eb%;c"), vec![(Severity::Warning, 11)]);
+        assert_eq!(check(b"%?%{0}%ta%eb%;c"), vec![(Severity::Warning, 8)]);
+        assert_eq!(check(b"%?%'x'%ta%;"), vec![]);
+        assert_eq!(check(b"%?%p1%ta%eb%;c"), vec![]);
+        assert_eq!(check(b"%?%?%p1%t%{1}%e%{0}%;%ta%;"), vec![]);
+
+        // unclosed and unsupported
+        assert_eq!(check(b"%?%';'%ta"), vec![(Severity::Error, 0)]);
+        assert_eq!(check(b"ab%?"), vec![(Severity::Error, 2)]);
+        assert_eq!(check(b"%p1%Pa%ga%d"), vec![(Severity::Warning, 3)]);
+        assert_eq!(check(b"\x1b[%w"), vec![(Severity::Error, 2)]);
+    }
+}
@@ -1,25 +1,51 @@
+mod builder;
+#[cfg(feature = "std")]
+mod cache;
+mod captables;
+#[cfg(feature = "std")]
+pub mod codegen;
 mod errors;
+mod ext;
 mod fields;
+#[cfg(feature = "std")]
+mod file;
+mod fingerprint;
 pub mod lang;
+pub mod lint;
+pub mod names;
+pub mod padding;
+pub mod sgr;
+mod signature;
 mod strtab;
 mod terminfo;
 mod terminfobuf;
+pub mod validate;
+mod warnings;
+pub mod wrap;
 
+pub use self::builder::TermInfoBuilder;
+#[cfg(feature = "std")]
+pub use self::cache::*;
 pub use self::errors::*;
+pub use self::ext::{ExtCapability, ExtKind, ExtValue};
 pub use self::fields::*;
+#[cfg(feature = "std")]
+pub use self::file::*;
+pub use self::signature::{check_arguments, signature, Param, Signature};
 pub use self::terminfo::*;
 pub use self::terminfobuf::*;
+pub use self::warnings::*;
 
 pub use self::BooleanField::*;
 pub use self::NumericField::*;
 pub use self::StringField::*;
 
-use failure::ResultExt;
+#[cfg(feature = "std")]
 use std::env;
-use std::fs::File;
-use std::io::Read;
-use std::path::PathBuf;
+#[cfg(feature = "std")]
+use std::path::{Path, PathBuf};
 
+#[cfg(feature = "std")]
 /// Enumerate any know terminfo databases on the system.
 pub fn databases() -> Vec<PathBuf> {
     let mut dbs = Vec::new();
@@ -44,6 +70,7 @@ pub fn databases() -> Vec<PathBuf> {
     dbs
 }
 
+#[cfg(feature = "std")]
 /// Get a path to the terminfo file base on the `$TERM` environment variable.
 ///
 /// This function emulates the `curses` method for finding the compiled terminfo file.
@@ -60,26 +87,107 @@ pub fn path() -> Option<PathBuf> {
         Err(_) => return None,
     };
 
-    let suffix = PathBuf::from(&terminal_name[..1]).join(terminal_name);
+    find(&terminal_name)
+}
+
+#[cfg(feature = "std")]
+/// Get a path to the terminfo file for the terminal called `name`, from the first database that has one.
+///
+/// Names that `check_name` rejects are never found.
+pub fn find(name: &str) -> Option<PathBuf> {
+    check_name(name).ok()?;
+    let first = name.chars().next()?;
+    let suffix = PathBuf::from(first.to_string()).join(name);
     databases()
         .iter()
         .find(|p| p.join(&suffix).exists())
         .map(|p| p.join(suffix))
 }
 
+/// Check that `name` can be looked up in a database, failing with `ErrorKind::InvalidTerminalName` if it's empty,
+/// has a path separator in it, or is `.` or `..`. A name like that would look outside the database's directory.
+pub fn check_name(name: &str) -> Result<()> {
+    if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\']) {
+        return Err(ErrorKind::InvalidTerminalName(name.into()).into());
+    }
+    Ok(())
+}
+
+#[cfg(feature = "std")]
+/// Load the terminfo file for the terminal named by `$TERM`.
+///
+/// On Windows, where there's usually neither, this falls back to the `builtin` entry. A `$TERM` that isn't a
+/// terminal's name (see `check_name`) fails with `ErrorKind::InvalidTerminalName`.
 pub fn from_env() -> Result<TermInfoBuf> {
-    let path = match path() {
-        Some(v) => v,
-        None => return Err(ErrorKind::FailedToFindTermInfo.into()),
-    };
+    Ok(map_env()?.info().into())
+}
+
+#[cfg(feature = "std")]
+/// Load the terminfo file for the terminal named by `$TERM` like `from_env`, without copying it into a
+/// `TermInfoBuf`.
+pub fn map_env() -> Result<MappedTermInfo> {
+    if let Ok(name) = env::var("TERM") {
+        if !name.is_empty() {
+            check_name(&name)?;
+        }
+    }
+    match path() {
+        Some(v) => map_path(v),
+        None if cfg!(windows) => MappedTermInfo::from_static(BUILTIN_XTERM),
+        None => Err(ErrorKind::FailedToFindTermInfo.into()),
+    }
+}
+
+/// The compiled xterm-256color entry that's built into the library.
+pub const BUILTIN_XTERM: &[u8] = include_bytes!("builtin/xterm-256color");
 
-    let mut file = File::open(path).context(ErrorKind::FailedToParseFile)?;
-    let mut data = Vec::new();
+/// An entry for terminals that aren't in any database but speak xterm's sequences, like the Windows console once
+/// virtual terminal processing is on.
+pub fn builtin() -> TermInfoBuf {
+    TermInfo::parse(BUILTIN_XTERM).unwrap().into()
+}
 
-    file.read_to_end(&mut data)
-        .context(ErrorKind::FailedToParseFile)?;
+#[cfg(feature = "std")]
+/// Load a terminfo file.
+///
+/// To parse a file without copying it into a `TermInfoBuf`, use `map_path` or `TermInfoFile` instead.
+pub fn from_path<P: AsRef<Path>>(path: P) -> Result<TermInfoBuf> {
+    Ok(map_path(path)?.info().into())
+}
+
+#[cfg(feature = "std")]
+/// Load a terminfo file, mapping it with the `mmap` feature, and return an entry that borrows from it.
+pub fn map_path<P: AsRef<Path>>(path: P) -> Result<MappedTermInfo> {
+    MappedTermInfo::open(path)
+}
 
-    Ok(TermInfo::parse(&data)
-        .context(ErrorKind::FailedToParseFile)?
-        .into())
+#[cfg(test)]
+mod test {
+    use terminfo::*;
+
+    #[test]
+    fn builtin_entry() {
+        let info = builtin();
+        assert_eq!(info.names[0], "xterm-256color");
+        assert_eq!(info.number(MaxColors), Some(256));
+        assert!(info.string(CursorAddress).is_some());
+        assert!(info.string(KeyF12).is_some());
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn names() {
+        for &name in ["", ".", "..", "../../etc/passwd", "xterm/../x", "a\\b"].iter() {
+            assert_eq!(*check_name(name).unwrap_err().kind(), ErrorKind::InvalidTerminalName(name.into()));
+            assert_eq!(find(name), None);
+        }
+        check_name("xterm-256color").unwrap();
+        // the first letter is the directory, however many bytes it takes
+        check_name("été").unwrap();
+        assert_eq!(find("été-no-such-terminal"), None);
+        assert_eq!(
+            *cached("../xterm").unwrap_err().kind(),
+            ErrorKind::InvalidTerminalName("../xterm".into())
+        );
+    }
 }
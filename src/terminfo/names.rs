@@ -0,0 +1,199 @@
+//! The names of the standard capabilities, and what they're for.
+//!
+//! Every standard capability has three names: its terminfo name (like `cup`), its termcap code (`cm`) and the
+//! variable name curses gives it (`cursor_address`). The tables here have all three, along with terminfo(5)'s
+//! description, for every `BooleanField`, `NumericField` and `StringField`, so tools can list or look up capabilities
+//! without loading an entry. They're generated by "./scripts/generate-terminfo-names.sh", from the same header as the
+//! fields.
+//!
+//! ```
+//! use nixterm::terminfo::names::{self, Field};
+//! use nixterm::terminfo::CursorAddress;
+//!
+//! assert_eq!(names::lookup("cm"), Some(Field::String(CursorAddress)));
+//! assert_eq!(CursorAddress.info().terminfo_name, "cup");
+//! ```
+use std::mem;
+use terminfo::fields::{BooleanField, NumericField, StringField};
+
+pub use terminfo::captables::{ALL_BOOLEANS, ALL_NUMBERS, ALL_STRINGS};
+
+/// The names and description of a standard capability.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct CapInfo {
+    /// The name used in terminfo sources, like `cup`.
+    pub terminfo_name: &'static str,
+    /// The two letter termcap name, like `cm`.
+    pub termcap_code: &'static str,
+    /// The name of curses' variable, like `cursor_address`.
+    pub variable_name: &'static str,
+    /// What terminfo(5) says it's for.
+    pub description: &'static str,
+}
+
+/// Any standard capability.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Field {
+    Boolean(BooleanField),
+    Number(NumericField),
+    String(StringField),
+}
+
+impl Field {
+    /// The capability's names and description.
+    pub fn info(self) -> &'static CapInfo {
+        match self {
+            Field::Boolean(field) => field.info(),
+            Field::Number(field) => field.info(),
+            Field::String(field) => field.info(),
+        }
+    }
+}
+
+impl From<BooleanField> for Field {
+    fn from(field: BooleanField) -> Field {
+        Field::Boolean(field)
+    }
+}
+
+impl From<NumericField> for Field {
+    fn from(field: NumericField) -> Field {
+        Field::Number(field)
+    }
+}
+
+impl From<StringField> for Field {
+    fn from(field: StringField) -> Field {
+        Field::String(field)
+    }
+}
+
+impl BooleanField {
+    /// The capability's names and description.
+    pub fn info(self) -> &'static CapInfo {
+        &ALL_BOOLEANS[self as usize]
+    }
+}
+
+impl NumericField {
+    /// The capability's names and description.
+    pub fn info(self) -> &'static CapInfo {
+        &ALL_NUMBERS[self as usize]
+    }
+}
+
+impl StringField {
+    /// The capability's names and description.
+    pub fn info(self) -> &'static CapInfo {
+        &ALL_STRINGS[self as usize]
+    }
+}
+
+/// Find the first capability `matches` accepts.
+fn find<F: Fn(&CapInfo) -> bool>(matches: F) -> Option<Field> {
+    // the fields are `repr(usize)`, with a variant for every index in their table
+    if let Some(i) = ALL_BOOLEANS.iter().position(&matches) {
+        return Some(Field::Boolean(unsafe { mem::transmute::<usize, BooleanField>(i) }));
+    }
+    if let Some(i) = ALL_NUMBERS.iter().position(&matches) {
+        return Some(Field::Number(unsafe { mem::transmute::<usize, NumericField>(i) }));
+    }
+    ALL_STRINGS
+        .iter()
+        .position(&matches)
+        .map(|i| Field::String(unsafe { mem::transmute::<usize, StringField>(i) }))
+}
+
+/// The capability with the terminfo name `name`, like `cup`.
+pub fn from_terminfo_name(name: &str) -> Option<Field> {
+    find(|info| info.terminfo_name == name)
+}
+
+/// The capability with the termcap code `code`, like `cm`.
+pub fn from_termcap_code(code: &str) -> Option<Field> {
+    find(|info| info.termcap_code == code)
+}
+
+/// The capability curses calls `name`, like `cursor_address`.
+pub fn from_variable_name(name: &str) -> Option<Field> {
+    find(|info| info.variable_name == name)
+}
+
+/// The capability called `name`, whichever of its names that is.
+///
+/// Some termcap codes are also the terminfo name of a different capability (`dl` deletes one line in termcap, but any
+/// number of them in terminfo), so terminfo names are tried first, then variable names, then termcap codes.
+pub fn lookup(name: &str) -> Option<Field> {
+    from_terminfo_name(name)
+        .or_else(|| from_variable_name(name))
+        .or_else(|| from_termcap_code(name))
+}
+
+#[cfg(test)]
+mod test {
+    use terminfo::names::*;
+    use terminfo::*;
+
+    #[test]
+    fn counts() {
+        // ncurses 6.4's Caps file, without the extensions in Caps-ncurses
+        assert_eq!(ALL_BOOLEANS.len(), 44);
+        assert_eq!(ALL_NUMBERS.len(), 39);
+        assert_eq!(ALL_STRINGS.len(), 414);
+
+        assert_eq!(ALL_BOOLEANS.len(), PREDEFINED_BOOLEANS_COUNT);
+        assert_eq!(ALL_NUMBERS.len(), PREDEFINED_NUMERICS_COUNT);
+        assert_eq!(ALL_STRINGS.len(), PREDEFINED_STRINGS_COUNT);
+    }
+
+    #[test]
+    fn tables_match_fields() {
+        // the fields are named after the variables, so every table entry is in the right place
+        fn camel(variable: &str) -> String {
+            variable
+                .split('_')
+                .map(|word| {
+                    let mut chars = word.chars();
+                    chars.next().map_or(String::new(), |c| {
+                        c.to_ascii_uppercase().to_string() + chars.as_str()
+                    })
+                })
+                .collect()
+        }
+
+        for info in ALL_BOOLEANS.iter().chain(ALL_NUMBERS).chain(ALL_STRINGS) {
+            let field = from_variable_name(info.variable_name).unwrap();
+            assert_eq!(field.info(), info);
+            let debug = match field {
+                Field::Boolean(f) => format!("{:?}", f),
+                Field::Number(f) => format!("{:?}", f),
+                Field::String(f) => format!("{:?}", f),
+            };
+            assert_eq!(debug, camel(info.variable_name));
+            assert!(!info.terminfo_name.is_empty() && !info.description.is_empty());
+        }
+    }
+
+    #[test]
+    fn lookups() {
+        let cup = Some(Field::String(CursorAddress));
+        assert_eq!(from_terminfo_name("cup"), cup);
+        assert_eq!(from_termcap_code("cm"), cup);
+        assert_eq!(from_variable_name("cursor_address"), cup);
+        assert_eq!(lookup("cup"), cup);
+        assert_eq!(lookup("cm"), cup);
+        assert_eq!(lookup("cursor_address"), cup);
+
+        assert_eq!(lookup("colors"), Some(Field::Number(MaxColors)));
+        assert_eq!(lookup("Co"), Some(Field::Number(MaxColors)));
+        assert_eq!(lookup("am"), Some(Field::Boolean(AutoRightMargin)));
+        assert_eq!(lookup("dl"), Some(Field::String(ParmDeleteLine)));
+        assert_eq!(from_termcap_code("dl"), Some(Field::String(DeleteLine)));
+        assert_eq!(lookup("OTbs"), Some(Field::Boolean(BackspacesWithBs)));
+        assert_eq!(lookup("setrgbf"), None);
+        assert_eq!(lookup("Cup"), None);
+
+        assert_eq!(MaxColors.info().description, "maximum number of colors on screen");
+        assert_eq!(Field::String(ClearScreen).info().termcap_code, "cl");
+    }
+}
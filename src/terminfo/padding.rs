@@ -0,0 +1,262 @@
+//! Deciding whether a `$<ms>` delay in an expanded string is carried out, and how, the way ncurses' `tputs` does.
+//!
+//! A delay is carried out if any of these are true:
+//!
+//! - it's mandatory, written with a `/` like `$<5/>`
+//! - the string is `bel` or `flash`, which are always delayed
+//! - the terminal doesn't pace itself with XON/XOFF (`xon` isn't set), and either it has no `pb` (lowest baud rate
+//!   needing padding) or the baud rate is at least `pb`
+//!
+//! Like ncurses, a `pb#0` means ordinary delays are never needed, rather than always needed.
+//!
+//! Carrying a delay out means sending the pad character (the first byte of `pad`, or NUL without one) as many
+//! times as the terminal can receive in the delay, counting 9 bits a byte like ncurses does. At an unknown baud rate
+//! of 0 that's none at all. If the entry has no pad character (`npc`) the delay is slept instead.
+use std::time::Duration;
+use terminfo::{NoPadChar, PadChar, PaddingBaudRate, XonXoff};
+use terminfo::{TermInfo, TermInfoBuf};
+
+/// How to carry out one `$<ms>` delay, from `PaddingRules::decide`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Padding {
+    /// Leave the delay out.
+    Skip,
+    /// Send `count` copies of `byte`.
+    Send { byte: u8, count: usize },
+    /// Flush what's been written, and wait.
+    Sleep(Duration),
+}
+
+/// The capabilities that decide how an entry's delays are carried out, see the module documentation.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct PaddingRules {
+    /// `xon`, the terminal uses XON/XOFF flow control.
+    pub xon_xoff: bool,
+    /// `pb`, the lowest baud rate that needs padding.
+    pub padding_baud_rate: Option<u32>,
+    /// The first byte of `pad`, or NUL if there isn't one.
+    pub pad_char: u8,
+    /// `npc`, the terminal has no pad character.
+    pub no_pad_char: bool,
+}
+
+impl PaddingRules {
+    /// True if delays that aren't mandatory are carried out at `baud`, in strings other than `bel` and `flash`.
+    pub fn pads_at(&self, baud: u32) -> bool {
+        !self.xon_xoff
+            && match self.padding_baud_rate {
+                None => true,
+                Some(0) => false,
+                Some(pb) => baud >= pb,
+            }
+    }
+
+    /// Decide what to do with a `delay` long delay, when writing at `baud`. `mandatory` is whether the delay had a
+    /// `/`, and `always` whether it's in `bel` or `flash`.
+    pub fn decide(&self, delay: Duration, baud: u32, mandatory: bool, always: bool) -> Padding {
+        if !(mandatory || always || self.pads_at(baud)) {
+            return Padding::Skip;
+        }
+        if self.no_pad_char {
+            return Padding::Sleep(delay);
+        }
+        match delay.as_micros() * u128::from(baud) / 9_000_000 {
+            0 => Padding::Skip,
+            count => Padding::Send {
+                byte: self.pad_char,
+                count: count as usize,
+            },
+        }
+    }
+}
+
+/// Find the first `$<ms>` delay in an expanded capability, returning where it starts and ends, and how long it is.
+///
+/// The delay can have a fraction of a millisecond, and end with `*` (multiply by the lines affected, which is
+/// ignored here) and `/` (the delay is mandatory).
+#[cfg(feature = "std")]
+pub(crate) fn find_delay(bytes: &[u8]) -> Option<(usize, usize, Duration)> {
+    let mut from = 0;
+    while let Some(i) = memchr::memchr(b'$', &bytes[from..]) {
+        let start = from + i;
+        from = start + 1;
+        if bytes.get(from) != Some(&b'<') {
+            continue;
+        }
+        let len = match memchr::memchr(b'>', &bytes[from..]) {
+            Some(len) => len,
+            None => break,
+        };
+        let spec = &bytes[from + 1..from + len];
+        let digits = spec
+            .iter()
+            .position(|&c| c == b'*' || c == b'/')
+            .map_or(spec, |n| &spec[..n]);
+        let ms = ::std::str::from_utf8(digits)
+            .ok()
+            .and_then(|ms| ms.parse::<f64>().ok());
+        if let Some(ms) = ms {
+            if spec[digits.len()..].iter().all(|&c| c == b'*' || c == b'/') {
+                return Some((start, from + len + 1, Duration::from_micros((ms * 1000.0) as u64)));
+            }
+        }
+    }
+    None
+}
+
+/// An expanded capability without its `$<ms>` delays, for writing where there's no waiting.
+#[cfg(feature = "std")]
+pub(crate) fn without_delays(mut bytes: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(bytes.len());
+    while let Some((start, end, _)) = find_delay(bytes) {
+        out.extend_from_slice(&bytes[..start]);
+        bytes = &bytes[end..];
+    }
+    out.extend_from_slice(bytes);
+    out
+}
+
+impl<'a> TermInfo<'a> {
+    /// How this entry's delays are carried out.
+    pub fn padding_rules(&self) -> PaddingRules {
+        PaddingRules {
+            xon_xoff: self.boolean(XonXoff),
+            padding_baud_rate: self.number(PaddingBaudRate),
+            pad_char: self
+                .string_bytes(PadChar)
+                .and_then(|pad| pad.first().cloned())
+                .unwrap_or(0),
+            no_pad_char: self.boolean(NoPadChar),
+        }
+    }
+}
+
+impl TermInfoBuf {
+    /// How this entry's delays are carried out.
+    pub fn padding_rules(&self) -> PaddingRules {
+        PaddingRules {
+            xon_xoff: self.boolean(XonXoff),
+            padding_baud_rate: self.number(PaddingBaudRate),
+            pad_char: self
+                .string_bytes(PadChar)
+                .and_then(|pad| pad.first().cloned())
+                .unwrap_or(0),
+            no_pad_char: self.boolean(NoPadChar),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use terminfo::padding::*;
+
+    const XTERM_INFO: &'static [u8] = include_bytes!("../../test-data/xterm");
+
+    #[test]
+    fn decide() {
+        use self::Padding::*;
+
+        let ms = Duration::from_millis(10);
+        let send = |count| Send { byte: 0, count };
+        let table = [
+            (false, None, 9600, false, false, send(10)),
+            (false, None, 300, false, false, Skip),
+            (false, Some(9600), 9600, false, false, send(10)),
+            (false, Some(9600), 38400, false, false, send(42)),
+            (false, Some(9600), 4800, false, false, Skip),
+            (false, Some(9600), 4800, true, false, send(5)),
+            (false, Some(9600), 4800, false, true, send(5)),
+            (false, Some(0), 9600, false, false, Skip),
+            (false, Some(0), 9600, true, false, send(10)),
+            (true, None, 9600, false, false, Skip),
+            (true, Some(1200), 9600, false, false, Skip),
+            (true, None, 9600, true, false, send(10)),
+            (true, Some(9600), 4800, false, true, send(5)),
+            (true, None, 0, true, true, Skip),
+            (false, None, 0, false, false, Skip),
+        ];
+        for &(xon_xoff, padding_baud_rate, baud, mandatory, always, padding) in &table {
+            let rules = PaddingRules {
+                xon_xoff,
+                padding_baud_rate,
+                pad_char: 0,
+                no_pad_char: false,
+            };
+            assert_eq!(
+                rules.decide(ms, baud, mandatory, always),
+                padding,
+                "xon={} pb={:?} baud={} mandatory={} always={}",
+                xon_xoff,
+                padding_baud_rate,
+                baud,
+                mandatory,
+                always
+            );
+        }
+    }
+
+    #[test]
+    fn pad_char() {
+        let mut rules = PaddingRules {
+            xon_xoff: false,
+            padding_baud_rate: None,
+            pad_char: b'*',
+            no_pad_char: false,
+        };
+        let ms = Duration::from_micros(1500);
+        assert_eq!(
+            rules.decide(ms, 9600, false, false),
+            Padding::Send {
+                byte: b'*',
+                count: 1
+            }
+        );
+        rules.no_pad_char = true;
+        assert_eq!(rules.decide(ms, 9600, false, false), Padding::Sleep(ms));
+        assert_eq!(rules.decide(ms, 0, true, false), Padding::Sleep(ms));
+        rules.xon_xoff = true;
+        assert_eq!(rules.decide(ms, 9600, false, false), Padding::Skip);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn delays() {
+        assert_eq!(
+            find_delay(b"\x1b[?5h$<100/>\x1b[?5l"),
+            Some((5, 12, Duration::from_millis(100)))
+        );
+        assert_eq!(find_delay(b"$<5*>"), Some((0, 5, Duration::from_millis(5))));
+        assert_eq!(find_delay(b"a$<1.5>"), Some((1, 7, Duration::from_micros(1500))));
+
+        // a `$` that isn't a delay is just text
+        assert_eq!(find_delay(b"$5 $<x> $<3"), None);
+        assert_eq!(find_delay(b"$$<2>"), Some((1, 5, Duration::from_millis(2))));
+    }
+
+    #[test]
+    fn from_entry() {
+        assert_eq!(
+            TermInfo::parse(XTERM_INFO).unwrap().padding_rules(),
+            PaddingRules {
+                xon_xoff: false,
+                padding_baud_rate: None,
+                pad_char: 0,
+                no_pad_char: true,
+            }
+        );
+
+        let mut info = TermInfoBuf::new();
+        info.set_boolean(XonXoff, true).unwrap();
+        info.set_number(PaddingBaudRate, 1200).unwrap();
+        info.set_string(PadChar, "\x7f").unwrap();
+        assert_eq!(
+            info.padding_rules(),
+            PaddingRules {
+                xon_xoff: true,
+                padding_baud_rate: Some(1200),
+                pad_char: 0x7f,
+                no_pad_char: false,
+            }
+        );
+    }
+}
@@ -0,0 +1,199 @@
+//! Setting every video attribute at once with `sgr`.
+//!
+//! `sgr` takes nine flags, in the order of `SgrAttrs`' fields, and turns off anything that isn't asked for. Entries
+//! without it (like a plain vt100) get the same effect from `sgr0` followed by the capability for each attribute
+//! that's on, `smso`, `smul`, `rev` and so on. Delays in the capabilities are left in the output.
+use std::vec::Vec;
+use terminfo::errors::*;
+use terminfo::lang::Argument;
+use terminfo::signature;
+use terminfo::StringField;
+use terminfo::StringField::*;
+use terminfo::{TermInfo, TermInfoBuf};
+
+/// The attributes `sgr` sets, in the order it takes them.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct SgrAttrs {
+    pub standout: bool,
+    pub underline: bool,
+    pub reverse: bool,
+    pub blink: bool,
+    pub dim: bool,
+    pub bold: bool,
+    pub invisible: bool,
+    pub protected: bool,
+    pub alt_charset: bool,
+}
+
+impl SgrAttrs {
+    /// Each attribute, with the capability that turns it on by itself.
+    fn capabilities(&self) -> [(bool, StringField); 9] {
+        [
+            (self.standout, EnterStandoutMode),
+            (self.underline, EnterUnderlineMode),
+            (self.reverse, EnterReverseMode),
+            (self.blink, EnterBlinkMode),
+            (self.dim, EnterDimMode),
+            (self.bold, EnterBoldMode),
+            (self.invisible, EnterSecureMode),
+            (self.protected, EnterProtectedMode),
+            (self.alt_charset, EnterAltCharsetMode),
+        ]
+    }
+
+    /// `sgr`'s nine arguments.
+    fn arguments(&self) -> Vec<Argument> {
+        self.capabilities()
+            .iter()
+            .map(|&(on, _)| Argument::from(on))
+            .collect()
+    }
+}
+
+fn sgr<'a, F>(string: F, attrs: SgrAttrs) -> Result<Vec<u8>>
+where
+    F: Fn(StringField) -> Option<&'a [u8]>,
+{
+    if let Some(sgr) = string(SetAttributes) {
+        return signature::format(SetAttributes, sgr, &attrs.arguments());
+    }
+    let mut out = sgr0(&string)?;
+    for &(on, field) in attrs.capabilities().iter() {
+        if on {
+            let cap = string(field).ok_or(ErrorKind::MissingCapability(field))?;
+            out.extend_from_slice(cap);
+        }
+    }
+    Ok(out)
+}
+
+fn sgr0<'a, F>(string: F) -> Result<Vec<u8>>
+where
+    F: Fn(StringField) -> Option<&'a [u8]>,
+{
+    if let Some(sgr0) = string(ExitAttributeMode) {
+        return Ok(sgr0.to_vec());
+    }
+    match string(SetAttributes) {
+        Some(sgr) => signature::format(SetAttributes, sgr, &SgrAttrs::default().arguments()),
+        None => Err(ErrorKind::MissingCapability(ExitAttributeMode).into()),
+    }
+}
+
+impl<'a> TermInfo<'a> {
+    /// Turn on exactly the attributes in `attrs`, with `sgr` or by putting together the single capabilities.
+    ///
+    /// Fails with `ErrorKind::MissingCapability` if there's no `sgr`, and the entry doesn't have `sgr0` or one of the
+    /// attributes that's on.
+    pub fn sgr(&self, attrs: SgrAttrs) -> Result<Vec<u8>> {
+        sgr(|field| self.string_bytes(field), attrs)
+    }
+
+    /// Turn every attribute off, with `sgr0` or else `sgr`.
+    pub fn sgr0(&self) -> Result<Vec<u8>> {
+        sgr0(|field| self.string_bytes(field))
+    }
+}
+
+impl TermInfoBuf {
+    /// Turn on exactly the attributes in `attrs`, with `sgr` or by putting together the single capabilities.
+    ///
+    /// Fails with `ErrorKind::MissingCapability` if there's no `sgr`, and the entry doesn't have `sgr0` or one of the
+    /// attributes that's on.
+    pub fn sgr(&self, attrs: SgrAttrs) -> Result<Vec<u8>> {
+        sgr(|field| self.string_bytes(field), attrs)
+    }
+
+    /// Turn every attribute off, with `sgr0` or else `sgr`.
+    pub fn sgr0(&self) -> Result<Vec<u8>> {
+        sgr0(|field| self.string_bytes(field))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use terminfo::sgr::*;
+    use terminfo::TermInfoBuilder;
+
+    const XTERM_INFO: &'static [u8] = include_bytes!("../../test-data/xterm");
+    const SCREEN_INFO: &'static [u8] = include_bytes!("../../test-data/screen");
+    const VT100_INFO: &'static [u8] = include_bytes!("../../test-data/vt100");
+
+    fn bold_underline() -> SgrAttrs {
+        SgrAttrs {
+            bold: true,
+            underline: true,
+            ..SgrAttrs::default()
+        }
+    }
+
+    #[test]
+    fn xterm() {
+        let info = TermInfo::parse(XTERM_INFO).unwrap();
+        assert_eq!(info.sgr(SgrAttrs::default()).unwrap(), b"\x1b(B\x1b[0m");
+        assert_eq!(info.sgr(bold_underline()).unwrap(), b"\x1b(B\x1b[0;1;4m");
+        assert_eq!(
+            info.sgr(SgrAttrs {
+                standout: true,
+                alt_charset: true,
+                ..SgrAttrs::default()
+            })
+            .unwrap(),
+            b"\x1b(0\x1b[0;7m"
+        );
+        assert_eq!(info.sgr0().unwrap(), b"\x1b(B\x1b[m");
+    }
+
+    #[test]
+    fn screen() {
+        let info = TermInfo::parse(SCREEN_INFO).unwrap();
+        assert_eq!(info.sgr(bold_underline()).unwrap(), b"\x1b[0;1;4m\x0f");
+        assert_eq!(
+            info.sgr(SgrAttrs {
+                standout: true,
+                dim: true,
+                alt_charset: true,
+                ..SgrAttrs::default()
+            })
+            .unwrap(),
+            b"\x1b[0;3;2m\x0e"
+        );
+        assert_eq!(info.sgr0().unwrap(), b"\x1b[m\x0f");
+    }
+
+    #[test]
+    fn without_sgr() {
+        let vt100 = TermInfo::parse(VT100_INFO).unwrap();
+        assert_eq!(vt100.sgr(bold_underline()).unwrap(), b"\x1b[0;1;4m\x0f$<2>");
+
+        let info = TermInfoBuilder::new()
+            .name("vt100-nosgr")
+            .cancel(SetAttributes)
+            .use_entry(&vt100)
+            .build()
+            .unwrap();
+        assert_eq!(info.sgr(SgrAttrs::default()).unwrap(), b"\x1b[m\x0f$<2>");
+        assert_eq!(
+            info.sgr(bold_underline()).unwrap(),
+            &b"\x1b[m\x0f$<2>\x1b[4m$<2>\x1b[1m$<2>"[..]
+        );
+        let dim = SgrAttrs {
+            dim: true,
+            ..SgrAttrs::default()
+        };
+        assert_eq!(
+            *info.sgr(dim).unwrap_err().kind(),
+            ErrorKind::MissingCapability(EnterDimMode)
+        );
+
+        let mut bare = TermInfoBuf::new();
+        assert_eq!(
+            *bare.sgr0().unwrap_err().kind(),
+            ErrorKind::MissingCapability(ExitAttributeMode)
+        );
+        bare.set_string(SetAttributes, "\x1b[0%?%p6%t;1%;m")
+            .unwrap();
+        assert_eq!(bare.sgr0().unwrap(), b"\x1b[0m");
+        assert_eq!(bare.sgr(bold_underline()).unwrap(), b"\x1b[0;1m");
+    }
+}
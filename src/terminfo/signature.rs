@@ -0,0 +1,236 @@
+//! What the standard parameterized capabilities expect to be called with.
+//!
+//! Capability strings can't say how many arguments they take, and expanding one with the wrong arguments quietly
+//! gives garbage (a missing argument is 0, and a string where a number should be is an error halfway through). So
+//! `TermInfo::format`, `TermInfoBuf::format` and `Term::write_cap` check their arguments against this table in debug
+//! builds, and `check_arguments` does the same in any build. Capabilities that aren't in the table, like the
+//! extended ones, aren't checked.
+use std::fmt;
+use std::vec::Vec;
+use terminfo::errors::*;
+use terminfo::fields::StringField;
+use terminfo::fields::StringField::*;
+use terminfo::lang::{Argument, Program};
+
+/// The type of one of a capability's parameters.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Param {
+    /// A number, or a character (which is passed as its code).
+    Integer,
+    String,
+}
+
+/// The parameters a capability takes.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Signature {
+    /// Every parameter, in order.
+    pub params: &'static [Param],
+    /// How many of them have to be given, the rest can be left out.
+    pub required: usize,
+}
+
+const INT: &[Param] = &[Param::Integer];
+const INT2: &[Param] = &[Param::Integer, Param::Integer];
+const INT3: &[Param] = &[Param::Integer, Param::Integer, Param::Integer];
+const INT4: &[Param] = &[Param::Integer; 4];
+const INT5: &[Param] = &[Param::Integer; 5];
+const INT6: &[Param] = &[Param::Integer; 6];
+const INT7: &[Param] = &[Param::Integer; 7];
+const INT9: &[Param] = &[Param::Integer; 9];
+const STR: &[Param] = &[Param::String];
+const INT_STR: &[Param] = &[Param::Integer, Param::String];
+const INT_STR2: &[Param] = &[Param::Integer, Param::String, Param::String];
+
+/// The signature of the standard capability `field`, from terminfo(5), or `None` if it takes no parameters or
+/// isn't known.
+pub fn signature(field: StringField) -> Option<Signature> {
+    let (params, required) = match field {
+        CursorAddress | CursorMemAddress | ChangeScrollRegion | SetLrMargin | SetTbMargin => {
+            (INT2, 2)
+        }
+        // the character, then how many times
+        RepeatChar => (INT2, 2),
+        ColumnAddress | RowAddress | ParmLeftCursor | ParmRightCursor | ParmUpCursor
+        | ParmDownCursor => (INT, 1),
+        ParmDch | ParmIch | ParmDeleteLine | ParmInsertLine | EraseChars | ParmIndex
+        | ParmRindex => (INT, 1),
+        SetAForeground | SetABackground | SetForeground | SetBackground | SetColorPair => (INT, 1),
+        InitializeColor => (INT4, 4),
+        InitializePair => (INT7, 7),
+        SetAttributes => (INT9, 9),
+        SetAAttributes => (INT6, 6),
+        // the column to start at, which some terminals don't take
+        ToStatusLine => (INT, 0),
+        SetLeftMarginParm | SetRightMarginParm | SetTopMarginParm | SetBottomMarginParm => {
+            (INT2, 1)
+        }
+        MicroColumnAddress | MicroRowAddress | ParmLeftMicro | ParmRightMicro | ParmUpMicro
+        | ParmDownMicro => (INT, 1),
+        ChangeCharPitch | ChangeLinePitch | ChangeResHorz | ChangeResVert | SelectCharSet => {
+            (INT, 1)
+        }
+        SetColorBand | GotoWindow => (INT, 1),
+        SetWindow => (INT4, 4),
+        CreateWindow => (INT5, 5),
+        SetClock | DefineChar => (INT3, 3),
+        StartCharSetDef => (INT2, 2),
+        PkeyKey | PkeyLocal | PkeyXmit | PlabNorm => (INT_STR, 2),
+        PkeyPlab => (INT_STR2, 3),
+        DialPhone | QuickDial => (STR, 1),
+        _ => return None,
+    };
+    Some(Signature { params, required })
+}
+
+/// Check `args` are what the standard capability `field` expects, see the module documentation.
+pub fn check_arguments(field: StringField, args: &[Argument]) -> Result<()> {
+    let expected = match signature(field) {
+        Some(sig) => sig,
+        None => return Ok(()),
+    };
+    if args.len() < expected.required || args.len() > expected.params.len() {
+        return Err(ErrorKind::ArityMismatch {
+            cap: field,
+            expected,
+            got: args.len(),
+        }
+        .into());
+    }
+
+    for (index, (arg, &param)) in args.iter().zip(expected.params).enumerate() {
+        let fits = match *arg {
+            Argument::String(_) => param == Param::String,
+            Argument::Integer(_) | Argument::Char(_) => param == Param::Integer,
+            // it's whatever the parameter should be, if the capability doesn't need it
+            Argument::Missing => true,
+        };
+        if !fits {
+            return Err(ErrorKind::ArgumentTypeMismatch {
+                cap: field,
+                index,
+                expected: param,
+            }
+            .into());
+        }
+    }
+    Ok(())
+}
+
+/// Expand `src`, the value of `field`, with `args`, checking them first in debug builds.
+pub(crate) fn format(field: StringField, src: &[u8], args: &[Argument]) -> Result<Vec<u8>> {
+    if cfg!(debug_assertions) {
+        check_arguments(field, args)?;
+    }
+    let mut buf = Vec::new();
+    Program::compile(src)?.exec_append(args, &mut buf)?;
+    Ok(buf)
+}
+
+impl fmt::Display for Param {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Param::Integer => write!(f, "integer"),
+            Param::String => write!(f, "string"),
+        }
+    }
+}
+
+/// Like "(integer, string)", with the optional parameters in square brackets.
+impl fmt::Display for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "(")?;
+        for (i, param) in self.params.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            if i < self.required {
+                write!(f, "{}", param)?;
+            } else {
+                write!(f, "[{}]", param)?;
+            }
+        }
+        write!(f, ")")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use terminfo::lang::Argument;
+    use terminfo::*;
+
+    #[test]
+    fn check() {
+        let int = Argument::Integer;
+        let cup = check_arguments(CursorAddress, &[int(3), int(4)]);
+        assert!(cup.is_ok());
+        assert!(check_arguments(ToStatusLine, &[]).is_ok());
+        assert!(check_arguments(ToStatusLine, &[int(1)]).is_ok());
+        assert!(check_arguments(PkeyXmit, &[int(1), Argument::from("ls\r")]).is_ok());
+        // a character passes for a number
+        assert!(check_arguments(RepeatChar, &[Argument::Char(b'-'), int(10)]).is_ok());
+        // capabilities without parameters, and unknown ones, aren't checked
+        assert!(check_arguments(ClearScreen, &[int(1)]).is_ok());
+
+        let e = check_arguments(CursorAddress, &[int(3)]).unwrap_err();
+        assert_eq!(
+            *e.kind(),
+            ErrorKind::ArityMismatch {
+                cap: CursorAddress,
+                expected: signature(CursorAddress).unwrap(),
+                got: 1,
+            }
+        );
+        assert_eq!(
+            e.to_string(),
+            "CursorAddress takes (integer, integer), but was given 1 argument(s)"
+        );
+        assert!(check_arguments(ToStatusLine, &[int(1), int(2)]).is_err());
+
+        let e = check_arguments(SetAForeground, &[Argument::from("red")]).unwrap_err();
+        assert_eq!(
+            *e.kind(),
+            ErrorKind::ArgumentTypeMismatch {
+                cap: SetAForeground,
+                index: 0,
+                expected: Param::Integer,
+            }
+        );
+        assert_eq!(
+            signature(SetLeftMarginParm).unwrap().to_string(),
+            "(integer, [integer])"
+        );
+    }
+
+    #[test]
+    fn format() {
+        let mut info = TermInfoBuf::new();
+        info.set_string(SetAForeground, "\x1b[3%p1%dm").unwrap();
+        info.set_string(CursorAddress, "\x1b[%i%p1%d;%p2%dH")
+            .unwrap();
+
+        assert_eq!(
+            info.format(SetAForeground, &[Argument::Integer(1)])
+                .unwrap()
+                .unwrap(),
+            b"\x1b[31m"
+        );
+        assert_eq!(
+            info.format(CursorAddress, &[Argument::Integer(2), Argument::Integer(5)])
+                .unwrap()
+                .unwrap(),
+            b"\x1b[3;6H"
+        );
+        assert!(info.format(SetABackground, &[]).is_none());
+
+        if cfg!(debug_assertions) {
+            let e = info
+                .format(CursorAddress, &[Argument::Integer(2)])
+                .unwrap()
+                .unwrap_err();
+            match *e.kind() {
+                ErrorKind::ArityMismatch { got: 1, .. } => (),
+                ref kind => panic!("{:?}", kind),
+            }
+        }
+    }
+}
@@ -1,6 +1,6 @@
-use failure::Fail;
 use std::mem::transmute;
 use std::ptr::write_bytes;
+use std::vec::Vec;
 use terminfo::errors::*;
 use util::strlen;
 
@@ -31,12 +31,26 @@ impl StringTable {
         start
     }
 
+    /// Add a string, unless it's already in the table (maybe as the end of a longer string), then share it.
+    pub fn intern<T: AsRef<str>>(&mut self, s: T) -> usize {
+        let bytes = s.as_ref().as_bytes();
+        let found = self
+            .table
+            .windows(bytes.len() + 1)
+            .position(|w| w[bytes.len()] == 0 && &w[..bytes.len()] == bytes);
+        match found {
+            Some(offset) => offset,
+            None => self.add(s),
+        }
+    }
+
     #[inline]
     pub fn get(&self, offset: usize) -> Result<&str> {
         if offset > self.table.len() {
-            return Err(ErrorKind::OutOfRange(offset, self.table.len())
-                .context(ErrorKind::FailedToReadStringFromTable)
-                .into());
+            return Err(Error::with_source(
+                ErrorKind::FailedToReadStringFromTable,
+                ErrorKind::OutOfRange(offset, self.table.len()),
+            ));
         }
 
         let slice = &self.table[offset..];
@@ -52,9 +66,10 @@ impl StringTable {
     #[inline]
     pub fn get_slice(&self, offset: usize) -> Result<&[u8]> {
         if offset > self.table.len() {
-            return Err(ErrorKind::OutOfRange(offset, self.table.len())
-                .context(ErrorKind::FailedToReadStringFromTable)
-                .into());
+            return Err(Error::with_source(
+                ErrorKind::FailedToReadStringFromTable,
+                ErrorKind::OutOfRange(offset, self.table.len()),
+            ));
         }
 
         let slice = &self.table[offset..];
@@ -62,12 +77,15 @@ impl StringTable {
     }
 
     /// This function will be useful in the future
+    ///
+    /// Strings added with `intern` can be shared, deleting one deletes it for everything using it.
     #[allow(dead_code)]
     pub fn del(&mut self, offset: usize) -> Result<()> {
         if offset > self.table.len() {
-            return Err(ErrorKind::OutOfRange(offset, self.table.len())
-                .context(ErrorKind::FailedToReadStringFromTable)
-                .into());
+            return Err(Error::with_source(
+                ErrorKind::FailedToReadStringFromTable,
+                ErrorKind::OutOfRange(offset, self.table.len()),
+            ));
         }
 
         let slice = &mut self.table[offset..];
@@ -88,9 +106,10 @@ impl<'a> StrTable<'a> {
     #[inline]
     pub fn get(&self, offset: usize) -> Result<&str> {
         if offset > self.table.len() {
-            return Err(ErrorKind::OutOfRange(offset, self.table.len())
-                .context(ErrorKind::FailedToReadStringFromTable)
-                .into());
+            return Err(Error::with_source(
+                ErrorKind::FailedToReadStringFromTable,
+                ErrorKind::OutOfRange(offset, self.table.len()),
+            ));
         }
 
         let slice = &self.table[offset..];
@@ -103,6 +122,19 @@ impl<'a> StrTable<'a> {
         self.table.iter().skip(offset).take_while(|&&c| c != 0)
     }
 
+    #[inline]
+    pub fn get_slice(&self, offset: usize) -> Result<&'a [u8]> {
+        if offset > self.table.len() {
+            return Err(Error::with_source(
+                ErrorKind::FailedToReadStringFromTable,
+                ErrorKind::OutOfRange(offset, self.table.len()),
+            ));
+        }
+
+        let slice = &self.table[offset..];
+        Ok(&slice[..strlen(slice)])
+    }
+
     pub fn to_string_table(&self) -> StringTable {
         StringTable {
             table: Vec::from(self.table),
@@ -1,7 +1,15 @@
+use std::borrow::Cow;
 use std::mem;
+use std::str;
+use std::string::String;
+use std::vec::Vec;
 use terminfo::errors::*;
+use terminfo::ext::{self, ExtCapability, ExtValue};
 use terminfo::fields::*;
+use terminfo::lang::Argument;
+use terminfo::signature;
 use terminfo::strtab::{StrTable, StringTable};
+use terminfo::warnings::*;
 use util::{invalid, read_le_u16, read_le_u32};
 
 /// TermInfo is immutable terminfo data.
@@ -14,7 +22,7 @@ use util::{invalid, read_le_u16, read_le_u32};
 pub struct TermInfo<'a> {
     /// True if this file supports 32-bit numbers
     long: bool,
-    names: &'a [u8],
+    names: Cow<'a, str>,
 
     bools: &'a [u8],
     numbers: &'a [u8],
@@ -39,11 +47,43 @@ pub(crate) struct TermInfoExt<'a> {
     nametab_start: usize,
 }
 
+/// Read number `i` from a numbers section, or `None` if it's negative (-1 if it's absent, or -2 if it was cancelled).
+fn read_number(numbers: &[u8], long: bool, i: usize) -> Option<u32> {
+    let number = if long {
+        if (i + 1) * 4 > numbers.len() {
+            return None;
+        }
+        read_le_u32(numbers, i) as i32
+    } else {
+        if (i + 1) * 2 > numbers.len() {
+            return None;
+        }
+        read_le_u16(numbers, i) as i16 as i32
+    };
+    if number < 0 {
+        None
+    } else {
+        Some(number as u32)
+    }
+}
+
+/// `bytes`, the value of `field`, as a `&str`.
+pub(crate) fn utf8(field: StringField, bytes: &[u8]) -> Result<&str> {
+    str::from_utf8(bytes).map_err(|e| {
+        ErrorKind::NotUtf8 {
+            cap: field,
+            valid_up_to: e.valid_up_to(),
+        }
+        .into()
+    })
+}
+
 /// Split a terminfo file into the fields of a `terminfo` struct.
 ///
 /// This function hardly analyzes the data at all, it just finds each section
-/// and creates a new terminfo struct based on those sections.
-fn split_terminfo<'a>(bytes: &'a [u8]) -> Result<TermInfo<'a>> {
+/// and creates a new terminfo struct based on those sections. Defects that can be worked around are added to
+/// `warnings` instead of failing.
+fn split_terminfo<'a>(bytes: &'a [u8], warnings: &mut Warnings) -> Result<TermInfo<'a>> {
     let file_size = bytes.len();
 
     // Terminfo files start with a 12-byte header, made up of 6 16-bit fields.
@@ -66,11 +106,15 @@ fn split_terminfo<'a>(bytes: &'a [u8]) -> Result<TermInfo<'a>> {
     let strings_count = read_le_u16(bytes, 4) as usize;
     let strtab_size = read_le_u16(bytes, 5) as usize;
 
-    let mut expected_filesize =
-        12 + bools_count + numbers_count * num_bytes + strings_count * 2 + strtab_size + names_size;
-    if bools_count + names_size % 2 != 0 {
-        expected_filesize += 1;
-    }
+    // the numbers are 2 byte aligned
+    let padding = (bools_count + names_size) % 2;
+    let expected_filesize = 12
+        + names_size
+        + bools_count
+        + padding
+        + numbers_count * num_bytes
+        + strings_count * 2
+        + strtab_size;
 
     // make sure the file's length and the section lengths match up
     // use <= here because they length might not match exactly, because of extensions
@@ -81,35 +125,63 @@ fn split_terminfo<'a>(bytes: &'a [u8]) -> Result<TermInfo<'a>> {
     let mut slice = &bytes[12..];
 
     // subtract one so we ignore the null terminator
-    let names = &slice[..names_size - 1];
+    let names = match str::from_utf8(&slice[..names_size - 1]) {
+        Ok(v) => Cow::Borrowed(v),
+        Err(e) => {
+            warnings.push(WarningKind::InvalidName, Location::Offset(12 + e.valid_up_to()));
+            String::from_utf8_lossy(&slice[..names_size - 1])
+        }
+    };
     slice = &slice[names_size..];
 
     let bools = &slice[..bools_count];
 
-    // 2 byte align
-    slice = if (bools_count + names_size) % 2 != 0 {
-        &slice[bools_count + 1..]
-    } else {
-        &slice[bools_count..]
-    };
+    slice = &slice[bools_count + padding..];
 
     let numbers = &slice[..numbers_count * num_bytes];
     slice = &slice[numbers_count * num_bytes..];
 
+    let strings_start = file_size - slice.len();
     let strings = &slice[..strings_count * 2];
     slice = &slice[strings_count * 2..];
 
-    let strtab = &slice[..strtab_size];
+    let strtab = StrTable::new(&slice[..strtab_size]);
+    for i in 0..strings_count {
+        let offset = read_le_u16(strings, i);
+        if offset == invalid() {
+            continue;
+        }
+        let location = if i < PREDEFINED_STRINGS_COUNT {
+            Location::String(unsafe { mem::transmute::<usize, StringField>(i) })
+        } else {
+            Location::Offset(strings_start + i * 2)
+        };
+        match strtab.get_slice(offset as usize) {
+            Ok(v) if str::from_utf8(v).is_err() => warnings.push(WarningKind::InvalidString, location),
+            Ok(_) => (),
+            Err(_) => warnings.push(
+                WarningKind::StringOutOfRange {
+                    offset: offset as usize,
+                    table_len: strtab_size,
+                },
+                location,
+            ),
+        }
+    }
 
     let ext = if expected_filesize < file_size {
-        Some(split_terminfo_ext(
-            if strtab_size % 2 != 0 {
-                &slice[strtab_size + 1..]
-            } else {
-                &slice[strtab_size..]
-            },
-            num_bytes,
-        )?)
+        // the extended section is 2 byte aligned too
+        let ext_bytes = &slice[strtab_size + strtab_size % 2..];
+        match split_terminfo_ext(ext_bytes, num_bytes) {
+            Ok(v) => Some(v),
+            Err(e) => {
+                warnings.push(
+                    WarningKind::InvalidExtendedSection(e.kind().clone()),
+                    Location::Offset(file_size - ext_bytes.len()),
+                );
+                None
+            }
+        }
     } else {
         None
     };
@@ -120,7 +192,7 @@ fn split_terminfo<'a>(bytes: &'a [u8]) -> Result<TermInfo<'a>> {
         bools: bools,
         numbers: numbers,
         strings: strings,
-        strtab: StrTable::new(strtab),
+        strtab,
         ext: ext,
     })
 }
@@ -198,19 +270,19 @@ fn split_terminfo_ext<'a>(bytes: &'a [u8], num_bytes: usize) -> Result<TermInfoE
 }
 
 impl<'a> TermInfoExt<'a> {
+    fn numbers_count(&self) -> usize {
+        self.numbers.len() / if self.long { 4 } else { 2 }
+    }
+
     pub(crate) fn get_tables(&self) -> (StringTable, StringTable) {
         self.strtab.split(self.nametab_start)
     }
 
+    /// The numbers, with absent and cancelled ones as `invalid()`.
     pub(crate) fn get_numbers(&self) -> Vec<u32> {
-        if self.long {
-            self.numbers.chunks(4).map(|n| read_le_u32(n, 0)).collect()
-        } else {
-            self.numbers
-                .chunks(2)
-                .map(|n| read_le_u16(n, 0) as u32)
-                .collect()
-        }
+        (0..self.numbers_count())
+            .map(|i| read_number(self.numbers, self.long, i).unwrap_or(invalid()))
+            .collect()
     }
 
     pub(crate) fn get_string_offsets(&self) -> Vec<u16> {
@@ -250,7 +322,18 @@ impl<'a> TermInfo<'a> {
     /// ```
     ///
     pub fn parse(bytes: &'a [u8]) -> Result<TermInfo<'a>> {
-        split_terminfo(bytes)
+        TermInfo::parse_with_warnings(bytes).map(|(info, _)| info)
+    }
+
+    /// Parse a terminfo file, and report the defects that were worked around while parsing it.
+    ///
+    /// Real terminfo databases have entries with small mistakes in them, like strings that aren't valid UTF-8.
+    /// Rather than failing, the broken parts are skipped (so `TermInfo::string` returns `None` for a broken
+    /// string), and a `Warning` says what was skipped and where.
+    pub fn parse_with_warnings(bytes: &'a [u8]) -> Result<(TermInfo<'a>, Warnings)> {
+        let mut warnings = Warnings::new();
+        let info = split_terminfo(bytes, &mut warnings)?;
+        Ok((info, warnings))
     }
 
     /// Get an iterator over the terminal's name(s)
@@ -259,28 +342,44 @@ impl<'a> TermInfo<'a> {
     /// The following names will usually be longer. Sometimes they will describe this terminal (e.g. linux-16color's second name is "linux console with 16 colors").
     /// Other names may expand on the first name if it is an acronym (e.g. kitty's second name is "KovIdTTY").
     pub fn names(&self) -> impl Iterator<Item = &str> {
-        self.names
-            .split(|&c| c == b'|')
-            .map(|slice| unsafe { mem::transmute::<&[u8], &str>(slice) })
+        self.names.split('|')
     }
 
     pub(crate) fn get_strtab(&self) -> StringTable {
         self.strtab.to_string_table()
     }
 
+    /// The numbers, with absent and cancelled ones as `invalid()`.
     pub(crate) fn get_numbers(&self) -> Vec<u32> {
-        if self.long {
-            self.numbers.chunks(4).map(|n| read_le_u32(n, 0)).collect()
-        } else {
-            self.numbers
-                .chunks(2)
-                .map(|n| read_le_u16(n, 0) as u32)
-                .collect()
-        }
+        (0..self.numbers.len() / if self.long { 4 } else { 2 })
+            .map(|i| read_number(self.numbers, self.long, i).unwrap_or(invalid()))
+            .collect()
     }
 
+    /// The offset of each string in the string table, strings that were skipped while parsing are left out. Strings
+    /// that aren't UTF-8 are kept, they're only left out of `TermInfoBuf::string`.
     pub(crate) fn get_string_offsets(&self) -> Vec<u16> {
-        self.strings.chunks(2).map(|n| read_le_u16(n, 0)).collect()
+        (0..self.strings.len() / 2)
+            .map(|i| match self.bytes_at(i) {
+                Some(_) => read_le_u16(self.strings, i),
+                None => invalid(),
+            })
+            .collect()
+    }
+
+    /// The `i`th string, if it's present and valid.
+    fn string_at(&self, i: usize) -> Option<&str> {
+        self.bytes_at(i).and_then(|v| str::from_utf8(v).ok())
+    }
+
+    pub(crate) fn bytes_at(&self, i: usize) -> Option<&'a [u8]> {
+        if i * 2 < self.strings.len() {
+            let offset = read_le_u16(self.strings, i);
+            if offset != invalid() {
+                return self.strtab.get_slice(offset as usize).ok();
+            }
+        }
+        None
     }
 
     pub(crate) fn get_bools(&self) -> Vec<bool> {
@@ -307,27 +406,82 @@ impl<'a> TermInfo<'a> {
         }
     }
 
+    /// The name of every extended capability, booleans first, then numbers, then strings.
+    pub(crate) fn ext_names(&self) -> Vec<&'a str> {
+        let mut names = Vec::new();
+        if let Some(ref ext) = self.ext {
+            for i in 0..ext.names.len() / 2 {
+                let name = read_le_u16(ext.names, i) as usize;
+                match ext.strtab.get_slice(name + ext.nametab_start).map(str::from_utf8) {
+                    Ok(Ok(name)) => names.push(name),
+                    _ => names.push(""),
+                }
+            }
+        }
+        names
+    }
+
+    /// The name of every extended boolean capability that's set.
+    pub(crate) fn ext_booleans(&self) -> Vec<&'a str> {
+        match self.ext {
+            Some(ref ext) => self.ext_names()
+                .into_iter()
+                .zip(ext.bools)
+                .filter(|&(_, &b)| b != 0)
+                .map(|(name, _)| name)
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// The name and value of every extended numeric capability that's set.
+    pub(crate) fn ext_numbers(&self) -> Vec<(&'a str, u32)> {
+        match self.ext {
+            Some(ref ext) => self.ext_names()
+                .into_iter()
+                .skip(ext.bools.len())
+                .take(ext.numbers_count())
+                .enumerate()
+                .filter_map(|(i, name)| read_number(ext.numbers, ext.long, i).map(|n| (name, n)))
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// The name and value of every extended string capability that's set.
+    pub(crate) fn ext_strings(&self) -> Vec<(&'a str, &'a [u8])> {
+        let mut strings = Vec::new();
+        if let Some(ref ext) = self.ext {
+            let names = self.ext_names();
+            let first_name = ext.bools.len() + ext.numbers_count();
+            for (i, &name) in names.iter().skip(first_name).take(ext.strings.len() / 2).enumerate() {
+                let offset = read_le_u16(ext.strings, i);
+                if offset == invalid() {
+                    continue;
+                }
+                if let Ok(value) = ext.strtab.get_slice(offset as usize) {
+                    strings.push((name, value));
+                }
+            }
+        }
+        strings
+    }
+
     /// Get a numeric field.
     ///
     /// Not all terminals will include a value for every field enumerated in `NumericField`.
     pub fn number(&self, field: NumericField) -> Option<u32> {
-        let i = field as usize;
+        read_number(self.numbers, self.long, field as usize)
+    }
 
-        if i * 2 < self.numbers.len() {
-            let number = if self.long {
-                read_le_u32(self.numbers, i)
-            } else {
-                read_le_u16(self.numbers, i) as u32
-            };
+    /// Get the number of columns between the terminal's initial tab stops (the `it` capability).
+    pub fn tab_width(&self) -> Option<u32> {
+        self.number(NumericField::InitTabs)
+    }
 
-            if number != invalid() {
-                Some(number)
-            } else {
-                None
-            }
-        } else {
-            None
-        }
+    /// Get the number of colors the terminal supports (the `colors` capability).
+    pub fn max_colors(&self) -> Option<u32> {
+        self.number(NumericField::MaxColors)
     }
 
     /// Get a boolean field.
@@ -347,15 +501,33 @@ impl<'a> TermInfo<'a> {
     ///
     /// Not all terminals will include a value for every field enumerated in `StringField`.
     pub fn string(&self, field: StringField) -> Option<&str> {
-        let i = field as usize;
+        self.string_at(field as usize)
+    }
 
-        if i * 2 < self.strings.len() {
-            let offset = read_le_u16(self.strings, i);
-            if offset != invalid() {
-                return Some(self.strtab.get(offset as usize).unwrap());
-            }
-        }
-        None
+    /// Get a string capability's bytes, borrowed straight from the file's string table.
+    ///
+    /// Unlike `TermInfo::string` this doesn't check the string is valid UTF-8.
+    pub fn string_bytes(&self, field: StringField) -> Option<&'a [u8]> {
+        self.bytes_at(field as usize)
+    }
+
+    /// Get a string capability as a `&str`, failing with `ErrorKind::NotUtf8` rather than leaving it out if it isn't
+    /// valid UTF-8, or with `ErrorKind::MissingCapability` if the entry doesn't have it.
+    pub fn get_str_utf8(&self, field: StringField) -> Result<&'a str> {
+        utf8(field, self.get_str_bytes(field)?)
+    }
+
+    /// Get a string capability's bytes as they are, or fail with `ErrorKind::MissingCapability`.
+    pub fn get_str_bytes(&self, field: StringField) -> Result<&'a [u8]> {
+        self.string_bytes(field)
+            .ok_or_else(|| ErrorKind::MissingCapability(field).into())
+    }
+
+    /// Expand the string capability `field` with `args`, or `None` if the terminal doesn't have it.
+    ///
+    /// In debug builds the arguments are checked against what the capability expects first, see `check_arguments`.
+    pub fn format(&self, field: StringField, args: &[Argument]) -> Option<Result<Vec<u8>>> {
+        Some(signature::format(field, self.string_bytes(field)?, args))
     }
 
     /// Check if the the terminfo file has an extensions section
@@ -403,7 +575,7 @@ impl<'a> TermInfo<'a> {
     pub fn ext_string<T: AsRef<str>>(&self, field: T) -> Option<&str> {
         if let Some(ref ext) = self.ext {
             if let Some(idx) = self.ext_index(field) {
-                let idx_offset = ext.bools.len() + (ext.numbers.len() / 2);
+                let idx_offset = ext.bools.len() + ext.numbers_count();
                 if idx >= idx_offset && idx - idx_offset < (ext.strings.len() / 2) {
                     let num = read_le_u16(ext.strings, idx - idx_offset);
                     if num != invalid() {
@@ -417,6 +589,35 @@ impl<'a> TermInfo<'a> {
         }
         None
     }
+
+    /// Look up the well-known extended capability `cap`, see `ExtCapability`.
+    pub fn ext(&self, cap: ExtCapability) -> Option<ExtValue<'_>> {
+        ext::Lookup::value(self, cap)
+    }
+
+    /// Expand the extended string capability `cap` with `args`, or `None` if the terminal doesn't have it.
+    pub fn ext_format(&self, cap: ExtCapability, args: &[Argument]) -> Option<Result<Vec<u8>>> {
+        ext::Lookup::format(self, cap, args)
+    }
+
+    /// True if the terminal takes 24-bit colors, which the entry says with `Tc` or `RGB` rather than `colors`.
+    pub fn has_truecolor(&self) -> bool {
+        ext::Lookup::truecolor(self)
+    }
+}
+
+impl<'a> ext::Lookup for TermInfo<'a> {
+    fn boolean(&self, name: &str) -> bool {
+        self.ext_boolean(name)
+    }
+
+    fn number(&self, name: &str) -> Option<u32> {
+        self.ext_number(name)
+    }
+
+    fn string(&self, name: &str) -> Option<&str> {
+        self.ext_string(name)
+    }
 }
 
 #[cfg(test)]
@@ -424,9 +625,45 @@ mod test {
     const RXVT_INFO: &'static [u8] = include_bytes!("../../test-data/rxvt");
     const XTERM_INFO: &'static [u8] = include_bytes!("../../test-data/xterm");
     const LINUX_16COLOR_INFO: &'static [u8] = include_bytes!("../../test-data/linux-16color");
+    const MESSY_INFO: &'static [u8] = include_bytes!("../../test-data/messy");
 
     use terminfo::*;
 
+    #[test]
+    fn get_str() {
+        let compiled = TermInfoBuilder::new()
+            .name("vt220-8bit")
+            .set_str(ClearScreen, b"\x9bH\x9bJ")
+            .set_str(CursorInvisible, "\x1b[?25l")
+            .compile()
+            .unwrap();
+        let info = TermInfo::parse(&compiled).unwrap();
+        assert_eq!(info.get_str_utf8(CursorInvisible).unwrap(), "\x1b[?25l");
+        assert_eq!(info.get_str_bytes(ClearScreen).unwrap(), b"\x9bH\x9bJ");
+        assert_eq!(
+            *info.get_str_utf8(ClearScreen).unwrap_err().kind(),
+            ErrorKind::NotUtf8 {
+                cap: ClearScreen,
+                valid_up_to: 0,
+            }
+        );
+        assert_eq!(
+            *info.get_str_bytes(FlashScreen).unwrap_err().kind(),
+            ErrorKind::MissingCapability(FlashScreen)
+        );
+        // `string` can't tell a string that isn't UTF-8 from a missing one
+        assert_eq!(info.string(ClearScreen), None);
+
+        let buf: TermInfoBuf = info.into();
+        assert_eq!(buf.get_str_bytes(ClearScreen).unwrap(), b"\x9bH\x9bJ");
+        assert!(buf.get_str_utf8(ClearScreen).is_err());
+        assert_eq!(buf.get_str_utf8(CursorInvisible).unwrap(), "\x1b[?25l");
+        assert_eq!(
+            buf.exec(ClearScreen).unwrap().seven_bit(true).vec().unwrap(),
+            b"\x1b[H\x1b[J"
+        );
+    }
+
     #[test]
     fn names() {
         let rxvt = TermInfo::parse(RXVT_INFO).unwrap();
@@ -438,6 +675,18 @@ mod test {
         );
     }
 
+    #[test]
+    fn no_padding() {
+        // the names and booleans take up an even number of bytes, so there's no padding before the numbers
+        let info = [
+            0x1a, 0x01, 2, 0, 2, 0, 1, 0, 0, 0, 0, 0, b'a', 0, 0, 1, 0x50, 0x00,
+        ];
+        let info = TermInfo::parse(&info).unwrap();
+        assert_eq!(info.names().nth(0), Some("a"));
+        assert!(info.boolean(BooleanField::AutoRightMargin));
+        assert_eq!(info.number(NumericField::Columns), Some(80));
+    }
+
     #[test]
     fn lookup_string() {
         let rxvt = TermInfo::parse(RXVT_INFO).unwrap();
@@ -490,6 +739,9 @@ mod test {
 
         assert_eq!(l16c.number(NumericField::BitImageEntwining), None);
         assert_eq!(l16c.number(NumericField::MaxColors), Some(16));
+
+        assert_eq!(rxvt.tab_width(), Some(8));
+        assert_eq!(xterm.tab_width(), Some(8));
     }
 
     #[test]
@@ -520,4 +772,58 @@ mod test {
         assert_eq!(l16c.ext_number("U8"), None);
     }
 
+    #[test]
+    fn warnings() {
+        let (_, warnings) = TermInfo::parse_with_warnings(RXVT_INFO).unwrap();
+        assert!(warnings.is_empty());
+
+        // rxvt, with a broken name, `bel` past the end of the string table, `cr` not valid UTF-8, and the
+        // extended section cut short.
+        let (messy, warnings) = TermInfo::parse_with_warnings(MESSY_INFO).unwrap();
+        assert_eq!(
+            warnings.iter().cloned().collect::<Vec<_>>(),
+            vec![
+                Warning {
+                    kind: WarningKind::InvalidName,
+                    location: Location::Offset(15),
+                },
+                Warning {
+                    kind: WarningKind::StringOutOfRange {
+                        offset: 0x7000,
+                        table_len: 1041,
+                    },
+                    location: Location::String(Bell),
+                },
+                Warning {
+                    kind: WarningKind::InvalidString,
+                    location: Location::String(CarriageReturn),
+                },
+                Warning {
+                    kind: WarningKind::InvalidExtendedSection(ErrorKind::IncompleteExtendedTermInfo),
+                    location: Location::Offset(1902),
+                },
+            ]
+        );
+        assert_eq!(
+            warnings.iter().nth(1).unwrap().to_string(),
+            "in the string capability Bell: the string starts at 28672, past the end of the 1041 byte string \
+             table, so it was skipped"
+        );
+
+        // everything else is still there
+        assert_eq!(messy.names().nth(0), Some("rxv\u{fffd}"));
+        assert_eq!(messy.string(Bell), None);
+        assert_eq!(messy.string(CarriageReturn), None);
+        assert_eq!(messy.string_bytes(CarriageReturn), Some(&b"\xff"[..]));
+        assert_eq!(messy.string_bytes(Bell), None);
+        assert_eq!(messy.string(ClearScreen), Some("\x1b[H\x1b[2J"));
+        assert_eq!(messy.has_ext(), false);
+
+        let buf = TermInfoBuf::from(messy);
+        assert_eq!(buf.string(Bell), None);
+        assert_eq!(buf.string(CarriageReturn), None);
+        assert_eq!(buf.string(ClearScreen), Some("\x1b[H\x1b[2J"));
+
+        assert!(TermInfo::parse(MESSY_INFO).is_ok());
+    }
 }
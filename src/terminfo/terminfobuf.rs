@@ -1,7 +1,10 @@
+use std::string::String;
+use std::vec::Vec;
 use terminfo::errors::*;
+use terminfo::ext::{self, ExtCapability, ExtValue};
 use terminfo::fields::*;
 use terminfo::strtab::StringTable;
-use terminfo::{lang, TermInfo};
+use terminfo::{lang, signature, TermInfo};
 use util::invalid;
 
 /// The owning, mutable version of `TermInfo`
@@ -102,18 +105,51 @@ impl TermInfoBuf {
         }
     }
 
+    /// Get the number of columns between the terminal's initial tab stops (the `it` capability).
+    pub fn tab_width(&self) -> Option<u32> {
+        self.number(NumericField::InitTabs)
+    }
+
+    /// Get the number of colors the terminal supports (the `colors` capability).
+    pub fn max_colors(&self) -> Option<u32> {
+        self.number(NumericField::MaxColors)
+    }
+
+    /// Get a string field, or `None` if the entry doesn't have it or it isn't valid UTF-8 (see `get_str_utf8`).
     pub fn string(&self, field: StringField) -> Option<&str> {
-        if let Ok(s) = self.strtab.get(
-            self.strings
-                .iter()
-                .nth(field as usize)
-                .map(|&x| x as usize)
-                .unwrap_or(invalid()),
-        ) {
-            Some(s)
-        } else {
-            None
-        }
+        self.string_bytes(field)
+            .and_then(|s| ::std::str::from_utf8(s).ok())
+    }
+
+    /// Get a string capability's bytes, all strings are kept in one table, so this is a slice of it.
+    pub fn string_bytes(&self, field: StringField) -> Option<&[u8]> {
+        self.strtab
+            .get_slice(
+                self.strings
+                    .get(field as usize)
+                    .map(|&x| x as usize)
+                    .unwrap_or(invalid()),
+            )
+            .ok()
+    }
+
+    /// Get a string capability as a `&str`, failing with `ErrorKind::NotUtf8` rather than leaving it out if it isn't
+    /// valid UTF-8, or with `ErrorKind::MissingCapability` if the entry doesn't have it.
+    pub fn get_str_utf8(&self, field: StringField) -> Result<&str> {
+        ::terminfo::terminfo::utf8(field, self.get_str_bytes(field)?)
+    }
+
+    /// Get a string capability's bytes as they are, or fail with `ErrorKind::MissingCapability`.
+    pub fn get_str_bytes(&self, field: StringField) -> Result<&[u8]> {
+        self.string_bytes(field)
+            .ok_or_else(|| ErrorKind::MissingCapability(field).into())
+    }
+
+    /// Expand the string capability `field` with `args`, or `None` if the terminal doesn't have it.
+    ///
+    /// In debug builds the arguments are checked against what the capability expects first, see `check_arguments`.
+    pub fn format(&self, field: StringField, args: &[lang::Argument]) -> Option<Result<Vec<u8>>> {
+        Some(signature::format(field, self.string_bytes(field)?, args))
     }
 
     /// Execute a string
@@ -184,6 +220,21 @@ impl TermInfoBuf {
         None
     }
 
+    /// Look up the well-known extended capability `cap`, see `ExtCapability`.
+    pub fn ext(&self, cap: ExtCapability) -> Option<ExtValue<'_>> {
+        ext::Lookup::value(self, cap)
+    }
+
+    /// Expand the extended string capability `cap` with `args`, or `None` if the terminal doesn't have it.
+    pub fn ext_format(&self, cap: ExtCapability, args: &[lang::Argument]) -> Option<Result<Vec<u8>>> {
+        ext::Lookup::format(self, cap, args)
+    }
+
+    /// True if the terminal takes 24-bit colors, which the entry says with `Tc` or `RGB` rather than `colors`.
+    pub fn has_truecolor(&self) -> bool {
+        ext::Lookup::truecolor(self)
+    }
+
     #[inline]
     pub fn set_boolean(&mut self, field: BooleanField, v: bool) -> Result<()> {
         let i = field as usize;
@@ -213,7 +264,7 @@ impl TermInfoBuf {
             self.strings.push(invalid())
         }
 
-        let offset = self.strtab.add(v);
+        let offset = self.strtab.intern(v);
         if offset >= invalid::<usize>() - 1 {
             return Err(ErrorKind::MaxStrTabSizeReached.into());
         }
@@ -304,7 +355,7 @@ impl TermInfoBuf {
                 return Err(ErrorKind::MaximumCapabilityCountExceeded.into());
             }
 
-            let strtab_ref = ext.strtab.add(v) as u16;
+            let strtab_ref = ext.strtab.intern(v) as u16;
             if strtab_ref >= invalid::<u16>() - 1 {
                 return Err(ErrorKind::MaxStrTabSizeReached.into());
             }
@@ -327,7 +378,7 @@ impl TermInfoBuf {
         }
 
         let mut ext = TermInfoExtBuf::new();
-        let strtab_ref = ext.strtab.add(v) as u16;
+        let strtab_ref = ext.strtab.intern(v) as u16;
         if strtab_ref >= invalid::<u16>() - 1 {
             return Err(ErrorKind::MaxStrTabSizeReached.into());
         }
@@ -345,6 +396,20 @@ impl TermInfoBuf {
     }
 }
 
+impl ext::Lookup for TermInfoBuf {
+    fn boolean(&self, name: &str) -> bool {
+        self.ext_boolean(name)
+    }
+
+    fn number(&self, name: &str) -> Option<u32> {
+        self.ext_number(name)
+    }
+
+    fn string(&self, name: &str) -> Option<&str> {
+        self.ext_string(name)
+    }
+}
+
 impl<'a> From<TermInfo<'a>> for TermInfoBuf {
     fn from(src: TermInfo<'a>) -> TermInfoBuf {
         TermInfoBuf::from_terminfo(&src)
@@ -454,6 +519,9 @@ mod test {
 
         assert_eq!(l16c.number(NumericField::BitImageEntwining), None);
         assert_eq!(l16c.number(NumericField::MaxColors), Some(16));
+
+        assert_eq!(rxvt.tab_width(), Some(8));
+        assert_eq!(TermInfoBuf::new().tab_width(), None);
     }
 
     #[test]
@@ -473,6 +541,26 @@ mod test {
         assert_eq!(new.string(StringField::WaitTone), Some("Hi"));
     }
 
+    #[test]
+    fn shared_strings() {
+        let mut rxvt: TermInfoBuf = TermInfo::parse(RXVT_INFO).unwrap().into();
+        let len = rxvt.strtab.table.len();
+
+        // "\x1b[A" is already in the table, and "A" is the end of it
+        let up = rxvt.string(StringField::CursorUp).unwrap().to_string();
+        rxvt.set_string(StringField::KeyF10, up.as_str()).unwrap();
+        rxvt.set_string(StringField::ZeroMotion, "A").unwrap();
+        assert_eq!(rxvt.strtab.table.len(), len);
+        assert_eq!(rxvt.string_bytes(StringField::KeyF10), Some(up.as_bytes()));
+        assert_eq!(rxvt.string_bytes(StringField::ZeroMotion), Some(&b"A"[..]));
+
+        rxvt.set_string(StringField::WaitTone, "new").unwrap();
+        assert_eq!(rxvt.strtab.table.len(), len + 4);
+        assert_eq!(rxvt.string_bytes(StringField::WaitTone), Some(&b"new"[..]));
+        assert_eq!(rxvt.string_bytes(StringField::Bell), Some(&b"\x07"[..]));
+        assert_eq!(rxvt.string_bytes(StringField::PrintScreen), None);
+    }
+
     #[test]
     fn set_bool() {
         let mut rxvt: TermInfoBuf = TermInfo::parse(RXVT_INFO).unwrap().into();
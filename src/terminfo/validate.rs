@@ -0,0 +1,364 @@
+//! Checking that an entry has what a kind of application needs before it starts, see `TermInfo::validate`.
+//!
+//! An application that finds out halfway through setting up that the terminal can't move its cursor has already
+//! switched screens and changed the tty's settings. Validating against a `Profile` first finds everything that's
+//! missing at once, along with what can be done instead, so the application can decide before it starts (or say
+//! why it won't).
+//!
+//! Where `Term` falls back to an ANSI sequence for a missing capability the suggestion is `Suggestion::Ansi`, but
+//! only for entries that look like they understand ANSI: not generic (`gn`), not hardcopy (`hc`) and not `dumb`.
+use std::fmt;
+use std::vec::Vec;
+use terminfo::{BooleanField, NumericField, StringField};
+use terminfo::{TermInfo, TermInfoBuf};
+
+/// A kind of application, and so a set of `Requirement`s.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Profile {
+    /// Takes over the whole screen, like an editor: cursor addressing, clearing the screen, the alternate screen
+    /// and the screen's size.
+    FullScreen,
+    /// Draws a few rows below the prompt (see `Term::inline_viewport`): moving the cursor up, erasing to the end of
+    /// the line, reporting where the cursor is and the screen's size.
+    InlineUi,
+    /// Prints colored text: at least 8 colors and a way to reset them.
+    ColorOutput,
+}
+
+/// Something a `Profile` needs, which one or more capabilities provide.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub enum Requirement {
+    /// Moving the cursor anywhere: `cup`, or `hpa` and `vpa`.
+    Addressing,
+    /// Clearing the screen: `clear`, or `home` and `ed`.
+    ClearScreen,
+    /// Switching to the alternate screen and back: `smcup` and `rmcup`.
+    AlternateScreen,
+    /// Knowing the screen's size: `cols` and `lines`.
+    Size,
+    /// Moving the cursor up: `cuu1` or `cuu`.
+    CursorUp,
+    /// Erasing to the end of the line: `el`.
+    ClearLine,
+    /// Asking where the cursor is: `u7`.
+    CursorReport,
+    /// Setting colors: `colors` of at least 8, and `setaf` or `setf`.
+    Colors,
+    /// Going back to the default colors: `op` or `sgr0`. Only checked when there are colors to reset.
+    ResetColors,
+}
+
+/// What can be done about a missing `Requirement`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Suggestion {
+    /// `Term` sends this ANSI sequence instead (written the way terminfo writes it), unless the degradation policy
+    /// is `Strict`.
+    Ansi(&'static str),
+    /// The application can get by doing this instead.
+    Workaround(&'static str),
+}
+
+/// A `Requirement` the entry doesn't meet.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Missing {
+    pub requirement: Requirement,
+    /// What to do instead, or `None` if nothing will do.
+    pub suggestion: Option<Suggestion>,
+}
+
+/// What `TermInfo::validate` found.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct ValidationReport {
+    pub profile: Profile,
+    /// Every requirement that isn't met, in the order `Profile::requirements` lists them.
+    pub missing: Vec<Missing>,
+}
+
+impl Profile {
+    /// What this profile needs.
+    pub fn requirements(&self) -> &'static [Requirement] {
+        use self::Requirement::*;
+        match *self {
+            Profile::FullScreen => &[Addressing, ClearScreen, AlternateScreen, Size],
+            Profile::InlineUi => &[CursorUp, ClearLine, CursorReport, Size],
+            Profile::ColorOutput => &[Colors, ResetColors],
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        match *self {
+            Profile::FullScreen => "a full-screen application",
+            Profile::InlineUi => "an inline UI",
+            Profile::ColorOutput => "colored output",
+        }
+    }
+}
+
+impl Requirement {
+    /// The capabilities that provide this, the way a person would list them.
+    pub fn capabilities(&self) -> &'static str {
+        match *self {
+            Requirement::Addressing => "cup, or hpa and vpa",
+            Requirement::ClearScreen => "clear, or home and ed",
+            Requirement::AlternateScreen => "smcup and rmcup",
+            Requirement::Size => "cols and lines",
+            Requirement::CursorUp => "cuu1 or cuu",
+            Requirement::ClearLine => "el",
+            Requirement::CursorReport => "u7",
+            Requirement::Colors => "colors, and setaf or setf",
+            Requirement::ResetColors => "op or sgr0",
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        match *self {
+            Requirement::Addressing => "cursor addressing",
+            Requirement::ClearScreen => "clearing the screen",
+            Requirement::AlternateScreen => "the alternate screen",
+            Requirement::Size => "the screen's size",
+            Requirement::CursorUp => "moving the cursor up",
+            Requirement::ClearLine => "erasing to the end of the line",
+            Requirement::CursorReport => "reporting the cursor's position",
+            Requirement::Colors => "at least 8 colors",
+            Requirement::ResetColors => "resetting the colors",
+        }
+    }
+
+    /// What can be done without it, `ansi` is whether the entry understands ANSI sequences.
+    fn suggestion(&self, ansi: bool) -> Option<Suggestion> {
+        let sequence = match *self {
+            Requirement::Addressing => "\\E[%i%p1%d;%p2%dH",
+            Requirement::ClearScreen => "\\E[H\\E[2J",
+            Requirement::ClearLine => "\\E[K",
+            Requirement::CursorReport => "\\E[6n",
+            Requirement::ResetColors => "\\E[m",
+            Requirement::AlternateScreen => {
+                return Some(Suggestion::Workaround("draw over the primary screen, and clear it afterwards"))
+            }
+            Requirement::Size => return Some(Suggestion::Workaround("ask the tty, or read $COLUMNS and $LINES")),
+            // `Term` has nothing to send instead of `cuu1`
+            Requirement::CursorUp => return None,
+            Requirement::Colors => return Some(Suggestion::Workaround("print without color")),
+        };
+        if ansi {
+            Some(Suggestion::Ansi(sequence))
+        } else {
+            None
+        }
+    }
+}
+
+impl ValidationReport {
+    /// True if nothing is missing.
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty()
+    }
+
+    /// True if there's something to do about everything that's missing, so the profile's application can run (if
+    /// not as well).
+    pub fn is_usable(&self) -> bool {
+        self.missing.iter().all(|m| m.suggestion.is_some())
+    }
+
+    /// True if `requirement` is missing.
+    pub fn lacks(&self, requirement: Requirement) -> bool {
+        self.missing.iter().any(|m| m.requirement == requirement)
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_complete() {
+            return write!(f, "the terminal has everything {} needs", self.profile.description());
+        }
+        write!(f, "the terminal is missing what {} needs:", self.profile.description())?;
+        for (i, m) in self.missing.iter().enumerate() {
+            let sep = if i == 0 { "" } else { ";" };
+            write!(f, "{} {} ({})", sep, m.requirement.description(), m.requirement.capabilities())?;
+            match m.suggestion {
+                Some(Suggestion::Ansi(seq)) => write!(f, ", {} will be sent instead", seq)?,
+                Some(Suggestion::Workaround(how)) => write!(f, ", {} instead", how)?,
+                None => write!(f, ", and nothing can stand in for it")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn validate<S, N, B>(string: S, number: N, boolean: B, ansi: bool, profile: Profile) -> ValidationReport
+where
+    S: Fn(StringField) -> bool,
+    N: Fn(NumericField) -> Option<u32>,
+    B: Fn(BooleanField) -> bool,
+{
+    use terminfo::BooleanField::*;
+    use terminfo::NumericField::*;
+    use terminfo::StringField::*;
+
+    let ansi = ansi && !boolean(GenericType) && !boolean(HardCopy);
+    let colors = number(MaxColors).unwrap_or(0) >= 8 && (string(SetAForeground) || string(SetForeground));
+    let met = |requirement| match requirement {
+        Requirement::Addressing => string(CursorAddress) || (string(ColumnAddress) && string(RowAddress)),
+        Requirement::ClearScreen => string(ClearScreen) || (string(CursorHome) && string(ClrEos)),
+        Requirement::AlternateScreen => string(EnterCaMode) && string(ExitCaMode),
+        Requirement::Size => number(Columns).unwrap_or(0) > 0 && number(Lines).unwrap_or(0) > 0,
+        Requirement::CursorUp => string(CursorUp) || string(ParmUpCursor),
+        Requirement::ClearLine => string(ClrEol),
+        Requirement::CursorReport => string(User7),
+        Requirement::Colors => colors,
+        // without colors there's nothing to reset
+        Requirement::ResetColors => !colors || string(OrigPair) || string(ExitAttributeMode),
+    };
+
+    ValidationReport {
+        profile,
+        missing: profile
+            .requirements()
+            .iter()
+            .filter(|&&requirement| !met(requirement))
+            .map(|&requirement| Missing {
+                requirement,
+                suggestion: requirement.suggestion(ansi),
+            })
+            .collect(),
+    }
+}
+
+impl<'a> TermInfo<'a> {
+    /// Check this entry against `profile`, finding every requirement it doesn't meet and what could be done instead,
+    /// see the module documentation.
+    pub fn validate(&self, profile: Profile) -> ValidationReport {
+        validate(
+            |field| self.string_bytes(field).is_some(),
+            |field| self.number(field),
+            |field| self.boolean(field),
+            self.names().next() != Some("dumb"),
+            profile,
+        )
+    }
+}
+
+impl TermInfoBuf {
+    /// Check this entry against `profile`, finding every requirement it doesn't meet and what could be done instead,
+    /// see the module documentation.
+    pub fn validate(&self, profile: Profile) -> ValidationReport {
+        validate(
+            |field| self.string_bytes(field).is_some(),
+            |field| self.number(field),
+            |field| self.boolean(field),
+            self.names.first().map(|name| name.as_str()) != Some("dumb"),
+            profile,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use terminfo::validate::*;
+    use terminfo::TermInfoBuilder;
+
+    const DUMB_INFO: &'static [u8] = include_bytes!("../../test-data/dumb");
+    const VT100_INFO: &'static [u8] = include_bytes!("../../test-data/vt100");
+    const XTERM_INFO: &'static [u8] = include_bytes!("../../test-data/xterm");
+
+    fn missing(info: &TermInfo, profile: Profile) -> Vec<(Requirement, Option<Suggestion>)> {
+        let report = info.validate(profile);
+        assert_eq!(report.profile, profile);
+        report.missing.iter().map(|m| (m.requirement, m.suggestion)).collect()
+    }
+
+    #[test]
+    fn dumb() {
+        use self::Requirement::*;
+        let info = TermInfo::parse(DUMB_INFO).unwrap();
+        let workaround = |r: Requirement| r.suggestion(false);
+        assert_eq!(
+            missing(&info, Profile::FullScreen),
+            vec![
+                (Addressing, None),
+                (ClearScreen, None),
+                (AlternateScreen, workaround(AlternateScreen)),
+                (Size, workaround(Size)),
+            ]
+        );
+        assert_eq!(
+            missing(&info, Profile::InlineUi),
+            vec![(CursorUp, None), (ClearLine, None), (CursorReport, None), (Size, workaround(Size))]
+        );
+        assert_eq!(missing(&info, Profile::ColorOutput), vec![(Colors, workaround(Colors))]);
+
+        let report = info.validate(Profile::FullScreen);
+        assert!(!report.is_usable());
+        assert!(report.lacks(Addressing));
+        assert!(info.validate(Profile::ColorOutput).is_usable());
+    }
+
+    #[test]
+    fn vt100() {
+        let info = TermInfo::parse(VT100_INFO).unwrap();
+        let report = info.validate(Profile::FullScreen);
+        assert_eq!(
+            report.missing,
+            vec![Missing {
+                requirement: Requirement::AlternateScreen,
+                suggestion: Some(Suggestion::Workaround("draw over the primary screen, and clear it afterwards")),
+            }]
+        );
+        assert!(report.is_usable() && !report.is_complete());
+        assert!(info.validate(Profile::InlineUi).is_complete());
+        assert_eq!(
+            missing(&info, Profile::ColorOutput),
+            vec![(Requirement::Colors, Some(Suggestion::Workaround("print without color")))]
+        );
+    }
+
+    #[test]
+    fn xterm() {
+        let info = TermInfo::parse(XTERM_INFO).unwrap();
+        for &profile in [Profile::FullScreen, Profile::InlineUi, Profile::ColorOutput].iter() {
+            assert!(info.validate(profile).is_complete(), "{:?}", profile);
+        }
+        assert_eq!(
+            format!("{}", info.validate(Profile::InlineUi)),
+            "the terminal has everything an inline UI needs"
+        );
+
+        // the same checks work on a `TermInfoBuf`, and fall back to ANSI sequences on an entry that isn't dumb
+        let buf = TermInfoBuilder::new()
+            .name("xterm-nocup")
+            .cancel(StringField::CursorAddress)
+            .cancel(StringField::RowAddress)
+            .use_entry(&info)
+            .build()
+            .unwrap();
+        let report = buf.validate(Profile::FullScreen);
+        assert_eq!(
+            report.missing,
+            vec![Missing {
+                requirement: Requirement::Addressing,
+                suggestion: Some(Suggestion::Ansi("\\E[%i%p1%d;%p2%dH")),
+            }]
+        );
+        assert_eq!(
+            format!("{}", report),
+            "the terminal is missing what a full-screen application needs: cursor addressing (cup, or hpa and vpa), \
+             \\E[%i%p1%d;%p2%dH will be sent instead"
+        );
+    }
+
+    #[test]
+    fn generic() {
+        let info = TermInfoBuilder::new()
+            .name("generic")
+            .set_bool(BooleanField::GenericType, true)
+            .build()
+            .unwrap();
+        assert_eq!(
+            format!("{}", info.validate(Profile::InlineUi)),
+            "the terminal is missing what an inline UI needs: moving the cursor up (cuu1 or cuu), and nothing can \
+             stand in for it; erasing to the end of the line (el), and nothing can stand in for it; reporting the \
+             cursor's position (u7), and nothing can stand in for it; the screen's size (cols and lines), ask the \
+             tty, or read $COLUMNS and $LINES instead"
+        );
+    }
+}
@@ -0,0 +1,121 @@
+use std::fmt;
+use std::slice;
+use std::vec::Vec;
+use std::vec;
+use terminfo::errors::ErrorKind;
+use terminfo::fields::StringField;
+
+/// A defect in a terminfo file that was worked around while parsing it.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Warning {
+    pub kind: WarningKind,
+    pub location: Location,
+}
+
+/// What was wrong, and what the parser did about it.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum WarningKind {
+    /// The terminal's names weren't valid UTF-8, the invalid bytes were replaced with U+FFFD.
+    InvalidName,
+
+    /// A string capability pointed past the end of the string table, it was skipped.
+    StringOutOfRange { offset: usize, table_len: usize },
+
+    /// A string capability wasn't valid UTF-8, it was skipped.
+    InvalidString,
+
+    /// The extended capabilities couldn't be read, they were all skipped.
+    InvalidExtendedSection(ErrorKind),
+}
+
+/// Where in the file a `Warning` is.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Location {
+    /// A byte offset from the start of the file.
+    Offset(usize),
+    /// A standard string capability.
+    String(StringField),
+}
+
+/// The warnings from parsing a terminfo file, in the order they were found.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Warnings {
+    warnings: Vec<Warning>,
+}
+
+impl Warnings {
+    pub fn new() -> Warnings {
+        Warnings::default()
+    }
+
+    pub(crate) fn push(&mut self, kind: WarningKind, location: Location) {
+        self.warnings.push(Warning { kind, location });
+    }
+
+    pub fn iter(&self) -> slice::Iter<'_, Warning> {
+        self.warnings.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.warnings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.warnings.is_empty()
+    }
+}
+
+impl IntoIterator for Warnings {
+    type Item = Warning;
+    type IntoIter = vec::IntoIter<Warning>;
+
+    fn into_iter(self) -> vec::IntoIter<Warning> {
+        self.warnings.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Warnings {
+    type Item = &'a Warning;
+    type IntoIter = slice::Iter<'a, Warning>;
+
+    fn into_iter(self) -> slice::Iter<'a, Warning> {
+        self.warnings.iter()
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.location, self.kind)
+    }
+}
+
+impl fmt::Display for WarningKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            WarningKind::InvalidName => write!(
+                f,
+                "the terminal's names aren't valid UTF-8, the invalid bytes were replaced"
+            ),
+            WarningKind::StringOutOfRange { offset, table_len } => write!(
+                f,
+                "the string starts at {}, past the end of the {} byte string table, so it was skipped",
+                offset, table_len
+            ),
+            WarningKind::InvalidString => write!(f, "the string isn't valid UTF-8, so it was skipped"),
+            WarningKind::InvalidExtendedSection(ref e) => write!(
+                f,
+                "the extended capabilities were skipped because {}",
+                e
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Location::Offset(offset) => write!(f, "at byte {}", offset),
+            Location::String(field) => write!(f, "in the string capability {:?}", field),
+        }
+    }
+}
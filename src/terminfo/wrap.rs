@@ -0,0 +1,88 @@
+//! What a terminal does when text reaches the right margin.
+//!
+//! Without `am` the cursor stays in the last column, and the next character replaces the one there. With it the
+//! text wraps onto the next line, and when that happens depends on `xenl`:
+//!
+//! - with `xenl` (like xterm) the terminal waits for the next character before wrapping. Writing the last column
+//!   leaves the cursor there, and a newline straight afterwards is ignored.
+//! - without it the cursor wraps as soon as the last column is written, so writing the bottom right cell scrolls
+//!   the whole screen up a line.
+//!
+//! `sam` is described too, on those terminals printing in the last column causes a carriage return.
+use terminfo::{AutoRightMargin, EatNewlineGlitch, SemiAutoRightMargin};
+use terminfo::{TermInfo, TermInfoBuf};
+
+/// The capabilities that decide what happens at the right margin, see the module documentation.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct WrapBehavior {
+    /// `am`, text wraps at the right margin.
+    pub auto_margins: bool,
+    /// `xenl`, the terminal waits for the next character before wrapping.
+    pub eat_newline: bool,
+    /// `sam`, printing in the last column causes a carriage return.
+    pub semi_auto_margins: bool,
+}
+
+impl WrapBehavior {
+    /// True if writing the bottom right cell scrolls the screen, so it has to be drawn some other way.
+    pub fn scrolls_at_bottom_right(&self) -> bool {
+        self.auto_margins && !self.eat_newline
+    }
+}
+
+impl<'a> TermInfo<'a> {
+    /// What this terminal does when text reaches the right margin.
+    pub fn wrap_behavior(&self) -> WrapBehavior {
+        WrapBehavior {
+            auto_margins: self.boolean(AutoRightMargin),
+            eat_newline: self.boolean(EatNewlineGlitch),
+            semi_auto_margins: self.boolean(SemiAutoRightMargin),
+        }
+    }
+}
+
+impl TermInfoBuf {
+    /// What this terminal does when text reaches the right margin.
+    pub fn wrap_behavior(&self) -> WrapBehavior {
+        WrapBehavior {
+            auto_margins: self.boolean(AutoRightMargin),
+            eat_newline: self.boolean(EatNewlineGlitch),
+            semi_auto_margins: self.boolean(SemiAutoRightMargin),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use terminfo::wrap::*;
+
+    const XTERM_INFO: &'static [u8] = include_bytes!("../../test-data/xterm");
+
+    #[test]
+    fn from_entry() {
+        let xterm = TermInfo::parse(XTERM_INFO).unwrap().wrap_behavior();
+        assert_eq!(
+            xterm,
+            WrapBehavior {
+                auto_margins: true,
+                eat_newline: true,
+                semi_auto_margins: false,
+            }
+        );
+        assert!(!xterm.scrolls_at_bottom_right());
+
+        let mut info = TermInfoBuf::new();
+        assert!(!info.wrap_behavior().scrolls_at_bottom_right());
+        info.set_boolean(AutoRightMargin, true).unwrap();
+        info.set_boolean(SemiAutoRightMargin, true).unwrap();
+        assert_eq!(
+            info.wrap_behavior(),
+            WrapBehavior {
+                auto_margins: true,
+                eat_newline: false,
+                semi_auto_margins: true,
+            }
+        );
+        assert!(info.wrap_behavior().scrolls_at_bottom_right());
+    }
+}
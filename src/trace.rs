@@ -0,0 +1,161 @@
+//! Tracing the escape sequences going to and coming from the terminal, with the `trace` feature.
+//!
+//! Without the feature `Output` only passes writes through, so nothing is parsed or formatted.
+use std::io;
+
+/// The terminal's output stream, which logs every escape sequence written to it when the `trace` feature is on.
+pub(crate) struct Output<O> {
+    inner: O,
+    #[cfg(feature = "trace")]
+    tracer: Tracer,
+}
+
+#[cfg(feature = "trace")]
+struct Tracer {
+    parser: ::ansi::Parser,
+    describer: ::ansi::describe::Describer,
+}
+
+impl<O> Output<O> {
+    pub(crate) fn new(inner: O) -> Output<O> {
+        Output {
+            inner,
+            #[cfg(feature = "trace")]
+            tracer: Tracer {
+                parser: ::ansi::Parser::new(),
+                describer: ::ansi::describe::Describer::new(false),
+            },
+        }
+    }
+
+    pub(crate) fn get_mut(&mut self) -> &mut O {
+        &mut self.inner
+    }
+}
+
+impl<O: io::Write> io::Write for Output<O> {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        #[cfg(feature = "trace")]
+        self.tracer.trace(&buf[..n]);
+        Ok(n)
+    }
+
+    #[inline]
+    fn write_vectored(&mut self, bufs: &[io::IoSlice]) -> io::Result<usize> {
+        let n = self.inner.write_vectored(bufs)?;
+        #[cfg(feature = "trace")]
+        for buf in ::util::written(bufs, n) {
+            self.tracer.trace(buf);
+        }
+        Ok(n)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(feature = "trace")]
+impl Tracer {
+    fn trace(&mut self, bytes: &[u8]) {
+        // the parser has to see everything even while nothing's logged, or it'd be lost in the middle of a sequence
+        // once logging is turned on
+        self.parser.feed(&mut self.describer, bytes);
+        let lines = self.describer.take();
+        if ::log::log_enabled!(target: "nixterm::output", ::log::Level::Trace) {
+            for line in lines {
+                ::log::trace!(target: "nixterm::output", "{}", line);
+            }
+        }
+    }
+}
+
+/// Log the input sequence `bytes`, which was decoded as `key`.
+#[cfg(feature = "trace")]
+pub(crate) fn trace_input(bytes: &[u8], key: &::events::Key) {
+    if ::log::log_enabled!(target: "nixterm::input", ::log::Level::Trace) {
+        let description = ::ansi::describe(bytes).join(", ");
+        ::log::trace!(target: "nixterm::input", "{} -> {:?}", description, key);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Write;
+    use trace::Output;
+
+    #[test]
+    fn passthrough() {
+        let mut out = Output::new(Vec::new());
+        out.write_all(b"\x1b[1mhi").unwrap();
+        assert_eq!(out.inner, b"\x1b[1mhi");
+
+        // with the feature off there's nothing to trace with
+        #[cfg(not(feature = "trace"))]
+        assert_eq!(
+            ::std::mem::size_of::<Output<Vec<u8>>>(),
+            ::std::mem::size_of::<Vec<u8>>()
+        );
+    }
+
+    #[cfg(feature = "trace")]
+    #[test]
+    fn traced() {
+        use log::{self, Log, Metadata, Record};
+        use std::sync::Mutex;
+        use std::thread::{self, ThreadId};
+
+        /// Records what's logged, the other tests write to terminals at the same time so keep the thread too.
+        struct Logger(Mutex<Vec<(ThreadId, String)>>);
+
+        impl Log for Logger {
+            fn enabled(&self, metadata: &Metadata) -> bool {
+                metadata.target().starts_with("nixterm::")
+            }
+
+            fn log(&self, record: &Record) {
+                if self.enabled(record.metadata()) {
+                    let line = format!("{}: {}", record.target(), record.args());
+                    self.0.lock().unwrap().push((thread::current().id(), line));
+                }
+            }
+
+            fn flush(&self) {}
+        }
+
+        static LOGGER: Logger = Logger(Mutex::new(Vec::new()));
+        log::set_logger(&LOGGER).unwrap();
+
+        let mut out = Output::new(Vec::new());
+        // a sequence that started before logging was turned on is still found
+        log::set_max_level(log::LevelFilter::Off);
+        out.write_all(b"\x1b[3").unwrap();
+        log::set_max_level(log::LevelFilter::Trace);
+        out.write_all(b"1m").unwrap();
+        // a sequence split between writes is still traced once it's finished
+        out.write_all(b"hi\x1b[38;5").unwrap();
+        out.write_all(b";208m\r\n").unwrap();
+        ::trace::trace_input(b"\x1bOA", &::events::Key::Up);
+        let lines: Vec<String> = LOGGER
+            .0
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|&&(id, _)| id == thread::current().id())
+            .map(|&(_, ref line)| line.clone())
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                "nixterm::output: CSI 31 m  (set fg red)",
+                "nixterm::output: CSI 38;5;208 m  (set fg indexed 208)",
+                "nixterm::output: CR  (carriage return)",
+                "nixterm::output: LF  (line feed)",
+                "nixterm::input: SS3 A  (up) -> Up",
+            ]
+        );
+    }
+}
@@ -1,11 +1,13 @@
 //! Private utility functions and structures
+#[cfg(feature = "std")]
 use errors::*;
-use failure::ResultExt;
 use memchr::memchr;
+#[cfg(feature = "std")]
 use std::io;
 use std::mem::{size_of, transmute};
 use std::slice::from_raw_parts;
 
+#[cfg(feature = "std")]
 const DIGITS: [u8; 36] = *b"0123456789abcdefghijklmnopqrstuvwxyz";
 
 /// Read a little endian u16 from a u8 slice.
@@ -52,6 +54,7 @@ pub fn strlen(s: &[u8]) -> usize {
 }
 
 /// Write a u8 in a ansi-escape code compatible format
+#[cfg(feature = "std")]
 #[inline]
 pub fn write_u8_ansi<W: io::Write>(w: &mut W, num: u8) -> Result<usize> {
     let mut num_buf = [0u8; 3];
@@ -77,3 +80,42 @@ pub fn write_u8_ansi<W: io::Write>(w: &mut W, num: u8) -> Result<usize> {
         .context(ErrorKind::FailedWriteToStdout)?;
     Ok(num_buf_len)
 }
+
+/// The parts of `bufs` that a vectored write of `n` bytes wrote.
+#[cfg(feature = "std")]
+pub fn written<'a>(bufs: &'a [io::IoSlice], n: usize) -> impl Iterator<Item = &'a [u8]> {
+    let mut left = n;
+    bufs.iter().map_while(move |buf| {
+        if left == 0 {
+            return None;
+        }
+        let take = left.min(buf.len());
+        left -= take;
+        Some(&buf[..take])
+    })
+}
+
+#[cfg(feature = "std")]
+const BASE64: [u8; 64] = *b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Encode `data` as padded, standard alphabet base64.
+#[cfg(feature = "std")]
+pub fn base64_encode(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).cloned().unwrap_or(0),
+            chunk.get(2).cloned().unwrap_or(0),
+        ];
+        let n = (u32::from(b[0]) << 16) | (u32::from(b[1]) << 8) | u32::from(b[2]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64[(n >> (18 - i * 6)) as usize & 0x3f]);
+            } else {
+                out.push(b'=');
+            }
+        }
+    }
+    out
+}
@@ -0,0 +1,390 @@
+//! The Windows console, with the `windows` feature.
+//!
+//! `Term` needs termios, so it's only built on Unix. The rest of the crate (the `ansi` layer, `Screen`'s buffers,
+//! `events::Key` and `Event`) works anywhere, and on Windows `Console` stands in for `Term`: it turns on the
+//! console's virtual terminal processing, so the escape sequences written to it are carried out like xterm's, and
+//! reads the console's input records as `Event`s.
+//!
+//! There's no terminfo database on Windows, `Console` uses `terminfo::from_env`, which ends up with
+//! `terminfo::builtin`.
+//!
+//! `translate_key` is built everywhere, for programs that read console input records some other way.
+use events::{self, Key};
+
+/// `SHIFT_PRESSED`, in the `dwControlKeyState` of a key event.
+pub const SHIFT_PRESSED: u32 = 0x0010;
+
+const VK_BACK: u16 = 0x08;
+const VK_TAB: u16 = 0x09;
+const VK_CLEAR: u16 = 0x0c;
+const VK_RETURN: u16 = 0x0d;
+const VK_ESCAPE: u16 = 0x1b;
+const VK_PRIOR: u16 = 0x21;
+const VK_NEXT: u16 = 0x22;
+const VK_END: u16 = 0x23;
+const VK_HOME: u16 = 0x24;
+const VK_LEFT: u16 = 0x25;
+const VK_UP: u16 = 0x26;
+const VK_RIGHT: u16 = 0x27;
+const VK_DOWN: u16 = 0x28;
+const VK_INSERT: u16 = 0x2d;
+const VK_DELETE: u16 = 0x2e;
+const VK_F1: u16 = 0x70;
+const VK_F24: u16 = 0x87;
+
+/// Translate a key press from the console, its virtual key code, the character it typed (if any) and the state of
+/// the modifier keys, into the `Key` a terminal would have sent for it.
+///
+/// Keys that don't type anything and that terminals don't send a code for, like Shift on its own, are `None`.
+/// Control characters are decoded like the bytes a terminal sends, so Ctrl+A is `Key::Control('A')`.
+pub fn translate_key(virtual_key: u16, c: Option<char>, control_key_state: u32) -> Option<Key> {
+    let key = match virtual_key {
+        VK_BACK => Key::Backspace,
+        VK_TAB if control_key_state & SHIFT_PRESSED != 0 => Key::Backtab,
+        VK_TAB => Key::Tab,
+        VK_CLEAR => Key::Clear,
+        VK_RETURN => Key::Enter,
+        VK_ESCAPE => Key::Escape,
+        VK_PRIOR => Key::PageUp,
+        VK_NEXT => Key::PageDown,
+        VK_END => Key::End,
+        VK_HOME => Key::Home,
+        VK_LEFT => Key::Left,
+        VK_UP => Key::Up,
+        VK_RIGHT => Key::Right,
+        VK_DOWN => Key::Down,
+        VK_INSERT => Key::Insert,
+        VK_DELETE => Key::Delete,
+        VK_F1..=VK_F24 => Key::Fn(usize::from(virtual_key - VK_F1) + 1),
+        _ => match c? {
+            '\0' => return None,
+            c if c.is_ascii() => events::byte_to_key(c as u8),
+            c => Key::Char(c),
+        },
+    };
+    Some(key)
+}
+
+#[cfg(windows)]
+pub use self::console::Console;
+
+#[cfg(windows)]
+mod console {
+    use errors::*;
+    use events::Event;
+    use std::collections::VecDeque;
+    use std::io::{self, Write};
+    use std::os::raw::c_void;
+    use std::time::{Duration, Instant};
+    use std::{char, mem, ptr};
+    use terminfo;
+    use trace::Output;
+    use windows::translate_key;
+
+    type Handle = *mut c_void;
+
+    const STD_INPUT_HANDLE: u32 = -10i32 as u32;
+    const STD_OUTPUT_HANDLE: u32 = -11i32 as u32;
+    const INVALID_HANDLE_VALUE: Handle = -1isize as Handle;
+
+    const ENABLE_PROCESSED_INPUT: u32 = 0x0001;
+    const ENABLE_LINE_INPUT: u32 = 0x0002;
+    const ENABLE_ECHO_INPUT: u32 = 0x0004;
+    const ENABLE_WINDOW_INPUT: u32 = 0x0008;
+    const ENABLE_VIRTUAL_TERMINAL_PROCESSING: u32 = 0x0004;
+
+    const CP_UTF8: u32 = 65001;
+
+    const KEY_EVENT: u16 = 0x0001;
+    const WINDOW_BUFFER_SIZE_EVENT: u16 = 0x0004;
+
+    const INFINITE: u32 = 0xffff_ffff;
+    const WAIT_OBJECT_0: u32 = 0;
+    const WAIT_TIMEOUT: u32 = 0x102;
+
+    #[repr(C)]
+    #[derive(Copy, Clone)]
+    struct Coord {
+        x: i16,
+        y: i16,
+    }
+
+    #[repr(C)]
+    #[derive(Copy, Clone)]
+    struct SmallRect {
+        left: i16,
+        top: i16,
+        right: i16,
+        bottom: i16,
+    }
+
+    #[repr(C)]
+    #[derive(Copy, Clone)]
+    struct ScreenBufferInfo {
+        size: Coord,
+        cursor_position: Coord,
+        attributes: u16,
+        window: SmallRect,
+        maximum_window_size: Coord,
+    }
+
+    #[repr(C)]
+    #[derive(Copy, Clone)]
+    struct KeyEventRecord {
+        key_down: i32,
+        repeat_count: u16,
+        virtual_key_code: u16,
+        virtual_scan_code: u16,
+        unicode_char: u16,
+        control_key_state: u32,
+    }
+
+    /// `INPUT_RECORD`, its event is a union of records that are at most 16 bytes long.
+    #[repr(C)]
+    #[derive(Copy, Clone)]
+    struct InputRecord {
+        event_type: u16,
+        event: [u32; 4],
+    }
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetStdHandle(std_handle: u32) -> Handle;
+        fn GetConsoleMode(console: Handle, mode: *mut u32) -> i32;
+        fn SetConsoleMode(console: Handle, mode: u32) -> i32;
+        fn GetConsoleOutputCP() -> u32;
+        fn SetConsoleOutputCP(code_page: u32) -> i32;
+        fn GetConsoleScreenBufferInfo(console: Handle, info: *mut ScreenBufferInfo) -> i32;
+        fn ReadConsoleInputW(
+            console: Handle,
+            buffer: *mut InputRecord,
+            length: u32,
+            read: *mut u32,
+        ) -> i32;
+        fn WaitForSingleObject(handle: Handle, milliseconds: u32) -> u32;
+    }
+
+    /// The Windows console, see the module documentation.
+    ///
+    /// Input is read a key at a time, without echoing it, much like a `Term` in raw mode. The console's modes and
+    /// code page are put back when it's dropped.
+    pub struct Console {
+        pub info: terminfo::TermInfoBuf,
+        input: Handle,
+        output: Handle,
+        input_mode: u32,
+        output_mode: u32,
+        code_page: u32,
+        stdout: Output<io::Stdout>,
+        /// Events from input records that have been read, but not returned yet.
+        events: VecDeque<Event>,
+        /// The first half of a character outside the BMP, which comes in two key events.
+        surrogate: Option<u16>,
+    }
+
+    /// The console `handle`'s mode, or `NotATty` if it isn't a console.
+    fn console_mode(handle: Handle) -> Result<u32> {
+        let mut mode = 0;
+        if handle.is_null()
+            || handle == INVALID_HANDLE_VALUE
+            || unsafe { GetConsoleMode(handle, &mut mode) } == 0
+        {
+            return Err(Error::NotATty);
+        }
+        Ok(mode)
+    }
+
+    fn set_console_mode(handle: Handle, mode: u32) -> Result<()> {
+        if unsafe { SetConsoleMode(handle, mode) } == 0 {
+            return Err(io::Error::last_os_error()).context(ErrorKind::TermInitFailed);
+        }
+        Ok(())
+    }
+
+    impl Console {
+        /// Take over the console the process's standard input and output are attached to.
+        ///
+        /// Fails with `NotATty` if either of them has been redirected, and with `TermInitFailed` if the console is
+        /// too old for virtual terminal processing (before Windows 10).
+        pub fn new() -> Result<Console> {
+            let info = terminfo::from_env().context(ErrorKind::FailedToCreateTermInstance)?;
+            let input = unsafe { GetStdHandle(STD_INPUT_HANDLE) };
+            let output = unsafe { GetStdHandle(STD_OUTPUT_HANDLE) };
+            let input_mode = console_mode(input)?;
+            let output_mode = console_mode(output)?;
+
+            set_console_mode(output, output_mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING)?;
+            let raw =
+                input_mode & !(ENABLE_LINE_INPUT | ENABLE_ECHO_INPUT | ENABLE_PROCESSED_INPUT);
+            if let Err(e) = set_console_mode(input, raw | ENABLE_WINDOW_INPUT) {
+                set_console_mode(output, output_mode)?;
+                return Err(e);
+            }
+            let code_page = unsafe { GetConsoleOutputCP() };
+            unsafe { SetConsoleOutputCP(CP_UTF8) };
+
+            Ok(Console {
+                info,
+                input,
+                output,
+                input_mode,
+                output_mode,
+                code_page,
+                stdout: Output::new(io::stdout()),
+                events: VecDeque::new(),
+                surrogate: None,
+            })
+        }
+
+        /// The size of the console's window, as (columns, rows).
+        pub fn size(&self) -> Result<(usize, usize)> {
+            let mut info: ScreenBufferInfo = unsafe { mem::zeroed() };
+            if unsafe { GetConsoleScreenBufferInfo(self.output, &mut info) } == 0 {
+                return Err(io::Error::last_os_error()).context(ErrorKind::FailedToGetSize);
+            }
+            let window = info.window;
+            Ok((
+                (window.right - window.left + 1) as usize,
+                (window.bottom - window.top + 1) as usize,
+            ))
+        }
+
+        /// Wait up to `timeout` (or for ever, with `None`) for the next key press or resize.
+        ///
+        /// Returns `None` if nothing happened in time. A resize is reported as the window's new size, however many
+        /// times the console said it changed since the last read.
+        pub fn read_event(&mut self, timeout: Option<Duration>) -> Result<Option<Event>> {
+            let deadline = timeout.map(|t| Instant::now() + t);
+            loop {
+                if let Some(event) = self.events.pop_front() {
+                    return Ok(Some(event));
+                }
+
+                let wait = match deadline {
+                    Some(deadline) => {
+                        let left = deadline.saturating_duration_since(Instant::now());
+                        left.as_millis().min(u128::from(INFINITE - 1)) as u32
+                    }
+                    None => INFINITE,
+                };
+                match unsafe { WaitForSingleObject(self.input, wait) } {
+                    WAIT_OBJECT_0 => self.read_records()?,
+                    WAIT_TIMEOUT => return Ok(None),
+                    _ => return Err(io::Error::last_os_error()).context(ErrorKind::ReadFailed),
+                }
+            }
+        }
+
+        /// Read the input records waiting, and queue the events they make.
+        fn read_records(&mut self) -> Result<()> {
+            let mut records: [InputRecord; 16] = unsafe { mem::zeroed() };
+            let mut read = 0;
+            let ok = unsafe {
+                ReadConsoleInputW(
+                    self.input,
+                    records.as_mut_ptr(),
+                    records.len() as u32,
+                    &mut read,
+                )
+            };
+            if ok == 0 {
+                return Err(io::Error::last_os_error()).context(ErrorKind::ReadFailed);
+            }
+
+            let mut resized = false;
+            for record in &records[..read as usize] {
+                match record.event_type {
+                    KEY_EVENT => {
+                        let key =
+                            unsafe { ptr::read(record.event.as_ptr() as *const KeyEventRecord) };
+                        self.key_event(&key);
+                    }
+                    // this is the buffer's size, which isn't always the window's
+                    WINDOW_BUFFER_SIZE_EVENT => resized = true,
+                    _ => (),
+                }
+            }
+            if resized {
+                let (cols, rows) = self.size()?;
+                self.events.push_back(Event::Resize(cols, rows));
+            }
+            Ok(())
+        }
+
+        fn key_event(&mut self, record: &KeyEventRecord) {
+            if record.key_down == 0 {
+                return;
+            }
+            let c = match record.unicode_char {
+                0 => None,
+                high @ 0xd800..=0xdbff => {
+                    self.surrogate = Some(high);
+                    return;
+                }
+                unit => {
+                    let units = match self.surrogate.take() {
+                        Some(high) => vec![high, unit],
+                        None => vec![unit],
+                    };
+                    char::decode_utf16(units).next().and_then(|c| c.ok())
+                }
+            };
+            if let Some(key) = translate_key(record.virtual_key_code, c, record.control_key_state) {
+                for _ in 0..record.repeat_count.max(1) {
+                    self.events.push_back(Event::Key(key.clone()));
+                }
+            }
+        }
+    }
+
+    impl io::Write for Console {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.stdout.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.stdout.flush()
+        }
+    }
+
+    impl Drop for Console {
+        fn drop(&mut self) {
+            let _ = self.stdout.flush();
+            unsafe {
+                SetConsoleMode(self.input, self.input_mode);
+                SetConsoleMode(self.output, self.output_mode);
+                SetConsoleOutputCP(self.code_page);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use events::Key;
+    use windows::*;
+
+    #[test]
+    fn keys() {
+        let key = |vk, c| translate_key(vk, c, 0);
+        assert_eq!(key(0x41, Some('a')), Some(Key::Char('a')));
+        assert_eq!(key(0x41, Some('\u{1}')), Some(Key::Control('A')));
+        assert_eq!(key(0x41, Some('é')), Some(Key::Char('é')));
+        assert_eq!(key(0x0d, Some('\r')), Some(Key::Enter));
+        assert_eq!(key(0x08, Some('\u{8}')), Some(Key::Backspace));
+        assert_eq!(key(0x1b, Some('\u{1b}')), Some(Key::Escape));
+        assert_eq!(key(0x26, None), Some(Key::Up));
+        assert_eq!(key(0x2e, None), Some(Key::Delete));
+        assert_eq!(key(0x70, None), Some(Key::Fn(1)));
+        assert_eq!(key(0x87, None), Some(Key::Fn(24)));
+        assert_eq!(key(0x09, Some('\t')), Some(Key::Tab));
+        assert_eq!(
+            translate_key(0x09, Some('\t'), SHIFT_PRESSED),
+            Some(Key::Backtab)
+        );
+
+        // Shift on its own
+        assert_eq!(key(0x10, None), None);
+        assert_eq!(key(0x10, Some('\0')), None);
+    }
+}
@@ -3,8 +3,8 @@
 ///! This module can also be used xterm-based terminal (rxvt, xterm-256, kitty, etc)
 use ansi;
 use errors::*;
-use failure::ResultExt;
 use std::fmt::Write;
+use util;
 
 #[derive(Debug, Clone)]
 pub enum XColor {
@@ -15,55 +15,140 @@ pub enum XColor {
 }
 
 pub fn set_icon_and_title<W: Write, T: AsRef<str>>(w: &mut W, s: T) -> Result<()> {
-    Ok(write!(w, "\x1b[0;{}\x1b\\", s.as_ref()).context(ErrorKind::OscFailed)?)
+    write_osc(w, 0, s.as_ref())
 }
 
 pub fn set_icon<W: Write, T: AsRef<str>>(w: &mut W, s: T) -> Result<()> {
-    Ok(write!(w, "\x1b[1;{}\x1b\\", s.as_ref()).context(ErrorKind::OscFailed)?)
+    write_osc(w, 1, s.as_ref())
 }
 
 pub fn set_title<W: Write, T: AsRef<str>>(w: &mut W, s: T) -> Result<()> {
-    Ok(write!(w, "\x1b[2;{}\x1b\\", s.as_ref()).context(ErrorKind::OscFailed)?)
+    write_osc(w, 2, s.as_ref())
 }
 
 pub fn reset_title<W: Write>(w: &mut W) -> Result<()> {
-    Ok(write!(w, "\x1b[2;\x1b\\").context(ErrorKind::OscFailed)?)
+    write_osc(w, 2, "")
 }
 
 pub fn set_x_property<W: Write, T: AsRef<str>, U: AsRef<str>>(w: &mut W, k: T, v: U) -> Result<()> {
-    Ok(write!(w, "\x1b[3;{}={}\x1b\\", k.as_ref(), v.as_ref()).context(ErrorKind::OscFailed)?)
+    write_osc(w, 3, &format!("{}={}", k.as_ref(), v.as_ref()))
 }
 
 pub fn remove_x_property<W: Write, T: AsRef<str>>(w: &mut W, k: T) -> Result<()> {
-    Ok(write!(w, "\x1b[3;{}\x1b\\", k.as_ref()).context(ErrorKind::OscFailed)?)
+    write_osc(w, 3, k.as_ref())
 }
 
 pub fn query_x_property<W: Write, T: AsRef<str>>(w: &mut W, k: T) -> Result<()> {
-    Ok(write!(w, "\x1b[3;?{}\x1b\\", k.as_ref()).context(ErrorKind::OscFailed)?)
+    write_osc(w, 3, &format!("?{}", k.as_ref()))
 }
 
 pub fn map_color<W: Write>(w: &mut W, c: u8, new_color: XColor) -> Result<()> {
-    Ok(match new_color {
-        XColor::Index(x) => write!(w, "\x1b[4;{};{}\x1b\\", c, x),
-        XColor::Rgbi(r, g, b) => write!(w, "\x1b[4;{};rgbi:{}/{}/{}\x1b\\", c, r, g, b),
-        XColor::Rgb(r, g, b) => write!(w, "\x1b[4;{};rgb:{}/{}/{}\x1b\\", c, r, g, b),
-        XColor::Raw(s) => write!(w, "\x1b[4;{};{}\x1b\\", c, s),
-    }.context(ErrorKind::OscFailed)?)
+    let spec = match new_color {
+        XColor::Index(x) => format!("{};{}", c, x),
+        XColor::Rgbi(r, g, b) => format!("{};rgbi:{}/{}/{}", c, r, g, b),
+        XColor::Rgb(r, g, b) => format!("{};rgb:{:04x}/{:04x}/{:04x}", c, r, g, b),
+        XColor::Raw(s) => format!("{};{}", c, s),
+    };
+    write_osc(w, 4, &spec)
 }
 
 pub fn query_color<W: Write>(w: &mut W, c: u8) -> Result<()> {
-    Ok(write!(w, "\x1b[4;{};?\x1b\\", c).context(ErrorKind::OscFailed)?)
+    write_osc(w, 4, &format!("{};?", c))
+}
+
+/// Reset every color in the palette to its default.
+pub fn reset_colors<W: Write>(w: &mut W) -> Result<()> {
+    write_osc(w, 104, "")
+}
+
+/// Write `OSC <number> ; <payload> ST`, see `ansi::osc`.
+fn write_osc<W: Write>(w: &mut W, number: u16, payload: &str) -> Result<()> {
+    let seq = ansi::osc(number, payload.as_bytes(), ansi::OscTerminator::St)?;
+    // the payload is text, so the whole sequence is
+    let seq = String::from_utf8(seq).context(ErrorKind::OscFailed)?;
+    w.write_str(&seq).context(ErrorKind::OscFailed)
+}
+
+/// How a terminal shows desktop notifications.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum NotificationStyle {
+    /// `OSC 777 ; notify ; <title> ; <body>`, from rxvt-unicode and picked up by VTE, foot, and WezTerm.
+    Osc777,
+    /// `OSC 9 ; <message>`, from iTerm2. There's no separate title.
+    Osc9,
+    /// The terminal doesn't show notifications, and might print the sequence literally.
+    Disabled,
+}
+
+/// Make `s` safe to put in a notification's field: whitespace becomes a space, other control characters are
+/// dropped, and `;` (which separates the fields) becomes `,`.
+fn sanitize_notification(s: &str) -> String {
+    s.chars()
+        .filter_map(|c| match c {
+            ';' => Some(','),
+            '\t' | '\n' | '\r' => Some(' '),
+            c if c.is_control() => None,
+            c => Some(c),
+        })
+        .collect()
+}
+
+/// Show a desktop notification using `OSC 777`.
+pub fn notify<W: Write>(w: &mut W, title: &str, body: &str) -> Result<()> {
+    let payload = format!(
+        "notify;{};{}",
+        sanitize_notification(title),
+        sanitize_notification(body)
+    );
+    write_osc(w, 777, &payload)
+}
+
+/// Show a desktop notification using `OSC 9`; the title and body are joined, since it only has room for one
+/// message.
+pub fn notify_message<W: Write>(w: &mut W, title: &str, body: &str) -> Result<()> {
+    let message = match (title.is_empty(), body.is_empty()) {
+        (false, false) => format!("{}: {}", title, body),
+        (true, _) => body.to_owned(),
+        (false, true) => title.to_owned(),
+    };
+    write_osc(w, 9, &sanitize_notification(&message))
+}
+
+/// Start a hyperlink to `url` using `OSC 8`, everything printed until `end_hyperlink` links to it. Control
+/// characters are removed from `url` first, so they can't end the sequence early.
+pub fn start_hyperlink<W: Write>(w: &mut W, url: &str) -> Result<()> {
+    let url: String = url.chars().filter(|c| !c.is_control()).collect();
+    write_osc(w, 8, &format!(";{}", url))
+}
+
+/// End the hyperlink `start_hyperlink` started.
+pub fn end_hyperlink<W: Write>(w: &mut W) -> Result<()> {
+    write_osc(w, 8, ";")
+}
+
+/// Put `data` on the clipboard using `OSC 52`. `selection` says which: `c` for the clipboard, `p` for the primary
+/// selection, or several together like `cp`.
+pub fn set_clipboard<W: Write>(w: &mut W, selection: &str, data: &[u8]) -> Result<()> {
+    let data = util::base64_encode(data);
+    write_osc(w, 52, &format!("{};{}", selection, String::from_utf8_lossy(&data)))
 }
 
 impl From<ansi::Color> for XColor {
     fn from(c: ansi::Color) -> XColor {
         match c {
-            ansi::Color::Rgb(r, g, b) => XColor::Rgb(r as u16, g as u16, b as u16),
+            ansi::Color::Rgb(r, g, b) => ansi::Rgb::new(r, g, b).into(),
             ansi::Color::Index(c) => XColor::Index(c),
         }
     }
 }
 
+impl From<ansi::Rgb> for XColor {
+    fn from(c: ansi::Rgb) -> XColor {
+        // scale each channel up to 16 bits, so 0xff becomes 0xffff
+        XColor::Rgb(u16::from(c.r) * 257, u16::from(c.g) * 257, u16::from(c.b) * 257)
+    }
+}
+
 impl From<(u16, u16, u16)> for XColor {
     fn from(c: (u16, u16, u16)) -> XColor {
         XColor::Rgb(c.0, c.1, c.2)
@@ -93,9 +178,9 @@ impl From<String> for XColor {
 pub mod kitty {
     use ansi;
     use errors::*;
-    use failure::ResultExt;
     use std::fmt::Write;
 
+    #[derive(Debug, Copy, Clone, Eq, PartialEq)]
     pub enum Underline {
         None,
         Straight,
@@ -105,25 +190,115 @@ pub mod kitty {
         Dashed,
     }
 
+    impl Underline {
+        /// The style's number, in `CSI 4 : <style> m` and to `Smulx`.
+        pub fn code(self) -> u16 {
+            match self {
+                Underline::None => 0,
+                Underline::Straight => 1,
+                Underline::Double => 2,
+                Underline::Curly => 3,
+                Underline::Dotted => 4,
+                Underline::Dashed => 5,
+            }
+        }
+    }
+
     pub fn set_underline<W: Write>(w: &mut W, u: Underline) -> Result<()> {
-        Ok(match u {
-            Underline::None => write!(w, "\x1b]4:0m"),
-            Underline::Straight => write!(w, "\x1b]4:1m"),
-            Underline::Double => write!(w, "\x1b]4:2m"),
-            Underline::Curly => write!(w, "\x1b]4:3m"),
-            Underline::Dotted => write!(w, "\x1b]4:4m"),
-            Underline::Dashed => write!(w, "\x1b]4:5m"),
-        }.context(ErrorKind::OscFailed)?)
+        write_csi(w, ansi::csi_sub(&[&[4, u.code()]], b"", b'm'))
     }
 
     pub fn set_underline_color<W: Write, T: Into<ansi::Color>>(w: &mut W, x: T) -> Result<()> {
-        Ok(match x.into() {
-            ansi::Color::Index(i) => write!(w, "\x1b]58;5;{}m", i),
-            ansi::Color::Rgb(r, g, b) => write!(w, "\x1b]58;2;{};{};{}m", r, g, b),
-        }.context(ErrorKind::OscFailed)?)
+        let seq = match x.into() {
+            ansi::Color::Index(i) => ansi::csi_sub(&[&[58, 5, u16::from(i)]], b"", b'm'),
+            ansi::Color::Rgb(r, g, b) => {
+                ansi::csi_sub(&[&[58, 2, u16::from(r), u16::from(g), u16::from(b)]], b"", b'm')
+            }
+        };
+        write_csi(w, seq)
     }
 
     pub fn reset_underline_color<W: Write>(w: &mut W) -> Result<()> {
-        Ok(write!(w, "\x1b]59m").context(ErrorKind::OscFailed)?)
+        write_csi(w, ansi::csi(&[59], b"", b'm'))
+    }
+
+    fn write_csi<W: Write>(w: &mut W, seq: Result<Vec<u8>>) -> Result<()> {
+        // a control sequence is all ASCII
+        w.write_str(&String::from_utf8_lossy(&seq?))
+            .context(ErrorKind::CsiFailed)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use xterm::*;
+
+    #[test]
+    fn hyperlinks() {
+        let mut s = String::new();
+        start_hyperlink(&mut s, "http://example.com/\x1b\\x").unwrap();
+        s.push_str("link");
+        end_hyperlink(&mut s).unwrap();
+        assert_eq!(s, "\x1b]8;;http://example.com/\\x\x1b\\link\x1b]8;;\x1b\\");
+    }
+
+    #[test]
+    fn notifications() {
+        let mut s = String::new();
+        notify(&mut s, "Build", "finished in 3s").unwrap();
+        assert_eq!(s, "\x1b]777;notify;Build;finished in 3s\x1b\\");
+
+        let mut s = String::new();
+        notify_message(&mut s, "Build", "finished in 3s").unwrap();
+        assert_eq!(s, "\x1b]9;Build: finished in 3s\x1b\\");
+
+        let mut s = String::new();
+        notify_message(&mut s, "", "done").unwrap();
+        assert_eq!(s, "\x1b]9;done\x1b\\");
+    }
+
+    #[test]
+    fn clipboard() {
+        let mut s = String::new();
+        set_clipboard(&mut s, "c", b"hi").unwrap();
+        set_clipboard(&mut s, "cp", b"").unwrap();
+        assert_eq!(s, "\x1b]52;c;aGk=\x1b\\\x1b]52;cp;\x1b\\");
+    }
+
+    #[test]
+    fn titles() {
+        let mut s = String::new();
+        set_title(&mut s, "build: ok").unwrap();
+        reset_title(&mut s).unwrap();
+        assert_eq!(s, "\x1b]2;build: ok\x1b\\\x1b]2;\x1b\\");
+
+        // a title from somewhere else can't end the sequence and carry on with its own
+        let mut s = String::new();
+        assert!(set_title(&mut s, "a\x1b\\\x1b]0;pwned").is_err());
+        assert!(map_color(&mut s, 1, XColor::Raw("red\x07".to_owned())).is_err());
+        assert!(s.is_empty());
+    }
+
+    #[test]
+    fn underlines() {
+        let mut s = String::new();
+        kitty::set_underline(&mut s, kitty::Underline::Curly).unwrap();
+        kitty::set_underline_color(&mut s, ::ansi::Color::Index(9)).unwrap();
+        kitty::set_underline_color(&mut s, ::ansi::Color::Rgb(255, 0, 128)).unwrap();
+        kitty::reset_underline_color(&mut s).unwrap();
+        kitty::set_underline(&mut s, kitty::Underline::None).unwrap();
+        assert_eq!(s, "\x1b[4:3m\x1b[58:5:9m\x1b[58:2:255:0:128m\x1b[59m\x1b[4:0m");
+    }
+
+    #[test]
+    fn notification_sanitizing() {
+        let mut s = String::new();
+        notify(&mut s, "a;b\x1b\\", "line 1\nline 2\x07\u{9c};x").unwrap();
+        assert_eq!(s, "\x1b]777;notify;a,b\\;line 1 line 2,x\x1b\\");
+
+        // `OSC 9 ; 4 ; ...` would be taken as a progress report by some terminals
+        let mut s = String::new();
+        notify_message(&mut s, "", "4;1;50").unwrap();
+        assert_eq!(s, "\x1b]9;4,1,50\x1b\\");
     }
 }
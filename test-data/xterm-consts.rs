@@ -0,0 +1,6 @@
+/// `clear`, clear screen and home cursor.
+pub const CLEAR: &[u8] = b"\x1b[H\x1b[2J";
+/// `sgr0`, turn off all attributes.
+pub const SGR0: &[u8] = b"\x1b(B\x1b[m";
+/// `civis`, make cursor invisible.
+pub const CIVIS: &[u8] = b"\x1b[?25l";
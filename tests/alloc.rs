@@ -0,0 +1,133 @@
+//! Expanding a compiled `Program` shouldn't touch the heap, and a `TermInfoBuf` shouldn't take much of it, this
+//! counts every allocation made while they run.
+
+extern crate nixterm;
+
+use nixterm::terminfo::lang::{Argument, Program};
+use nixterm::terminfo::{StringField, TermInfo, TermInfoBuf, PREDEFINED_STRINGS_COUNT};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::mem;
+
+struct Counter;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    static BYTES: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for Counter {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|n| n.set(n.get() + 1));
+        BYTES.with(|n| n.set(n.get() + layout.size()));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.with(|n| n.set(n.get() + 1));
+        BYTES.with(|n| n.set(n.get() + new_size.saturating_sub(layout.size())));
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counter = Counter;
+
+const XTERM_INFO: &'static [u8] = include_bytes!("../test-data/xterm");
+
+/// Run `f`, returning how many allocations it made on this thread.
+fn allocations<F: FnOnce()>(f: F) -> usize {
+    let before = ALLOCATIONS.with(|n| n.get());
+    f();
+    ALLOCATIONS.with(|n| n.get()) - before
+}
+
+/// Run `f`, returning its result and how many bytes it allocated on this thread.
+fn allocated<T, F: FnOnce() -> T>(f: F) -> (T, usize) {
+    let before = BYTES.with(|n| n.get());
+    let v = f();
+    (v, BYTES.with(|n| n.get()) - before)
+}
+
+fn assert_no_allocations(src: &[u8], args: &[Argument], expected: &[u8]) {
+    let program = Program::compile(src).unwrap();
+    let mut buffer = [0u8; 64];
+    let mut len = 0;
+    let n = allocations(|| {
+        let mut w = &mut buffer[..];
+        program.exec_into(args, &mut w).unwrap();
+        len = 64 - w.len();
+    });
+    assert_eq!(&buffer[..len], expected);
+    assert_eq!(
+        n,
+        0,
+        "expanding {:?} allocated",
+        String::from_utf8_lossy(src)
+    );
+}
+
+#[test]
+fn cup() {
+    let info = TermInfo::parse(XTERM_INFO).unwrap();
+    let cup = info.string(StringField::CursorAddress).unwrap();
+    assert_no_allocations(
+        cup.as_bytes(),
+        &[Argument::Integer(4), Argument::Integer(9)],
+        b"\x1b[5;10H",
+    );
+}
+
+#[test]
+fn setaf() {
+    let info = TermInfo::parse(XTERM_INFO).unwrap();
+    let setaf = info.string(StringField::SetAForeground).unwrap();
+    assert_no_allocations(setaf.as_bytes(), &[Argument::Integer(3)], b"\x1b[33m");
+
+    let setaf256 = b"\x1b[%?%p1%{8}%<%t3%p1%d%e%p1%{16}%<%t9%p1%{8}%-%d%e38;5;%p1%d%;m";
+    assert_no_allocations(setaf256, &[Argument::Integer(200)], b"\x1b[38;5;200m");
+}
+
+#[test]
+fn reserved_append() {
+    let info = TermInfo::parse(XTERM_INFO).unwrap();
+    let cup =
+        Program::compile(info.string(StringField::CursorAddress).unwrap().as_bytes()).unwrap();
+    let mut buffer = b"frame".to_vec();
+    buffer.reserve_exact(cup.max_output_hint().unwrap());
+
+    let args = [Argument::Integer(1_000_000), Argument::Integer(-1_000_000)];
+    let n = allocations(|| cup.exec_append(&args, &mut buffer).unwrap());
+    assert_eq!(buffer, b"frame\x1b[1000001;-999999H".to_vec());
+    assert_eq!(n, 0);
+}
+
+#[test]
+fn footprint() {
+    let info = TermInfo::parse(XTERM_INFO).unwrap();
+    let (buf, bytes) = allocated(|| TermInfoBuf::from(info.clone()));
+
+    // `StringField` is numbered from 0 to `PREDEFINED_STRINGS_COUNT`
+    let strings: Vec<&[u8]> = (0..PREDEFINED_STRINGS_COUNT)
+        .filter_map(|i| buf.string_bytes(unsafe { mem::transmute::<usize, StringField>(i) }))
+        .collect();
+    let separate =
+        strings.len() * mem::size_of::<Vec<u8>>() + strings.iter().map(|s| s.len()).sum::<usize>();
+
+    assert!(
+        bytes < separate,
+        "{} bytes on the heap, {} strings would take {} bytes as separate vectors",
+        bytes,
+        strings.len(),
+        separate
+    );
+}
+
+#[test]
+fn counter() {
+    assert!(allocations(|| drop(Vec::<u8>::with_capacity(1))) > 0);
+}
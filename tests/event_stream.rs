@@ -0,0 +1,254 @@
+//! `EventStream` reading from a pseudo terminal, in a small tokio runtime.
+//!
+//! Run these with `cargo test --features async-tokio --test event_stream`.
+#![cfg(all(feature = "async-tokio", unix))]
+
+extern crate nix;
+extern crate nixterm;
+extern crate tokio;
+
+use nix::libc;
+use nix::pty;
+use nix::sys::signal;
+use nixterm::events::{Event, EventLoopOptions, Key};
+use nixterm::stream::EventStream;
+use nixterm::terminfo::{TermInfo, TermInfoBuf};
+use nixterm::{EnvOverrides, Term};
+use std::fs::File;
+use std::io::Write;
+use std::os::unix::io::{AsRawFd, FromRawFd};
+use std::time::{Duration, Instant};
+use tokio::runtime::{Builder, Runtime};
+
+const XTERM_INFO: &'static [u8] = include_bytes!("../test-data/xterm");
+
+/// Run `f` against a terminal on a raw pseudo terminal, with the pty's master end to type into.
+fn with_pty<F>(f: F)
+where
+    F: FnOnce(&Runtime, &Term<File, File>, &mut File),
+{
+    let winsize = pty::Winsize {
+        ws_row: 24,
+        ws_col: 80,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    let pty = pty::openpty(&winsize, None).unwrap();
+    let mut master = unsafe { File::from_raw_fd(pty.master) };
+    let input = unsafe { File::from_raw_fd(pty.slave) };
+    let output = input.try_clone().unwrap();
+
+    let info: TermInfoBuf = TermInfo::parse(XTERM_INFO).unwrap().into();
+    let term = Term::from_streams(info, input, output);
+    term.update(term.settings().raw()).unwrap();
+    term.set_env_overrides(EnvOverrides::from_map(Vec::<(String, String)>::new()));
+
+    let rt = Builder::new_current_thread().enable_all().build().unwrap();
+    f(&rt, &term, &mut master);
+}
+
+/// Wait for the next key. The tests run at the same time, so skip the resizes from `resize` raising `SIGWINCH`.
+fn next_key(rt: &Runtime, events: &mut EventStream<File, File>) -> Key {
+    loop {
+        match rt.block_on(events.next_event()) {
+            Some(Ok(Event::Key(key))) => return key,
+            Some(Ok(Event::Resize(..))) => (),
+            e => panic!("expected a key, got {:?}", e),
+        }
+    }
+}
+
+#[test]
+fn keys() {
+    with_pty(|rt, term, master| {
+        let _rt = rt.enter();
+        let mut events = EventStream::new(term).unwrap();
+
+        master.write_all(b"a\x1bOA\x1bb").unwrap();
+        assert_eq!(next_key(rt, &mut events), Key::Char('a'));
+        assert_eq!(next_key(rt, &mut events), Key::Up);
+        assert_eq!(next_key(rt, &mut events), Key::Escape);
+        assert_eq!(next_key(rt, &mut events), Key::Char('b'));
+
+        // the escape key on its own, which could be the start of a key code until nothing more comes
+        master.write_all(b"\x1b").unwrap();
+        assert_eq!(next_key(rt, &mut events), Key::Escape);
+    });
+}
+
+#[test]
+fn cancelled() {
+    with_pty(|rt, term, master| {
+        let _rt = rt.enter();
+        let mut events = EventStream::new(term).unwrap();
+
+        // half of a key arrives, and whoever was waiting for it gives up before it'd count as the escape key
+        master.write_all(b"\x1bO").unwrap();
+        loop {
+            let wait = tokio::time::timeout(Duration::from_millis(10), events.next_event());
+            match rt.block_on(wait) {
+                Err(_) => break,
+                Ok(Some(Ok(Event::Resize(..)))) => (),
+                e => panic!("expected to time out, got {:?}", e),
+            }
+        }
+
+        // the half that was read isn't lost
+        master.write_all(b"B").unwrap();
+        assert_eq!(next_key(rt, &mut events), Key::Down);
+    });
+}
+
+#[test]
+fn resize() {
+    with_pty(|rt, term, master| {
+        let _rt = rt.enter();
+        let mut events = EventStream::new(term).unwrap();
+
+        let size = libc::winsize {
+            ws_row: 40,
+            ws_col: 100,
+            ws_xpixel: 0,
+            ws_ypixel: 0,
+        };
+        assert_eq!(
+            unsafe { libc::ioctl(master.as_raw_fd(), libc::TIOCSWINSZ, &size) },
+            0
+        );
+        // the pty isn't this process's controlling terminal, so the kernel won't send it
+        signal::raise(signal::Signal::SIGWINCH).unwrap();
+
+        match rt.block_on(events.next_event()) {
+            Some(Ok(Event::Resize(100, 40))) => (),
+            e => panic!("expected a resize, got {:?}", e),
+        }
+    });
+}
+
+#[test]
+fn resize_other_terminal() {
+    with_pty(|rt, term, master| {
+        with_pty(|_, other, _| {
+            let _rt = rt.enter();
+            let mut events = EventStream::new(term).unwrap();
+            let mut others = EventStream::new(other).unwrap();
+
+            let size = libc::winsize {
+                ws_row: 50,
+                ws_col: 132,
+                ws_xpixel: 0,
+                ws_ypixel: 0,
+            };
+            assert_eq!(
+                unsafe { libc::ioctl(master.as_raw_fd(), libc::TIOCSWINSZ, &size) },
+                0
+            );
+            signal::raise(signal::Signal::SIGWINCH).unwrap();
+
+            match rt.block_on(events.next_event()) {
+                Some(Ok(Event::Resize(132, 50))) => (),
+                e => panic!("expected a resize, got {:?}", e),
+            }
+            // the other terminal's size hasn't changed, so its stream has nothing to say
+            let wait = tokio::time::timeout(Duration::from_millis(50), others.next_event());
+            match rt.block_on(wait) {
+                Err(_) => (),
+                e => panic!("expected to time out, got {:?}", e),
+            }
+        });
+    });
+}
+
+#[test]
+fn resize_debounce() {
+    with_pty(|rt, term, master| {
+        let _rt = rt.enter();
+        let mut events = EventStream::new(term)
+            .unwrap()
+            .resize_debounce(Duration::from_millis(100));
+
+        // a window being dragged
+        for &(cols, rows) in &[(90, 30), (95, 35), (110, 45)] {
+            let size = libc::winsize {
+                ws_row: rows,
+                ws_col: cols,
+                ws_xpixel: 0,
+                ws_ypixel: 0,
+            };
+            assert_eq!(
+                unsafe { libc::ioctl(master.as_raw_fd(), libc::TIOCSWINSZ, &size) },
+                0
+            );
+            signal::raise(signal::Signal::SIGWINCH).unwrap();
+        }
+
+        // keys aren't held up while the size settles
+        master.write_all(b"a").unwrap();
+        match rt.block_on(events.next_event()) {
+            Some(Ok(Event::Key(Key::Char('a')))) => (),
+            e => panic!("expected a key, got {:?}", e),
+        }
+        match rt.block_on(events.next_event()) {
+            Some(Ok(Event::Resize(110, 45))) => (),
+            e => panic!("expected a resize, got {:?}", e),
+        }
+        let wait = tokio::time::timeout(Duration::from_millis(150), events.next_event());
+        match rt.block_on(wait) {
+            Err(_) => (),
+            e => panic!("expected to time out, got {:?}", e),
+        }
+    });
+}
+
+/// Wait for the next key or tick, skipping resizes like `next_key`.
+fn next_event(rt: &Runtime, events: &mut EventStream<File, File>) -> Event {
+    loop {
+        match rt.block_on(events.next_event()) {
+            Some(Ok(Event::Resize(..))) => (),
+            Some(Ok(event)) => return event,
+            e => panic!("expected an event, got {:?}", e),
+        }
+    }
+}
+
+#[test]
+fn ticks() {
+    with_pty(|rt, term, master| {
+        let _rt = rt.enter();
+        let interval = Duration::from_millis(40);
+        let start = Instant::now();
+        let mut events = EventStream::new(term)
+            .unwrap()
+            .options(EventLoopOptions::new().tick(interval));
+
+        assert_eq!(next_event(rt, &mut events), Event::Tick);
+        assert_eq!(next_event(rt, &mut events), Event::Tick);
+        assert!(start.elapsed() >= interval * 2);
+
+        // keys come between them
+        master.write_all(b"a").unwrap();
+        assert_eq!(next_event(rt, &mut events), Event::Key(Key::Char('a')));
+        assert_eq!(next_event(rt, &mut events), Event::Tick);
+    });
+}
+
+#[test]
+fn ticks_when_idle() {
+    with_pty(|rt, term, master| {
+        let _rt = rt.enter();
+        let interval = Duration::from_millis(100);
+        let mut events = EventStream::new(term)
+            .unwrap()
+            .options(EventLoopOptions::new().tick(interval).tick_when_idle(true));
+
+        // typing keeps putting the tick off
+        for _ in 0..3 {
+            std::thread::sleep(interval / 2);
+            master.write_all(b"a").unwrap();
+            assert_eq!(next_event(rt, &mut events), Event::Key(Key::Char('a')));
+        }
+        let typed = Instant::now();
+        assert_eq!(next_event(rt, &mut events), Event::Tick);
+        assert!(typed.elapsed() >= interval * 9 / 10);
+    });
+}
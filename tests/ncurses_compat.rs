@@ -0,0 +1,246 @@
+//! Compares `Program::exec_append` with ncurses' `tparm` for every parameterized string in a set of entries.
+//!
+//! Run these with `cargo test --features ncurses-compat-tests --test ncurses_compat`. `tparm` is loaded with `dlopen`
+//! rather than linked, so nothing needs ncurses to build, and the test passes (saying why) when there's no ncurses
+//! (or libtinfo) to compare with.
+//!
+//! Capabilities that take string arguments (`%s` and `%l`) are left out, `tparm` would read the numbers we pass as
+//! pointers. The other divergences are in `ALLOWED`.
+#![cfg(feature = "ncurses-compat-tests")]
+
+extern crate nix;
+extern crate nixterm;
+
+use nix::libc::{self, c_char, c_long, c_void};
+use nixterm::terminfo::lang::{Argument, Program};
+use nixterm::terminfo::{self, StringField, TermInfo, PREDEFINED_STRINGS_COUNT};
+use std::ffi::{CStr, CString};
+use std::fs;
+use std::path::Path;
+
+type Tparm = unsafe extern "C" fn(*const c_char, ...) -> *mut c_char;
+
+const LIBRARIES: &[&str] = &[
+    "libtinfo.so.6",
+    "libncursesw.so.6",
+    "libncurses.so.6",
+    "libtinfo.so.5",
+    "libncurses.so.5",
+];
+
+/// Installed entries to compare, the ones that aren't installed are skipped.
+const ENTRIES: &[&str] = &[
+    "xterm",
+    "xterm-256color",
+    "xterm-direct",
+    "screen",
+    "screen-256color",
+    "tmux-256color",
+    "rxvt-unicode",
+    "st-256color",
+    "linux",
+    "vt100",
+    "vt220",
+    "ansi",
+    "putty",
+    "kitty",
+    "alacritty",
+    "wsvt25",
+    "cons25",
+];
+
+/// The entries in `test-data`.
+const TEST_DATA: &[&str] = &["xterm", "rxvt", "linux-16color", "tmux"];
+
+/// Capabilities that aren't meant for `tparm` at all, so they're not compared, and why.
+///
+/// There's one more known difference: `tparm` returns a C string, so it can't return a NUL. ncurses prints `%c` of 0
+/// as `\200` instead, but other multiples of 256 end the string. So where we print a NUL, either is fine.
+const ALLOWED: &[(&str, &str)] = &[
+    // `u6` to `u9` are templates for reading the terminal's replies with `scanf` (like `u6`'s
+    // `\E[%i%d;%dR` for the cursor position report), ncurses expands termcap style strings like `%d`
+    // without a `%p` by popping the arguments, we don't
+    ("User6", "a scanf template for the cursor position report"),
+    ("User7", "the cursor position request, sent as is"),
+    ("User8", "a scanf template for the device attributes reply"),
+    ("User9", "the device attributes request, sent as is"),
+];
+
+/// Every value each argument is tried with: all 512 combinations of 0 and 1 (for `sgr`), then pairs of a few
+/// interesting numbers for the first two arguments, with the rest 0 and then counting up.
+fn grid() -> Vec<[i64; 9]> {
+    let mut rows = Vec::new();
+    for bits in 0..512 {
+        let mut row = [0; 9];
+        for (i, arg) in row.iter_mut().enumerate() {
+            *arg = (bits >> i) & 1;
+        }
+        rows.push(row);
+    }
+
+    let values = [0, 1, 2, 7, 8, 9, 10, 15, 16, 99, 100, 127, 255, 256, 1000];
+    for &a in &values {
+        for &b in &values {
+            rows.push([a, b, 0, 0, 0, 0, 0, 0, 0]);
+            rows.push([a, b, 3, 4, 5, 6, 7, 8, 9]);
+        }
+    }
+    rows
+}
+
+fn load_tparm() -> Option<Tparm> {
+    for name in LIBRARIES {
+        let cname = CString::new(*name).unwrap();
+        let lib = unsafe { libc::dlopen(cname.as_ptr(), libc::RTLD_NOW) };
+        if lib.is_null() {
+            continue;
+        }
+        let sym = unsafe { libc::dlsym(lib, b"tparm\0".as_ptr() as *const c_char) };
+        if !sym.is_null() {
+            return Some(unsafe { std::mem::transmute::<*mut c_void, Tparm>(sym) });
+        }
+    }
+    None
+}
+
+fn tparm(f: Tparm, src: &[u8], args: &[i64; 9]) -> Option<Vec<u8>> {
+    let src = CString::new(src).ok()?;
+    let a: Vec<c_long> = args.iter().map(|&a| a as c_long).collect();
+    let out = unsafe {
+        f(
+            src.as_ptr(),
+            a[0],
+            a[1],
+            a[2],
+            a[3],
+            a[4],
+            a[5],
+            a[6],
+            a[7],
+            a[8],
+        )
+    };
+    if out.is_null() {
+        None
+    } else {
+        Some(unsafe { CStr::from_ptr(out) }.to_bytes().to_vec())
+    }
+}
+
+fn ours(src: &[u8], args: &[i64; 9]) -> Option<Vec<u8>> {
+    let program = Program::compile(src).ok()?;
+    let args: Vec<Argument> = args.iter().map(|&a| Argument::from(a)).collect();
+    let mut buf = Vec::new();
+    program.exec_append(&args, &mut buf).ok()?;
+    Some(buf)
+}
+
+fn field(i: usize) -> StringField {
+    assert!(i < PREDEFINED_STRINGS_COUNT);
+    // `StringField` is `repr(usize)`, with a variant for every index below `PREDEFINED_STRINGS_COUNT`
+    unsafe { std::mem::transmute::<usize, StringField>(i) }
+}
+
+/// Compare every parameterized string in `entry`, returning the mismatches.
+fn compare(f: Tparm, entry: &str, bytes: &[u8], grid: &[[i64; 9]]) -> Vec<String> {
+    let info = match TermInfo::parse(bytes) {
+        Ok(info) => info,
+        Err(e) => return vec![format!("{}: failed to parse: {}", entry, e)],
+    };
+
+    let mut mismatches = Vec::new();
+    for i in 0..PREDEFINED_STRINGS_COUNT {
+        let field = field(i);
+        let src = match info.string_bytes(field) {
+            Some(src) if src.contains(&b'%') => src,
+            _ => continue,
+        };
+        if src.windows(2).any(|w| w == b"%s" || w == b"%l") {
+            continue;
+        }
+        let name = format!("{:?}", field);
+        if ALLOWED.iter().any(|&(allowed, _)| allowed == name) {
+            continue;
+        }
+
+        for args in grid {
+            let theirs = tparm(f, src, args);
+            let ours = ours(src, args);
+            // tparm can't return a NUL, see `ALLOWED`
+            let same = match (&theirs, &ours) {
+                (&Some(ref theirs), &Some(ref ours)) if ours.contains(&0) => {
+                    let nul = ours.iter().position(|&b| b == 0).unwrap();
+                    let escaped: Vec<u8> = ours
+                        .iter()
+                        .map(|&b| if b == 0 { 0o200 } else { b })
+                        .collect();
+                    *theirs == ours[..nul] || *theirs == escaped
+                }
+                _ => theirs == ours,
+            };
+            if !same {
+                mismatches.push(format!(
+                    "{}: {} = {:?} with {:?}: tparm gave {:?}, we gave {:?}",
+                    entry,
+                    name,
+                    String::from_utf8_lossy(src),
+                    args,
+                    theirs.map(|v| String::from_utf8_lossy(&v).into_owned()),
+                    ours.map(|v| String::from_utf8_lossy(&v).into_owned()),
+                ));
+                // one example for each capability is plenty
+                break;
+            }
+        }
+    }
+    mismatches
+}
+
+#[test]
+fn tparm_compat() {
+    let f = match load_tparm() {
+        Some(f) => f,
+        None => {
+            eprintln!("skipping, there's no ncurses to compare with");
+            return;
+        }
+    };
+
+    let grid = grid();
+    let mut entries = Vec::new();
+    for name in TEST_DATA {
+        let path = format!("{}/test-data/{}", env!("CARGO_MANIFEST_DIR"), name);
+        entries.push((format!("test-data/{}", name), fs::read(path).unwrap()));
+    }
+    for name in ENTRIES {
+        // Debian keeps the common entries in /lib/terminfo, which `find` doesn't look in
+        let path = terminfo::find(name).or_else(|| {
+            ["/lib/terminfo", "/etc/terminfo"]
+                .iter()
+                .map(|db| Path::new(db).join(&name[..1]).join(name))
+                .find(|path| path.exists())
+        });
+        if let Some(path) = path {
+            entries.push((name.to_string(), fs::read(path).unwrap()));
+        }
+    }
+
+    eprintln!(
+        "comparing {}",
+        entries
+            .iter()
+            .map(|&(ref name, _)| &name[..])
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    let mismatches: Vec<String> = entries
+        .iter()
+        .flat_map(|&(ref name, ref bytes)| compare(f, name, bytes, &grid))
+        .collect();
+    assert!(
+        mismatches.is_empty(),
+        "{} mismatches:\n{}",
+        mismatches.len(),
+        mismatches.join("\n")
+    );
+}
@@ -0,0 +1,62 @@
+//! The terminfo parser and expansion engine, as used without an OS.
+//!
+//! These only use what's available without the `std` feature, run them against that build with
+//! `cargo test --no-default-features --test no_std`.
+
+extern crate nixterm;
+
+use nixterm::terminfo::lang::Executor;
+use nixterm::terminfo::{self, TermInfo, TermInfoBuf};
+
+const RXVT_INFO: &'static [u8] = include_bytes!("../test-data/rxvt");
+
+fn expand(src: &[u8], args: &[i64]) -> Vec<u8> {
+    let mut exe = Executor::new(src);
+    for &a in args {
+        exe = exe.arg(a);
+    }
+    exe.vec().unwrap()
+}
+
+#[test]
+fn expansion() {
+    assert_eq!(expand(b"%p1%p2%+%d", &[30, 2]), b"32");
+    assert_eq!(expand(b"%{8}%{22}%+%d%'c'%c", &[]), b"30c");
+    assert_eq!(expand(b"%i%p1%d.%p2%d", &[1, 2]), b"2.3");
+    assert_eq!(expand(b"%?%p1%tyes!%eno!%;", &[0]), b"no!");
+    assert_eq!(expand(b"%p1%02x", &[10]), b"0a");
+    assert_eq!(
+        expand(
+            b"\x1b[%?%p1%{8}%<%t3%p1%d%e%p1%{16}%<%t9%p1%{8}%-%d%e38;5;%p1%d%;m",
+            &[3]
+        ),
+        b"\x1b[33m"
+    );
+
+    let s = Executor::new(b"%p1%l%p1\"%s\" is %d characters long!")
+        .arg("Hello World")
+        .string()
+        .unwrap();
+    assert_eq!(s, "\"Hello World\" is 11 characters long!");
+
+    let mut out = Vec::new();
+    assert_eq!(Executor::new(b"%p1%d").arg(42).write(&mut out).unwrap(), 2);
+    assert_eq!(out, b"42");
+}
+
+#[test]
+fn compiled_entry() {
+    let info = TermInfo::parse(RXVT_INFO).unwrap();
+    assert_eq!(info.names().nth(0), Some("rxvt"));
+    assert_eq!(info.number(terminfo::MaxColors), Some(8));
+
+    let buf = TermInfoBuf::from(info);
+    let cup = buf
+        .exec(terminfo::CursorAddress)
+        .unwrap()
+        .arg(4)
+        .arg(9)
+        .vec()
+        .unwrap();
+    assert_eq!(cup, b"\x1b[5;10H");
+}
@@ -0,0 +1,377 @@
+//! Running commands on pseudo terminals with `pty::Pty::spawn`.
+//!
+//! Run these with `cargo test --features pty --test pty`.
+#![cfg(all(feature = "pty", unix))]
+
+extern crate nix;
+extern crate nixterm;
+
+use nix::sys::termios;
+use nixterm::ansi::{Style, TestScreen};
+use nixterm::pty::{self, Pty};
+use nixterm::query::{Handling, SequenceFamily};
+use nixterm::terminfo::{TermInfo, TermInfoBuf};
+use nixterm::Term;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd};
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+
+/// Run `sh -c script`, type `input`, and read everything it writes until it exits.
+fn run(script: &str, input: &[u8], size: Option<(usize, usize)>) -> String {
+    let mut command = Command::new("/bin/sh");
+    command.arg("-c").arg(script);
+    let mut pty = Pty::spawn(command).unwrap();
+    if let Some((cols, rows)) = size {
+        pty.master.resize(cols, rows).unwrap();
+    }
+    pty.master.write_all(input).unwrap();
+
+    let mut out = String::new();
+    pty.master.read_to_string(&mut out).unwrap();
+    assert!(pty.child.wait().unwrap().success(), "{:?}", out);
+    out
+}
+
+#[test]
+fn echo() {
+    let mut pty = Pty::spawn({
+        let mut command = Command::new("/bin/echo");
+        command.arg("hello").arg("world");
+        command
+    })
+    .unwrap();
+    let mut out = String::new();
+    pty.master.read_to_string(&mut out).unwrap();
+    assert!(pty.child.wait().unwrap().success());
+    // the terminal turns `\n` into `\r\n`
+    assert_eq!(out, "hello world\r\n");
+}
+
+#[test]
+fn stty() {
+    // wait for the resize before asking
+    let out = run("read _; stty -a", b"\n", Some((132, 43)));
+    let field = |name: &str| {
+        out.split(';')
+            .map(str::trim)
+            .find(|f| f.starts_with(name))
+            .unwrap_or_else(|| panic!("no {} in {:?}", name, out))
+            .split_whitespace()
+            .nth(1)
+            .unwrap()
+            .parse::<usize>()
+            .unwrap()
+    };
+    assert_eq!((field("columns"), field("rows")), (132, 43));
+
+    // a new terminal starts out cooked
+    let flags: Vec<&str> = out.split_whitespace().collect();
+    for flag in &["icanon", "echo", "isig", "onlcr"] {
+        assert!(flags.contains(flag), "{} isn't on in {:?}", flag, out);
+    }
+}
+
+#[test]
+fn controlling_terminal() {
+    // only works if the pty is the command's controlling terminal
+    assert_eq!(run("echo ok > /dev/tty", b"", None), "ok\r\n");
+}
+
+#[test]
+fn slave_term() {
+    let (mut master, slave) = pty::openpty().unwrap();
+    master.resize(100, 30).unwrap();
+    // too big for a `winsize`, rather than cut down to one
+    assert!(master.resize(65536, 30).is_err());
+    let term = Term::from_streams(TermInfoBuf::new(), slave.try_clone().unwrap(), slave);
+    assert_eq!(term.size().unwrap(), (100, 30));
+
+    term.print("hi").unwrap();
+    let mut buf = [0u8; 2];
+    master.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"hi");
+}
+
+/// Type `keys` into a `Term` on a new pty once `prompt` is shown, returning what `read` gave and everything shown.
+fn prompt<F>(keys: &'static [u8], redirect: bool, read: F) -> (nixterm::Result<String>, String)
+where
+    F: FnOnce(&Term<pty::Slave, File>) -> nixterm::Result<String>,
+{
+    let (mut master, slave) = pty::openpty().unwrap();
+    let settings = slave.try_clone().unwrap();
+    let stdout = if redirect {
+        OpenOptions::new().write(true).open("/dev/null").unwrap()
+    } else {
+        unsafe { File::from_raw_fd(slave.try_clone().unwrap().into_raw_fd()) }
+    };
+    let term = Term::from_streams(TermInfoBuf::new(), slave, stdout);
+
+    let typist = thread::spawn(move || {
+        // the terminal's settings are changed before the prompt
+        let mut shown = Vec::new();
+        let mut buf = [0u8; 64];
+        while !shown.ends_with(b"> ") {
+            let n = master.read(&mut buf).unwrap();
+            assert!(n > 0, "no prompt in {:?}", String::from_utf8_lossy(&shown));
+            shown.extend_from_slice(&buf[..n]);
+        }
+        master.write_all(keys).unwrap();
+        master.read_to_end(&mut shown).unwrap();
+        (String::from_utf8(shown).unwrap(), master)
+    });
+
+    let line = read(&term);
+    let echo = termios::tcgetattr(settings.as_raw_fd())
+        .unwrap()
+        .local_flags;
+    // the pty's output ends when every copy of the slave is closed
+    drop((term, settings));
+    let (shown, _master) = typist.join().unwrap();
+    assert!(
+        echo.contains(termios::LocalFlags::ECHO),
+        "echo wasn't turned back on"
+    );
+    (line, shown)
+}
+
+#[test]
+fn read_line() {
+    let keys = b"helxo\x7f\x7flo wrold\x17there\x1b[D!\r";
+    let (line, shown) = prompt(keys, false, |t| t.read_line("name> "));
+    assert_eq!(line.unwrap(), "hello there!");
+    assert_eq!(
+        shown,
+        "name> helxo\x08 \x08\x08 \x08lo wrold\x08 \x08\x08 \x08\x08 \x08\x08 \x08\x08 \x08there!\r\n"
+    );
+
+    let (line, _) = prompt(b"gone\x15back\r", false, |t| t.read_line("> "));
+    assert_eq!(line.unwrap(), "back");
+    let (line, _) = prompt(b"\x04", false, |t| t.read_line("> "));
+    assert!(line.is_err());
+
+    // with the output redirected, the prompt and the echo still go to the terminal
+    let (line, shown) = prompt(b"hi\r", true, |t| t.read_line("> "));
+    assert_eq!(line.unwrap(), "hi");
+    assert_eq!(shown, "> hi\r\n");
+}
+
+#[test]
+fn read_password() {
+    let (line, shown) = prompt(b"hunter2\x7f3\r", false, |t| t.read_password("password> "));
+    assert_eq!(line.unwrap(), "hunter3");
+    assert_eq!(shown, "password> \r\n");
+
+    // what was typed before the prompt isn't thrown away when the settings change, and without it the line would
+    // be empty once the late `\r` came
+    let (mut master, slave) = pty::openpty().unwrap();
+    master.write_all(b"typed ahead\r").unwrap();
+    let late = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(200));
+        master.write_all(b"\r").unwrap();
+        master
+    });
+    let term = Term::from_streams(TermInfoBuf::new(), slave.try_clone().unwrap(), slave);
+    assert_eq!(term.read_password("password> ").unwrap(), "typed ahead");
+    late.join().unwrap();
+
+    // echo is turned back on when the input ends, too
+    let (line, shown) = prompt(b"\x04", true, |t| t.read_password("password> "));
+    assert!(line.is_err());
+    assert_eq!(shown, "password> \r\n");
+}
+
+/// Play the terminal on `master` with a `TestScreen`, answering cursor position queries, until the slave is closed.
+/// The sequences in `printed` are printed like a terminal that doesn't know them would, without their escapes.
+fn emulate(
+    mut master: pty::Master,
+    rows: usize,
+    cols: usize,
+    printed: &'static [&'static [u8]],
+) -> thread::JoinHandle<TestScreen> {
+    let feed = move |screen: &mut TestScreen, mut output: Vec<u8>| {
+        for seq in printed {
+            let shown: Vec<u8> = seq.iter().cloned().filter(|&c| c != 0x1b).collect();
+            while let Some(i) = output.windows(seq.len()).position(|w| w == *seq) {
+                output.splice(i..i + seq.len(), shown.iter().cloned());
+            }
+        }
+        screen.feed(&output);
+    };
+    thread::spawn(move || {
+        let mut screen = TestScreen::new(rows, cols);
+        let mut output = Vec::new();
+        let mut answered = 0;
+        let mut buf = [0u8; 256];
+        while let Ok(n) = master.read(&mut buf) {
+            if n == 0 {
+                break;
+            }
+            output.extend_from_slice(&buf[..n]);
+            // queries are answered once everything before them is on the screen
+            while let Some(i) = output[answered..].windows(4).position(|w| w == b"\x1b[6n") {
+                let end = answered + i + 4;
+                feed(&mut screen, output[answered..end].to_vec());
+                answered = end;
+                let (row, col) = screen.cursor();
+                write!(master, "\x1b[{};{}R", row + 1, col + 1).unwrap();
+            }
+        }
+        feed(&mut screen, output[answered..].to_vec());
+        screen
+    })
+}
+
+/// Run `f` against a `Term` on a new `rows` by `cols` pty, returning the screen it leaves behind.
+fn inline<F>(rows: usize, cols: usize, f: F) -> TestScreen
+where
+    F: FnOnce(&Term<pty::Slave, pty::Slave>),
+{
+    inline_printing(rows, cols, &[], f)
+}
+
+/// Like `inline`, on a terminal that prints the sequences in `printed`, see `emulate`.
+fn inline_printing<F>(rows: usize, cols: usize, printed: &'static [&'static [u8]], f: F) -> TestScreen
+where
+    F: FnOnce(&Term<pty::Slave, pty::Slave>),
+{
+    let (master, slave) = pty::openpty().unwrap();
+    master.resize(cols, rows).unwrap();
+    let info = TermInfo::parse(include_bytes!("../test-data/xterm-256color")).unwrap();
+    let screen = emulate(master, rows, cols, printed);
+    {
+        let term = Term::from_streams(info.into(), slave.try_clone().unwrap(), slave);
+        term.update(term.settings().raw()).unwrap();
+        f(&term);
+    }
+    screen.join().unwrap()
+}
+
+#[test]
+fn inline_viewport() {
+    let timeout = Duration::from_secs(5);
+    let screen = inline(10, 20, |t| {
+        for i in 1..9 {
+            t.print(format!("line {}\r\n", i)).unwrap();
+        }
+        t.print("$ ").unwrap();
+        let mut vp = t.inline_viewport(4, timeout).unwrap();
+        assert_eq!(vp.top(), 6);
+        t.print("> first").unwrap();
+        vp.move_to(2, 3).unwrap();
+        t.print("last").unwrap();
+    });
+    // the UI is gone, and the lines scrolled away to make room for it are in the scrollback
+    screen.assert_snapshot("line 4\nline 5\nline 6\nline 7\nline 8\n$");
+    assert_eq!(screen.cursor(), (5, 2));
+    assert_eq!(screen.scrollback(), vec!["line 1", "line 2", "line 3"]);
+
+    // a screen shorter than the viewport
+    let screen = inline(5, 20, |t| {
+        t.print("one\r\ntwo\r\n").unwrap();
+        let vp = t.inline_viewport(8, timeout).unwrap();
+        assert_eq!((vp.size(), vp.top()), ((20, 5), 0));
+        t.print("> ui").unwrap();
+    });
+    screen.assert_snapshot("");
+    assert_eq!(screen.cursor(), (0, 0));
+    assert_eq!(screen.scrollback(), vec!["one", "two"]);
+}
+
+#[test]
+fn sequence_safety() {
+    let timeout = Duration::from_secs(5);
+    let probe = |t: &Term<pty::Slave, pty::Slave>| {
+        t.print("$ ").unwrap();
+        let safety = t.probe_sequence_safety(timeout).unwrap();
+        let handling: Vec<_> = SequenceFamily::ALL
+            .iter()
+            .map(|&family| safety.handling(family).unwrap())
+            .collect();
+        // it's only probed once
+        assert_eq!(t.sequence_safety(), Some(safety));
+        assert_eq!(t.probe_sequence_safety(Duration::from_millis(0)).unwrap(), safety);
+        handling
+    };
+    let (consumed, echoed) = (Handling::Consumed, Handling::Echoed);
+
+    let screen = inline(3, 20, |t| {
+        assert_eq!(probe(t), vec![consumed, consumed, consumed]);
+        t.hyperlink("http://example.com", "link").unwrap();
+    });
+    screen.assert_snapshot("$ link");
+
+    // what was printed is erased, and hyperlinks are left out
+    let screen = inline_printing(3, 20, &[b"\x1b]8;;\x1b\\", b"\x1b_\x1b\\"], |t| {
+        assert_eq!(probe(t), vec![echoed, consumed, echoed]);
+        t.hyperlink("http://example.com", "link").unwrap();
+    });
+    screen.assert_snapshot("$ link");
+    assert_eq!(screen.cursor(), (0, 6));
+
+    // and so is synchronized output
+    let printed: &[&[u8]] = &[b"\x1b[?2026l", b"\x1b[?2026h"];
+    let screen = inline_printing(3, 20, printed, |t| {
+        assert_eq!(probe(t), vec![consumed, echoed, consumed]);
+        t.frame(|t| t.print("frame").unwrap()).unwrap();
+    });
+    screen.assert_snapshot("$ frame");
+}
+
+#[test]
+fn cooked_output() {
+    let (mut master, slave) = pty::openpty().unwrap();
+    let shown = thread::spawn(move || {
+        let mut shown = Vec::new();
+        master.read_to_end(&mut shown).unwrap();
+        shown
+    });
+    {
+        let info = TermInfo::parse(include_bytes!("../test-data/xterm-256color")).unwrap();
+        let term = Term::from_streams(info.into(), slave.try_clone().unwrap(), slave);
+        let cooked = term.settings();
+        term.update(cooked.clone().raw()).unwrap();
+        write!(term.styled_writer(), "> half typed").unwrap();
+        term.cooked_println(format_args!("loaded {} files\nand {} more", 2, 3))
+            .unwrap();
+
+        // the prompt's bold, which the log lines aren't, and it's put back once they're written
+        let mut writer = term.cooked_writer();
+        writer.set_active_style(Style::new().bold());
+        write!(writer, "one\ntwo").unwrap();
+        writeln!(writer, " three").unwrap();
+        drop(writer);
+        write!(term.styled_writer(), ">").unwrap();
+
+        // a cooked tty turns `\n` into `\r\n` itself
+        term.update(cooked).unwrap();
+        term.cooked_println(format_args!("a\nb")).unwrap();
+    }
+    let shown = shown.join().unwrap();
+    assert_eq!(
+        String::from_utf8(shown).unwrap(),
+        "> half typed\r\x1b[Kloaded 2 files\r\nand 3 more\r\n\
+         \r\x1b[K\x1b[0mone\r\ntwo three\r\n\x1b[1m>\
+         a\r\nb\r\n"
+    );
+
+    // a short line over a long prompt doesn't leave the end of the prompt showing
+    let (mut master, slave) = pty::openpty().unwrap();
+    let shown = thread::spawn(move || {
+        let mut shown = Vec::new();
+        master.read_to_end(&mut shown).unwrap();
+        shown
+    });
+    {
+        let info = TermInfo::parse(include_bytes!("../test-data/xterm-256color")).unwrap();
+        let term = Term::from_streams(info.into(), slave.try_clone().unwrap(), slave);
+        term.update(term.settings().raw()).unwrap();
+        write!(term.styled_writer(), "> a long half typed line").unwrap();
+        term.cooked_println(format_args!("ok")).unwrap();
+    }
+    let mut screen = TestScreen::new(3, 40);
+    screen.feed(&shown.join().unwrap());
+    screen.assert_snapshot("ok");
+}
@@ -0,0 +1,49 @@
+//! `nixterm::quick` against a terminfo database made from the test fixtures.
+//!
+//! These change the environment, so they're kept out of the library's tests (which run on several threads).
+#![cfg(all(feature = "std", unix))]
+
+extern crate nixterm;
+
+use nixterm::quick;
+use std::env;
+use std::fs;
+
+#[test]
+fn quick() {
+    let db = env::temp_dir().join(format!("nixterm-quick-{}", std::process::id()));
+    fs::create_dir_all(db.join("n")).unwrap();
+    fs::copy(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/test-data/xterm"),
+        db.join("n/nixterm-quick-xterm"),
+    )
+    .unwrap();
+    env::set_var("TERMINFO", &db);
+    env::set_var("TERM", "nixterm-quick-xterm");
+
+    assert_eq!(quick::colors(), 8);
+    assert_eq!(quick::bold("hi"), "\x1b[1mhi\x1b(B\x1b[m");
+    assert_eq!(quick::move_to(2, 3), "\x1b[3;4H");
+    quick::clear();
+
+    env::set_var("COLUMNS", "123");
+    env::set_var("LINES", "45");
+    assert_eq!((quick::columns(), quick::lines()), (123, 45));
+    env::set_var("COLUMNS", "not a number");
+    env::remove_var("LINES");
+    assert!(quick::columns() > 0);
+    assert!(quick::lines() > 0);
+
+    // no terminal, no escape codes
+    env::remove_var("TERM");
+    assert_eq!(quick::colors(), 0);
+    assert_eq!(quick::bold("hi"), "hi");
+    assert_eq!(quick::move_to(2, 3), "");
+    quick::clear();
+
+    env::set_var("TERM", "nixterm-no-such-terminal");
+    assert_eq!(quick::bold("hi"), "hi");
+    assert!(quick::columns() > 0);
+
+    fs::remove_dir_all(&db).unwrap();
+}
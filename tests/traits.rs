@@ -0,0 +1,97 @@
+//! The public types work as the keys and values of a program's own maps and sets, the way it would use them.
+#![cfg(feature = "std")]
+
+extern crate nixterm;
+
+use nixterm::ansi::{Color, Style};
+use nixterm::events::{Event, Key};
+use nixterm::terminfo::lang::{Argument, Program};
+use nixterm::terminfo::{self, ExtCapability, StringField};
+use nixterm::{Capability, ErrorKind};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Action {
+    Quit,
+    Save,
+}
+
+#[test]
+fn keys() {
+    let mut bindings = HashMap::new();
+    bindings.insert(Key::Control('c'), Action::Quit);
+    bindings.insert(Key::Control('s'), Action::Save);
+    let key = Key::Control('s');
+    assert_eq!(bindings.get(&key), Some(&Action::Save));
+
+    let events: HashSet<Event> = [Event::Key(key), Event::Resize(80, 24), Event::Tick]
+        .iter()
+        .cloned()
+        .collect();
+    assert!(events.contains(&Event::Key(key)));
+    // the variants that aren't handled here still have to be
+    let handled = |event: Event| match event {
+        Event::Key(key) => bindings.get(&key).cloned(),
+        _ => None,
+    };
+    assert_eq!(handled(Event::Tick), None);
+}
+
+#[test]
+fn styles() {
+    let mut names = HashMap::new();
+    let error = Style {
+        foreground: Some(Color::Index(1)),
+        bold: true,
+        ..Style::default()
+    };
+    names.insert(error, "error");
+    names.insert(Style::default(), "plain");
+    assert_eq!(names[&error], "error");
+
+    let colors: HashSet<Color> = [Color::Index(1), Color::Rgb(1, 2, 3), Color::Index(1)]
+        .iter()
+        .cloned()
+        .collect();
+    assert_eq!(colors.len(), 2);
+}
+
+#[test]
+fn capabilities() {
+    let mut overrides = BTreeMap::new();
+    overrides.insert(StringField::CursorAddress, "\x1b[%i%p1%d;%p2%dH");
+    overrides.insert(terminfo::ClearScreen, "\x1b[H\x1b[2J");
+    assert_eq!(overrides.keys().next(), Some(&StringField::ClearScreen));
+
+    let ext: BTreeMap<ExtCapability, bool> =
+        vec![(ExtCapability::Sync, true)].into_iter().collect();
+    assert!(ext[&ExtCapability::Sync]);
+
+    let missing: HashSet<Capability> = vec![
+        Capability::String(terminfo::ClearScreen),
+        Capability::Images,
+    ]
+    .into_iter()
+    .collect();
+    assert!(missing.contains(&Capability::Images));
+}
+
+#[test]
+fn programs() {
+    let program = Program::compile(b"\x1b[%p1%dX").unwrap();
+    let mut cache = HashMap::new();
+    let args = vec![Argument::Integer(4)];
+    cache.insert((program.clone(), args.clone()), b"\x1b[4X".to_vec());
+    assert!(cache.contains_key(&(program, args)));
+}
+
+#[test]
+fn errors() {
+    let kind = ErrorKind::ReadFailed;
+    let described = match kind {
+        ErrorKind::ReadFailed => "read",
+        ErrorKind::WriteFailed => "write",
+        _ => "something else",
+    };
+    assert_eq!(described, "read");
+}